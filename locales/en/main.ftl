@@ -0,0 +1,185 @@
+## Auth
+user-registered = User created successfully. Please check your email for verification code.
+email-verified = Email verified successfully. You can now login.
+otp-resent = Verification code sent to your email.
+phone-otp-sent = Verification code sent to your phone.
+phone-verified = Phone number verified successfully.
+sms-otp-code = Your SocializationApp verification code is { $code }. It expires in { $minutes } minutes.
+login-successful = Login successful
+password-reset-requested = If an account exists for that email, a reset token has been sent to it.
+password-reset-successful = Password reset successfully. You can now login with your new password.
+password-changed = Password changed successfully. Please login again with your new password.
+account-deleted = Your account has been deleted.
+token-refreshed = Token refreshed successfully
+logout-successful = Logged out successfully
+profile-updated = Profile updated successfully
+profile-fetched = Profile retrieved successfully
+access-token-created = Access token created. Store it now; it cannot be shown again.
+access-tokens-fetched = Access tokens retrieved successfully
+access-token-revoked = Access token revoked
+
+## Posts
+post-created = Post created successfully
+post-fetched = Post fetched successfully
+post-updated = Post updated successfully
+post-deleted = Post deleted successfully
+post-subscribed = You'll be notified about new comments on this post
+post-unsubscribed = You'll no longer be notified about new comments on this post
+post-shared = Share link ready
+post-pinned = Post pinned to your profile
+post-unpinned = Post unpinned from your profile
+link-warning = This link may be unsafe. Continue only if you trust where it leads.
+link-blocked = This link leads to a site known to be unsafe and has been blocked.
+
+## Topics
+topics-fetched = Topics retrieved successfully
+topic-posts-fetched = Posts retrieved successfully
+interests-fetched = Interests retrieved successfully
+interests-updated = Interests updated successfully
+
+## Comments
+comment-created = Comment created successfully
+comments-fetched = Comments retrieved successfully
+comment-fetched = Comment retrieved successfully
+comment-updated = Comment updated successfully
+comment-deleted = Comment deleted successfully
+comment-count-fetched = Comment count retrieved successfully
+comment-draft-fetched = Draft retrieved successfully
+comment-draft-saved = Draft saved
+comment-draft-deleted = Draft discarded
+
+## Groups
+group-created = Group created successfully
+group-fetched = Group retrieved successfully
+groups-fetched = Groups retrieved successfully
+group-joined = You joined the group
+group-join-requested = Your request to join has been sent to the group admins
+group-left = You left the group
+group-members-fetched = Group members retrieved successfully
+group-member-invited = Invitation sent
+group-member-approved = Member approved
+group-member-updated = Member role updated
+group-member-removed = Member removed
+group-feed-fetched = Group posts retrieved successfully
+
+## Friends
+friends-fetched = Friends retrieved successfully
+friend-removed = Friend removed
+friend-request-sent = Friend request sent
+friend-requests-fetched = Friend requests retrieved successfully
+friend-request-accepted = Friend request accepted
+friend-request-declined = Friend request declined
+friend-request-cancelled = Friend request cancelled
+privacy-settings-fetched = Privacy settings retrieved successfully
+privacy-settings-updated = Privacy settings updated successfully
+suggestions-fetched = Suggestions retrieved successfully
+contacts-matched = Contacts matched successfully
+nearby-location-updated = Location updated
+nearby-location-cleared = Location sharing turned off
+nearby-users-fetched = Nearby users retrieved successfully
+insights-fetched = Insights retrieved successfully
+blocks-fetched = Blocked users retrieved successfully
+user-blocked = User blocked
+user-unblocked = User unblocked
+
+## Chat
+chat-message-sent = Message sent
+chat-messages-fetched = Messages retrieved successfully
+chat-media-fetched = Media retrieved successfully
+chat-messages-deleted = Messages deleted
+chat-location-updated = Location updated
+chat-rooms-fetched = Rooms retrieved successfully
+chat-room-joined = You joined the room
+chat-room-updated = Room updated
+chat-room-read-only-updated = Read-only mode updated
+chat-room-slow-mode-updated = Slow mode updated
+chat-invite-created = Invite link created
+sticker-packs-fetched = Sticker packs retrieved successfully
+
+## Activity
+activity-fetched = Activity retrieved successfully
+
+## Analytics
+events-accepted = Events received
+
+## Feature flags
+feature-flags-fetched = Feature flags retrieved successfully
+
+## Badges
+badges-fetched = Badges retrieved successfully
+badge-first-post = Published a first post
+badge-verified-email = Verified an email address
+badge-one-year-member = Member for a year
+
+## Verification
+verification-requested = Verification request submitted
+verification-fetched = Verification request retrieved successfully
+
+## Notifications
+notifications-fetched = Notifications retrieved successfully
+unread-count-fetched = Unread count retrieved successfully
+notifications-marked-read = Notifications marked as read
+notification-settings-fetched = Notification settings retrieved successfully
+notification-settings-updated = Notification settings updated successfully
+
+## Leaderboards
+leaderboard-fetched = Leaderboard retrieved successfully
+
+## Moderation
+report-created = Report submitted. A moderator will review it.
+
+## Uploads
+upload-no-file = No file provided
+upload-no-files = No files provided
+
+## Validation
+validation-length = must be between { $min } and { $max } characters
+validation-length-min = must be at least { $min } characters
+validation-length-max = must be at most { $max } characters
+validation-range = must be between { $min } and { $max }
+validation-email = must be a valid email address
+validation-url = must be a valid URL
+validation-blank = must not be blank
+validation-object-id = must be a valid id
+
+## Emails
+email-footer = You are receiving this email because of your { $app_name } account.
+email-ignore = If you didn't request this, please ignore this email.
+email-greeting = Hi { $username },
+
+email-verification-subject = Verify Your Email - { $app_name }
+email-verification-welcome = Welcome to { $app_name }!
+email-verification-code = Your verification code is:
+email-verification-expiry = This code will expire in { $minutes } minutes.
+
+email-password-reset-subject = Password Reset - { $app_name }
+email-password-reset-intro = You requested a password reset.
+email-password-reset-token = Your reset token is:
+email-password-reset-expiry = This token will expire in { $minutes } minutes.
+
+email-welcome-subject = Welcome to { $app_name }
+email-welcome-ready = Your email is verified and your { $app_name } account is ready.
+email-welcome-start = Start by completing your profile, sharing your first post and joining a conversation in chat.
+email-welcome-signoff = See you around!
+
+email-onboarding-tips-subject = Getting started with { $app_name }
+email-onboarding-tips-intro = A few tips to get the most out of { $app_name }:
+email-onboarding-tips-picture = Add a profile picture so friends recognise you.
+email-onboarding-tips-comment = Comment on posts you enjoy - the most active commenters make the weekly leaderboard.
+email-onboarding-tips-chat = Start a chat room and invite people to talk in real time.
+email-onboarding-tips-opt-out = You can turn off onboarding emails in your notification settings.
+
+email-digest-subject = Your { $app_name } digest
+email-digest-intro = Here is what you missed on { $app_name }:
+
+email-report-export-subject = Your { $app_name } report export
+email-report-export-ready = Your scheduled { $report ->
+        [weekly_signups] signups
+        [content_reports] content reports
+       *[engagement] engagement
+    } export is ready with { $rows ->
+        [one] one row
+       *[other] { $rows } rows
+    }.
+email-report-export-download = Download the CSV:
+email-report-export-expiry = This link will expire in { $hours } hours.
@@ -0,0 +1,185 @@
+## Auth
+user-registered = Compte créé. Consultez vos e-mails pour obtenir le code de vérification.
+email-verified = Adresse e-mail vérifiée. Vous pouvez maintenant vous connecter.
+otp-resent = Un code de vérification a été envoyé à votre adresse e-mail.
+phone-otp-sent = Un code de vérification a été envoyé à votre téléphone.
+phone-verified = Numéro de téléphone vérifié.
+sms-otp-code = Votre code de vérification SocializationApp est { $code }. Il expire dans { $minutes } minutes.
+login-successful = Connexion réussie
+password-reset-requested = Si un compte existe pour cette adresse e-mail, un code de réinitialisation y a été envoyé.
+password-reset-successful = Mot de passe réinitialisé. Vous pouvez maintenant vous connecter avec votre nouveau mot de passe.
+password-changed = Mot de passe modifié. Veuillez vous reconnecter avec votre nouveau mot de passe.
+account-deleted = Votre compte a été supprimé.
+token-refreshed = Jeton actualisé avec succès
+logout-successful = Déconnexion réussie
+profile-updated = Profil mis à jour avec succès
+profile-fetched = Profil récupéré avec succès
+access-token-created = Jeton d'accès créé. Conservez-le maintenant ; il ne sera plus affiché.
+access-tokens-fetched = Jetons d'accès récupérés avec succès
+access-token-revoked = Jeton d'accès révoqué
+
+## Posts
+post-created = Publication créée
+post-fetched = Publication récupérée
+post-updated = Publication mise à jour
+post-deleted = Publication supprimée
+post-subscribed = Vous serez averti des nouveaux commentaires sur cette publication
+post-unsubscribed = Vous ne serez plus averti des nouveaux commentaires sur cette publication
+post-shared = Lien de partage prêt
+post-pinned = Publication épinglée sur votre profil
+post-unpinned = Publication désépinglée de votre profil
+link-warning = Ce lien peut être dangereux. Continuez seulement si vous faites confiance à sa destination.
+link-blocked = Ce lien mène à un site connu comme dangereux et a été bloqué.
+
+## Topics
+topics-fetched = Sujets récupérés avec succès
+topic-posts-fetched = Publications récupérées avec succès
+interests-fetched = Centres d'intérêt récupérés avec succès
+interests-updated = Centres d'intérêt mis à jour avec succès
+
+## Comments
+comment-created = Commentaire créé
+comments-fetched = Commentaires récupérés
+comment-fetched = Commentaire récupéré
+comment-updated = Commentaire mis à jour
+comment-deleted = Commentaire supprimé
+comment-count-fetched = Nombre de commentaires récupéré
+comment-draft-fetched = Brouillon récupéré
+comment-draft-saved = Brouillon enregistré
+comment-draft-deleted = Brouillon supprimé
+
+## Groups
+group-created = Groupe créé avec succès
+group-fetched = Groupe récupéré avec succès
+groups-fetched = Groupes récupérés avec succès
+group-joined = Vous avez rejoint le groupe
+group-join-requested = Votre demande d'adhésion a été envoyée aux administrateurs du groupe
+group-left = Vous avez quitté le groupe
+group-members-fetched = Membres du groupe récupérés avec succès
+group-member-invited = Invitation envoyée
+group-member-approved = Membre approuvé
+group-member-updated = Rôle du membre mis à jour
+group-member-removed = Membre retiré
+group-feed-fetched = Publications du groupe récupérées avec succès
+
+## Friends
+friends-fetched = Amis récupérés avec succès
+friend-removed = Ami retiré
+friend-request-sent = Demande d'ami envoyée
+friend-requests-fetched = Demandes d'ami récupérées avec succès
+friend-request-accepted = Demande d'ami acceptée
+friend-request-declined = Demande d'ami refusée
+friend-request-cancelled = Demande d'ami annulée
+privacy-settings-fetched = Paramètres de confidentialité récupérés
+privacy-settings-updated = Paramètres de confidentialité mis à jour
+suggestions-fetched = Suggestions récupérées avec succès
+contacts-matched = Contacts retrouvés avec succès
+nearby-location-updated = Position mise à jour
+nearby-location-cleared = Partage de position désactivé
+nearby-users-fetched = Utilisateurs à proximité récupérés
+insights-fetched = Statistiques récupérées avec succès
+blocks-fetched = Utilisateurs bloqués récupérés
+user-blocked = Utilisateur bloqué
+user-unblocked = Utilisateur débloqué
+
+## Chat
+chat-message-sent = Message envoyé
+chat-messages-fetched = Messages récupérés avec succès
+chat-media-fetched = Médias récupérés avec succès
+chat-messages-deleted = Messages supprimés
+chat-location-updated = Position mise à jour
+chat-rooms-fetched = Salons récupérés avec succès
+chat-room-joined = Vous avez rejoint le salon
+chat-room-updated = Salon mis à jour
+chat-room-read-only-updated = Mode lecture seule mis à jour
+chat-room-slow-mode-updated = Mode lent mis à jour
+chat-invite-created = Lien d'invitation créé
+sticker-packs-fetched = Packs de stickers récupérés avec succès
+
+## Activity
+activity-fetched = Activité récupérée avec succès
+
+## Analytics
+events-accepted = Événements reçus
+
+## Feature flags
+feature-flags-fetched = Fonctionnalités récupérées avec succès
+
+## Badges
+badges-fetched = Badges récupérés avec succès
+badge-first-post = A publié un premier article
+badge-verified-email = A vérifié son adresse e-mail
+badge-one-year-member = Membre depuis un an
+
+## Verification
+verification-requested = Demande de vérification envoyée
+verification-fetched = Demande de vérification récupérée avec succès
+
+## Notifications
+notifications-fetched = Notifications récupérées
+unread-count-fetched = Nombre de notifications non lues récupéré
+notifications-marked-read = Notifications marquées comme lues
+notification-settings-fetched = Préférences de notification récupérées
+notification-settings-updated = Préférences de notification mises à jour
+
+## Leaderboards
+leaderboard-fetched = Classement récupéré
+
+## Moderation
+report-created = Signalement envoyé. Un modérateur va l'examiner.
+
+## Uploads
+upload-no-file = Aucun fichier fourni
+upload-no-files = Aucun fichier fourni
+
+## Validation
+validation-length = doit contenir entre { $min } et { $max } caractères
+validation-length-min = doit contenir au moins { $min } caractères
+validation-length-max = doit contenir au plus { $max } caractères
+validation-range = doit être compris entre { $min } et { $max }
+validation-email = doit être une adresse e-mail valide
+validation-url = doit être une URL valide
+validation-blank = ne doit pas être vide
+validation-object-id = doit être un identifiant valide
+
+## Emails
+email-footer = Vous recevez cet e-mail en raison de votre compte { $app_name }.
+email-ignore = Si vous n'êtes pas à l'origine de cette demande, ignorez cet e-mail.
+email-greeting = Bonjour { $username },
+
+email-verification-subject = Vérifiez votre adresse e-mail - { $app_name }
+email-verification-welcome = Bienvenue sur { $app_name } !
+email-verification-code = Votre code de vérification est :
+email-verification-expiry = Ce code expirera dans { $minutes } minutes.
+
+email-password-reset-subject = Réinitialisation du mot de passe - { $app_name }
+email-password-reset-intro = Vous avez demandé la réinitialisation de votre mot de passe.
+email-password-reset-token = Votre jeton de réinitialisation est :
+email-password-reset-expiry = Ce jeton expirera dans { $minutes } minutes.
+
+email-welcome-subject = Bienvenue sur { $app_name }
+email-welcome-ready = Votre adresse e-mail est vérifiée et votre compte { $app_name } est prêt.
+email-welcome-start = Commencez par compléter votre profil, partager votre première publication et rejoindre une conversation.
+email-welcome-signoff = À bientôt !
+
+email-onboarding-tips-subject = Bien démarrer sur { $app_name }
+email-onboarding-tips-intro = Quelques conseils pour profiter pleinement de { $app_name } :
+email-onboarding-tips-picture = Ajoutez une photo de profil pour que vos amis vous reconnaissent.
+email-onboarding-tips-comment = Commentez les publications qui vous plaisent - les commentateurs les plus actifs apparaissent dans le classement hebdomadaire.
+email-onboarding-tips-chat = Créez un salon de discussion et invitez des personnes à échanger en temps réel.
+email-onboarding-tips-opt-out = Vous pouvez désactiver les e-mails de bienvenue dans vos préférences de notification.
+
+email-digest-subject = Votre résumé { $app_name }
+email-digest-intro = Voici ce que vous avez manqué sur { $app_name } :
+
+email-report-export-subject = Votre export de rapport { $app_name }
+email-report-export-ready = Votre export planifié { $report ->
+        [weekly_signups] des inscriptions
+        [content_reports] des signalements
+       *[engagement] de l'engagement
+    } est prêt avec { $rows ->
+        [one] une ligne
+       *[other] { $rows } lignes
+    }.
+email-report-export-download = Télécharger le CSV :
+email-report-export-expiry = Ce lien expirera dans { $hours } heures.
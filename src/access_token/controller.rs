@@ -0,0 +1,60 @@
+use crate::access_token::model::CreateAccessTokenRequest;
+use crate::access_token::service::AccessTokenService;
+use crate::middleware::auth::AuthUser;
+use crate::utils::error::CustomError;
+use crate::utils::i18n::Locale;
+use crate::utils::response::ApiResponse;
+use crate::utils::validation::ValidatedJson;
+use actix_web::{HttpResponse, web};
+use mongodb::bson::oid::ObjectId;
+use serde_json::json;
+
+/// Create a personal access token; the plaintext is only returned here
+/// POST /auth/user/tokens
+pub async fn create_access_token(
+    locale: Locale,
+    auth_user: AuthUser,
+    token_service: web::Data<AccessTokenService>,
+    body: ValidatedJson<CreateAccessTokenRequest>,
+) -> Result<HttpResponse, CustomError> {
+    let body = body.into_inner();
+    let (token, plaintext) = token_service
+        .create_token(auth_user.id, body.name, body.scopes, body.expires_in_days)
+        .await?;
+
+    Ok(ApiResponse::created(locale.t("access-token-created"))
+        .data(json!({ "token": plaintext, "access_token": token }))
+        .into())
+}
+
+/// GET /auth/user/tokens
+pub async fn list_access_tokens(
+    locale: Locale,
+    auth_user: AuthUser,
+    token_service: web::Data<AccessTokenService>,
+) -> Result<HttpResponse, CustomError> {
+    let tokens = token_service.list_tokens(&auth_user.id).await?;
+
+    Ok(ApiResponse::ok(locale.t("access-tokens-fetched"))
+        .list(tokens)
+        .into())
+}
+
+/// DELETE /auth/user/tokens/{id}
+pub async fn revoke_access_token(
+    locale: Locale,
+    auth_user: AuthUser,
+    token_service: web::Data<AccessTokenService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    let token_id = ObjectId::parse_str(path.into_inner())
+        .map_err(|_| CustomError::BadRequestError("Invalid token ID".to_string()))?;
+
+    if !token_service.revoke_token(&auth_user.id, &token_id).await? {
+        return Err(CustomError::NotFoundError(
+            "Access token not found".to_string(),
+        ));
+    }
+
+    Ok(ApiResponse::ok(locale.t("access-token-revoked")).into())
+}
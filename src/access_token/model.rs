@@ -0,0 +1,163 @@
+use crate::utils::datetime::{bson_datetime, option_bson_datetime};
+use crate::utils::validation::not_blank;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError};
+
+/// Prepended to personal access tokens so `verify_token` can tell them from JWTs
+pub const ACCESS_TOKEN_PREFIX: &str = "pat_";
+
+/// Top-level API resources a token can be scoped to, as in `read:posts`
+pub const SCOPE_RESOURCES: &[&str] = &[
+    "posts",
+    "comments",
+    "groups",
+    "topics",
+    "friends",
+    "chat",
+    "users",
+    "notifications",
+];
+
+/// Scope a request needs: `read:` for safe methods and `write:` otherwise,
+/// followed by the first path segment after the API version. `None` for
+/// routes tokens can never use, such as admin and token management, and
+/// for paths outside `/api/v1`.
+pub fn required_scope(method: &actix_web::http::Method, path: &str) -> Option<String> {
+    let resource = path.strip_prefix("/api/v1/")?.split('/').next()?;
+    if !SCOPE_RESOURCES.contains(&resource) {
+        return None;
+    }
+    let access = if method.is_safe() { "read" } else { "write" };
+    Some(format!("{}:{}", access, resource))
+}
+
+fn valid_scopes(scopes: &[String]) -> Result<(), ValidationError> {
+    let valid = scopes.iter().all(|scope| {
+        scope.split_once(':').is_some_and(|(access, resource)| {
+            matches!(access, "read" | "write") && SCOPE_RESOURCES.contains(&resource)
+        })
+    });
+    if !valid {
+        return Err(ValidationError::new("scope").with_message(
+            format!(
+                "scopes must be read:<resource> or write:<resource> with a resource among {}",
+                SCOPE_RESOURCES.join(", ")
+            )
+            .into(),
+        ));
+    }
+    Ok(())
+}
+
+/// A long-lived token a user creates for bots and integrations. Only a
+/// SHA-256 hash of the token is stored.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PersonalAccessToken {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: ObjectId,
+    pub name: String,
+    /// First characters of the token, so users can tell tokens apart
+    pub prefix: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub token_hash: String,
+    pub scopes: Vec<String>,
+    /// Tokens without an expiry last until revoked
+    #[serde(default, with = "option_bson_datetime")]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(default, with = "option_bson_datetime")]
+    pub last_used_at: Option<DateTime<Utc>>,
+    #[serde(default, with = "option_bson_datetime")]
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl PersonalAccessToken {
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
+
+    /// Copy safe to return from the API
+    pub fn redacted(mut self) -> Self {
+        self.token_hash.clear();
+        self
+    }
+}
+
+#[derive(Deserialize, Validate)]
+pub struct CreateAccessTokenRequest {
+    #[validate(
+        length(max = 100, message = "must be at most 100 characters"),
+        custom(function = "not_blank")
+    )]
+    pub name: String,
+    #[validate(
+        length(min = 1, message = "must list at least one scope"),
+        custom(function = "valid_scopes")
+    )]
+    pub scopes: Vec<String>,
+    /// Omit for a token that never expires
+    #[validate(range(min = 1, max = 365, message = "must be between 1 and 365 days"))]
+    pub expires_in_days: Option<i64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::Method;
+
+    #[test]
+    fn admin_routes_need_no_scope_a_token_can_hold() {
+        assert_eq!(required_scope(&Method::GET, "/api/v1/admin/users"), None);
+        assert_eq!(required_scope(&Method::POST, "/api/v1/admin/flags"), None);
+    }
+
+    #[test]
+    fn token_management_is_off_limits() {
+        assert_eq!(
+            required_scope(&Method::GET, "/api/v1/auth/user/tokens"),
+            None
+        );
+        assert_eq!(
+            required_scope(&Method::DELETE, "/api/v1/auth/user/tokens/abc"),
+            None
+        );
+    }
+
+    #[test]
+    fn safe_methods_read_and_others_write() {
+        assert_eq!(
+            required_scope(&Method::GET, "/api/v1/posts/x").as_deref(),
+            Some("read:posts")
+        );
+        assert_eq!(
+            required_scope(&Method::HEAD, "/api/v1/posts/x").as_deref(),
+            Some("read:posts")
+        );
+        assert_eq!(
+            required_scope(&Method::DELETE, "/api/v1/posts/x").as_deref(),
+            Some("write:posts")
+        );
+        assert_eq!(
+            required_scope(&Method::POST, "/api/v1/comments").as_deref(),
+            Some("write:comments")
+        );
+    }
+
+    #[test]
+    fn unknown_resources_need_no_scope_a_token_can_hold() {
+        assert_eq!(required_scope(&Method::GET, "/api/v1/exports/x"), None);
+        assert_eq!(required_scope(&Method::GET, "/api/v1/"), None);
+        assert_eq!(required_scope(&Method::GET, "/api/v1"), None);
+    }
+
+    #[test]
+    fn paths_outside_the_api_prefix_are_rejected() {
+        assert_eq!(required_scope(&Method::GET, "/posts/x"), None);
+        assert_eq!(required_scope(&Method::GET, "/api/v1posts/x"), None);
+        assert_eq!(required_scope(&Method::GET, "/api/v2/posts/x"), None);
+    }
+}
@@ -0,0 +1,188 @@
+use crate::access_token::model::{ACCESS_TOKEN_PREFIX, PersonalAccessToken};
+use crate::api_key::service::{hash_key, hex};
+use crate::utils::datetime::bson_now;
+use crate::utils::error::CustomError;
+use crate::utils::sanitize::{Markup, sanitize};
+use chrono::{Duration, Utc};
+use futures_util::TryStreamExt;
+use mongodb::bson::{Bson, doc, oid::ObjectId};
+use mongodb::options::IndexOptions;
+use mongodb::{Client, Collection, IndexModel};
+use rand::Rng;
+
+/// Characters of the token kept in clear for identification
+const VISIBLE_PREFIX_LEN: usize = 12;
+
+/// Active tokens a single user may hold
+const MAX_TOKENS_PER_USER: u64 = 20;
+
+pub struct AccessTokenService {
+    collection: Collection<PersonalAccessToken>,
+}
+
+impl AccessTokenService {
+    pub fn new(client: &Client) -> Self {
+        let collection = client
+            .database("rust_blogdb")
+            .collection::<PersonalAccessToken>("access_tokens");
+        AccessTokenService { collection }
+    }
+
+    /// Look tokens up by hash, and list them per user
+    #[tracing::instrument(skip_all)]
+    pub async fn ensure_indexes(&self) -> Result<(), CustomError> {
+        let indexes = vec![
+            IndexModel::builder()
+                .keys(doc! { "token_hash": 1 })
+                .options(IndexOptions::builder().unique(true).build())
+                .build(),
+            IndexModel::builder()
+                .keys(doc! { "user_id": 1, "created_at": -1 })
+                .build(),
+        ];
+
+        self.collection.create_indexes(indexes).await.map_err(|e| {
+            CustomError::InternalServerError(format!(
+                "Failed to create access token indexes: {}",
+                e
+            ))
+        })?;
+
+        Ok(())
+    }
+
+    /// Generate a token. The plaintext is returned once and never stored.
+    #[tracing::instrument(skip_all)]
+    pub async fn create_token(
+        &self,
+        user_id: ObjectId,
+        name: String,
+        scopes: Vec<String>,
+        expires_in_days: Option<i64>,
+    ) -> Result<(PersonalAccessToken, String), CustomError> {
+        let active = self
+            .collection
+            .count_documents(doc! { "user_id": user_id, "revoked_at": Bson::Null })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+        if active >= MAX_TOKENS_PER_USER {
+            return Err(CustomError::BadRequestError(format!(
+                "You can hold at most {} active tokens",
+                MAX_TOKENS_PER_USER
+            )));
+        }
+
+        let secret: [u8; 32] = rand::rng().random();
+        let plaintext = format!("{}{}", ACCESS_TOKEN_PREFIX, hex(&secret));
+        let now = Utc::now();
+
+        let mut token = PersonalAccessToken {
+            id: None,
+            user_id,
+            name: sanitize(&name, Markup::None),
+            prefix: plaintext[..VISIBLE_PREFIX_LEN].to_string(),
+            token_hash: hash_key(&plaintext),
+            scopes,
+            expires_at: expires_in_days.map(|days| now + Duration::days(days)),
+            created_at: now,
+            last_used_at: None,
+            revoked_at: None,
+        };
+
+        let result = self.collection.insert_one(&token).await.map_err(|e| {
+            CustomError::InternalServerError(format!("Failed to create access token: {}", e))
+        })?;
+        token.id = result.inserted_id.as_object_id();
+
+        Ok((token.redacted(), plaintext))
+    }
+
+    /// A user's tokens, newest first, without their hashes
+    #[tracing::instrument(skip_all)]
+    pub async fn list_tokens(
+        &self,
+        user_id: &ObjectId,
+    ) -> Result<Vec<PersonalAccessToken>, CustomError> {
+        let tokens: Vec<PersonalAccessToken> = self
+            .collection
+            .find(doc! { "user_id": user_id })
+            .sort(doc! { "created_at": -1 })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        Ok(tokens
+            .into_iter()
+            .map(PersonalAccessToken::redacted)
+            .collect())
+    }
+
+    /// Revoke one of the user's tokens, returning whether an active one was found
+    #[tracing::instrument(skip_all)]
+    pub async fn revoke_token(
+        &self,
+        user_id: &ObjectId,
+        id: &ObjectId,
+    ) -> Result<bool, CustomError> {
+        let result = self
+            .collection
+            .update_one(
+                doc! { "_id": id, "user_id": user_id, "revoked_at": Bson::Null },
+                doc! { "$set": { "revoked_at": bson_now() } },
+            )
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        Ok(result.matched_count > 0)
+    }
+
+    /// Revoke every active token of a user, when their credentials change or
+    /// the account goes away. Returns how many were revoked.
+    #[tracing::instrument(skip_all)]
+    pub async fn revoke_all_for_user(&self, user_id: &ObjectId) -> Result<u64, CustomError> {
+        let result = self
+            .collection
+            .update_many(
+                doc! { "user_id": user_id, "revoked_at": Bson::Null },
+                doc! { "$set": { "revoked_at": bson_now() } },
+            )
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        Ok(result.modified_count)
+    }
+
+    /// The active, unexpired token matching a presented one
+    #[tracing::instrument(skip_all)]
+    pub async fn verify(&self, token: &str) -> Result<PersonalAccessToken, CustomError> {
+        let token = self
+            .collection
+            .find_one(doc! {
+                "token_hash": hash_key(token),
+                "revoked_at": Bson::Null,
+                "$or": [
+                    { "expires_at": Bson::Null },
+                    { "expires_at": { "$gt": bson_now() } },
+                ],
+            })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .ok_or_else(|| CustomError::UnauthorizedError("Invalid access token".to_string()))?;
+
+        // Usage tracking is informational only
+        if let Err(e) = self
+            .collection
+            .update_one(
+                doc! { "_id": token.id },
+                doc! { "$set": { "last_used_at": bson_now() } },
+            )
+            .await
+        {
+            log::warn!("Failed to record access token usage: {}", e);
+        }
+
+        Ok(token.redacted())
+    }
+}
@@ -0,0 +1,39 @@
+use crate::activity::service::ActivityService;
+use crate::database::RedisService;
+use crate::middleware::auth::AuthUser;
+use crate::utils::error::CustomError;
+use crate::utils::i18n::Locale;
+use crate::utils::response::ApiResponse;
+use actix_web::{HttpResponse, web};
+use serde::Deserialize;
+
+/// Feeds are rebuilt on read, so cache them briefly per user
+const ACTIVITY_CACHE_SECONDS: u64 = 60;
+
+#[derive(Debug, Deserialize)]
+pub struct ActivityQuery {
+    pub limit: Option<i64>,
+}
+
+/// Recent posts, comments and group joins by the caller's friends
+/// GET /users/me/activity?limit=50
+pub async fn get_activity(
+    locale: Locale,
+    auth_user: AuthUser,
+    activity_service: web::Data<ActivityService>,
+    redis_service: web::Data<RedisService>,
+    query: web::Query<ActivityQuery>,
+) -> Result<HttpResponse, CustomError> {
+    let limit = query.limit.unwrap_or(50).clamp(1, 100);
+    let activity = redis_service
+        .cache_get_or_set_json(
+            &format!("activity:{}:{}", auth_user.id.to_hex(), limit),
+            ACTIVITY_CACHE_SECONDS,
+            || activity_service.feed_for(&auth_user.id, limit),
+        )
+        .await?;
+
+    Ok(ApiResponse::ok(locale.t("activity-fetched"))
+        .list(activity)
+        .into())
+}
@@ -0,0 +1,31 @@
+use crate::utils::datetime::bson_datetime;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::Enum))]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityKind {
+    Posted,
+    Commented,
+    JoinedGroup,
+}
+
+/// Something a friend did, derived from posts, comments and memberships
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Activity {
+    pub kind: ActivityKind,
+    pub actor_id: ObjectId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_id: Option<ObjectId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment_id: Option<ObjectId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group_id: Option<ObjectId>,
+    /// Post title or group name, for rendering without another lookup
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
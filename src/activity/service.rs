@@ -0,0 +1,214 @@
+use crate::activity::model::{Activity, ActivityKind};
+use crate::comment::model::Comment;
+use crate::friend::service::FriendService;
+use crate::group::model::{Group, GroupMember, GroupVisibility, MembershipStatus};
+use crate::moderation::service::ModerationService;
+use crate::post::post_model::Post;
+use crate::utils::error::CustomError;
+use futures_util::TryStreamExt;
+use mongodb::bson::{Bson, doc, oid::ObjectId};
+use mongodb::{Client, Collection};
+use std::collections::HashMap;
+
+/// Builds activity feeds on read from the posts, comments and memberships
+/// collections; the controller caches the result briefly
+pub struct ActivityService {
+    posts: Collection<Post>,
+    comments: Collection<Comment>,
+    members: Collection<GroupMember>,
+    groups: Collection<Group>,
+    friends: FriendService,
+    moderation: ModerationService,
+}
+
+impl ActivityService {
+    pub fn new(client: &Client) -> Self {
+        let db = client.database("rust_blogdb");
+        ActivityService {
+            posts: db.collection::<Post>("posts"),
+            comments: db.collection::<Comment>("comments"),
+            members: db.collection::<GroupMember>("group_members"),
+            groups: db.collection::<Group>("groups"),
+            friends: FriendService::new(client),
+            moderation: ModerationService::new(client),
+        }
+    }
+
+    /// Recent posts, comments and group joins by the user's friends, newest
+    /// first. Shadow-banned friends contribute nothing.
+    #[tracing::instrument(skip_all)]
+    pub async fn feed_for(
+        &self,
+        user_id: &ObjectId,
+        limit: i64,
+    ) -> Result<Vec<Activity>, CustomError> {
+        let hidden = self.moderation.hidden_authors(Some(user_id)).await?;
+        let friend_ids: Vec<ObjectId> = self
+            .friends
+            .list_friends(user_id)
+            .await?
+            .into_iter()
+            .map(|friendship| friendship.friend_id)
+            .filter(|friend_id| !hidden.contains(friend_id))
+            .collect();
+        if friend_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut activity = self.posted(&friend_ids, limit).await?;
+        activity.extend(self.commented(&friend_ids, &hidden, limit).await?);
+        activity.extend(self.joined_groups(&friend_ids, limit).await?);
+
+        activity.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        activity.truncate(limit as usize);
+        Ok(activity)
+    }
+
+    /// Profile posts only; group posts belong to the group's feed
+    async fn posted(
+        &self,
+        friend_ids: &[ObjectId],
+        limit: i64,
+    ) -> Result<Vec<Activity>, CustomError> {
+        let posts: Vec<Post> = self
+            .posts
+            .find(doc! {
+                "author_id": { "$in": friend_ids },
+                "group_id": Bson::Null,
+                "deleted_at": Bson::Null,
+                "hidden_at": Bson::Null,
+            })
+            .sort(doc! { "created_at": -1 })
+            .limit(limit)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        Ok(posts
+            .into_iter()
+            .map(|post| Activity {
+                kind: ActivityKind::Posted,
+                actor_id: post.author_id,
+                post_id: Some(post.id),
+                comment_id: None,
+                group_id: None,
+                summary: Some(post.title),
+                created_at: post.created_at,
+            })
+            .collect())
+    }
+
+    /// Comments on profile posts that are still visible
+    async fn commented(
+        &self,
+        friend_ids: &[ObjectId],
+        hidden: &[ObjectId],
+        limit: i64,
+    ) -> Result<Vec<Activity>, CustomError> {
+        let comments: Vec<Comment> = self
+            .comments
+            .find(doc! {
+                "author_id": { "$in": friend_ids },
+                "deleted_at": Bson::Null,
+                "hidden_at": Bson::Null,
+            })
+            .sort(doc! { "created_at": -1 })
+            .limit(limit)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        let post_ids: Vec<ObjectId> = comments.iter().map(|comment| comment.post_id).collect();
+        let titles: HashMap<ObjectId, String> = self
+            .posts
+            .find(doc! {
+                "_id": { "$in": post_ids },
+                "author_id": { "$nin": hidden },
+                "group_id": Bson::Null,
+                "deleted_at": Bson::Null,
+                "hidden_at": Bson::Null,
+            })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect::<Vec<Post>>()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .into_iter()
+            .map(|post| (post.id, post.title))
+            .collect();
+
+        Ok(comments
+            .into_iter()
+            .filter_map(|comment| {
+                let title = titles.get(&comment.post_id)?;
+                Some(Activity {
+                    kind: ActivityKind::Commented,
+                    actor_id: comment.author_id,
+                    post_id: Some(comment.post_id),
+                    comment_id: comment.id,
+                    group_id: None,
+                    summary: Some(title.clone()),
+                    created_at: comment.created_at,
+                })
+            })
+            .collect())
+    }
+
+    /// Joins of listed groups; invite-only groups stay private
+    async fn joined_groups(
+        &self,
+        friend_ids: &[ObjectId],
+        limit: i64,
+    ) -> Result<Vec<Activity>, CustomError> {
+        let members: Vec<GroupMember> = self
+            .members
+            .find(doc! {
+                "user_id": { "$in": friend_ids },
+                "status": MembershipStatus::Active.name(),
+            })
+            .sort(doc! { "updated_at": -1 })
+            .limit(limit)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        let group_ids: Vec<ObjectId> = members.iter().map(|member| member.group_id).collect();
+        let names: HashMap<ObjectId, String> = self
+            .groups
+            .find(doc! {
+                "_id": { "$in": group_ids },
+                "visibility": { "$ne": GroupVisibility::InviteOnly.name() },
+            })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect::<Vec<Group>>()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .into_iter()
+            .map(|group| (group.id, group.name))
+            .collect();
+
+        Ok(members
+            .into_iter()
+            .filter_map(|member| {
+                let name = names.get(&member.group_id)?;
+                Some(Activity {
+                    kind: ActivityKind::JoinedGroup,
+                    actor_id: member.user_id,
+                    post_id: None,
+                    comment_id: None,
+                    group_id: Some(member.group_id),
+                    summary: Some(name.clone()),
+                    // Activation time; invitations and requests are accepted later
+                    created_at: member.updated_at,
+                })
+            })
+            .collect())
+    }
+}
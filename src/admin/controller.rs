@@ -0,0 +1,90 @@
+use crate::middleware::auth::AuthUser;
+use crate::utils::error::CustomError;
+use crate::utils::outbox::{EmailOutbox, OutboxEmailStatus, OutboxStatus};
+use crate::utils::response::ApiResponse;
+use actix_web::{HttpResponse, web};
+use mongodb::bson::oid::ObjectId;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct OutboxQuery {
+    pub status: Option<String>,
+    pub email: Option<String>,
+    pub limit: Option<i64>,
+}
+
+fn parse_outbox_id(id: String) -> Result<ObjectId, CustomError> {
+    ObjectId::parse_str(id)
+        .map_err(|_| CustomError::BadRequestError("Invalid outbox email ID".to_string()))
+}
+
+/// Delivery status of recent emails, for support
+/// GET /admin/email-outbox?status=failed&email=...&limit=50
+pub async fn list_outbox_emails(
+    auth_user: AuthUser,
+    outbox: web::Data<EmailOutbox>,
+    query: web::Query<OutboxQuery>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_admin()?;
+
+    let query = query.into_inner();
+    let status = match query.status.as_deref() {
+        Some(name) => Some(OutboxStatus::from_name(name).ok_or_else(|| {
+            CustomError::BadRequestError(
+                "status must be one of pending, sending, sent, failed or skipped".to_string(),
+            )
+        })?),
+        None => None,
+    };
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+
+    let emails: Vec<OutboxEmailStatus> = outbox
+        .list(status, query.email.as_deref(), limit)
+        .await?
+        .into_iter()
+        .map(OutboxEmailStatus::from)
+        .collect();
+
+    Ok(ApiResponse::ok("Outbox emails retrieved successfully")
+        .list(emails)
+        .into())
+}
+
+/// Delivery status of a single email
+/// GET /admin/email-outbox/{id}
+pub async fn get_outbox_email(
+    auth_user: AuthUser,
+    outbox: web::Data<EmailOutbox>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_admin()?;
+
+    let id = parse_outbox_id(path.into_inner())?;
+    let email = outbox
+        .get(&id)
+        .await?
+        .ok_or_else(|| CustomError::NotFoundError("Outbox email not found".to_string()))?;
+
+    Ok(ApiResponse::ok("Outbox email retrieved successfully")
+        .data(OutboxEmailStatus::from(email))
+        .into())
+}
+
+/// Queue a failed email for another round of delivery attempts
+/// POST /admin/email-outbox/{id}/retry
+pub async fn retry_outbox_email(
+    auth_user: AuthUser,
+    outbox: web::Data<EmailOutbox>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_admin()?;
+
+    let id = parse_outbox_id(path.into_inner())?;
+    if !outbox.requeue(&id).await? {
+        return Err(CustomError::NotFoundError(
+            "No failed outbox email with that ID".to_string(),
+        ));
+    }
+
+    Ok(ApiResponse::ok("Email queued for delivery").into())
+}
@@ -0,0 +1,16 @@
+use super::controller::{get_outbox_email, list_outbox_emails, retry_outbox_email};
+use crate::middleware::auth::verify_token;
+use crate::middleware::limits::RequestTimeout;
+use actix_web::web;
+use actix_web_httpauth::middleware::HttpAuthentication;
+
+pub fn admin_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/admin/email-outbox")
+            .wrap(RequestTimeout::standard())
+            .wrap(HttpAuthentication::bearer(verify_token))
+            .route("", web::get().to(list_outbox_emails))
+            .route("/{id}", web::get().to(get_outbox_email))
+            .route("/{id}/retry", web::post().to(retry_outbox_email)),
+    );
+}
@@ -0,0 +1,78 @@
+use crate::analytics::model::{EventBatchRequest, IngestSummary};
+use crate::analytics::service::AnalyticsService;
+use crate::middleware::auth::AuthUser;
+use crate::utils::error::CustomError;
+use crate::utils::i18n::Locale;
+use crate::utils::response::ApiResponse;
+use crate::utils::validation::ValidatedJson;
+use actix_web::{HttpResponse, web};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct SummaryQuery {
+    pub days: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DailyQuery {
+    pub name: String,
+    pub days: Option<i64>,
+}
+
+fn lookback(days: Option<i64>) -> i64 {
+    days.unwrap_or(7).clamp(1, 90)
+}
+
+/// Record a batch of client-side events (screen views, shares, ...)
+/// POST /events
+pub async fn ingest_events(
+    locale: Locale,
+    auth_user: AuthUser,
+    analytics_service: web::Data<AnalyticsService>,
+    body: ValidatedJson<EventBatchRequest>,
+) -> Result<HttpResponse, CustomError> {
+    let events = body.into_inner().events;
+    let received = events.len();
+    let stored = analytics_service.ingest(auth_user.id, events).await?;
+
+    Ok(ApiResponse::with_status(
+        actix_web::http::StatusCode::ACCEPTED,
+        locale.t("events-accepted"),
+    )
+    .data(IngestSummary { received, stored })
+    .into())
+}
+
+/// Estimated totals and unique users per event
+/// GET /admin/analytics/summary?days=7
+pub async fn event_summary(
+    auth_user: AuthUser,
+    analytics_service: web::Data<AnalyticsService>,
+    query: web::Query<SummaryQuery>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_admin()?;
+
+    let summary = analytics_service.summary(lookback(query.days)).await?;
+
+    Ok(ApiResponse::ok("Event summary retrieved successfully")
+        .list(summary)
+        .into())
+}
+
+/// Estimated daily counts of one event
+/// GET /admin/analytics/daily?name=screen_view&days=30
+pub async fn daily_counts(
+    auth_user: AuthUser,
+    analytics_service: web::Data<AnalyticsService>,
+    query: web::Query<DailyQuery>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_admin()?;
+
+    let counts = analytics_service
+        .daily(&query.name, lookback(query.days))
+        .await?;
+
+    Ok(ApiResponse::ok("Daily event counts retrieved successfully")
+        .list(counts)
+        .into())
+}
@@ -0,0 +1,21 @@
+use super::controller::{daily_counts, event_summary, ingest_events};
+use crate::middleware::auth::verify_token;
+use crate::middleware::limits::RequestTimeout;
+use actix_web::web;
+use actix_web_httpauth::middleware::HttpAuthentication;
+
+pub fn analytics_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/events")
+            .wrap(RequestTimeout::standard())
+            .wrap(HttpAuthentication::bearer(verify_token))
+            .route("", web::post().to(ingest_events)),
+    );
+    cfg.service(
+        web::scope("/admin/analytics")
+            .wrap(RequestTimeout::standard())
+            .wrap(HttpAuthentication::bearer(verify_token))
+            .route("/summary", web::get().to(event_summary))
+            .route("/daily", web::get().to(daily_counts)),
+    );
+}
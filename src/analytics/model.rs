@@ -0,0 +1,85 @@
+use crate::utils::datetime::bson_datetime;
+use chrono::{DateTime, Utc};
+use mongodb::bson::{Document, oid::ObjectId};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+use validator::{Validate, ValidationError};
+
+/// Most properties kept per event
+pub const MAX_PROPERTIES: usize = 20;
+/// String property values are cut to this many characters
+pub const MAX_PROPERTY_LENGTH: usize = 200;
+
+static EVENT_NAME: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[a-z][a-z0-9_.]{0,63}$").expect("valid event name regex"));
+
+fn event_name(name: &str) -> Result<(), ValidationError> {
+    if EVENT_NAME.is_match(name) {
+        return Ok(());
+    }
+    Err(ValidationError::new("event_name")
+        .with_message("must be lowercase letters, digits, '_' or '.'".into()))
+}
+
+/// A client-side event as stored; properties are already scrubbed
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AnalyticsEvent {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub name: String,
+    pub user_id: ObjectId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    pub properties: Document,
+    /// Share of events kept when this one was; aggregates weight by its inverse
+    pub sample_rate: f64,
+    #[serde(with = "bson_datetime")]
+    pub occurred_at: DateTime<Utc>,
+    #[serde(with = "bson_datetime")]
+    pub received_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct EventInput {
+    #[validate(custom(function = "event_name"))]
+    pub name: String,
+    #[validate(length(max = 100, message = "must be at most 100 characters"))]
+    pub session_id: Option<String>,
+    #[serde(default)]
+    pub properties: serde_json::Map<String, serde_json::Value>,
+    /// Client clock; defaults to when the batch arrives
+    pub occurred_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct EventBatchRequest {
+    #[validate(
+        length(min = 1, max = 50, message = "must contain between 1 and 50 events"),
+        nested
+    )]
+    pub events: Vec<EventInput>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IngestSummary {
+    pub received: usize,
+    pub stored: usize,
+}
+
+/// Estimated totals for one event name
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EventSummary {
+    #[serde(rename = "_id")]
+    pub name: String,
+    pub count: f64,
+    pub unique_users: i64,
+}
+
+/// Estimated count of an event on one day (UTC)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DailyCount {
+    #[serde(rename = "_id")]
+    pub day: String,
+    pub count: f64,
+}
@@ -0,0 +1,195 @@
+use crate::analytics::model::{
+    AnalyticsEvent, DailyCount, EventInput, EventSummary, MAX_PROPERTIES, MAX_PROPERTY_LENGTH,
+};
+use crate::utils::error::CustomError;
+use chrono::{Duration, Utc};
+use futures_util::TryStreamExt;
+use mongodb::bson::{self, Bson, Document, doc, oid::ObjectId};
+use mongodb::options::IndexOptions;
+use mongodb::{Client, Collection, IndexModel};
+use regex::Regex;
+use serde_json::Value;
+use std::sync::LazyLock;
+use std::time::Duration as StdDuration;
+
+/// Raw events are kept this long; dashboards only look back a few weeks
+const EVENT_RETENTION_DAYS: u64 = 90;
+
+/// Property keys that are dropped outright
+const PII_KEYS: &[&str] = &[
+    "email", "phone", "password", "passwd", "token", "secret", "address", "ssn", "card",
+];
+
+static EMAIL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}").expect("valid email regex")
+});
+static PHONE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\+?\d[\d\s().-]{6,}\d").expect("valid phone regex"));
+
+/// Keep scalar properties only, drop PII-looking keys and mask emails and
+/// phone numbers inside strings
+fn scrub_properties(properties: serde_json::Map<String, Value>) -> Document {
+    let mut scrubbed = Document::new();
+    for (key, value) in properties {
+        if scrubbed.len() >= MAX_PROPERTIES {
+            break;
+        }
+        let lowered = key.to_lowercase();
+        if PII_KEYS.iter().any(|pii| lowered.contains(pii)) {
+            continue;
+        }
+
+        let value = match value {
+            Value::Bool(b) => Bson::Boolean(b),
+            Value::Number(n) => match (n.as_i64(), n.as_f64()) {
+                (Some(i), _) => Bson::Int64(i),
+                (None, Some(f)) => Bson::Double(f),
+                _ => continue,
+            },
+            Value::String(s) => {
+                let s = EMAIL.replace_all(&s, "[email]");
+                let s = PHONE.replace_all(&s, "[phone]");
+                Bson::String(s.chars().take(MAX_PROPERTY_LENGTH).collect())
+            }
+            Value::Null | Value::Array(_) | Value::Object(_) => continue,
+        };
+        scrubbed.insert(key.chars().take(64).collect::<String>(), value);
+    }
+    scrubbed
+}
+
+pub struct AnalyticsService {
+    events: Collection<AnalyticsEvent>,
+    /// Share of events kept, from `ANALYTICS_SAMPLE_RATE` (0 to 1, default 1)
+    sample_rate: f64,
+}
+
+impl AnalyticsService {
+    pub fn new(client: &Client) -> Self {
+        let db = client.database("rust_blogdb");
+        let sample_rate = std::env::var("ANALYTICS_SAMPLE_RATE")
+            .ok()
+            .and_then(|value| value.parse::<f64>().ok())
+            .filter(|rate| *rate > 0.0)
+            .map(|rate| rate.min(1.0))
+            .unwrap_or(1.0);
+
+        AnalyticsService {
+            events: db.collection::<AnalyticsEvent>("analytics_events"),
+            sample_rate,
+        }
+    }
+
+    /// Expire raw events, and serve the per-name dashboard queries
+    #[tracing::instrument(skip_all)]
+    pub async fn ensure_indexes(&self) -> Result<(), CustomError> {
+        let indexes = vec![
+            IndexModel::builder()
+                .keys(doc! { "received_at": 1 })
+                .options(
+                    IndexOptions::builder()
+                        .expire_after(StdDuration::from_secs(EVENT_RETENTION_DAYS * 86400))
+                        .build(),
+                )
+                .build(),
+            IndexModel::builder()
+                .keys(doc! { "name": 1, "occurred_at": -1 })
+                .build(),
+        ];
+
+        self.events.create_indexes(indexes).await.map_err(|e| {
+            CustomError::InternalServerError(format!("Failed to create analytics indexes: {}", e))
+        })?;
+
+        Ok(())
+    }
+
+    /// Sample, scrub and store a batch; returns how many events were kept
+    #[tracing::instrument(skip_all)]
+    pub async fn ingest(
+        &self,
+        user_id: ObjectId,
+        events: Vec<EventInput>,
+    ) -> Result<usize, CustomError> {
+        let now = Utc::now();
+        let kept: Vec<AnalyticsEvent> = events
+            .into_iter()
+            .filter(|_| self.sample_rate >= 1.0 || rand::random::<f64>() < self.sample_rate)
+            .map(|event| AnalyticsEvent {
+                id: None,
+                name: event.name,
+                user_id,
+                session_id: event.session_id,
+                properties: scrub_properties(event.properties),
+                sample_rate: self.sample_rate,
+                // Clamp client clocks that run ahead
+                occurred_at: event.occurred_at.map_or(now, |at| at.min(now)),
+                received_at: now,
+            })
+            .collect();
+
+        if kept.is_empty() {
+            return Ok(0);
+        }
+        self.events.insert_many(&kept).await.map_err(|e| {
+            CustomError::InternalServerError(format!("Failed to store events: {}", e))
+        })?;
+
+        Ok(kept.len())
+    }
+
+    /// Estimated totals and unique users per event name over the last `days`
+    #[tracing::instrument(skip_all)]
+    pub async fn summary(&self, days: i64) -> Result<Vec<EventSummary>, CustomError> {
+        let pipeline = vec![
+            doc! { "$match": { "occurred_at": { "$gte": Self::since(days) } } },
+            doc! { "$group": {
+                "_id": "$name",
+                "count": { "$sum": { "$divide": [1, "$sample_rate"] } },
+                "users": { "$addToSet": "$user_id" },
+            } },
+            doc! { "$project": { "count": 1, "unique_users": { "$size": "$users" } } },
+            doc! { "$sort": { "count": -1 } },
+        ];
+        self.aggregate(pipeline).await
+    }
+
+    /// Estimated daily counts of one event over the last `days`
+    #[tracing::instrument(skip_all)]
+    pub async fn daily(&self, name: &str, days: i64) -> Result<Vec<DailyCount>, CustomError> {
+        let pipeline = vec![
+            doc! { "$match": { "name": name, "occurred_at": { "$gte": Self::since(days) } } },
+            doc! { "$group": {
+                "_id": { "$dateToString": { "format": "%Y-%m-%d", "date": "$occurred_at" } },
+                "count": { "$sum": { "$divide": [1, "$sample_rate"] } },
+            } },
+            doc! { "$sort": { "_id": 1 } },
+        ];
+        self.aggregate(pipeline).await
+    }
+
+    fn since(days: i64) -> bson::DateTime {
+        bson::DateTime::from_chrono(Utc::now() - Duration::days(days))
+    }
+
+    async fn aggregate<T: serde::de::DeserializeOwned>(
+        &self,
+        pipeline: Vec<Document>,
+    ) -> Result<Vec<T>, CustomError> {
+        let rows: Vec<Document> = self
+            .events
+            .aggregate(pipeline)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        rows.into_iter()
+            .map(|row| {
+                bson::from_document(row)
+                    .map_err(|e| CustomError::InternalServerError(e.to_string()))
+            })
+            .collect()
+    }
+}
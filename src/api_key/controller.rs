@@ -0,0 +1,75 @@
+use crate::api_key::model::CreateApiKeyRequest;
+use crate::api_key::service::ApiKeyService;
+use crate::middleware::auth::AuthUser;
+use crate::utils::error::CustomError;
+use crate::utils::outbox::EmailOutbox;
+use crate::utils::response::ApiResponse;
+use crate::utils::validation::ValidatedJson;
+use actix_web::{HttpResponse, web};
+use mongodb::bson::oid::ObjectId;
+use serde_json::json;
+
+/// Create an API key; the plaintext key is only returned here
+/// POST /admin/api-keys
+pub async fn create_api_key(
+    auth_user: AuthUser,
+    api_key_service: web::Data<ApiKeyService>,
+    body: ValidatedJson<CreateApiKeyRequest>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_admin()?;
+
+    let body = body.into_inner();
+    let (api_key, plaintext) = api_key_service
+        .create_key(body.name, body.scopes, auth_user.id)
+        .await?;
+
+    Ok(
+        ApiResponse::created("API key created. Store it now; it cannot be shown again.")
+            .data(json!({ "key": plaintext, "api_key": api_key }))
+            .into(),
+    )
+}
+
+/// List API keys
+/// GET /admin/api-keys
+pub async fn list_api_keys(
+    auth_user: AuthUser,
+    api_key_service: web::Data<ApiKeyService>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_admin()?;
+
+    let keys = api_key_service.list_keys().await?;
+
+    Ok(ApiResponse::ok("API keys retrieved successfully")
+        .list(keys)
+        .into())
+}
+
+/// Revoke an API key
+/// DELETE /admin/api-keys/{id}
+pub async fn revoke_api_key(
+    auth_user: AuthUser,
+    api_key_service: web::Data<ApiKeyService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_admin()?;
+
+    let key_id = ObjectId::parse_str(path.into_inner())
+        .map_err(|_| CustomError::BadRequestError("Invalid API key ID".to_string()))?;
+
+    if !api_key_service.revoke_key(&key_id).await? {
+        return Err(CustomError::NotFoundError("API key not found".to_string()));
+    }
+
+    Ok(ApiResponse::ok("API key revoked successfully").into())
+}
+
+/// Deliver pending outbox emails now instead of waiting for the worker
+/// POST /internal/jobs/email-outbox
+pub async fn run_email_outbox(outbox: web::Data<EmailOutbox>) -> Result<HttpResponse, CustomError> {
+    let delivered = outbox.process_pending().await?;
+
+    Ok(ApiResponse::ok("Email outbox processed")
+        .data(json!({ "delivered": delivered }))
+        .into())
+}
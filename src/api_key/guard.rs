@@ -0,0 +1,92 @@
+use crate::api_key::service::ApiKeyService;
+use crate::utils::error::CustomError;
+use actix_web::body::EitherBody;
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform, forward_ready};
+use actix_web::{Error, HttpMessage, ResponseError, web};
+use futures_util::future::{LocalBoxFuture, Ready, ready};
+use std::rc::Rc;
+
+/// Header carrying the API key
+pub const API_KEY_HEADER: &str = "X-API-Key";
+
+/// Require a valid API key with `scope` on every route in a scope.
+///
+/// On success the verified `ApiKey` is stored in the request extensions.
+#[derive(Clone)]
+pub struct ApiKeyGuard {
+    scope: &'static str,
+}
+
+impl ApiKeyGuard {
+    pub fn new(scope: &'static str) -> Self {
+        ApiKeyGuard { scope }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for ApiKeyGuard
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = ApiKeyGuardMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ApiKeyGuardMiddleware {
+            service: Rc::new(service),
+            scope: self.scope,
+        }))
+    }
+}
+
+pub struct ApiKeyGuardMiddleware<S> {
+    service: Rc<S>,
+    scope: &'static str,
+}
+
+impl<S, B> Service<ServiceRequest> for ApiKeyGuardMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let scope = self.scope;
+
+        Box::pin(async move {
+            let key = req
+                .headers()
+                .get(API_KEY_HEADER)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string);
+
+            let verified = match (key, req.app_data::<web::Data<ApiKeyService>>()) {
+                (Some(key), Some(api_key_service)) => api_key_service.verify(&key, scope).await,
+                (None, _) => Err(CustomError::UnauthorizedError(format!(
+                    "Missing {} header",
+                    API_KEY_HEADER
+                ))),
+                (_, None) => Err(CustomError::InternalServerError(
+                    "API key service is not configured".to_string(),
+                )),
+            };
+
+            match verified {
+                Ok(api_key) => {
+                    req.extensions_mut().insert(api_key);
+                    service.call(req).await.map(|res| res.map_into_left_body())
+                }
+                Err(e) => Ok(req.into_response(e.error_response()).map_into_right_body()),
+            }
+        })
+    }
+}
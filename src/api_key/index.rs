@@ -0,0 +1,28 @@
+use super::controller::{create_api_key, list_api_keys, revoke_api_key, run_email_outbox};
+use crate::api_key::guard::ApiKeyGuard;
+use crate::api_key::model::JOBS_SCOPE;
+use crate::middleware::auth::verify_token;
+use crate::middleware::limits::RequestTimeout;
+use actix_web::web;
+use actix_web_httpauth::middleware::HttpAuthentication;
+
+pub fn api_key_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/admin/api-keys")
+            .wrap(RequestTimeout::standard())
+            .wrap(HttpAuthentication::bearer(verify_token))
+            .route("", web::post().to(create_api_key))
+            .route("", web::get().to(list_api_keys))
+            .route("/{id}", web::delete().to(revoke_api_key)),
+    );
+}
+
+/// Service-to-service routes authenticated by API key instead of JWT
+pub fn internal_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/internal/jobs")
+            .wrap(RequestTimeout::standard())
+            .wrap(ApiKeyGuard::new(JOBS_SCOPE))
+            .route("/email-outbox", web::post().to(run_email_outbox)),
+    );
+}
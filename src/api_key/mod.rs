@@ -0,0 +1,5 @@
+pub mod controller;
+pub mod guard;
+pub mod index;
+pub mod model;
+pub mod service;
@@ -0,0 +1,58 @@
+use crate::utils::datetime::{bson_datetime, option_bson_datetime};
+use crate::utils::validation::not_blank;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+/// Scope granting every permission
+pub const WILDCARD_SCOPE: &str = "*";
+
+/// Scope for triggering background jobs through `/internal/jobs`
+pub const JOBS_SCOPE: &str = "jobs";
+
+/// A service-to-service credential. Only a SHA-256 hash of the key is stored.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ApiKey {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub name: String,
+    /// First characters of the key, so admins can tell keys apart
+    pub prefix: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub key_hash: String,
+    pub scopes: Vec<String>,
+    pub created_by: ObjectId,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(default, with = "option_bson_datetime")]
+    pub last_used_at: Option<DateTime<Utc>>,
+    #[serde(default, with = "option_bson_datetime")]
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl ApiKey {
+    /// Whether this key may call routes requiring `scope`
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes
+            .iter()
+            .any(|s| s == scope || s == WILDCARD_SCOPE)
+    }
+
+    /// Copy safe to return from the API
+    pub fn redacted(mut self) -> Self {
+        self.key_hash.clear();
+        self
+    }
+}
+
+#[derive(Deserialize, Validate)]
+pub struct CreateApiKeyRequest {
+    #[validate(
+        length(max = 100, message = "must be at most 100 characters"),
+        custom(function = "not_blank")
+    )]
+    pub name: String,
+    #[validate(length(min = 1, message = "must list at least one scope"))]
+    pub scopes: Vec<String>,
+}
@@ -0,0 +1,154 @@
+use crate::api_key::model::ApiKey;
+use crate::utils::datetime::bson_now;
+use crate::utils::error::CustomError;
+use chrono::Utc;
+use futures_util::TryStreamExt;
+use mongodb::bson::{doc, oid::ObjectId};
+use mongodb::options::IndexOptions;
+use mongodb::{Client, Collection, IndexModel};
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+/// Prepended to generated keys so they are recognisable in logs and scanners
+const KEY_PREFIX: &str = "sk_";
+
+/// Characters of the key kept in clear for identification
+const VISIBLE_PREFIX_LEN: usize = 10;
+
+pub struct ApiKeyService {
+    collection: Collection<ApiKey>,
+}
+
+impl ApiKeyService {
+    pub fn new(client: &Client) -> Self {
+        let collection = client
+            .database("rust_blogdb")
+            .collection::<ApiKey>("api_keys");
+        ApiKeyService { collection }
+    }
+
+    /// Create the unique index used to look keys up by hash
+    #[tracing::instrument(skip_all)]
+    pub async fn ensure_indexes(&self) -> Result<(), CustomError> {
+        let index = IndexModel::builder()
+            .keys(doc! { "key_hash": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+
+        self.collection.create_index(index).await.map_err(|e| {
+            CustomError::InternalServerError(format!("Failed to create API key index: {}", e))
+        })?;
+
+        Ok(())
+    }
+
+    /// Generate a new key. The plaintext is returned once and never stored.
+    #[tracing::instrument(skip_all)]
+    pub async fn create_key(
+        &self,
+        name: String,
+        scopes: Vec<String>,
+        created_by: ObjectId,
+    ) -> Result<(ApiKey, String), CustomError> {
+        let secret: [u8; 32] = rand::rng().random();
+        let plaintext = format!("{}{}", KEY_PREFIX, hex(&secret));
+
+        let mut api_key = ApiKey {
+            id: None,
+            name,
+            prefix: plaintext[..VISIBLE_PREFIX_LEN].to_string(),
+            key_hash: hash_key(&plaintext),
+            scopes,
+            created_by,
+            created_at: Utc::now(),
+            last_used_at: None,
+            revoked_at: None,
+        };
+
+        let result = self.collection.insert_one(&api_key).await.map_err(|e| {
+            CustomError::InternalServerError(format!("Failed to create API key: {}", e))
+        })?;
+        api_key.id = result.inserted_id.as_object_id();
+
+        Ok((api_key.redacted(), plaintext))
+    }
+
+    /// List all keys, newest first, without their hashes
+    #[tracing::instrument(skip_all)]
+    pub async fn list_keys(&self) -> Result<Vec<ApiKey>, CustomError> {
+        let cursor = self
+            .collection
+            .find(doc! {})
+            .sort(doc! { "created_at": -1 })
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to fetch API keys: {}", e))
+            })?;
+
+        let keys: Vec<ApiKey> = cursor.try_collect().await.map_err(|e| {
+            CustomError::InternalServerError(format!("Failed to collect API keys: {}", e))
+        })?;
+
+        Ok(keys.into_iter().map(ApiKey::redacted).collect())
+    }
+
+    /// Revoke a key, returning whether an active key was found
+    #[tracing::instrument(skip_all)]
+    pub async fn revoke_key(&self, id: &ObjectId) -> Result<bool, CustomError> {
+        let result = self
+            .collection
+            .update_one(
+                doc! { "_id": id, "revoked_at": null },
+                doc! { "$set": { "revoked_at": bson_now() } },
+            )
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to revoke API key: {}", e))
+            })?;
+
+        Ok(result.matched_count > 0)
+    }
+
+    /// Check a presented key and that it grants `scope`
+    #[tracing::instrument(skip_all)]
+    pub async fn verify(&self, key: &str, scope: &str) -> Result<ApiKey, CustomError> {
+        let api_key = self
+            .collection
+            .find_one(doc! { "key_hash": hash_key(key), "revoked_at": null })
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to verify API key: {}", e))
+            })?
+            .ok_or_else(|| CustomError::UnauthorizedError("Invalid API key".to_string()))?;
+
+        if !api_key.has_scope(scope) {
+            return Err(CustomError::ForbiddenError(format!(
+                "API key is missing the '{}' scope",
+                scope
+            )));
+        }
+
+        // Usage tracking is informational only
+        if let Err(e) = self
+            .collection
+            .update_one(
+                doc! { "_id": api_key.id },
+                doc! { "$set": { "last_used_at": bson_now() } },
+            )
+            .await
+        {
+            log::warn!("Failed to record API key usage: {}", e);
+        }
+
+        Ok(api_key.redacted())
+    }
+}
+
+/// Keys are long random strings, so a fast unsalted hash is sufficient
+pub fn hash_key(key: &str) -> String {
+    hex(&Sha256::digest(key.as_bytes()))
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
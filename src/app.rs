@@ -0,0 +1,61 @@
+use crate::app_state::AppState;
+use crate::middleware::cors::cors;
+use crate::middleware::error_handler::handle_error;
+use crate::middleware::not_found::not_found;
+use crate::middleware::rate_limit::IpRateLimit;
+use crate::middleware::security_headers::security_headers;
+use crate::router::index::routes;
+use crate::utils::response::ApiResponse;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::http::StatusCode;
+use actix_web::middleware::{Compress, Condition, ErrorHandlers, Logger};
+use actix_web::{App, Error, Responder, get, web};
+use tracing_actix_web::TracingLogger;
+
+#[get("/")]
+async fn default() -> impl Responder {
+    ApiResponse::ok("Welcome to my Rust web-Server")
+}
+
+/// The application every worker serves: middleware, app data and routes.
+/// The test harness builds the same app, so tests see what clients see.
+pub fn build_app(
+    state: &AppState,
+) -> App<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = Error,
+        InitError = (),
+    >,
+> {
+    let config = &state.config;
+    App::new()
+        // gzip/brotli for large JSON responses
+        .wrap(config.compression)
+        .wrap(Condition::new(
+            config.compression.enabled,
+            Compress::default(),
+        ))
+        .wrap(IpRateLimit::global())
+        // Headers added to every response
+        .wrap(security_headers(&config.security_headers))
+        // Cross-origin access for browser clients
+        .wrap(Condition::new(config.cors.is_enabled(), cors(&config.cors)))
+        .wrap(TracingLogger::default())
+        .wrap(Logger::default())
+        .wrap(Logger::new("%a %{User-Agent}i"))
+        // Body size limits
+        .app_data(web::JsonConfig::default().limit(config.limits.json_limit))
+        .app_data(web::PayloadConfig::new(config.limits.json_limit))
+        .configure(|cfg| state.register(cfg))
+        .configure(routes)
+        .wrap(
+            ErrorHandlers::new()
+                .handler(StatusCode::NOT_FOUND, not_found)
+                .default_handler(handle_error),
+        )
+        .service(default)
+}
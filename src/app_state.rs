@@ -0,0 +1,623 @@
+use crate::access_token::service::AccessTokenService;
+use crate::activity::service::ActivityService;
+use crate::analytics::service::AnalyticsService;
+use crate::api_key::service::ApiKeyService;
+use crate::badge::service::BadgeService;
+use crate::chat::server::ChatServer;
+use crate::chat::service::ChatService;
+use crate::comment::service::CommentService;
+use crate::counter::service::CounterService;
+use crate::database::RedisService;
+use crate::export::service::ExportService;
+use crate::feature_flag::service::FeatureFlagService;
+use crate::fingerprint::service::FingerprintService;
+use crate::friend::service::FriendService;
+#[cfg(feature = "graphql")]
+use crate::graphql::schema::{BlogSchema, build_schema};
+use crate::group::service::GroupService;
+use crate::insights::service::InsightsService;
+use crate::leaderboard::service::LeaderboardService;
+use crate::link_safety::service::LinkGuard;
+use crate::middleware::limits::HttpLimits;
+use crate::moderation::service::ModerationService;
+use crate::nearby::service::NearbyService;
+use crate::notification::service::NotificationService;
+use crate::oauth::service::GithubOAuth;
+use crate::post::post_service::PostService;
+use crate::share::service::ShareService;
+use crate::spam_guard::service::SpamGuard;
+use crate::sticker::service::StickerService;
+use crate::subscription::service::SubscriptionService;
+use crate::topic::service::TopicService;
+use crate::uploader::service::MediaService;
+use crate::user::service::UserService;
+use crate::utils::config::AppConfig;
+use crate::utils::outbox::EmailOutbox;
+use crate::verification::service::VerificationService;
+use actix::{Actor, Addr};
+use actix_web::web;
+use log::info;
+use mongodb::Client;
+
+/// Everything handlers extract from app data, built once at startup and
+/// shared by every worker
+#[derive(Clone)]
+pub struct AppState {
+    pub config: web::Data<AppConfig>,
+    pub http_limits: web::Data<HttpLimits>,
+    pub mongo_client: web::Data<Client>,
+    pub redis_service: web::Data<RedisService>,
+    pub chat_server: web::Data<Addr<ChatServer>>,
+    pub counter_service: web::Data<CounterService>,
+    pub user_service: web::Data<UserService>,
+    pub post_service: web::Data<PostService>,
+    pub comment_service: web::Data<CommentService>,
+    pub notification_service: web::Data<NotificationService>,
+    pub leaderboard_service: web::Data<LeaderboardService>,
+    pub api_key_service: web::Data<ApiKeyService>,
+    pub email_outbox: web::Data<EmailOutbox>,
+    pub group_service: web::Data<GroupService>,
+    pub moderation_service: web::Data<ModerationService>,
+    pub friend_service: web::Data<FriendService>,
+    pub chat_service: web::Data<ChatService>,
+    pub activity_service: web::Data<ActivityService>,
+    pub analytics_service: web::Data<AnalyticsService>,
+    pub feature_flag_service: web::Data<FeatureFlagService>,
+    pub badge_service: web::Data<BadgeService>,
+    pub topic_service: web::Data<TopicService>,
+    pub verification_service: web::Data<VerificationService>,
+    pub access_token_service: web::Data<AccessTokenService>,
+    pub spam_guard: web::Data<SpamGuard>,
+    pub link_guard: web::Data<LinkGuard>,
+    pub github_oauth: web::Data<GithubOAuth>,
+    pub fingerprint_service: web::Data<FingerprintService>,
+    pub subscription_service: web::Data<SubscriptionService>,
+    pub share_service: web::Data<ShareService>,
+    pub media_service: web::Data<MediaService>,
+    pub sticker_service: web::Data<StickerService>,
+    pub nearby_service: web::Data<NearbyService>,
+    pub insights_service: web::Data<InsightsService>,
+    pub export_service: web::Data<ExportService>,
+    #[cfg(feature = "graphql")]
+    pub graphql_schema: web::Data<BlogSchema>,
+}
+
+/// Construct every service, create their indexes and start the chat server.
+/// Panics if an index can't be created, as services rely on them.
+pub async fn build_app_state(
+    config: &AppConfig,
+    mongo_client: &Client,
+    redis_service: RedisService,
+) -> AppState {
+    let redis_service = web::Data::new(redis_service);
+
+    // Start WebSocket chat server
+    let chat_server = ChatServer::new(redis_service.get_ref().clone()).start();
+    info!("WebSocket chat server started");
+
+    // Create services
+    let counter_service = web::Data::new(CounterService::new(
+        mongo_client,
+        redis_service.get_ref().clone(),
+    ));
+    counter_service
+        .ensure_indexes()
+        .await
+        .expect("Failed to create counter indexes");
+    let user_service = web::Data::new(UserService::new(
+        mongo_client,
+        redis_service.get_ref().clone(),
+        config,
+    ));
+    user_service
+        .ensure_indexes()
+        .await
+        .expect("Failed to create user indexes");
+    let post_service = web::Data::new(PostService::new(mongo_client));
+    let comment_service = web::Data::new(CommentService::new(mongo_client));
+    let notification_service = web::Data::new(NotificationService::new(
+        mongo_client,
+        redis_service.get_ref().clone(),
+    ));
+    notification_service
+        .ensure_indexes()
+        .await
+        .expect("Failed to create notification indexes");
+    let leaderboard_service = web::Data::new(LeaderboardService::new(
+        mongo_client,
+        redis_service.get_ref().clone(),
+    ));
+    let api_key_service = web::Data::new(ApiKeyService::new(mongo_client));
+    api_key_service
+        .ensure_indexes()
+        .await
+        .expect("Failed to create API key indexes");
+    let email_outbox = web::Data::new(EmailOutbox::new(mongo_client, config));
+    email_outbox
+        .ensure_indexes()
+        .await
+        .expect("Failed to create email outbox indexes");
+    let group_service = web::Data::new(GroupService::new(
+        mongo_client,
+        redis_service.get_ref().clone(),
+    ));
+    group_service
+        .ensure_indexes()
+        .await
+        .expect("Failed to create group indexes");
+    let moderation_service = web::Data::new(ModerationService::new(mongo_client));
+    moderation_service
+        .ensure_indexes()
+        .await
+        .expect("Failed to create moderation indexes");
+    let friend_service = web::Data::new(FriendService::new(mongo_client));
+    friend_service
+        .ensure_indexes()
+        .await
+        .expect("Failed to create friend indexes");
+    let chat_service = web::Data::new(ChatService::new(
+        mongo_client,
+        redis_service.get_ref().clone(),
+    ));
+    chat_service
+        .ensure_indexes()
+        .await
+        .expect("Failed to create chat indexes");
+    let activity_service = web::Data::new(ActivityService::new(mongo_client));
+    let analytics_service = web::Data::new(AnalyticsService::new(mongo_client));
+    analytics_service
+        .ensure_indexes()
+        .await
+        .expect("Failed to create analytics indexes");
+    let feature_flag_service = web::Data::new(FeatureFlagService::new(
+        mongo_client,
+        redis_service.get_ref().clone(),
+    ));
+    let badge_service = web::Data::new(BadgeService::new(mongo_client));
+    let topic_service = web::Data::new(TopicService::new(
+        mongo_client,
+        redis_service.get_ref().clone(),
+    ));
+    topic_service
+        .ensure_indexes()
+        .await
+        .expect("Failed to create topic indexes");
+    let verification_service = web::Data::new(VerificationService::new(mongo_client));
+    verification_service
+        .ensure_indexes()
+        .await
+        .expect("Failed to create verification indexes");
+    let access_token_service = web::Data::new(AccessTokenService::new(mongo_client));
+    access_token_service
+        .ensure_indexes()
+        .await
+        .expect("Failed to create access token indexes");
+    let spam_guard = web::Data::new(SpamGuard::new(
+        mongo_client,
+        redis_service.get_ref().clone(),
+        config.spam,
+    ));
+    spam_guard
+        .ensure_indexes()
+        .await
+        .expect("Failed to create spam guard indexes");
+    let link_guard = web::Data::new(LinkGuard::new(mongo_client, config.link_safety.clone()));
+    link_guard
+        .ensure_indexes()
+        .await
+        .expect("Failed to create link safety indexes");
+    let github_oauth = web::Data::new(GithubOAuth::new(
+        config.github_oauth.clone(),
+        redis_service.get_ref().clone(),
+    ));
+    let fingerprint_service = web::Data::new(FingerprintService::new(
+        mongo_client,
+        config.fingerprint_salt.clone(),
+    ));
+    fingerprint_service
+        .ensure_indexes()
+        .await
+        .expect("Failed to create fingerprint indexes");
+    let subscription_service = web::Data::new(SubscriptionService::new(mongo_client));
+    subscription_service
+        .ensure_indexes()
+        .await
+        .expect("Failed to create subscription indexes");
+    let share_service = web::Data::new(ShareService::new(
+        mongo_client,
+        redis_service.get_ref().clone(),
+    ));
+    share_service
+        .ensure_indexes()
+        .await
+        .expect("Failed to create share indexes");
+    let media_service = web::Data::new(MediaService::new(mongo_client));
+    media_service
+        .ensure_indexes()
+        .await
+        .expect("Failed to create upload indexes");
+    let sticker_service = web::Data::new(StickerService::new(mongo_client));
+    sticker_service
+        .ensure_indexes()
+        .await
+        .expect("Failed to create sticker indexes");
+    let nearby_service = web::Data::new(NearbyService::new(mongo_client));
+    nearby_service
+        .ensure_indexes()
+        .await
+        .expect("Failed to create nearby indexes");
+    let insights_service = web::Data::new(InsightsService::new(
+        mongo_client,
+        redis_service.get_ref().clone(),
+    ));
+    insights_service
+        .ensure_indexes()
+        .await
+        .expect("Failed to create insights indexes");
+    let export_service = web::Data::new(ExportService::new(mongo_client, config));
+    export_service
+        .ensure_indexes()
+        .await
+        .expect("Failed to create export indexes");
+    #[cfg(feature = "graphql")]
+    let graphql_schema = web::Data::new(build_schema(
+        post_service.clone(),
+        group_service.clone(),
+        friend_service.clone(),
+        activity_service.clone(),
+        moderation_service.clone(),
+    ));
+
+    AppState {
+        config: web::Data::new(config.clone()),
+        http_limits: web::Data::new(config.limits),
+        mongo_client: web::Data::new(mongo_client.clone()),
+        redis_service,
+        chat_server: web::Data::new(chat_server),
+        counter_service,
+        user_service,
+        post_service,
+        comment_service,
+        notification_service,
+        leaderboard_service,
+        api_key_service,
+        email_outbox,
+        group_service,
+        moderation_service,
+        friend_service,
+        chat_service,
+        activity_service,
+        analytics_service,
+        feature_flag_service,
+        badge_service,
+        topic_service,
+        verification_service,
+        access_token_service,
+        spam_guard,
+        link_guard,
+        github_oauth,
+        fingerprint_service,
+        subscription_service,
+        share_service,
+        media_service,
+        sticker_service,
+        nearby_service,
+        insights_service,
+        export_service,
+        #[cfg(feature = "graphql")]
+        graphql_schema,
+    }
+}
+
+impl AppState {
+    /// Register every service as app data, for `App::configure`
+    pub fn register(&self, cfg: &mut web::ServiceConfig) {
+        cfg.app_data(self.config.clone())
+            .app_data(self.http_limits.clone())
+            .app_data(self.mongo_client.clone())
+            .app_data(self.redis_service.clone())
+            .app_data(self.chat_server.clone())
+            .app_data(self.counter_service.clone())
+            .app_data(self.user_service.clone())
+            .app_data(self.post_service.clone())
+            .app_data(self.comment_service.clone())
+            .app_data(self.notification_service.clone())
+            .app_data(self.leaderboard_service.clone())
+            .app_data(self.api_key_service.clone())
+            .app_data(self.email_outbox.clone())
+            .app_data(self.group_service.clone())
+            .app_data(self.moderation_service.clone())
+            .app_data(self.friend_service.clone())
+            .app_data(self.chat_service.clone())
+            .app_data(self.activity_service.clone())
+            .app_data(self.analytics_service.clone())
+            .app_data(self.feature_flag_service.clone())
+            .app_data(self.badge_service.clone())
+            .app_data(self.topic_service.clone())
+            .app_data(self.verification_service.clone())
+            .app_data(self.access_token_service.clone())
+            .app_data(self.spam_guard.clone())
+            .app_data(self.link_guard.clone())
+            .app_data(self.github_oauth.clone())
+            .app_data(self.fingerprint_service.clone())
+            .app_data(self.subscription_service.clone())
+            .app_data(self.share_service.clone())
+            .app_data(self.media_service.clone())
+            .app_data(self.sticker_service.clone())
+            .app_data(self.nearby_service.clone())
+            .app_data(self.insights_service.clone())
+            .app_data(self.export_service.clone());
+        #[cfg(feature = "graphql")]
+        cfg.app_data(self.graphql_schema.clone());
+    }
+}
+
+// Needs the Docker-backed harness: cargo test --features test-utils
+#[cfg(all(test, feature = "test-utils"))]
+mod tests {
+    use crate::middleware::auth::create_token_with_session;
+    use crate::test_utils::TestApp;
+    use crate::user::model::Role;
+    use crate::utils::i18n::Locale;
+    use actix_web::http::Method;
+    use actix_web::test;
+    use mongodb::bson::oid::ObjectId;
+    use regex::Regex;
+    use serde_json::json;
+
+    /// What actix answers when a handler's `web::Data` isn't registered
+    const MISSING_DATA: &str = "application data is not configured";
+    /// What the app answers when no route matches
+    const NO_ROUTE: &str = "Route does not exist";
+
+    /// Every `(method, path)` the app serves, relative to `/api/v1`. Add new
+    /// routes here; `every_route_resolves_its_data` sends each one through
+    /// `build_app`.
+    const ROUTES: &[(&str, &str)] = &[
+        // router/index.rs
+        ("GET", "/error-codes"),
+        ("GET", "/openapi.json"),
+        // admin/index.rs
+        ("GET", "/admin/email-outbox"),
+        ("GET", "/admin/email-outbox/{id}"),
+        ("POST", "/admin/email-outbox/{id}/retry"),
+        // analytics/index.rs
+        ("POST", "/events"),
+        ("GET", "/admin/analytics/summary"),
+        ("GET", "/admin/analytics/daily"),
+        // api_key/index.rs
+        ("POST", "/admin/api-keys"),
+        ("GET", "/admin/api-keys"),
+        ("DELETE", "/admin/api-keys/{id}"),
+        ("POST", "/internal/jobs/email-outbox"),
+        // badge/index.rs
+        ("GET", "/badges"),
+        // chat/index.rs
+        ("GET", "/ws/chat"),
+        ("GET", "/ws/chat/token"),
+        ("GET", "/chat/rooms/public"),
+        ("GET", "/chat/rooms/summary"),
+        ("PATCH", "/chat/rooms/{id}"),
+        ("POST", "/chat/rooms/{id}/join"),
+        ("POST", "/chat/rooms/{id}/invite-link"),
+        ("POST", "/chat/join/{invite_code}"),
+        ("PUT", "/chat/rooms/{id}/read-only"),
+        ("PUT", "/chat/rooms/{id}/slow-mode"),
+        ("POST", "/chat/rooms/{id}/messages"),
+        ("GET", "/chat/rooms/{id}/messages"),
+        ("DELETE", "/chat/rooms/{id}/messages"),
+        ("DELETE", "/chat/rooms/{id}/users/{user_id}/messages"),
+        ("POST", "/chat/rooms/{id}/stickers"),
+        ("POST", "/chat/rooms/{id}/locations"),
+        ("PUT", "/chat/messages/{id}/location"),
+        ("DELETE", "/chat/messages/{id}/location"),
+        ("GET", "/chat/rooms/{id}/media"),
+        // comment/index.rs
+        ("POST", "/comments"),
+        ("GET", "/comments/post/{post_id}"),
+        ("GET", "/comments/post/{post_id}/tree"),
+        ("GET", "/comments/count/{post_id}"),
+        ("GET", "/comments/drafts/{post_id}"),
+        ("PUT", "/comments/drafts/{post_id}"),
+        ("DELETE", "/comments/drafts/{post_id}"),
+        ("GET", "/comments/{comment_id}"),
+        ("PUT", "/comments/{comment_id}"),
+        ("DELETE", "/comments/{comment_id}"),
+        // export/index.rs
+        ("GET", "/admin/exports"),
+        ("GET", "/admin/exports/schedules"),
+        ("POST", "/admin/exports/schedules"),
+        ("DELETE", "/admin/exports/schedules/{id}"),
+        ("POST", "/admin/exports/schedules/{id}/run"),
+        ("GET", "/exports/{id}/download"),
+        // feature_flag/index.rs
+        ("GET", "/feature-flags"),
+        ("GET", "/admin/feature-flags"),
+        ("POST", "/admin/feature-flags"),
+        ("GET", "/admin/feature-flags/{key}"),
+        ("PATCH", "/admin/feature-flags/{key}"),
+        ("DELETE", "/admin/feature-flags/{key}"),
+        // fingerprint/index.rs
+        ("GET", "/admin/fingerprints/clusters"),
+        ("GET", "/admin/fingerprints/users/{user_id}"),
+        // friend/index.rs
+        ("GET", "/friends"),
+        ("GET", "/friends/privacy"),
+        ("PATCH", "/friends/privacy"),
+        ("GET", "/friends/blocks"),
+        ("POST", "/friends/blocks/{user_id}"),
+        ("DELETE", "/friends/blocks/{user_id}"),
+        ("POST", "/friends/requests"),
+        ("GET", "/friends/requests/incoming"),
+        ("GET", "/friends/requests/outgoing"),
+        ("POST", "/friends/requests/{id}/accept"),
+        ("POST", "/friends/requests/{id}/decline"),
+        ("DELETE", "/friends/requests/{id}"),
+        ("DELETE", "/friends/{user_id}"),
+        // group/index.rs
+        ("POST", "/groups"),
+        ("GET", "/groups"),
+        ("GET", "/groups/mine"),
+        ("GET", "/groups/{id}"),
+        ("POST", "/groups/{id}/join"),
+        ("POST", "/groups/{id}/leave"),
+        ("GET", "/groups/{id}/members"),
+        ("POST", "/groups/{id}/invites"),
+        ("POST", "/groups/{id}/members/{user_id}/approve"),
+        ("PATCH", "/groups/{id}/members/{user_id}"),
+        ("DELETE", "/groups/{id}/members/{user_id}"),
+        ("POST", "/groups/{id}/posts"),
+        ("GET", "/groups/{id}/posts"),
+        // leaderboard/index.rs
+        ("GET", "/leaderboards/{name}"),
+        // link_safety/index.rs
+        ("GET", "/l/{slug}"),
+        // moderation/index.rs
+        ("POST", "/reports"),
+        ("GET", "/moderation/reports"),
+        ("GET", "/moderation/reports/{id}"),
+        ("POST", "/moderation/reports/{id}/actions"),
+        ("GET", "/moderation/users/{id}/strikes"),
+        ("PUT", "/moderation/users/{id}/shadow-ban"),
+        ("DELETE", "/moderation/users/{id}/shadow-ban"),
+        ("PUT", "/moderation/posts/{id}/sensitive"),
+        ("DELETE", "/moderation/posts/{id}/sensitive"),
+        // notification/index.rs
+        ("GET", "/notifications"),
+        ("GET", "/notifications/unread-count"),
+        ("POST", "/notifications/read-all"),
+        ("GET", "/notifications/settings"),
+        ("PATCH", "/notifications/settings"),
+        // oauth/index.rs
+        ("GET", "/auth/oauth/github"),
+        ("GET", "/auth/oauth/github/callback"),
+        // post/post_index.rs
+        ("POST", "/posts"),
+        ("GET", "/posts/{id}"),
+        ("GET", "/posts/{id}/full"),
+        ("POST", "/posts/{id}/subscribe"),
+        ("DELETE", "/posts/{id}/subscribe"),
+        ("POST", "/posts/{id}/share"),
+        ("PUT", "/posts/{id}/pin"),
+        ("DELETE", "/posts/{id}/pin"),
+        ("PUT", "/posts/{id}"),
+        ("DELETE", "/posts/{id}"),
+        // share/index.rs
+        ("GET", "/p/{slug}"),
+        // spam_guard/index.rs
+        ("GET", "/admin/spam-guard/overrides"),
+        ("POST", "/admin/spam-guard/overrides"),
+        ("DELETE", "/admin/spam-guard/overrides/{user_id}"),
+        // sticker/index.rs
+        ("GET", "/stickers/packs"),
+        ("GET", "/admin/stickers/packs"),
+        ("POST", "/admin/stickers/packs"),
+        ("DELETE", "/admin/stickers/packs/{id}"),
+        ("POST", "/admin/stickers/packs/{id}/stickers"),
+        ("DELETE", "/admin/stickers/packs/{id}/stickers/{sticker_id}"),
+        // topic/index.rs
+        ("GET", "/topics"),
+        ("GET", "/topics/discover"),
+        ("GET", "/topics/{slug}/posts"),
+        // uploader/index.rs
+        ("POST", "/upload/single"),
+        ("POST", "/upload/multiple"),
+        // user/index.rs
+        ("POST", "/auth/user/tokens"),
+        ("GET", "/auth/user/tokens"),
+        ("DELETE", "/auth/user/tokens/{id}"),
+        ("POST", "/auth/user/register"),
+        ("POST", "/auth/user/verify-email"),
+        ("POST", "/auth/user/resend-otp"),
+        ("POST", "/auth/user/send-phone-otp"),
+        ("POST", "/auth/user/verify-phone"),
+        ("POST", "/auth/user/login"),
+        ("POST", "/auth/user/refresh"),
+        ("POST", "/auth/user/logout"),
+        ("POST", "/auth/user/forgot-password"),
+        ("POST", "/auth/user/reset-password"),
+        ("PUT", "/auth/user/change-password"),
+        ("DELETE", "/auth/user/me"),
+        ("GET", "/users/me"),
+        ("PATCH", "/users/me"),
+        ("GET", "/users/me/activity"),
+        ("GET", "/users/me/interests"),
+        ("PUT", "/users/me/interests"),
+        ("GET", "/users/me/qr"),
+        ("GET", "/users/me/insights"),
+        ("PUT", "/users/me/location"),
+        ("DELETE", "/users/me/location"),
+        ("GET", "/users/suggestions"),
+        ("POST", "/users/find-by-contacts"),
+        ("GET", "/users/nearby"),
+        ("GET", "/users/by-username/{username}"),
+        ("GET", "/users/by-qr/{token}"),
+        ("GET", "/users/{user_id}/badges"),
+        // verification/index.rs
+        ("POST", "/verification/requests"),
+        ("GET", "/verification/requests/me"),
+        ("GET", "/admin/verification"),
+        ("POST", "/admin/verification/{id}/review"),
+        ("DELETE", "/admin/verification/users/{user_id}"),
+    ];
+
+    /// Routes only served with the `graphql` feature
+    #[cfg(feature = "graphql")]
+    const GRAPHQL_ROUTES: &[(&str, &str)] = &[("GET", "/graphql/explorer"), ("POST", "/graphql")];
+
+    fn routes() -> Vec<(Method, &'static str)> {
+        let routes = ROUTES.iter();
+        #[cfg(feature = "graphql")]
+        let routes = routes.chain(GRAPHQL_ROUTES);
+        routes
+            .map(|&(method, path)| {
+                let method = Method::from_bytes(method.as_bytes()).expect("Invalid method");
+                (method, path)
+            })
+            .collect()
+    }
+
+    /// Sends every route its own method as an admin. Bodies are empty JSON
+    /// objects, so most requests fail validation; what matters is that none
+    /// fails for want of app data. An extractor that rejects the request
+    /// before the `web::Data` ones run can still hide a missing service.
+    #[actix_web::test]
+    async fn every_route_resolves_its_data() {
+        let app = TestApp::spawn().await;
+        let admin = app.create_user_with_role("root", Role::Admin).await;
+        let params = Regex::new(r"\{[^}]+\}").expect("Invalid parameter pattern");
+
+        for (method, path) in routes() {
+            // Logging out ends the session, so every request gets its own
+            let token = create_token_with_session(
+                &admin.id.to_hex(),
+                Role::Admin,
+                Locale::default(),
+                &app.state.config.jwt_secret,
+                &app.state.redis_service,
+            )
+            .await
+            .expect("Failed to create token");
+            let uri = params.replace_all(path, ObjectId::new().to_hex().as_str());
+            let mut req = test::TestRequest::default()
+                .method(method.clone())
+                .uri(&format!("/api/v1{}", uri))
+                .insert_header(("Authorization", format!("Bearer {}", token)));
+            if method != Method::GET && method != Method::DELETE {
+                req = req.set_json(json!({}));
+            }
+
+            let (status, body) = app.call(req).await;
+            let message = body["message"].as_str().unwrap_or_default();
+            assert_ne!(message, NO_ROUTE, "{} {} is not routed", method, path);
+            assert!(
+                !message.contains(MISSING_DATA),
+                "{} {} is missing app data ({}): {}",
+                method,
+                path,
+                status,
+                body
+            );
+        }
+    }
+}
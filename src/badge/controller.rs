@@ -0,0 +1,43 @@
+use crate::badge::model::{Badge, BadgeDefinition};
+use crate::badge::service::BadgeService;
+use crate::utils::error::{CustomError, ErrorCode};
+use crate::utils::i18n::Locale;
+use crate::utils::response::ApiResponse;
+use actix_web::{HttpResponse, web};
+use mongodb::bson::oid::ObjectId;
+
+/// Every badge that can be earned
+/// GET /badges
+pub async fn list_badges(locale: Locale) -> Result<HttpResponse, CustomError> {
+    let badges: Vec<BadgeDefinition> = Badge::ALL
+        .iter()
+        .map(|badge| BadgeDefinition {
+            badge: *badge,
+            description: locale.t(&badge.message_key()),
+        })
+        .collect();
+
+    Ok(ApiResponse::ok(locale.t("badges-fetched"))
+        .list(badges)
+        .into())
+}
+
+/// Badges a user has earned
+/// GET /users/{user_id}/badges
+pub async fn get_user_badges(
+    locale: Locale,
+    badge_service: web::Data<BadgeService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    let user_id = ObjectId::parse_str(path.into_inner())
+        .map_err(|_| CustomError::BadRequestError("Invalid user ID".to_string()))?;
+
+    let badges = badge_service
+        .earned(&user_id)
+        .await?
+        .ok_or_else(|| CustomError::coded(ErrorCode::UserNotFound, "User not found"))?;
+
+    Ok(ApiResponse::ok(locale.t("badges-fetched"))
+        .list(badges)
+        .into())
+}
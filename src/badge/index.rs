@@ -0,0 +1,14 @@
+use super::controller::list_badges;
+use crate::middleware::auth::verify_token;
+use crate::middleware::limits::RequestTimeout;
+use actix_web::web;
+use actix_web_httpauth::middleware::HttpAuthentication;
+
+pub fn badge_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/badges")
+            .wrap(RequestTimeout::standard())
+            .wrap(HttpAuthentication::bearer(verify_token))
+            .route("", web::get().to(list_badges)),
+    );
+}
@@ -0,0 +1,49 @@
+use crate::utils::datetime::bson_datetime;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Achievements a user can earn
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Badge {
+    FirstPost,
+    VerifiedEmail,
+    OneYearMember,
+}
+
+impl Badge {
+    pub const ALL: [Badge; 3] = [
+        Badge::FirstPost,
+        Badge::VerifiedEmail,
+        Badge::OneYearMember,
+    ];
+
+    /// Name stored on the user and used in the API
+    pub fn name(&self) -> &'static str {
+        match self {
+            Badge::FirstPost => "first-post",
+            Badge::VerifiedEmail => "verified-email",
+            Badge::OneYearMember => "one-year-member",
+        }
+    }
+
+    /// Translation key of the badge's description
+    pub fn message_key(&self) -> String {
+        format!("badge-{}", self.name())
+    }
+}
+
+/// A badge stored on the user document
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EarnedBadge {
+    pub badge: Badge,
+    #[serde(with = "bson_datetime")]
+    pub awarded_at: DateTime<Utc>,
+}
+
+/// Catalogue entry shown to clients, including badges not yet earned
+#[derive(Debug, Serialize)]
+pub struct BadgeDefinition {
+    pub badge: Badge,
+    pub description: String,
+}
@@ -0,0 +1,119 @@
+use crate::badge::model::{Badge, EarnedBadge};
+use crate::user::model::User;
+use crate::utils::datetime::bson_now;
+use crate::utils::error::CustomError;
+use chrono::{Duration, Utc};
+use mongodb::bson::{self, Document, doc, oid::ObjectId};
+use mongodb::{Client, Collection};
+use serde::Deserialize;
+use std::future::Future;
+
+/// Only the badges of a user document
+#[derive(Debug, Deserialize)]
+struct UserBadges {
+    #[serde(default)]
+    badges: Vec<EarnedBadge>,
+}
+
+/// The badge events handlers report, so handler tests can ignore them.
+/// `BadgeService` implements it by delegating to its inherent methods.
+pub trait BadgeServiceTrait: Send + Sync + 'static {
+    fn on_post_created(&self, author_id: &ObjectId) -> impl Future<Output = ()> + Send;
+}
+
+pub struct BadgeService {
+    users: Collection<User>,
+}
+
+impl BadgeService {
+    pub fn new(client: &Client) -> Self {
+        let db = client.database("rust_blogdb");
+        BadgeService {
+            users: db.collection::<User>("users"),
+        }
+    }
+
+    /// Users matching `filter` that don't hold the badge yet
+    fn without(badge: Badge, mut filter: Document) -> Document {
+        filter.insert("badges.badge", doc! { "$ne": badge.name() });
+        filter
+    }
+
+    fn push(badge: Badge) -> Document {
+        doc! { "$push": { "badges": { "badge": badge.name(), "awarded_at": bson_now() } } }
+    }
+
+    /// Award a badge once. Badges are a bonus, so failures are only logged
+    /// and never fail the request that triggered them.
+    #[tracing::instrument(skip_all, fields(badge = badge.name()))]
+    pub async fn award(&self, user_id: &ObjectId, badge: Badge) {
+        if let Err(e) = self
+            .users
+            .update_one(
+                Self::without(badge, doc! { "_id": user_id }),
+                Self::push(badge),
+            )
+            .await
+        {
+            log::warn!(
+                "Failed to award {} badge to {}: {}",
+                badge.name(),
+                user_id,
+                e
+            );
+        }
+    }
+
+    /// Hook for new posts, profile or group
+    pub async fn on_post_created(&self, author_id: &ObjectId) {
+        self.award(author_id, Badge::FirstPost).await;
+    }
+
+    /// Hook for a confirmed email address
+    pub async fn on_email_verified(&self, user_id: &ObjectId) {
+        self.award(user_id, Badge::VerifiedEmail).await;
+    }
+
+    /// Award the anniversary badge to every account older than a year;
+    /// returns how many users got it
+    #[tracing::instrument(skip_all)]
+    pub async fn award_anniversaries(&self) -> Result<u64, CustomError> {
+        let joined_before = bson::DateTime::from_chrono(Utc::now() - Duration::days(365));
+        let result = self
+            .users
+            .update_many(
+                Self::without(
+                    Badge::OneYearMember,
+                    doc! { "created_at": { "$lte": joined_before } },
+                ),
+                Self::push(Badge::OneYearMember),
+            )
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        Ok(result.modified_count)
+    }
+
+    /// Badges earned by a user, oldest first; `None` if the user doesn't exist
+    #[tracing::instrument(skip_all)]
+    pub async fn earned(
+        &self,
+        user_id: &ObjectId,
+    ) -> Result<Option<Vec<EarnedBadge>>, CustomError> {
+        let user = self
+            .users
+            .clone_with_type::<UserBadges>()
+            .find_one(doc! { "_id": user_id })
+            .projection(doc! { "badges": 1 })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        Ok(user.map(|user| user.badges))
+    }
+}
+
+impl BadgeServiceTrait for BadgeService {
+    async fn on_post_created(&self, author_id: &ObjectId) {
+        Self::on_post_created(self, author_id).await
+    }
+}
@@ -1,11 +1,72 @@
 use actix::Addr;
 use actix_web::{HttpRequest, HttpResponse, web};
 use actix_web_actors::ws;
+use chrono::{DateTime, Duration, Utc};
+use mongodb::bson::{self, Bson, doc, oid::ObjectId};
+use serde::Deserialize;
+use serde_json::json;
 
-use crate::chat::server::ChatServer;
+use crate::chat::model::{
+    ChatMessage, CreateInviteLinkRequest, InviteLink, MediaType, ReadOnlyMode, RoomSummary,
+    SendMessageRequest, SendStickerRequest, ServerMessage, SetReadOnlyRequest, SetSlowModeRequest,
+    ShareLocationRequest, UpdateLocationRequest, UpdateRoomRequest,
+};
+use crate::chat::server::{Broadcast, ChatServer, SetReadOnly, SetSlowMode};
+use crate::chat::service::{ChatService, notify_direct_recipient};
 use crate::chat::session::WsSession;
-use crate::middleware::auth::Claims;
+use crate::database::RedisService;
+use crate::group::model::GroupVisibility;
+use crate::group::service::GroupService;
+use crate::link_safety::service::LinkGuard;
+use crate::middleware::auth::{AuthUser, Claims};
+use crate::middleware::rate_limit::{
+    CHAT_RATE_LIMIT, CHAT_RATE_WINDOW_SECONDS, check_rate_limit, check_slow_mode,
+};
+use crate::notification::service::NotificationService;
+use crate::spam_guard::model::SpamAction;
+use crate::spam_guard::service::SpamGuard;
+use crate::uploader::service::MediaService;
+use crate::utils::config::AppConfig;
 use crate::utils::error::CustomError;
+use crate::utils::i18n::Locale;
+use crate::utils::response::ApiResponse;
+use crate::utils::sanitize::{Markup, sanitize, sanitize_required};
+use crate::utils::validation::ValidatedJson;
+
+/// How long invite links work when the request doesn't say
+const DEFAULT_INVITE_HOURS: u32 = 24;
+
+#[derive(Debug, Deserialize)]
+pub struct PublicRoomQuery {
+    pub q: Option<String>,
+    pub page: Option<u64>,
+    pub per_page: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConversationQuery {
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MediaQuery {
+    #[serde(rename = "type")]
+    pub media_type: Option<MediaType>,
+    pub page: Option<u64>,
+    pub per_page: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PurgeRangeQuery {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HistoryQuery {
+    pub before: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+}
 
 /// WebSocket connection handler
 /// GET /ws/chat
@@ -13,6 +74,11 @@ pub async fn ws_chat(
     req: HttpRequest,
     stream: web::Payload,
     server: web::Data<Addr<ChatServer>>,
+    redis_service: web::Data<RedisService>,
+    chat_service: web::Data<ChatService>,
+    spam_guard: web::Data<SpamGuard>,
+    link_guard: web::Data<LinkGuard>,
+    notifications: web::Data<NotificationService>,
 ) -> Result<HttpResponse, actix_web::Error> {
     // Get user_id from auth (JWT claims in request extensions)
     let user_id = req
@@ -24,7 +90,15 @@ pub async fn ws_chat(
     log::info!("WebSocket connection request from user: {}", user_id);
 
     // Create WebSocket session
-    let session = WsSession::new(user_id, server.get_ref().clone());
+    let session = WsSession::new(
+        user_id,
+        server.get_ref().clone(),
+        redis_service.get_ref().clone(),
+        chat_service,
+        spam_guard,
+        link_guard,
+        notifications,
+    );
 
     // Start WebSocket connection
     ws::start(session, &req, stream)
@@ -36,31 +110,766 @@ pub async fn ws_chat_with_token(
     req: HttpRequest,
     stream: web::Payload,
     server: web::Data<Addr<ChatServer>>,
+    redis_service: web::Data<RedisService>,
+    chat_service: web::Data<ChatService>,
+    spam_guard: web::Data<SpamGuard>,
+    link_guard: web::Data<LinkGuard>,
+    notifications: web::Data<NotificationService>,
+    config: web::Data<AppConfig>,
     query: web::Query<TokenQuery>,
 ) -> Result<HttpResponse, actix_web::Error> {
     // Validate JWT token from query parameter
-    let user_id = validate_token(&query.token).unwrap_or_else(|_| "anonymous".to_string());
+    let user_id = validate_token(&query.token, &config.jwt_secret)
+        .unwrap_or_else(|_| "anonymous".to_string());
 
     log::info!("WebSocket connection request from user: {}", user_id);
 
     // Create WebSocket session
-    let session = WsSession::new(user_id, server.get_ref().clone());
+    let session = WsSession::new(
+        user_id,
+        server.get_ref().clone(),
+        redis_service.get_ref().clone(),
+        chat_service,
+        spam_guard,
+        link_guard,
+        notifications,
+    );
 
     // Start WebSocket connection
     ws::start(session, &req, stream)
 }
 
+/// Send a message without a WebSocket; connected members get it live
+/// POST /chat/rooms/{id}/messages
+pub async fn send_message(
+    locale: Locale,
+    auth_user: AuthUser,
+    server: web::Data<Addr<ChatServer>>,
+    chat_service: web::Data<ChatService>,
+    redis_service: web::Data<RedisService>,
+    spam_guard: web::Data<SpamGuard>,
+    link_guard: web::Data<LinkGuard>,
+    notifications: web::Data<NotificationService>,
+    path: web::Path<String>,
+    body: ValidatedJson<SendMessageRequest>,
+) -> Result<HttpResponse, CustomError> {
+    let room_id = path.into_inner();
+    let sender_id = auth_user.id.to_hex();
+    ensure_can_post(&chat_service, &redis_service, &room_id, &sender_id).await?;
+
+    let content = sanitize_required(&body.content, Markup::None, "content")?;
+    spam_guard
+        .check(&auth_user.id, SpamAction::ChatMessage, &content)
+        .await?;
+    let content = link_guard.screen(&content).await?;
+    let message = chat_service
+        .save_message(&room_id, &sender_id, content)
+        .await?;
+
+    server.do_send(Broadcast {
+        room_id,
+        visible_to: message.shadow_banned.then(|| sender_id.clone()),
+        message: ServerMessage::from(&message),
+    });
+    notify_direct_recipient(&server, &notifications, &message).await;
+
+    Ok(ApiResponse::created(locale.t("chat-message-sent"))
+        .data(message)
+        .into())
+}
+
+/// Send a sticker from one of the packs
+/// POST /chat/rooms/{id}/stickers
+pub async fn send_sticker(
+    locale: Locale,
+    auth_user: AuthUser,
+    server: web::Data<Addr<ChatServer>>,
+    chat_service: web::Data<ChatService>,
+    redis_service: web::Data<RedisService>,
+    notifications: web::Data<NotificationService>,
+    path: web::Path<String>,
+    body: ValidatedJson<SendStickerRequest>,
+) -> Result<HttpResponse, CustomError> {
+    let room_id = path.into_inner();
+    let sender_id = auth_user.id.to_hex();
+    ensure_can_post(&chat_service, &redis_service, &room_id, &sender_id).await?;
+
+    let message = chat_service
+        .save_sticker(&room_id, &sender_id, &body.sticker_id)
+        .await?;
+
+    server.do_send(Broadcast {
+        room_id,
+        visible_to: message.shadow_banned.then(|| sender_id.clone()),
+        message: ServerMessage::from(&message),
+    });
+    notify_direct_recipient(&server, &notifications, &message).await;
+
+    Ok(ApiResponse::created(locale.t("chat-message-sent"))
+        .data(message)
+        .into())
+}
+
+/// Room history, newest first
+/// GET /chat/rooms/{id}/messages?before=<rfc3339>&limit=50
+pub async fn list_messages(
+    locale: Locale,
+    auth_user: AuthUser,
+    chat_service: web::Data<ChatService>,
+    path: web::Path<String>,
+    query: web::Query<HistoryQuery>,
+) -> Result<HttpResponse, CustomError> {
+    let room_id = path.into_inner();
+    ensure_room_access(&chat_service, &room_id, &auth_user.id.to_hex()).await?;
+
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+    let messages = chat_service
+        .list_messages(&room_id, &auth_user.id, query.before, limit)
+        .await?;
+
+    Ok(ApiResponse::ok(locale.t("chat-messages-fetched"))
+        .list(messages)
+        .into())
+}
+
+/// Delete everything a user sent in a room, for spam cleanup. Moderators only.
+/// DELETE /chat/rooms/{id}/users/{user_id}/messages
+pub async fn purge_user_messages(
+    locale: Locale,
+    auth_user: AuthUser,
+    server: web::Data<Addr<ChatServer>>,
+    chat_service: web::Data<ChatService>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_moderator()?;
+    let (room_id, user_id) = path.into_inner();
+
+    let deleted = chat_service.purge_sender(&room_id, &user_id).await?;
+    log::info!(
+        "Moderator {} purged {} message(s) from user {} in room {}",
+        auth_user.id,
+        deleted.len(),
+        user_id,
+        room_id
+    );
+    announce_deleted(&server, room_id, &deleted);
+
+    Ok(ApiResponse::ok(locale.t("chat-messages-deleted"))
+        .data(json!({ "deleted": deleted.len() }))
+        .into())
+}
+
+/// Delete the messages sent in a room within a time range. Moderators only.
+/// DELETE /chat/rooms/{id}/messages?from=<rfc3339>&to=<rfc3339>
+pub async fn purge_messages_in_range(
+    locale: Locale,
+    auth_user: AuthUser,
+    server: web::Data<Addr<ChatServer>>,
+    chat_service: web::Data<ChatService>,
+    path: web::Path<String>,
+    query: web::Query<PurgeRangeQuery>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_moderator()?;
+    if query.from >= query.to {
+        return Err(CustomError::BadRequestError(
+            "from must be before to".to_string(),
+        ));
+    }
+    let room_id = path.into_inner();
+
+    let deleted = chat_service
+        .purge_range(&room_id, query.from, query.to)
+        .await?;
+    log::info!(
+        "Moderator {} purged {} message(s) in room {} from {} to {}",
+        auth_user.id,
+        deleted.len(),
+        room_id,
+        query.from,
+        query.to
+    );
+    announce_deleted(&server, room_id, &deleted);
+
+    Ok(ApiResponse::ok(locale.t("chat-messages-deleted"))
+        .data(json!({ "deleted": deleted.len() }))
+        .into())
+}
+
+/// Tell connected members which messages to take down
+fn announce_deleted(server: &Addr<ChatServer>, room_id: String, deleted: &[ObjectId]) {
+    if deleted.is_empty() {
+        return;
+    }
+    server.do_send(Broadcast {
+        message: ServerMessage::MessagesDeleted {
+            room_id: room_id.clone(),
+            message_ids: deleted.iter().map(ObjectId::to_hex).collect(),
+        },
+        room_id,
+        visible_to: None,
+    });
+}
+
+/// Images, videos and files shared in a room, newest first
+/// GET /chat/rooms/{id}/media?type=image|video|file&page=1&per_page=30
+pub async fn list_media(
+    locale: Locale,
+    auth_user: AuthUser,
+    chat_service: web::Data<ChatService>,
+    path: web::Path<String>,
+    query: web::Query<MediaQuery>,
+) -> Result<HttpResponse, CustomError> {
+    let room_id = path.into_inner();
+    ensure_room_access(&chat_service, &room_id, &auth_user.id.to_hex()).await?;
+
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query.per_page.unwrap_or(30).clamp(1, 100);
+    let media = chat_service
+        .list_media(&room_id, &auth_user.id, query.media_type, page, per_page)
+        .await?;
+
+    Ok(ApiResponse::ok(locale.t("chat-media-fetched"))
+        .data(media)
+        .into())
+}
+
+/// The caller's conversations, most recently active first, with whether
+/// each direct-chat counterpart is online and typing
+/// GET /chat/rooms/summary?limit=50
+pub async fn list_conversations(
+    locale: Locale,
+    auth_user: AuthUser,
+    chat_service: web::Data<ChatService>,
+    redis_service: web::Data<RedisService>,
+    query: web::Query<ConversationQuery>,
+) -> Result<HttpResponse, CustomError> {
+    let limit = query.limit.unwrap_or(50).clamp(1, 100);
+    let mut conversations = chat_service.conversations(&auth_user.id, limit).await?;
+
+    let pairs: Vec<(String, String)> = conversations
+        .iter()
+        .filter_map(|conversation| {
+            let counterpart = conversation.counterpart.as_ref()?;
+            Some((conversation.room_id.clone(), counterpart.user_id.clone()))
+        })
+        .collect();
+    // Presence is a nice-to-have, so the list is still served without it
+    match redis_service.presence_lookup(&pairs).await {
+        Ok(presence) => {
+            let counterparts = conversations
+                .iter_mut()
+                .filter_map(|conversation| conversation.counterpart.as_mut());
+            for (counterpart, (online, is_typing)) in counterparts.zip(presence) {
+                counterpart.online = online;
+                counterpart.is_typing = is_typing;
+            }
+        }
+        Err(e) => log::warn!("Chat presence unavailable: {}", e),
+    }
+
+    Ok(ApiResponse::ok(locale.t("chat-rooms-fetched"))
+        .list(conversations)
+        .into())
+}
+
+/// Public rooms to discover, most recently active first
+/// GET /chat/rooms/public?q=&page=1&per_page=20
+pub async fn list_public_rooms(
+    locale: Locale,
+    auth_user: AuthUser,
+    chat_service: web::Data<ChatService>,
+    query: web::Query<PublicRoomQuery>,
+) -> Result<HttpResponse, CustomError> {
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query.per_page.unwrap_or(20).clamp(1, 100);
+    let rooms = chat_service
+        .public_rooms(query.q.as_deref(), &auth_user.id, page, per_page)
+        .await?;
+
+    Ok(ApiResponse::ok(locale.t("chat-rooms-fetched"))
+        .data(rooms)
+        .into())
+}
+
+/// Join a public room; members connected to it are told
+/// POST /chat/rooms/{id}/join
+pub async fn join_room(
+    locale: Locale,
+    auth_user: AuthUser,
+    server: web::Data<Addr<ChatServer>>,
+    chat_service: web::Data<ChatService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    let room_id = path.into_inner();
+    let user_id = auth_user.id.to_hex();
+    let (room, joined) = chat_service
+        .join_public_room(&room_id, &auth_user.id)
+        .await?;
+
+    if joined {
+        server.do_send(Broadcast {
+            room_id: room_id.clone(),
+            message: ServerMessage::UserJoined {
+                room_id,
+                user_id: user_id.clone(),
+            },
+            visible_to: None,
+        });
+    }
+
+    let mut room = RoomSummary::new(room, &user_id);
+    if joined {
+        room.member_count += 1;
+        room.is_member = true;
+    }
+
+    Ok(ApiResponse::ok(locale.t("chat-room-joined"))
+        .data(room)
+        .into())
+}
+
+/// Invite link to a private group's room, usable once or until it expires.
+/// Group admins and the owner only.
+/// POST /chat/rooms/{id}/invite-link
+pub async fn create_invite_link(
+    locale: Locale,
+    auth_user: AuthUser,
+    config: web::Data<AppConfig>,
+    chat_service: web::Data<ChatService>,
+    group_service: web::Data<GroupService>,
+    path: web::Path<String>,
+    body: ValidatedJson<CreateInviteLinkRequest>,
+) -> Result<HttpResponse, CustomError> {
+    let room_id = path.into_inner();
+    let group = group_service
+        .group_for_room(&room_id)
+        .await?
+        .ok_or_else(|| {
+            CustomError::NotFoundError("Only group rooms have invite links".to_string())
+        })?;
+    if group.visibility == GroupVisibility::Public {
+        return Err(CustomError::BadRequestError(
+            "Anyone can join a public group's room".to_string(),
+        ));
+    }
+    let can_manage = group_service
+        .membership(&group.id, &auth_user.id)
+        .await?
+        .is_some_and(|member| member.can_manage());
+    if !can_manage && !auth_user.is_admin() {
+        return Err(CustomError::ForbiddenError(
+            "Only group admins can create invite links".to_string(),
+        ));
+    }
+
+    let hours = body.expires_in_hours.unwrap_or(DEFAULT_INVITE_HOURS);
+    let invite = chat_service
+        .create_invite(
+            &room_id,
+            &auth_user.id.to_hex(),
+            body.single_use,
+            Utc::now() + Duration::hours(hours.into()),
+        )
+        .await?;
+
+    let link = InviteLink {
+        url: config
+            .share_link_base_url
+            .as_ref()
+            .map(|base_url| format!("{}/chat/join/{}", base_url, invite.code)),
+        code: invite.code,
+        room_id: invite.room_id,
+        single_use: invite.single_use,
+        expires_at: invite.expires_at,
+    };
+
+    Ok(ApiResponse::created(locale.t("chat-invite-created"))
+        .data(link)
+        .into())
+}
+
+/// Join a private group's room with an invite code; the user becomes a
+/// member of the group
+/// POST /chat/join/{invite_code}
+pub async fn join_with_invite(
+    locale: Locale,
+    auth_user: AuthUser,
+    server: web::Data<Addr<ChatServer>>,
+    chat_service: web::Data<ChatService>,
+    group_service: web::Data<GroupService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    let code = path.into_inner();
+    let invite = chat_service.find_invite(&code).await?;
+    let group = group_service
+        .group_for_room(&invite.room_id)
+        .await?
+        .ok_or_else(|| CustomError::NotFoundError("Room not found".to_string()))?;
+
+    // Members don't use up a single-use link
+    if group_service
+        .membership(&group.id, &auth_user.id)
+        .await?
+        .is_some_and(|member| member.is_active())
+    {
+        return Err(CustomError::ConflictError(
+            "You are already a member of this group".to_string(),
+        ));
+    }
+    chat_service.redeem_invite(&code).await?;
+    group_service.join_by_invite(&group, auth_user.id).await?;
+
+    let user_id = auth_user.id.to_hex();
+    server.do_send(Broadcast {
+        room_id: invite.room_id.clone(),
+        message: ServerMessage::UserJoined {
+            room_id: invite.room_id.clone(),
+            user_id: user_id.clone(),
+        },
+        visible_to: None,
+    });
+
+    let room = chat_service
+        .get_room(&invite.room_id)
+        .await?
+        .ok_or_else(|| CustomError::NotFoundError("Room not found".to_string()))?;
+
+    Ok(ApiResponse::ok(locale.t("chat-room-joined"))
+        .data(RoomSummary::new(room, &user_id))
+        .into())
+}
+
+/// Rename a room or change its description and avatar; room owners and
+/// admins only
+/// PATCH /chat/rooms/{id}
+pub async fn update_room(
+    locale: Locale,
+    auth_user: AuthUser,
+    chat_service: web::Data<ChatService>,
+    media_service: web::Data<MediaService>,
+    path: web::Path<String>,
+    body: ValidatedJson<UpdateRoomRequest>,
+) -> Result<HttpResponse, CustomError> {
+    let room_id = path.into_inner();
+    let user_id = auth_user.id.to_hex();
+    let room = chat_service
+        .get_room(&room_id)
+        .await?
+        .ok_or_else(|| CustomError::NotFoundError("Room not found".to_string()))?;
+    if room.created_by != user_id && !auth_user.is_admin() {
+        return Err(CustomError::ForbiddenError(
+            "Only the room owner can edit it".to_string(),
+        ));
+    }
+
+    let body = body.into_inner();
+    let mut changes = doc! {};
+    if let Some(name) = body.name {
+        changes.insert("name", sanitize_required(&name, Markup::None, "name")?);
+    }
+    if let Some(description) = body.description {
+        let description = sanitize(&description, Markup::None);
+        let description = match description.trim() {
+            "" => Bson::Null,
+            description => Bson::String(description.to_string()),
+        };
+        changes.insert("description", description);
+    }
+    if let Some(public_id) = body.avatar_public_id {
+        let avatar = media_service
+            .owned_by(&auth_user.id, &[public_id])
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| CustomError::BadRequestError("Avatar upload not found".to_string()))?;
+        changes.insert("avatar_url", avatar.secure_url);
+    }
+
+    let room = chat_service.update_room(&room_id, changes).await?;
+
+    Ok(ApiResponse::ok(locale.t("chat-room-updated"))
+        .data(RoomSummary::new(room, &user_id))
+        .into())
+}
+
+/// Turn read-only mode on or off; while it's on only the owner and whoever
+/// turned it on can post. Room owners and admins only.
+/// PUT /chat/rooms/{id}/read-only
+pub async fn set_read_only(
+    locale: Locale,
+    auth_user: AuthUser,
+    server: web::Data<Addr<ChatServer>>,
+    chat_service: web::Data<ChatService>,
+    path: web::Path<String>,
+    body: ValidatedJson<SetReadOnlyRequest>,
+) -> Result<HttpResponse, CustomError> {
+    let room_id = path.into_inner();
+    let user_id = auth_user.id.to_hex();
+    let room = chat_service
+        .get_room(&room_id)
+        .await?
+        .ok_or_else(|| CustomError::NotFoundError("Room not found".to_string()))?;
+    if room.created_by != user_id && !auth_user.is_admin() {
+        return Err(CustomError::ForbiddenError(
+            "Only the room owner can make it read-only".to_string(),
+        ));
+    }
+
+    let now = Utc::now();
+    let mode = body.enabled.then(|| ReadOnlyMode {
+        enabled_by: user_id.clone(),
+        until: body
+            .duration_minutes
+            .map(|minutes| now + Duration::minutes(minutes.into())),
+    });
+    let was_read_only = room
+        .read_only
+        .as_ref()
+        .is_some_and(|mode| mode.is_active(now));
+    let read_only = match &mode {
+        Some(mode) => {
+            bson::to_bson(mode).map_err(|e| CustomError::InternalServerError(e.to_string()))?
+        }
+        None => Bson::Null,
+    };
+    let room = chat_service
+        .update_room(&room_id, doc! { "read_only": read_only })
+        .await?;
+
+    server.do_send(SetReadOnly {
+        room_id: room_id.clone(),
+        owner_id: room.created_by.clone(),
+        mode: mode.clone(),
+    });
+
+    let notice = match &mode {
+        Some(ReadOnlyMode {
+            until: Some(until), ..
+        }) => Some(format!(
+            "Only the room owner can post until {}",
+            until.to_rfc3339()
+        )),
+        Some(_) => Some("Only the room owner can post in this room for now".to_string()),
+        None if was_read_only => Some("Everyone can post in this room again".to_string()),
+        None => None,
+    };
+    if let Some(notice) = notice {
+        announce(&server, &chat_service, &room_id, notice).await;
+    }
+
+    Ok(ApiResponse::ok(locale.t("chat-room-read-only-updated"))
+        .data(RoomSummary::new(room, &user_id))
+        .into())
+}
+
+/// Make members wait between messages, or stop doing so with 0 seconds.
+/// Room owners and admins only; the owner is never held back.
+/// PUT /chat/rooms/{id}/slow-mode
+pub async fn set_slow_mode(
+    locale: Locale,
+    auth_user: AuthUser,
+    server: web::Data<Addr<ChatServer>>,
+    chat_service: web::Data<ChatService>,
+    path: web::Path<String>,
+    body: ValidatedJson<SetSlowModeRequest>,
+) -> Result<HttpResponse, CustomError> {
+    let room_id = path.into_inner();
+    let user_id = auth_user.id.to_hex();
+    let room = chat_service
+        .get_room(&room_id)
+        .await?
+        .ok_or_else(|| CustomError::NotFoundError("Room not found".to_string()))?;
+    if room.created_by != user_id && !auth_user.is_admin() {
+        return Err(CustomError::ForbiddenError(
+            "Only the room owner can change slow mode".to_string(),
+        ));
+    }
+
+    let seconds = Some(body.seconds).filter(|seconds| *seconds > 0);
+    let previous = room.slow_mode_seconds;
+    let room = chat_service
+        .update_room(
+            &room_id,
+            doc! { "slow_mode_seconds": seconds.map_or(Bson::Null, |seconds| Bson::Int64(seconds.into())) },
+        )
+        .await?;
+
+    server.do_send(SetSlowMode {
+        room_id: room_id.clone(),
+        owner_id: room.created_by.clone(),
+        seconds,
+    });
+
+    if seconds != previous {
+        let notice = match seconds {
+            Some(seconds) => format!(
+                "Slow mode is on: members can send one message every {} seconds",
+                seconds
+            ),
+            None => "Slow mode is off".to_string(),
+        };
+        announce(&server, &chat_service, &room_id, notice).await;
+    }
+
+    Ok(ApiResponse::ok(locale.t("chat-room-slow-mode-updated"))
+        .data(RoomSummary::new(room, &user_id))
+        .into())
+}
+
+/// Store a system message about a change to the room and send it to the
+/// connected members. The change is already made, so failures are only logged.
+async fn announce(
+    server: &Addr<ChatServer>,
+    chat_service: &ChatService,
+    room_id: &str,
+    notice: String,
+) {
+    match chat_service.save_system_message(room_id, notice).await {
+        Ok(saved) => server.do_send(Broadcast {
+            room_id: room_id.to_string(),
+            message: ServerMessage::from(&saved),
+            visible_to: None,
+        }),
+        Err(e) => log::warn!("Failed to store room notice: {}", e),
+    }
+}
+
+/// Share a place, optionally live for a while
+/// POST /chat/rooms/{id}/locations
+pub async fn share_location(
+    locale: Locale,
+    auth_user: AuthUser,
+    server: web::Data<Addr<ChatServer>>,
+    chat_service: web::Data<ChatService>,
+    redis_service: web::Data<RedisService>,
+    notifications: web::Data<NotificationService>,
+    path: web::Path<String>,
+    body: ValidatedJson<ShareLocationRequest>,
+) -> Result<HttpResponse, CustomError> {
+    let room_id = path.into_inner();
+    let sender_id = auth_user.id.to_hex();
+    ensure_can_post(&chat_service, &redis_service, &room_id, &sender_id).await?;
+
+    let message = chat_service
+        .share_location(&room_id, &sender_id, body.into_inner())
+        .await?;
+
+    server.do_send(Broadcast {
+        room_id,
+        visible_to: message.shadow_banned.then(|| sender_id.clone()),
+        message: ServerMessage::from(&message),
+    });
+    notify_direct_recipient(&server, &notifications, &message).await;
+
+    Ok(ApiResponse::created(locale.t("chat-message-sent"))
+        .data(message)
+        .into())
+}
+
+/// Move a live location
+/// PUT /chat/messages/{id}/location
+pub async fn update_location(
+    locale: Locale,
+    auth_user: AuthUser,
+    server: web::Data<Addr<ChatServer>>,
+    chat_service: web::Data<ChatService>,
+    path: web::Path<String>,
+    body: ValidatedJson<UpdateLocationRequest>,
+) -> Result<HttpResponse, CustomError> {
+    let message = chat_service
+        .update_live_location(&path, &auth_user.id.to_hex(), body.lat, body.lng)
+        .await?;
+    announce_location(&server, &message);
+
+    Ok(ApiResponse::ok(locale.t("chat-location-updated"))
+        .data(message)
+        .into())
+}
+
+/// End a live location early
+/// DELETE /chat/messages/{id}/location
+pub async fn stop_location(
+    locale: Locale,
+    auth_user: AuthUser,
+    server: web::Data<Addr<ChatServer>>,
+    chat_service: web::Data<ChatService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    let message = chat_service
+        .stop_live_location(&path, &auth_user.id.to_hex())
+        .await?;
+    announce_location(&server, &message);
+
+    Ok(ApiResponse::ok(locale.t("chat-location-updated"))
+        .data(message)
+        .into())
+}
+
+/// Tell the room a live location moved or stopped
+fn announce_location(server: &Addr<ChatServer>, message: &ChatMessage) {
+    if let Some(update) = ServerMessage::location_updated(message) {
+        server.do_send(Broadcast {
+            room_id: message.room_id.clone(),
+            message: update,
+            visible_to: message.shadow_banned.then(|| message.sender_id.clone()),
+        });
+    }
+}
+
+/// Checks shared by the REST send paths: room access, read-only mode, and
+/// the same rate limit and slow mode as the WebSocket path
+async fn ensure_can_post(
+    chat_service: &ChatService,
+    redis_service: &RedisService,
+    room_id: &str,
+    sender_id: &str,
+) -> Result<(), CustomError> {
+    ensure_room_access(chat_service, room_id, sender_id).await?;
+    let room = chat_service.get_room(room_id).await?;
+    if let Some(room) = &room
+        && !room.can_post(sender_id, Utc::now())
+    {
+        return Err(CustomError::ForbiddenError(
+            "Only the room owner can post while the room is read-only".to_string(),
+        ));
+    }
+
+    check_rate_limit(
+        redis_service,
+        &format!("chat:{}", sender_id),
+        CHAT_RATE_LIMIT,
+        CHAT_RATE_WINDOW_SECONDS,
+    )
+    .await?;
+    if let Some(seconds) = room.and_then(|room| room.slow_mode_for(sender_id)) {
+        check_slow_mode(redis_service, room_id, sender_id, seconds).await?;
+    }
+    Ok(())
+}
+
+/// The REST endpoints apply the same room rules as joining over WebSocket
+async fn ensure_room_access(
+    chat_service: &ChatService,
+    room_id: &str,
+    user_id: &str,
+) -> Result<(), CustomError> {
+    if !chat_service.can_join(room_id, user_id).await? {
+        return Err(CustomError::ForbiddenError(
+            "You can't use this room".to_string(),
+        ));
+    }
+    Ok(())
+}
+
 #[derive(serde::Deserialize)]
 pub struct TokenQuery {
     pub token: String,
 }
 
 /// Validate JWT token and extract user_id
-fn validate_token(token: &str) -> Result<String, CustomError> {
+fn validate_token(token: &str, secret: &str) -> Result<String, CustomError> {
     use jsonwebtoken::{DecodingKey, Validation, decode};
 
-    let secret = std::env::var("JWT_SECRET").unwrap_or_else(|_| "secret".to_string());
-
     let token_data = decode::<Claims>(
         token,
         &DecodingKey::from_secret(secret.as_bytes()),
@@ -1,5 +1,13 @@
-use super::controller::{ws_chat, ws_chat_with_token};
+use super::controller::{
+    create_invite_link, join_room, join_with_invite, list_conversations, list_media, list_messages,
+    list_public_rooms, purge_messages_in_range, purge_user_messages, send_message, send_sticker,
+    set_read_only, set_slow_mode, share_location, stop_location, update_location, update_room,
+    ws_chat, ws_chat_with_token,
+};
+use crate::middleware::auth::verify_token;
+use crate::middleware::limits::RequestTimeout;
 use actix_web::web;
+use actix_web_httpauth::middleware::HttpAuthentication;
 
 pub fn chat_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
@@ -7,4 +15,35 @@ pub fn chat_routes(cfg: &mut web::ServiceConfig) {
             .route("/chat", web::get().to(ws_chat))
             .route("/chat/token", web::get().to(ws_chat_with_token)),
     );
+    cfg.service(
+        web::scope("/chat")
+            .wrap(RequestTimeout::standard())
+            .wrap(HttpAuthentication::bearer(verify_token))
+            .route("/rooms/public", web::get().to(list_public_rooms))
+            .route("/rooms/summary", web::get().to(list_conversations))
+            .route("/rooms/{id}", web::patch().to(update_room))
+            .route("/rooms/{id}/join", web::post().to(join_room))
+            .route(
+                "/rooms/{id}/invite-link",
+                web::post().to(create_invite_link),
+            )
+            .route("/join/{invite_code}", web::post().to(join_with_invite))
+            .route("/rooms/{id}/read-only", web::put().to(set_read_only))
+            .route("/rooms/{id}/slow-mode", web::put().to(set_slow_mode))
+            .route("/rooms/{id}/messages", web::post().to(send_message))
+            .route("/rooms/{id}/messages", web::get().to(list_messages))
+            .route(
+                "/rooms/{id}/messages",
+                web::delete().to(purge_messages_in_range),
+            )
+            .route(
+                "/rooms/{id}/users/{user_id}/messages",
+                web::delete().to(purge_user_messages),
+            )
+            .route("/rooms/{id}/stickers", web::post().to(send_sticker))
+            .route("/rooms/{id}/locations", web::post().to(share_location))
+            .route("/messages/{id}/location", web::put().to(update_location))
+            .route("/messages/{id}/location", web::delete().to(stop_location))
+            .route("/rooms/{id}/media", web::get().to(list_media)),
+    );
 }
@@ -2,4 +2,5 @@ pub mod controller;
 pub mod index;
 pub mod model;
 pub mod server;
+pub mod service;
 pub mod session;
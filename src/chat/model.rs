@@ -1,7 +1,16 @@
+use crate::sticker::model::Sticker;
+use crate::utils::datetime::{bson_datetime, option_bson_datetime};
+use crate::utils::error::CustomError;
+use crate::utils::validation::not_blank;
 use chrono::{DateTime, Utc};
 use mongodb::bson::oid::ObjectId;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use uuid::Uuid;
+use validator::Validate;
+
+/// Sender id of messages posted by the server itself
+pub const SYSTEM_SENDER_ID: &str = "system";
 
 /// Chat message stored in database
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -11,9 +20,41 @@ pub struct ChatMessage {
     pub room_id: String,
     pub sender_id: String,
     pub sender_username: Option<String>,
+    /// Whether the sender had the verified badge when sending
+    #[serde(default)]
+    pub sender_verified: bool,
     pub content: String,
     pub message_type: MessageType,
+    /// Where a forwarded message was first sent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forwarded_from: Option<ForwardedFrom>,
+    /// Question, options and votes of a poll message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub poll: Option<Poll>,
+    /// Copy of the sticker sent, kept even if its pack changes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sticker: Option<Sticker>,
+    /// Place shared by a location message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<SharedLocation>,
+    #[serde(with = "bson_datetime")]
     pub created_at: DateTime<Utc>,
+    /// Whether the sender is shadow banned, or blocked from the other side
+    /// of a direct room, so only they get the broadcast. Never sent. History
+    /// checks a shadow ban's current status; the block is stored with the
+    /// message, so lifting it doesn't reveal what it dropped.
+    #[serde(skip)]
+    pub shadow_banned: bool,
+}
+
+/// Attribution kept on a forwarded message. Forwarding a forward keeps
+/// pointing at the first message.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ForwardedFrom {
+    pub message_id: ObjectId,
+    pub room_id: String,
+    pub sender_id: String,
+    pub sender_username: Option<String>,
 }
 
 /// Type of message
@@ -22,8 +63,142 @@ pub struct ChatMessage {
 pub enum MessageType {
     Text,
     Image,
+    Video,
     File,
     System,
+    Poll,
+    Sticker,
+    Location,
+}
+
+impl MessageType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MessageType::Text => "text",
+            MessageType::Image => "image",
+            MessageType::Video => "video",
+            MessageType::File => "file",
+            MessageType::System => "system",
+            MessageType::Poll => "poll",
+            MessageType::Sticker => "sticker",
+            MessageType::Location => "location",
+        }
+    }
+}
+
+/// Kinds of message shown in a room's media gallery
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaType {
+    Image,
+    Video,
+    File,
+}
+
+impl MediaType {
+    pub const ALL: [MediaType; 3] = [MediaType::Image, MediaType::Video, MediaType::File];
+
+    pub fn message_type(&self) -> MessageType {
+        match self {
+            MediaType::Image => MessageType::Image,
+            MediaType::Video => MessageType::Video,
+            MediaType::File => MessageType::File,
+        }
+    }
+}
+
+/// Longest a live location can be shared for, in minutes
+pub const MAX_LIVE_LOCATION_MINUTES: u32 = 8 * 60;
+/// Longest location label, in characters
+pub const MAX_LOCATION_LABEL_CHARS: usize = 100;
+
+/// A place shared in a chat. Live locations follow the sender until
+/// `live_until`; after that the last position stays as sent.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SharedLocation {
+    pub lat: f64,
+    pub lng: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(default, with = "option_bson_datetime")]
+    pub live_until: Option<DateTime<Utc>>,
+    /// When a live location last moved
+    #[serde(default, with = "option_bson_datetime")]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl SharedLocation {
+    pub fn is_live(&self, now: DateTime<Utc>) -> bool {
+        self.live_until.is_some_and(|until| until > now)
+    }
+}
+
+/// Most options a poll can offer
+pub const MAX_POLL_OPTIONS: usize = 10;
+/// Longest poll question, in characters
+pub const MAX_POLL_QUESTION_CHARS: usize = 300;
+/// Longest poll option, in characters
+pub const MAX_POLL_OPTION_CHARS: usize = 100;
+
+/// A quick poll run in a chat room
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Poll {
+    pub question: String,
+    pub options: Vec<String>,
+    /// Whether voters may pick several options
+    #[serde(default)]
+    pub multiple_choice: bool,
+    /// Option indexes picked, by voter id
+    #[serde(default)]
+    pub votes: HashMap<String, Vec<u32>>,
+}
+
+impl Poll {
+    /// Vote counts per option, without who voted
+    pub fn results(&self) -> PollResults {
+        let mut counts = vec![0; self.options.len()];
+        for picked in self.votes.values() {
+            for &option in picked {
+                if let Some(count) = counts.get_mut(option as usize) {
+                    *count += 1;
+                }
+            }
+        }
+
+        PollResults {
+            question: self.question.clone(),
+            multiple_choice: self.multiple_choice,
+            options: self
+                .options
+                .iter()
+                .zip(counts)
+                .map(|(text, votes)| PollOptionResult {
+                    text: text.clone(),
+                    votes,
+                })
+                .collect(),
+            total_voters: self
+                .votes
+                .values()
+                .filter(|picked| !picked.is_empty())
+                .count(),
+        }
+    }
+}
+
+/// Live poll state sent to room members
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PollResults {
+    pub question: String,
+    pub multiple_choice: bool,
+    pub options: Vec<PollOptionResult>,
+    pub total_voters: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PollOptionResult {
+    pub text: String,
+    pub votes: usize,
 }
 
 /// Chat room
@@ -36,10 +211,117 @@ pub struct ChatRoom {
     pub room_type: RoomType,
     pub participants: Vec<String>, // user IDs
     pub created_by: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avatar_url: Option<String>,
+    /// When the newest message was sent, for ranking public rooms
+    #[serde(default, with = "option_bson_datetime")]
+    pub last_message_at: Option<DateTime<Utc>>,
+    /// Set while only the owner and whoever turned it on may post
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read_only: Option<ReadOnlyMode>,
+    /// Seconds each member must wait between messages, when slow mode is on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slow_mode_seconds: Option<u32>,
+    #[serde(with = "bson_datetime")]
     pub created_at: DateTime<Utc>,
+    #[serde(with = "bson_datetime")]
     pub updated_at: DateTime<Utc>,
 }
 
+impl ChatRoom {
+    /// Whether `user_id` may post in the room at `now`
+    pub fn can_post(&self, user_id: &str, now: DateTime<Utc>) -> bool {
+        self.read_only
+            .as_ref()
+            .is_none_or(|mode| mode.allows(user_id, &self.created_by, now))
+    }
+
+    /// The slow mode interval `user_id` is held to; the owner isn't
+    pub fn slow_mode_for(&self, user_id: &str) -> Option<u32> {
+        self.slow_mode_seconds
+            .filter(|_| user_id != self.created_by)
+    }
+}
+
+/// Announcement mode of a room, optionally ending on its own
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ReadOnlyMode {
+    pub enabled_by: String,
+    /// `None` keeps the room read-only until it is turned off
+    #[serde(default, with = "option_bson_datetime")]
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl ReadOnlyMode {
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.until.is_none_or(|until| now < until)
+    }
+
+    /// The owner and whoever enabled the mode can still post
+    pub fn allows(&self, user_id: &str, owner_id: &str, now: DateTime<Utc>) -> bool {
+        !self.is_active(now) || user_id == owner_id || user_id == self.enabled_by
+    }
+}
+
+/// A room as shown in room lists
+#[derive(Debug, Serialize)]
+pub struct RoomSummary {
+    pub room_id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub avatar_url: Option<String>,
+    pub member_count: usize,
+    /// Newest message, or the room's creation for rooms without any
+    pub last_activity_at: DateTime<Utc>,
+    pub is_member: bool,
+    pub is_read_only: bool,
+    /// When read-only mode ends by itself, if it does
+    pub read_only_until: Option<DateTime<Utc>>,
+    pub slow_mode_seconds: Option<u32>,
+}
+
+impl RoomSummary {
+    pub fn new(room: ChatRoom, viewer_id: &str) -> Self {
+        let read_only = room.read_only.filter(|mode| mode.is_active(Utc::now()));
+        RoomSummary {
+            is_read_only: read_only.is_some(),
+            read_only_until: read_only.and_then(|mode| mode.until),
+            slow_mode_seconds: room.slow_mode_seconds,
+            is_member: room.participants.iter().any(|id| id == viewer_id),
+            member_count: room.participants.len(),
+            last_activity_at: room.last_message_at.unwrap_or(room.created_at),
+            room_id: room.room_id,
+            name: room.name,
+            description: room.description,
+            avatar_url: room.avatar_url,
+        }
+    }
+}
+
+/// A room in the user's conversation list
+#[derive(Debug, Serialize)]
+pub struct ConversationSummary {
+    pub room_id: String,
+    pub name: String,
+    pub room_type: RoomType,
+    /// Newest message, or the room's creation for rooms without any
+    pub last_activity_at: DateTime<Utc>,
+    /// The other person in a direct chat
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub counterpart: Option<Counterpart>,
+}
+
+/// The other side of a direct chat, with their live presence
+#[derive(Debug, Serialize)]
+pub struct Counterpart {
+    pub user_id: String,
+    pub username: Option<String>,
+    pub online: bool,
+    pub is_typing: bool,
+}
+
 /// Type of chat room
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(rename_all = "lowercase")]
@@ -49,16 +331,107 @@ pub enum RoomType {
     Public, // Public room
 }
 
+/// Version of the WebSocket protocol this server speaks
+pub const PROTOCOL_VERSION: u32 = 2;
+/// Oldest protocol version still served. Clients that never send a hello
+/// are treated as this version.
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+/// Optional protocol features a client can ask for in its hello
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    /// MessagePack frames in both directions instead of JSON text
+    BinaryEncoding,
+    /// Emoji reactions on messages
+    Reactions,
+    /// Replies grouped under a parent message
+    Threads,
+}
+
+impl Capability {
+    /// Features this server can switch on for a session
+    pub const SUPPORTED: [Capability; 1] = [Capability::BinaryEncoding];
+
+    /// Known feature by its wire name; names from newer clients are ignored
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "binary_encoding" => Some(Capability::BinaryEncoding),
+            "reactions" => Some(Capability::Reactions),
+            "threads" => Some(Capability::Threads),
+            _ => None,
+        }
+    }
+
+    /// Features from a hello that this server supports
+    pub fn negotiate(requested: &[String]) -> Vec<Capability> {
+        let mut accepted = Vec::new();
+        for capability in requested.iter().filter_map(|name| Capability::parse(name)) {
+            if Capability::SUPPORTED.contains(&capability) && !accepted.contains(&capability) {
+                accepted.push(capability);
+            }
+        }
+        accepted
+    }
+}
+
 /// WebSocket message from client
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ClientMessage {
+    /// First message of a session: the client's protocol version and the
+    /// optional features it wants
+    Hello {
+        version: u32,
+        #[serde(default)]
+        capabilities: Vec<String>,
+    },
     /// Join a chat room
     Join { room_id: String },
+    /// Rejoin rooms after a reconnect, with the last sequence number seen in
+    /// each, to be sent the events missed in between
+    Resume { rooms: HashMap<String, u64> },
     /// Leave a chat room
     Leave { room_id: String },
     /// Send a message
     Message { room_id: String, content: String },
+    /// Copy a stored message into another room
+    Forward {
+        message_id: String,
+        target_room_id: String,
+    },
+    /// Start a poll in a joined room
+    CreatePoll {
+        room_id: String,
+        question: String,
+        options: Vec<String>,
+        #[serde(default)]
+        multiple_choice: bool,
+    },
+    /// Share a place. With `live_minutes` the sender keeps it updated for
+    /// that long.
+    ShareLocation {
+        room_id: String,
+        lat: f64,
+        lng: f64,
+        #[serde(default)]
+        label: Option<String>,
+        #[serde(default)]
+        live_minutes: Option<u32>,
+    },
+    /// Move a live location the user shared
+    UpdateLocation {
+        message_id: String,
+        lat: f64,
+        lng: f64,
+    },
+    /// End a live location early
+    StopLocation { message_id: String },
+    /// Send a sticker from one of the packs
+    Sticker { room_id: String, sticker_id: String },
+    /// Vote for an option of a poll, by index. In single choice polls this
+    /// replaces the earlier vote; in multiple choice polls it toggles the option.
+    Vote { message_id: String, option: u32 },
     /// Typing indicator
     Typing { room_id: String },
     /// Stop typing indicator
@@ -73,18 +446,63 @@ pub enum ClientMessage {
 pub enum ServerMessage {
     /// Connection established
     Connected { user_id: String, session_id: String },
+    /// Answer to a hello: the version both sides will speak and the features
+    /// switched on. Frames after this one use the accepted encoding.
+    Welcome {
+        version: u32,
+        min_version: u32,
+        accepted: Vec<Capability>,
+    },
     /// Joined a room
     Joined { room_id: String },
     /// Left a room
     Left { room_id: String },
+    /// Events since the resumed sequence are no longer buffered; the client
+    /// refetches the room's history instead
+    ResyncRequired { room_id: String, seq: u64 },
     /// New message in room
     Message {
+        /// Missing when the message could not be stored
+        #[serde(skip_serializing_if = "Option::is_none")]
+        message_id: Option<String>,
         room_id: String,
         sender_id: String,
         sender_username: Option<String>,
+        sender_verified: bool,
+        content: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        forwarded_from: Option<ForwardedFrom>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        poll: Option<PollResults>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        sticker: Option<Sticker>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        location: Option<SharedLocation>,
+        timestamp: String,
+    },
+    /// A live location moved or stopped
+    LocationUpdated {
+        room_id: String,
+        message_id: String,
+        location: SharedLocation,
+    },
+    /// Notice from the server, such as a change of room mode
+    System {
+        room_id: String,
         content: String,
         timestamp: String,
     },
+    /// A moderator deleted messages; clients drop them from view
+    MessagesDeleted {
+        room_id: String,
+        message_ids: Vec<String>,
+    },
+    /// Votes on a poll changed
+    PollUpdated {
+        room_id: String,
+        message_id: String,
+        poll: PollResults,
+    },
     /// User started typing
     UserTyping { room_id: String, user_id: String },
     /// User stopped typing
@@ -95,21 +513,210 @@ pub enum ServerMessage {
     UserLeft { room_id: String, user_id: String },
     /// Error message
     Error { message: String },
+    /// The room's slow mode held back a message
+    SlowMode {
+        room_id: String,
+        retry_after_seconds: u64,
+        retry_at: String,
+    },
     /// Pong response
     Pong,
 }
 
+impl ServerMessage {
+    /// Room events that get a sequence number and are replayed on resume.
+    /// Presence and typing are left out; they are stale by then.
+    pub fn is_replayable(&self) -> bool {
+        matches!(
+            self,
+            ServerMessage::Message { .. }
+                | ServerMessage::System { .. }
+                | ServerMessage::MessagesDeleted { .. }
+                | ServerMessage::PollUpdated { .. }
+                | ServerMessage::LocationUpdated { .. }
+        )
+    }
+
+    /// Change to the live location of a location message
+    pub fn location_updated(message: &ChatMessage) -> Option<Self> {
+        Some(ServerMessage::LocationUpdated {
+            room_id: message.room_id.clone(),
+            message_id: message.id?.to_hex(),
+            location: message.location.clone()?,
+        })
+    }
+
+    /// Error for a message held back by slow mode, or a plain error otherwise
+    pub fn from_error(room_id: &str, error: &CustomError) -> Self {
+        match error {
+            CustomError::SlowModeError {
+                retry_after_seconds,
+            } => ServerMessage::SlowMode {
+                room_id: room_id.to_string(),
+                retry_after_seconds: *retry_after_seconds,
+                retry_at: (Utc::now() + chrono::Duration::seconds(*retry_after_seconds as i64))
+                    .to_rfc3339(),
+            },
+            error => ServerMessage::Error {
+                message: error.to_string(),
+            },
+        }
+    }
+}
+
+/// A room event with its place in the room's sequence
+#[derive(Debug, Serialize)]
+pub struct Sequenced<'a> {
+    pub seq: u64,
+    #[serde(flatten)]
+    pub message: &'a ServerMessage,
+}
+
+impl From<&ChatMessage> for ServerMessage {
+    fn from(message: &ChatMessage) -> Self {
+        if message.message_type == MessageType::System {
+            return ServerMessage::System {
+                room_id: message.room_id.clone(),
+                content: message.content.clone(),
+                timestamp: message.created_at.to_rfc3339(),
+            };
+        }
+        ServerMessage::Message {
+            message_id: message.id.map(|id| id.to_hex()),
+            room_id: message.room_id.clone(),
+            sender_id: message.sender_id.clone(),
+            sender_username: message.sender_username.clone(),
+            sender_verified: message.sender_verified,
+            content: message.content.clone(),
+            forwarded_from: message.forwarded_from.clone(),
+            poll: message.poll.as_ref().map(Poll::results),
+            sticker: message.sticker.clone(),
+            location: message.location.clone(),
+            timestamp: message.created_at.to_rfc3339(),
+        }
+    }
+}
+
 /// Request to create a chat room
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct CreateRoomRequest {
+    #[validate(
+        length(max = 100, message = "must be at most 100 characters"),
+        custom(function = "not_blank")
+    )]
     pub name: String,
     pub room_type: RoomType,
     pub participants: Vec<String>,
 }
 
-/// Request to send a message (REST endpoint)
-#[derive(Debug, Deserialize)]
-pub struct SendMessageRequest {
+/// Request body for `PATCH /chat/rooms/{id}`; absent fields are left
+/// unchanged and a blank description clears it
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateRoomRequest {
+    #[validate(
+        length(max = 100, message = "must be at most 100 characters"),
+        custom(function = "not_blank")
+    )]
+    pub name: Option<String>,
+    #[validate(length(max = 500, message = "must be at most 500 characters"))]
+    pub description: Option<String>,
+    /// Public id of one of the caller's uploads, from `/upload/single`
+    #[validate(length(max = 255, message = "must be at most 255 characters"))]
+    pub avatar_public_id: Option<String>,
+}
+
+/// Invite link for a private group room. Links expire, and single-use
+/// links stop working once redeemed.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RoomInvite {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub code: String,
     pub room_id: String,
+    pub created_by: String,
+    pub single_use: bool,
+    #[serde(default)]
+    pub uses: u32,
+    #[serde(with = "bson_datetime")]
+    pub expires_at: DateTime<Utc>,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// Request body for `POST /chat/rooms/{id}/invite-link`
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateInviteLinkRequest {
+    #[serde(default)]
+    pub single_use: bool,
+    /// How long the link works; a day when omitted
+    #[validate(range(min = 1, max = 720, message = "must be between 1 and 720 hours"))]
+    pub expires_in_hours: Option<u32>,
+}
+
+/// An invite link as returned to the room admin who made it
+#[derive(Debug, Serialize)]
+pub struct InviteLink {
+    pub code: String,
+    /// Link to share, when the app's web address is configured
+    pub url: Option<String>,
+    pub room_id: String,
+    pub single_use: bool,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Request body for `PUT /chat/rooms/{id}/read-only`
+#[derive(Debug, Deserialize, Validate)]
+pub struct SetReadOnlyRequest {
+    pub enabled: bool,
+    /// Turn read-only mode off by itself after this long; omit to keep it on
+    #[validate(range(min = 1, max = 10080, message = "must be between 1 and 10080 minutes"))]
+    pub duration_minutes: Option<u32>,
+}
+
+/// Request body for `PUT /chat/rooms/{id}/slow-mode`
+#[derive(Debug, Deserialize, Validate)]
+pub struct SetSlowModeRequest {
+    /// Seconds between each member's messages; 0 turns slow mode off
+    #[validate(range(max = 21600, message = "must be at most 21600 seconds"))]
+    pub seconds: u32,
+}
+
+/// Request to send a message (REST endpoint); the room comes from the path
+#[derive(Debug, Deserialize, Validate)]
+pub struct SendMessageRequest {
+    #[validate(
+        length(max = 4000, message = "must be at most 4000 characters"),
+        custom(function = "not_blank")
+    )]
     pub content: String,
 }
+
+/// Request body for `POST /chat/rooms/{id}/locations`
+#[derive(Debug, Deserialize, Validate)]
+pub struct ShareLocationRequest {
+    #[validate(range(min = -90.0, max = 90.0))]
+    pub lat: f64,
+    #[validate(range(min = -180.0, max = 180.0))]
+    pub lng: f64,
+    #[validate(length(max = 100, message = "must be at most 100 characters"))]
+    pub label: Option<String>,
+    /// Share a live location for this many minutes
+    #[validate(range(min = 1, max = 480))]
+    pub live_minutes: Option<u32>,
+}
+
+/// Request body for `PUT /chat/messages/{id}/location`
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateLocationRequest {
+    #[validate(range(min = -90.0, max = 90.0))]
+    pub lat: f64,
+    #[validate(range(min = -180.0, max = 180.0))]
+    pub lng: f64,
+}
+
+/// Request body for `POST /chat/rooms/{id}/stickers`
+#[derive(Debug, Deserialize, Validate)]
+pub struct SendStickerRequest {
+    #[validate(custom(function = "not_blank"))]
+    pub sticker_id: String,
+}
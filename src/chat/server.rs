@@ -1,6 +1,22 @@
-use crate::chat::model::ServerMessage;
+use crate::chat::model::{ReadOnlyMode, Sequenced, ServerMessage};
+use crate::database::RedisService;
 use actix::prelude::*;
+use chrono::Utc;
 use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::time::Duration;
+
+/// How often connected users are marked online again in Redis
+const PRESENCE_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+/// How long a user stays online in Redis without a refresh, so users of a
+/// crashed instance go offline by themselves
+pub const PRESENCE_TTL_SECONDS: u64 = 90;
+/// How long a typing indicator lasts without a stop
+pub const TYPING_TTL_SECONDS: u64 = 6;
+/// Room events kept per room for clients that reconnect
+const REPLAY_BUFFER_SIZE: usize = 200;
+/// How long a quiet room's replay buffer is kept
+const REPLAY_TTL_SECONDS: u64 = 24 * 60 * 60;
 
 /// Message sent to chat server to connect a session
 #[derive(Message)]
@@ -41,6 +57,69 @@ pub struct RoomMessage {
     pub room_id: String,
     pub sender_session_id: String,
     pub message: ServerMessage,
+    /// Deliver only to this user's sessions, for shadow-banned senders
+    pub visible_to: Option<String>,
+}
+
+/// Message for delivering to everyone in a room, from outside any session
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct Broadcast {
+    pub room_id: String,
+    pub message: ServerMessage,
+    /// Deliver only to this user's sessions, for shadow-banned senders
+    pub visible_to: Option<String>,
+}
+
+/// Send a session the events of a room after sequence number `after`
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct Replay {
+    pub session_id: String,
+    pub room_id: String,
+    pub after: u64,
+}
+
+/// Message for setting or clearing a room's read-only mode
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct SetReadOnly {
+    pub room_id: String,
+    pub owner_id: String,
+    pub mode: Option<ReadOnlyMode>,
+}
+
+/// Ask whether a user may post in a room right now
+#[derive(Message)]
+#[rtype(result = "bool")]
+pub struct CanPost {
+    pub room_id: String,
+    pub user_id: String,
+}
+
+/// Ask whether a user has a session in a room
+#[derive(Message)]
+#[rtype(result = "bool")]
+pub struct InRoom {
+    pub room_id: String,
+    pub user_id: String,
+}
+
+/// Message for setting or clearing a room's slow mode
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct SetSlowMode {
+    pub room_id: String,
+    pub owner_id: String,
+    pub seconds: Option<u32>,
+}
+
+/// Ask how long a user must wait between messages in a room, if at all
+#[derive(Message)]
+#[rtype(result = "Option<u32>")]
+pub struct SlowModeFor {
+    pub room_id: String,
+    pub user_id: String,
 }
 
 /// WebSocket message wrapper
@@ -55,6 +134,14 @@ pub struct SessionInfo {
     pub addr: Recipient<WsMessage>,
 }
 
+/// Where a room's sequence numbers stand
+enum RoomSequence {
+    /// The last number is being read from Redis; events wait here in order
+    Loading(Vec<ServerMessage>),
+    /// The last number given out
+    Ready(u64),
+}
+
 /// Chat server actor - manages rooms and sessions
 pub struct ChatServer {
     /// Map of session_id -> session info
@@ -63,25 +150,163 @@ pub struct ChatServer {
     rooms: HashMap<String, HashSet<String>>,
     /// Map of user_id -> session_id (for direct messaging)
     user_sessions: HashMap<String, String>,
+    /// Map of room_id -> (owner id, read-only mode) for read-only rooms
+    read_only: HashMap<String, (String, ReadOnlyMode)>,
+    /// Map of room_id -> (owner id, seconds between messages) for slow rooms
+    slow_mode: HashMap<String, (String, u32)>,
+    /// Map of room_id -> numbering of its replayable events
+    sequences: HashMap<String, RoomSequence>,
+    /// Mirrors presence and typing for other instances and REST endpoints
+    redis: RedisService,
 }
 
 impl ChatServer {
-    pub fn new() -> Self {
+    pub fn new(redis: RedisService) -> Self {
         ChatServer {
             sessions: HashMap::new(),
             rooms: HashMap::new(),
             user_sessions: HashMap::new(),
+            read_only: HashMap::new(),
+            slow_mode: HashMap::new(),
+            sequences: HashMap::new(),
+            redis,
+        }
+    }
+
+    /// Mirror presence to Redis in the background; failures are only logged
+    fn mirror<F>(&self, update: impl FnOnce(RedisService) -> F)
+    where
+        F: Future<Output = Result<(), String>> + 'static,
+    {
+        let update = update(self.redis.clone());
+        actix_web::rt::spawn(async move {
+            if let Err(e) = update.await {
+                log::warn!("Failed to mirror chat presence: {}", e);
+            }
+        });
+    }
+
+    /// Deliver a room event, numbering it first when clients may need it
+    /// replayed. Events only one user sees stay out of the room's sequence.
+    fn publish(
+        &mut self,
+        room_id: String,
+        message: ServerMessage,
+        visible_to: Option<String>,
+        ctx: &mut Context<Self>,
+    ) {
+        if visible_to.is_some() || !message.is_replayable() {
+            self.deliver(&room_id, &message, visible_to.as_deref());
+            return;
+        }
+        match self.sequences.get_mut(&room_id) {
+            Some(RoomSequence::Ready(seq)) => {
+                *seq += 1;
+                let seq = *seq;
+                self.send_sequenced(&room_id, seq, &message);
+            }
+            Some(RoomSequence::Loading(pending)) => pending.push(message),
+            None => {
+                self.sequences
+                    .insert(room_id.clone(), RoomSequence::Loading(vec![message]));
+                self.load_sequence(room_id, ctx);
+            }
+        }
+    }
+
+    /// Carry on a room's numbering from Redis, then send the events that
+    /// waited for it. Other rooms aren't held up meanwhile.
+    fn load_sequence(&mut self, room_id: String, ctx: &mut Context<Self>) {
+        let redis = self.redis.clone();
+        let load_room = room_id.clone();
+        ctx.spawn(
+            async move { redis.replay_latest(&load_room).await }
+                .into_actor(self)
+                .map(move |result, act, _| {
+                    let Some(RoomSequence::Loading(pending)) = act.sequences.remove(&room_id)
+                    else {
+                        return;
+                    };
+                    match result {
+                        Ok(mut seq) => {
+                            for message in &pending {
+                                seq += 1;
+                                act.send_sequenced(&room_id, seq, message);
+                            }
+                            act.sequences.insert(room_id, RoomSequence::Ready(seq));
+                        }
+                        // The room's next event tries again
+                        Err(e) => {
+                            log::warn!("Failed to sequence chat events: {}", e);
+                            for message in &pending {
+                                act.deliver(&room_id, message, None);
+                            }
+                        }
+                    }
+                }),
+        );
+    }
+
+    /// Send a numbered event to a room, buffering it for replay in the
+    /// background
+    fn send_sequenced(&self, room_id: &str, seq: u64, message: &ServerMessage) {
+        let Ok(payload) = serde_json::to_string(message) else {
+            return;
+        };
+        let redis = self.redis.clone();
+        let buffer_room = room_id.to_string();
+        actix_web::rt::spawn(async move {
+            if let Err(e) = redis
+                .replay_store(
+                    &buffer_room,
+                    seq,
+                    &payload,
+                    REPLAY_BUFFER_SIZE,
+                    REPLAY_TTL_SECONDS,
+                )
+                .await
+            {
+                log::warn!("Failed to buffer chat event: {}", e);
+            }
+        });
+
+        let sequenced = Sequenced { seq, message };
+        let msg_json = serde_json::to_string(&sequenced).unwrap_or_default();
+        self.send_json_to_room(room_id, &msg_json, None);
+    }
+
+    /// Send message to the sessions in a room, or only to one user's
+    fn deliver(&self, room_id: &str, message: &ServerMessage, visible_to: Option<&str>) {
+        let Some(user_id) = visible_to else {
+            self.send_to_room(room_id, message, None);
+            return;
+        };
+        if let Some(sessions) = self.rooms.get(room_id) {
+            for session_id in sessions {
+                if self
+                    .sessions
+                    .get(session_id)
+                    .is_some_and(|session| session.user_id == user_id)
+                {
+                    self.send_to_session(session_id, message);
+                }
+            }
         }
     }
 
     /// Send message to all sessions in a room
     fn send_to_room(&self, room_id: &str, message: &ServerMessage, skip_session: Option<&str>) {
+        let msg_json = serde_json::to_string(message).unwrap_or_default();
+        self.send_json_to_room(room_id, &msg_json, skip_session);
+    }
+
+    /// Send an encoded message to all sessions in a room
+    fn send_json_to_room(&self, room_id: &str, msg_json: &str, skip_session: Option<&str>) {
         if let Some(sessions) = self.rooms.get(room_id) {
-            let msg_json = serde_json::to_string(message).unwrap_or_default();
             for session_id in sessions {
                 if skip_session.map_or(true, |s| s != session_id) {
                     if let Some(session) = self.sessions.get(session_id) {
-                        let _ = session.addr.do_send(WsMessage(msg_json.clone()));
+                        let _ = session.addr.do_send(WsMessage(msg_json.to_string()));
                     }
                 }
             }
@@ -97,14 +322,25 @@ impl ChatServer {
     }
 }
 
-impl Default for ChatServer {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
 impl Actor for ChatServer {
     type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.run_interval(PRESENCE_REFRESH_INTERVAL, |act, _| {
+            let user_ids: Vec<String> = act
+                .sessions
+                .values()
+                .map(|session| session.user_id.clone())
+                .collect::<HashSet<_>>()
+                .into_iter()
+                .collect();
+            act.mirror(|redis| async move {
+                redis
+                    .presence_set_online(&user_ids, PRESENCE_TTL_SECONDS)
+                    .await
+            });
+        });
+    }
 }
 
 /// Handler for Connect message
@@ -131,6 +367,13 @@ impl Handler<Connect> for ChatServer {
         self.user_sessions
             .insert(msg.user_id.clone(), msg.session_id.clone());
 
+        let user_ids = vec![msg.user_id.clone()];
+        self.mirror(|redis| async move {
+            redis
+                .presence_set_online(&user_ids, PRESENCE_TTL_SECONDS)
+                .await
+        });
+
         // Send connected confirmation
         self.send_to_session(
             &msg.session_id,
@@ -175,7 +418,14 @@ impl Handler<Disconnect> for ChatServer {
         }
 
         // Remove session
-        self.sessions.remove(&msg.session_id);
+        if let Some(session) = self.sessions.remove(&msg.session_id)
+            && !self
+                .sessions
+                .values()
+                .any(|other| other.user_id == session.user_id)
+        {
+            self.mirror(|redis| async move { redis.presence_set_offline(&session.user_id).await });
+        }
     }
 }
 
@@ -262,7 +512,201 @@ impl Handler<LeaveRoom> for ChatServer {
 impl Handler<RoomMessage> for ChatServer {
     type Result = ();
 
-    fn handle(&mut self, msg: RoomMessage, _: &mut Context<Self>) {
-        self.send_to_room(&msg.room_id, &msg.message, None);
+    fn handle(&mut self, msg: RoomMessage, ctx: &mut Context<Self>) {
+        // Only sessions in the room may post to it, so membership checks on join hold
+        let in_room = self
+            .rooms
+            .get(&msg.room_id)
+            .is_some_and(|sessions| sessions.contains(&msg.sender_session_id));
+        if !in_room {
+            self.send_to_session(
+                &msg.sender_session_id,
+                &ServerMessage::Error {
+                    message: format!("Join room {} before sending to it", msg.room_id),
+                },
+            );
+            return;
+        }
+
+        let typing = match &msg.message {
+            ServerMessage::UserTyping { user_id, .. } => Some((user_id.clone(), true)),
+            ServerMessage::UserStopTyping { user_id, .. } => Some((user_id.clone(), false)),
+            _ => None,
+        };
+        if let Some((user_id, typing)) = typing {
+            let room_id = msg.room_id.clone();
+            self.mirror(|redis| async move {
+                redis
+                    .presence_set_typing(&room_id, &user_id, typing, TYPING_TTL_SECONDS)
+                    .await
+            });
+        }
+
+        self.publish(msg.room_id, msg.message, msg.visible_to, ctx);
+    }
+}
+
+/// Handler for Broadcast, used by the REST endpoints
+impl Handler<Broadcast> for ChatServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: Broadcast, ctx: &mut Context<Self>) {
+        self.publish(msg.room_id, msg.message, msg.visible_to, ctx);
+    }
+}
+
+/// Handler for Replay. Live events wait until the replay is out, so the
+/// session sees the room in order.
+impl Handler<Replay> for ChatServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: Replay, ctx: &mut Context<Self>) {
+        let redis = self.redis.clone();
+        let room_id = msg.room_id.clone();
+        let after = msg.after;
+        ctx.wait(
+            async move { redis.replay_since(&room_id, after).await }
+                .into_actor(self)
+                .map(move |result, act, _| {
+                    let (events, latest) = result.unwrap_or_else(|e| {
+                        log::warn!("Failed to read chat replay: {}", e);
+                        (Vec::new(), 0)
+                    });
+                    // Events still being buffered are already numbered here
+                    let latest = match act.sequences.get(&msg.room_id) {
+                        Some(RoomSequence::Ready(seq)) => latest.max(*seq),
+                        _ => latest,
+                    };
+                    // A gap at the front means the buffer was trimmed or has expired
+                    let complete = match events.first() {
+                        Some((seq, _)) => *seq == msg.after + 1,
+                        None => latest == msg.after,
+                    };
+                    if !complete {
+                        act.send_to_session(
+                            &msg.session_id,
+                            &ServerMessage::ResyncRequired {
+                                room_id: msg.room_id,
+                                seq: latest,
+                            },
+                        );
+                        return;
+                    }
+                    let Some(session) = act.sessions.get(&msg.session_id) else {
+                        return;
+                    };
+                    for (seq, payload) in events {
+                        let Ok(message) = serde_json::from_str::<ServerMessage>(&payload) else {
+                            continue;
+                        };
+                        let sequenced = Sequenced {
+                            seq,
+                            message: &message,
+                        };
+                        if let Ok(msg_json) = serde_json::to_string(&sequenced) {
+                            let _ = session.addr.do_send(WsMessage(msg_json));
+                        }
+                    }
+                }),
+        );
+    }
+}
+
+/// Handler for SetReadOnly. Timed modes are lifted here when they run out,
+/// with a notice to the room.
+impl Handler<SetReadOnly> for ChatServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: SetReadOnly, ctx: &mut Context<Self>) {
+        let now = Utc::now();
+        let Some(mode) = msg.mode.filter(|mode| mode.is_active(now)) else {
+            self.read_only.remove(&msg.room_id);
+            return;
+        };
+        // Sessions joining a room report its mode again; only schedule once
+        if self
+            .read_only
+            .get(&msg.room_id)
+            .is_some_and(|(_, current)| *current == mode)
+        {
+            return;
+        }
+
+        if let Some(until) = mode.until {
+            let room_id = msg.room_id.clone();
+            let expected = mode.clone();
+            let delay = (until - now).to_std().unwrap_or_default();
+            ctx.run_later(delay, move |act, ctx| {
+                if act
+                    .read_only
+                    .get(&room_id)
+                    .is_none_or(|(_, current)| *current != expected)
+                {
+                    return;
+                }
+                act.read_only.remove(&room_id);
+                let notice = ServerMessage::System {
+                    room_id: room_id.clone(),
+                    content: "Everyone can post in this room again".to_string(),
+                    timestamp: Utc::now().to_rfc3339(),
+                };
+                act.publish(room_id, notice, None, ctx);
+            });
+        }
+        self.read_only.insert(msg.room_id, (msg.owner_id, mode));
+    }
+}
+
+/// Handler for CanPost
+impl Handler<CanPost> for ChatServer {
+    type Result = bool;
+
+    fn handle(&mut self, msg: CanPost, _: &mut Context<Self>) -> bool {
+        self.read_only
+            .get(&msg.room_id)
+            .is_none_or(|(owner_id, mode)| mode.allows(&msg.user_id, owner_id, Utc::now()))
+    }
+}
+
+/// Handler for SetSlowMode
+impl Handler<SetSlowMode> for ChatServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: SetSlowMode, _: &mut Context<Self>) {
+        match msg.seconds {
+            Some(seconds) => {
+                self.slow_mode.insert(msg.room_id, (msg.owner_id, seconds));
+            }
+            None => {
+                self.slow_mode.remove(&msg.room_id);
+            }
+        }
+    }
+}
+
+/// Handler for SlowModeFor; the room owner isn't held to slow mode
+impl Handler<SlowModeFor> for ChatServer {
+    type Result = Option<u32>;
+
+    fn handle(&mut self, msg: SlowModeFor, _: &mut Context<Self>) -> Option<u32> {
+        self.slow_mode
+            .get(&msg.room_id)
+            .filter(|(owner_id, _)| *owner_id != msg.user_id)
+            .map(|(_, seconds)| *seconds)
+    }
+}
+
+/// Handler for InRoom
+impl Handler<InRoom> for ChatServer {
+    type Result = bool;
+
+    fn handle(&mut self, msg: InRoom, _: &mut Context<Self>) -> bool {
+        self.rooms.get(&msg.room_id).is_some_and(|sessions| {
+            sessions.iter().any(|session_id| {
+                self.sessions
+                    .get(session_id)
+                    .is_some_and(|session| session.user_id == msg.user_id)
+            })
+        })
     }
 }
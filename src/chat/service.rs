@@ -0,0 +1,1139 @@
+use crate::chat::model::{
+    ChatMessage, ChatRoom, ConversationSummary, Counterpart, ForwardedFrom,
+    MAX_LIVE_LOCATION_MINUTES, MAX_LOCATION_LABEL_CHARS, MAX_POLL_OPTION_CHARS, MAX_POLL_OPTIONS,
+    MAX_POLL_QUESTION_CHARS, MediaType, MessageType, Poll, RoomInvite, RoomSummary, RoomType,
+    SYSTEM_SENDER_ID, ShareLocationRequest, SharedLocation,
+};
+use crate::chat::server::{ChatServer, InRoom};
+use crate::database::{Page, RedisService};
+use crate::friend::model::{DIRECT_ROOM_PREFIX, direct_room_participants};
+use crate::friend::service::FriendService;
+use crate::group::model::GROUP_ROOM_PREFIX;
+use crate::group::service::GroupService;
+use crate::moderation::service::ModerationService;
+use crate::notification::service::NotificationService;
+use crate::sticker::model::Sticker;
+use crate::sticker::service::StickerService;
+use crate::utils::datetime::bson_now;
+use crate::utils::error::CustomError;
+use crate::utils::sanitize::{Markup, sanitize, sanitize_required};
+use actix::Addr;
+use chrono::{DateTime, Utc};
+use futures_util::TryStreamExt;
+use mongodb::bson::{self, Document, doc, oid::ObjectId};
+use mongodb::options::{IndexOptions, ReturnDocument};
+use mongodb::{Client, Collection, IndexModel};
+use rand::Rng;
+use rand::distr::Alphanumeric;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration as StdDuration;
+
+/// Reject coordinates off the globe
+fn check_coordinates(lat: f64, lng: f64) -> Result<(), CustomError> {
+    if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lng) {
+        return Err(CustomError::BadRequestError(
+            "lat must be between -90 and 90 and lng between -180 and 180".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Length of the base62 code in invite links
+const INVITE_CODE_LENGTH: usize = 12;
+
+/// What a message carries besides its text
+#[derive(Default)]
+struct Attachments {
+    forwarded_from: Option<ForwardedFrom>,
+    poll: Option<Poll>,
+    sticker: Option<Sticker>,
+    location: Option<SharedLocation>,
+}
+
+/// What a message records about its sender
+#[derive(Debug, Deserialize)]
+struct Sender {
+    username: String,
+    #[serde(default)]
+    is_verified: bool,
+    #[serde(default)]
+    shadow_banned: bool,
+}
+
+/// A message as inserted. `blocked` stays out of `ChatMessage`, which is
+/// what clients get, but history needs it once the block is lifted.
+#[derive(Serialize)]
+struct StoredMessage<'a> {
+    #[serde(flatten)]
+    message: &'a ChatMessage,
+    /// Dropped by a block in a direct room: only the sender sees it
+    blocked: bool,
+}
+
+/// `$or` clauses leaving out messages dropped by a block, except for
+/// their sender
+fn unless_blocked(viewer: &ObjectId) -> Vec<Document> {
+    vec![
+        doc! { "blocked": { "$ne": true } },
+        doc! { "sender_id": viewer.to_hex() },
+    ]
+}
+
+/// Message history and room access, shared by the WebSocket and REST paths
+pub struct ChatService {
+    messages: Collection<ChatMessage>,
+    rooms: Collection<ChatRoom>,
+    invites: Collection<RoomInvite>,
+    users: Collection<Sender>,
+    groups: GroupService,
+    friends: FriendService,
+    moderation: ModerationService,
+    stickers: StickerService,
+}
+
+impl ChatService {
+    pub fn new(client: &Client, redis_service: RedisService) -> Self {
+        let db = client.database("rust_blogdb");
+        ChatService {
+            messages: db.collection::<ChatMessage>("chat_messages"),
+            rooms: db.collection::<ChatRoom>("chat_rooms"),
+            invites: db.collection::<RoomInvite>("chat_invites"),
+            users: db.collection::<Sender>("users"),
+            groups: GroupService::new(client, redis_service),
+            friends: FriendService::new(client),
+            moderation: ModerationService::new(client),
+            stickers: StickerService::new(client),
+        }
+    }
+
+    /// Room history, newest first, and its media gallery; rooms by id and
+    /// public rooms by
+    /// activity; invites by code, dropped once expired
+    #[tracing::instrument(skip_all)]
+    pub async fn ensure_indexes(&self) -> Result<(), CustomError> {
+        self.messages
+            .create_indexes(vec![
+                IndexModel::builder()
+                    .keys(doc! { "room_id": 1, "created_at": -1 })
+                    .build(),
+                IndexModel::builder()
+                    .keys(doc! { "room_id": 1, "message_type": 1, "created_at": -1 })
+                    .build(),
+            ])
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to create chat indexes: {}", e))
+            })?;
+
+        self.rooms
+            .create_indexes(vec![
+                IndexModel::builder().keys(doc! { "room_id": 1 }).build(),
+                IndexModel::builder()
+                    .keys(doc! { "room_type": 1, "last_message_at": -1 })
+                    .build(),
+            ])
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!(
+                    "Failed to create chat room indexes: {}",
+                    e
+                ))
+            })?;
+
+        self.invites
+            .create_indexes(vec![
+                IndexModel::builder()
+                    .keys(doc! { "code": 1 })
+                    .options(IndexOptions::builder().unique(true).build())
+                    .build(),
+                IndexModel::builder()
+                    .keys(doc! { "expires_at": 1 })
+                    .options(
+                        IndexOptions::builder()
+                            .expire_after(StdDuration::from_secs(0))
+                            .build(),
+                    )
+                    .build(),
+            ])
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!(
+                    "Failed to create chat invite indexes: {}",
+                    e
+                ))
+            })?;
+
+        Ok(())
+    }
+
+    /// Public rooms, most recently active first, optionally matching `query`
+    /// in their name
+    #[tracing::instrument(skip_all)]
+    pub async fn public_rooms(
+        &self,
+        query: Option<&str>,
+        viewer_id: &ObjectId,
+        page: u64,
+        per_page: u64,
+    ) -> Result<Page<RoomSummary>, CustomError> {
+        let mut filter = doc! { "room_type": "public" };
+        if let Some(query) = query.map(str::trim).filter(|query| !query.is_empty()) {
+            filter.insert(
+                "name",
+                doc! { "$regex": regex::escape(query), "$options": "i" },
+            );
+        }
+
+        let total = self
+            .rooms
+            .count_documents(filter.clone())
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+        let rooms: Vec<ChatRoom> = self
+            .rooms
+            .find(filter)
+            .sort(doc! { "last_message_at": -1, "created_at": -1 })
+            .skip((page - 1) * per_page)
+            .limit(per_page as i64)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        let viewer_id = viewer_id.to_hex();
+        Ok(Page {
+            items: rooms
+                .into_iter()
+                .map(|room| RoomSummary::new(room, &viewer_id))
+                .collect(),
+            page,
+            per_page,
+            total,
+        })
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn get_room(&self, room_id: &str) -> Result<Option<ChatRoom>, CustomError> {
+        self.rooms
+            .find_one(doc! { "room_id": room_id })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))
+    }
+
+    /// Apply `$set` changes to a room and return it as updated
+    #[tracing::instrument(skip_all)]
+    pub async fn update_room(
+        &self,
+        room_id: &str,
+        mut changes: Document,
+    ) -> Result<ChatRoom, CustomError> {
+        changes.insert("updated_at", bson_now());
+        self.rooms
+            .find_one_and_update(doc! { "room_id": room_id }, doc! { "$set": changes })
+            .return_document(ReturnDocument::After)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .ok_or_else(|| CustomError::NotFoundError("Room not found".to_string()))
+    }
+
+    /// Add the user to a public room's participants. Returns the room and
+    /// whether the user was newly added.
+    #[tracing::instrument(skip_all)]
+    pub async fn join_public_room(
+        &self,
+        room_id: &str,
+        user_id: &ObjectId,
+    ) -> Result<(ChatRoom, bool), CustomError> {
+        let room = self
+            .get_room(room_id)
+            .await?
+            .filter(|room| room.room_type == RoomType::Public)
+            .ok_or_else(|| CustomError::NotFoundError("Room not found".to_string()))?;
+
+        let user_id = user_id.to_hex();
+        let result = self
+            .rooms
+            .update_one(
+                doc! { "room_id": room_id, "participants": { "$ne": user_id.clone() } },
+                doc! {
+                    "$push": { "participants": user_id },
+                    "$set": { "updated_at": bson_now() },
+                },
+            )
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        Ok((room, result.matched_count > 0))
+    }
+
+    /// Rooms the user takes part in, most recently active first. Direct
+    /// chats come from their messages, as they have no room document.
+    /// Presence is left for the caller to fill in.
+    #[tracing::instrument(skip_all)]
+    pub async fn conversations(
+        &self,
+        user_id: &ObjectId,
+        limit: i64,
+    ) -> Result<Vec<ConversationSummary>, CustomError> {
+        let viewer = user_id.to_hex();
+        let rooms: Vec<ChatRoom> = self
+            .rooms
+            .find(doc! { "participants": &viewer })
+            .sort(doc! { "last_message_at": -1 })
+            .limit(limit)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        #[derive(Deserialize)]
+        struct DirectActivity {
+            #[serde(rename = "_id")]
+            room_id: String,
+            last_message_at: bson::DateTime,
+        }
+        let direct: Vec<DirectActivity> = self
+            .messages
+            .aggregate(vec![
+                doc! { "$match": {
+                    "room_id": { "$regex": format!("^{}.*{}", DIRECT_ROOM_PREFIX, viewer) },
+                } },
+                doc! { "$group": { "_id": "$room_id", "last_message_at": { "$max": "$created_at" } } },
+                doc! { "$sort": { "last_message_at": -1 } },
+                doc! { "$limit": limit },
+            ])
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect::<Vec<Document>>()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .into_iter()
+            .filter_map(|doc| bson::from_document(doc).ok())
+            .collect();
+
+        let mut conversations: Vec<ConversationSummary> = rooms
+            .into_iter()
+            .map(|room| ConversationSummary {
+                last_activity_at: room.last_message_at.unwrap_or(room.created_at),
+                counterpart: None,
+                room_id: room.room_id,
+                name: room.name,
+                room_type: room.room_type,
+            })
+            .collect();
+        for activity in direct {
+            if conversations
+                .iter()
+                .any(|conversation| conversation.room_id == activity.room_id)
+            {
+                continue;
+            }
+            conversations.push(ConversationSummary {
+                name: String::new(),
+                room_type: RoomType::Direct,
+                last_activity_at: activity.last_message_at.to_chrono(),
+                counterpart: None,
+                room_id: activity.room_id,
+            });
+        }
+        conversations.sort_by(|a, b| b.last_activity_at.cmp(&a.last_activity_at));
+        conversations.truncate(limit.max(0) as usize);
+
+        // Direct chats are named after the other person
+        let others: HashMap<String, ObjectId> = conversations
+            .iter()
+            .filter_map(|conversation| {
+                let (first, second) = direct_room_participants(&conversation.room_id)?;
+                let other = if first == *user_id { second } else { first };
+                Some((conversation.room_id.clone(), other))
+            })
+            .collect();
+        let ids: Vec<ObjectId> = others.values().copied().collect();
+        let names: HashMap<ObjectId, String> = self
+            .users
+            .clone_with_type::<Document>()
+            .find(doc! { "_id": { "$in": ids } })
+            .projection(doc! { "username": 1 })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect::<Vec<Document>>()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .into_iter()
+            .filter_map(|user| {
+                Some((
+                    user.get_object_id("_id").ok()?,
+                    user.get_str("username").ok()?.to_string(),
+                ))
+            })
+            .collect();
+        for conversation in &mut conversations {
+            let Some(other) = others.get(&conversation.room_id) else {
+                continue;
+            };
+            let username = names.get(other).cloned();
+            if let Some(username) = &username {
+                conversation.name = username.clone();
+            }
+            conversation.counterpart = Some(Counterpart {
+                user_id: other.to_hex(),
+                username,
+                online: false,
+                is_typing: false,
+            });
+        }
+
+        Ok(conversations)
+    }
+
+    /// Create an invite link to a private group's room
+    #[tracing::instrument(skip_all)]
+    pub async fn create_invite(
+        &self,
+        room_id: &str,
+        created_by: &str,
+        single_use: bool,
+        expires_at: DateTime<Utc>,
+    ) -> Result<RoomInvite, CustomError> {
+        let mut invite = RoomInvite {
+            id: None,
+            code: rand::rng()
+                .sample_iter(&Alphanumeric)
+                .take(INVITE_CODE_LENGTH)
+                .map(char::from)
+                .collect(),
+            room_id: room_id.to_string(),
+            created_by: created_by.to_string(),
+            single_use,
+            uses: 0,
+            expires_at,
+            created_at: Utc::now(),
+        };
+        let result = self.invites.insert_one(&invite).await.map_err(|e| {
+            CustomError::InternalServerError(format!("Failed to create invite link: {}", e))
+        })?;
+        invite.id = result.inserted_id.as_object_id();
+
+        Ok(invite)
+    }
+
+    /// An invite that still works, without redeeming it
+    #[tracing::instrument(skip_all)]
+    pub async fn find_invite(&self, code: &str) -> Result<RoomInvite, CustomError> {
+        self.invites
+            .find_one(Self::usable_invite(code))
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .ok_or_else(|| {
+                CustomError::NotFoundError("This invite link is invalid or has expired".to_string())
+            })
+    }
+
+    /// Count a use of an invite. Fails if it stopped working meanwhile, so a
+    /// single-use link can't be redeemed twice.
+    #[tracing::instrument(skip_all)]
+    pub async fn redeem_invite(&self, code: &str) -> Result<RoomInvite, CustomError> {
+        self.invites
+            .find_one_and_update(Self::usable_invite(code), doc! { "$inc": { "uses": 1 } })
+            .return_document(ReturnDocument::After)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .ok_or_else(|| {
+                CustomError::NotFoundError("This invite link is invalid or has expired".to_string())
+            })
+    }
+
+    fn usable_invite(code: &str) -> Document {
+        doc! {
+            "code": code,
+            "expires_at": { "$gt": bson_now() },
+            "$or": [{ "single_use": false }, { "uses": 0 }],
+        }
+    }
+
+    /// Group rooms are for active members only, and direct rooms for their
+    /// two participants, subject to the recipient's privacy. Other rooms are open.
+    pub async fn can_join(&self, room_id: &str, user_id: &str) -> Result<bool, CustomError> {
+        let is_group = room_id.starts_with(GROUP_ROOM_PREFIX);
+        if !is_group && !room_id.starts_with(DIRECT_ROOM_PREFIX) {
+            return Ok(true);
+        }
+        let Ok(user_id) = ObjectId::parse_str(user_id) else {
+            return Ok(false);
+        };
+
+        if is_group {
+            self.groups.can_chat(room_id, &user_id).await
+        } else {
+            self.friends.can_direct_message(room_id, &user_id).await
+        }
+    }
+
+    /// Store a text message sent to a room
+    #[tracing::instrument(skip_all)]
+    pub async fn save_message(
+        &self,
+        room_id: &str,
+        sender_id: &str,
+        content: String,
+    ) -> Result<ChatMessage, CustomError> {
+        self.insert_message(
+            room_id,
+            sender_id,
+            content,
+            MessageType::Text,
+            Attachments::default(),
+        )
+        .await
+    }
+
+    /// Store a sticker message. Its text is the sticker's description, for
+    /// notifications and clients that can't render stickers.
+    #[tracing::instrument(skip_all)]
+    pub async fn save_sticker(
+        &self,
+        room_id: &str,
+        sender_id: &str,
+        sticker_id: &str,
+    ) -> Result<ChatMessage, CustomError> {
+        let not_found = || CustomError::NotFoundError("Sticker not found".to_string());
+        let sticker_id = ObjectId::parse_str(sticker_id).map_err(|_| not_found())?;
+        let sticker = self
+            .stickers
+            .find_sticker(&sticker_id)
+            .await?
+            .ok_or_else(not_found)?;
+
+        self.insert_message(
+            room_id,
+            sender_id,
+            sticker
+                .alt_text
+                .clone()
+                .unwrap_or_else(|| "Sticker".to_string()),
+            MessageType::Sticker,
+            Attachments {
+                sticker: Some(sticker),
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    /// Store a notice from the server, shown in the room's history
+    #[tracing::instrument(skip_all)]
+    pub async fn save_system_message(
+        &self,
+        room_id: &str,
+        content: String,
+    ) -> Result<ChatMessage, CustomError> {
+        self.insert_message(
+            room_id,
+            SYSTEM_SENDER_ID,
+            content,
+            MessageType::System,
+            Attachments::default(),
+        )
+        .await
+    }
+
+    /// Store a poll sent to a room. Polls aren't allowed in direct rooms.
+    #[tracing::instrument(skip_all)]
+    pub async fn create_poll(
+        &self,
+        room_id: &str,
+        sender_id: &str,
+        question: &str,
+        options: &[String],
+        multiple_choice: bool,
+    ) -> Result<ChatMessage, CustomError> {
+        if room_id.starts_with(DIRECT_ROOM_PREFIX) {
+            return Err(CustomError::BadRequestError(
+                "Polls can only be run in group rooms".to_string(),
+            ));
+        }
+
+        let question = sanitize_required(question, Markup::None, "question")?;
+        if question.chars().count() > MAX_POLL_QUESTION_CHARS {
+            return Err(CustomError::BadRequestError(format!(
+                "question must be at most {} characters",
+                MAX_POLL_QUESTION_CHARS
+            )));
+        }
+        if !(2..=MAX_POLL_OPTIONS).contains(&options.len()) {
+            return Err(CustomError::BadRequestError(format!(
+                "A poll needs between 2 and {} options",
+                MAX_POLL_OPTIONS
+            )));
+        }
+        let options = options
+            .iter()
+            .map(|option| {
+                let option = sanitize_required(option, Markup::None, "option")?;
+                if option.chars().count() > MAX_POLL_OPTION_CHARS {
+                    return Err(CustomError::BadRequestError(format!(
+                        "option must be at most {} characters",
+                        MAX_POLL_OPTION_CHARS
+                    )));
+                }
+                Ok(option)
+            })
+            .collect::<Result<Vec<String>, CustomError>>()?;
+
+        let poll = Poll {
+            question: question.clone(),
+            options,
+            multiple_choice,
+            votes: HashMap::new(),
+        };
+        self.insert_message(
+            room_id,
+            sender_id,
+            question,
+            MessageType::Poll,
+            Attachments {
+                poll: Some(poll),
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    /// Record a vote on a poll and return the updated message. The voter
+    /// must be able to use the poll's room.
+    #[tracing::instrument(skip_all)]
+    pub async fn vote(
+        &self,
+        message_id: &str,
+        user_id: &str,
+        option: u32,
+    ) -> Result<ChatMessage, CustomError> {
+        let not_found = || CustomError::NotFoundError("Poll not found".to_string());
+        let message_id = ObjectId::parse_str(message_id)
+            .map_err(|_| CustomError::BadRequestError("Invalid message ID".to_string()))?;
+        let message = self
+            .messages
+            .find_one(doc! { "_id": message_id })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .ok_or_else(not_found)?;
+        let Some(poll) = &message.poll else {
+            return Err(not_found());
+        };
+        if !self.can_join(&message.room_id, user_id).await? {
+            return Err(not_found());
+        }
+        if option as usize >= poll.options.len() {
+            return Err(CustomError::BadRequestError(
+                "That option isn't part of the poll".to_string(),
+            ));
+        }
+
+        // Votes are keyed by voter, so each change is a single atomic update
+        let key = format!("poll.votes.{}", user_id);
+        let update = if !poll.multiple_choice {
+            doc! { "$set": { key: [option] } }
+        } else if poll
+            .votes
+            .get(user_id)
+            .is_some_and(|picked| picked.contains(&option))
+        {
+            doc! { "$pull": { key: option } }
+        } else {
+            doc! { "$addToSet": { key: option } }
+        };
+
+        self.messages
+            .find_one_and_update(doc! { "_id": message_id }, update)
+            .return_document(ReturnDocument::After)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .ok_or_else(not_found)
+    }
+
+    /// Copy a message into another room. The user must be able to use both
+    /// rooms and to see the original.
+    #[tracing::instrument(skip_all)]
+    pub async fn forward_message(
+        &self,
+        message_id: &str,
+        target_room_id: &str,
+        user_id: &ObjectId,
+    ) -> Result<ChatMessage, CustomError> {
+        let not_found = || CustomError::NotFoundError("Message not found".to_string());
+        let message_id = ObjectId::parse_str(message_id)
+            .map_err(|_| CustomError::BadRequestError("Invalid message ID".to_string()))?;
+        let original = self
+            .messages
+            .find_one(doc! { "_id": message_id })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .ok_or_else(not_found)?;
+
+        let forwarder = user_id.to_hex();
+        let hidden = self.moderation.hidden_authors(Some(user_id)).await?;
+        let sender_hidden = hidden.iter().any(|id| id.to_hex() == original.sender_id);
+        if sender_hidden || !self.can_join(&original.room_id, &forwarder).await? {
+            return Err(not_found());
+        }
+        if original.room_id == target_room_id {
+            return Err(CustomError::BadRequestError(
+                "Forward the message to a different room".to_string(),
+            ));
+        }
+        if !self.can_join(target_room_id, &forwarder).await? {
+            return Err(CustomError::ForbiddenError(
+                "You can't use this room".to_string(),
+            ));
+        }
+
+        let forwarded_from = original.forwarded_from.clone().unwrap_or(ForwardedFrom {
+            message_id,
+            room_id: original.room_id,
+            sender_id: original.sender_id,
+            sender_username: original.sender_username,
+        });
+        self.insert_message(
+            target_room_id,
+            &forwarder,
+            original.content,
+            if original.poll.is_some() {
+                MessageType::Poll
+            } else if original.sticker.is_some() {
+                MessageType::Sticker
+            } else if original.location.is_some() {
+                MessageType::Location
+            } else {
+                MessageType::Text
+            },
+            Attachments {
+                forwarded_from: Some(forwarded_from),
+                poll: original.poll.map(|poll| Poll {
+                    votes: HashMap::new(),
+                    ..poll
+                }),
+                sticker: original.sticker,
+                // A forwarded live location is a snapshot of where it was
+                location: original.location.map(|location| SharedLocation {
+                    live_until: None,
+                    updated_at: None,
+                    ..location
+                }),
+            },
+        )
+        .await
+    }
+
+    /// Store a location message. Live locations can be moved by the sender
+    /// until they run out or are stopped.
+    #[tracing::instrument(skip_all)]
+    pub async fn share_location(
+        &self,
+        room_id: &str,
+        sender_id: &str,
+        request: ShareLocationRequest,
+    ) -> Result<ChatMessage, CustomError> {
+        check_coordinates(request.lat, request.lng)?;
+        let label = request
+            .label
+            .map(|label| sanitize(&label, Markup::None))
+            .filter(|label| !label.trim().is_empty());
+        if label
+            .as_ref()
+            .is_some_and(|label| label.chars().count() > MAX_LOCATION_LABEL_CHARS)
+        {
+            return Err(CustomError::BadRequestError(format!(
+                "label must be at most {} characters",
+                MAX_LOCATION_LABEL_CHARS
+            )));
+        }
+        let live_until = match request.live_minutes {
+            Some(minutes) if (1..=MAX_LIVE_LOCATION_MINUTES).contains(&minutes) => {
+                Some(Utc::now() + chrono::Duration::minutes(minutes as i64))
+            }
+            Some(_) => {
+                return Err(CustomError::BadRequestError(format!(
+                    "A live location can be shared for 1 to {} minutes",
+                    MAX_LIVE_LOCATION_MINUTES
+                )));
+            }
+            None => None,
+        };
+
+        let location = SharedLocation {
+            lat: request.lat,
+            lng: request.lng,
+            label: label.clone(),
+            live_until,
+            updated_at: None,
+        };
+        self.insert_message(
+            room_id,
+            sender_id,
+            label.unwrap_or_else(|| "Location".to_string()),
+            MessageType::Location,
+            Attachments {
+                location: Some(location),
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    /// Move a live location the user shared. Fails once it has ended.
+    #[tracing::instrument(skip_all)]
+    pub async fn update_live_location(
+        &self,
+        message_id: &str,
+        user_id: &str,
+        lat: f64,
+        lng: f64,
+    ) -> Result<ChatMessage, CustomError> {
+        check_coordinates(lat, lng)?;
+        self.update_live(
+            message_id,
+            user_id,
+            doc! {
+                "location.lat": lat,
+                "location.lng": lng,
+                "location.updated_at": bson::DateTime::now(),
+            },
+        )
+        .await
+    }
+
+    /// End a live location before it runs out
+    #[tracing::instrument(skip_all)]
+    pub async fn stop_live_location(
+        &self,
+        message_id: &str,
+        user_id: &str,
+    ) -> Result<ChatMessage, CustomError> {
+        self.update_live(
+            message_id,
+            user_id,
+            doc! { "location.live_until": bson::DateTime::now() },
+        )
+        .await
+    }
+
+    /// Apply `set` to a live location of the user's that hasn't ended
+    async fn update_live(
+        &self,
+        message_id: &str,
+        user_id: &str,
+        set: Document,
+    ) -> Result<ChatMessage, CustomError> {
+        let not_found = || CustomError::NotFoundError("Live location not found".to_string());
+        let message_id = ObjectId::parse_str(message_id).map_err(|_| not_found())?;
+
+        let mut message = self
+            .messages
+            .find_one_and_update(
+                doc! {
+                    "_id": message_id,
+                    "sender_id": user_id,
+                    "location.live_until": { "$gt": bson::DateTime::now() },
+                },
+                doc! { "$set": set },
+            )
+            .return_document(ReturnDocument::After)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .ok_or_else(not_found)?;
+
+        // Updates reach the same people the message did
+        let shadow_banned = match ObjectId::parse_str(user_id) {
+            Ok(id) => self
+                .users
+                .find_one(doc! { "_id": id })
+                .projection(doc! { "username": 1, "shadow_banned": 1 })
+                .await
+                .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+                .is_some_and(|sender| sender.shadow_banned),
+            Err(_) => false,
+        };
+        message.shadow_banned = shadow_banned
+            || self
+                .blocked_in_direct_room(&message.room_id, user_id)
+                .await?;
+        Ok(message)
+    }
+
+    /// Whether a block stands between the two sides of a direct room
+    async fn blocked_in_direct_room(
+        &self,
+        room_id: &str,
+        sender_id: &str,
+    ) -> Result<bool, CustomError> {
+        match (
+            direct_room_participants(room_id),
+            ObjectId::parse_str(sender_id),
+        ) {
+            (Some((first, second)), Ok(sender)) => {
+                let other = if first == sender { second } else { first };
+                self.friends.is_blocked_between(&sender, &other).await
+            }
+            _ => Ok(false),
+        }
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn insert_message(
+        &self,
+        room_id: &str,
+        sender_id: &str,
+        content: String,
+        message_type: MessageType,
+        attachments: Attachments,
+    ) -> Result<ChatMessage, CustomError> {
+        // Messages keep the sender's name and badge as they were when sent
+        let sender = match ObjectId::parse_str(sender_id) {
+            Ok(id) => self
+                .users
+                .find_one(doc! { "_id": id })
+                .projection(doc! { "username": 1, "is_verified": 1, "shadow_banned": 1 })
+                .await
+                .map_err(|e| CustomError::InternalServerError(e.to_string()))?,
+            Err(_) => None,
+        };
+
+        // A block between the two sides of a direct room drops the message
+        let blocked = self.blocked_in_direct_room(room_id, sender_id).await?;
+
+        let mut message = ChatMessage {
+            id: None,
+            room_id: room_id.to_string(),
+            sender_id: sender_id.to_string(),
+            sender_username: sender.as_ref().map(|sender| sender.username.clone()),
+            sender_verified: sender.as_ref().is_some_and(|sender| sender.is_verified),
+            content,
+            message_type,
+            forwarded_from: attachments.forwarded_from,
+            poll: attachments.poll,
+            sticker: attachments.sticker,
+            location: attachments.location,
+            created_at: Utc::now(),
+            shadow_banned: blocked || sender.is_some_and(|sender| sender.shadow_banned),
+        };
+
+        let result = self
+            .messages
+            .clone_with_type::<StoredMessage>()
+            .insert_one(StoredMessage {
+                message: &message,
+                blocked,
+            })
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to save message: {}", e))
+            })?;
+        message.id = result.inserted_id.as_object_id();
+
+        // Only used to rank public rooms, so a failure just gets logged
+        if let Err(e) = self
+            .rooms
+            .update_one(
+                doc! { "room_id": room_id },
+                doc! { "$set": { "last_message_at": bson::DateTime::from_chrono(message.created_at) } },
+            )
+            .await
+        {
+            log::warn!("Failed to record room activity: {}", e);
+        }
+
+        Ok(message)
+    }
+
+    /// Delete every message a user sent in a room, returning the ids removed
+    #[tracing::instrument(skip_all)]
+    pub async fn purge_sender(
+        &self,
+        room_id: &str,
+        sender_id: &str,
+    ) -> Result<Vec<ObjectId>, CustomError> {
+        self.purge(doc! { "room_id": room_id, "sender_id": sender_id })
+            .await
+    }
+
+    /// Delete the messages sent in a room between `from` and `to`,
+    /// returning the ids removed
+    #[tracing::instrument(skip_all)]
+    pub async fn purge_range(
+        &self,
+        room_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<ObjectId>, CustomError> {
+        self.purge(doc! {
+            "room_id": room_id,
+            "created_at": {
+                "$gte": bson::DateTime::from_chrono(from),
+                "$lte": bson::DateTime::from_chrono(to),
+            },
+        })
+        .await
+    }
+
+    /// Ids are read first so clients can be told exactly what went
+    async fn purge(&self, filter: Document) -> Result<Vec<ObjectId>, CustomError> {
+        let ids: Vec<ObjectId> = self
+            .messages
+            .distinct("_id", filter)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .into_iter()
+            .filter_map(|id| id.as_object_id())
+            .collect();
+        if ids.is_empty() {
+            return Ok(ids);
+        }
+
+        self.messages
+            .delete_many(doc! { "_id": { "$in": &ids } })
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to delete messages: {}", e))
+            })?;
+
+        Ok(ids)
+    }
+
+    /// Image, video and file messages in a room, newest first, optionally
+    /// of one kind. Hidden like history.
+    #[tracing::instrument(skip_all)]
+    pub async fn list_media(
+        &self,
+        room_id: &str,
+        viewer: &ObjectId,
+        media_type: Option<MediaType>,
+        page: u64,
+        per_page: u64,
+    ) -> Result<Page<ChatMessage>, CustomError> {
+        let mut hidden = self.moderation.hidden_authors(Some(viewer)).await?;
+        hidden.extend(self.friends.blocked_ids(viewer).await?);
+        let hidden: Vec<String> = hidden.into_iter().map(|id| id.to_hex()).collect();
+        let types: Vec<&str> = match media_type {
+            Some(media_type) => vec![media_type.message_type().name()],
+            None => MediaType::ALL
+                .iter()
+                .map(|media_type| media_type.message_type().name())
+                .collect(),
+        };
+        let mut filter = doc! {
+            "room_id": room_id,
+            "message_type": { "$in": types },
+            "sender_id": { "$nin": hidden },
+        };
+        filter.insert("$or", unless_blocked(viewer));
+
+        let total = self
+            .messages
+            .count_documents(filter.clone())
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+        let items = self
+            .messages
+            .find(filter)
+            .sort(doc! { "created_at": -1 })
+            .skip((page - 1) * per_page)
+            .limit(per_page as i64)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        Ok(Page {
+            items,
+            page,
+            per_page,
+            total,
+        })
+    }
+
+    /// Messages in a room older than `before`, newest first. Messages from
+    /// shadow-banned senders, and those a block dropped, are only shown to
+    /// their senders; messages from users the viewer blocked aren't shown
+    /// at all.
+    #[tracing::instrument(skip_all)]
+    pub async fn list_messages(
+        &self,
+        room_id: &str,
+        viewer: &ObjectId,
+        before: Option<DateTime<Utc>>,
+        limit: i64,
+    ) -> Result<Vec<ChatMessage>, CustomError> {
+        let mut hidden = self.moderation.hidden_authors(Some(viewer)).await?;
+        hidden.extend(self.friends.blocked_ids(viewer).await?);
+        let hidden: Vec<String> = hidden.into_iter().map(|id| id.to_hex()).collect();
+        let mut filter = doc! { "room_id": room_id, "sender_id": { "$nin": hidden } };
+        filter.insert("$or", unless_blocked(viewer));
+        if let Some(before) = before {
+            filter.insert(
+                "created_at",
+                doc! { "$lt": bson::DateTime::from_chrono(before) },
+            );
+        }
+
+        let cursor = self
+            .messages
+            .find(filter)
+            .sort(doc! { "created_at": -1 })
+            .limit(limit)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        cursor
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))
+    }
+}
+
+/// Notify the other side of a direct room about a message, unless they
+/// are in the room to see it. Failures are only logged.
+pub async fn notify_direct_recipient(
+    server: &Addr<ChatServer>,
+    notifications: &NotificationService,
+    message: &ChatMessage,
+) {
+    let (Some((first, second)), Ok(sender_id)) = (
+        direct_room_participants(&message.room_id),
+        ObjectId::parse_str(&message.sender_id),
+    ) else {
+        return;
+    };
+    // Dropped messages never reach the recipient
+    if message.shadow_banned {
+        return;
+    }
+    let recipient = if first == sender_id { second } else { first };
+
+    let present = server
+        .send(InRoom {
+            room_id: message.room_id.clone(),
+            user_id: recipient.to_hex(),
+        })
+        .await
+        .unwrap_or(false);
+    if present {
+        return;
+    }
+
+    if let Err(e) = notifications
+        .notify_chat_message(
+            recipient,
+            Some(sender_id),
+            message.sender_username.as_deref(),
+            &message.content,
+            message.id,
+        )
+        .await
+    {
+        log::warn!("Failed to notify chat recipient: {}", e);
+    }
+}
@@ -1,17 +1,39 @@
-use actix::{Actor, ActorContext, Addr, AsyncContext, Handler, Running, StreamHandler};
+use actix::{
+    Actor, ActorContext, ActorFutureExt, Addr, AsyncContext, Handler, Running, StreamHandler,
+    WrapFuture,
+};
+use actix_web::web;
 use actix_web_actors::ws;
+use mongodb::bson::oid::ObjectId;
+use std::collections::HashSet;
 use std::time::{Duration, Instant};
 use uuid::Uuid;
 
-use crate::chat::model::{ClientMessage, ServerMessage};
+use crate::chat::model::{
+    Capability, ChatMessage, ClientMessage, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION, Poll,
+    ServerMessage, ShareLocationRequest,
+};
 use crate::chat::server::{
-    ChatServer, Connect, Disconnect, JoinRoom, LeaveRoom, RoomMessage, WsMessage,
+    Broadcast, CanPost, ChatServer, Connect, Disconnect, JoinRoom, LeaveRoom, Replay, RoomMessage,
+    SetReadOnly, SetSlowMode, SlowModeFor, WsMessage,
 };
+use crate::chat::service::{ChatService, notify_direct_recipient};
+use crate::database::RedisService;
+use crate::group::model::GROUP_ROOM_PREFIX;
+use crate::link_safety::service::LinkGuard;
+use crate::middleware::rate_limit::{CHAT_RATE_LIMIT, CHAT_RATE_WINDOW_SECONDS, check_slow_mode};
+use crate::notification::service::NotificationService;
+use crate::spam_guard::model::SpamAction;
+use crate::spam_guard::service::SpamGuard;
+use crate::utils::error::CustomError;
+use crate::utils::sanitize::{Markup, sanitize};
 
 /// How often heartbeat pings are sent
 const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
 /// How long before lack of client response causes a timeout
 const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Sent when a member who isn't the owner posts in a read-only room
+const READ_ONLY_ERROR: &str = "Only the room owner can post while the room is read-only";
 
 /// WebSocket session actor
 pub struct WsSession {
@@ -21,16 +43,48 @@ pub struct WsSession {
     pub user_id: String,
     /// Chat server address
     pub server_addr: Addr<ChatServer>,
+    /// Redis service (for rate limiting)
+    pub redis_service: RedisService,
+    /// Room access checks and message history
+    pub chat_service: web::Data<ChatService>,
+    /// Stricter limits for new accounts
+    pub spam_guard: web::Data<SpamGuard>,
+    /// Rejects malicious links and rewrites the rest
+    pub link_guard: web::Data<LinkGuard>,
+    /// Tells direct message recipients who aren't in the room
+    pub notifications: web::Data<NotificationService>,
+    /// Rooms this session has joined
+    pub rooms: HashSet<String>,
+    /// Protocol version agreed in the hello
+    pub protocol_version: u32,
+    /// Optional features agreed in the hello
+    pub capabilities: HashSet<Capability>,
     /// Last heartbeat timestamp
     pub last_heartbeat: Instant,
 }
 
 impl WsSession {
-    pub fn new(user_id: String, server_addr: Addr<ChatServer>) -> Self {
+    pub fn new(
+        user_id: String,
+        server_addr: Addr<ChatServer>,
+        redis_service: RedisService,
+        chat_service: web::Data<ChatService>,
+        spam_guard: web::Data<SpamGuard>,
+        link_guard: web::Data<LinkGuard>,
+        notifications: web::Data<NotificationService>,
+    ) -> Self {
         WsSession {
             session_id: Uuid::new_v4().to_string(),
             user_id,
             server_addr,
+            redis_service,
+            chat_service,
+            spam_guard,
+            link_guard,
+            notifications,
+            rooms: HashSet::new(),
+            protocol_version: MIN_PROTOCOL_VERSION,
+            capabilities: HashSet::new(),
             last_heartbeat: Instant::now(),
         }
     }
@@ -48,34 +102,538 @@ impl WsSession {
         });
     }
 
+    /// Join a room once the user's access is checked. Resumed joins are
+    /// also sent the room's events after `resume_after`.
+    fn join(
+        &mut self,
+        room_id: String,
+        resume_after: Option<u64>,
+        ctx: &mut ws::WebsocketContext<Self>,
+    ) {
+        let chat_service = self.chat_service.clone();
+        let user_id = self.user_id.clone();
+        let check_room = room_id.clone();
+        ctx.wait(
+            async move {
+                let allowed = chat_service.can_join(&check_room, &user_id).await;
+                // The server learns a room's mode from whoever joins it
+                let room = chat_service.get_room(&check_room).await.ok().flatten();
+                (allowed, room)
+            }
+            .into_actor(self)
+            .map(move |(result, room), act, ctx| {
+                if !matches!(result, Ok(true)) {
+                    let message = if room_id.starts_with(GROUP_ROOM_PREFIX) {
+                        "Only group members can join this room"
+                    } else {
+                        "You can't message this user"
+                    };
+                    act.send_message(
+                        &ServerMessage::Error {
+                            message: message.to_string(),
+                        },
+                        ctx,
+                    );
+                    return;
+                }
+                if let Some(room) = room {
+                    act.server_addr.do_send(SetSlowMode {
+                        room_id: room_id.clone(),
+                        owner_id: room.created_by.clone(),
+                        seconds: room.slow_mode_seconds,
+                    });
+                    act.server_addr.do_send(SetReadOnly {
+                        room_id: room_id.clone(),
+                        owner_id: room.created_by,
+                        mode: room.read_only,
+                    });
+                }
+                act.rooms.insert(room_id.clone());
+                act.server_addr.do_send(JoinRoom {
+                    session_id: act.session_id.clone(),
+                    room_id: room_id.clone(),
+                });
+                if let Some(after) = resume_after {
+                    act.server_addr.do_send(Replay {
+                        session_id: act.session_id.clone(),
+                        room_id,
+                        after,
+                    });
+                }
+            }),
+        );
+    }
+
     /// Handle incoming client message
     fn handle_message(&mut self, msg: ClientMessage, ctx: &mut ws::WebsocketContext<Self>) {
         match msg {
-            ClientMessage::Join { room_id } => {
-                self.server_addr.do_send(JoinRoom {
-                    session_id: self.session_id.clone(),
-                    room_id,
-                });
+            ClientMessage::Hello {
+                version,
+                capabilities,
+            } => {
+                if version < MIN_PROTOCOL_VERSION {
+                    self.send_message(
+                        &ServerMessage::Error {
+                            message: format!(
+                                "Protocol version {} is no longer supported, please update the app",
+                                version
+                            ),
+                        },
+                        ctx,
+                    );
+                    ctx.stop();
+                    return;
+                }
+                // Newer clients fall back to the features this server knows
+                let accepted = Capability::negotiate(&capabilities);
+                self.protocol_version = version.min(PROTOCOL_VERSION);
+                self.send_message(
+                    &ServerMessage::Welcome {
+                        version: self.protocol_version,
+                        min_version: MIN_PROTOCOL_VERSION,
+                        accepted: accepted.clone(),
+                    },
+                    ctx,
+                );
+                self.capabilities = accepted.into_iter().collect();
+            }
+            ClientMessage::Join { room_id } => self.join(room_id, None, ctx),
+            ClientMessage::Resume { rooms } => {
+                for (room_id, after) in rooms {
+                    self.join(room_id, Some(after), ctx);
+                }
             }
             ClientMessage::Leave { room_id } => {
+                self.rooms.remove(&room_id);
                 self.server_addr.do_send(LeaveRoom {
                     session_id: self.session_id.clone(),
                     room_id,
                 });
             }
             ClientMessage::Message { room_id, content } => {
-                let message = ServerMessage::Message {
+                let content = sanitize(&content, Markup::None);
+                if content.trim().is_empty() {
+                    return;
+                }
+
+                let redis_service = self.redis_service.clone();
+                let rate_key = format!("chat:{}", self.user_id);
+                let spam_guard = self.spam_guard.clone();
+                let link_guard = self.link_guard.clone();
+                let server_addr = self.server_addr.clone();
+                let can_post = CanPost {
                     room_id: room_id.clone(),
-                    sender_id: self.user_id.clone(),
-                    sender_username: None, // TODO: fetch username
-                    content,
-                    timestamp: chrono::Utc::now().to_rfc3339(),
+                    user_id: self.user_id.clone(),
                 };
-                self.server_addr.do_send(RoomMessage {
-                    room_id,
-                    sender_session_id: self.session_id.clone(),
-                    message,
-                });
+                let slow_room = room_id.clone();
+                let user_id = self.user_id.clone();
+                let sender_id = ObjectId::parse_str(&self.user_id).ok();
+                let checked = content.clone();
+
+                // Wait on the checks so messages keep their order
+                ctx.wait(
+                    async move {
+                        if !server_addr.send(can_post).await.unwrap_or(true) {
+                            return Err(ServerMessage::Error {
+                                message: READ_ONLY_ERROR.to_string(),
+                            });
+                        }
+                        if let Err(e) =
+                            slow_mode(&server_addr, &redis_service, &slow_room, &user_id).await
+                        {
+                            return Err(ServerMessage::from_error(&slow_room, &e));
+                        }
+                        let decision = redis_service
+                            .sliding_window_check(
+                                &rate_key,
+                                CHAT_RATE_LIMIT,
+                                CHAT_RATE_WINDOW_SECONDS,
+                            )
+                            .await;
+                        let spam = match sender_id {
+                            Some(sender_id) => {
+                                spam_guard
+                                    .check(&sender_id, SpamAction::ChatMessage, &checked)
+                                    .await
+                            }
+                            None => Ok(()),
+                        };
+                        let screened = link_guard.screen(&checked).await;
+                        Ok((decision, spam, screened))
+                    }
+                    .into_actor(self)
+                    .map(move |checks, act, ctx| {
+                        let (result, spam, screened) = match checks {
+                            Ok(checks) => checks,
+                            Err(message) => {
+                                act.send_message(&message, ctx);
+                                return;
+                            }
+                        };
+                        if let Ok(decision) = &result
+                            && !decision.allowed
+                        {
+                            let message = format!(
+                                "You are sending messages too quickly. Try again in {} seconds.",
+                                decision.retry_after_seconds
+                            );
+                            act.send_message(&ServerMessage::Error { message }, ctx);
+                            return;
+                        }
+                        match spam {
+                            Err(CustomError::InternalServerError(e)) => {
+                                log::warn!("Spam guard unavailable: {}", e);
+                            }
+                            Err(e) => {
+                                act.send_message(
+                                    &ServerMessage::Error {
+                                        message: e.to_string(),
+                                    },
+                                    ctx,
+                                );
+                                return;
+                            }
+                            Ok(()) => {}
+                        }
+                        let content = match screened {
+                            Ok(screened) => screened,
+                            Err(CustomError::InternalServerError(e)) => {
+                                log::warn!("Link safety check unavailable: {}", e);
+                                content
+                            }
+                            Err(e) => {
+                                act.send_message(
+                                    &ServerMessage::Error {
+                                        message: e.to_string(),
+                                    },
+                                    ctx,
+                                );
+                                return;
+                            }
+                        };
+
+                        // Messages in joined rooms are stored first, so the
+                        // broadcast carries the sender's name and badge
+                        if act.rooms.contains(&room_id) {
+                            let chat_service = act.chat_service.clone();
+                            let sender_id = act.user_id.clone();
+                            let fallback_room = room_id.clone();
+                            let fallback = ServerMessage::Message {
+                                message_id: None,
+                                room_id: room_id.clone(),
+                                sender_id: sender_id.clone(),
+                                sender_username: None,
+                                sender_verified: false,
+                                content: content.clone(),
+                                forwarded_from: None,
+                                poll: None,
+                                sticker: None,
+                                location: None,
+                                timestamp: chrono::Utc::now().to_rfc3339(),
+                            };
+                            ctx.spawn(
+                                async move {
+                                    chat_service
+                                        .save_message(&room_id, &sender_id, content)
+                                        .await
+                                }
+                                .into_actor(act)
+                                .map(move |result, act, _ctx| {
+                                    // Storage failures still deliver the message
+                                    let (room_id, message, visible_to) = match result {
+                                        Ok(saved) => {
+                                            let server_addr = act.server_addr.clone();
+                                            let notifications = act.notifications.clone();
+                                            let message = ServerMessage::from(&saved);
+                                            let visible_to = saved
+                                                .shadow_banned
+                                                .then(|| saved.sender_id.clone());
+                                            let room_id = saved.room_id.clone();
+                                            actix_web::rt::spawn(async move {
+                                                notify_direct_recipient(
+                                                    &server_addr,
+                                                    &notifications,
+                                                    &saved,
+                                                )
+                                                .await;
+                                            });
+                                            (room_id, message, visible_to)
+                                        }
+                                        Err(e) => {
+                                            log::warn!("Failed to store chat message: {}", e);
+                                            (fallback_room, fallback, None)
+                                        }
+                                    };
+                                    act.server_addr.do_send(RoomMessage {
+                                        room_id,
+                                        sender_session_id: act.session_id.clone(),
+                                        message,
+                                        visible_to,
+                                    });
+                                }),
+                            );
+                            return;
+                        }
+
+                        let message = ServerMessage::Message {
+                            message_id: None,
+                            room_id: room_id.clone(),
+                            sender_id: act.user_id.clone(),
+                            sender_username: None,
+                            sender_verified: false,
+                            content,
+                            forwarded_from: None,
+                            poll: None,
+                            sticker: None,
+                            location: None,
+                            timestamp: chrono::Utc::now().to_rfc3339(),
+                        };
+                        act.server_addr.do_send(RoomMessage {
+                            room_id,
+                            sender_session_id: act.session_id.clone(),
+                            message,
+                            visible_to: None,
+                        });
+                    }),
+                );
+            }
+            ClientMessage::Forward {
+                message_id,
+                target_room_id,
+            } => {
+                let Ok(user_id) = ObjectId::parse_str(&self.user_id) else {
+                    self.send_message(
+                        &ServerMessage::Error {
+                            message: "Sign in to forward messages".to_string(),
+                        },
+                        ctx,
+                    );
+                    return;
+                };
+                let redis_service = self.redis_service.clone();
+                let chat_service = self.chat_service.clone();
+                let server_addr = self.server_addr.clone();
+                let forwarder = self.user_id.clone();
+                let error_room = target_room_id.clone();
+
+                // Forwards count against the same budget as messages
+                ctx.wait(
+                    async move {
+                        check_posting(&server_addr, &redis_service, &target_room_id, &forwarder)
+                            .await?;
+                        chat_service
+                            .forward_message(&message_id, &target_room_id, &user_id)
+                            .await
+                    }
+                    .into_actor(self)
+                    .map(move |result, act, ctx| {
+                        let forwarded = match result {
+                            Ok(forwarded) => forwarded,
+                            Err(e) => {
+                                act.send_message(&ServerMessage::from_error(&error_room, &e), ctx);
+                                return;
+                            }
+                        };
+
+                        let message = ServerMessage::from(&forwarded);
+                        // The sender may forward to a room this session hasn't joined
+                        if !act.rooms.contains(&forwarded.room_id) {
+                            act.send_message(&message, ctx);
+                        }
+                        act.server_addr.do_send(Broadcast {
+                            room_id: forwarded.room_id.clone(),
+                            message,
+                            visible_to: forwarded
+                                .shadow_banned
+                                .then(|| forwarded.sender_id.clone()),
+                        });
+                    }),
+                );
+            }
+            ClientMessage::CreatePoll {
+                room_id,
+                question,
+                options,
+                multiple_choice,
+            } => {
+                if !self.rooms.contains(&room_id) {
+                    self.send_message(
+                        &ServerMessage::Error {
+                            message: "Join the room before starting a poll".to_string(),
+                        },
+                        ctx,
+                    );
+                    return;
+                }
+                let redis_service = self.redis_service.clone();
+                let chat_service = self.chat_service.clone();
+                let sender_id = self.user_id.clone();
+                let server_addr = self.server_addr.clone();
+                let error_room = room_id.clone();
+
+                // Polls count against the same budget as messages
+                ctx.wait(
+                    async move {
+                        check_posting(&server_addr, &redis_service, &room_id, &sender_id).await?;
+                        chat_service
+                            .create_poll(&room_id, &sender_id, &question, &options, multiple_choice)
+                            .await
+                    }
+                    .into_actor(self)
+                    .map(move |result, act, ctx| match result {
+                        Ok(poll) => act.server_addr.do_send(Broadcast {
+                            room_id: poll.room_id.clone(),
+                            message: ServerMessage::from(&poll),
+                            visible_to: poll.shadow_banned.then(|| poll.sender_id.clone()),
+                        }),
+                        Err(e) => {
+                            act.send_message(&ServerMessage::from_error(&error_room, &e), ctx)
+                        }
+                    }),
+                );
+            }
+            ClientMessage::Sticker {
+                room_id,
+                sticker_id,
+            } => {
+                if !self.rooms.contains(&room_id) {
+                    self.send_message(
+                        &ServerMessage::Error {
+                            message: "Join the room before sending stickers".to_string(),
+                        },
+                        ctx,
+                    );
+                    return;
+                }
+                let redis_service = self.redis_service.clone();
+                let chat_service = self.chat_service.clone();
+                let sender_id = self.user_id.clone();
+                let server_addr = self.server_addr.clone();
+                let error_room = room_id.clone();
+
+                // Stickers count against the same budget as messages
+                ctx.wait(
+                    async move {
+                        check_posting(&server_addr, &redis_service, &room_id, &sender_id).await?;
+                        chat_service
+                            .save_sticker(&room_id, &sender_id, &sticker_id)
+                            .await
+                    }
+                    .into_actor(self)
+                    .map(move |result, act, ctx| match result {
+                        Ok(message) => act.publish_saved(message),
+                        Err(e) => {
+                            act.send_message(&ServerMessage::from_error(&error_room, &e), ctx)
+                        }
+                    }),
+                );
+            }
+            ClientMessage::ShareLocation {
+                room_id,
+                lat,
+                lng,
+                label,
+                live_minutes,
+            } => {
+                if !self.rooms.contains(&room_id) {
+                    self.send_message(
+                        &ServerMessage::Error {
+                            message: "Join the room before sharing a location".to_string(),
+                        },
+                        ctx,
+                    );
+                    return;
+                }
+                let redis_service = self.redis_service.clone();
+                let chat_service = self.chat_service.clone();
+                let sender_id = self.user_id.clone();
+                let server_addr = self.server_addr.clone();
+                let error_room = room_id.clone();
+                let request = ShareLocationRequest {
+                    lat,
+                    lng,
+                    label,
+                    live_minutes,
+                };
+
+                // Locations count against the same budget as messages
+                ctx.wait(
+                    async move {
+                        check_posting(&server_addr, &redis_service, &room_id, &sender_id).await?;
+                        chat_service
+                            .share_location(&room_id, &sender_id, request)
+                            .await
+                    }
+                    .into_actor(self)
+                    .map(move |result, act, ctx| match result {
+                        Ok(message) => act.publish_saved(message),
+                        Err(e) => {
+                            act.send_message(&ServerMessage::from_error(&error_room, &e), ctx)
+                        }
+                    }),
+                );
+            }
+            ClientMessage::UpdateLocation {
+                message_id,
+                lat,
+                lng,
+            } => {
+                let chat_service = self.chat_service.clone();
+                let user_id = self.user_id.clone();
+                ctx.spawn(
+                    async move {
+                        chat_service
+                            .update_live_location(&message_id, &user_id, lat, lng)
+                            .await
+                    }
+                    .into_actor(self)
+                    .map(|result, act, ctx| act.publish_location(result, ctx)),
+                );
+            }
+            ClientMessage::StopLocation { message_id } => {
+                let chat_service = self.chat_service.clone();
+                let user_id = self.user_id.clone();
+                ctx.spawn(
+                    async move { chat_service.stop_live_location(&message_id, &user_id).await }
+                        .into_actor(self)
+                        .map(|result, act, ctx| act.publish_location(result, ctx)),
+                );
+            }
+            ClientMessage::Vote { message_id, option } => {
+                let chat_service = self.chat_service.clone();
+                let user_id = self.user_id.clone();
+                ctx.spawn(
+                    async move { chat_service.vote(&message_id, &user_id, option).await }
+                        .into_actor(self)
+                        .map(|result, act, ctx| {
+                            let poll = match result {
+                                Ok(message) => message,
+                                Err(e) => {
+                                    act.send_message(
+                                        &ServerMessage::Error {
+                                            message: e.to_string(),
+                                        },
+                                        ctx,
+                                    );
+                                    return;
+                                }
+                            };
+                            let (Some(message_id), Some(results)) =
+                                (poll.id, poll.poll.as_ref().map(Poll::results))
+                            else {
+                                return;
+                            };
+                            act.server_addr.do_send(Broadcast {
+                                room_id: poll.room_id.clone(),
+                                message: ServerMessage::PollUpdated {
+                                    room_id: poll.room_id,
+                                    message_id: message_id.to_hex(),
+                                    poll: results,
+                                },
+                                visible_to: None,
+                            });
+                        }),
+                );
             }
             ClientMessage::Typing { room_id } => {
                 let message = ServerMessage::UserTyping {
@@ -86,6 +644,7 @@ impl WsSession {
                     room_id,
                     sender_session_id: self.session_id.clone(),
                     message,
+                    visible_to: None,
                 });
             }
             ClientMessage::StopTyping { room_id } => {
@@ -97,6 +656,7 @@ impl WsSession {
                     room_id,
                     sender_session_id: self.session_id.clone(),
                     message,
+                    visible_to: None,
                 });
             }
             ClientMessage::Ping => {
@@ -105,12 +665,118 @@ impl WsSession {
         }
     }
 
+    /// Broadcast a stored message to its room and tell a direct message
+    /// recipient who isn't there
+    fn publish_saved(&self, message: ChatMessage) {
+        self.server_addr.do_send(Broadcast {
+            room_id: message.room_id.clone(),
+            message: ServerMessage::from(&message),
+            visible_to: message.shadow_banned.then(|| message.sender_id.clone()),
+        });
+        let server_addr = self.server_addr.clone();
+        let notifications = self.notifications.clone();
+        actix_web::rt::spawn(async move {
+            notify_direct_recipient(&server_addr, &notifications, &message).await;
+        });
+    }
+
+    /// Broadcast a live location change, or report why it failed
+    fn publish_location(
+        &self,
+        result: Result<ChatMessage, CustomError>,
+        ctx: &mut ws::WebsocketContext<Self>,
+    ) {
+        let message = match result {
+            Ok(message) => message,
+            Err(e) => {
+                self.send_message(
+                    &ServerMessage::Error {
+                        message: e.to_string(),
+                    },
+                    ctx,
+                );
+                return;
+            }
+        };
+        if let Some(update) = ServerMessage::location_updated(&message) {
+            self.server_addr.do_send(Broadcast {
+                room_id: message.room_id,
+                message: update,
+                visible_to: message.shadow_banned.then_some(message.sender_id),
+            });
+        }
+    }
+
     /// Send message to WebSocket client
     fn send_message(&self, msg: &ServerMessage, ctx: &mut ws::WebsocketContext<Self>) {
-        if let Ok(json) = serde_json::to_string(msg) {
+        if self.binary() {
+            match rmp_serde::to_vec_named(msg) {
+                Ok(bytes) => ctx.binary(bytes),
+                Err(e) => log::warn!("Failed to encode WebSocket message: {}", e),
+            }
+        } else if let Ok(json) = serde_json::to_string(msg) {
             ctx.text(json);
         }
     }
+
+    /// Whether this session agreed to MessagePack frames
+    fn binary(&self) -> bool {
+        self.capabilities.contains(&Capability::BinaryEncoding)
+    }
+}
+
+/// Checks for posts other than plain messages: the room's read-only mode,
+/// the sender's message budget and the room's slow mode
+async fn check_posting(
+    server_addr: &Addr<ChatServer>,
+    redis_service: &RedisService,
+    room_id: &str,
+    user_id: &str,
+) -> Result<(), CustomError> {
+    let can_post = CanPost {
+        room_id: room_id.to_string(),
+        user_id: user_id.to_string(),
+    };
+    if !server_addr.send(can_post).await.unwrap_or(true) {
+        return Err(CustomError::ForbiddenError(READ_ONLY_ERROR.to_string()));
+    }
+    let decision = redis_service
+        .sliding_window_check(
+            &format!("chat:{}", user_id),
+            CHAT_RATE_LIMIT,
+            CHAT_RATE_WINDOW_SECONDS,
+        )
+        .await;
+    if let Ok(decision) = &decision
+        && !decision.allowed
+    {
+        return Err(CustomError::TooManyRequestsError(format!(
+            "You are sending messages too quickly. Try again in {} seconds.",
+            decision.retry_after_seconds
+        )));
+    }
+    slow_mode(server_addr, redis_service, room_id, user_id).await
+}
+
+/// Hold the user to the room's slow mode, if it has one
+async fn slow_mode(
+    server_addr: &Addr<ChatServer>,
+    redis_service: &RedisService,
+    room_id: &str,
+    user_id: &str,
+) -> Result<(), CustomError> {
+    let seconds = server_addr
+        .send(SlowModeFor {
+            room_id: room_id.to_string(),
+            user_id: user_id.to_string(),
+        })
+        .await
+        .ok()
+        .flatten();
+    match seconds {
+        Some(seconds) => check_slow_mode(redis_service, room_id, user_id, seconds).await,
+        None => Ok(()),
+    }
 }
 
 impl Actor for WsSession {
@@ -145,7 +811,19 @@ impl Handler<WsMessage> for WsSession {
     type Result = ();
 
     fn handle(&mut self, msg: WsMessage, ctx: &mut Self::Context) {
-        ctx.text(msg.0);
+        if !self.binary() {
+            ctx.text(msg.0);
+            return;
+        }
+        // The chat server encodes once for every session; re-encode here,
+        // keeping fields such as `seq` that aren't part of the message
+        let encoded = serde_json::from_str::<serde_json::Value>(&msg.0)
+            .map_err(|e| e.to_string())
+            .and_then(|value| rmp_serde::to_vec_named(&value).map_err(|e| e.to_string()));
+        match encoded {
+            Ok(bytes) => ctx.binary(bytes),
+            Err(e) => log::warn!("Failed to encode WebSocket message: {}", e),
+        }
     }
 }
 
@@ -179,8 +857,27 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WsSession {
                     }
                 }
             }
-            Ok(ws::Message::Binary(_)) => {
-                log::warn!("Binary messages not supported");
+            Ok(ws::Message::Binary(bytes)) => {
+                self.last_heartbeat = Instant::now();
+
+                if !self.binary() {
+                    log::warn!("Binary message before binary encoding was agreed");
+                    return;
+                }
+                match rmp_serde::from_slice::<ClientMessage>(&bytes) {
+                    Ok(client_msg) => {
+                        self.handle_message(client_msg, ctx);
+                    }
+                    Err(e) => {
+                        log::warn!("Failed to parse WebSocket message: {}", e);
+                        self.send_message(
+                            &ServerMessage::Error {
+                                message: format!("Invalid message format: {}", e),
+                            },
+                            ctx,
+                        );
+                    }
+                }
             }
             Ok(ws::Message::Close(reason)) => {
                 log::info!("WebSocket close: {:?}", reason);
@@ -1,135 +1,281 @@
-use crate::comment::model::{CreateCommentRequest, UpdateCommentRequest};
-use crate::comment::service::CommentService;
-use crate::middleware::auth::get_user_id_from_request;
-use crate::utils::error::CustomError;
-use actix_web::{HttpRequest, HttpResponse, web};
+use crate::comment::dto::{CommentDto, CommentNodeDto};
+use crate::comment::model::{
+    CommentDraft, CommentPageQuery, CommentTreeQuery, CreateCommentRequest, SaveCommentDraftRequest,
+    UpdateCommentRequest,
+};
+use crate::comment::service::CommentServiceTrait;
+use crate::database::{RedisService, RedisServiceTrait};
+use crate::leaderboard::service::LeaderboardServiceTrait;
+use crate::link_safety::service::LinkGuardTrait;
+use crate::middleware::auth::AuthUser;
+use crate::middleware::rate_limit::{
+    COMMENT_RATE_LIMIT, COMMENT_RATE_WINDOW_SECONDS, check_rate_limit,
+};
+use crate::moderation::service::{ModerationService, ModerationServiceTrait};
+use crate::notification::model::NotificationKind;
+use crate::notification::service::NotificationServiceTrait;
+use crate::post::post_controller::invalidate_post_detail;
+use crate::post::post_service::PostServiceTrait;
+use crate::spam_guard::model::SpamAction;
+use crate::spam_guard::service::SpamGuardTrait;
+use crate::subscription::service::SubscriptionServiceTrait;
+use crate::utils::error::{CustomError, ErrorCode};
+use crate::utils::i18n::Locale;
+use crate::utils::response::ApiResponse;
+use crate::utils::validation::ValidatedJson;
+use actix_web::{HttpResponse, web};
 use mongodb::bson::oid::ObjectId;
 use serde_json::json;
 
+/// How long an untouched comment draft is kept
+const COMMENT_DRAFT_TTL_SECONDS: u64 = 7 * 24 * 60 * 60;
+
+/// Cache key for a user's draft on a post
+fn comment_draft_key(user_id: &ObjectId, post_id: &ObjectId) -> String {
+    format!("comment_draft:{}:{}", user_id.to_hex(), post_id.to_hex())
+}
+
 /// Create a new comment on a post
 /// POST /comments
-pub async fn create_comment(
-    req: HttpRequest,
-    comment_service: web::Data<CommentService>,
-    body: web::Json<CreateCommentRequest>,
+pub async fn create_comment<
+    C: CommentServiceTrait,
+    P: PostServiceTrait,
+    N: NotificationServiceTrait,
+    B: LeaderboardServiceTrait,
+    R: RedisServiceTrait,
+    S: SpamGuardTrait,
+    L: LinkGuardTrait,
+    M: ModerationServiceTrait,
+    U: SubscriptionServiceTrait,
+>(
+    locale: Locale,
+    auth_user: AuthUser,
+    comment_service: web::Data<C>,
+    post_service: web::Data<P>,
+    notification_service: web::Data<N>,
+    leaderboard_service: web::Data<B>,
+    redis_service: web::Data<R>,
+    spam_guard: web::Data<S>,
+    link_guard: web::Data<L>,
+    moderation_service: web::Data<M>,
+    subscription_service: web::Data<U>,
+    body: ValidatedJson<CreateCommentRequest>,
 ) -> Result<HttpResponse, CustomError> {
     // Get user ID from auth middleware
-    let user_id_str = get_user_id_from_request(&req)
-        .ok_or_else(|| CustomError::UnauthorizedError("Not authenticated".to_string()))?;
+    let author_id = auth_user.id;
 
-    let author_id = ObjectId::parse_str(&user_id_str)
-        .map_err(|_| CustomError::BadRequestError("Invalid user ID".to_string()))?;
+    check_rate_limit(
+        redis_service.get_ref(),
+        &format!("comment:{}", auth_user.id),
+        COMMENT_RATE_LIMIT,
+        COMMENT_RATE_WINDOW_SECONDS,
+    )
+    .await?;
+    spam_guard
+        .check(&author_id, SpamAction::Comment, &body.content)
+        .await?;
+    let content = link_guard.screen(&body.content).await?;
 
     let post_id = ObjectId::parse_str(&body.post_id)
         .map_err(|_| CustomError::BadRequestError("Invalid post ID".to_string()))?;
+    let parent_id = match &body.parent_id {
+        Some(parent_id) => Some(
+            reply_parent(
+                comment_service.get_ref(),
+                moderation_service.get_ref(),
+                &post_id,
+                &author_id,
+                parent_id,
+            )
+            .await?,
+        ),
+        None => None,
+    };
+
+    let comment_id = comment_service
+        .add_comment(post_id, parent_id, author_id, None, content)
+        .await?;
+    invalidate_post_detail(redis_service.get_ref(), &post_id.to_hex()).await;
+    leaderboard_service.record_comment(&author_id).await;
+    if let Err(e) = redis_service
+        .cache_delete(&comment_draft_key(&author_id, &post_id))
+        .await
+    {
+        log::warn!("Failed to clear comment draft: {}", e);
+    }
+
+    // Notify the post's subscribers, the author included unless they muted
+    // it. A shadow-banned commenter's comment is invisible to them, so
+    // nobody is told.
+    if let Some(post) = post_service.get_post(&post_id.to_hex()).await?
+        && !moderation_service.is_shadow_banned(&author_id).await?
+    {
+        let subscribers = subscription_service
+            .subscribers(&post.id, &post.author_id)
+            .await?;
+        for user_id in subscribers.into_iter().filter(|id| *id != author_id) {
+            let message = if user_id == post.author_id {
+                "Someone commented on your post"
+            } else {
+                "Someone commented on a post you follow"
+            };
+            if let Err(e) = notification_service
+                .notify(
+                    user_id,
+                    Some(author_id),
+                    NotificationKind::Comment,
+                    message.to_string(),
+                    Some(post_id),
+                )
+                .await
+            {
+                log::warn!("Failed to notify subscriber of new comment: {}", e);
+            }
+        }
+    }
+
+    Ok(ApiResponse::created(locale.t("comment-created"))
+        .data(json!({ "comment_id": comment_id.to_hex() }))
+        .into())
+}
+
+/// A comment the author may reply to: visible to them and on the same post
+async fn reply_parent<C: CommentServiceTrait, M: ModerationServiceTrait>(
+    comment_service: &C,
+    moderation_service: &M,
+    post_id: &ObjectId,
+    author_id: &ObjectId,
+    parent_id: &str,
+) -> Result<ObjectId, CustomError> {
+    let parent_id = ObjectId::parse_str(parent_id)
+        .map_err(|_| CustomError::BadRequestError("Invalid parent comment ID".to_string()))?;
+    let hidden = moderation_service.hidden_authors(Some(author_id)).await?;
+    let parent = comment_service
+        .get_comment_by_id(&parent_id)
+        .await?
+        .filter(|parent| !hidden.contains(&parent.author_id))
+        .ok_or_else(|| CustomError::NotFoundError("Parent comment not found".to_string()))?;
 
-    if body.content.trim().is_empty() {
+    if parent.post_id != *post_id {
         return Err(CustomError::BadRequestError(
-            "Comment content cannot be empty".to_string(),
+            "Replies must be on the same post as their parent".to_string(),
         ));
     }
+    Ok(parent_id)
+}
 
-    let comment_id = comment_service
-        .add_comment(post_id, author_id, None, body.content.clone())
+/// A page of a post's comments, oldest first
+/// GET /comments/post/{post_id}?page=1&per_page=50
+pub async fn get_post_comments<C: CommentServiceTrait>(
+    locale: Locale,
+    auth_user: Option<AuthUser>,
+    comment_service: web::Data<C>,
+    moderation_service: web::Data<ModerationService>,
+    path: web::Path<String>,
+    query: web::Query<CommentPageQuery>,
+) -> Result<HttpResponse, CustomError> {
+    let post_id = ObjectId::parse_str(path.into_inner())
+        .map_err(|_| CustomError::BadRequestError("Invalid post ID".to_string()))?;
+
+    let hidden = moderation_service
+        .hidden_authors(auth_user.as_ref().map(|user| &user.id))
+        .await?;
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query.per_page.unwrap_or(50).clamp(1, 100);
+    let comments = comment_service
+        .get_comments_for_post(&post_id, &hidden, page, per_page)
         .await?;
 
-    Ok(HttpResponse::Created().json(json!({
-        "success": true,
-        "message": "Comment created successfully",
-        "httpStatusCode": 201,
-        "comment_id": comment_id.to_hex()
-    })))
+    Ok(ApiResponse::ok(locale.t("comments-fetched"))
+        .data(comments.map(CommentDto::from))
+        .into())
 }
 
-/// Get all comments for a post
-/// GET /comments/post/{post_id}
-pub async fn get_post_comments(
-    comment_service: web::Data<CommentService>,
+/// Get a post's comments as a nested tree with reply counts
+/// GET /comments/post/{post_id}/tree?depth=3&top_replies=3
+pub async fn get_comment_tree<C: CommentServiceTrait>(
+    locale: Locale,
+    auth_user: Option<AuthUser>,
+    comment_service: web::Data<C>,
+    moderation_service: web::Data<ModerationService>,
     path: web::Path<String>,
+    query: web::Query<CommentTreeQuery>,
 ) -> Result<HttpResponse, CustomError> {
     let post_id = ObjectId::parse_str(path.into_inner())
         .map_err(|_| CustomError::BadRequestError("Invalid post ID".to_string()))?;
+    let depth = query.depth.unwrap_or(3).min(10);
+    let top_replies = query.top_replies.unwrap_or(3).min(50);
 
-    let comments = comment_service.get_comments_for_post(&post_id).await?;
-    let count = comments.len();
+    let hidden = moderation_service
+        .hidden_authors(auth_user.as_ref().map(|user| &user.id))
+        .await?;
+    let tree = comment_service
+        .get_comment_tree(&post_id, &hidden, depth, top_replies)
+        .await?;
 
-    Ok(HttpResponse::Ok().json(json!({
-        "success": true,
-        "message": "Comments retrieved successfully",
-        "httpStatusCode": 200,
-        "count": count,
-        "data": comments
-    })))
+    Ok(ApiResponse::ok(locale.t("comments-fetched"))
+        .list(tree.into_iter().map(CommentNodeDto::from).collect())
+        .into())
 }
 
 /// Get a single comment by ID
 /// GET /comments/{comment_id}
-pub async fn get_comment(
-    comment_service: web::Data<CommentService>,
+pub async fn get_comment<C: CommentServiceTrait>(
+    locale: Locale,
+    auth_user: Option<AuthUser>,
+    comment_service: web::Data<C>,
+    moderation_service: web::Data<ModerationService>,
     path: web::Path<String>,
 ) -> Result<HttpResponse, CustomError> {
     let comment_id = ObjectId::parse_str(path.into_inner())
         .map_err(|_| CustomError::BadRequestError("Invalid comment ID".to_string()))?;
 
+    let hidden = moderation_service
+        .hidden_authors(auth_user.as_ref().map(|user| &user.id))
+        .await?;
     let comment = comment_service
         .get_comment_by_id(&comment_id)
         .await?
-        .ok_or_else(|| CustomError::NotFoundError("Comment not found".to_string()))?;
-
-    Ok(HttpResponse::Ok().json(json!({
-        "success": true,
-        "message": "Comment retrieved successfully",
-        "httpStatusCode": 200,
-        "data": comment
-    })))
+        .filter(|comment| !hidden.contains(&comment.author_id))
+        .ok_or_else(|| CustomError::coded(ErrorCode::CommentNotFound, "Comment not found"))?;
+
+    Ok(ApiResponse::ok(locale.t("comment-fetched"))
+        .data(CommentDto::from(comment))
+        .into())
 }
 
 /// Update a comment
 /// PUT /comments/{comment_id}
-pub async fn update_comment(
-    req: HttpRequest,
-    comment_service: web::Data<CommentService>,
+pub async fn update_comment<C: CommentServiceTrait>(
+    locale: Locale,
+    auth_user: AuthUser,
+    comment_service: web::Data<C>,
+    link_guard: web::Data<LinkGuard>,
     path: web::Path<String>,
-    body: web::Json<UpdateCommentRequest>,
+    body: ValidatedJson<UpdateCommentRequest>,
 ) -> Result<HttpResponse, CustomError> {
-    let user_id_str = get_user_id_from_request(&req)
-        .ok_or_else(|| CustomError::UnauthorizedError("Not authenticated".to_string()))?;
-
-    let author_id = ObjectId::parse_str(&user_id_str)
-        .map_err(|_| CustomError::BadRequestError("Invalid user ID".to_string()))?;
+    let author_id = auth_user.id;
 
     let comment_id = ObjectId::parse_str(path.into_inner())
         .map_err(|_| CustomError::BadRequestError("Invalid comment ID".to_string()))?;
-
-    if body.content.trim().is_empty() {
-        return Err(CustomError::BadRequestError(
-            "Comment content cannot be empty".to_string(),
-        ));
-    }
+    let content = link_guard.screen(&body.content).await?;
 
     comment_service
-        .update_comment(&comment_id, &author_id, body.content.clone())
+        .update_comment(&comment_id, &author_id, content, body.version)
         .await?;
 
-    Ok(HttpResponse::Ok().json(json!({
-        "success": true,
-        "message": "Comment updated successfully",
-        "httpStatusCode": 200
-    })))
+    Ok(ApiResponse::ok(locale.t("comment-updated")).into())
 }
 
 /// Delete a comment
 /// DELETE /comments/{comment_id}
-pub async fn delete_comment(
-    req: HttpRequest,
-    comment_service: web::Data<CommentService>,
+pub async fn delete_comment<C: CommentServiceTrait>(
+    locale: Locale,
+    auth_user: AuthUser,
+    comment_service: web::Data<C>,
     path: web::Path<String>,
 ) -> Result<HttpResponse, CustomError> {
-    let user_id_str = get_user_id_from_request(&req)
-        .ok_or_else(|| CustomError::UnauthorizedError("Not authenticated".to_string()))?;
-
-    let author_id = ObjectId::parse_str(&user_id_str)
-        .map_err(|_| CustomError::BadRequestError("Invalid user ID".to_string()))?;
+    let author_id = auth_user.id;
 
     let comment_id = ObjectId::parse_str(path.into_inner())
         .map_err(|_| CustomError::BadRequestError("Invalid comment ID".to_string()))?;
@@ -138,28 +284,376 @@ pub async fn delete_comment(
         .delete_comment(&comment_id, &author_id)
         .await?;
 
-    Ok(HttpResponse::Ok().json(json!({
-        "success": true,
-        "message": "Comment deleted successfully",
-        "httpStatusCode": 200
-    })))
+    Ok(ApiResponse::ok(locale.t("comment-deleted")).into())
 }
 
 /// Get comment count for a post
 /// GET /comments/count/{post_id}
-pub async fn get_comment_count(
-    comment_service: web::Data<CommentService>,
+pub async fn get_comment_count<C: CommentServiceTrait>(
+    locale: Locale,
+    auth_user: Option<AuthUser>,
+    comment_service: web::Data<C>,
+    moderation_service: web::Data<ModerationService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    let post_id = ObjectId::parse_str(path.into_inner())
+        .map_err(|_| CustomError::BadRequestError("Invalid post ID".to_string()))?;
+
+    let hidden = moderation_service
+        .hidden_authors(auth_user.as_ref().map(|user| &user.id))
+        .await?;
+    let count = comment_service.get_comment_count(&post_id, &hidden).await?;
+
+    Ok(ApiResponse::ok(locale.t("comment-count-fetched"))
+        .data(json!({ "count": count }))
+        .into())
+}
+
+/// Get the current user's draft comment on a post; `null` when there is none
+/// GET /comments/drafts/{post_id}
+pub async fn get_draft(
+    locale: Locale,
+    auth_user: AuthUser,
+    redis_service: web::Data<RedisService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    let post_id = ObjectId::parse_str(path.into_inner())
+        .map_err(|_| CustomError::BadRequestError("Invalid post ID".to_string()))?;
+
+    let draft: Option<CommentDraft> = redis_service
+        .cache_get_json(&comment_draft_key(&auth_user.id, &post_id))
+        .await
+        .map_err(CustomError::InternalServerError)?;
+
+    Ok(ApiResponse::ok(locale.t("comment-draft-fetched"))
+        .data(draft)
+        .into())
+}
+
+/// Save the current user's draft comment on a post, replacing any earlier one
+/// PUT /comments/drafts/{post_id}
+pub async fn save_draft<P: PostServiceTrait>(
+    locale: Locale,
+    auth_user: AuthUser,
+    post_service: web::Data<P>,
+    redis_service: web::Data<RedisService>,
+    path: web::Path<String>,
+    body: ValidatedJson<SaveCommentDraftRequest>,
+) -> Result<HttpResponse, CustomError> {
+    let post_id = ObjectId::parse_str(path.into_inner())
+        .map_err(|_| CustomError::BadRequestError("Invalid post ID".to_string()))?;
+    post_service
+        .get_post(&post_id.to_hex())
+        .await?
+        .ok_or_else(|| CustomError::coded(ErrorCode::PostNotFound, "Post not found"))?;
+
+    let body = body.into_inner();
+    let draft = CommentDraft {
+        post_id: post_id.to_hex(),
+        content: body.content,
+        reply_to: body.reply_to,
+        updated_at: chrono::Utc::now(),
+    };
+    redis_service
+        .cache_set_json(
+            &comment_draft_key(&auth_user.id, &post_id),
+            &draft,
+            COMMENT_DRAFT_TTL_SECONDS,
+        )
+        .await
+        .map_err(CustomError::InternalServerError)?;
+
+    Ok(ApiResponse::ok(locale.t("comment-draft-saved"))
+        .data(draft)
+        .into())
+}
+
+/// Discard the current user's draft comment on a post
+/// DELETE /comments/drafts/{post_id}
+pub async fn delete_draft(
+    locale: Locale,
+    auth_user: AuthUser,
+    redis_service: web::Data<RedisService>,
     path: web::Path<String>,
 ) -> Result<HttpResponse, CustomError> {
     let post_id = ObjectId::parse_str(path.into_inner())
         .map_err(|_| CustomError::BadRequestError("Invalid post ID".to_string()))?;
 
-    let count = comment_service.get_comment_count(&post_id).await?;
+    redis_service
+        .cache_delete(&comment_draft_key(&auth_user.id, &post_id))
+        .await
+        .map_err(CustomError::InternalServerError)?;
+
+    Ok(ApiResponse::ok(locale.t("comment-draft-deleted")).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comment::model::{Comment, CommentNode};
+    use crate::database::Page;
+    use crate::post::post_controller::post_detail_cache_key;
+    use crate::post::post_model::Post;
+    use crate::user::model::Role;
+    use crate::utils::mocks::{
+        MockModeration, MockNotifications, MockPosts, MockRedis, MockSubscriptions, NoOp,
+        PassThrough, not_mocked, post_by,
+    };
+    use actix_web::http::StatusCode;
+    use std::sync::Mutex;
+
+    /// Records additions and deletions instead of touching MongoDB
+    #[derive(Default)]
+    struct MockComments {
+        added: Mutex<Vec<(ObjectId, ObjectId, String)>>,
+        deleted: Mutex<Vec<(ObjectId, ObjectId)>>,
+    }
+
+    impl CommentServiceTrait for MockComments {
+        async fn add_comment(
+            &self,
+            post_id: ObjectId,
+            _parent_id: Option<ObjectId>,
+            author_id: ObjectId,
+            _author_username: Option<String>,
+            content: String,
+        ) -> Result<ObjectId, CustomError> {
+            self.added
+                .lock()
+                .unwrap()
+                .push((post_id, author_id, content));
+            Ok(ObjectId::new())
+        }
+
+        async fn get_comments_for_post(
+            &self,
+            _post_id: &ObjectId,
+            _hidden_authors: &[ObjectId],
+            _page: u64,
+            _per_page: u64,
+        ) -> Result<Page<Comment>, CustomError> {
+            not_mocked()
+        }
+
+        async fn get_comment_tree(
+            &self,
+            _post_id: &ObjectId,
+            _hidden_authors: &[ObjectId],
+            _depth: usize,
+            _top_replies: usize,
+        ) -> Result<Vec<CommentNode>, CustomError> {
+            not_mocked()
+        }
+
+        async fn get_comment_by_id(
+            &self,
+            _comment_id: &ObjectId,
+        ) -> Result<Option<Comment>, CustomError> {
+            not_mocked()
+        }
+
+        async fn update_comment(
+            &self,
+            _comment_id: &ObjectId,
+            _author_id: &ObjectId,
+            _content: String,
+            _expected_version: i64,
+        ) -> Result<bool, CustomError> {
+            not_mocked()
+        }
+
+        async fn delete_comment(
+            &self,
+            comment_id: &ObjectId,
+            author_id: &ObjectId,
+        ) -> Result<bool, CustomError> {
+            self.deleted.lock().unwrap().push((*comment_id, *author_id));
+            Ok(true)
+        }
+
+        async fn delete_comments_by_author(
+            &self,
+            _author_id: &ObjectId,
+        ) -> Result<u64, CustomError> {
+            not_mocked()
+        }
+
+        async fn get_comment_count(
+            &self,
+            _post_id: &ObjectId,
+            _hidden_authors: &[ObjectId],
+        ) -> Result<u64, CustomError> {
+            not_mocked()
+        }
+    }
+
+    fn caller() -> AuthUser {
+        AuthUser {
+            id: ObjectId::new(),
+            role: Role::User,
+        }
+    }
+
+    /// Collaborators for `create_comment` around a single post, which
+    /// `subscribers` follow
+    struct CommentFixture {
+        comments: web::Data<MockComments>,
+        posts: web::Data<MockPosts>,
+        notifications: web::Data<MockNotifications>,
+        redis: web::Data<MockRedis>,
+        moderation: web::Data<MockModeration>,
+        subscriptions: web::Data<MockSubscriptions>,
+    }
+
+    impl CommentFixture {
+        fn new(post: Post, subscribers: Vec<ObjectId>) -> Self {
+            CommentFixture {
+                comments: web::Data::new(MockComments::default()),
+                posts: web::Data::new(MockPosts::with(vec![post])),
+                notifications: web::Data::new(MockNotifications::default()),
+                redis: web::Data::new(MockRedis::default()),
+                moderation: web::Data::new(MockModeration::default()),
+                subscriptions: web::Data::new(MockSubscriptions { subscribers }),
+            }
+        }
 
-    Ok(HttpResponse::Ok().json(json!({
-        "success": true,
-        "message": "Comment count retrieved successfully",
-        "httpStatusCode": 200,
-        "count": count
-    })))
+        async fn comment(
+            &self,
+            auth_user: AuthUser,
+            post_id: &str,
+        ) -> Result<HttpResponse, CustomError> {
+            create_comment(
+                Locale::default(),
+                auth_user,
+                self.comments.clone(),
+                self.posts.clone(),
+                self.notifications.clone(),
+                web::Data::new(NoOp),
+                self.redis.clone(),
+                web::Data::new(PassThrough),
+                web::Data::new(PassThrough),
+                self.moderation.clone(),
+                self.subscriptions.clone(),
+                ValidatedJson(CreateCommentRequest {
+                    post_id: post_id.to_string(),
+                    parent_id: None,
+                    content: "Nice post".to_string(),
+                }),
+            )
+            .await
+        }
+    }
+
+    #[actix_web::test]
+    async fn create_comment_notifies_subscribers_except_the_commenter() {
+        let auth_user = caller();
+        let (author, follower) = (ObjectId::new(), ObjectId::new());
+        let post = post_by(author);
+        let post_id = post.id;
+        let fixture = CommentFixture::new(post, vec![author, follower, auth_user.id]);
+
+        let res = fixture
+            .comment(auth_user, &post_id.to_hex())
+            .await
+            .expect("create_comment failed");
+
+        assert_eq!(res.status(), StatusCode::CREATED);
+        assert_eq!(
+            *fixture.comments.added.lock().unwrap(),
+            vec![(post_id, auth_user.id, "Nice post".to_string())]
+        );
+        assert_eq!(
+            *fixture.notifications.sent.lock().unwrap(),
+            vec![author, follower]
+        );
+        assert!(
+            fixture
+                .redis
+                .deleted
+                .lock()
+                .unwrap()
+                .contains(&post_detail_cache_key(&post_id.to_hex()))
+        );
+    }
+
+    #[actix_web::test]
+    async fn create_comment_by_shadow_banned_user_notifies_nobody() {
+        let auth_user = caller();
+        let author = ObjectId::new();
+        let post = post_by(author);
+        let post_id = post.id;
+        let mut fixture = CommentFixture::new(post, vec![author]);
+        fixture.moderation = web::Data::new(MockModeration {
+            shadow_banned: vec![auth_user.id],
+        });
+
+        fixture
+            .comment(auth_user, &post_id.to_hex())
+            .await
+            .expect("create_comment failed");
+
+        assert_eq!(fixture.comments.added.lock().unwrap().len(), 1);
+        assert!(fixture.notifications.sent.lock().unwrap().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn create_comment_is_rate_limited() {
+        let post = post_by(ObjectId::new());
+        let post_id = post.id;
+        let mut fixture = CommentFixture::new(post, Vec::new());
+        fixture.redis = web::Data::new(MockRedis::limited());
+
+        let result = fixture.comment(caller(), &post_id.to_hex()).await;
+
+        assert!(matches!(result, Err(CustomError::TooManyRequestsError(_))));
+        assert!(fixture.comments.added.lock().unwrap().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn create_comment_rejects_malformed_post_ids() {
+        let fixture = CommentFixture::new(post_by(ObjectId::new()), Vec::new());
+
+        let result = fixture.comment(caller(), "not-an-id").await;
+
+        assert!(matches!(result, Err(CustomError::BadRequestError(_))));
+        assert!(fixture.comments.added.lock().unwrap().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn delete_comment_acts_as_the_caller() {
+        let comments = web::Data::new(MockComments::default());
+        let auth_user = caller();
+        let comment_id = ObjectId::new();
+
+        let res = delete_comment(
+            Locale::default(),
+            auth_user,
+            comments.clone(),
+            web::Path::from(comment_id.to_hex()),
+        )
+        .await
+        .expect("delete_comment failed");
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            *comments.deleted.lock().unwrap(),
+            vec![(comment_id, auth_user.id)]
+        );
+    }
+
+    #[actix_web::test]
+    async fn delete_comment_rejects_malformed_ids() {
+        let comments = web::Data::new(MockComments::default());
+
+        let result = delete_comment(
+            Locale::default(),
+            caller(),
+            comments.clone(),
+            web::Path::from("not-an-id".to_string()),
+        )
+        .await;
+
+        assert!(matches!(result, Err(CustomError::BadRequestError(_))));
+        assert!(comments.deleted.lock().unwrap().is_empty());
+    }
 }
@@ -0,0 +1,56 @@
+use crate::comment::model::{Comment, CommentNode};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// A comment as returned by the API
+#[derive(Debug, Serialize)]
+pub struct CommentDto {
+    pub id: String,
+    pub post_id: String,
+    pub parent_id: Option<String>,
+    pub author_id: String,
+    pub author_username: Option<String>,
+    pub content: String,
+    pub version: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<Comment> for CommentDto {
+    fn from(comment: Comment) -> Self {
+        CommentDto {
+            id: comment.id.map(|id| id.to_hex()).unwrap_or_default(),
+            post_id: comment.post_id.to_hex(),
+            parent_id: comment.parent_id.map(|id| id.to_hex()),
+            author_id: comment.author_id.to_hex(),
+            author_username: comment.author_username,
+            content: comment.content,
+            version: comment.version,
+            created_at: comment.created_at,
+            updated_at: comment.updated_at,
+        }
+    }
+}
+
+/// `CommentNode` as returned by the API
+#[derive(Debug, Serialize)]
+pub struct CommentNodeDto {
+    #[serde(flatten)]
+    pub comment: CommentDto,
+    pub reply_count: usize,
+    pub descendant_count: usize,
+    pub replies: Vec<CommentNodeDto>,
+    pub has_more_replies: bool,
+}
+
+impl From<CommentNode> for CommentNodeDto {
+    fn from(node: CommentNode) -> Self {
+        CommentNodeDto {
+            comment: node.comment.into(),
+            reply_count: node.reply_count,
+            descendant_count: node.descendant_count,
+            replies: node.replies.into_iter().map(Into::into).collect(),
+            has_more_replies: node.has_more_replies,
+        }
+    }
+}
@@ -1,17 +1,66 @@
 use super::controller::{
-    create_comment, delete_comment, get_comment, get_comment_count, get_post_comments,
-    update_comment,
+    create_comment, delete_comment, delete_draft, get_comment, get_comment_count, get_comment_tree,
+    get_draft, get_post_comments, save_draft, update_comment,
 };
+use super::service::CommentService;
+use crate::database::RedisService;
+use crate::leaderboard::service::LeaderboardService;
+use crate::link_safety::service::LinkGuard;
+use crate::middleware::limits::RequestTimeout;
+use crate::moderation::service::ModerationService;
+use crate::notification::service::NotificationService;
+use crate::post::post_service::PostService;
+use crate::spam_guard::service::SpamGuard;
+use crate::subscription::service::SubscriptionService;
 use actix_web::web;
 
 pub fn comment_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/comments")
-            .route("", web::post().to(create_comment))
-            .route("/post/{post_id}", web::get().to(get_post_comments))
-            .route("/count/{post_id}", web::get().to(get_comment_count))
-            .route("/{comment_id}", web::get().to(get_comment))
-            .route("/{comment_id}", web::put().to(update_comment))
-            .route("/{comment_id}", web::delete().to(delete_comment)),
+            .wrap(RequestTimeout::standard())
+            .route(
+                "",
+                web::post().to(create_comment::<
+                    CommentService,
+                    PostService,
+                    NotificationService,
+                    LeaderboardService,
+                    RedisService,
+                    SpamGuard,
+                    LinkGuard,
+                    ModerationService,
+                    SubscriptionService,
+                >),
+            )
+            .route(
+                "/post/{post_id}",
+                web::get().to(get_post_comments::<CommentService>),
+            )
+            .route(
+                "/post/{post_id}/tree",
+                web::get().to(get_comment_tree::<CommentService>),
+            )
+            .route(
+                "/count/{post_id}",
+                web::get().to(get_comment_count::<CommentService>),
+            )
+            .route("/drafts/{post_id}", web::get().to(get_draft))
+            .route(
+                "/drafts/{post_id}",
+                web::put().to(save_draft::<PostService>),
+            )
+            .route("/drafts/{post_id}", web::delete().to(delete_draft))
+            .route(
+                "/{comment_id}",
+                web::get().to(get_comment::<CommentService>),
+            )
+            .route(
+                "/{comment_id}",
+                web::put().to(update_comment::<CommentService>),
+            )
+            .route(
+                "/{comment_id}",
+                web::delete().to(delete_comment::<CommentService>),
+            ),
     );
 }
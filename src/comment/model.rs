@@ -1,26 +1,102 @@
+use crate::utils::datetime::bson_datetime;
+use crate::utils::validation::{not_blank, object_id};
 use chrono::{DateTime, Utc};
 use mongodb::bson::oid::ObjectId;
 use serde::{Deserialize, Serialize};
+use validator::Validate;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Comment {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<ObjectId>,
     pub post_id: ObjectId,
+    /// Comment this one replies to; top-level comments have none
+    #[serde(default)]
+    pub parent_id: Option<ObjectId>,
     pub author_id: ObjectId,
     pub author_username: Option<String>,
     pub content: String,
+    /// Incremented on every update for optimistic concurrency control
+    #[serde(default)]
+    pub version: i64,
+    #[serde(with = "bson_datetime")]
     pub created_at: DateTime<Utc>,
+    #[serde(with = "bson_datetime")]
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Validate)]
 pub struct CreateCommentRequest {
+    #[validate(custom(function = "object_id"))]
     pub post_id: String,
+    /// Comment on the same post to reply to
+    #[validate(custom(function = "object_id"))]
+    pub parent_id: Option<String>,
+    #[validate(
+        length(max = 2000, message = "must be at most 2000 characters"),
+        custom(function = "not_blank")
+    )]
     pub content: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Validate)]
 pub struct UpdateCommentRequest {
+    #[validate(
+        length(max = 2000, message = "must be at most 2000 characters"),
+        custom(function = "not_blank")
+    )]
     pub content: String,
+    /// Version the client last read; the update is rejected if it is stale
+    pub version: i64,
+}
+
+/// A comment with its most replied-to replies, nested down to the
+/// requested depth. Counts cover every visible reply, shown or not, so
+/// clients can offer to expand what was left out.
+#[derive(Debug, Serialize)]
+pub struct CommentNode {
+    #[serde(flatten)]
+    pub comment: Comment,
+    /// Direct replies
+    pub reply_count: usize,
+    /// Replies at any depth
+    pub descendant_count: usize,
+    pub replies: Vec<CommentNode>,
+    /// Whether some direct replies were left out of `replies`
+    pub has_more_replies: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CommentTreeQuery {
+    /// Levels of replies to include under top-level comments (default 3, max 10)
+    pub depth: Option<usize>,
+    /// Replies to include per comment (default 3, max 50)
+    pub top_replies: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CommentPageQuery {
+    /// Page to return, starting at 1
+    pub page: Option<u64>,
+    /// Comments per page (default 50, max 100)
+    pub per_page: Option<u64>,
+}
+
+/// Unsent comment kept for the author across devices, in Redis
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CommentDraft {
+    pub post_id: String,
+    pub content: String,
+    /// Comment the draft replies to, if any
+    pub reply_to: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct SaveCommentDraftRequest {
+    /// Blank drafts are allowed so the composer can be saved as typed
+    #[validate(length(max = 2000, message = "must be at most 2000 characters"))]
+    pub content: String,
+    #[validate(custom(function = "object_id"))]
+    pub reply_to: Option<String>,
 }
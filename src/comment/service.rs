@@ -1,26 +1,101 @@
-use crate::comment::model::Comment;
+use crate::comment::model::{Comment, CommentNode};
+use crate::database::{MongoRepository, Page, Repository};
+use crate::utils::datetime::bson_now;
 use crate::utils::error::CustomError;
+use crate::utils::sanitize::{Markup, sanitize_required};
 use chrono::Utc;
-use futures_util::TryStreamExt;
+use mongodb::Client;
 use mongodb::bson::{doc, oid::ObjectId};
-use mongodb::{Client, Collection};
+use std::collections::HashMap;
+use std::future::Future;
 
-pub struct CommentService {
-    collection: Collection<Comment>,
+/// Comments read per query when building a whole comment tree
+const TREE_BATCH_SIZE: u64 = 1000;
+
+/// The comment operations handlers use, so they can be unit-tested against a
+/// mock instead of MongoDB. `CommentService` implements it by delegating to
+/// its inherent methods.
+pub trait CommentServiceTrait: Send + Sync + 'static {
+    fn add_comment(
+        &self,
+        post_id: ObjectId,
+        parent_id: Option<ObjectId>,
+        author_id: ObjectId,
+        author_username: Option<String>,
+        content: String,
+    ) -> impl Future<Output = Result<ObjectId, CustomError>> + Send;
+
+    fn get_comments_for_post(
+        &self,
+        post_id: &ObjectId,
+        hidden_authors: &[ObjectId],
+        page: u64,
+        per_page: u64,
+    ) -> impl Future<Output = Result<Page<Comment>, CustomError>> + Send;
+
+    fn get_comment_tree(
+        &self,
+        post_id: &ObjectId,
+        hidden_authors: &[ObjectId],
+        depth: usize,
+        top_replies: usize,
+    ) -> impl Future<Output = Result<Vec<CommentNode>, CustomError>> + Send;
+
+    fn get_comment_by_id(
+        &self,
+        comment_id: &ObjectId,
+    ) -> impl Future<Output = Result<Option<Comment>, CustomError>> + Send;
+
+    fn update_comment(
+        &self,
+        comment_id: &ObjectId,
+        author_id: &ObjectId,
+        content: String,
+        expected_version: i64,
+    ) -> impl Future<Output = Result<bool, CustomError>> + Send;
+
+    fn delete_comment(
+        &self,
+        comment_id: &ObjectId,
+        author_id: &ObjectId,
+    ) -> impl Future<Output = Result<bool, CustomError>> + Send;
+
+    fn delete_comments_by_author(
+        &self,
+        author_id: &ObjectId,
+    ) -> impl Future<Output = Result<u64, CustomError>> + Send;
+
+    fn get_comment_count(
+        &self,
+        post_id: &ObjectId,
+        hidden_authors: &[ObjectId],
+    ) -> impl Future<Output = Result<u64, CustomError>> + Send;
+}
+
+pub struct CommentService<R: Repository<Comment> = MongoRepository<Comment>> {
+    repository: R,
 }
 
 impl CommentService {
     pub fn new(client: &Client) -> Self {
-        let collection = client
-            .database("rust_blogdb")
-            .collection::<Comment>("comments");
-        CommentService { collection }
+        CommentService {
+            repository: MongoRepository::new(client, "comments"),
+        }
+    }
+}
+
+impl<R: Repository<Comment>> CommentService<R> {
+    /// Create a CommentService over any repository (e.g. an in-memory mock)
+    pub fn with_repository(repository: R) -> Self {
+        CommentService { repository }
     }
 
-    /// Add a new comment to a post
+    /// Add a new comment to a post, optionally as a reply to `parent_id`
+    #[tracing::instrument(skip_all)]
     pub async fn add_comment(
         &self,
         post_id: ObjectId,
+        parent_id: Option<ObjectId>,
         author_id: ObjectId,
         author_username: Option<String>,
         content: String,
@@ -28,137 +103,305 @@ impl CommentService {
         let comment = Comment {
             id: None,
             post_id,
+            parent_id,
             author_id,
             author_username,
-            content,
+            content: sanitize_required(&content, Markup::Safe, "content")?,
+            version: 0,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         };
 
-        let result = self.collection.insert_one(comment).await.map_err(|e| {
-            CustomError::InternalServerError(format!("Failed to add comment: {}", e))
-        })?;
-
-        result.inserted_id.as_object_id().ok_or_else(|| {
-            CustomError::InternalServerError("Failed to get inserted comment ID".to_string())
-        })
+        self.repository.insert(&comment).await
     }
 
-    /// Get all comments for a specific post
+    /// A page of a post's comments, oldest first, leaving out `hidden_authors`
+    #[tracing::instrument(skip_all)]
     pub async fn get_comments_for_post(
         &self,
         post_id: &ObjectId,
-    ) -> Result<Vec<Comment>, CustomError> {
-        let cursor = self
-            .collection
-            .find(doc! { "post_id": post_id })
+        hidden_authors: &[ObjectId],
+        page: u64,
+        per_page: u64,
+    ) -> Result<Page<Comment>, CustomError> {
+        self.repository
+            .find_paginated(
+                doc! { "post_id": post_id, "author_id": { "$nin": hidden_authors } },
+                doc! { "created_at": 1 },
+                page,
+                per_page,
+            )
             .await
-            .map_err(|e| {
-                CustomError::InternalServerError(format!("Failed to fetch comments: {}", e))
-            })?;
-
-        let comments: Vec<Comment> = cursor.try_collect().await.map_err(|e| {
-            CustomError::InternalServerError(format!("Failed to collect comments: {}", e))
-        })?;
+    }
 
+    /// Every comment on a post, oldest first, read in batches
+    async fn all_comments_for_post(
+        &self,
+        post_id: &ObjectId,
+        hidden_authors: &[ObjectId],
+    ) -> Result<Vec<Comment>, CustomError> {
+        let mut comments = Vec::new();
+        for page in 1.. {
+            let batch = self
+                .get_comments_for_post(post_id, hidden_authors, page, TREE_BATCH_SIZE)
+                .await?;
+            let done = batch.items.is_empty() || page * TREE_BATCH_SIZE >= batch.total;
+            comments.extend(batch.items);
+            if done {
+                break;
+            }
+        }
         Ok(comments)
     }
 
+    /// A post's comments as a tree: top-level comments oldest first, each
+    /// with up to `top_replies` replies, those with the most replies first,
+    /// down to `depth` levels. Replies whose parent is hidden or deleted are
+    /// left out with it.
+    #[tracing::instrument(skip_all)]
+    pub async fn get_comment_tree(
+        &self,
+        post_id: &ObjectId,
+        hidden_authors: &[ObjectId],
+        depth: usize,
+        top_replies: usize,
+    ) -> Result<Vec<CommentNode>, CustomError> {
+        let comments = self.all_comments_for_post(post_id, hidden_authors).await?;
+
+        let mut roots = Vec::new();
+        let mut children: HashMap<ObjectId, Vec<Comment>> = HashMap::new();
+        for comment in comments {
+            match comment.parent_id {
+                Some(parent_id) => children.entry(parent_id).or_default().push(comment),
+                None => roots.push(comment),
+            }
+        }
+
+        Ok(roots
+            .into_iter()
+            .map(|root| build_node(root, &mut children, depth, top_replies))
+            .collect())
+    }
+
     /// Get a single comment by ID
+    #[tracing::instrument(skip_all)]
     pub async fn get_comment_by_id(
         &self,
         comment_id: &ObjectId,
     ) -> Result<Option<Comment>, CustomError> {
-        self.collection
-            .find_one(doc! { "_id": comment_id })
-            .await
-            .map_err(|e| {
-                CustomError::InternalServerError(format!("Failed to fetch comment: {}", e))
+        self.repository.find_by_id(comment_id).await
+    }
+
+    /// Fetch a comment and check that it belongs to the given author
+    #[tracing::instrument(skip_all)]
+    async fn get_owned_comment(
+        &self,
+        comment_id: &ObjectId,
+        author_id: &ObjectId,
+    ) -> Result<Comment, CustomError> {
+        self.repository
+            .find_one(doc! { "_id": comment_id, "author_id": author_id })
+            .await?
+            .ok_or_else(|| {
+                CustomError::NotFoundError("Comment not found or not authorized".to_string())
             })
     }
 
-    /// Update a comment (only author can update)
+    /// Update a comment (only author can update), rejecting stale versions
+    #[tracing::instrument(skip_all)]
     pub async fn update_comment(
         &self,
         comment_id: &ObjectId,
         author_id: &ObjectId,
         content: String,
+        expected_version: i64,
     ) -> Result<bool, CustomError> {
-        let result = self
-            .collection
-            .update_one(
-                doc! { "_id": comment_id, "author_id": author_id },
+        self.get_owned_comment(comment_id, author_id).await?;
+        let content = sanitize_required(&content, Markup::Safe, "content")?;
+
+        self.repository
+            .update_versioned(
+                comment_id,
+                expected_version,
                 doc! {
-                    "$set": {
-                        "content": content,
-                        "updated_at": Utc::now().to_rfc3339()
-                    }
+                    "content": content,
+                    "updated_at": bson_now()
                 },
             )
             .await
-            .map_err(|e| {
-                CustomError::InternalServerError(format!("Failed to update comment: {}", e))
-            })?;
-
-        if result.matched_count == 0 {
-            return Err(CustomError::NotFoundError(
-                "Comment not found or not authorized".to_string(),
-            ));
-        }
-
-        Ok(result.modified_count > 0)
     }
 
     /// Delete a comment (only author can delete)
+    #[tracing::instrument(skip_all)]
     pub async fn delete_comment(
         &self,
         comment_id: &ObjectId,
         author_id: &ObjectId,
     ) -> Result<bool, CustomError> {
-        let result = self
-            .collection
-            .delete_one(doc! { "_id": comment_id, "author_id": author_id })
-            .await
-            .map_err(|e| {
-                CustomError::InternalServerError(format!("Failed to delete comment: {}", e))
-            })?;
-
-        if result.deleted_count == 0 {
-            return Err(CustomError::NotFoundError(
-                "Comment not found or not authorized".to_string(),
-            ));
-        }
+        self.get_owned_comment(comment_id, author_id).await?;
 
-        Ok(true)
+        self.repository.soft_delete(comment_id).await
     }
 
-    /// Get comment count for a post
-    pub async fn get_comment_count(&self, post_id: &ObjectId) -> Result<u64, CustomError> {
-        self.collection
-            .count_documents(doc! { "post_id": post_id })
+    /// Soft-delete every comment by `author_id`, returning how many. Replies
+    /// to them stay, as they do when a single comment is deleted.
+    #[tracing::instrument(skip_all)]
+    pub async fn delete_comments_by_author(
+        &self,
+        author_id: &ObjectId,
+    ) -> Result<u64, CustomError> {
+        self.repository
+            .soft_delete_many(doc! { "author_id": author_id })
+            .await
+    }
+
+    /// Get comment count for a post, leaving out `hidden_authors`
+    #[tracing::instrument(skip_all)]
+    pub async fn get_comment_count(
+        &self,
+        post_id: &ObjectId,
+        hidden_authors: &[ObjectId],
+    ) -> Result<u64, CustomError> {
+        self.repository
+            .count(doc! { "post_id": post_id, "author_id": { "$nin": hidden_authors } })
             .await
-            .map_err(|e| {
-                CustomError::InternalServerError(format!("Failed to count comments: {}", e))
-            })
     }
 
-    /// Get all comments by a user
+    /// A page of a user's comments, newest first
+    #[tracing::instrument(skip_all)]
     pub async fn get_comments_by_user(
         &self,
         author_id: &ObjectId,
-    ) -> Result<Vec<Comment>, CustomError> {
-        let cursor = self
-            .collection
-            .find(doc! { "author_id": author_id })
+        page: u64,
+        per_page: u64,
+    ) -> Result<Page<Comment>, CustomError> {
+        self.repository
+            .find_paginated(
+                doc! { "author_id": author_id },
+                doc! { "created_at": -1 },
+                page,
+                per_page,
+            )
             .await
-            .map_err(|e| {
-                CustomError::InternalServerError(format!("Failed to fetch comments: {}", e))
-            })?;
+    }
+}
 
-        let comments: Vec<Comment> = cursor.try_collect().await.map_err(|e| {
-            CustomError::InternalServerError(format!("Failed to collect comments: {}", e))
-        })?;
+impl<R: Repository<Comment> + 'static> CommentServiceTrait for CommentService<R> {
+    async fn add_comment(
+        &self,
+        post_id: ObjectId,
+        parent_id: Option<ObjectId>,
+        author_id: ObjectId,
+        author_username: Option<String>,
+        content: String,
+    ) -> Result<ObjectId, CustomError> {
+        Self::add_comment(
+            self,
+            post_id,
+            parent_id,
+            author_id,
+            author_username,
+            content,
+        )
+        .await
+    }
 
-        Ok(comments)
+    async fn get_comments_for_post(
+        &self,
+        post_id: &ObjectId,
+        hidden_authors: &[ObjectId],
+        page: u64,
+        per_page: u64,
+    ) -> Result<Page<Comment>, CustomError> {
+        Self::get_comments_for_post(self, post_id, hidden_authors, page, per_page).await
+    }
+
+    async fn get_comment_tree(
+        &self,
+        post_id: &ObjectId,
+        hidden_authors: &[ObjectId],
+        depth: usize,
+        top_replies: usize,
+    ) -> Result<Vec<CommentNode>, CustomError> {
+        Self::get_comment_tree(self, post_id, hidden_authors, depth, top_replies).await
+    }
+
+    async fn get_comment_by_id(
+        &self,
+        comment_id: &ObjectId,
+    ) -> Result<Option<Comment>, CustomError> {
+        Self::get_comment_by_id(self, comment_id).await
+    }
+
+    async fn update_comment(
+        &self,
+        comment_id: &ObjectId,
+        author_id: &ObjectId,
+        content: String,
+        expected_version: i64,
+    ) -> Result<bool, CustomError> {
+        Self::update_comment(self, comment_id, author_id, content, expected_version).await
+    }
+
+    async fn delete_comment(
+        &self,
+        comment_id: &ObjectId,
+        author_id: &ObjectId,
+    ) -> Result<bool, CustomError> {
+        Self::delete_comment(self, comment_id, author_id).await
+    }
+
+    async fn delete_comments_by_author(&self, author_id: &ObjectId) -> Result<u64, CustomError> {
+        Self::delete_comments_by_author(self, author_id).await
+    }
+
+    async fn get_comment_count(
+        &self,
+        post_id: &ObjectId,
+        hidden_authors: &[ObjectId],
+    ) -> Result<u64, CustomError> {
+        Self::get_comment_count(self, post_id, hidden_authors).await
+    }
+}
+
+/// Shape a comment and its replies into a node, taking the replies out of
+/// `children`
+fn build_node(
+    comment: Comment,
+    children: &mut HashMap<ObjectId, Vec<Comment>>,
+    depth: usize,
+    top_replies: usize,
+) -> CommentNode {
+    let replies = comment
+        .id
+        .and_then(|id| children.remove(&id))
+        .unwrap_or_default();
+    let reply_count = replies.len();
+
+    // Every reply is built to count descendants; only the top ones are kept
+    let mut replies: Vec<CommentNode> = replies
+        .into_iter()
+        .map(|reply| build_node(reply, children, depth.saturating_sub(1), top_replies))
+        .collect();
+    let descendant_count = reply_count
+        + replies
+            .iter()
+            .map(|reply| reply.descendant_count)
+            .sum::<usize>();
+
+    let shown = if depth == 0 { 0 } else { top_replies };
+    replies.sort_by(|a, b| {
+        b.descendant_count
+            .cmp(&a.descendant_count)
+            .then(a.comment.created_at.cmp(&b.comment.created_at))
+    });
+    replies.truncate(shown);
+
+    CommentNode {
+        comment,
+        reply_count,
+        descendant_count,
+        has_more_replies: replies.len() < reply_count,
+        replies,
     }
 }
@@ -0,0 +1,101 @@
+use crate::insights::model::INSIGHTS_RETENTION_DAYS;
+use crate::utils::datetime::{bson_datetime, option_bson_datetime};
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+/// Counters served from Redis. Each is derived from a Mongo source it can
+/// be recounted from, and a copy is flushed to Mongo so a cold cache
+/// doesn't need a recount.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Counter {
+    /// Delivered notifications the user hasn't read
+    UnreadNotifications,
+    /// The user's friends, who are also the people following them
+    Friends,
+    /// Distinct viewers of a profile on one day
+    ProfileViews,
+    /// Times a post was shared
+    PostShares,
+    /// Posts tagged with a topic
+    TopicPosts,
+    /// Active members of a group
+    GroupMembers,
+}
+
+impl Counter {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Counter::UnreadNotifications => "unread_notifications",
+            Counter::Friends => "friends",
+            Counter::ProfileViews => "profile_views",
+            Counter::PostShares => "post_shares",
+            Counter::TopicPosts => "topic_posts",
+            Counter::GroupMembers => "group_members",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "unread_notifications" => Some(Counter::UnreadNotifications),
+            "friends" => Some(Counter::Friends),
+            "profile_views" => Some(Counter::ProfileViews),
+            "post_shares" => Some(Counter::PostShares),
+            "topic_posts" => Some(Counter::TopicPosts),
+            "group_members" => Some(Counter::GroupMembers),
+            _ => None,
+        }
+    }
+
+    /// How long the stored copy outlives its last change; None keeps it
+    pub fn retention_days(&self) -> Option<i64> {
+        match self {
+            Counter::ProfileViews => Some(INSIGHTS_RETENTION_DAYS + 1),
+            _ => None,
+        }
+    }
+
+    /// Field of the counted document that keeps a copy, for counters that
+    /// queries sort by; it is written on every flush
+    pub fn mirror_field(&self) -> Option<&'static str> {
+        match self {
+            Counter::PostShares => Some("share_count"),
+            Counter::TopicPosts => Some("post_count"),
+            Counter::GroupMembers => Some("member_count"),
+            _ => None,
+        }
+    }
+
+    /// Key of this counter for `subject`, in Redis and Mongo alike
+    pub fn id(&self, subject: &str) -> String {
+        format!("{}:{}", self.name(), subject)
+    }
+
+    /// Split a key from `id` back into the counter and its subject
+    pub fn parse_id(id: &str) -> Option<(Self, &str)> {
+        let (name, subject) = id.split_once(':')?;
+        Some((Counter::parse(name)?, subject))
+    }
+}
+
+/// Subject of a `Counter::ProfileViews` counter, for `day` as YYYY-MM-DD
+pub fn profile_views_subject(user_id: &ObjectId, day: &str) -> String {
+    format!("{}:{}", user_id.to_hex(), day)
+}
+
+/// The copy of a counter flushed to Mongo
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StoredCounter {
+    /// `Counter::id` of the counter
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub value: i64,
+    #[serde(with = "bson_datetime")]
+    pub updated_at: DateTime<Utc>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "option_bson_datetime"
+    )]
+    pub expires_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,490 @@
+use crate::counter::model::{Counter, StoredCounter};
+use crate::database::RedisService;
+use crate::group::model::MembershipStatus;
+use crate::utils::error::CustomError;
+use chrono::{Duration, Utc};
+use futures_util::TryStreamExt;
+use mongodb::bson::{self, Bson, Document, doc, oid::ObjectId};
+use mongodb::options::IndexOptions;
+use mongodb::{Client, Collection, IndexModel};
+use std::collections::HashMap;
+
+/// How long a counter stays cached in Redis after its last change
+const COUNTER_CACHE_SECONDS: u64 = 7 * 86400;
+/// Counters written to Mongo per round of a flush
+const FLUSH_BATCH: usize = 500;
+/// Counters changed within this long are recounted by `reconcile`; a little
+/// over the hour the job runs on, so runs overlap
+const RECONCILE_WINDOW_MINUTES: i64 = 90;
+/// Most counters recounted by one reconcile run
+const RECONCILE_BATCH: i64 = 5000;
+
+/// Counters kept in Redis, so hot paths never count documents.
+///
+/// Changes are applied in Redis and queued; `flush` writes them to Mongo
+/// and `reconcile` recounts recently changed counters from their source to
+/// undo drift. A counter missing from Redis is loaded from its stored copy,
+/// or recounted when it has none. With Redis down, changes go straight to
+/// the stored copy.
+///
+/// Counters with a `mirror_field` are also copied onto the counted
+/// document, so queries can keep sorting by them.
+pub struct CounterService {
+    counters: Collection<StoredCounter>,
+    notifications: Collection<Document>,
+    friendships: Collection<Document>,
+    visits: Collection<Document>,
+    posts: Collection<Document>,
+    shares: Collection<Document>,
+    topics: Collection<Document>,
+    groups: Collection<Document>,
+    group_members: Collection<Document>,
+    redis_service: RedisService,
+}
+
+impl CounterService {
+    pub fn new(client: &Client, redis_service: RedisService) -> Self {
+        let db = client.database("rust_blogdb");
+        CounterService {
+            counters: db.collection::<StoredCounter>("counters"),
+            notifications: db.collection::<Document>("notifications"),
+            friendships: db.collection::<Document>("friendships"),
+            visits: db.collection::<Document>("profile_visits"),
+            posts: db.collection::<Document>("posts"),
+            shares: db.collection::<Document>("post_shares"),
+            topics: db.collection::<Document>("topics"),
+            groups: db.collection::<Document>("groups"),
+            group_members: db.collection::<Document>("group_members"),
+            redis_service,
+        }
+    }
+
+    /// The reconcile sweep, and expiry for counters with a retention
+    #[tracing::instrument(skip_all)]
+    pub async fn ensure_indexes(&self) -> Result<(), CustomError> {
+        let indexes = vec![
+            IndexModel::builder().keys(doc! { "updated_at": 1 }).build(),
+            IndexModel::builder()
+                .keys(doc! { "expires_at": 1 })
+                .options(
+                    IndexOptions::builder()
+                        .expire_after(std::time::Duration::ZERO)
+                        .build(),
+                )
+                .build(),
+        ];
+        self.counters.create_indexes(indexes).await.map_err(|e| {
+            CustomError::InternalServerError(format!("Failed to create counter indexes: {}", e))
+        })?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn incr(&self, counter: Counter, subject: &str) -> Result<(), CustomError> {
+        self.add(counter, subject, 1).await
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn decr(&self, counter: Counter, subject: &str) -> Result<(), CustomError> {
+        self.add(counter, subject, -1).await
+    }
+
+    /// Apply a change the source has already seen
+    #[tracing::instrument(skip_all)]
+    pub async fn add(&self, counter: Counter, subject: &str, by: i64) -> Result<(), CustomError> {
+        let id = counter.id(subject);
+        let mut loaded = false;
+
+        loop {
+            match self
+                .redis_service
+                .counter_add(&id, by, COUNTER_CACHE_SECONDS)
+                .await
+            {
+                Ok(Some(_)) => {
+                    self.mark_dirty(vec![id]).await;
+                    return Ok(());
+                }
+                Ok(None) if !loaded => {
+                    loaded = true;
+                    // A recount already includes the change being applied
+                    let (value, recounted) = self.load(counter, subject).await?;
+                    let value = if recounted { value } else { value + by };
+                    match self
+                        .redis_service
+                        .counter_set(&id, value, COUNTER_CACHE_SECONDS, true)
+                        .await
+                    {
+                        Ok(true) => {
+                            self.mark_dirty(vec![id]).await;
+                            return Ok(());
+                        }
+                        // Someone else loaded it first; add to theirs
+                        Ok(false) => continue,
+                        Err(e) => {
+                            log::warn!("Failed to cache counter {}: {}", id, e);
+                            break;
+                        }
+                    }
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    log::warn!("Failed to update counter {}: {}", id, e);
+                    break;
+                }
+            }
+        }
+
+        self.store_add(counter, &id, by).await
+    }
+
+    /// Overwrite a counter, e.g. to zero it when everything is read
+    #[tracing::instrument(skip_all)]
+    pub async fn set(
+        &self,
+        counter: Counter,
+        subject: &str,
+        value: i64,
+    ) -> Result<(), CustomError> {
+        let id = counter.id(subject);
+        match self
+            .redis_service
+            .counter_set(&id, value, COUNTER_CACHE_SECONDS, false)
+            .await
+        {
+            Ok(_) => {
+                self.mark_dirty(vec![id]).await;
+                Ok(())
+            }
+            Err(e) => {
+                log::warn!("Failed to set counter {}: {}", id, e);
+                self.store(counter, &id, value).await
+            }
+        }
+    }
+
+    /// Recount a counter from its source, for changes whose effect on it
+    /// isn't known
+    #[tracing::instrument(skip_all)]
+    pub async fn refresh(&self, counter: Counter, subject: &str) -> Result<(), CustomError> {
+        let value = self.source_count(counter, subject).await?;
+        self.set(counter, subject, value).await
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn get(&self, counter: Counter, subject: &str) -> Result<i64, CustomError> {
+        let values = self.get_many(counter, &[subject.to_string()]).await?;
+        Ok(values.into_iter().next().unwrap_or_default())
+    }
+
+    /// Values of `counter` for each of `subjects`, in order. Counters
+    /// missing from Redis are loaded and cached.
+    #[tracing::instrument(skip_all)]
+    pub async fn get_many(
+        &self,
+        counter: Counter,
+        subjects: &[String],
+    ) -> Result<Vec<i64>, CustomError> {
+        let ids: Vec<String> = subjects.iter().map(|subject| counter.id(subject)).collect();
+        let cached = match self.redis_service.counter_get_many(&ids).await {
+            Ok(cached) => Some(cached),
+            Err(e) => {
+                log::warn!("Falling back to stored counters: {}", e);
+                None
+            }
+        };
+
+        let cached_at = |index: usize| cached.as_ref().and_then(|cached| cached[index]);
+        let missing: Vec<String> = ids
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| cached_at(*index).is_none())
+            .map(|(_, id)| id.clone())
+            .collect();
+        let mut stored = if missing.is_empty() {
+            HashMap::new()
+        } else {
+            self.stored(&missing).await?
+        };
+
+        let mut values = Vec::with_capacity(subjects.len());
+        let mut recounted = Vec::new();
+        for (index, (subject, id)) in subjects.iter().zip(&ids).enumerate() {
+            if let Some(value) = cached_at(index) {
+                values.push(value);
+                continue;
+            }
+
+            let value = match stored.remove(id) {
+                Some(value) => value,
+                None => {
+                    recounted.push(id.clone());
+                    self.source_count(counter, subject).await?
+                }
+            };
+            if cached.is_some()
+                && let Err(e) = self
+                    .redis_service
+                    .counter_set(id, value, COUNTER_CACHE_SECONDS, true)
+                    .await
+            {
+                log::warn!("Failed to cache counter {}: {}", id, e);
+            }
+            values.push(value);
+        }
+
+        // Recounts have no stored copy yet
+        if cached.is_some() {
+            self.mark_dirty(recounted).await;
+        }
+
+        Ok(values)
+    }
+
+    /// Write counters changed in Redis to their stored copies. Returns how
+    /// many were written.
+    #[tracing::instrument(skip_all)]
+    pub async fn flush(&self) -> Result<u64, CustomError> {
+        let mut flushed = 0;
+
+        loop {
+            let ids = self
+                .redis_service
+                .counter_take_dirty(FLUSH_BATCH)
+                .await
+                .map_err(CustomError::InternalServerError)?;
+            if ids.is_empty() {
+                break;
+            }
+            let values = match self.redis_service.counter_get_many(&ids).await {
+                Ok(values) => values,
+                Err(e) => {
+                    self.mark_dirty(ids).await;
+                    return Err(CustomError::InternalServerError(e));
+                }
+            };
+
+            let batch = ids.len();
+            for (index, (id, value)) in ids.iter().zip(values).enumerate() {
+                // Expired from Redis since it changed; the stored copy stays
+                let Some(value) = value else { continue };
+                let Some((counter, _)) = Counter::parse_id(id) else {
+                    continue;
+                };
+                if let Err(e) = self.store(counter, id, value).await {
+                    self.mark_dirty(ids[index..].to_vec()).await;
+                    return Err(e);
+                }
+                flushed += 1;
+            }
+
+            if batch < FLUSH_BATCH {
+                break;
+            }
+        }
+
+        Ok(flushed)
+    }
+
+    /// Recount recently changed counters from their source and correct the
+    /// ones that drifted. Returns how many were corrected.
+    #[tracing::instrument(skip_all)]
+    pub async fn reconcile(&self) -> Result<u64, CustomError> {
+        let since = Utc::now() - Duration::minutes(RECONCILE_WINDOW_MINUTES);
+        let recent: Vec<StoredCounter> = self
+            .counters
+            .find(doc! { "updated_at": { "$gte": bson::DateTime::from_chrono(since) } })
+            .sort(doc! { "updated_at": -1 })
+            .limit(RECONCILE_BATCH)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        let mut corrected = 0;
+        for stored in recent {
+            let Some((counter, subject)) = Counter::parse_id(&stored.id) else {
+                continue;
+            };
+            let value = self.source_count(counter, subject).await?;
+            if value == stored.value {
+                continue;
+            }
+
+            self.store(counter, &stored.id, value).await?;
+            if let Err(e) = self
+                .redis_service
+                .counter_set(&stored.id, value, COUNTER_CACHE_SECONDS, false)
+                .await
+            {
+                log::warn!("Failed to cache counter {}: {}", stored.id, e);
+            }
+            corrected += 1;
+        }
+
+        Ok(corrected)
+    }
+
+    /// A counter's stored copy, or a recount from its source when there is
+    /// none; the flag tells which
+    async fn load(&self, counter: Counter, subject: &str) -> Result<(i64, bool), CustomError> {
+        let id = counter.id(subject);
+        if let Some(value) = self.stored(std::slice::from_ref(&id)).await?.remove(&id) {
+            return Ok((value, false));
+        }
+
+        Ok((self.source_count(counter, subject).await?, true))
+    }
+
+    async fn stored(&self, ids: &[String]) -> Result<HashMap<String, i64>, CustomError> {
+        let stored: Vec<StoredCounter> = self
+            .counters
+            .find(doc! { "_id": { "$in": ids } })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        Ok(stored
+            .into_iter()
+            .map(|stored| (stored.id, stored.value))
+            .collect())
+    }
+
+    async fn store(&self, counter: Counter, id: &str, value: i64) -> Result<(), CustomError> {
+        self.counters
+            .update_one(
+                doc! { "_id": id },
+                doc! { "$set": Self::stored_fields(counter, value) },
+            )
+            .upsert(true)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        self.mirror(counter, id, "$set", value).await
+    }
+
+    /// Apply a change to the stored copy alone, for when Redis is down. A
+    /// copy created here starts from zero until `reconcile` recounts it.
+    async fn store_add(&self, counter: Counter, id: &str, by: i64) -> Result<(), CustomError> {
+        let mut set = Self::stored_fields(counter, 0);
+        set.remove("value");
+        self.counters
+            .update_one(
+                doc! { "_id": id },
+                doc! { "$inc": { "value": by }, "$set": set },
+            )
+            .upsert(true)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        self.mirror(counter, id, "$inc", by).await
+    }
+
+    /// Apply `operator` with `value` to the counted document's copy of a
+    /// counter, when it keeps one
+    async fn mirror(
+        &self,
+        counter: Counter,
+        id: &str,
+        operator: &str,
+        value: i64,
+    ) -> Result<(), CustomError> {
+        let Some(field) = counter.mirror_field() else {
+            return Ok(());
+        };
+        let Some((_, subject)) = Counter::parse_id(id) else {
+            return Ok(());
+        };
+        let object_id = || {
+            ObjectId::parse_str(subject)
+                .map(Bson::ObjectId)
+                .map_err(|_| {
+                    CustomError::InternalServerError(format!("Invalid counter subject {}", subject))
+                })
+        };
+
+        let (collection, key) = match counter {
+            Counter::PostShares => (&self.posts, object_id()?),
+            Counter::TopicPosts => (&self.topics, Bson::String(subject.to_string())),
+            Counter::GroupMembers => (&self.groups, object_id()?),
+            _ => return Ok(()),
+        };
+        collection
+            .update_one(doc! { "_id": key }, doc! { operator: { field: value } })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    fn stored_fields(counter: Counter, value: i64) -> Document {
+        let now = Utc::now();
+        let mut fields = doc! {
+            "value": value,
+            "updated_at": bson::DateTime::from_chrono(now),
+        };
+        if let Some(days) = counter.retention_days() {
+            fields.insert(
+                "expires_at",
+                bson::DateTime::from_chrono(now + Duration::days(days)),
+            );
+        }
+        fields
+    }
+
+    /// Counters are derived data, so a failure to queue one only costs the
+    /// stored copy some freshness until `reconcile`
+    async fn mark_dirty(&self, ids: Vec<String>) {
+        if let Err(e) = self.redis_service.counter_mark_dirty(&ids).await {
+            log::warn!("Failed to queue counter flush: {}", e);
+        }
+    }
+
+    /// Count a counter's value from the documents it is derived from
+    async fn source_count(&self, counter: Counter, subject: &str) -> Result<i64, CustomError> {
+        let invalid =
+            || CustomError::InternalServerError(format!("Invalid counter subject {}", subject));
+
+        let (collection, filter) = match counter {
+            Counter::UnreadNotifications => {
+                let user_id = ObjectId::parse_str(subject).map_err(|_| invalid())?;
+                (
+                    &self.notifications,
+                    doc! { "user_id": user_id, "queued_until": null, "is_read": false },
+                )
+            }
+            Counter::Friends => {
+                let user_id = ObjectId::parse_str(subject).map_err(|_| invalid())?;
+                (&self.friendships, doc! { "user_id": user_id })
+            }
+            Counter::ProfileViews => {
+                let (user_id, day) = subject.split_once(':').ok_or_else(invalid)?;
+                let user_id = ObjectId::parse_str(user_id).map_err(|_| invalid())?;
+                (&self.visits, doc! { "profile_id": user_id, "day": day })
+            }
+            Counter::PostShares => {
+                let post_id = ObjectId::parse_str(subject).map_err(|_| invalid())?;
+                (&self.shares, doc! { "post_id": post_id })
+            }
+            Counter::TopicPosts => (&self.posts, doc! { "topics": subject }),
+            Counter::GroupMembers => {
+                let group_id = ObjectId::parse_str(subject).map_err(|_| invalid())?;
+                (
+                    &self.group_members,
+                    doc! { "group_id": group_id, "status": MembershipStatus::Active.name() },
+                )
+            }
+        };
+
+        let count = collection
+            .count_documents(filter)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        Ok(count as i64)
+    }
+}
@@ -1,26 +1,78 @@
 use mongodb::bson::doc;
 use mongodb::{Client, options::ClientOptions};
 use std::error::Error;
+use std::time::Duration;
+
+/// MongoDB connection pool and timeout settings
+#[derive(Clone)]
+pub struct MongoConfig {
+    pub uri: String,
+    pub max_pool_size: Option<u32>,
+    pub min_pool_size: Option<u32>,
+    pub connect_timeout: Duration,
+    pub server_selection_timeout: Duration,
+}
+
+impl MongoConfig {
+    /// Load MongoDB configuration from environment variables
+    pub fn from_env() -> Result<Self, String> {
+        let uri = std::env::var("MONGODB_URI").map_err(|_| "MONGODB_URI is required")?;
+        if !uri.starts_with("mongodb://") && !uri.starts_with("mongodb+srv://") {
+            return Err("MONGODB_URI must start with mongodb:// or mongodb+srv://".to_string());
+        }
+
+        Ok(Self {
+            uri,
+            max_pool_size: parse_optional("MONGODB_MAX_POOL_SIZE")?,
+            min_pool_size: parse_optional("MONGODB_MIN_POOL_SIZE")?,
+            connect_timeout: Duration::from_millis(
+                parse_optional("MONGODB_CONNECT_TIMEOUT_MS")?.unwrap_or(10_000),
+            ),
+            server_selection_timeout: Duration::from_millis(
+                parse_optional("MONGODB_SERVER_SELECTION_TIMEOUT_MS")?.unwrap_or(5_000),
+            ),
+        })
+    }
+}
+
+/// Parse an optional numeric environment variable
+pub(crate) fn parse_optional<T: std::str::FromStr>(name: &str) -> Result<Option<T>, String> {
+    match std::env::var(name) {
+        Ok(value) => value
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("{} must be a valid number", name)),
+        Err(_) => Ok(None),
+    }
+}
 
 pub struct Database {
     pub client: Client,
 }
 
 impl Database {
-    pub async fn init() -> Result<Self, Box<dyn Error>> {
-        let mongodb_uri = std::env::var("MONGODB_URI")
-            .unwrap_or_else(|_| "mongodb://localhost:27017".to_string());
-
-        let mut client_options = ClientOptions::parse(&mongodb_uri).await?;
+    pub async fn init(config: &MongoConfig) -> Result<Self, Box<dyn Error>> {
+        let mut client_options = ClientOptions::parse(&config.uri).await?;
         client_options.app_name = Some("rust_project".to_string());
+        client_options.max_pool_size = config.max_pool_size;
+        client_options.min_pool_size = config.min_pool_size;
+        client_options.connect_timeout = Some(config.connect_timeout);
+        client_options.server_selection_timeout = Some(config.server_selection_timeout);
 
         let client = Client::with_options(client_options)?;
 
-        // Ping the server to see if you can connect to the cluster
+        // Ping the server to see if you can connect to the cluster; this fails
+        // once server_selection_timeout elapses instead of hanging on the default
         client
             .database("admin")
             .run_command(doc! {"ping": 1})
-            .await?;
+            .await
+            .map_err(|e| {
+                format!(
+                    "MongoDB is unreachable (server selection timed out after {:?}): {}",
+                    config.server_selection_timeout, e
+                )
+            })?;
 
         println!("Connected successfully to MongoDB");
 
@@ -31,8 +83,8 @@ impl Database {
 }
 
 // This function is a convenience wrapper around Database::init()
-pub async fn connect_to_mongo() -> Result<Client, Box<dyn Error>> {
-    let database = Database::init().await.map_err(|e| {
+pub async fn connect_to_mongo(config: &MongoConfig) -> Result<Client, Box<dyn Error>> {
+    let database = Database::init(config).await.map_err(|e| {
         eprintln!("Failed to initialize database: {:?}", e);
         e
     })?;
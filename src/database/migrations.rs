@@ -0,0 +1,77 @@
+use crate::user::model::phone_hash;
+use futures_util::TryStreamExt;
+use mongodb::Client;
+use mongodb::bson::{Document, doc};
+
+/// Date fields per collection that older code wrote as RFC 3339 strings
+const DATE_FIELDS: &[(&str, &[&str])] = &[
+    ("users", &["created_at", "updated_at"]),
+    ("otps", &["expires_at", "created_at"]),
+    ("posts", &["created_at", "updated_at"]),
+    ("comments", &["created_at", "updated_at"]),
+    ("notifications", &["created_at"]),
+    ("email_outbox", &["created_at", "updated_at"]),
+    ("chat_rooms", &["created_at", "updated_at"]),
+    ("chat_messages", &["created_at"]),
+];
+
+/// Convert string-typed date fields into native BSON dates.
+///
+/// Idempotent: only documents still holding a string are touched. Strings
+/// that don't parse are left as they are rather than failing the batch.
+pub async fn migrate_string_dates(client: &Client) -> Result<u64, mongodb::error::Error> {
+    let db = client.database("rust_blogdb");
+    let mut migrated = 0;
+
+    for (collection, fields) in DATE_FIELDS {
+        let collection = db.collection::<Document>(collection);
+
+        for field in fields.iter() {
+            let result = collection
+                .update_many(
+                    doc! { *field: { "$type": "string" } },
+                    vec![doc! { "$set": { *field: {
+                        "$convert": {
+                            "input": format!("${}", field),
+                            "to": "date",
+                            "onError": format!("${}", field),
+                        }
+                    } } }],
+                )
+                .await?;
+            migrated += result.modified_count;
+        }
+    }
+
+    Ok(migrated)
+}
+
+/// Store `phone_hash` on accounts created before contact matching existed.
+///
+/// Idempotent: only users without a hash are touched.
+pub async fn backfill_phone_hashes(client: &Client) -> Result<u64, mongodb::error::Error> {
+    let users = client
+        .database("rust_blogdb")
+        .collection::<Document>("users");
+    let mut cursor = users
+        .find(doc! { "phone_hash": { "$exists": false } })
+        .projection(doc! { "phone_number": 1 })
+        .await?;
+    let mut migrated = 0;
+
+    while let Some(user) = cursor.try_next().await? {
+        let (Ok(id), Ok(phone_number)) = (user.get_object_id("_id"), user.get_str("phone_number"))
+        else {
+            continue;
+        };
+        let result = users
+            .update_one(
+                doc! { "_id": id },
+                doc! { "$set": { "phone_hash": phone_hash(phone_number) } },
+            )
+            .await?;
+        migrated += result.modified_count;
+    }
+
+    Ok(migrated)
+}
@@ -1,5 +1,11 @@
 mod db;
+mod migrations;
 mod redis;
+mod repository;
+mod seed;
 
 pub use db::*;
+pub use migrations::*;
 pub use redis::*;
+pub use repository::*;
+pub use seed::*;
@@ -1,47 +1,481 @@
-use redis::aio::MultiplexedConnection;
-use redis::{AsyncCommands, Client};
+use crate::database::db::parse_optional;
+use futures_util::{Stream, StreamExt};
+use rand::Rng;
+use redis::aio::{ConnectionLike, ConnectionManager, ConnectionManagerConfig};
+use redis::cluster::ClusterClient;
+use redis::cluster_async::ClusterConnection;
+use redis::sentinel::Sentinel;
+use redis::{
+    AsyncCommands, Client, Cmd, ErrorKind, Pipeline, RedisError, RedisFuture, RedisResult, Value,
+};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
 use std::env;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use uuid::Uuid;
 
-/// Redis connection wrapper
-pub struct RedisClient {
-    connection: MultiplexedConnection,
+/// Deletes a lock only if it is still held by the caller's token
+const RELEASE_LOCK_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("DEL", KEYS[1])
+else
+    return 0
+end
+"#;
+
+/// Keeps a room event, numbered ARGV[1], in the room's replay buffer,
+/// trimmed to the newest ARGV[3] entries, and moves the room's counter up to
+/// it. Members carry the sequence so equal payloads stay distinct.
+const REPLAY_STORE_SCRIPT: &str = r#"
+local seq = tonumber(ARGV[1])
+if seq > tonumber(redis.call("GET", KEYS[1]) or "0") then
+    redis.call("SET", KEYS[1], seq)
+end
+redis.call("ZADD", KEYS[2], seq, seq .. ":" .. ARGV[2])
+redis.call("ZREMRANGEBYRANK", KEYS[2], 0, -tonumber(ARGV[3]) - 1)
+redis.call("EXPIRE", KEYS[2], ARGV[4])
+return seq
+"#;
+
+/// Adds ARGV[1] to a counter that is already cached. A missing counter is
+/// left alone, as restarting it from zero would lose its history.
+const COUNTER_ADD_SCRIPT: &str = r#"
+if redis.call("EXISTS", KEYS[1]) == 0 then
+    return false
+end
+local value = redis.call("INCRBY", KEYS[1], ARGV[1])
+redis.call("EXPIRE", KEYS[1], ARGV[2])
+return value
+"#;
+
+/// Set of counters changed since they were last flushed to Mongo
+const COUNTER_DIRTY_KEY: &str = "counter:dirty";
+
+/// How long a cache rebuild may hold its lock before others take over
+const CACHE_REBUILD_LOCK_MS: u64 = 5_000;
+/// How often waiters poll for a rebuilt cache entry
+const CACHE_REBUILD_POLL: Duration = Duration::from_millis(50);
+/// Fraction of the TTL added as random jitter (1/10 = up to 10%)
+const CACHE_TTL_JITTER_DIVISOR: u64 = 10;
+
+/// How the application reaches Redis
+#[derive(Debug, Clone)]
+pub enum RedisTopology {
+    /// A single server; `rediss://` URLs enable TLS
+    Standalone { url: String },
+    /// Resolve the master named `master_name` through Sentinel
+    Sentinel {
+        sentinel_urls: Vec<String>,
+        master_name: String,
+    },
+    /// Redis Cluster seeded from one or more nodes
+    Cluster { nodes: Vec<String> },
 }
 
-impl RedisClient {
-    /// Initialize Redis connection from environment variable
-    pub async fn init() -> Result<Self, String> {
-        let redis_url =
-            env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string());
+/// Redis connection and reconnect settings
+#[derive(Debug, Clone)]
+pub struct RedisConfig {
+    pub topology: RedisTopology,
+    /// Reconnect attempts before a command fails
+    pub reconnect_retries: usize,
+    /// Base delay for exponential reconnect backoff
+    pub reconnect_base_delay_ms: u64,
+    /// Upper bound on a single reconnect delay
+    pub reconnect_max_delay_ms: u64,
+}
+
+impl RedisConfig {
+    /// Load Redis configuration from environment variables.
+    ///
+    /// `REDIS_MODE` selects `standalone` (default, `REDIS_URL`), `sentinel`
+    /// (`REDIS_SENTINEL_URLS`, `REDIS_SENTINEL_MASTER`) or `cluster`
+    /// (`REDIS_CLUSTER_NODES`). URL lists are comma separated.
+    pub fn from_env() -> Result<Self, String> {
+        let mode = env::var("REDIS_MODE").unwrap_or_else(|_| "standalone".to_string());
+
+        let topology = match mode.to_lowercase().as_str() {
+            "standalone" => RedisTopology::Standalone {
+                url: env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string()),
+            },
+            "sentinel" => RedisTopology::Sentinel {
+                sentinel_urls: url_list("REDIS_SENTINEL_URLS")?,
+                master_name: env::var("REDIS_SENTINEL_MASTER")
+                    .map_err(|_| "REDIS_SENTINEL_MASTER is required in sentinel mode")?,
+            },
+            "cluster" => RedisTopology::Cluster {
+                nodes: url_list("REDIS_CLUSTER_NODES")?,
+            },
+            other => return Err(format!("Unknown REDIS_MODE: {}", other)),
+        };
+
+        Ok(Self {
+            topology,
+            reconnect_retries: parse_optional("REDIS_RECONNECT_RETRIES")?.unwrap_or(6),
+            reconnect_base_delay_ms: parse_optional("REDIS_RECONNECT_BASE_DELAY_MS")?
+                .unwrap_or(100),
+            reconnect_max_delay_ms: parse_optional("REDIS_RECONNECT_MAX_DELAY_MS")?
+                .unwrap_or(5_000),
+        })
+    }
+}
+
+/// Parse a required comma-separated list of URLs
+fn url_list(name: &str) -> Result<Vec<String>, String> {
+    let urls: Vec<String> = env::var(name)
+        .unwrap_or_default()
+        .split(',')
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty())
+        .collect();
+
+    if urls.is_empty() {
+        return Err(format!("{} must list at least one URL", name));
+    }
+    Ok(urls)
+}
+
+/// A command connection to a single node, a Sentinel-managed master or a
+/// cluster.
+///
+/// Every variant reconnects on its own: the standalone connection through
+/// `ConnectionManager` with exponential backoff, the Sentinel connection by
+/// asking Sentinel for the master again when the old one is lost, and the
+/// cluster connection by refreshing slots and retrying against the new owner.
+#[derive(Clone)]
+pub enum RedisConnection {
+    Single(ConnectionManager),
+    Sentinel(SentinelConnection),
+    Cluster(ClusterConnection),
+}
+
+impl ConnectionLike for RedisConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        match self {
+            RedisConnection::Single(conn) => conn.req_packed_command(cmd),
+            RedisConnection::Sentinel(conn) => conn.req_packed_command(cmd),
+            RedisConnection::Cluster(conn) => conn.req_packed_command(cmd),
+        }
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        match self {
+            RedisConnection::Single(conn) => conn.req_packed_commands(cmd, offset, count),
+            RedisConnection::Sentinel(conn) => conn.req_packed_commands(cmd, offset, count),
+            RedisConnection::Cluster(conn) => conn.req_packed_commands(cmd, offset, count),
+        }
+    }
+
+    fn get_db(&self) -> i64 {
+        match self {
+            RedisConnection::Single(conn) => conn.get_db(),
+            RedisConnection::Sentinel(conn) => conn.get_db(),
+            RedisConnection::Cluster(conn) => conn.get_db(),
+        }
+    }
+}
+
+/// The master Sentinel currently names for a service, with its connection
+struct SentinelMaster {
+    client: Client,
+    connection: ConnectionManager,
+    /// Bumped on every failover, so concurrent callers that saw the same
+    /// dead master resolve it only once
+    generation: u64,
+}
 
-        let client =
-            Client::open(redis_url).map_err(|e| format!("Failed to create Redis client: {}", e))?;
+/// Connection to a Sentinel-managed master that follows failovers.
+///
+/// Commands go through a `ConnectionManager`, which retries the current
+/// master with backoff. When that master stays unreachable, or answers
+/// READONLY because it was demoted to a replica, Sentinel is asked for the
+/// master again and the command is retried once against it.
+#[derive(Clone)]
+pub struct SentinelConnection {
+    sentinel: Arc<tokio::sync::Mutex<Sentinel>>,
+    master_name: String,
+    config: RedisConfig,
+    master: Arc<RwLock<SentinelMaster>>,
+}
+
+impl SentinelConnection {
+    async fn connect(
+        sentinel_urls: &[String],
+        master_name: &str,
+        config: &RedisConfig,
+    ) -> Result<Self, String> {
+        let mut sentinel = Sentinel::build(sentinel_urls.to_vec())
+            .map_err(|e| format!("Failed to create Sentinel client: {}", e))?;
+        let (client, connection) = Self::resolve(&mut sentinel, master_name, config).await?;
 
-        let connection = client
-            .get_multiplexed_async_connection()
+        Ok(Self {
+            sentinel: Arc::new(tokio::sync::Mutex::new(sentinel)),
+            master_name: master_name.to_string(),
+            config: config.clone(),
+            master: Arc::new(RwLock::new(SentinelMaster {
+                client,
+                connection,
+                generation: 0,
+            })),
+        })
+    }
+
+    /// Ask Sentinel for the current master and connect to it
+    async fn resolve(
+        sentinel: &mut Sentinel,
+        master_name: &str,
+        config: &RedisConfig,
+    ) -> Result<(Client, ConnectionManager), String> {
+        let client = sentinel
+            .async_master_for(master_name, None)
+            .await
+            .map_err(|e| format!("Failed to resolve master {}: {}", master_name, e))?;
+        let connection = ConnectionManager::new_with_config(client.clone(), manager_config(config))
             .await
-            .map_err(|e| format!("Failed to connect to Redis: {}", e))?;
+            .map_err(|e| format!("Failed to connect to Redis master {}: {}", master_name, e))?;
+        Ok((client, connection))
+    }
+
+    /// Client for the current master, to open dedicated connections
+    pub fn client(&self) -> Client {
+        self.master
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .client
+            .clone()
+    }
+
+    fn current(&self) -> (u64, ConnectionManager) {
+        let master = self.master.read().unwrap_or_else(|e| e.into_inner());
+        (master.generation, master.connection.clone())
+    }
+
+    /// Whether an error means the master is gone rather than the command
+    /// being wrong
+    fn master_lost(error: &RedisError) -> bool {
+        error.is_io_error()
+            || error.is_connection_dropped()
+            || error.is_connection_refusal()
+            || error.kind() == ErrorKind::ReadOnly
+    }
+
+    /// Replace the master seen at `generation`, unless another caller
+    /// already did, and return the connection to use now
+    async fn failover(&self, generation: u64) -> RedisResult<ConnectionManager> {
+        let mut sentinel = self.sentinel.lock().await;
+        let (current, connection) = self.current();
+        if current != generation {
+            return Ok(connection);
+        }
+
+        let (client, connection) = Self::resolve(&mut sentinel, &self.master_name, &self.config)
+            .await
+            .map_err(|e| RedisError::from((ErrorKind::IoError, "Sentinel failover failed", e)))?;
+        log::warn!("Redis master {} changed; reconnected", self.master_name);
+
+        let mut master = self.master.write().unwrap_or_else(|e| e.into_inner());
+        *master = SentinelMaster {
+            client,
+            connection: connection.clone(),
+            generation: generation + 1,
+        };
+        Ok(connection)
+    }
+}
+
+impl ConnectionLike for SentinelConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        Box::pin(async move {
+            let (generation, mut connection) = self.current();
+            match connection.req_packed_command(cmd).await {
+                Err(e) if Self::master_lost(&e) => {
+                    self.failover(generation)
+                        .await?
+                        .req_packed_command(cmd)
+                        .await
+                }
+                result => result,
+            }
+        })
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        Box::pin(async move {
+            let (generation, mut connection) = self.current();
+            match connection.req_packed_commands(cmd, offset, count).await {
+                Err(e) if Self::master_lost(&e) => {
+                    self.failover(generation)
+                        .await?
+                        .req_packed_commands(cmd, offset, count)
+                        .await
+                }
+                result => result,
+            }
+        })
+    }
+
+    fn get_db(&self) -> i64 {
+        self.current().1.get_db()
+    }
+}
+
+/// Backoff settings for a `ConnectionManager`
+fn manager_config(config: &RedisConfig) -> ConnectionManagerConfig {
+    ConnectionManagerConfig::new()
+        .set_exponent_base(2)
+        .set_factor(config.reconnect_base_delay_ms)
+        .set_max_delay(config.reconnect_max_delay_ms)
+        .set_number_of_retries(config.reconnect_retries)
+}
+
+/// Redis connection wrapper
+pub struct RedisClient {
+    /// Client for dedicated connections (pub/sub); in cluster mode this
+    /// points at the first seed node, which is enough since PUBLISH is
+    /// broadcast cluster-wide
+    client: Client,
+    connection: RedisConnection,
+}
+
+impl RedisClient {
+    /// Initialize Redis connection from explicit configuration
+    #[tracing::instrument(skip_all)]
+    pub async fn with_config(config: RedisConfig) -> Result<Self, String> {
+        let (client, connection) = match &config.topology {
+            RedisTopology::Standalone { url } => {
+                let client = Client::open(url.as_str())
+                    .map_err(|e| format!("Failed to create Redis client: {}", e))?;
+                let connection = Self::connection_manager(&client, &config).await?;
+                (client, connection)
+            }
+            RedisTopology::Sentinel {
+                sentinel_urls,
+                master_name,
+            } => {
+                let connection =
+                    SentinelConnection::connect(sentinel_urls, master_name, &config).await?;
+                (connection.client(), RedisConnection::Sentinel(connection))
+            }
+            RedisTopology::Cluster { nodes } => {
+                let cluster = ClusterClient::builder(nodes.clone())
+                    .retries(config.reconnect_retries as u32)
+                    .min_retry_wait(config.reconnect_base_delay_ms)
+                    .max_retry_wait(config.reconnect_max_delay_ms)
+                    .build()
+                    .map_err(|e| format!("Failed to create Redis cluster client: {}", e))?;
+                let connection = cluster
+                    .get_async_connection()
+                    .await
+                    .map_err(|e| format!("Failed to connect to Redis cluster: {}", e))?;
+                let client = Client::open(nodes[0].as_str())
+                    .map_err(|e| format!("Failed to create Redis client: {}", e))?;
+                (client, RedisConnection::Cluster(connection))
+            }
+        };
 
         println!("✅ Connected successfully to Redis");
 
-        Ok(Self { connection })
+        Ok(Self { client, connection })
+    }
+
+    /// Open a self-healing connection that retries with exponential backoff
+    #[tracing::instrument(skip_all)]
+    async fn connection_manager(
+        client: &Client,
+        config: &RedisConfig,
+    ) -> Result<RedisConnection, String> {
+        ConnectionManager::new_with_config(client.clone(), manager_config(config))
+            .await
+            .map(RedisConnection::Single)
+            .map_err(|e| format!("Failed to connect to Redis: {}", e))
     }
 
     /// Get the Redis connection
-    pub fn get_connection(&self) -> MultiplexedConnection {
+    pub fn get_connection(&self) -> RedisConnection {
         self.connection.clone()
     }
+
+    /// Get the underlying client (used to open dedicated connections). With
+    /// Sentinel this is the master at the time of the call.
+    pub fn get_client(&self) -> Client {
+        match &self.connection {
+            RedisConnection::Sentinel(connection) => connection.client(),
+            _ => self.client.clone(),
+        }
+    }
+}
+
+/// A pub/sub channel carrying JSON-encoded payloads of type `T`
+pub struct Channel<T> {
+    name: String,
+    _payload: PhantomData<fn() -> T>,
+}
+
+impl<T> Channel<T> {
+    /// Create a typed channel handle
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            _payload: PhantomData,
+        }
+    }
+
+    /// Get the channel name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Outcome of a rate limit check
+#[derive(Debug, Clone)]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    pub limit: u64,
+    pub remaining: u64,
+    /// Seconds until the oldest request in the window expires
+    pub reset_after_seconds: u64,
+    /// Seconds until the next request would be accepted (0 when allowed)
+    pub retry_after_seconds: u64,
+}
+
+/// The Redis calls handlers make directly, so handler tests can stub the
+/// rate limiter and cache. `RedisService` implements it by delegating to its
+/// inherent methods.
+pub trait RedisServiceTrait: Send + Sync + 'static {
+    fn sliding_window_check(
+        &self,
+        key: &str,
+        max_requests: u64,
+        window_seconds: u64,
+    ) -> impl Future<Output = Result<RateLimitDecision, String>> + Send;
+
+    fn cache_delete(&self, key: &str) -> impl Future<Output = Result<(), String>> + Send;
 }
 
 /// Redis service for session and cache management
 #[derive(Clone)]
 pub struct RedisService {
-    connection: MultiplexedConnection,
+    client: Client,
+    connection: RedisConnection,
 }
 
 impl RedisService {
     /// Create a new Redis service
     pub fn new(client: &RedisClient) -> Self {
         Self {
+            client: client.get_client(),
             connection: client.get_connection(),
         }
     }
@@ -51,6 +485,7 @@ impl RedisService {
     // ============================================
 
     /// Store a session token in Redis
+    #[tracing::instrument(skip_all)]
     pub async fn store_session(
         &self,
         user_id: &str,
@@ -74,6 +509,7 @@ impl RedisService {
     }
 
     /// Validate a session token
+    #[tracing::instrument(skip_all)]
     pub async fn validate_session(&self, token: &str) -> Result<Option<String>, String> {
         let mut conn = self.connection.clone();
         let token_key = format!("token:{}", token);
@@ -87,6 +523,7 @@ impl RedisService {
     }
 
     /// Get user's current session token
+    #[tracing::instrument(skip_all)]
     pub async fn get_session(&self, user_id: &str) -> Result<Option<String>, String> {
         let mut conn = self.connection.clone();
         let key = format!("session:{}", user_id);
@@ -100,6 +537,7 @@ impl RedisService {
     }
 
     /// Invalidate a user's session (logout)
+    #[tracing::instrument(skip_all)]
     pub async fn invalidate_session(&self, user_id: &str) -> Result<(), String> {
         let mut conn = self.connection.clone();
         let session_key = format!("session:{}", user_id);
@@ -119,9 +557,101 @@ impl RedisService {
         Ok(())
     }
 
-    /// Invalidate all sessions for a user
+    /// Invalidate all sessions for a user, including their refresh tokens
+    #[tracing::instrument(skip_all)]
     pub async fn invalidate_all_sessions(&self, user_id: &str) -> Result<(), String> {
-        self.invalidate_session(user_id).await
+        self.invalidate_session(user_id).await?;
+        self.revoke_refresh_tokens(user_id).await
+    }
+
+    /// Store a refresh token by its hash, indexed under the user so every
+    /// token can be revoked at once
+    #[tracing::instrument(skip_all)]
+    pub async fn store_refresh_token(
+        &self,
+        user_id: &str,
+        token_hash: &str,
+        expiry_seconds: u64,
+    ) -> Result<(), String> {
+        let mut conn = self.connection.clone();
+        let token_key = format!("refresh:{}", token_hash);
+        let user_key = format!("refresh_tokens:{}", user_id);
+
+        conn.set_ex::<_, _, ()>(&token_key, user_id, expiry_seconds)
+            .await
+            .map_err(|e| format!("Failed to store refresh token: {}", e))?;
+        conn.sadd::<_, _, ()>(&user_key, token_hash)
+            .await
+            .map_err(|e| format!("Failed to index refresh token: {}", e))?;
+        conn.expire::<_, ()>(&user_key, expiry_seconds as i64)
+            .await
+            .map_err(|e| format!("Failed to index refresh token: {}", e))?;
+
+        Ok(())
+    }
+
+    /// Consume a refresh token, returning its user. A token can be taken
+    /// only once, so a replayed token finds nothing.
+    #[tracing::instrument(skip_all)]
+    pub async fn take_refresh_token(&self, token_hash: &str) -> Result<Option<String>, String> {
+        let mut conn = self.connection.clone();
+        let token_key = format!("refresh:{}", token_hash);
+
+        let user_id: Option<String> = conn
+            .get_del(&token_key)
+            .await
+            .map_err(|e| format!("Failed to take refresh token: {}", e))?;
+        if let Some(user_id) = &user_id {
+            conn.srem::<_, _, ()>(format!("refresh_tokens:{}", user_id), token_hash)
+                .await
+                .map_err(|e| format!("Failed to unindex refresh token: {}", e))?;
+        }
+
+        Ok(user_id)
+    }
+
+    /// Revoke every refresh token issued to a user
+    #[tracing::instrument(skip_all)]
+    pub async fn revoke_refresh_tokens(&self, user_id: &str) -> Result<(), String> {
+        let mut conn = self.connection.clone();
+        let user_key = format!("refresh_tokens:{}", user_id);
+
+        let hashes: Vec<String> = conn
+            .smembers(&user_key)
+            .await
+            .map_err(|e| format!("Failed to list refresh tokens: {}", e))?;
+        // One key per command, as the keys may live on different cluster slots
+        for hash in hashes {
+            conn.del::<_, ()>(format!("refresh:{}", hash))
+                .await
+                .map_err(|e| format!("Failed to revoke refresh token: {}", e))?;
+        }
+        conn.del::<_, ()>(&user_key)
+            .await
+            .map_err(|e| format!("Failed to revoke refresh tokens: {}", e))?;
+
+        Ok(())
+    }
+
+    /// Remember the `state` handed to an OAuth provider, to check on callback
+    #[tracing::instrument(skip_all)]
+    pub async fn store_oauth_state(&self, state: &str, expiry_seconds: u64) -> Result<(), String> {
+        let mut conn = self.connection.clone();
+        conn.set_ex::<_, _, ()>(format!("oauth_state:{}", state), 1, expiry_seconds)
+            .await
+            .map_err(|e| format!("Failed to store OAuth state: {}", e))
+    }
+
+    /// Whether an OAuth `state` was issued here and not yet used; it is
+    /// consumed either way
+    #[tracing::instrument(skip_all)]
+    pub async fn take_oauth_state(&self, state: &str) -> Result<bool, String> {
+        let mut conn = self.connection.clone();
+        let stored: Option<String> = conn
+            .get_del(format!("oauth_state:{}", state))
+            .await
+            .map_err(|e| format!("Failed to take OAuth state: {}", e))?;
+        Ok(stored.is_some())
     }
 
     // ============================================
@@ -129,6 +659,7 @@ impl RedisService {
     // ============================================
 
     /// Set a cache value with expiration
+    #[tracing::instrument(skip_all)]
     pub async fn cache_set(
         &self,
         key: &str,
@@ -146,6 +677,7 @@ impl RedisService {
     }
 
     /// Get a cached value
+    #[tracing::instrument(skip_all)]
     pub async fn cache_get(&self, key: &str) -> Result<Option<String>, String> {
         let mut conn = self.connection.clone();
         let cache_key = format!("cache:{}", key);
@@ -159,6 +691,7 @@ impl RedisService {
     }
 
     /// Delete a cached value
+    #[tracing::instrument(skip_all)]
     pub async fn cache_delete(&self, key: &str) -> Result<(), String> {
         let mut conn = self.connection.clone();
         let cache_key = format!("cache:{}", key);
@@ -171,6 +704,7 @@ impl RedisService {
     }
 
     /// Set a cache value with JSON serialization
+    #[tracing::instrument(skip_all)]
     pub async fn cache_set_json<T: serde::Serialize>(
         &self,
         key: &str,
@@ -183,6 +717,7 @@ impl RedisService {
     }
 
     /// Get a cached JSON value
+    #[tracing::instrument(skip_all)]
     pub async fn cache_get_json<T: serde::de::DeserializeOwned>(
         &self,
         key: &str,
@@ -198,6 +733,7 @@ impl RedisService {
     }
 
     /// Check if a cache key exists
+    #[tracing::instrument(skip_all)]
     pub async fn cache_exists(&self, key: &str) -> Result<bool, String> {
         let mut conn = self.connection.clone();
         let cache_key = format!("cache:{}", key);
@@ -211,6 +747,7 @@ impl RedisService {
     }
 
     /// Set cache TTL (time to live)
+    #[tracing::instrument(skip_all)]
     pub async fn cache_expire(&self, key: &str, seconds: u64) -> Result<(), String> {
         let mut conn = self.connection.clone();
         let cache_key = format!("cache:{}", key);
@@ -222,11 +759,81 @@ impl RedisService {
         Ok(())
     }
 
+    /// Read a JSON value from cache, computing and storing it on a miss.
+    ///
+    /// Only one caller per key runs `fetch` at a time; concurrent callers wait
+    /// for it to populate the cache instead of all hitting the database. The
+    /// TTL gets random jitter so entries cached together don't expire together.
+    /// Redis errors are logged and fall back to calling `fetch` directly.
+    #[tracing::instrument(skip_all)]
+    pub async fn cache_get_or_set_json<T, E, F, Fut>(
+        &self,
+        key: &str,
+        expiry_seconds: u64,
+        fetch: F,
+    ) -> Result<T, E>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        match self.cache_get_json::<T>(key).await {
+            Ok(Some(value)) => return Ok(value),
+            Ok(None) => {}
+            Err(e) => log::warn!("Cache read failed for {}: {}", key, e),
+        }
+
+        let lock_key = format!("cache:{}", key);
+        let token = match self.acquire_lock(&lock_key, CACHE_REBUILD_LOCK_MS).await {
+            Ok(token) => token,
+            Err(e) => {
+                log::warn!("Cache lock failed for {}: {}", key, e);
+                return fetch().await;
+            }
+        };
+
+        let Some(token) = token else {
+            // Someone else is rebuilding; wait for them up to the lock TTL
+            let attempts = CACHE_REBUILD_LOCK_MS / CACHE_REBUILD_POLL.as_millis() as u64;
+            for _ in 0..attempts {
+                tokio::time::sleep(CACHE_REBUILD_POLL).await;
+                if let Ok(Some(value)) = self.cache_get_json::<T>(key).await {
+                    return Ok(value);
+                }
+            }
+            return fetch().await;
+        };
+
+        // The previous holder may have filled the cache while we were acquiring
+        if let Ok(Some(value)) = self.cache_get_json::<T>(key).await {
+            let _ = self.release_lock(&lock_key, &token).await;
+            return Ok(value);
+        }
+
+        let result = fetch().await;
+        if let Ok(value) = &result {
+            let jitter = rand::rng().random_range(0..=expiry_seconds / CACHE_TTL_JITTER_DIVISOR);
+            if let Err(e) = self
+                .cache_set_json(key, value, expiry_seconds + jitter)
+                .await
+            {
+                log::warn!("Cache write failed for {}: {}", key, e);
+            }
+        }
+
+        if let Err(e) = self.release_lock(&lock_key, &token).await {
+            log::warn!("Failed to release cache lock for {}: {}", key, e);
+        }
+
+        result
+    }
+
     // ============================================
     // Rate Limiting Helper
     // ============================================
 
     /// Increment a rate limit counter
+    #[tracing::instrument(skip_all)]
     pub async fn rate_limit_increment(
         &self,
         key: &str,
@@ -252,6 +859,7 @@ impl RedisService {
     }
 
     /// Check if rate limit is exceeded
+    #[tracing::instrument(skip_all)]
     pub async fn is_rate_limited(
         &self,
         key: &str,
@@ -261,9 +869,556 @@ impl RedisService {
         let count = self.rate_limit_increment(key, window_seconds).await?;
         Ok(count > max_requests)
     }
+
+    /// Sliding-window rate limit backed by a sorted set of request timestamps.
+    ///
+    /// Unlike the fixed window above, this never admits more than
+    /// `max_requests` within any `window_seconds` span.
+    #[tracing::instrument(skip_all)]
+    pub async fn sliding_window_check(
+        &self,
+        key: &str,
+        max_requests: u64,
+        window_seconds: u64,
+    ) -> Result<RateLimitDecision, String> {
+        let mut conn = self.connection.clone();
+        let rate_key = format!("ratelimit:sw:{}", key);
+        let now_ms = chrono::Utc::now().timestamp_millis();
+        let window_ms = (window_seconds * 1000) as i64;
+        let member = format!("{}-{}", now_ms, Uuid::new_v4());
+
+        let (count, oldest): (u64, Vec<(String, f64)>) = redis::pipe()
+            .atomic()
+            .zrembyscore(&rate_key, 0, now_ms - window_ms)
+            .ignore()
+            .zadd(&rate_key, &member, now_ms)
+            .ignore()
+            .zcard(&rate_key)
+            .zrange_withscores(&rate_key, 0, 0)
+            .pexpire(&rate_key, window_ms)
+            .ignore()
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| format!("Failed to check rate limit: {}", e))?;
+
+        let oldest_ms = oldest
+            .first()
+            .map(|(_, score)| *score as i64)
+            .unwrap_or(now_ms);
+        let reset_after_seconds = ((oldest_ms + window_ms - now_ms).max(0) as u64).div_ceil(1000);
+
+        if count <= max_requests {
+            return Ok(RateLimitDecision {
+                allowed: true,
+                limit: max_requests,
+                remaining: max_requests - count,
+                reset_after_seconds,
+                retry_after_seconds: 0,
+            });
+        }
+
+        // Rejected requests don't occupy a slot in the window
+        conn.zrem::<_, _, ()>(&rate_key, &member)
+            .await
+            .map_err(|e| format!("Failed to update rate limit: {}", e))?;
+
+        Ok(RateLimitDecision {
+            allowed: false,
+            limit: max_requests,
+            remaining: 0,
+            reset_after_seconds,
+            retry_after_seconds: reset_after_seconds,
+        })
+    }
+
+    /// Start a cooldown of `seconds` on `key` unless one is running.
+    ///
+    /// Returns 0 when the cooldown was started, otherwise the seconds left on
+    /// the running one.
+    #[tracing::instrument(skip_all)]
+    pub async fn cooldown_start(&self, key: &str, seconds: u64) -> Result<u64, String> {
+        let mut conn = self.connection.clone();
+        let cooldown_key = format!("cooldown:{}", key);
+
+        let started: Option<String> = redis::cmd("SET")
+            .arg(&cooldown_key)
+            .arg(1)
+            .arg("NX")
+            .arg("EX")
+            .arg(seconds)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| format!("Failed to start cooldown: {}", e))?;
+        if started.is_some() {
+            return Ok(0);
+        }
+
+        let remaining: i64 = conn
+            .ttl(&cooldown_key)
+            .await
+            .map_err(|e| format!("Failed to read cooldown: {}", e))?;
+
+        // The key may expire between the two commands
+        Ok(remaining.max(1) as u64)
+    }
+
+    // ============================================
+    // Leaderboards
+    // ============================================
+
+    /// Add `by` to a member's score on a leaderboard, refreshing its expiry
+    #[tracing::instrument(skip_all)]
+    pub async fn leaderboard_increment(
+        &self,
+        board: &str,
+        member: &str,
+        by: f64,
+        expiry_seconds: u64,
+    ) -> Result<f64, String> {
+        let mut conn = self.connection.clone();
+        let key = format!("leaderboard:{}", board);
+
+        let (score,): (f64,) = redis::pipe()
+            .atomic()
+            .zincr(&key, member, by)
+            .expire(&key, expiry_seconds as i64)
+            .ignore()
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| format!("Failed to update leaderboard: {}", e))?;
+
+        Ok(score)
+    }
+
+    /// Get the highest-scoring members of a leaderboard, best first
+    #[tracing::instrument(skip_all)]
+    pub async fn leaderboard_top(
+        &self,
+        board: &str,
+        limit: usize,
+    ) -> Result<Vec<(String, f64)>, String> {
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut conn = self.connection.clone();
+        let key = format!("leaderboard:{}", board);
+
+        let entries: Vec<(String, f64)> = conn
+            .zrevrange_withscores(&key, 0, limit as isize - 1)
+            .await
+            .map_err(|e| format!("Failed to read leaderboard: {}", e))?;
+
+        Ok(entries)
+    }
+
+    // ============================================
+    // Chat Presence
+    // ============================================
+
+    /// Mark users as online for `ttl_seconds`; the chat server refreshes
+    /// this while they stay connected
+    #[tracing::instrument(skip_all)]
+    pub async fn presence_set_online(
+        &self,
+        user_ids: &[String],
+        ttl_seconds: u64,
+    ) -> Result<(), String> {
+        if user_ids.is_empty() {
+            return Ok(());
+        }
+        let mut conn = self.connection.clone();
+        let mut pipe = redis::pipe();
+        for user_id in user_ids {
+            pipe.set_ex(format!("presence:online:{}", user_id), 1, ttl_seconds)
+                .ignore();
+        }
+
+        let () = pipe
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| format!("Failed to update presence: {}", e))?;
+
+        Ok(())
+    }
+
+    /// Mark a user as offline
+    #[tracing::instrument(skip_all)]
+    pub async fn presence_set_offline(&self, user_id: &str) -> Result<(), String> {
+        let mut conn = self.connection.clone();
+
+        conn.del::<_, ()>(format!("presence:online:{}", user_id))
+            .await
+            .map_err(|e| format!("Failed to update presence: {}", e))
+    }
+
+    /// Mark a user as typing in a room, or clear it. Typing expires by
+    /// itself after `ttl_seconds` in case the stop is never sent.
+    #[tracing::instrument(skip_all)]
+    pub async fn presence_set_typing(
+        &self,
+        room_id: &str,
+        user_id: &str,
+        typing: bool,
+        ttl_seconds: u64,
+    ) -> Result<(), String> {
+        let mut conn = self.connection.clone();
+        let key = format!("presence:typing:{}:{}", room_id, user_id);
+
+        let result = if typing {
+            conn.set_ex::<_, _, ()>(&key, 1, ttl_seconds).await
+        } else {
+            conn.del::<_, ()>(&key).await
+        };
+        result.map_err(|e| format!("Failed to update typing state: {}", e))
+    }
+
+    /// Whether each user is online, and typing in the paired room
+    #[tracing::instrument(skip_all)]
+    pub async fn presence_lookup(
+        &self,
+        pairs: &[(String, String)],
+    ) -> Result<Vec<(bool, bool)>, String> {
+        if pairs.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut conn = self.connection.clone();
+        let mut pipe = redis::pipe();
+        for (room_id, user_id) in pairs {
+            pipe.exists(format!("presence:online:{}", user_id))
+                .exists(format!("presence:typing:{}:{}", room_id, user_id));
+        }
+
+        let flags: Vec<bool> = pipe
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| format!("Failed to read presence: {}", e))?;
+
+        Ok(flags
+            .chunks_exact(2)
+            .map(|flags| (flags[0], flags[1]))
+            .collect())
+    }
+
+    // ============================================
+    // Chat Replay
+    // ============================================
+
+    /// Keys for a room's sequence counter and replay buffer. The hash tag
+    /// keeps both on one cluster slot for the store script.
+    fn replay_keys(room_id: &str) -> (String, String) {
+        (
+            format!("chat:seq:{{{}}}", room_id),
+            format!("chat:replay:{{{}}}", room_id),
+        )
+    }
+
+    /// The last sequence number given out in a room, 0 before its first
+    /// event
+    #[tracing::instrument(skip_all)]
+    pub async fn replay_latest(&self, room_id: &str) -> Result<u64, String> {
+        let mut conn = self.connection.clone();
+        let (seq_key, _) = Self::replay_keys(room_id);
+
+        let latest: Option<u64> = conn
+            .get(&seq_key)
+            .await
+            .map_err(|e| format!("Failed to read chat sequence: {}", e))?;
+        Ok(latest.unwrap_or(0))
+    }
+
+    /// Buffer a room event under the sequence number it was sent with. The
+    /// counter only moves forward and never expires, so numbers don't
+    /// restart when a quiet room's buffer does.
+    #[tracing::instrument(skip_all)]
+    pub async fn replay_store(
+        &self,
+        room_id: &str,
+        seq: u64,
+        payload: &str,
+        capacity: usize,
+        ttl_seconds: u64,
+    ) -> Result<(), String> {
+        let mut conn = self.connection.clone();
+        let (seq_key, buffer_key) = Self::replay_keys(room_id);
+
+        let _: u64 = redis::Script::new(REPLAY_STORE_SCRIPT)
+            .key(seq_key)
+            .key(buffer_key)
+            .arg(seq)
+            .arg(payload)
+            .arg(capacity)
+            .arg(ttl_seconds)
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| format!("Failed to buffer chat event: {}", e))?;
+        Ok(())
+    }
+
+    /// Buffered events of a room after `after`, oldest first, and the room's
+    /// latest sequence number
+    #[tracing::instrument(skip_all)]
+    pub async fn replay_since(
+        &self,
+        room_id: &str,
+        after: u64,
+    ) -> Result<(Vec<(u64, String)>, u64), String> {
+        let mut conn = self.connection.clone();
+        let (seq_key, buffer_key) = Self::replay_keys(room_id);
+
+        let (members, latest): (Vec<String>, Option<u64>) = redis::pipe()
+            .zrangebyscore(&buffer_key, format!("({}", after), "+inf")
+            .get(&seq_key)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| format!("Failed to read chat replay: {}", e))?;
+
+        let events = members
+            .into_iter()
+            .filter_map(|member| {
+                let (seq, payload) = member.split_once(':')?;
+                Some((seq.parse().ok()?, payload.to_string()))
+            })
+            .collect();
+        Ok((events, latest.unwrap_or(0)))
+    }
+
+    // ============================================
+    // Counters
+    // ============================================
+
+    /// Add `by` to a cached counter, refreshing its expiry. Returns None when
+    /// the counter isn't cached, so the caller can load it first.
+    #[tracing::instrument(skip_all)]
+    pub async fn counter_add(
+        &self,
+        counter_id: &str,
+        by: i64,
+        expiry_seconds: u64,
+    ) -> Result<Option<i64>, String> {
+        let mut conn = self.connection.clone();
+
+        redis::Script::new(COUNTER_ADD_SCRIPT)
+            .key(format!("counter:{}", counter_id))
+            .arg(by)
+            .arg(expiry_seconds)
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| format!("Failed to update counter: {}", e))
+    }
+
+    /// Cache a counter's value. With `only_if_missing` an already cached
+    /// value wins, so a load can't overwrite a concurrent update; returns
+    /// whether the value was written.
+    #[tracing::instrument(skip_all)]
+    pub async fn counter_set(
+        &self,
+        counter_id: &str,
+        value: i64,
+        expiry_seconds: u64,
+        only_if_missing: bool,
+    ) -> Result<bool, String> {
+        let mut conn = self.connection.clone();
+        let mut cmd = redis::cmd("SET");
+        cmd.arg(format!("counter:{}", counter_id))
+            .arg(value)
+            .arg("EX")
+            .arg(expiry_seconds);
+        if only_if_missing {
+            cmd.arg("NX");
+        }
+
+        let written: Option<String> = cmd
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| format!("Failed to set counter: {}", e))?;
+
+        Ok(written.is_some())
+    }
+
+    /// Cached values of `counter_ids`, None where a counter isn't cached
+    #[tracing::instrument(skip_all)]
+    pub async fn counter_get_many(
+        &self,
+        counter_ids: &[String],
+    ) -> Result<Vec<Option<i64>>, String> {
+        if counter_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut conn = self.connection.clone();
+        let keys: Vec<String> = counter_ids
+            .iter()
+            .map(|counter_id| format!("counter:{}", counter_id))
+            .collect();
+
+        // MGET explicitly: a one-key `mget` would be sent as GET
+        redis::cmd("MGET")
+            .arg(&keys)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| format!("Failed to read counters: {}", e))
+    }
+
+    /// Queue counters for the next flush to Mongo
+    #[tracing::instrument(skip_all)]
+    pub async fn counter_mark_dirty(&self, counter_ids: &[String]) -> Result<(), String> {
+        if counter_ids.is_empty() {
+            return Ok(());
+        }
+
+        let mut conn = self.connection.clone();
+        conn.sadd::<_, _, ()>(COUNTER_DIRTY_KEY, counter_ids)
+            .await
+            .map_err(|e| format!("Failed to queue counter flush: {}", e))
+    }
+
+    /// Take up to `count` counters queued for flushing
+    #[tracing::instrument(skip_all)]
+    pub async fn counter_take_dirty(&self, count: usize) -> Result<Vec<String>, String> {
+        let mut conn = self.connection.clone();
+
+        redis::cmd("SPOP")
+            .arg(COUNTER_DIRTY_KEY)
+            .arg(count)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| format!("Failed to take queued counters: {}", e))
+    }
+
+    // ============================================
+    // Distributed Locks
+    // ============================================
+
+    /// Try to acquire a lock for `ttl_ms` milliseconds.
+    ///
+    /// Returns the owner token on success, or None if another instance holds it.
+    #[tracing::instrument(skip_all)]
+    pub async fn acquire_lock(&self, key: &str, ttl_ms: u64) -> Result<Option<String>, String> {
+        let mut conn = self.connection.clone();
+        let lock_key = format!("lock:{}", key);
+        let token = Uuid::new_v4().to_string();
+
+        let acquired: Option<String> = redis::cmd("SET")
+            .arg(&lock_key)
+            .arg(&token)
+            .arg("NX")
+            .arg("PX")
+            .arg(ttl_ms)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| format!("Failed to acquire lock: {}", e))?;
+
+        Ok(acquired.map(|_| token))
+    }
+
+    /// Release a lock, but only if `token` still owns it
+    #[tracing::instrument(skip_all)]
+    pub async fn release_lock(&self, key: &str, token: &str) -> Result<bool, String> {
+        let mut conn = self.connection.clone();
+        let lock_key = format!("lock:{}", key);
+
+        let released: i64 = redis::Script::new(RELEASE_LOCK_SCRIPT)
+            .key(&lock_key)
+            .arg(token)
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| format!("Failed to release lock: {}", e))?;
+
+        Ok(released == 1)
+    }
+
+    // ============================================
+    // Pub/Sub
+    // ============================================
+
+    /// Publish a payload on a channel, returning the number of receivers
+    #[tracing::instrument(skip_all)]
+    pub async fn publish<T: Serialize>(
+        &self,
+        channel: &Channel<T>,
+        payload: &T,
+    ) -> Result<u64, String> {
+        let mut conn = self.connection.clone();
+        let message = serde_json::to_string(payload)
+            .map_err(|e| format!("Failed to serialize message: {}", e))?;
+
+        let receivers: u64 = conn
+            .publish(channel.name(), message)
+            .await
+            .map_err(|e| format!("Failed to publish message: {}", e))?;
+
+        Ok(receivers)
+    }
+
+    /// Subscribe to a channel on a dedicated connection.
+    ///
+    /// Messages that fail to decode are logged and skipped.
+    #[tracing::instrument(skip_all)]
+    pub async fn subscribe<T: DeserializeOwned>(
+        &self,
+        channel: &Channel<T>,
+    ) -> Result<impl Stream<Item = T> + use<T>, String> {
+        let client = match &self.connection {
+            RedisConnection::Sentinel(connection) => connection.client(),
+            _ => self.client.clone(),
+        };
+        let mut pubsub = client
+            .get_async_pubsub()
+            .await
+            .map_err(|e| format!("Failed to open pub/sub connection: {}", e))?;
+
+        pubsub
+            .subscribe(channel.name())
+            .await
+            .map_err(|e| format!("Failed to subscribe to {}: {}", channel.name(), e))?;
+
+        let stream = pubsub.into_on_message().filter_map(|msg| async move {
+            let payload: String = match msg.get_payload() {
+                Ok(payload) => payload,
+                Err(e) => {
+                    log::warn!(
+                        "Invalid pub/sub payload on {}: {}",
+                        msg.get_channel_name(),
+                        e
+                    );
+                    return None;
+                }
+            };
+
+            match serde_json::from_str(&payload) {
+                Ok(value) => Some(value),
+                Err(e) => {
+                    log::warn!(
+                        "Failed to decode message on {}: {}",
+                        msg.get_channel_name(),
+                        e
+                    );
+                    None
+                }
+            }
+        });
+
+        Ok(stream)
+    }
+}
+
+impl RedisServiceTrait for RedisService {
+    async fn sliding_window_check(
+        &self,
+        key: &str,
+        max_requests: u64,
+        window_seconds: u64,
+    ) -> Result<RateLimitDecision, String> {
+        Self::sliding_window_check(self, key, max_requests, window_seconds).await
+    }
+
+    async fn cache_delete(&self, key: &str) -> Result<(), String> {
+        Self::cache_delete(self, key).await
+    }
 }
 
 /// Convenience function to connect to Redis
-pub async fn connect_to_redis() -> Result<RedisClient, String> {
-    RedisClient::init().await
+pub async fn connect_to_redis(config: &RedisConfig) -> Result<RedisClient, String> {
+    RedisClient::with_config(config.clone()).await
 }
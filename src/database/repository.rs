@@ -0,0 +1,350 @@
+use crate::utils::datetime::bson_now;
+use crate::utils::error::CustomError;
+use futures_util::TryStreamExt;
+use mongodb::bson::{Document, doc, oid::ObjectId};
+use mongodb::{Client, ClientSession, Collection};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::future::Future;
+
+/// A page of results returned by `Repository::find_paginated`
+#[derive(Debug, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub page: u64,
+    pub per_page: u64,
+    pub total: u64,
+}
+
+impl<T> Page<T> {
+    /// Convert each item, keeping the page position
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            page: self.page,
+            per_page: self.per_page,
+            total: self.total,
+        }
+    }
+}
+
+/// Common persistence operations shared by every collection.
+///
+/// Soft-deleted documents (with a `deleted_at` timestamp) and documents hidden
+/// by moderation (`hidden_at`) are excluded from all reads.
+pub trait Repository<T>: Send + Sync {
+    /// Find a document by its `_id`
+    fn find_by_id(
+        &self,
+        id: &ObjectId,
+    ) -> impl Future<Output = Result<Option<T>, CustomError>> + Send;
+
+    /// Find the first document matching a filter
+    fn find_one(
+        &self,
+        filter: Document,
+    ) -> impl Future<Output = Result<Option<T>, CustomError>> + Send;
+
+    /// Find documents matching a filter, one page at a time (pages start at
+    /// 1, and pages hold at least one item)
+    fn find_paginated(
+        &self,
+        filter: Document,
+        sort: Document,
+        page: u64,
+        per_page: u64,
+    ) -> impl Future<Output = Result<Page<T>, CustomError>> + Send;
+
+    /// Count documents matching a filter
+    fn count(&self, filter: Document) -> impl Future<Output = Result<u64, CustomError>> + Send;
+
+    /// Count documents matching a filter, soft-deleted and hidden ones
+    /// included, e.g. to check a value a unique index covers
+    fn count_including_deleted(
+        &self,
+        filter: Document,
+    ) -> impl Future<Output = Result<u64, CustomError>> + Send;
+
+    /// Insert a document and return its `_id`
+    fn insert(&self, item: &T) -> impl Future<Output = Result<ObjectId, CustomError>> + Send;
+
+    /// Insert a document as part of a transaction and return its `_id`
+    fn insert_in_session(
+        &self,
+        item: &T,
+        session: &mut ClientSession,
+    ) -> impl Future<Output = Result<ObjectId, CustomError>> + Send;
+
+    /// Apply `$set` changes to a document, returning whether it was found
+    fn update(
+        &self,
+        id: &ObjectId,
+        changes: Document,
+    ) -> impl Future<Output = Result<bool, CustomError>> + Send;
+
+    /// Apply a full update document (`$set`, `$push`, ...) to the first
+    /// document matching a filter, returning whether one was found
+    fn update_one(
+        &self,
+        filter: Document,
+        update: Document,
+    ) -> impl Future<Output = Result<bool, CustomError>> + Send;
+
+    /// Apply `$set` changes only if the stored `version` matches, bumping it.
+    /// Returns whether the document was found and fails with a conflict when
+    /// someone else updated it first.
+    fn update_versioned(
+        &self,
+        id: &ObjectId,
+        expected_version: i64,
+        changes: Document,
+    ) -> impl Future<Output = Result<bool, CustomError>> + Send;
+
+    /// Mark a document as deleted, returning whether it was found
+    fn soft_delete(&self, id: &ObjectId) -> impl Future<Output = Result<bool, CustomError>> + Send;
+
+    /// Mark every document matching a filter as deleted, returning how many
+    fn soft_delete_many(
+        &self,
+        filter: Document,
+    ) -> impl Future<Output = Result<u64, CustomError>> + Send;
+
+    /// Run an aggregation pipeline and collect the raw result documents
+    fn aggregate(
+        &self,
+        pipeline: Vec<Document>,
+    ) -> impl Future<Output = Result<Vec<Document>, CustomError>> + Send;
+}
+
+/// MongoDB-backed repository
+pub struct MongoRepository<T: Send + Sync> {
+    collection: Collection<T>,
+}
+
+impl<T: Send + Sync> MongoRepository<T> {
+    pub fn new(client: &Client, collection_name: &str) -> Self {
+        let collection = client
+            .database("rust_blogdb")
+            .collection::<T>(collection_name);
+        MongoRepository { collection }
+    }
+
+    /// Access the underlying collection for queries the trait doesn't cover
+    pub fn collection(&self) -> &Collection<T> {
+        &self.collection
+    }
+
+    /// Restrict a filter to documents that have not been soft-deleted or
+    /// hidden by a moderator
+    fn not_deleted(mut filter: Document) -> Document {
+        filter.insert("deleted_at", mongodb::bson::Bson::Null);
+        filter.insert("hidden_at", mongodb::bson::Bson::Null);
+        filter
+    }
+}
+
+impl<T> Repository<T> for MongoRepository<T>
+where
+    T: Serialize + DeserializeOwned + Unpin + Send + Sync,
+{
+    #[tracing::instrument(skip_all, fields(collection = %self.collection.name()))]
+    async fn find_by_id(&self, id: &ObjectId) -> Result<Option<T>, CustomError> {
+        self.find_one(doc! { "_id": id }).await
+    }
+
+    #[tracing::instrument(skip_all, fields(collection = %self.collection.name()))]
+    async fn find_one(&self, filter: Document) -> Result<Option<T>, CustomError> {
+        self.collection
+            .find_one(Self::not_deleted(filter))
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to fetch document: {}", e))
+            })
+    }
+
+    #[tracing::instrument(skip_all, fields(collection = %self.collection.name()))]
+    async fn find_paginated(
+        &self,
+        filter: Document,
+        sort: Document,
+        page: u64,
+        per_page: u64,
+    ) -> Result<Page<T>, CustomError> {
+        let page = page.max(1);
+        // A limit of 0 means no limit to MongoDB
+        let per_page = per_page.max(1);
+        let filter = Self::not_deleted(filter);
+
+        let total = self.count(filter.clone()).await?;
+
+        let cursor = self
+            .collection
+            .find(filter)
+            .sort(sort)
+            .skip((page - 1) * per_page)
+            .limit(per_page as i64)
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to fetch documents: {}", e))
+            })?;
+
+        let items: Vec<T> = cursor.try_collect().await.map_err(|e| {
+            CustomError::InternalServerError(format!("Failed to collect documents: {}", e))
+        })?;
+
+        Ok(Page {
+            items,
+            page,
+            per_page,
+            total,
+        })
+    }
+
+    #[tracing::instrument(skip_all, fields(collection = %self.collection.name()))]
+    async fn count(&self, filter: Document) -> Result<u64, CustomError> {
+        self.collection
+            .count_documents(Self::not_deleted(filter))
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to count documents: {}", e))
+            })
+    }
+
+    #[tracing::instrument(skip_all, fields(collection = %self.collection.name()))]
+    async fn count_including_deleted(&self, filter: Document) -> Result<u64, CustomError> {
+        self.collection.count_documents(filter).await.map_err(|e| {
+            CustomError::InternalServerError(format!("Failed to count documents: {}", e))
+        })
+    }
+
+    #[tracing::instrument(skip_all, fields(collection = %self.collection.name()))]
+    async fn insert(&self, item: &T) -> Result<ObjectId, CustomError> {
+        let result = self.collection.insert_one(item).await.map_err(|e| {
+            CustomError::InternalServerError(format!("Failed to insert document: {}", e))
+        })?;
+
+        result.inserted_id.as_object_id().ok_or_else(|| {
+            CustomError::InternalServerError("Failed to get inserted ID".to_string())
+        })
+    }
+
+    #[tracing::instrument(skip_all, fields(collection = %self.collection.name()))]
+    async fn insert_in_session(
+        &self,
+        item: &T,
+        session: &mut ClientSession,
+    ) -> Result<ObjectId, CustomError> {
+        let result = self
+            .collection
+            .insert_one(item)
+            .session(session)
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to insert document: {}", e))
+            })?;
+
+        result.inserted_id.as_object_id().ok_or_else(|| {
+            CustomError::InternalServerError("Failed to get inserted ID".to_string())
+        })
+    }
+
+    #[tracing::instrument(skip_all, fields(collection = %self.collection.name()))]
+    async fn update(&self, id: &ObjectId, changes: Document) -> Result<bool, CustomError> {
+        let result = self
+            .collection
+            .update_one(
+                Self::not_deleted(doc! { "_id": id }),
+                doc! { "$set": changes },
+            )
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to update document: {}", e))
+            })?;
+
+        Ok(result.matched_count > 0)
+    }
+
+    #[tracing::instrument(skip_all, fields(collection = %self.collection.name()))]
+    async fn update_one(&self, filter: Document, update: Document) -> Result<bool, CustomError> {
+        let result = self
+            .collection
+            .update_one(Self::not_deleted(filter), update)
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to update document: {}", e))
+            })?;
+
+        Ok(result.matched_count > 0)
+    }
+
+    #[tracing::instrument(skip_all, fields(collection = %self.collection.name()))]
+    async fn update_versioned(
+        &self,
+        id: &ObjectId,
+        expected_version: i64,
+        changes: Document,
+    ) -> Result<bool, CustomError> {
+        // Documents written before versioning have no field and count as version 0
+        let version_filter = if expected_version == 0 {
+            doc! { "$in": [0_i64, mongodb::bson::Bson::Null] }
+        } else {
+            doc! { "$eq": expected_version }
+        };
+
+        let result = self
+            .collection
+            .update_one(
+                Self::not_deleted(doc! { "_id": id, "version": version_filter }),
+                doc! { "$set": changes, "$inc": { "version": 1_i64 } },
+            )
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to update document: {}", e))
+            })?;
+
+        if result.matched_count > 0 {
+            return Ok(true);
+        }
+
+        // Distinguish a missing document from a stale version
+        if self.count(doc! { "_id": id }).await? > 0 {
+            return Err(CustomError::ConflictError(
+                "The resource was modified by someone else. Reload and try again.".to_string(),
+            ));
+        }
+
+        Ok(false)
+    }
+
+    #[tracing::instrument(skip_all, fields(collection = %self.collection.name()))]
+    async fn soft_delete(&self, id: &ObjectId) -> Result<bool, CustomError> {
+        self.update(id, doc! { "deleted_at": bson_now() }).await
+    }
+
+    #[tracing::instrument(skip_all, fields(collection = %self.collection.name()))]
+    async fn soft_delete_many(&self, filter: Document) -> Result<u64, CustomError> {
+        let result = self
+            .collection
+            .update_many(
+                Self::not_deleted(filter),
+                doc! { "$set": { "deleted_at": bson_now() } },
+            )
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to delete documents: {}", e))
+            })?;
+
+        Ok(result.modified_count)
+    }
+
+    #[tracing::instrument(skip_all, fields(collection = %self.collection.name()))]
+    async fn aggregate(&self, pipeline: Vec<Document>) -> Result<Vec<Document>, CustomError> {
+        let cursor = self.collection.aggregate(pipeline).await.map_err(|e| {
+            CustomError::InternalServerError(format!("Failed to run aggregation: {}", e))
+        })?;
+
+        cursor.try_collect().await.map_err(|e| {
+            CustomError::InternalServerError(format!("Failed to collect aggregation: {}", e))
+        })
+    }
+}
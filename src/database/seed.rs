@@ -0,0 +1,236 @@
+use crate::chat::model::{ChatRoom, RoomType};
+use crate::comment::model::Comment;
+use crate::post::post_model::Post;
+use crate::user::model::{Role, SensitiveContent, User, phone_hash};
+use crate::utils::hashing;
+use crate::utils::i18n::Locale;
+use crate::utils::language::detect_language;
+use chrono::{Duration, Utc};
+use mongodb::Client;
+use mongodb::bson::{Document, doc, oid::ObjectId};
+use rand::Rng;
+use rand::seq::IndexedRandom;
+use std::error::Error;
+use uuid::Uuid;
+
+/// Password shared by every seeded account
+pub const SEED_PASSWORD: &str = "Password123";
+
+const USERNAMES: &[&str] = &[
+    "ada_lovelace",
+    "alan_turing",
+    "grace_hopper",
+    "linus_t",
+    "margaret_h",
+    "dennis_r",
+    "barbara_l",
+    "ken_thompson",
+];
+
+const POST_TITLES: &[&str] = &[
+    "My first week learning Rust",
+    "Weekend hiking photos",
+    "Thoughts on remote work",
+    "Best coffee spots in town",
+    "What I'm reading this month",
+    "A small win at work today",
+    "Tips for staying productive",
+    "Favourite recipes for busy evenings",
+];
+
+const POST_BODIES: &[&str] = &[
+    "Spent the whole evening fighting the borrow checker and I think I finally get it.",
+    "The views from the top were absolutely worth the climb. Highly recommend going early.",
+    "Working from home has its perks, but I really miss the spontaneous chats.",
+    "Found a tiny place with the best flat white I've had in years.",
+    "Currently halfway through a great book on distributed systems.",
+    "Shipped a feature I've been working on for weeks. Feels good!",
+    "Time blocking and turning off notifications changed everything for me.",
+    "Twenty-minute stir fry has become my go-to weeknight dinner.",
+];
+
+const COMMENTS: &[&str] = &[
+    "Love this, thanks for sharing!",
+    "Totally agree with you.",
+    "This is so helpful.",
+    "Haha, been there.",
+    "Great post, looking forward to more.",
+    "Where was this taken?",
+    "Congrats!",
+    "Interesting take, never thought about it that way.",
+];
+
+/// Populate the database with fake users, posts, comments and chat rooms.
+///
+/// Does nothing when any seed account already exists, so running it twice
+/// doesn't duplicate the data.
+pub async fn seed_database(client: &Client) -> Result<(), Box<dyn Error>> {
+    let db = client.database("rust_blogdb");
+    let existing = db
+        .collection::<Document>("users")
+        .count_documents(doc! { "username": { "$in": USERNAMES } })
+        .await?;
+    if existing > 0 {
+        log::info!("Seed users already exist; skipping seeding");
+        return Ok(());
+    }
+
+    let mut rng = rand::rng();
+    let password = hashing::hash_password(SEED_PASSWORD)?;
+
+    // Users
+    let users: Vec<User> = USERNAMES
+        .iter()
+        .enumerate()
+        .map(|(i, username)| {
+            let joined = Utc::now() - Duration::days(rng.random_range(1..365));
+            let phone_number = format!("+1555000{:04}", i);
+            User {
+                id: Some(ObjectId::new()),
+                username: username.to_string(),
+                email: format!("{}@example.com", username),
+                password: Some(password.clone()),
+                providers: Vec::new(),
+                phone_hash: Some(phone_hash(&phone_number)),
+                phone_number,
+                profile_token: None,
+                profile_picture: None,
+                is_email_verified: true,
+                is_phone_verified: false,
+                is_verified: false,
+                role: Role::User,
+                locale: Locale::default(),
+                suspended_until: None,
+                shadow_banned: false,
+                badges: Vec::new(),
+                interests: Vec::new(),
+                sensitive_content: SensitiveContent::default(),
+                content_languages: Vec::new(),
+                created_at: joined,
+                updated_at: joined,
+            }
+        })
+        .collect();
+    db.collection::<User>("users").insert_many(&users).await?;
+    let user_ids: Vec<ObjectId> = users.iter().filter_map(|u| u.id).collect();
+
+    // Posts
+    let posts: Vec<Post> = POST_TITLES
+        .iter()
+        .zip(POST_BODIES.iter())
+        .map(|(title, content)| {
+            let created = Utc::now() - Duration::hours(rng.random_range(1..720));
+            Post {
+                id: ObjectId::new(),
+                title: title.to_string(),
+                content: content.to_string(),
+                author_id: *user_ids.choose(&mut rng).expect("seed users exist"),
+                group_id: None,
+                topics: Vec::new(),
+                images: Vec::new(),
+                version: 0,
+                share_slug: None,
+                share_count: 0,
+                is_sensitive: false,
+                sensitive_locked: false,
+                language: detect_language(content),
+                pinned_at: None,
+                created_at: created,
+                updated_at: created,
+            }
+        })
+        .collect();
+    db.collection::<Post>("posts").insert_many(&posts).await?;
+
+    // Comments
+    let mut comments = Vec::new();
+    for post in &posts {
+        for _ in 0..rng.random_range(1..5) {
+            let (author_id, author) = users
+                .choose(&mut rng)
+                .and_then(|u| u.id.map(|id| (id, u.username.clone())))
+                .expect("seed users exist");
+            let created = post.created_at + Duration::minutes(rng.random_range(1..600));
+            comments.push(Comment {
+                id: None,
+                post_id: post.id,
+                parent_id: None,
+                author_id,
+                author_username: Some(author),
+                content: COMMENTS
+                    .choose(&mut rng)
+                    .expect("comments exist")
+                    .to_string(),
+                version: 0,
+                created_at: created,
+                updated_at: created,
+            });
+        }
+    }
+    db.collection::<Comment>("comments")
+        .insert_many(&comments)
+        .await?;
+
+    // Chat rooms
+    let participant_ids: Vec<String> = user_ids.iter().map(|id| id.to_hex()).collect();
+    let rooms = vec![
+        ChatRoom {
+            id: None,
+            room_id: Uuid::new_v4().to_string(),
+            name: "General".to_string(),
+            room_type: RoomType::Public,
+            participants: participant_ids.clone(),
+            created_by: participant_ids[0].clone(),
+            description: None,
+            avatar_url: None,
+            last_message_at: None,
+            read_only: None,
+            slow_mode_seconds: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        },
+        ChatRoom {
+            id: None,
+            room_id: Uuid::new_v4().to_string(),
+            name: "Book club".to_string(),
+            room_type: RoomType::Group,
+            participants: participant_ids[..4].to_vec(),
+            created_by: participant_ids[1].clone(),
+            description: None,
+            avatar_url: None,
+            last_message_at: None,
+            read_only: None,
+            slow_mode_seconds: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        },
+        ChatRoom {
+            id: None,
+            room_id: Uuid::new_v4().to_string(),
+            name: format!("{} & {}", USERNAMES[0], USERNAMES[1]),
+            room_type: RoomType::Direct,
+            participants: participant_ids[..2].to_vec(),
+            created_by: participant_ids[0].clone(),
+            description: None,
+            avatar_url: None,
+            last_message_at: None,
+            read_only: None,
+            slow_mode_seconds: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        },
+    ];
+    db.collection::<ChatRoom>("chat_rooms")
+        .insert_many(&rooms)
+        .await?;
+
+    log::info!(
+        "Seeded {} users, {} posts, {} comments and {} chat rooms",
+        users.len(),
+        posts.len(),
+        comments.len(),
+        rooms.len()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,142 @@
+use crate::export::model::{CreateExportScheduleRequest, DownloadQuery};
+use crate::export::service::ExportService;
+use crate::middleware::auth::AuthUser;
+use crate::utils::error::CustomError;
+use crate::utils::response::ApiResponse;
+use crate::utils::validation::ValidatedJson;
+use actix_web::http::header;
+use actix_web::{HttpResponse, web};
+use mongodb::bson::oid::ObjectId;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct ExportsQuery {
+    pub limit: Option<i64>,
+}
+
+fn parse_id(id: &str, what: &str) -> Result<ObjectId, CustomError> {
+    ObjectId::parse_str(id)
+        .map_err(|_| CustomError::BadRequestError(format!("Invalid {} ID", what)))
+}
+
+fn schedule_not_found() -> CustomError {
+    CustomError::NotFoundError("Export schedule not found".to_string())
+}
+
+/// GET /admin/exports/schedules
+pub async fn list_export_schedules(
+    auth_user: AuthUser,
+    export_service: web::Data<ExportService>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_admin()?;
+
+    let schedules = export_service.list_schedules().await?;
+
+    Ok(ApiResponse::ok("Export schedules retrieved successfully")
+        .list(schedules)
+        .into())
+}
+
+/// POST /admin/exports/schedules
+pub async fn create_export_schedule(
+    auth_user: AuthUser,
+    export_service: web::Data<ExportService>,
+    body: ValidatedJson<CreateExportScheduleRequest>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_admin()?;
+
+    let schedule = export_service
+        .create_schedule(body.into_inner(), auth_user.id)
+        .await?;
+
+    Ok(ApiResponse::created("Export scheduled successfully")
+        .data(schedule)
+        .into())
+}
+
+/// DELETE /admin/exports/schedules/{id}
+pub async fn delete_export_schedule(
+    auth_user: AuthUser,
+    export_service: web::Data<ExportService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_admin()?;
+    let id = parse_id(&path, "export schedule")?;
+
+    if !export_service.delete_schedule(&id).await? {
+        return Err(schedule_not_found());
+    }
+
+    Ok(ApiResponse::ok("Export schedule deleted successfully").into())
+}
+
+/// Generate and deliver a schedule's export now
+/// POST /admin/exports/schedules/{id}/run
+pub async fn run_export_schedule(
+    auth_user: AuthUser,
+    export_service: web::Data<ExportService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_admin()?;
+    let id = parse_id(&path, "export schedule")?;
+
+    let export = export_service
+        .run_now(&id)
+        .await?
+        .and_then(|file| export_service.summarize(file))
+        .ok_or_else(schedule_not_found)?;
+
+    Ok(ApiResponse::created("Export generated successfully")
+        .data(export)
+        .into())
+}
+
+/// Recent exports with download links
+/// GET /admin/exports?limit=50
+pub async fn list_exports(
+    auth_user: AuthUser,
+    export_service: web::Data<ExportService>,
+    query: web::Query<ExportsQuery>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_admin()?;
+
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+    let exports = export_service.list_exports(limit).await?;
+
+    Ok(ApiResponse::ok("Exports retrieved successfully")
+        .list(exports)
+        .into())
+}
+
+/// Download an export through a signed link; no sign-in needed
+/// GET /exports/{id}/download?expires=...&signature=...
+pub async fn download_export(
+    export_service: web::Data<ExportService>,
+    path: web::Path<String>,
+    query: web::Query<DownloadQuery>,
+) -> Result<HttpResponse, CustomError> {
+    let id = parse_id(&path, "export")?;
+    if !export_service.verify_download(&id, query.expires, &query.signature) {
+        return Err(CustomError::ForbiddenError(
+            "This download link is invalid or has expired".to_string(),
+        ));
+    }
+
+    let file = export_service
+        .get_file(&id)
+        .await?
+        .ok_or_else(|| CustomError::NotFoundError("Export not found".to_string()))?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!(
+                "attachment; filename=\"{}-{}.csv\"",
+                file.report.name(),
+                file.period_end.format("%Y-%m-%d")
+            ),
+        ))
+        .insert_header((header::CACHE_CONTROL, "private, no-store"))
+        .body(file.csv))
+}
@@ -0,0 +1,27 @@
+use super::controller::{
+    create_export_schedule, delete_export_schedule, download_export, list_export_schedules,
+    list_exports, run_export_schedule,
+};
+use crate::middleware::auth::verify_token;
+use crate::middleware::limits::RequestTimeout;
+use actix_web::web;
+use actix_web_httpauth::middleware::HttpAuthentication;
+
+pub fn export_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/admin/exports")
+            .wrap(RequestTimeout::standard())
+            .wrap(HttpAuthentication::bearer(verify_token))
+            .route("", web::get().to(list_exports))
+            .route("/schedules", web::get().to(list_export_schedules))
+            .route("/schedules", web::post().to(create_export_schedule))
+            .route("/schedules/{id}", web::delete().to(delete_export_schedule))
+            .route("/schedules/{id}/run", web::post().to(run_export_schedule)),
+    );
+    // Signed links, opened from emails without a bearer token
+    cfg.service(
+        web::scope("/exports")
+            .wrap(RequestTimeout::standard())
+            .route("/{id}/download", web::get().to(download_export)),
+    );
+}
@@ -0,0 +1,145 @@
+use crate::utils::datetime::{bson_datetime, option_bson_datetime};
+use chrono::{DateTime, Duration, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidateEmail, ValidationError};
+
+/// How long a signed download link works
+pub const EXPORT_LINK_TTL_HOURS: i64 = 72;
+/// Generated files are deleted after this long
+pub const EXPORT_RETENTION_DAYS: u64 = 7;
+
+/// Reports admins can export as CSV
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportReport {
+    /// New accounts per day
+    WeeklySignups,
+    /// Moderation reports filed during the period
+    ContentReports,
+    /// Posts, comments, shares and chat messages per day
+    Engagement,
+}
+
+impl ExportReport {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportReport::WeeklySignups => "weekly_signups",
+            ExportReport::ContentReports => "content_reports",
+            ExportReport::Engagement => "engagement",
+        }
+    }
+}
+
+/// How often a schedule runs; each export covers the period since the
+/// previous one
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFrequency {
+    Daily,
+    Weekly,
+}
+
+impl ExportFrequency {
+    pub fn period(&self) -> Duration {
+        match self {
+            ExportFrequency::Daily => Duration::days(1),
+            ExportFrequency::Weekly => Duration::weeks(1),
+        }
+    }
+}
+
+/// How a finished export reaches the admins
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportDelivery {
+    /// Email a signed download link to the schedule's recipients
+    Email,
+    /// Only list the export, with its signed link, under `/admin/exports`
+    Link,
+}
+
+/// A recurring export, run by the job scheduler
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExportSchedule {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub report: ExportReport,
+    pub frequency: ExportFrequency,
+    pub delivery: ExportDelivery,
+    #[serde(default)]
+    pub recipients: Vec<String>,
+    pub created_by: ObjectId,
+    #[serde(with = "bson_datetime")]
+    pub next_run_at: DateTime<Utc>,
+    #[serde(default, with = "option_bson_datetime")]
+    pub last_run_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub last_export_id: Option<ObjectId>,
+    #[serde(default)]
+    pub last_error: Option<String>,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// A generated CSV file
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExportFile {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    /// Missing for exports run by hand
+    pub schedule_id: Option<ObjectId>,
+    pub report: ExportReport,
+    /// Left out when exports are listed
+    #[serde(default)]
+    pub csv: String,
+    pub row_count: u64,
+    #[serde(with = "bson_datetime")]
+    pub period_start: DateTime<Utc>,
+    #[serde(with = "bson_datetime")]
+    pub period_end: DateTime<Utc>,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// An export as listed to admins, without its contents
+#[derive(Debug, Serialize)]
+pub struct ExportSummary {
+    pub id: ObjectId,
+    pub schedule_id: Option<ObjectId>,
+    pub report: ExportReport,
+    pub row_count: u64,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub download_url: String,
+}
+
+fn email_list(recipients: &[String]) -> Result<(), ValidationError> {
+    if recipients.iter().all(|email| email.validate_email()) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("email").with_message("must be valid email addresses".into()))
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateExportScheduleRequest {
+    pub report: ExportReport,
+    pub frequency: ExportFrequency,
+    pub delivery: ExportDelivery,
+    /// Required for email delivery
+    #[serde(default)]
+    #[validate(
+        length(max = 10, message = "must contain at most 10 addresses"),
+        custom(function = "email_list")
+    )]
+    pub recipients: Vec<String>,
+}
+
+/// Query string of a signed download link
+#[derive(Debug, Deserialize)]
+pub struct DownloadQuery {
+    pub expires: i64,
+    pub signature: String,
+}
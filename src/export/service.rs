@@ -0,0 +1,569 @@
+use crate::api_key::service::hex;
+use crate::export::model::{
+    CreateExportScheduleRequest, EXPORT_LINK_TTL_HOURS, EXPORT_RETENTION_DAYS, ExportDelivery,
+    ExportFile, ExportReport, ExportSchedule, ExportSummary,
+};
+use crate::moderation::model::Report;
+use crate::utils::config::AppConfig;
+use crate::utils::error::CustomError;
+use crate::utils::i18n::Locale;
+use crate::utils::outbox::{EmailOutbox, OutboxPayload};
+use chrono::{DateTime, Duration, NaiveTime, Utc};
+use futures_util::TryStreamExt;
+use hmac::{Hmac, Mac};
+use mongodb::bson::{self, Bson, Document, doc, oid::ObjectId};
+use mongodb::options::IndexOptions;
+use mongodb::{Client, Collection, IndexModel};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::time::Duration as StdDuration;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Recurring CSV exports of admin reports
+pub struct ExportService {
+    schedules: Collection<ExportSchedule>,
+    files: Collection<ExportFile>,
+    users: Collection<Document>,
+    reports: Collection<Report>,
+    posts: Collection<Document>,
+    comments: Collection<Document>,
+    shares: Collection<Document>,
+    messages: Collection<Document>,
+    outbox: EmailOutbox,
+    /// Origin download links point at
+    public_base_url: String,
+    /// Key download links are signed with
+    signing_key: String,
+}
+
+impl ExportService {
+    pub fn new(client: &Client, config: &AppConfig) -> Self {
+        let db = client.database("rust_blogdb");
+        ExportService {
+            schedules: db.collection::<ExportSchedule>("export_schedules"),
+            files: db.collection::<ExportFile>("export_files"),
+            users: db.collection::<Document>("users"),
+            reports: db.collection::<Report>("reports"),
+            posts: db.collection::<Document>("posts"),
+            comments: db.collection::<Document>("comments"),
+            shares: db.collection::<Document>("post_shares"),
+            messages: db.collection::<Document>("chat_messages"),
+            outbox: EmailOutbox::new(client, config),
+            public_base_url: config.public_base_url.clone(),
+            signing_key: config.export_signing_secret.clone(),
+        }
+    }
+
+    /// Due schedules are polled; generated files expire
+    #[tracing::instrument(skip_all)]
+    pub async fn ensure_indexes(&self) -> Result<(), CustomError> {
+        self.schedules
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "next_run_at": 1 })
+                    .build(),
+            )
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to create export indexes: {}", e))
+            })?;
+
+        self.files
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "created_at": 1 })
+                    .options(
+                        IndexOptions::builder()
+                            .expire_after(StdDuration::from_secs(EXPORT_RETENTION_DAYS * 86400))
+                            .build(),
+                    )
+                    .build(),
+            )
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to create export indexes: {}", e))
+            })?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn list_schedules(&self) -> Result<Vec<ExportSchedule>, CustomError> {
+        self.schedules
+            .find(doc! {})
+            .sort(doc! { "created_at": 1 })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))
+    }
+
+    /// Schedule an export; the first one runs at the next UTC midnight
+    #[tracing::instrument(skip_all)]
+    pub async fn create_schedule(
+        &self,
+        request: CreateExportScheduleRequest,
+        created_by: ObjectId,
+    ) -> Result<ExportSchedule, CustomError> {
+        if request.delivery == ExportDelivery::Email && request.recipients.is_empty() {
+            return Err(CustomError::BadRequestError(
+                "Email delivery needs at least one recipient".to_string(),
+            ));
+        }
+
+        let now = Utc::now();
+        let mut schedule = ExportSchedule {
+            id: None,
+            report: request.report,
+            frequency: request.frequency,
+            delivery: request.delivery,
+            recipients: request.recipients,
+            created_by,
+            next_run_at: start_of_day(now) + Duration::days(1),
+            last_run_at: None,
+            last_export_id: None,
+            last_error: None,
+            created_at: now,
+        };
+        let result = self.schedules.insert_one(&schedule).await.map_err(|e| {
+            CustomError::InternalServerError(format!("Failed to create export schedule: {}", e))
+        })?;
+        schedule.id = result.inserted_id.as_object_id();
+
+        Ok(schedule)
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn delete_schedule(&self, id: &ObjectId) -> Result<bool, CustomError> {
+        let result = self
+            .schedules
+            .delete_one(doc! { "_id": id })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        Ok(result.deleted_count > 0)
+    }
+
+    /// Run a schedule now, outside its regular slots
+    #[tracing::instrument(skip_all)]
+    pub async fn run_now(&self, id: &ObjectId) -> Result<Option<ExportFile>, CustomError> {
+        let schedule = self
+            .schedules
+            .find_one(doc! { "_id": id })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        match schedule {
+            Some(schedule) => self.run(&schedule).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Run every schedule whose time has come, returning how many ran.
+    /// Missed runs collapse into one, covering the latest period.
+    #[tracing::instrument(skip_all)]
+    pub async fn run_due(&self) -> Result<usize, CustomError> {
+        let now = Utc::now();
+        let due: Vec<ExportSchedule> = self
+            .schedules
+            .find(doc! { "next_run_at": { "$lte": bson::DateTime::from_chrono(now) } })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        let mut ran = 0;
+        for schedule in due {
+            let Some(id) = schedule.id else { continue };
+            let period = schedule.frequency.period();
+            let mut next_run_at = schedule.next_run_at + period;
+            while next_run_at <= now {
+                next_run_at += period;
+            }
+
+            // Claim the run, so an overlapping worker can't run it twice
+            let claimed = self
+                .schedules
+                .update_one(
+                    doc! {
+                        "_id": id,
+                        "next_run_at": bson::DateTime::from_chrono(schedule.next_run_at),
+                    },
+                    doc! { "$set": { "next_run_at": bson::DateTime::from_chrono(next_run_at) } },
+                )
+                .await
+                .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+                .modified_count
+                > 0;
+            if !claimed {
+                continue;
+            }
+
+            match self.run(&schedule).await {
+                Ok(_) => ran += 1,
+                Err(e) => log::error!("Export schedule {} failed: {}", id, e),
+            }
+        }
+
+        Ok(ran)
+    }
+
+    /// Generate the export for the schedule's latest full period, deliver
+    /// it and record the outcome on the schedule
+    async fn run(&self, schedule: &ExportSchedule) -> Result<ExportFile, CustomError> {
+        let result = self.generate(schedule).await;
+
+        let update = match &result {
+            Ok(file) => doc! {
+                "last_run_at": bson::DateTime::now(),
+                "last_export_id": file.id,
+                "last_error": Bson::Null,
+            },
+            Err(e) => doc! {
+                "last_run_at": bson::DateTime::now(),
+                "last_error": e.to_string(),
+            },
+        };
+        self.schedules
+            .update_one(doc! { "_id": schedule.id }, doc! { "$set": update })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        result
+    }
+
+    async fn generate(&self, schedule: &ExportSchedule) -> Result<ExportFile, CustomError> {
+        let period_end = start_of_day(Utc::now());
+        let period_start = period_end - schedule.frequency.period();
+        let (rows, row_count) = match schedule.report {
+            ExportReport::WeeklySignups => self.signups(period_start, period_end).await?,
+            ExportReport::ContentReports => self.content_reports(period_start, period_end).await?,
+            ExportReport::Engagement => self.engagement(period_start, period_end).await?,
+        };
+
+        let mut file = ExportFile {
+            id: None,
+            schedule_id: schedule.id,
+            report: schedule.report,
+            csv: to_csv(rows)?,
+            row_count,
+            period_start,
+            period_end,
+            created_at: Utc::now(),
+        };
+        let result = self.files.insert_one(&file).await.map_err(|e| {
+            CustomError::InternalServerError(format!("Failed to store export: {}", e))
+        })?;
+        let id = result
+            .inserted_id
+            .as_object_id()
+            .ok_or_else(|| CustomError::InternalServerError("Export ID missing".to_string()))?;
+        file.id = Some(id);
+
+        if schedule.delivery == ExportDelivery::Email {
+            let download_url = self.download_url(&id);
+            for recipient in &schedule.recipients {
+                self.outbox
+                    .enqueue(
+                        recipient,
+                        OutboxPayload::ReportExport {
+                            report: schedule.report.name().to_string(),
+                            row_count,
+                            download_url: download_url.clone(),
+                            expires_in_hours: EXPORT_LINK_TTL_HOURS as u32,
+                            locale: Locale::default(),
+                        },
+                        None,
+                    )
+                    .await?;
+            }
+        }
+
+        Ok(file)
+    }
+
+    /// Recent exports, newest first, with fresh download links
+    #[tracing::instrument(skip_all)]
+    pub async fn list_exports(&self, limit: i64) -> Result<Vec<ExportSummary>, CustomError> {
+        let files: Vec<ExportFile> = self
+            .files
+            .find(doc! {})
+            .projection(doc! { "csv": 0 })
+            .sort(doc! { "created_at": -1 })
+            .limit(limit)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        Ok(files
+            .into_iter()
+            .filter_map(|file| self.summarize(file))
+            .collect())
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn get_file(&self, id: &ObjectId) -> Result<Option<ExportFile>, CustomError> {
+        self.files
+            .find_one(doc! { "_id": id })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))
+    }
+
+    /// Accounts created per day, and how many of them verified their email
+    async fn signups(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<(Vec<Vec<String>>, u64), CustomError> {
+        let rows: Vec<Document> = self
+            .users
+            .aggregate(vec![
+                doc! { "$match": { "created_at": date_range(start, end) } },
+                doc! { "$group": {
+                    "_id": { "$dateToString": { "format": "%Y-%m-%d", "date": "$created_at" } },
+                    "signups": { "$sum": 1 },
+                    "verified": { "$sum": { "$cond": ["$is_email_verified", 1, 0] } },
+                } },
+            ])
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+        let by_day: HashMap<String, (i64, i64)> = rows
+            .into_iter()
+            .filter_map(|row| {
+                Some((
+                    row.get_str("_id").ok()?.to_string(),
+                    (count(&row, "signups"), count(&row, "verified")),
+                ))
+            })
+            .collect();
+
+        let mut csv = vec![header(&["date", "signups", "verified"])];
+        for day in days(start, end) {
+            let (signups, verified) = by_day.get(&day).copied().unwrap_or_default();
+            csv.push(vec![day, signups.to_string(), verified.to_string()]);
+        }
+        let row_count = (csv.len() - 1) as u64;
+        Ok((csv, row_count))
+    }
+
+    /// Every moderation report filed during the period
+    async fn content_reports(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<(Vec<Vec<String>>, u64), CustomError> {
+        let reports: Vec<Report> = self
+            .reports
+            .find(doc! { "created_at": date_range(start, end) })
+            .sort(doc! { "created_at": 1 })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        let mut csv = vec![header(&[
+            "id",
+            "created_at",
+            "target",
+            "target_id",
+            "offender_id",
+            "reason",
+            "status",
+            "action",
+        ])];
+        for report in reports {
+            csv.push(vec![
+                report.id.map(|id| id.to_hex()).unwrap_or_default(),
+                report.created_at.to_rfc3339(),
+                report.target.name().to_string(),
+                report.target_id.to_hex(),
+                report.offender_id.to_hex(),
+                text_cell(&report.reason),
+                report.status.name().to_string(),
+                report
+                    .action
+                    .map(|action| action.past_tense().to_string())
+                    .unwrap_or_default(),
+            ]);
+        }
+        let row_count = (csv.len() - 1) as u64;
+        Ok((csv, row_count))
+    }
+
+    /// Posts, comments, shares and chat messages per day
+    async fn engagement(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<(Vec<Vec<String>>, u64), CustomError> {
+        let posts = daily_counts(&self.posts, start, end).await?;
+        let comments = daily_counts(&self.comments, start, end).await?;
+        let shares = daily_counts(&self.shares, start, end).await?;
+        let messages = daily_counts(&self.messages, start, end).await?;
+
+        let mut csv = vec![header(&[
+            "date",
+            "posts",
+            "comments",
+            "shares",
+            "chat_messages",
+        ])];
+        for day in days(start, end) {
+            let of = |counts: &HashMap<String, i64>| counts.get(&day).copied().unwrap_or(0);
+            csv.push(vec![
+                day.clone(),
+                of(&posts).to_string(),
+                of(&comments).to_string(),
+                of(&shares).to_string(),
+                of(&messages).to_string(),
+            ]);
+        }
+        let row_count = (csv.len() - 1) as u64;
+        Ok((csv, row_count))
+    }
+
+    /// An export as listed to admins, with a fresh download link
+    pub fn summarize(&self, file: ExportFile) -> Option<ExportSummary> {
+        let id = file.id?;
+        Some(ExportSummary {
+            id,
+            schedule_id: file.schedule_id,
+            report: file.report,
+            row_count: file.row_count,
+            period_start: file.period_start,
+            period_end: file.period_end,
+            created_at: file.created_at,
+            download_url: self.download_url(&id),
+        })
+    }
+
+    /// Link that downloads an export without signing in, until it expires
+    pub fn download_url(&self, id: &ObjectId) -> String {
+        let expires = (Utc::now() + Duration::hours(EXPORT_LINK_TTL_HOURS)).timestamp();
+        format!(
+            "{}/api/v1/exports/{}/download?expires={}&signature={}",
+            self.public_base_url,
+            id.to_hex(),
+            expires,
+            hex(&self.signer(id, expires).finalize().into_bytes())
+        )
+    }
+
+    /// Whether a download link is genuine and unexpired
+    pub fn verify_download(&self, id: &ObjectId, expires: i64, signature: &str) -> bool {
+        if expires < Utc::now().timestamp() {
+            return false;
+        }
+        match decode_hex(signature) {
+            Some(signature) => self.signer(id, expires).verify_slice(&signature).is_ok(),
+            None => false,
+        }
+    }
+
+    fn signer(&self, id: &ObjectId, expires: i64) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(self.signing_key.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(format!("export:{}:{}", id.to_hex(), expires).as_bytes());
+        mac
+    }
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    if value.len() % 2 != 0 {
+        return None;
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(value.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn start_of_day(at: DateTime<Utc>) -> DateTime<Utc> {
+    at.date_naive().and_time(NaiveTime::MIN).and_utc()
+}
+
+fn date_range(start: DateTime<Utc>, end: DateTime<Utc>) -> Document {
+    doc! {
+        "$gte": bson::DateTime::from_chrono(start),
+        "$lt": bson::DateTime::from_chrono(end),
+    }
+}
+
+/// Every UTC day in `[start, end)`, as YYYY-MM-DD
+fn days(start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<String> {
+    start
+        .date_naive()
+        .iter_days()
+        .take_while(|day| *day < end.date_naive())
+        .map(|day| day.format("%Y-%m-%d").to_string())
+        .collect()
+}
+
+/// Documents created per UTC day in `[start, end)`
+async fn daily_counts(
+    collection: &Collection<Document>,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<HashMap<String, i64>, CustomError> {
+    let rows: Vec<Document> = collection
+        .aggregate(vec![
+            doc! { "$match": { "created_at": date_range(start, end) } },
+            doc! { "$group": {
+                "_id": { "$dateToString": { "format": "%Y-%m-%d", "date": "$created_at" } },
+                "count": { "$sum": 1 },
+            } },
+        ])
+        .await
+        .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+        .try_collect()
+        .await
+        .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|row| Some((row.get_str("_id").ok()?.to_string(), count(&row, "count"))))
+        .collect())
+}
+
+fn count(row: &Document, field: &str) -> i64 {
+    match row.get(field) {
+        Some(Bson::Int32(n)) => *n as i64,
+        Some(Bson::Int64(n)) => *n,
+        _ => 0,
+    }
+}
+
+fn header(columns: &[&str]) -> Vec<String> {
+    columns.iter().map(|column| column.to_string()).collect()
+}
+
+/// User-written text, defused so spreadsheets don't run it as a formula
+fn text_cell(value: &str) -> String {
+    if value.starts_with(['=', '+', '-', '@']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    }
+}
+
+fn to_csv(rows: Vec<Vec<String>>) -> Result<String, CustomError> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for row in rows {
+        writer
+            .write_record(&row)
+            .map_err(|e| CustomError::InternalServerError(format!("Failed to write CSV: {}", e)))?;
+    }
+    let bytes = writer
+        .into_inner()
+        .map_err(|e| CustomError::InternalServerError(format!("Failed to write CSV: {}", e)))?;
+    String::from_utf8(bytes).map_err(|e| CustomError::InternalServerError(e.to_string()))
+}
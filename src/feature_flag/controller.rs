@@ -0,0 +1,107 @@
+use crate::feature_flag::model::{CreateFeatureFlagRequest, UpdateFeatureFlagRequest};
+use crate::feature_flag::service::FeatureFlagService;
+use crate::middleware::auth::AuthUser;
+use crate::utils::error::CustomError;
+use crate::utils::i18n::Locale;
+use crate::utils::response::ApiResponse;
+use crate::utils::validation::ValidatedJson;
+use actix_web::{HttpResponse, web};
+
+fn flag_not_found(key: &str) -> CustomError {
+    CustomError::NotFoundError(format!("Feature flag {} not found", key))
+}
+
+/// Every flag evaluated for the caller
+/// GET /feature-flags
+pub async fn my_feature_flags(
+    locale: Locale,
+    auth_user: AuthUser,
+    flag_service: web::Data<FeatureFlagService>,
+) -> Result<HttpResponse, CustomError> {
+    let flags = flag_service.evaluate_all(&auth_user.id).await?;
+
+    Ok(ApiResponse::ok(locale.t("feature-flags-fetched"))
+        .data(flags)
+        .into())
+}
+
+/// GET /admin/feature-flags
+pub async fn list_feature_flags(
+    auth_user: AuthUser,
+    flag_service: web::Data<FeatureFlagService>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_admin()?;
+
+    let flags = flag_service.list().await?;
+
+    Ok(ApiResponse::ok("Feature flags retrieved successfully")
+        .list(flags)
+        .into())
+}
+
+/// POST /admin/feature-flags
+pub async fn create_feature_flag(
+    auth_user: AuthUser,
+    flag_service: web::Data<FeatureFlagService>,
+    body: ValidatedJson<CreateFeatureFlagRequest>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_admin()?;
+
+    let flag = flag_service.create(body.into_inner()).await?;
+
+    Ok(ApiResponse::created("Feature flag created successfully")
+        .data(flag)
+        .into())
+}
+
+/// GET /admin/feature-flags/{key}
+pub async fn get_feature_flag(
+    auth_user: AuthUser,
+    flag_service: web::Data<FeatureFlagService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_admin()?;
+
+    let flag = flag_service
+        .get(&path)
+        .await?
+        .ok_or_else(|| flag_not_found(&path))?;
+
+    Ok(ApiResponse::ok("Feature flag retrieved successfully")
+        .data(flag)
+        .into())
+}
+
+/// PATCH /admin/feature-flags/{key}
+pub async fn update_feature_flag(
+    auth_user: AuthUser,
+    flag_service: web::Data<FeatureFlagService>,
+    path: web::Path<String>,
+    body: ValidatedJson<UpdateFeatureFlagRequest>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_admin()?;
+
+    let flag = flag_service
+        .update(&path, body.into_inner())
+        .await?
+        .ok_or_else(|| flag_not_found(&path))?;
+
+    Ok(ApiResponse::ok("Feature flag updated successfully")
+        .data(flag)
+        .into())
+}
+
+/// DELETE /admin/feature-flags/{key}
+pub async fn delete_feature_flag(
+    auth_user: AuthUser,
+    flag_service: web::Data<FeatureFlagService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_admin()?;
+
+    if !flag_service.delete(&path).await? {
+        return Err(flag_not_found(&path));
+    }
+
+    Ok(ApiResponse::ok("Feature flag deleted successfully").into())
+}
@@ -0,0 +1,27 @@
+use super::controller::{
+    create_feature_flag, delete_feature_flag, get_feature_flag, list_feature_flags,
+    my_feature_flags, update_feature_flag,
+};
+use crate::middleware::auth::verify_token;
+use crate::middleware::limits::RequestTimeout;
+use actix_web::web;
+use actix_web_httpauth::middleware::HttpAuthentication;
+
+pub fn feature_flag_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/feature-flags")
+            .wrap(RequestTimeout::standard())
+            .wrap(HttpAuthentication::bearer(verify_token))
+            .route("", web::get().to(my_feature_flags)),
+    );
+    cfg.service(
+        web::scope("/admin/feature-flags")
+            .wrap(RequestTimeout::standard())
+            .wrap(HttpAuthentication::bearer(verify_token))
+            .route("", web::get().to(list_feature_flags))
+            .route("", web::post().to(create_feature_flag))
+            .route("/{key}", web::get().to(get_feature_flag))
+            .route("/{key}", web::patch().to(update_feature_flag))
+            .route("/{key}", web::delete().to(delete_feature_flag)),
+    );
+}
@@ -0,0 +1,90 @@
+use crate::utils::datetime::bson_datetime;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::LazyLock;
+use validator::{Validate, ValidationError};
+
+static FLAG_KEY: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[a-z][a-z0-9_-]{1,63}$").expect("valid flag key regex"));
+
+fn flag_key(key: &str) -> Result<(), ValidationError> {
+    if FLAG_KEY.is_match(key) {
+        return Ok(());
+    }
+    Err(ValidationError::new("flag_key")
+        .with_message("must be lowercase letters, digits, '_' or '-'".into()))
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FeatureFlag {
+    /// Stable name code checks against, e.g. `reactions`
+    #[serde(rename = "_id")]
+    pub key: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Global switch; nobody gets a disabled flag, not even the allowlist
+    pub enabled: bool,
+    /// Share of users (0-100) that get the flag
+    #[serde(default)]
+    pub rollout_percent: u8,
+    /// Users that get the flag regardless of the rollout
+    #[serde(default)]
+    pub allowlist: Vec<ObjectId>,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "bson_datetime")]
+    pub updated_at: DateTime<Utc>,
+}
+
+impl FeatureFlag {
+    pub fn is_enabled_for(&self, user_id: &ObjectId) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        if self.allowlist.contains(user_id) {
+            return true;
+        }
+        self.bucket(user_id) < self.rollout_percent
+    }
+
+    /// Stable 0-99 bucket per flag and user, so raising the percentage only
+    /// adds users and different flags pick different cohorts
+    fn bucket(&self, user_id: &ObjectId) -> u8 {
+        let digest = Sha256::new()
+            .chain_update(self.key.as_bytes())
+            .chain_update(user_id.bytes())
+            .finalize();
+        let mut prefix = [0u8; 8];
+        prefix.copy_from_slice(&digest[..8]);
+        (u64::from_be_bytes(prefix) % 100) as u8
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateFeatureFlagRequest {
+    #[validate(custom(function = "flag_key"))]
+    pub key: String,
+    #[validate(length(max = 500, message = "must be at most 500 characters"))]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    #[validate(range(max = 100, message = "must be between 0 and 100"))]
+    pub rollout_percent: u8,
+    #[serde(default)]
+    pub allowlist: Vec<ObjectId>,
+}
+
+/// Partial update; omitted fields are unchanged
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateFeatureFlagRequest {
+    #[validate(length(max = 500, message = "must be at most 500 characters"))]
+    pub description: Option<String>,
+    pub enabled: Option<bool>,
+    #[validate(range(max = 100, message = "must be between 0 and 100"))]
+    pub rollout_percent: Option<u8>,
+    pub allowlist: Option<Vec<ObjectId>>,
+}
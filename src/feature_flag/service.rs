@@ -0,0 +1,174 @@
+use crate::database::RedisService;
+use crate::feature_flag::model::{CreateFeatureFlagRequest, FeatureFlag, UpdateFeatureFlagRequest};
+use crate::utils::error::CustomError;
+use crate::utils::sanitize::{Markup, sanitize};
+use chrono::Utc;
+use futures_util::TryStreamExt;
+use mongodb::bson::{self, doc, oid::ObjectId};
+use mongodb::options::ReturnDocument;
+use mongodb::{Client, Collection};
+use std::collections::BTreeMap;
+
+/// How long evaluations may see a stale flag on other instances
+const FLAG_CACHE_SECONDS: u64 = 30;
+
+fn cache_key(key: &str) -> String {
+    format!("feature_flag:{}", key)
+}
+
+pub struct FeatureFlagService {
+    flags: Collection<FeatureFlag>,
+    redis_service: RedisService,
+}
+
+impl FeatureFlagService {
+    pub fn new(client: &Client, redis_service: RedisService) -> Self {
+        let db = client.database("rust_blogdb");
+        FeatureFlagService {
+            flags: db.collection::<FeatureFlag>("feature_flags"),
+            redis_service,
+        }
+    }
+
+    /// Whether a feature is on for the user. Unknown flags are off, and so is
+    /// every flag while the database is unreachable.
+    #[tracing::instrument(skip_all, fields(flag = key))]
+    pub async fn is_enabled(&self, key: &str, user_id: &ObjectId) -> bool {
+        match self.cached(key).await {
+            Ok(Some(flag)) => flag.is_enabled_for(user_id),
+            Ok(None) => false,
+            Err(e) => {
+                log::warn!("Failed to evaluate feature flag {}: {}", key, e);
+                false
+            }
+        }
+    }
+
+    /// Every flag evaluated for one user, for clients to branch on
+    #[tracing::instrument(skip_all)]
+    pub async fn evaluate_all(
+        &self,
+        user_id: &ObjectId,
+    ) -> Result<BTreeMap<String, bool>, CustomError> {
+        Ok(self
+            .list()
+            .await?
+            .into_iter()
+            .map(|flag| {
+                let enabled = flag.is_enabled_for(user_id);
+                (flag.key, enabled)
+            })
+            .collect())
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn list(&self) -> Result<Vec<FeatureFlag>, CustomError> {
+        let cursor = self
+            .flags
+            .find(doc! {})
+            .sort(doc! { "_id": 1 })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        cursor
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn get(&self, key: &str) -> Result<Option<FeatureFlag>, CustomError> {
+        self.flags
+            .find_one(doc! { "_id": key })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn create(
+        &self,
+        request: CreateFeatureFlagRequest,
+    ) -> Result<FeatureFlag, CustomError> {
+        let now = Utc::now();
+        let flag = FeatureFlag {
+            key: request.key,
+            description: request
+                .description
+                .map(|description| sanitize(&description, Markup::None)),
+            enabled: request.enabled,
+            rollout_percent: request.rollout_percent,
+            allowlist: request.allowlist,
+            created_at: now,
+            updated_at: now,
+        };
+
+        if self.get(&flag.key).await?.is_some() {
+            return Err(CustomError::ConflictError(format!(
+                "Feature flag {} already exists",
+                flag.key
+            )));
+        }
+        self.flags.insert_one(&flag).await.map_err(|e| {
+            CustomError::InternalServerError(format!("Failed to create feature flag: {}", e))
+        })?;
+        self.invalidate(&flag.key).await;
+
+        Ok(flag)
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn update(
+        &self,
+        key: &str,
+        request: UpdateFeatureFlagRequest,
+    ) -> Result<Option<FeatureFlag>, CustomError> {
+        let mut set = doc! { "updated_at": bson::DateTime::now() };
+        if let Some(description) = request.description {
+            set.insert("description", sanitize(&description, Markup::None));
+        }
+        if let Some(enabled) = request.enabled {
+            set.insert("enabled", enabled);
+        }
+        if let Some(rollout_percent) = request.rollout_percent {
+            set.insert("rollout_percent", rollout_percent as i32);
+        }
+        if let Some(allowlist) = request.allowlist {
+            set.insert("allowlist", allowlist);
+        }
+
+        let flag = self
+            .flags
+            .find_one_and_update(doc! { "_id": key }, doc! { "$set": set })
+            .return_document(ReturnDocument::After)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+        self.invalidate(key).await;
+
+        Ok(flag)
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn delete(&self, key: &str) -> Result<bool, CustomError> {
+        let result = self
+            .flags
+            .delete_one(doc! { "_id": key })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+        self.invalidate(key).await;
+
+        Ok(result.deleted_count > 0)
+    }
+
+    /// Flags are read on hot paths, so evaluations go through Redis
+    async fn cached(&self, key: &str) -> Result<Option<FeatureFlag>, CustomError> {
+        self.redis_service
+            .cache_get_or_set_json(&cache_key(key), FLAG_CACHE_SECONDS, || self.get(key))
+            .await
+    }
+
+    async fn invalidate(&self, key: &str) {
+        if let Err(e) = self.redis_service.cache_delete(&cache_key(key)).await {
+            log::warn!("Failed to invalidate feature flag {}: {}", key, e);
+        }
+    }
+}
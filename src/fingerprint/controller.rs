@@ -0,0 +1,93 @@
+use crate::fingerprint::model::{DEVICE_FINGERPRINT_HEADER, FingerprintKind};
+use crate::fingerprint::service::{ClientFingerprint, FingerprintService};
+use crate::middleware::auth::AuthUser;
+use crate::middleware::rate_limit::client_ip;
+use crate::utils::error::CustomError;
+use crate::utils::response::ApiResponse;
+use actix_web::http::header::USER_AGENT;
+use actix_web::{HttpRequest, HttpResponse, web};
+use mongodb::bson::oid::ObjectId;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct ClustersQuery {
+    pub kind: Option<String>,
+    pub min_accounts: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RelatedQuery {
+    pub limit: Option<i64>,
+}
+
+/// Where a request came from: the client IP, and the device header or
+/// failing that the user agent
+pub fn client_fingerprint(req: &HttpRequest) -> ClientFingerprint {
+    let header = |name| {
+        req.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    };
+    let device = header(DEVICE_FINGERPRINT_HEADER)
+        .or_else(|| header(USER_AGENT.as_str()))
+        .unwrap_or_default()
+        .to_string();
+
+    ClientFingerprint {
+        ip: client_ip(req),
+        device,
+    }
+}
+
+/// Fingerprints shared by several accounts
+/// GET /admin/fingerprints/clusters?kind=ip&min_accounts=2&limit=50
+pub async fn list_fingerprint_clusters(
+    auth_user: AuthUser,
+    fingerprint_service: web::Data<FingerprintService>,
+    query: web::Query<ClustersQuery>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_admin()?;
+
+    let kind = match query.kind.as_deref() {
+        Some(name) => Some(FingerprintKind::from_name(name).ok_or_else(|| {
+            CustomError::BadRequestError("kind must be one of ip or device".to_string())
+        })?),
+        None => None,
+    };
+    let min_accounts = query.min_accounts.unwrap_or(2).max(2);
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+
+    let clusters = fingerprint_service
+        .clusters(kind, min_accounts, limit)
+        .await?;
+
+    Ok(
+        ApiResponse::ok("Fingerprint clusters retrieved successfully")
+            .list(clusters)
+            .into(),
+    )
+}
+
+/// Accounts sharing a fingerprint with a user, for ban evasion checks
+/// GET /admin/fingerprints/users/{user_id}?limit=50
+pub async fn get_related_accounts(
+    auth_user: AuthUser,
+    fingerprint_service: web::Data<FingerprintService>,
+    path: web::Path<String>,
+    query: web::Query<RelatedQuery>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_admin()?;
+
+    let user_id = ObjectId::parse_str(path.into_inner())
+        .map_err(|_| CustomError::BadRequestError("Invalid user ID".to_string()))?;
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+
+    let clusters = fingerprint_service.related(&user_id, limit).await?;
+
+    Ok(ApiResponse::ok("Related accounts retrieved successfully")
+        .list(clusters)
+        .into())
+}
@@ -0,0 +1,15 @@
+use super::controller::{get_related_accounts, list_fingerprint_clusters};
+use crate::middleware::auth::verify_token;
+use crate::middleware::limits::RequestTimeout;
+use actix_web::web;
+use actix_web_httpauth::middleware::HttpAuthentication;
+
+pub fn fingerprint_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/admin/fingerprints")
+            .wrap(RequestTimeout::standard())
+            .wrap(HttpAuthentication::bearer(verify_token))
+            .route("/clusters", web::get().to(list_fingerprint_clusters))
+            .route("/users/{user_id}", web::get().to(get_related_accounts)),
+    );
+}
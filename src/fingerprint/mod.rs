@@ -0,0 +1,4 @@
+pub mod controller;
+pub mod index;
+pub mod model;
+pub mod service;
@@ -0,0 +1,79 @@
+use crate::utils::datetime::bson_datetime;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+/// Header clients may set with a stable device identifier; the user agent
+/// is used when it is missing
+pub const DEVICE_FINGERPRINT_HEADER: &str = "X-Device-Fingerprint";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FingerprintKind {
+    Ip,
+    Device,
+}
+
+impl FingerprintKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "ip" => Some(FingerprintKind::Ip),
+            "device" => Some(FingerprintKind::Device),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FingerprintKind::Ip => "ip",
+            FingerprintKind::Device => "device",
+        }
+    }
+}
+
+/// When a fingerprint was seen
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FingerprintEvent {
+    Registration,
+    Login,
+}
+
+impl FingerprintEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FingerprintEvent::Registration => "registration",
+            FingerprintEvent::Login => "login",
+        }
+    }
+}
+
+/// A salted hash of an IP address or device seen on an account. The raw
+/// values are never stored.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AccountFingerprint {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: ObjectId,
+    pub kind: FingerprintKind,
+    pub hash: String,
+    /// Set once, when the account was registered from this fingerprint
+    #[serde(default)]
+    pub at_registration: bool,
+    pub seen_count: i64,
+    #[serde(with = "bson_datetime")]
+    pub first_seen: DateTime<Utc>,
+    #[serde(with = "bson_datetime")]
+    pub last_seen: DateTime<Utc>,
+}
+
+/// Accounts sharing one fingerprint
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FingerprintCluster {
+    pub kind: FingerprintKind,
+    pub hash: String,
+    pub user_ids: Vec<ObjectId>,
+    pub account_count: i64,
+    #[serde(with = "bson_datetime")]
+    pub last_seen: DateTime<Utc>,
+}
@@ -0,0 +1,202 @@
+use crate::api_key::service::hash_key;
+use crate::fingerprint::model::{
+    AccountFingerprint, FingerprintCluster, FingerprintEvent, FingerprintKind,
+};
+use crate::utils::datetime::bson_now;
+use crate::utils::error::CustomError;
+use futures_util::TryStreamExt;
+use mongodb::bson::{Document, doc, oid::ObjectId};
+use mongodb::options::IndexOptions;
+use mongodb::{Client, Collection, IndexModel};
+use std::future::Future;
+
+/// The IP address and device a request came from
+#[derive(Debug, Clone)]
+pub struct ClientFingerprint {
+    pub ip: String,
+    pub device: String,
+}
+
+/// The fingerprint events auth handlers record, so handler tests can ignore
+/// them. `FingerprintService` implements it by delegating to its inherent
+/// methods.
+pub trait FingerprintServiceTrait: Send + Sync + 'static {
+    fn record(
+        &self,
+        user_id: &ObjectId,
+        event: FingerprintEvent,
+        client: &ClientFingerprint,
+    ) -> impl Future<Output = ()> + Send;
+}
+
+/// Records salted fingerprints of where accounts sign up and log in from,
+/// so moderators can spot one person behind several accounts
+pub struct FingerprintService {
+    fingerprints: Collection<AccountFingerprint>,
+    salt: String,
+}
+
+impl FingerprintService {
+    pub fn new(client: &Client, salt: String) -> Self {
+        let db = client.database("rust_blogdb");
+        FingerprintService {
+            fingerprints: db.collection::<AccountFingerprint>("account_fingerprints"),
+            salt,
+        }
+    }
+
+    /// One record per account and fingerprint, and lookups by fingerprint
+    #[tracing::instrument(skip_all)]
+    pub async fn ensure_indexes(&self) -> Result<(), CustomError> {
+        let indexes = vec![
+            IndexModel::builder()
+                .keys(doc! { "user_id": 1, "kind": 1, "hash": 1 })
+                .options(IndexOptions::builder().unique(true).build())
+                .build(),
+            IndexModel::builder()
+                .keys(doc! { "kind": 1, "hash": 1 })
+                .build(),
+        ];
+
+        self.fingerprints
+            .create_indexes(indexes)
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!(
+                    "Failed to create fingerprint indexes: {}",
+                    e
+                ))
+            })?;
+
+        Ok(())
+    }
+
+    fn hash(&self, kind: FingerprintKind, value: &str) -> String {
+        hash_key(&format!("{}:{}:{}", self.salt, kind.name(), value))
+    }
+
+    /// Note the fingerprints of a registration or login. This only feeds
+    /// abuse detection, so failures are logged rather than returned.
+    #[tracing::instrument(skip_all, fields(event = event.name()))]
+    pub async fn record(
+        &self,
+        user_id: &ObjectId,
+        event: FingerprintEvent,
+        client: &ClientFingerprint,
+    ) {
+        let values = [
+            (FingerprintKind::Ip, client.ip.as_str()),
+            (FingerprintKind::Device, client.device.as_str()),
+        ];
+        for (kind, value) in values {
+            if value.is_empty() || value == "unknown" {
+                continue;
+            }
+            let now = bson_now();
+            if let Err(e) = self
+                .fingerprints
+                .update_one(
+                    doc! { "user_id": user_id, "kind": kind.name(), "hash": self.hash(kind, value) },
+                    doc! {
+                        "$inc": { "seen_count": 1 },
+                        "$set": { "last_seen": now },
+                        "$setOnInsert": {
+                            "first_seen": now,
+                            "at_registration": event == FingerprintEvent::Registration,
+                        },
+                    },
+                )
+                .upsert(true)
+                .await
+            {
+                log::warn!("Failed to record {} fingerprint: {}", kind.name(), e);
+            }
+        }
+    }
+
+    /// Fingerprints shared by at least `min_accounts` accounts, largest first
+    #[tracing::instrument(skip_all)]
+    pub async fn clusters(
+        &self,
+        kind: Option<FingerprintKind>,
+        min_accounts: i64,
+        limit: i64,
+    ) -> Result<Vec<FingerprintCluster>, CustomError> {
+        let filter = match kind {
+            Some(kind) => doc! { "kind": kind.name() },
+            None => doc! {},
+        };
+        self.group(filter, min_accounts, limit).await
+    }
+
+    /// Fingerprints the user shares with other accounts
+    #[tracing::instrument(skip_all)]
+    pub async fn related(
+        &self,
+        user_id: &ObjectId,
+        limit: i64,
+    ) -> Result<Vec<FingerprintCluster>, CustomError> {
+        let hashes: Vec<String> = self
+            .fingerprints
+            .distinct("hash", doc! { "user_id": user_id })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .into_iter()
+            .filter_map(|hash| hash.as_str().map(str::to_string))
+            .collect();
+        if hashes.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        self.group(doc! { "hash": { "$in": hashes } }, 2, limit)
+            .await
+    }
+
+    async fn group(
+        &self,
+        filter: Document,
+        min_accounts: i64,
+        limit: i64,
+    ) -> Result<Vec<FingerprintCluster>, CustomError> {
+        let pipeline = vec![
+            doc! { "$match": filter },
+            doc! { "$group": {
+                "_id": { "kind": "$kind", "hash": "$hash" },
+                "user_ids": { "$addToSet": "$user_id" },
+                "last_seen": { "$max": "$last_seen" },
+            } },
+            doc! { "$addFields": { "account_count": { "$size": "$user_ids" } } },
+            doc! { "$match": { "account_count": { "$gte": min_accounts } } },
+            doc! { "$sort": { "account_count": -1, "last_seen": -1 } },
+            doc! { "$limit": limit },
+            doc! { "$project": {
+                "_id": 0,
+                "kind": "$_id.kind",
+                "hash": "$_id.hash",
+                "user_ids": 1,
+                "account_count": 1,
+                "last_seen": 1,
+            } },
+        ];
+
+        self.fingerprints
+            .aggregate(pipeline)
+            .with_type::<FingerprintCluster>()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))
+    }
+}
+
+impl FingerprintServiceTrait for FingerprintService {
+    async fn record(
+        &self,
+        user_id: &ObjectId,
+        event: FingerprintEvent,
+        client: &ClientFingerprint,
+    ) {
+        Self::record(self, user_id, event, client).await
+    }
+}
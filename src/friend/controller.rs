@@ -0,0 +1,293 @@
+use crate::counter::model::Counter;
+use crate::counter::service::CounterService;
+use crate::database::RedisService;
+use crate::friend::model::{
+    FindByContactsRequest, SendFriendRequest, UpdatePrivacySettingsRequest,
+};
+use crate::friend::service::FriendService;
+use crate::middleware::auth::AuthUser;
+use crate::middleware::rate_limit::{
+    CONTACT_SYNC_RATE_LIMIT, CONTACT_SYNC_RATE_WINDOW_SECONDS, check_rate_limit,
+};
+use crate::utils::error::CustomError;
+use crate::utils::i18n::Locale;
+use crate::utils::response::ApiResponse;
+use crate::utils::validation::ValidatedJson;
+use actix_web::{HttpResponse, web};
+use mongodb::bson::oid::ObjectId;
+use serde::Deserialize;
+
+/// Suggestions returned when the client doesn't ask for a number
+pub const DEFAULT_SUGGESTIONS: usize = 10;
+
+#[derive(Debug, Deserialize)]
+pub struct SuggestionsQuery {
+    pub limit: Option<usize>,
+}
+
+/// Apply a friendship change to both users' friend counts. The counts are
+/// derived data, so failures are only logged.
+pub(crate) async fn update_friend_counts(
+    counter_service: &CounterService,
+    users: [&ObjectId; 2],
+    by: Option<i64>,
+) {
+    for user_id in users {
+        let subject = user_id.to_hex();
+        let result = match by {
+            Some(by) => counter_service.add(Counter::Friends, &subject, by).await,
+            // The change to the count isn't known, so recount it
+            None => counter_service.refresh(Counter::Friends, &subject).await,
+        };
+        if let Err(e) = result {
+            log::warn!("Failed to update friend count: {}", e);
+        }
+    }
+}
+
+fn parse_id(id: &str, what: &str) -> Result<ObjectId, CustomError> {
+    ObjectId::parse_str(id)
+        .map_err(|_| CustomError::BadRequestError(format!("Invalid {} ID", what)))
+}
+
+/// GET /friends
+pub async fn list_friends(
+    locale: Locale,
+    auth_user: AuthUser,
+    friend_service: web::Data<FriendService>,
+) -> Result<HttpResponse, CustomError> {
+    let friends = friend_service.list_friends(&auth_user.id).await?;
+
+    Ok(ApiResponse::ok(locale.t("friends-fetched"))
+        .list(friends)
+        .into())
+}
+
+/// DELETE /friends/{user_id}
+pub async fn unfriend(
+    locale: Locale,
+    auth_user: AuthUser,
+    friend_service: web::Data<FriendService>,
+    counter_service: web::Data<CounterService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    let friend_id = parse_id(&path, "user")?;
+    friend_service.unfriend(&auth_user.id, &friend_id).await?;
+    update_friend_counts(&counter_service, [&auth_user.id, &friend_id], Some(-1)).await;
+
+    Ok(ApiResponse::ok(locale.t("friend-removed")).into())
+}
+
+/// Users the caller has blocked
+/// GET /friends/blocks
+pub async fn list_blocked(
+    locale: Locale,
+    auth_user: AuthUser,
+    friend_service: web::Data<FriendService>,
+) -> Result<HttpResponse, CustomError> {
+    let blocks = friend_service.list_blocked(&auth_user.id).await?;
+
+    Ok(ApiResponse::ok(locale.t("blocks-fetched"))
+        .list(blocks)
+        .into())
+}
+
+/// Block a user; this also ends any friendship between the two
+/// POST /friends/blocks/{user_id}
+pub async fn block_user(
+    locale: Locale,
+    auth_user: AuthUser,
+    friend_service: web::Data<FriendService>,
+    counter_service: web::Data<CounterService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    let blocked_id = parse_id(&path, "user")?;
+    let block = friend_service.block(&auth_user.id, &blocked_id).await?;
+    update_friend_counts(&counter_service, [&auth_user.id, &blocked_id], None).await;
+
+    Ok(ApiResponse::ok(locale.t("user-blocked")).data(block).into())
+}
+
+/// DELETE /friends/blocks/{user_id}
+pub async fn unblock_user(
+    locale: Locale,
+    auth_user: AuthUser,
+    friend_service: web::Data<FriendService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    let blocked_id = parse_id(&path, "user")?;
+    friend_service.unblock(&auth_user.id, &blocked_id).await?;
+
+    Ok(ApiResponse::ok(locale.t("user-unblocked")).into())
+}
+
+/// Send a friend request, or accept the one the other user already sent
+/// POST /friends/requests
+pub async fn send_request(
+    locale: Locale,
+    auth_user: AuthUser,
+    friend_service: web::Data<FriendService>,
+    body: ValidatedJson<SendFriendRequest>,
+) -> Result<HttpResponse, CustomError> {
+    let to_id = parse_id(&body.user_id, "user")?;
+    let request = friend_service.send_request(auth_user.id, to_id).await?;
+
+    if request.from_id != auth_user.id {
+        return Ok(ApiResponse::ok(locale.t("friend-request-accepted"))
+            .data(request)
+            .into());
+    }
+    Ok(ApiResponse::created(locale.t("friend-request-sent"))
+        .data(request)
+        .into())
+}
+
+/// Pending requests sent to the caller
+/// GET /friends/requests/incoming
+pub async fn incoming_requests(
+    locale: Locale,
+    auth_user: AuthUser,
+    friend_service: web::Data<FriendService>,
+) -> Result<HttpResponse, CustomError> {
+    let requests = friend_service.incoming(&auth_user.id).await?;
+
+    Ok(ApiResponse::ok(locale.t("friend-requests-fetched"))
+        .list(requests)
+        .into())
+}
+
+/// Pending requests the caller sent
+/// GET /friends/requests/outgoing
+pub async fn outgoing_requests(
+    locale: Locale,
+    auth_user: AuthUser,
+    friend_service: web::Data<FriendService>,
+) -> Result<HttpResponse, CustomError> {
+    let requests = friend_service.outgoing(&auth_user.id).await?;
+
+    Ok(ApiResponse::ok(locale.t("friend-requests-fetched"))
+        .list(requests)
+        .into())
+}
+
+/// POST /friends/requests/{id}/accept
+pub async fn accept_request(
+    locale: Locale,
+    auth_user: AuthUser,
+    friend_service: web::Data<FriendService>,
+    counter_service: web::Data<CounterService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    let request_id = parse_id(&path, "request")?;
+    let request = friend_service.accept(&request_id, &auth_user.id).await?;
+    update_friend_counts(
+        &counter_service,
+        [&request.from_id, &request.to_id],
+        Some(1),
+    )
+    .await;
+
+    Ok(ApiResponse::ok(locale.t("friend-request-accepted"))
+        .data(request)
+        .into())
+}
+
+/// POST /friends/requests/{id}/decline
+pub async fn decline_request(
+    locale: Locale,
+    auth_user: AuthUser,
+    friend_service: web::Data<FriendService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    let request_id = parse_id(&path, "request")?;
+    friend_service.decline(&request_id, &auth_user.id).await?;
+
+    Ok(ApiResponse::ok(locale.t("friend-request-declined")).into())
+}
+
+/// Withdraw a request the caller sent
+/// DELETE /friends/requests/{id}
+pub async fn cancel_request(
+    locale: Locale,
+    auth_user: AuthUser,
+    friend_service: web::Data<FriendService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    let request_id = parse_id(&path, "request")?;
+    friend_service.cancel(&request_id, &auth_user.id).await?;
+
+    Ok(ApiResponse::ok(locale.t("friend-request-cancelled")).into())
+}
+
+/// GET /friends/privacy
+pub async fn get_privacy(
+    locale: Locale,
+    auth_user: AuthUser,
+    friend_service: web::Data<FriendService>,
+) -> Result<HttpResponse, CustomError> {
+    let settings = friend_service.privacy(&auth_user.id).await?;
+
+    Ok(ApiResponse::ok(locale.t("privacy-settings-fetched"))
+        .data(settings)
+        .into())
+}
+
+/// Restrict direct messages and posts to friends, or open them to everyone
+/// PATCH /friends/privacy
+pub async fn update_privacy(
+    locale: Locale,
+    auth_user: AuthUser,
+    friend_service: web::Data<FriendService>,
+    body: ValidatedJson<UpdatePrivacySettingsRequest>,
+) -> Result<HttpResponse, CustomError> {
+    let settings = friend_service
+        .update_privacy(&auth_user.id, body.into_inner())
+        .await?;
+
+    Ok(ApiResponse::ok(locale.t("privacy-settings-updated"))
+        .data(settings)
+        .into())
+}
+
+/// People the caller may know
+/// GET /users/suggestions?limit=10
+pub async fn get_suggestions(
+    locale: Locale,
+    auth_user: AuthUser,
+    friend_service: web::Data<FriendService>,
+    query: web::Query<SuggestionsQuery>,
+) -> Result<HttpResponse, CustomError> {
+    let limit = query.limit.unwrap_or(DEFAULT_SUGGESTIONS).clamp(1, 50);
+    let suggestions = friend_service.suggestions(&auth_user.id, limit).await?;
+
+    Ok(ApiResponse::ok(locale.t("suggestions-fetched"))
+        .list(suggestions)
+        .into())
+}
+
+/// Which of the caller's contacts are on the app. Numbers are sent as
+/// `user::model::phone_hash` digests, never in the clear.
+/// POST /users/find-by-contacts
+pub async fn find_by_contacts(
+    locale: Locale,
+    auth_user: AuthUser,
+    friend_service: web::Data<FriendService>,
+    redis_service: web::Data<RedisService>,
+    body: ValidatedJson<FindByContactsRequest>,
+) -> Result<HttpResponse, CustomError> {
+    check_rate_limit(
+        redis_service.get_ref(),
+        &format!("contacts:{}", auth_user.id),
+        CONTACT_SYNC_RATE_LIMIT,
+        CONTACT_SYNC_RATE_WINDOW_SECONDS,
+    )
+    .await?;
+
+    let matches = friend_service
+        .find_by_contacts(&auth_user.id, &body.phone_hashes)
+        .await?;
+
+    Ok(ApiResponse::ok(locale.t("contacts-matched"))
+        .list(matches)
+        .into())
+}
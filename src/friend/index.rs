@@ -0,0 +1,30 @@
+use super::controller::{
+    accept_request, block_user, cancel_request, decline_request, get_privacy, incoming_requests,
+    list_blocked, list_friends, outgoing_requests, send_request, unblock_user, unfriend,
+    update_privacy,
+};
+use crate::middleware::auth::verify_token;
+use crate::middleware::limits::RequestTimeout;
+use actix_web::web;
+use actix_web_httpauth::middleware::HttpAuthentication;
+
+pub fn friend_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/friends")
+            .wrap(RequestTimeout::standard())
+            .wrap(HttpAuthentication::bearer(verify_token))
+            .route("", web::get().to(list_friends))
+            .route("/privacy", web::get().to(get_privacy))
+            .route("/privacy", web::patch().to(update_privacy))
+            .route("/blocks", web::get().to(list_blocked))
+            .route("/blocks/{user_id}", web::post().to(block_user))
+            .route("/blocks/{user_id}", web::delete().to(unblock_user))
+            .route("/requests", web::post().to(send_request))
+            .route("/requests/incoming", web::get().to(incoming_requests))
+            .route("/requests/outgoing", web::get().to(outgoing_requests))
+            .route("/requests/{id}/accept", web::post().to(accept_request))
+            .route("/requests/{id}/decline", web::post().to(decline_request))
+            .route("/requests/{id}", web::delete().to(cancel_request))
+            .route("/{user_id}", web::delete().to(unfriend)),
+    );
+}
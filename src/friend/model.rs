@@ -0,0 +1,184 @@
+use crate::post::post_model::AuthorSummary;
+use crate::utils::datetime::{bson_datetime, option_bson_datetime};
+use crate::utils::validation::object_id;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError};
+
+/// Prefix of one-to-one chat rooms: `dm:<user id>:<user id>`, ids sorted
+pub const DIRECT_ROOM_PREFIX: &str = "dm:";
+
+/// The two participants of a direct room id, if it is a well-formed one
+pub fn direct_room_participants(room_id: &str) -> Option<(ObjectId, ObjectId)> {
+    let (first, second) = room_id.strip_prefix(DIRECT_ROOM_PREFIX)?.split_once(':')?;
+    if first >= second {
+        return None;
+    }
+    Some((
+        ObjectId::parse_str(first).ok()?,
+        ObjectId::parse_str(second).ok()?,
+    ))
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FriendRequestStatus {
+    Pending,
+    Accepted,
+    Declined,
+    Cancelled,
+}
+
+impl FriendRequestStatus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FriendRequestStatus::Pending => "pending",
+            FriendRequestStatus::Accepted => "accepted",
+            FriendRequestStatus::Declined => "declined",
+            FriendRequestStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FriendRequest {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub from_id: ObjectId,
+    pub to_id: ObjectId,
+    pub status: FriendRequestStatus,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(default, with = "option_bson_datetime")]
+    pub responded_at: Option<DateTime<Utc>>,
+}
+
+/// One direction of a friendship; every friendship is stored as two edges
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Friendship {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: ObjectId,
+    pub friend_id: ObjectId,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// A user hiding another from them: no direct chats either way, and the
+/// blocked user's chat messages are left out of the blocker's history
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Block {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub blocker_id: ObjectId,
+    pub blocked_id: ObjectId,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// Who may reach a user
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Audience {
+    #[default]
+    Everyone,
+    Friends,
+}
+
+/// Per-user privacy preferences; users without a document get the defaults
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PrivacySettings {
+    #[serde(rename = "_id")]
+    pub user_id: ObjectId,
+    /// Who can open a direct chat with the user
+    #[serde(default)]
+    pub direct_messages: Audience,
+    /// Who can read the user's posts outside groups
+    #[serde(default)]
+    pub posts: Audience,
+    /// Whether people with the user's number in their contacts can find them
+    #[serde(default = "discoverable_by_default")]
+    pub discoverable_by_phone: bool,
+}
+
+fn discoverable_by_default() -> bool {
+    true
+}
+
+impl PrivacySettings {
+    pub fn defaults(user_id: ObjectId) -> Self {
+        PrivacySettings {
+            user_id,
+            direct_messages: Audience::Everyone,
+            posts: Audience::Everyone,
+            discoverable_by_phone: true,
+        }
+    }
+}
+
+#[derive(Deserialize, Validate)]
+pub struct SendFriendRequest {
+    #[validate(custom(function = "object_id"))]
+    pub user_id: String,
+}
+
+/// Partial update of privacy settings; omitted fields are unchanged
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdatePrivacySettingsRequest {
+    pub direct_messages: Option<Audience>,
+    pub posts: Option<Audience>,
+    pub discoverable_by_phone: Option<bool>,
+}
+
+/// Hashed address book entries, see `user::model::phone_hash`
+#[derive(Debug, Deserialize, Validate)]
+pub struct FindByContactsRequest {
+    #[validate(
+        length(
+            min = 1,
+            max = 1000,
+            message = "must contain between 1 and 1000 hashes"
+        ),
+        custom(function = "sha256_hexes")
+    )]
+    pub phone_hashes: Vec<String>,
+}
+
+fn sha256_hexes(hashes: &[String]) -> Result<(), ValidationError> {
+    if hashes
+        .iter()
+        .all(|hash| hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()))
+    {
+        Ok(())
+    } else {
+        Err(ValidationError::new("sha256_hex")
+            .with_message("must be hex-encoded SHA-256 digests".into()))
+    }
+}
+
+/// Why a user was suggested
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SuggestionReason {
+    MutualFriends,
+    Popular,
+}
+
+/// Someone the user may want to befriend
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Suggestion {
+    pub user: AuthorSummary,
+    pub reason: SuggestionReason,
+    /// Mutual friends, or the user's friend count for popular accounts
+    pub score: i64,
+}
+
+/// A contact who is on the app
+#[derive(Debug, Serialize)]
+pub struct ContactMatch {
+    /// The submitted hash this user matched
+    pub phone_hash: String,
+    pub user: AuthorSummary,
+    pub is_friend: bool,
+}
@@ -0,0 +1,817 @@
+use crate::friend::model::{
+    Audience, Block, ContactMatch, FriendRequest, FriendRequestStatus, Friendship, PrivacySettings,
+    Suggestion, SuggestionReason, UpdatePrivacySettingsRequest, direct_room_participants,
+};
+use crate::post::post_model::AuthorSummary;
+use crate::user::model::User;
+use crate::utils::datetime::bson_now;
+use crate::utils::error::{CustomError, ErrorCode};
+use chrono::Utc;
+use futures_util::TryStreamExt;
+use mongodb::bson::{self, Bson, Document, doc, oid::ObjectId};
+use mongodb::options::{IndexOptions, ReturnDocument};
+use mongodb::{Client, Collection, IndexModel};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+
+/// A user matched by contact sync, with the hash that matched
+#[derive(Deserialize)]
+struct ContactUser {
+    #[serde(flatten)]
+    user: AuthorSummary,
+    phone_hash: String,
+}
+
+pub struct FriendService {
+    requests: Collection<FriendRequest>,
+    friendships: Collection<Friendship>,
+    privacy: Collection<PrivacySettings>,
+    blocks: Collection<Block>,
+    users: Collection<User>,
+}
+
+impl FriendService {
+    pub fn new(client: &Client) -> Self {
+        let db = client.database("rust_blogdb");
+        FriendService {
+            requests: db.collection::<FriendRequest>("friend_requests"),
+            friendships: db.collection::<Friendship>("friendships"),
+            privacy: db.collection::<PrivacySettings>("privacy_settings"),
+            blocks: db.collection::<Block>("user_blocks"),
+            users: db.collection::<User>("users"),
+        }
+    }
+
+    /// One edge per direction, the inbox and outbox lookups, and one block
+    /// per pair of users
+    #[tracing::instrument(skip_all)]
+    pub async fn ensure_indexes(&self) -> Result<(), CustomError> {
+        self.friendships
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "user_id": 1, "friend_id": 1 })
+                    .options(IndexOptions::builder().unique(true).build())
+                    .build(),
+            )
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to create friend indexes: {}", e))
+            })?;
+
+        let indexes = vec![
+            IndexModel::builder()
+                .keys(doc! { "to_id": 1, "status": 1, "created_at": -1 })
+                .build(),
+            IndexModel::builder()
+                .keys(doc! { "from_id": 1, "status": 1, "created_at": -1 })
+                .build(),
+        ];
+        self.requests.create_indexes(indexes).await.map_err(|e| {
+            CustomError::InternalServerError(format!("Failed to create friend indexes: {}", e))
+        })?;
+
+        let indexes = vec![
+            IndexModel::builder()
+                .keys(doc! { "blocker_id": 1, "blocked_id": 1 })
+                .options(IndexOptions::builder().unique(true).build())
+                .build(),
+            IndexModel::builder().keys(doc! { "blocked_id": 1 }).build(),
+        ];
+        self.blocks.create_indexes(indexes).await.map_err(|e| {
+            CustomError::InternalServerError(format!("Failed to create friend indexes: {}", e))
+        })?;
+
+        // Serves contact matching; accounts from before hashing have none
+        self.users
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "phone_hash": 1 })
+                    .options(IndexOptions::builder().sparse(true).build())
+                    .build(),
+            )
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to create friend indexes: {}", e))
+            })?;
+
+        Ok(())
+    }
+
+    pub async fn are_friends(&self, a: &ObjectId, b: &ObjectId) -> Result<bool, CustomError> {
+        let count = self
+            .friendships
+            .count_documents(doc! { "user_id": a, "friend_id": b })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+        Ok(count > 0)
+    }
+
+    /// Friends of a user, newest first
+    #[tracing::instrument(skip_all)]
+    pub async fn list_friends(&self, user_id: &ObjectId) -> Result<Vec<Friendship>, CustomError> {
+        let cursor = self
+            .friendships
+            .find(doc! { "user_id": user_id })
+            .sort(doc! { "created_at": -1 })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        cursor
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))
+    }
+
+    /// Send a friend request. If the other user already asked, this accepts
+    /// their request instead of opening a second one.
+    #[tracing::instrument(skip_all)]
+    pub async fn send_request(
+        &self,
+        from_id: ObjectId,
+        to_id: ObjectId,
+    ) -> Result<FriendRequest, CustomError> {
+        if from_id == to_id {
+            return Err(CustomError::BadRequestError(
+                "You cannot send a friend request to yourself".to_string(),
+            ));
+        }
+
+        let exists = self
+            .users
+            .count_documents(doc! { "_id": to_id })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+        if exists == 0 {
+            return Err(CustomError::coded(
+                ErrorCode::UserNotFound,
+                "User not found",
+            ));
+        }
+
+        if self.are_friends(&from_id, &to_id).await? {
+            return Err(CustomError::ConflictError(
+                "You are already friends".to_string(),
+            ));
+        }
+
+        if self.is_blocked_between(&from_id, &to_id).await? {
+            return Err(CustomError::ForbiddenError(
+                "You can't send a friend request to this user".to_string(),
+            ));
+        }
+
+        if let Some(id) = self
+            .pending_between(&to_id, &from_id)
+            .await?
+            .and_then(|incoming| incoming.id)
+        {
+            return self.accept(&id, &from_id).await;
+        }
+
+        if self.pending_between(&from_id, &to_id).await?.is_some() {
+            return Err(CustomError::ConflictError(
+                "You already sent a friend request to this user".to_string(),
+            ));
+        }
+
+        let mut request = FriendRequest {
+            id: None,
+            from_id,
+            to_id,
+            status: FriendRequestStatus::Pending,
+            created_at: Utc::now(),
+            responded_at: None,
+        };
+        let result = self.requests.insert_one(&request).await.map_err(|e| {
+            CustomError::InternalServerError(format!("Failed to send friend request: {}", e))
+        })?;
+        request.id = result.inserted_id.as_object_id();
+
+        Ok(request)
+    }
+
+    /// Pending requests received by a user (the inbox)
+    #[tracing::instrument(skip_all)]
+    pub async fn incoming(&self, user_id: &ObjectId) -> Result<Vec<FriendRequest>, CustomError> {
+        self.pending(doc! { "to_id": user_id }).await
+    }
+
+    /// Pending requests a user has sent
+    #[tracing::instrument(skip_all)]
+    pub async fn outgoing(&self, user_id: &ObjectId) -> Result<Vec<FriendRequest>, CustomError> {
+        self.pending(doc! { "from_id": user_id }).await
+    }
+
+    /// Accept a request addressed to the user and create the friendship
+    #[tracing::instrument(skip_all)]
+    pub async fn accept(
+        &self,
+        request_id: &ObjectId,
+        user_id: &ObjectId,
+    ) -> Result<FriendRequest, CustomError> {
+        let request = self
+            .respond(
+                doc! { "_id": request_id, "to_id": user_id },
+                FriendRequestStatus::Accepted,
+            )
+            .await?;
+
+        for (user_id, friend_id) in [
+            (request.from_id, request.to_id),
+            (request.to_id, request.from_id),
+        ] {
+            self.friendships
+                .update_one(
+                    doc! { "user_id": user_id, "friend_id": friend_id },
+                    doc! {
+                        "$setOnInsert": {
+                            "user_id": user_id,
+                            "friend_id": friend_id,
+                            "created_at": bson_now(),
+                        }
+                    },
+                )
+                .upsert(true)
+                .await
+                .map_err(|e| {
+                    CustomError::InternalServerError(format!("Failed to add friend: {}", e))
+                })?;
+        }
+
+        Ok(request)
+    }
+
+    /// Decline a request addressed to the user
+    #[tracing::instrument(skip_all)]
+    pub async fn decline(
+        &self,
+        request_id: &ObjectId,
+        user_id: &ObjectId,
+    ) -> Result<FriendRequest, CustomError> {
+        self.respond(
+            doc! { "_id": request_id, "to_id": user_id },
+            FriendRequestStatus::Declined,
+        )
+        .await
+    }
+
+    /// Withdraw a request the user sent
+    #[tracing::instrument(skip_all)]
+    pub async fn cancel(
+        &self,
+        request_id: &ObjectId,
+        user_id: &ObjectId,
+    ) -> Result<FriendRequest, CustomError> {
+        self.respond(
+            doc! { "_id": request_id, "from_id": user_id },
+            FriendRequestStatus::Cancelled,
+        )
+        .await
+    }
+
+    /// Remove both edges of a friendship
+    #[tracing::instrument(skip_all)]
+    pub async fn unfriend(
+        &self,
+        user_id: &ObjectId,
+        friend_id: &ObjectId,
+    ) -> Result<(), CustomError> {
+        let result = self
+            .friendships
+            .delete_many(doc! {
+                "$or": [
+                    { "user_id": user_id, "friend_id": friend_id },
+                    { "user_id": friend_id, "friend_id": user_id },
+                ]
+            })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        if result.deleted_count == 0 {
+            return Err(CustomError::NotFoundError(
+                "You are not friends with this user".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Drop every friendship and pending request involving `user_id`, for
+    /// account deletion. Returns the former friends, whose counts change.
+    #[tracing::instrument(skip_all)]
+    pub async fn remove_user(&self, user_id: &ObjectId) -> Result<Vec<ObjectId>, CustomError> {
+        let friend_ids = self
+            .list_friends(user_id)
+            .await?
+            .into_iter()
+            .map(|friendship| friendship.friend_id)
+            .collect();
+
+        self.friendships
+            .delete_many(doc! { "$or": [{ "user_id": user_id }, { "friend_id": user_id }] })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+        self.requests
+            .delete_many(doc! { "$or": [{ "from_id": user_id }, { "to_id": user_id }] })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        Ok(friend_ids)
+    }
+
+    /// Block a user. Any friendship or pending request between the two ends.
+    #[tracing::instrument(skip_all)]
+    pub async fn block(
+        &self,
+        blocker_id: &ObjectId,
+        blocked_id: &ObjectId,
+    ) -> Result<Block, CustomError> {
+        if blocker_id == blocked_id {
+            return Err(CustomError::BadRequestError(
+                "You cannot block yourself".to_string(),
+            ));
+        }
+
+        let exists = self
+            .users
+            .count_documents(doc! { "_id": blocked_id })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+        if exists == 0 {
+            return Err(CustomError::coded(
+                ErrorCode::UserNotFound,
+                "User not found",
+            ));
+        }
+
+        let block = self
+            .blocks
+            .find_one_and_update(
+                doc! { "blocker_id": blocker_id, "blocked_id": blocked_id },
+                doc! {
+                    "$setOnInsert": {
+                        "blocker_id": blocker_id,
+                        "blocked_id": blocked_id,
+                        "created_at": bson_now(),
+                    }
+                },
+            )
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .await
+            .map_err(|e| CustomError::InternalServerError(format!("Failed to block user: {}", e)))?
+            .ok_or_else(|| CustomError::InternalServerError("Failed to block user".to_string()))?;
+
+        let between = doc! {
+            "$or": [
+                { "user_id": blocker_id, "friend_id": blocked_id },
+                { "user_id": blocked_id, "friend_id": blocker_id },
+            ]
+        };
+        self.friendships
+            .delete_many(between)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+        self.requests
+            .update_many(
+                doc! {
+                    "status": FriendRequestStatus::Pending.name(),
+                    "$or": [
+                        { "from_id": blocker_id, "to_id": blocked_id },
+                        { "from_id": blocked_id, "to_id": blocker_id },
+                    ]
+                },
+                doc! {
+                    "$set": {
+                        "status": FriendRequestStatus::Cancelled.name(),
+                        "responded_at": bson_now(),
+                    }
+                },
+            )
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        Ok(block)
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn unblock(
+        &self,
+        blocker_id: &ObjectId,
+        blocked_id: &ObjectId,
+    ) -> Result<(), CustomError> {
+        let result = self
+            .blocks
+            .delete_one(doc! { "blocker_id": blocker_id, "blocked_id": blocked_id })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        if result.deleted_count == 0 {
+            return Err(CustomError::NotFoundError(
+                "You haven't blocked this user".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Users the user has blocked, newest first
+    #[tracing::instrument(skip_all)]
+    pub async fn list_blocked(&self, user_id: &ObjectId) -> Result<Vec<Block>, CustomError> {
+        let cursor = self
+            .blocks
+            .find(doc! { "blocker_id": user_id })
+            .sort(doc! { "created_at": -1 })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        cursor
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))
+    }
+
+    /// Ids of the users the user has blocked
+    #[tracing::instrument(skip_all)]
+    pub async fn blocked_ids(&self, user_id: &ObjectId) -> Result<Vec<ObjectId>, CustomError> {
+        Ok(self
+            .blocks
+            .distinct("blocked_id", doc! { "blocker_id": user_id })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .into_iter()
+            .filter_map(|id| id.as_object_id())
+            .collect())
+    }
+
+    /// Whether either user has blocked the other
+    pub async fn is_blocked_between(
+        &self,
+        a: &ObjectId,
+        b: &ObjectId,
+    ) -> Result<bool, CustomError> {
+        let count = self
+            .blocks
+            .count_documents(doc! {
+                "$or": [
+                    { "blocker_id": a, "blocked_id": b },
+                    { "blocker_id": b, "blocked_id": a },
+                ]
+            })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+        Ok(count > 0)
+    }
+
+    /// Privacy settings for a user; defaults when they never changed them
+    #[tracing::instrument(skip_all)]
+    pub async fn privacy(&self, user_id: &ObjectId) -> Result<PrivacySettings, CustomError> {
+        let settings = self
+            .privacy
+            .find_one(doc! { "_id": user_id })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+        Ok(settings.unwrap_or_else(|| PrivacySettings::defaults(*user_id)))
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn update_privacy(
+        &self,
+        user_id: &ObjectId,
+        request: UpdatePrivacySettingsRequest,
+    ) -> Result<PrivacySettings, CustomError> {
+        let mut settings = self.privacy(user_id).await?;
+        if let Some(direct_messages) = request.direct_messages {
+            settings.direct_messages = direct_messages;
+        }
+        if let Some(posts) = request.posts {
+            settings.posts = posts;
+        }
+        if let Some(discoverable_by_phone) = request.discoverable_by_phone {
+            settings.discoverable_by_phone = discoverable_by_phone;
+        }
+
+        self.privacy
+            .replace_one(doc! { "_id": user_id }, &settings)
+            .upsert(true)
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to save privacy settings: {}", e))
+            })?;
+
+        Ok(settings)
+    }
+
+    /// Whether the viewer may read posts written by the author
+    pub async fn can_view_posts(
+        &self,
+        author_id: &ObjectId,
+        viewer_id: &ObjectId,
+    ) -> Result<bool, CustomError> {
+        if author_id == viewer_id {
+            return Ok(true);
+        }
+        match self.privacy(author_id).await?.posts {
+            Audience::Everyone => Ok(true),
+            Audience::Friends => self.are_friends(author_id, viewer_id).await,
+        }
+    }
+
+    /// Whether the user may use a direct room: they must be one of its two
+    /// participants, neither may have blocked the other, and the other one
+    /// must accept messages from them
+    pub async fn can_direct_message(
+        &self,
+        room_id: &str,
+        user_id: &ObjectId,
+    ) -> Result<bool, CustomError> {
+        let Some((first, second)) = direct_room_participants(room_id) else {
+            return Ok(false);
+        };
+        let other = if first == *user_id {
+            second
+        } else if second == *user_id {
+            first
+        } else {
+            return Ok(false);
+        };
+        if self.is_blocked_between(&other, user_id).await? {
+            return Ok(false);
+        }
+
+        match self.privacy(&other).await?.direct_messages {
+            Audience::Everyone => Ok(true),
+            Audience::Friends => self.are_friends(&other, user_id).await,
+        }
+    }
+
+    async fn pending_between(
+        &self,
+        from_id: &ObjectId,
+        to_id: &ObjectId,
+    ) -> Result<Option<FriendRequest>, CustomError> {
+        self.requests
+            .find_one(doc! {
+                "from_id": from_id,
+                "to_id": to_id,
+                "status": FriendRequestStatus::Pending.name(),
+            })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))
+    }
+
+    async fn pending(&self, mut filter: bson::Document) -> Result<Vec<FriendRequest>, CustomError> {
+        filter.insert("status", FriendRequestStatus::Pending.name());
+        let cursor = self
+            .requests
+            .find(filter)
+            .sort(doc! { "created_at": -1 })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        cursor
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))
+    }
+
+    /// Move a pending request matching `filter` to its final status
+    async fn respond(
+        &self,
+        mut filter: bson::Document,
+        status: FriendRequestStatus,
+    ) -> Result<FriendRequest, CustomError> {
+        filter.insert("status", FriendRequestStatus::Pending.name());
+        self.requests
+            .find_one_and_update(
+                filter,
+                doc! { "$set": { "status": status.name(), "responded_at": bson_now() } },
+            )
+            .return_document(ReturnDocument::After)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .ok_or_else(|| CustomError::NotFoundError("Friend request not found".to_string()))
+    }
+
+    /// People the user may know: friends of friends ranked by mutual
+    /// friends, topped up with the best connected accounts so that new users
+    /// get suggestions too
+    #[tracing::instrument(skip_all)]
+    pub async fn suggestions(
+        &self,
+        user_id: &ObjectId,
+        limit: usize,
+    ) -> Result<Vec<Suggestion>, CustomError> {
+        let mut excluded: Vec<ObjectId> = self
+            .list_friends(user_id)
+            .await?
+            .into_iter()
+            .map(|friendship| friendship.friend_id)
+            .collect();
+        excluded.push(*user_id);
+        for request in self.pending_requests(user_id).await? {
+            excluded.push(if request.from_id == *user_id {
+                request.to_id
+            } else {
+                request.from_id
+            });
+        }
+
+        let mut ranked: Vec<(ObjectId, i64, SuggestionReason)> = self
+            .ranked(vec![
+                doc! { "$match": { "user_id": user_id } },
+                doc! { "$lookup": {
+                    "from": "friendships",
+                    "localField": "friend_id",
+                    "foreignField": "user_id",
+                    "as": "second",
+                } },
+                doc! { "$unwind": "$second" },
+                doc! { "$match": { "second.friend_id": { "$nin": excluded.clone() } } },
+                doc! { "$group": { "_id": "$second.friend_id", "count": { "$sum": 1 } } },
+                doc! { "$sort": { "count": -1, "_id": 1 } },
+                doc! { "$limit": limit as i64 },
+            ])
+            .await?
+            .into_iter()
+            .map(|(id, count)| (id, count, SuggestionReason::MutualFriends))
+            .collect();
+
+        if ranked.len() < limit {
+            excluded.extend(ranked.iter().map(|(id, ..)| *id));
+            let popular = self
+                .ranked(vec![
+                    doc! { "$match": { "user_id": { "$nin": excluded.clone() } } },
+                    doc! { "$group": { "_id": "$user_id", "count": { "$sum": 1 } } },
+                    doc! { "$sort": { "count": -1, "_id": 1 } },
+                    doc! { "$limit": (limit - ranked.len()) as i64 },
+                ])
+                .await?;
+            ranked.extend(
+                popular
+                    .into_iter()
+                    .map(|(id, count)| (id, count, SuggestionReason::Popular)),
+            );
+        }
+
+        // Only suggest verified accounts that are still around
+        let ids: Vec<ObjectId> = ranked.iter().map(|(id, ..)| *id).collect();
+        let mut profiles: HashMap<ObjectId, AuthorSummary> = self
+            .users
+            .clone_with_type::<AuthorSummary>()
+            .find(doc! {
+                "_id": { "$in": ids },
+                "is_email_verified": true,
+                "deleted_at": Bson::Null,
+            })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect::<Vec<AuthorSummary>>()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .into_iter()
+            .map(|profile| (profile.id, profile))
+            .collect();
+
+        Ok(ranked
+            .into_iter()
+            .filter_map(|(id, score, reason)| {
+                profiles.remove(&id).map(|user| Suggestion {
+                    user,
+                    reason,
+                    score,
+                })
+            })
+            .collect())
+    }
+
+    /// Registered users whose phone number hashes to one of `phone_hashes`.
+    /// The caller, blocked users in either direction and users who turned
+    /// off phone discovery are left out.
+    #[tracing::instrument(skip_all)]
+    pub async fn find_by_contacts(
+        &self,
+        user_id: &ObjectId,
+        phone_hashes: &[String],
+    ) -> Result<Vec<ContactMatch>, CustomError> {
+        let phone_hashes: Vec<String> = phone_hashes
+            .iter()
+            .map(|hash| hash.to_ascii_lowercase())
+            .collect();
+        let candidates: Vec<ContactUser> = self
+            .users
+            .clone_with_type::<ContactUser>()
+            .find(doc! {
+                "phone_hash": { "$in": phone_hashes },
+                "_id": { "$ne": user_id },
+                "is_email_verified": true,
+                // Anyone can type in a number; only its verified owner matches
+                "is_phone_verified": true,
+                "shadow_banned": { "$ne": true },
+                "deleted_at": Bson::Null,
+            })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+        if candidates.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let ids: Vec<ObjectId> = candidates.iter().map(|c| c.user.id).collect();
+        let mut hidden: HashSet<ObjectId> = self
+            .privacy
+            .distinct(
+                "_id",
+                doc! { "_id": { "$in": ids.clone() }, "discoverable_by_phone": false },
+            )
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .into_iter()
+            .filter_map(|id| id.as_object_id())
+            .collect();
+        let blocks: Vec<Block> = self
+            .blocks
+            .find(doc! {
+                "$or": [
+                    { "blocker_id": user_id, "blocked_id": { "$in": ids.clone() } },
+                    { "blocker_id": { "$in": ids.clone() }, "blocked_id": user_id },
+                ]
+            })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+        hidden.extend(blocks.into_iter().map(|block| {
+            if block.blocker_id == *user_id {
+                block.blocked_id
+            } else {
+                block.blocker_id
+            }
+        }));
+
+        let friends: HashSet<ObjectId> = self
+            .friendships
+            .distinct(
+                "friend_id",
+                doc! { "user_id": user_id, "friend_id": { "$in": ids } },
+            )
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .into_iter()
+            .filter_map(|id| id.as_object_id())
+            .collect();
+
+        Ok(candidates
+            .into_iter()
+            .filter(|candidate| !hidden.contains(&candidate.user.id))
+            .map(|candidate| ContactMatch {
+                is_friend: friends.contains(&candidate.user.id),
+                phone_hash: candidate.phone_hash,
+                user: candidate.user,
+            })
+            .collect())
+    }
+
+    /// Pending requests sent or received by the user
+    async fn pending_requests(
+        &self,
+        user_id: &ObjectId,
+    ) -> Result<Vec<FriendRequest>, CustomError> {
+        self.requests
+            .find(doc! {
+                "$or": [{ "from_id": user_id }, { "to_id": user_id }],
+                "status": FriendRequestStatus::Pending.name(),
+            })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))
+    }
+
+    /// Run a friendship aggregation yielding `{ _id, count }` rows
+    async fn ranked(&self, pipeline: Vec<Document>) -> Result<Vec<(ObjectId, i64)>, CustomError> {
+        let rows: Vec<Document> = self
+            .friendships
+            .aggregate(pipeline)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| {
+                let id = row.get_object_id("_id").ok()?;
+                let count = match row.get("count")? {
+                    Bson::Int32(n) => *n as i64,
+                    Bson::Int64(n) => *n,
+                    _ => 0,
+                };
+                Some((id, count))
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,29 @@
+use crate::graphql::loader::Loaders;
+use crate::graphql::schema::BlogSchema;
+use crate::middleware::auth::AuthUser;
+use actix_web::{HttpResponse, web};
+use async_graphql::http::GraphiQLSource;
+use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse};
+use mongodb::Client;
+
+/// POST /graphql
+pub async fn graphql(
+    auth_user: AuthUser,
+    schema: web::Data<BlogSchema>,
+    client: web::Data<Client>,
+    request: GraphQLRequest,
+) -> GraphQLResponse {
+    let request = request
+        .into_inner()
+        .data(auth_user)
+        .data(Loaders::new(&client));
+    schema.execute(request).await.into()
+}
+
+/// Interactive explorer; queries still need a bearer token
+/// GET /graphql/explorer
+pub async fn graphiql() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(GraphiQLSource::build().endpoint("/api/v1/graphql").finish())
+}
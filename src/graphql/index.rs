@@ -0,0 +1,15 @@
+use super::controller::{graphiql, graphql};
+use crate::middleware::auth::verify_token;
+use crate::middleware::limits::RequestTimeout;
+use actix_web::web;
+use actix_web_httpauth::middleware::HttpAuthentication;
+
+pub fn graphql_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("/graphql/explorer", web::get().to(graphiql));
+    cfg.service(
+        web::scope("/graphql")
+            .wrap(RequestTimeout::standard())
+            .wrap(HttpAuthentication::bearer(verify_token))
+            .route("", web::post().to(graphql)),
+    );
+}
@@ -0,0 +1,183 @@
+use crate::comment::model::Comment;
+use crate::post::post_model::{AuthorSummary, Post};
+use crate::utils::error::CustomError;
+use async_graphql::dataloader::{DataLoader, Loader};
+use futures_util::TryStreamExt;
+use mongodb::bson::{Bson, Document, doc, oid::ObjectId};
+use mongodb::{Client, Collection};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Comments loaded per post for the `comments` field
+const MAX_COMMENTS_PER_POST: usize = 100;
+
+type LoadResult<V> = Result<HashMap<ObjectId, V>, Arc<CustomError>>;
+
+fn db_error(e: mongodb::error::Error) -> Arc<CustomError> {
+    Arc::new(CustomError::InternalServerError(e.to_string()))
+}
+
+/// Public author profiles by user id
+pub struct AuthorLoader {
+    users: Collection<AuthorSummary>,
+}
+
+impl Loader<ObjectId> for AuthorLoader {
+    type Value = AuthorSummary;
+    type Error = Arc<CustomError>;
+
+    async fn load(&self, keys: &[ObjectId]) -> LoadResult<AuthorSummary> {
+        let authors: Vec<AuthorSummary> = self
+            .users
+            .find(doc! { "_id": { "$in": keys } })
+            .projection(doc! { "username": 1, "profile_picture": 1, "is_verified": 1 })
+            .await
+            .map_err(db_error)?
+            .try_collect()
+            .await
+            .map_err(db_error)?;
+
+        Ok(authors
+            .into_iter()
+            .map(|author| (author.id, author))
+            .collect())
+    }
+}
+
+/// Visible posts by id
+pub struct PostLoader {
+    posts: Collection<Post>,
+}
+
+impl Loader<ObjectId> for PostLoader {
+    type Value = Post;
+    type Error = Arc<CustomError>;
+
+    async fn load(&self, keys: &[ObjectId]) -> LoadResult<Post> {
+        let posts: Vec<Post> = self
+            .posts
+            .find(doc! {
+                "_id": { "$in": keys },
+                "deleted_at": Bson::Null,
+                "hidden_at": Bson::Null,
+            })
+            .await
+            .map_err(db_error)?
+            .try_collect()
+            .await
+            .map_err(db_error)?;
+
+        Ok(posts.into_iter().map(|post| (post.id, post)).collect())
+    }
+}
+
+/// Oldest comments first, grouped by post id
+pub struct CommentsLoader {
+    comments: Collection<Comment>,
+}
+
+impl Loader<ObjectId> for CommentsLoader {
+    type Value = Vec<Comment>;
+    type Error = Arc<CustomError>;
+
+    async fn load(&self, keys: &[ObjectId]) -> LoadResult<Vec<Comment>> {
+        let comments: Vec<Comment> = self
+            .comments
+            .find(doc! {
+                "post_id": { "$in": keys },
+                "deleted_at": Bson::Null,
+                "hidden_at": Bson::Null,
+            })
+            .sort(doc! { "created_at": 1 })
+            .await
+            .map_err(db_error)?
+            .try_collect()
+            .await
+            .map_err(db_error)?;
+
+        let mut by_post: HashMap<ObjectId, Vec<Comment>> = HashMap::new();
+        for comment in comments {
+            let entry = by_post.entry(comment.post_id).or_default();
+            if entry.len() < MAX_COMMENTS_PER_POST {
+                entry.push(comment);
+            }
+        }
+        Ok(by_post)
+    }
+}
+
+/// Visible comment counts by post id
+pub struct CommentCountLoader {
+    comments: Collection<Document>,
+}
+
+impl Loader<ObjectId> for CommentCountLoader {
+    type Value = i64;
+    type Error = Arc<CustomError>;
+
+    async fn load(&self, keys: &[ObjectId]) -> LoadResult<i64> {
+        let counts: Vec<Document> = self
+            .comments
+            .aggregate(vec![
+                doc! { "$match": {
+                    "post_id": { "$in": keys },
+                    "deleted_at": Bson::Null,
+                    "hidden_at": Bson::Null,
+                } },
+                doc! { "$group": { "_id": "$post_id", "count": { "$sum": 1 } } },
+            ])
+            .await
+            .map_err(db_error)?
+            .try_collect()
+            .await
+            .map_err(db_error)?;
+
+        let mut by_post: HashMap<ObjectId, i64> = keys.iter().map(|key| (*key, 0)).collect();
+        for count in counts {
+            if let (Ok(post_id), Ok(n)) = (count.get_object_id("_id"), count.get_i32("count")) {
+                by_post.insert(post_id, n as i64);
+            }
+        }
+        Ok(by_post)
+    }
+}
+
+/// Per-request loaders, so batching and caching never cross requests
+pub struct Loaders {
+    pub authors: DataLoader<AuthorLoader>,
+    pub posts: DataLoader<PostLoader>,
+    pub comments: DataLoader<CommentsLoader>,
+    pub comment_counts: DataLoader<CommentCountLoader>,
+}
+
+impl Loaders {
+    pub fn new(client: &Client) -> Self {
+        let db = client.database("rust_blogdb");
+        Loaders {
+            authors: DataLoader::new(
+                AuthorLoader {
+                    users: db.collection("users"),
+                },
+                actix_web::rt::spawn,
+            ),
+            posts: DataLoader::new(
+                PostLoader {
+                    posts: db.collection("posts"),
+                },
+                actix_web::rt::spawn,
+            ),
+            comments: DataLoader::new(
+                CommentsLoader {
+                    comments: db.collection("comments"),
+                },
+                actix_web::rt::spawn,
+            ),
+            comment_counts: DataLoader::new(
+                CommentCountLoader {
+                    comments: db.collection("comments"),
+                },
+                actix_web::rt::spawn,
+            ),
+        }
+    }
+}
@@ -0,0 +1,316 @@
+use crate::activity::model::{Activity, ActivityKind};
+use crate::activity::service::ActivityService;
+use crate::comment::model::Comment;
+use crate::friend::service::FriendService;
+use crate::graphql::loader::Loaders;
+use crate::group::controller::readable_group;
+use crate::group::service::GroupService;
+use crate::middleware::auth::AuthUser;
+use crate::moderation::service::ModerationService;
+use crate::post::post_controller::{ensure_author_access, ensure_group_access};
+use crate::post::post_model::{AuthorSummary, Post, PostImage};
+use crate::post::post_service::PostService;
+use crate::utils::error::CustomError;
+use actix_web::web;
+use async_graphql::{Context, EmptyMutation, EmptySubscription, ID, Object, Result, Schema};
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+
+/// Deepest selection a query may nest
+const MAX_DEPTH: usize = 10;
+/// Upper bound on the estimated cost of a query
+const MAX_COMPLEXITY: usize = 1000;
+const DEFAULT_PAGE_SIZE: i32 = 20;
+const MAX_PAGE_SIZE: i32 = 100;
+
+pub type BlogSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+/// Services are shared across requests; the caller and loaders are added per
+/// request by the controller
+pub fn build_schema(
+    post_service: web::Data<PostService>,
+    group_service: web::Data<GroupService>,
+    friend_service: web::Data<FriendService>,
+    activity_service: web::Data<ActivityService>,
+    moderation_service: web::Data<ModerationService>,
+) -> BlogSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(post_service)
+        .data(group_service)
+        .data(friend_service)
+        .data(activity_service)
+        .data(moderation_service)
+        .limit_depth(MAX_DEPTH)
+        .limit_complexity(MAX_COMPLEXITY)
+        .finish()
+}
+
+fn parse_id(id: &ID, what: &str) -> Result<ObjectId, CustomError> {
+    ObjectId::parse_str(id.as_str())
+        .map_err(|_| CustomError::BadRequestError(format!("Invalid {} ID", what)))
+}
+
+fn page_size(first: Option<i32>) -> u64 {
+    first.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE) as u64
+}
+
+/// Shadow-banned authors the caller must not see
+async fn hidden_authors(ctx: &Context<'_>) -> Result<Vec<ObjectId>> {
+    let auth_user = ctx.data::<AuthUser>()?;
+    let moderation_service = ctx.data::<web::Data<ModerationService>>()?;
+    Ok(moderation_service
+        .hidden_authors(Some(&auth_user.id))
+        .await?)
+}
+
+/// Whether the caller may read a post, applying the REST endpoints' rules
+async fn can_read(ctx: &Context<'_>, post: &Post) -> Result<bool> {
+    let auth_user = ctx.data::<AuthUser>()?;
+    let group_service = ctx.data::<web::Data<GroupService>>()?;
+    let friend_service = ctx.data::<web::Data<FriendService>>()?;
+
+    let allowed = !hidden_authors(ctx).await?.contains(&post.author_id)
+        && ensure_group_access(group_service, post.group_id, auth_user)
+            .await
+            .is_ok()
+        && ensure_author_access(friend_service, post.group_id, &post.author_id, auth_user)
+            .await
+            .is_ok();
+    Ok(allowed)
+}
+
+async fn load_author(ctx: &Context<'_>, id: ObjectId) -> Result<Option<UserNode>> {
+    let loaders = ctx.data::<Loaders>()?;
+    Ok(loaders.authors.load_one(id).await?.map(UserNode))
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// The signed-in user
+    async fn me(&self, ctx: &Context<'_>) -> Result<Option<UserNode>> {
+        let auth_user = ctx.data::<AuthUser>()?;
+        load_author(ctx, auth_user.id).await
+    }
+
+    async fn user(&self, ctx: &Context<'_>, id: ID) -> Result<Option<UserNode>> {
+        load_author(ctx, parse_id(&id, "user")?).await
+    }
+
+    async fn post(&self, ctx: &Context<'_>, id: ID) -> Result<Option<PostNode>> {
+        let loaders = ctx.data::<Loaders>()?;
+        let Some(post) = loaders.posts.load_one(parse_id(&id, "post")?).await? else {
+            return Ok(None);
+        };
+        if !can_read(ctx, &post).await? {
+            return Ok(None);
+        }
+        Ok(Some(PostNode(post)))
+    }
+
+    /// Recent activity by the caller's friends
+    async fn activity(&self, ctx: &Context<'_>, first: Option<i32>) -> Result<Vec<ActivityNode>> {
+        let auth_user = ctx.data::<AuthUser>()?;
+        let activity_service = ctx.data::<web::Data<ActivityService>>()?;
+        let activity = activity_service
+            .feed_for(&auth_user.id, page_size(first) as i64)
+            .await?;
+        Ok(activity.into_iter().map(ActivityNode).collect())
+    }
+
+    /// Newest posts in a group the caller can read
+    async fn group_feed(
+        &self,
+        ctx: &Context<'_>,
+        group_id: ID,
+        page: Option<i32>,
+        first: Option<i32>,
+    ) -> Result<Vec<PostNode>> {
+        let auth_user = ctx.data::<AuthUser>()?;
+        let group_service = ctx.data::<web::Data<GroupService>>()?;
+        let post_service = ctx.data::<web::Data<PostService>>()?;
+
+        let group = readable_group(group_service, group_id.as_str(), auth_user).await?;
+        let hidden = hidden_authors(ctx).await?;
+        let page = page.unwrap_or(1).max(1) as u64;
+        let feed = post_service
+            .get_group_feed(&group.id, &hidden, false, &[], page, page_size(first))
+            .await?;
+        Ok(feed.items.into_iter().map(PostNode).collect())
+    }
+}
+
+pub struct UserNode(AuthorSummary);
+
+#[Object(name = "User")]
+impl UserNode {
+    async fn id(&self) -> ID {
+        ID(self.0.id.to_hex())
+    }
+
+    async fn username(&self) -> &str {
+        &self.0.username
+    }
+
+    async fn profile_picture(&self) -> Option<&str> {
+        self.0.profile_picture.as_deref()
+    }
+
+    async fn is_verified(&self) -> bool {
+        self.0.is_verified
+    }
+
+    /// Profile posts, newest first; empty when the author shares with friends only
+    async fn posts(
+        &self,
+        ctx: &Context<'_>,
+        page: Option<i32>,
+        first: Option<i32>,
+    ) -> Result<Vec<PostNode>> {
+        let auth_user = ctx.data::<AuthUser>()?;
+        let friend_service = ctx.data::<web::Data<FriendService>>()?;
+        if hidden_authors(ctx).await?.contains(&self.0.id)
+            || !friend_service
+                .can_view_posts(&self.0.id, &auth_user.id)
+                .await?
+        {
+            return Ok(Vec::new());
+        }
+
+        let post_service = ctx.data::<web::Data<PostService>>()?;
+        let page = page.unwrap_or(1).max(1) as u64;
+        let posts = post_service
+            .get_posts_by_author(&self.0.id, page, page_size(first))
+            .await?;
+        Ok(posts.items.into_iter().map(PostNode).collect())
+    }
+}
+
+pub struct PostNode(Post);
+
+#[Object(name = "Post")]
+impl PostNode {
+    async fn id(&self) -> ID {
+        ID(self.0.id.to_hex())
+    }
+
+    async fn title(&self) -> &str {
+        &self.0.title
+    }
+
+    async fn content(&self) -> &str {
+        &self.0.content
+    }
+
+    async fn group_id(&self) -> Option<ID> {
+        self.0.group_id.map(|id| ID(id.to_hex()))
+    }
+
+    /// Gallery images in display order
+    async fn images(&self) -> &[PostImage] {
+        &self.0.images
+    }
+
+    /// Clients blur the post's media unless the reader opted to see it
+    async fn is_sensitive(&self) -> bool {
+        self.0.is_sensitive
+    }
+
+    /// ISO 639-3 code detected from the content
+    async fn language(&self) -> Option<&str> {
+        self.0.language.as_deref()
+    }
+
+    async fn created_at(&self) -> DateTime<Utc> {
+        self.0.created_at
+    }
+
+    async fn updated_at(&self) -> DateTime<Utc> {
+        self.0.updated_at
+    }
+
+    async fn author(&self, ctx: &Context<'_>) -> Result<Option<UserNode>> {
+        load_author(ctx, self.0.author_id).await
+    }
+
+    async fn comment_count(&self, ctx: &Context<'_>) -> Result<i64> {
+        let loaders = ctx.data::<Loaders>()?;
+        Ok(loaders
+            .comment_counts
+            .load_one(self.0.id)
+            .await?
+            .unwrap_or(0))
+    }
+
+    /// Oldest comments first
+    async fn comments(&self, ctx: &Context<'_>, first: Option<i32>) -> Result<Vec<CommentNode>> {
+        let loaders = ctx.data::<Loaders>()?;
+        let comments = loaders
+            .comments
+            .load_one(self.0.id)
+            .await?
+            .unwrap_or_default();
+        let hidden = hidden_authors(ctx).await?;
+        Ok(comments
+            .into_iter()
+            .filter(|comment| !hidden.contains(&comment.author_id))
+            .take(page_size(first) as usize)
+            .map(CommentNode)
+            .collect())
+    }
+}
+
+pub struct CommentNode(Comment);
+
+#[Object(name = "Comment")]
+impl CommentNode {
+    async fn id(&self) -> Option<ID> {
+        self.0.id.map(|id| ID(id.to_hex()))
+    }
+
+    async fn content(&self) -> &str {
+        &self.0.content
+    }
+
+    async fn created_at(&self) -> DateTime<Utc> {
+        self.0.created_at
+    }
+
+    async fn author(&self, ctx: &Context<'_>) -> Result<Option<UserNode>> {
+        load_author(ctx, self.0.author_id).await
+    }
+}
+
+pub struct ActivityNode(Activity);
+
+#[Object(name = "Activity")]
+impl ActivityNode {
+    async fn kind(&self) -> ActivityKind {
+        self.0.kind
+    }
+
+    async fn summary(&self) -> Option<&str> {
+        self.0.summary.as_deref()
+    }
+
+    async fn group_id(&self) -> Option<ID> {
+        self.0.group_id.map(|id| ID(id.to_hex()))
+    }
+
+    async fn created_at(&self) -> DateTime<Utc> {
+        self.0.created_at
+    }
+
+    async fn actor(&self, ctx: &Context<'_>) -> Result<Option<UserNode>> {
+        load_author(ctx, self.0.actor_id).await
+    }
+
+    async fn post(&self, ctx: &Context<'_>) -> Result<Option<PostNode>> {
+        let Some(post_id) = self.0.post_id else {
+            return Ok(None);
+        };
+        let loaders = ctx.data::<Loaders>()?;
+        Ok(loaders.posts.load_one(post_id).await?.map(PostNode))
+    }
+}
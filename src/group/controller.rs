@@ -0,0 +1,406 @@
+use crate::badge::service::BadgeService;
+use crate::group::model::{
+    CreateGroupRequest, Group, GroupMember, GroupVisibility, InviteMemberRequest, MembershipStatus,
+    UpdateMemberRoleRequest,
+};
+use crate::group::service::GroupService;
+use crate::link_safety::service::LinkGuard;
+use crate::middleware::auth::AuthUser;
+use crate::moderation::service::ModerationService;
+use crate::post::post_controller::gallery_images;
+use crate::post::post_model::{CreatePostRequest, Post};
+use crate::post::post_service::PostService;
+use crate::spam_guard::model::SpamAction;
+use crate::spam_guard::service::SpamGuard;
+use crate::topic::service::TopicService;
+use crate::uploader::service::MediaService;
+use crate::user::service::UserService;
+use crate::utils::error::CustomError;
+use crate::utils::i18n::Locale;
+use crate::utils::response::ApiResponse;
+use crate::utils::validation::ValidatedJson;
+use actix_web::{HttpResponse, web};
+use mongodb::bson::oid::ObjectId;
+use serde::Deserialize;
+
+const DEFAULT_FEED_PAGE_SIZE: u64 = 20;
+const MAX_FEED_PAGE_SIZE: u64 = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct GroupListQuery {
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MemberQuery {
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FeedQuery {
+    pub page: Option<u64>,
+    pub per_page: Option<u64>,
+}
+
+fn parse_id(id: &str, what: &str) -> Result<ObjectId, CustomError> {
+    ObjectId::parse_str(id)
+        .map_err(|_| CustomError::BadRequestError(format!("Invalid {} ID", what)))
+}
+
+/// Load a group the caller may see. Groups they can't see are reported as
+/// missing so invite-only groups stay unlisted.
+async fn visible_group(
+    group_service: &GroupService,
+    group_id: &str,
+    auth_user: &AuthUser,
+) -> Result<Group, CustomError> {
+    let group_id = parse_id(group_id, "group")?;
+    let group = group_service
+        .get_group(&group_id)
+        .await?
+        .ok_or_else(|| CustomError::NotFoundError("Group not found".to_string()))?;
+
+    if group.visibility == GroupVisibility::InviteOnly
+        && group_service
+            .membership(&group.id, &auth_user.id)
+            .await?
+            .is_none()
+    {
+        return Err(CustomError::NotFoundError("Group not found".to_string()));
+    }
+    Ok(group)
+}
+
+/// Load a group and require the caller to be one of its admins
+async fn managed_group(
+    group_service: &GroupService,
+    group_id: &str,
+    auth_user: &AuthUser,
+) -> Result<Group, CustomError> {
+    let group = visible_group(group_service, group_id, auth_user).await?;
+    let can_manage = group_service
+        .membership(&group.id, &auth_user.id)
+        .await?
+        .is_some_and(|member| member.can_manage());
+    if !can_manage {
+        return Err(CustomError::ForbiddenError(
+            "Only group admins can do that".to_string(),
+        ));
+    }
+    Ok(group)
+}
+
+/// Load a group whose content the caller may read
+pub async fn readable_group(
+    group_service: &GroupService,
+    group_id: &str,
+    auth_user: &AuthUser,
+) -> Result<Group, CustomError> {
+    let group = visible_group(group_service, group_id, auth_user).await?;
+    if !group_service.can_view(&group, &auth_user.id).await? {
+        return Err(CustomError::ForbiddenError(
+            "Join this group to see its content".to_string(),
+        ));
+    }
+    Ok(group)
+}
+
+/// POST /groups
+pub async fn create_group(
+    locale: Locale,
+    auth_user: AuthUser,
+    group_service: web::Data<GroupService>,
+    body: ValidatedJson<CreateGroupRequest>,
+) -> Result<HttpResponse, CustomError> {
+    let group = group_service
+        .create_group(auth_user.id, body.into_inner())
+        .await?;
+
+    Ok(ApiResponse::created(locale.t("group-created"))
+        .data(group)
+        .into())
+}
+
+/// Public and private groups to discover
+/// GET /groups?limit=50
+pub async fn list_groups(
+    locale: Locale,
+    group_service: web::Data<GroupService>,
+    query: web::Query<GroupListQuery>,
+) -> Result<HttpResponse, CustomError> {
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+    let groups = group_service.list_discoverable(limit).await?;
+
+    Ok(ApiResponse::ok(locale.t("groups-fetched"))
+        .list(groups)
+        .into())
+}
+
+/// Groups the caller belongs to
+/// GET /groups/mine
+pub async fn my_groups(
+    locale: Locale,
+    auth_user: AuthUser,
+    group_service: web::Data<GroupService>,
+) -> Result<HttpResponse, CustomError> {
+    let groups = group_service.list_for_user(&auth_user.id).await?;
+
+    Ok(ApiResponse::ok(locale.t("groups-fetched"))
+        .list(groups)
+        .into())
+}
+
+/// GET /groups/{id}
+pub async fn get_group(
+    locale: Locale,
+    auth_user: AuthUser,
+    group_service: web::Data<GroupService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    let group = visible_group(&group_service, &path, &auth_user).await?;
+
+    Ok(ApiResponse::ok(locale.t("group-fetched"))
+        .data(group)
+        .into())
+}
+
+/// Join a public group, accept an invitation or ask to join a private group
+/// POST /groups/{id}/join
+pub async fn join_group(
+    locale: Locale,
+    auth_user: AuthUser,
+    group_service: web::Data<GroupService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    let group = visible_group(&group_service, &path, &auth_user).await?;
+    let member = group_service.join(&group, auth_user.id).await?;
+
+    let message = match member.status {
+        MembershipStatus::Pending => locale.t("group-join-requested"),
+        _ => locale.t("group-joined"),
+    };
+    Ok(ApiResponse::ok(message).data(member).into())
+}
+
+/// Leave a group, or withdraw a request or invitation
+/// POST /groups/{id}/leave
+pub async fn leave_group(
+    locale: Locale,
+    auth_user: AuthUser,
+    group_service: web::Data<GroupService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    let group = visible_group(&group_service, &path, &auth_user).await?;
+    group_service.leave(&group, &auth_user.id).await?;
+
+    Ok(ApiResponse::ok(locale.t("group-left")).into())
+}
+
+/// Members, or pending requests and invitations for group admins
+/// GET /groups/{id}/members?status=pending
+pub async fn list_members(
+    locale: Locale,
+    auth_user: AuthUser,
+    group_service: web::Data<GroupService>,
+    path: web::Path<String>,
+    query: web::Query<MemberQuery>,
+) -> Result<HttpResponse, CustomError> {
+    let status = match query.status.as_deref() {
+        None | Some("active") => MembershipStatus::Active,
+        Some("pending") => MembershipStatus::Pending,
+        Some("invited") => MembershipStatus::Invited,
+        Some(_) => {
+            return Err(CustomError::BadRequestError(
+                "status must be one of active, pending or invited".to_string(),
+            ));
+        }
+    };
+
+    let group = if status == MembershipStatus::Active {
+        readable_group(&group_service, &path, &auth_user).await?
+    } else {
+        managed_group(&group_service, &path, &auth_user).await?
+    };
+    let members: Vec<GroupMember> = group_service.list_members(&group.id, status).await?;
+
+    Ok(ApiResponse::ok(locale.t("group-members-fetched"))
+        .list(members)
+        .into())
+}
+
+/// Invite a user to the group
+/// POST /groups/{id}/invites
+pub async fn invite_member(
+    locale: Locale,
+    auth_user: AuthUser,
+    group_service: web::Data<GroupService>,
+    path: web::Path<String>,
+    body: ValidatedJson<InviteMemberRequest>,
+) -> Result<HttpResponse, CustomError> {
+    let group = managed_group(&group_service, &path, &auth_user).await?;
+    let user_id = parse_id(&body.user_id, "user")?;
+    group_service.invite(&group, user_id).await?;
+
+    Ok(ApiResponse::ok(locale.t("group-member-invited")).into())
+}
+
+/// Approve a request to join a private group
+/// POST /groups/{id}/members/{user_id}/approve
+pub async fn approve_member(
+    locale: Locale,
+    auth_user: AuthUser,
+    group_service: web::Data<GroupService>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, CustomError> {
+    let (group_id, user_id) = path.into_inner();
+    let group = managed_group(&group_service, &group_id, &auth_user).await?;
+    group_service
+        .approve(&group, &parse_id(&user_id, "user")?)
+        .await?;
+
+    Ok(ApiResponse::ok(locale.t("group-member-approved")).into())
+}
+
+/// Promote a member to admin or demote an admin; owner only
+/// PATCH /groups/{id}/members/{user_id}
+pub async fn update_member_role(
+    locale: Locale,
+    auth_user: AuthUser,
+    group_service: web::Data<GroupService>,
+    path: web::Path<(String, String)>,
+    body: ValidatedJson<UpdateMemberRoleRequest>,
+) -> Result<HttpResponse, CustomError> {
+    let (group_id, user_id) = path.into_inner();
+    let group = visible_group(&group_service, &group_id, &auth_user).await?;
+    if group.owner_id != auth_user.id {
+        return Err(CustomError::ForbiddenError(
+            "Only the group owner can change roles".to_string(),
+        ));
+    }
+    group_service
+        .set_role(&group, &parse_id(&user_id, "user")?, body.role)
+        .await?;
+
+    Ok(ApiResponse::ok(locale.t("group-member-updated")).into())
+}
+
+/// Remove a member, or decline a request or invitation
+/// DELETE /groups/{id}/members/{user_id}
+pub async fn remove_member(
+    locale: Locale,
+    auth_user: AuthUser,
+    group_service: web::Data<GroupService>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, CustomError> {
+    let (group_id, user_id) = path.into_inner();
+    let group = managed_group(&group_service, &group_id, &auth_user).await?;
+    group_service
+        .remove_member(&group, &parse_id(&user_id, "user")?)
+        .await?;
+
+    Ok(ApiResponse::ok(locale.t("group-member-removed")).into())
+}
+
+/// Share a post in a group; members only
+/// POST /groups/{id}/posts
+pub async fn create_group_post(
+    locale: Locale,
+    auth_user: AuthUser,
+    group_service: web::Data<GroupService>,
+    post_service: web::Data<PostService>,
+    badge_service: web::Data<BadgeService>,
+    topic_service: web::Data<TopicService>,
+    spam_guard: web::Data<SpamGuard>,
+    link_guard: web::Data<LinkGuard>,
+    media_service: web::Data<MediaService>,
+    path: web::Path<String>,
+    body: ValidatedJson<CreatePostRequest>,
+) -> Result<HttpResponse, CustomError> {
+    let group = visible_group(&group_service, &path, &auth_user).await?;
+    let is_member = group_service
+        .membership(&group.id, &auth_user.id)
+        .await?
+        .is_some_and(|member| member.is_active());
+    if !is_member {
+        return Err(CustomError::ForbiddenError(
+            "Only members can post in this group".to_string(),
+        ));
+    }
+
+    let body = body.into_inner();
+    spam_guard
+        .check(
+            &auth_user.id,
+            SpamAction::Post,
+            &format!("{}\n{}", body.title, body.content),
+        )
+        .await?;
+    let content = link_guard.screen(&body.content).await?;
+    let images = gallery_images(media_service.get_ref(), &auth_user.id, &body.images).await?;
+    let post = post_service
+        .create_post(Post {
+            id: ObjectId::new(),
+            title: body.title,
+            content,
+            author_id: auth_user.id,
+            group_id: Some(group.id),
+            topics: body.topics,
+            images,
+            version: 0,
+            share_slug: None,
+            share_count: 0,
+            is_sensitive: body.is_sensitive,
+            sensitive_locked: false,
+            language: None,
+            pinned_at: None,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        })
+        .await?;
+    badge_service.on_post_created(&auth_user.id).await;
+    topic_service.on_post_created(&post.topics).await;
+
+    Ok(ApiResponse::created(locale.t("post-created"))
+        .data(post)
+        .into())
+}
+
+/// Newest posts in a group
+/// GET /groups/{id}/posts?page=1&per_page=20
+pub async fn get_group_feed(
+    locale: Locale,
+    auth_user: AuthUser,
+    group_service: web::Data<GroupService>,
+    post_service: web::Data<PostService>,
+    moderation_service: web::Data<ModerationService>,
+    user_service: web::Data<UserService>,
+    path: web::Path<String>,
+    query: web::Query<FeedQuery>,
+) -> Result<HttpResponse, CustomError> {
+    let group = readable_group(&group_service, &path, &auth_user).await?;
+    let hidden = moderation_service
+        .hidden_authors(Some(&auth_user.id))
+        .await?;
+    let hide_sensitive = user_service.hides_sensitive(&auth_user.id).await?;
+    let languages = user_service.content_languages(&auth_user.id).await?;
+
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query
+        .per_page
+        .unwrap_or(DEFAULT_FEED_PAGE_SIZE)
+        .clamp(1, MAX_FEED_PAGE_SIZE);
+    let feed = post_service
+        .get_group_feed(
+            &group.id,
+            &hidden,
+            hide_sensitive,
+            &languages,
+            page,
+            per_page,
+        )
+        .await?;
+
+    Ok(ApiResponse::ok(locale.t("group-feed-fetched"))
+        .data(feed)
+        .into())
+}
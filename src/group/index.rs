@@ -0,0 +1,36 @@
+use super::controller::{
+    approve_member, create_group, create_group_post, get_group, get_group_feed, invite_member,
+    join_group, leave_group, list_groups, list_members, my_groups, remove_member,
+    update_member_role,
+};
+use crate::middleware::auth::verify_token;
+use crate::middleware::limits::RequestTimeout;
+use actix_web::web;
+use actix_web_httpauth::middleware::HttpAuthentication;
+
+pub fn group_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/groups")
+            .wrap(RequestTimeout::standard())
+            .wrap(HttpAuthentication::bearer(verify_token))
+            .route("", web::post().to(create_group))
+            .route("", web::get().to(list_groups))
+            .route("/mine", web::get().to(my_groups))
+            .route("/{id}", web::get().to(get_group))
+            .route("/{id}/join", web::post().to(join_group))
+            .route("/{id}/leave", web::post().to(leave_group))
+            .route("/{id}/members", web::get().to(list_members))
+            .route("/{id}/invites", web::post().to(invite_member))
+            .route(
+                "/{id}/members/{user_id}/approve",
+                web::post().to(approve_member),
+            )
+            .route(
+                "/{id}/members/{user_id}",
+                web::patch().to(update_member_role),
+            )
+            .route("/{id}/members/{user_id}", web::delete().to(remove_member))
+            .route("/{id}/posts", web::post().to(create_group_post))
+            .route("/{id}/posts", web::get().to(get_group_feed)),
+    );
+}
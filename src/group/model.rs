@@ -0,0 +1,132 @@
+use crate::utils::datetime::bson_datetime;
+use crate::utils::validation::{not_blank, object_id};
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+/// Prefix of the chat room that belongs to a group
+pub const GROUP_ROOM_PREFIX: &str = "group:";
+
+/// Chat room id for a group
+pub fn group_room_id(group_id: &ObjectId) -> String {
+    format!("{}{}", GROUP_ROOM_PREFIX, group_id.to_hex())
+}
+
+/// Who can find, join and read a group
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupVisibility {
+    /// Listed; anyone can join and read
+    Public,
+    /// Listed; joining needs an admin's approval and only members read
+    Private,
+    /// Unlisted; members are added by invitation only
+    InviteOnly,
+}
+
+impl GroupVisibility {
+    pub fn name(&self) -> &'static str {
+        match self {
+            GroupVisibility::Public => "public",
+            GroupVisibility::Private => "private",
+            GroupVisibility::InviteOnly => "invite_only",
+        }
+    }
+}
+
+/// Role of a member within a group
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupRole {
+    Member,
+    Admin,
+    Owner,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MembershipStatus {
+    Active,
+    /// Asked to join a private group
+    Pending,
+    /// Invited by an admin, not yet accepted
+    Invited,
+}
+
+impl MembershipStatus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MembershipStatus::Active => "active",
+            MembershipStatus::Pending => "pending",
+            MembershipStatus::Invited => "invited",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Group {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub name: String,
+    pub description: Option<String>,
+    pub visibility: GroupVisibility,
+    pub owner_id: ObjectId,
+    /// Chat room shared by the group's members
+    pub chat_room_id: String,
+    /// Active members, copied from `Counter::GroupMembers`
+    #[serde(default)]
+    pub member_count: i64,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "bson_datetime")]
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GroupMember {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub group_id: ObjectId,
+    pub user_id: ObjectId,
+    pub role: GroupRole,
+    pub status: MembershipStatus,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "bson_datetime")]
+    pub updated_at: DateTime<Utc>,
+}
+
+impl GroupMember {
+    pub fn is_active(&self) -> bool {
+        self.status == MembershipStatus::Active
+    }
+
+    /// Active admins and the owner manage members
+    pub fn can_manage(&self) -> bool {
+        self.is_active() && self.role >= GroupRole::Admin
+    }
+}
+
+#[derive(Deserialize, Validate)]
+pub struct CreateGroupRequest {
+    #[validate(
+        length(min = 3, max = 80, message = "must be between 3 and 80 characters"),
+        custom(function = "not_blank")
+    )]
+    pub name: String,
+    #[validate(length(max = 1000, message = "must be at most 1000 characters"))]
+    pub description: Option<String>,
+    pub visibility: GroupVisibility,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct InviteMemberRequest {
+    #[validate(custom(function = "object_id"))]
+    pub user_id: String,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct UpdateMemberRoleRequest {
+    pub role: GroupRole,
+}
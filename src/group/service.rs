@@ -0,0 +1,497 @@
+use crate::chat::model::{ChatRoom, RoomType};
+use crate::counter::model::Counter;
+use crate::counter::service::CounterService;
+use crate::database::RedisService;
+use crate::group::model::{
+    CreateGroupRequest, Group, GroupMember, GroupRole, GroupVisibility, MembershipStatus,
+    group_room_id,
+};
+use crate::utils::datetime::bson_now;
+use crate::utils::error::CustomError;
+use crate::utils::sanitize::{Markup, sanitize, sanitize_required};
+use chrono::Utc;
+use futures_util::TryStreamExt;
+use mongodb::bson::{self, doc, oid::ObjectId};
+use mongodb::options::IndexOptions;
+use mongodb::{Client, Collection, IndexModel};
+
+pub struct GroupService {
+    groups: Collection<Group>,
+    members: Collection<GroupMember>,
+    rooms: Collection<ChatRoom>,
+    counters: CounterService,
+}
+
+impl GroupService {
+    pub fn new(client: &Client, redis_service: RedisService) -> Self {
+        let db = client.database("rust_blogdb");
+        GroupService {
+            groups: db.collection::<Group>("groups"),
+            members: db.collection::<GroupMember>("group_members"),
+            rooms: db.collection::<ChatRoom>("chat_rooms"),
+            counters: CounterService::new(client, redis_service),
+        }
+    }
+
+    /// One membership per user and group, plus the per-user lookup
+    #[tracing::instrument(skip_all)]
+    pub async fn ensure_indexes(&self) -> Result<(), CustomError> {
+        let indexes = vec![
+            IndexModel::builder()
+                .keys(doc! { "group_id": 1, "user_id": 1 })
+                .options(IndexOptions::builder().unique(true).build())
+                .build(),
+            IndexModel::builder()
+                .keys(doc! { "user_id": 1, "status": 1 })
+                .build(),
+        ];
+
+        self.members.create_indexes(indexes).await.map_err(|e| {
+            CustomError::InternalServerError(format!("Failed to create group indexes: {}", e))
+        })?;
+
+        Ok(())
+    }
+
+    /// Create a group with its chat room; the creator becomes the owner
+    #[tracing::instrument(skip_all)]
+    pub async fn create_group(
+        &self,
+        owner_id: ObjectId,
+        request: CreateGroupRequest,
+    ) -> Result<Group, CustomError> {
+        let id = ObjectId::new();
+        let now = Utc::now();
+        let group = Group {
+            id,
+            name: sanitize_required(&request.name, Markup::None, "name")?,
+            description: request
+                .description
+                .map(|description| sanitize(&description, Markup::None)),
+            visibility: request.visibility,
+            owner_id,
+            chat_room_id: group_room_id(&id),
+            member_count: 1,
+            created_at: now,
+            updated_at: now,
+        };
+
+        self.groups.insert_one(&group).await.map_err(|e| {
+            CustomError::InternalServerError(format!("Failed to create group: {}", e))
+        })?;
+
+        self.members
+            .insert_one(GroupMember {
+                id: None,
+                group_id: id,
+                user_id: owner_id,
+                role: GroupRole::Owner,
+                status: MembershipStatus::Active,
+                created_at: now,
+                updated_at: now,
+            })
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to add group owner: {}", e))
+            })?;
+
+        self.rooms
+            .insert_one(ChatRoom {
+                id: None,
+                room_id: group.chat_room_id.clone(),
+                name: group.name.clone(),
+                room_type: RoomType::Group,
+                participants: vec![owner_id.to_hex()],
+                created_by: owner_id.to_hex(),
+                description: None,
+                avatar_url: None,
+                last_message_at: None,
+                read_only: None,
+                slow_mode_seconds: None,
+                created_at: now,
+                updated_at: now,
+            })
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to create group chat: {}", e))
+            })?;
+
+        Ok(group)
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn get_group(&self, id: &ObjectId) -> Result<Option<Group>, CustomError> {
+        self.groups
+            .find_one(doc! { "_id": id })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn membership(
+        &self,
+        group_id: &ObjectId,
+        user_id: &ObjectId,
+    ) -> Result<Option<GroupMember>, CustomError> {
+        self.members
+            .find_one(doc! { "group_id": group_id, "user_id": user_id })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))
+    }
+
+    /// Public groups are readable by anyone; others only by active members
+    pub async fn can_view(&self, group: &Group, user_id: &ObjectId) -> Result<bool, CustomError> {
+        if group.visibility == GroupVisibility::Public {
+            return Ok(true);
+        }
+
+        Ok(self
+            .membership(&group.id, user_id)
+            .await?
+            .is_some_and(|member| member.is_active()))
+    }
+
+    /// The group a chat room belongs to
+    #[tracing::instrument(skip_all)]
+    pub async fn group_for_room(&self, room_id: &str) -> Result<Option<Group>, CustomError> {
+        self.groups
+            .find_one(doc! { "chat_room_id": room_id })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))
+    }
+
+    /// Whether the user may use the group's chat room
+    pub async fn can_chat(&self, room_id: &str, user_id: &ObjectId) -> Result<bool, CustomError> {
+        let Some(group) = self.group_for_room(room_id).await? else {
+            return Ok(false);
+        };
+
+        Ok(self
+            .membership(&group.id, user_id)
+            .await?
+            .is_some_and(|member| member.is_active()))
+    }
+
+    /// Public and private groups, largest first; invite-only groups are unlisted
+    #[tracing::instrument(skip_all)]
+    pub async fn list_discoverable(&self, limit: i64) -> Result<Vec<Group>, CustomError> {
+        let cursor = self
+            .groups
+            .find(doc! { "visibility": { "$ne": GroupVisibility::InviteOnly.name() } })
+            .sort(doc! { "member_count": -1, "created_at": -1 })
+            .limit(limit)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        cursor
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))
+    }
+
+    /// Groups the user is an active member of
+    #[tracing::instrument(skip_all)]
+    pub async fn list_for_user(&self, user_id: &ObjectId) -> Result<Vec<Group>, CustomError> {
+        let group_ids: Vec<ObjectId> = self
+            .members
+            .distinct(
+                "group_id",
+                doc! { "user_id": user_id, "status": MembershipStatus::Active.name() },
+            )
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .into_iter()
+            .filter_map(|id| id.as_object_id())
+            .collect();
+
+        let cursor = self
+            .groups
+            .find(doc! { "_id": { "$in": group_ids } })
+            .sort(doc! { "name": 1 })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        cursor
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))
+    }
+
+    /// Members of a group with the given status
+    #[tracing::instrument(skip_all)]
+    pub async fn list_members(
+        &self,
+        group_id: &ObjectId,
+        status: MembershipStatus,
+    ) -> Result<Vec<GroupMember>, CustomError> {
+        let cursor = self
+            .members
+            .find(doc! { "group_id": group_id, "status": status.name() })
+            .sort(doc! { "created_at": 1 })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        cursor
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))
+    }
+
+    /// Join a public group, accept an invitation, or ask to join a private group
+    #[tracing::instrument(skip_all)]
+    pub async fn join(&self, group: &Group, user_id: ObjectId) -> Result<GroupMember, CustomError> {
+        if let Some(member) = self.membership(&group.id, &user_id).await? {
+            return match member.status {
+                MembershipStatus::Invited => {
+                    self.activate(group, &user_id).await?;
+                    self.require_membership(&group.id, &user_id).await
+                }
+                MembershipStatus::Active => Err(CustomError::ConflictError(
+                    "You are already a member of this group".to_string(),
+                )),
+                MembershipStatus::Pending => Err(CustomError::ConflictError(
+                    "Your request to join is awaiting approval".to_string(),
+                )),
+            };
+        }
+
+        let status = match group.visibility {
+            GroupVisibility::Public => MembershipStatus::Active,
+            GroupVisibility::Private => MembershipStatus::Pending,
+            GroupVisibility::InviteOnly => {
+                return Err(CustomError::ForbiddenError(
+                    "This group is invite-only".to_string(),
+                ));
+            }
+        };
+
+        let member = self.insert_member(group.id, user_id, status).await?;
+        if member.is_active() {
+            self.adjust_member_count(group, &user_id, 1).await?;
+        }
+
+        Ok(member)
+    }
+
+    /// Join through an invite link: the user becomes an active member
+    /// whatever the group's visibility, and is added to the group chat
+    #[tracing::instrument(skip_all)]
+    pub async fn join_by_invite(
+        &self,
+        group: &Group,
+        user_id: ObjectId,
+    ) -> Result<GroupMember, CustomError> {
+        match self.membership(&group.id, &user_id).await? {
+            Some(member) if member.is_active() => Err(CustomError::ConflictError(
+                "You are already a member of this group".to_string(),
+            )),
+            Some(_) => {
+                self.activate(group, &user_id).await?;
+                self.require_membership(&group.id, &user_id).await
+            }
+            None => {
+                let member = self
+                    .insert_member(group.id, user_id, MembershipStatus::Active)
+                    .await?;
+                self.adjust_member_count(group, &user_id, 1).await?;
+                Ok(member)
+            }
+        }
+    }
+
+    /// Approve a pending join request
+    #[tracing::instrument(skip_all)]
+    pub async fn approve(&self, group: &Group, user_id: &ObjectId) -> Result<(), CustomError> {
+        match self.membership(&group.id, user_id).await? {
+            Some(member) if member.status == MembershipStatus::Pending => {
+                self.activate(group, user_id).await
+            }
+            _ => Err(CustomError::NotFoundError(
+                "No pending request from that user".to_string(),
+            )),
+        }
+    }
+
+    /// Invite a user; they join by calling the join endpoint
+    #[tracing::instrument(skip_all)]
+    pub async fn invite(&self, group: &Group, user_id: ObjectId) -> Result<(), CustomError> {
+        match self.membership(&group.id, &user_id).await? {
+            // Inviting someone who asked to join is an approval
+            Some(member) if member.status == MembershipStatus::Pending => {
+                self.activate(group, &user_id).await
+            }
+            Some(_) => Err(CustomError::ConflictError(
+                "That user is already a member or invited".to_string(),
+            )),
+            None => {
+                self.insert_member(group.id, user_id, MembershipStatus::Invited)
+                    .await?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Leave a group, or cancel a pending request or invitation
+    #[tracing::instrument(skip_all)]
+    pub async fn leave(&self, group: &Group, user_id: &ObjectId) -> Result<(), CustomError> {
+        if group.owner_id == *user_id {
+            return Err(CustomError::BadRequestError(
+                "The owner cannot leave the group".to_string(),
+            ));
+        }
+        self.remove(group, user_id).await
+    }
+
+    /// Remove a member; the owner cannot be removed
+    #[tracing::instrument(skip_all)]
+    pub async fn remove_member(
+        &self,
+        group: &Group,
+        user_id: &ObjectId,
+    ) -> Result<(), CustomError> {
+        if group.owner_id == *user_id {
+            return Err(CustomError::BadRequestError(
+                "The owner cannot be removed".to_string(),
+            ));
+        }
+        self.remove(group, user_id).await
+    }
+
+    /// Promote a member to admin or demote an admin; ownership isn't transferable here
+    #[tracing::instrument(skip_all)]
+    pub async fn set_role(
+        &self,
+        group: &Group,
+        user_id: &ObjectId,
+        role: GroupRole,
+    ) -> Result<(), CustomError> {
+        if role == GroupRole::Owner || group.owner_id == *user_id {
+            return Err(CustomError::BadRequestError(
+                "The owner's role cannot be changed".to_string(),
+            ));
+        }
+        let role =
+            bson::to_bson(&role).map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        let result = self
+            .members
+            .update_one(
+                doc! {
+                    "group_id": group.id,
+                    "user_id": user_id,
+                    "status": MembershipStatus::Active.name(),
+                },
+                doc! { "$set": { "role": role, "updated_at": bson_now() } },
+            )
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        if result.matched_count == 0 {
+            return Err(CustomError::NotFoundError(
+                "That user is not a member of this group".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    async fn require_membership(
+        &self,
+        group_id: &ObjectId,
+        user_id: &ObjectId,
+    ) -> Result<GroupMember, CustomError> {
+        self.membership(group_id, user_id)
+            .await?
+            .ok_or_else(|| CustomError::NotFoundError("Membership not found".to_string()))
+    }
+
+    async fn insert_member(
+        &self,
+        group_id: ObjectId,
+        user_id: ObjectId,
+        status: MembershipStatus,
+    ) -> Result<GroupMember, CustomError> {
+        let now = Utc::now();
+        let mut member = GroupMember {
+            id: None,
+            group_id,
+            user_id,
+            role: GroupRole::Member,
+            status,
+            created_at: now,
+            updated_at: now,
+        };
+
+        let result = self.members.insert_one(&member).await.map_err(|e| {
+            CustomError::InternalServerError(format!("Failed to add group member: {}", e))
+        })?;
+        member.id = result.inserted_id.as_object_id();
+
+        Ok(member)
+    }
+
+    /// Make a membership active and add the user to the group chat
+    async fn activate(&self, group: &Group, user_id: &ObjectId) -> Result<(), CustomError> {
+        let result = self
+            .members
+            .update_one(
+                doc! {
+                    "group_id": group.id,
+                    "user_id": user_id,
+                    "status": { "$ne": MembershipStatus::Active.name() },
+                },
+                doc! {
+                    "$set": {
+                        "status": MembershipStatus::Active.name(),
+                        "updated_at": bson_now(),
+                    }
+                },
+            )
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        if result.modified_count > 0 {
+            self.adjust_member_count(group, user_id, 1).await?;
+        }
+        Ok(())
+    }
+
+    async fn remove(&self, group: &Group, user_id: &ObjectId) -> Result<(), CustomError> {
+        let member = self
+            .members
+            .find_one_and_delete(doc! { "group_id": group.id, "user_id": user_id })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .ok_or_else(|| {
+                CustomError::NotFoundError("That user is not a member of this group".to_string())
+            })?;
+
+        if member.is_active() {
+            self.adjust_member_count(group, user_id, -1).await?;
+        }
+        Ok(())
+    }
+
+    /// Keep the member count and chat participants in step with memberships
+    async fn adjust_member_count(
+        &self,
+        group: &Group,
+        user_id: &ObjectId,
+        delta: i64,
+    ) -> Result<(), CustomError> {
+        self.counters
+            .add(Counter::GroupMembers, &group.id.to_hex(), delta)
+            .await?;
+
+        let participants = if delta > 0 {
+            doc! { "$addToSet": { "participants": user_id.to_hex() } }
+        } else {
+            doc! { "$pull": { "participants": user_id.to_hex() } }
+        };
+        self.rooms
+            .update_one(doc! { "room_id": &group.chat_room_id }, participants)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,33 @@
+use crate::database::RedisService;
+use crate::insights::model::InsightsQuery;
+use crate::insights::service::InsightsService;
+use crate::middleware::auth::AuthUser;
+use crate::utils::error::CustomError;
+use crate::utils::i18n::Locale;
+use crate::utils::response::ApiResponse;
+use actix_web::{HttpResponse, web};
+
+/// Insights are aggregated on read, so cache them briefly per user and range
+const INSIGHTS_CACHE_SECONDS: u64 = 300;
+
+/// Profile views, friend growth and top posts
+/// GET /users/me/insights?range=7d|30d|90d
+pub async fn get_insights(
+    locale: Locale,
+    auth_user: AuthUser,
+    insights_service: web::Data<InsightsService>,
+    redis_service: web::Data<RedisService>,
+    query: web::Query<InsightsQuery>,
+) -> Result<HttpResponse, CustomError> {
+    let insights = redis_service
+        .cache_get_or_set_json(
+            &format!("insights:{}:{}", auth_user.id.to_hex(), query.range.name()),
+            INSIGHTS_CACHE_SECONDS,
+            || insights_service.insights(&auth_user.id, query.range),
+        )
+        .await?;
+
+    Ok(ApiResponse::ok(locale.t("insights-fetched"))
+        .data(insights)
+        .into())
+}
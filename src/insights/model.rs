@@ -0,0 +1,103 @@
+use crate::utils::datetime::bson_datetime;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+/// Profile views are kept this long, which covers the longest range
+pub const INSIGHTS_RETENTION_DAYS: i64 = 90;
+/// Posts listed under top posts
+pub const TOP_POSTS: usize = 5;
+
+/// Someone viewing a profile; one record per viewer and UTC day, so
+/// repeated visits on the same day count once
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProfileVisit {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub profile_id: ObjectId,
+    pub viewer_id: ObjectId,
+    /// UTC day of the visit, as YYYY-MM-DD
+    pub day: String,
+    #[serde(with = "bson_datetime")]
+    pub viewed_at: DateTime<Utc>,
+}
+
+/// Period covered by insights, ending today
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InsightsRange {
+    #[default]
+    #[serde(rename = "7d")]
+    Week,
+    #[serde(rename = "30d")]
+    Month,
+    #[serde(rename = "90d")]
+    Quarter,
+}
+
+impl InsightsRange {
+    pub fn days(self) -> i64 {
+        match self {
+            InsightsRange::Week => 7,
+            InsightsRange::Month => 30,
+            InsightsRange::Quarter => INSIGHTS_RETENTION_DAYS,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            InsightsRange::Week => "7d",
+            InsightsRange::Month => "30d",
+            InsightsRange::Quarter => "90d",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InsightsQuery {
+    #[serde(default)]
+    pub range: InsightsRange,
+}
+
+/// A count for one UTC day
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DailyCount {
+    /// YYYY-MM-DD
+    pub date: String,
+    pub count: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ViewInsights {
+    pub total: u64,
+    pub unique_viewers: u64,
+    pub daily: Vec<DailyCount>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FriendInsights {
+    /// Friends today
+    pub total: u64,
+    /// Friends made during the range
+    pub gained: u64,
+    pub daily: Vec<DailyCount>,
+}
+
+/// A post ranked by engagement during the range
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PostInsight {
+    pub post_id: ObjectId,
+    pub title: String,
+    /// Comments by other people
+    pub comments: u64,
+    pub shares: u64,
+}
+
+/// How the user's profile and posts did over a range
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProfileInsights {
+    pub range: InsightsRange,
+    pub since: DateTime<Utc>,
+    pub profile_views: ViewInsights,
+    pub friends: FriendInsights,
+    pub top_posts: Vec<PostInsight>,
+}
@@ -0,0 +1,361 @@
+use crate::comment::model::Comment;
+use crate::counter::model::{Counter, profile_views_subject};
+use crate::counter::service::CounterService;
+use crate::database::RedisService;
+use crate::friend::model::Friendship;
+use crate::insights::model::{
+    DailyCount, FriendInsights, INSIGHTS_RETENTION_DAYS, InsightsRange, PostInsight,
+    ProfileInsights, ProfileVisit, TOP_POSTS, ViewInsights,
+};
+use crate::post::post_model::Post;
+use crate::share::model::PostShare;
+use crate::utils::error::CustomError;
+use chrono::{DateTime, Duration, NaiveTime, Utc};
+use futures_util::TryStreamExt;
+use mongodb::bson::{self, Bson, Document, doc, oid::ObjectId};
+use mongodb::options::IndexOptions;
+use mongodb::{Client, Collection, IndexModel};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration as StdDuration;
+
+/// Visits outlive the longest range by a day
+const VISIT_TTL_SECONDS: u64 = (INSIGHTS_RETENTION_DAYS as u64 + 1) * 86400;
+
+/// Profile views, recorded as they happen, and insights aggregated on read.
+/// Daily view totals and friend counts come from counters.
+pub struct InsightsService {
+    visits: Collection<ProfileVisit>,
+    friendships: Collection<Friendship>,
+    posts: Collection<Post>,
+    comments: Collection<Comment>,
+    shares: Collection<PostShare>,
+    counters: CounterService,
+}
+
+impl InsightsService {
+    pub fn new(client: &Client, redis_service: RedisService) -> Self {
+        let db = client.database("rust_blogdb");
+        InsightsService {
+            visits: db.collection::<ProfileVisit>("profile_visits"),
+            friendships: db.collection::<Friendship>("friendships"),
+            posts: db.collection::<Post>("posts"),
+            comments: db.collection::<Comment>("comments"),
+            shares: db.collection::<PostShare>("post_shares"),
+            counters: CounterService::new(client, redis_service),
+        }
+    }
+
+    /// One visit per viewer and day; visits expire after the longest range
+    #[tracing::instrument(skip_all)]
+    pub async fn ensure_indexes(&self) -> Result<(), CustomError> {
+        let indexes = vec![
+            IndexModel::builder()
+                .keys(doc! { "profile_id": 1, "day": 1, "viewer_id": 1 })
+                .options(IndexOptions::builder().unique(true).build())
+                .build(),
+            IndexModel::builder()
+                .keys(doc! { "viewed_at": 1 })
+                .options(
+                    IndexOptions::builder()
+                        .expire_after(StdDuration::from_secs(VISIT_TTL_SECONDS))
+                        .build(),
+                )
+                .build(),
+        ];
+        self.visits.create_indexes(indexes).await.map_err(|e| {
+            CustomError::InternalServerError(format!("Failed to create insights indexes: {}", e))
+        })?;
+
+        Ok(())
+    }
+
+    /// Record a profile view. Views are derived data, so failures only get
+    /// logged; people looking at their own profile aren't counted.
+    #[tracing::instrument(skip_all)]
+    pub async fn record_view(&self, profile_id: &ObjectId, viewer_id: &ObjectId) {
+        if profile_id == viewer_id {
+            return;
+        }
+
+        let now = Utc::now();
+        let day = now.format("%Y-%m-%d").to_string();
+        let result = self
+            .visits
+            .update_one(
+                doc! { "profile_id": profile_id, "viewer_id": viewer_id, "day": &day },
+                doc! { "$setOnInsert": { "viewed_at": bson::DateTime::from_chrono(now) } },
+            )
+            .upsert(true)
+            .await;
+
+        match result {
+            // Only a viewer's first visit of the day is counted
+            Ok(result) if result.upserted_id.is_some() => {
+                if let Err(e) = self
+                    .counters
+                    .incr(
+                        Counter::ProfileViews,
+                        &profile_views_subject(profile_id, &day),
+                    )
+                    .await
+                {
+                    log::warn!("Failed to count profile view: {}", e);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => log::warn!("Failed to record profile view: {}", e),
+        }
+    }
+
+    /// Views, friends and top posts over `range`, ending today
+    #[tracing::instrument(skip_all)]
+    pub async fn insights(
+        &self,
+        user_id: &ObjectId,
+        range: InsightsRange,
+    ) -> Result<ProfileInsights, CustomError> {
+        let today = Utc::now().date_naive();
+        let first_day = today - Duration::days(range.days() - 1);
+        let since = first_day.and_time(NaiveTime::MIN).and_utc();
+        let days: Vec<String> = first_day
+            .iter_days()
+            .take(range.days() as usize)
+            .map(|day| day.format("%Y-%m-%d").to_string())
+            .collect();
+
+        let profile_views = self.views(user_id, &days).await?;
+
+        let total = self
+            .counters
+            .get(Counter::Friends, &user_id.to_hex())
+            .await?
+            .max(0) as u64;
+        let gained_by_day = self
+            .daily_counts(
+                &self.friendships,
+                doc! {
+                    "user_id": user_id,
+                    "created_at": { "$gte": bson::DateTime::from_chrono(since) },
+                },
+                "$created_at",
+            )
+            .await?;
+        let friends = FriendInsights {
+            total,
+            gained: gained_by_day.values().sum(),
+            daily: per_day(&days, &gained_by_day),
+        };
+
+        Ok(ProfileInsights {
+            range,
+            since,
+            profile_views,
+            friends,
+            top_posts: self.top_posts(user_id, since).await?,
+        })
+    }
+
+    async fn views(
+        &self,
+        user_id: &ObjectId,
+        days: &[String],
+    ) -> Result<ViewInsights, CustomError> {
+        let subjects: Vec<String> = days
+            .iter()
+            .map(|day| profile_views_subject(user_id, day))
+            .collect();
+        let counts = self
+            .counters
+            .get_many(Counter::ProfileViews, &subjects)
+            .await?;
+        let daily: Vec<DailyCount> = days
+            .iter()
+            .zip(counts)
+            .map(|(day, count)| DailyCount {
+                date: day.clone(),
+                count: count.max(0) as u64,
+            })
+            .collect();
+
+        let unique_viewers = self
+            .visits
+            .distinct(
+                "viewer_id",
+                doc! { "profile_id": user_id, "day": { "$in": days } },
+            )
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .len() as u64;
+
+        Ok(ViewInsights {
+            total: daily.iter().map(|day| day.count).sum(),
+            unique_viewers,
+            daily,
+        })
+    }
+
+    /// The user's live posts with the most comments from others and shares
+    /// since `since`
+    async fn top_posts(
+        &self,
+        user_id: &ObjectId,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<PostInsight>, CustomError> {
+        let post_ids: Vec<ObjectId> = self
+            .posts
+            .distinct(
+                "_id",
+                doc! { "author_id": user_id, "deleted_at": Bson::Null, "hidden_at": Bson::Null },
+            )
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .into_iter()
+            .filter_map(|id| id.as_object_id())
+            .collect();
+        if post_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let since = bson::DateTime::from_chrono(since);
+        let comments = by_post(
+            self.counts(
+                &self.comments,
+                vec![
+                    doc! { "$match": {
+                        "post_id": { "$in": post_ids.clone() },
+                        "author_id": { "$ne": user_id },
+                        "created_at": { "$gte": since },
+                    } },
+                    doc! { "$group": { "_id": "$post_id", "count": { "$sum": 1 } } },
+                ],
+            )
+            .await?,
+        );
+        let shares = by_post(
+            self.counts(
+                &self.shares,
+                vec![
+                    doc! { "$match": {
+                        "post_id": { "$in": post_ids },
+                        "created_at": { "$gte": since },
+                    } },
+                    doc! { "$group": { "_id": "$post_id", "count": { "$sum": 1 } } },
+                ],
+            )
+            .await?,
+        );
+
+        let mut ranked: Vec<(ObjectId, u64, u64)> = comments
+            .keys()
+            .chain(shares.keys())
+            .copied()
+            .collect::<HashSet<ObjectId>>()
+            .into_iter()
+            .map(|id| {
+                (
+                    id,
+                    comments.get(&id).copied().unwrap_or(0),
+                    shares.get(&id).copied().unwrap_or(0),
+                )
+            })
+            .collect();
+        ranked.sort_by(|a, b| (b.1 + b.2).cmp(&(a.1 + a.2)).then(b.0.cmp(&a.0)));
+        ranked.truncate(TOP_POSTS);
+
+        let ids: Vec<ObjectId> = ranked.iter().map(|(id, ..)| *id).collect();
+        let titles: HashMap<ObjectId, String> = self
+            .posts
+            .find(doc! { "_id": { "$in": ids } })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect::<Vec<Post>>()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .into_iter()
+            .map(|post| (post.id, post.title))
+            .collect();
+
+        Ok(ranked
+            .into_iter()
+            .filter_map(|(post_id, comments, shares)| {
+                Some(PostInsight {
+                    post_id,
+                    title: titles.get(&post_id)?.clone(),
+                    comments,
+                    shares,
+                })
+            })
+            .collect())
+    }
+
+    /// Documents matching `filter`, grouped by the UTC day of `date_field`
+    async fn daily_counts<T: Send + Sync>(
+        &self,
+        collection: &Collection<T>,
+        filter: Document,
+        date_field: &str,
+    ) -> Result<HashMap<String, u64>, CustomError> {
+        let rows = self
+            .counts(
+                collection,
+                vec![
+                    doc! { "$match": filter },
+                    doc! { "$group": {
+                        "_id": { "$dateToString": { "format": "%Y-%m-%d", "date": date_field } },
+                        "count": { "$sum": 1 },
+                    } },
+                ],
+            )
+            .await?;
+        Ok(by_day(rows))
+    }
+
+    /// Run an aggregation yielding `{ _id, count }` rows
+    async fn counts<T: Send + Sync>(
+        &self,
+        collection: &Collection<T>,
+        pipeline: Vec<Document>,
+    ) -> Result<Vec<(Bson, u64)>, CustomError> {
+        let rows: Vec<Document> = collection
+            .aggregate(pipeline)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| {
+                let count = match row.get("count")? {
+                    Bson::Int32(n) => *n as u64,
+                    Bson::Int64(n) => *n as u64,
+                    _ => 0,
+                };
+                Some((row.get("_id")?.clone(), count))
+            })
+            .collect())
+    }
+}
+
+fn by_day(rows: Vec<(Bson, u64)>) -> HashMap<String, u64> {
+    rows.into_iter()
+        .filter_map(|(id, count)| Some((id.as_str()?.to_string(), count)))
+        .collect()
+}
+
+fn by_post(rows: Vec<(Bson, u64)>) -> HashMap<ObjectId, u64> {
+    rows.into_iter()
+        .filter_map(|(id, count)| Some((id.as_object_id()?, count)))
+        .collect()
+}
+
+/// A count for every day, zero where nothing happened
+fn per_day(days: &[String], counts: &HashMap<String, u64>) -> Vec<DailyCount> {
+    days.iter()
+        .map(|day| DailyCount {
+            date: day.clone(),
+            count: counts.get(day).copied().unwrap_or(0),
+        })
+        .collect()
+}
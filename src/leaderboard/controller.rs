@@ -0,0 +1,39 @@
+use crate::leaderboard::model::Leaderboard;
+use crate::leaderboard::service::LeaderboardService;
+use crate::utils::error::CustomError;
+use crate::utils::i18n::Locale;
+use crate::utils::response::ApiResponse;
+use actix_web::{HttpResponse, web};
+use serde::Deserialize;
+use serde_json::json;
+
+const DEFAULT_LEADERBOARD_SIZE: usize = 10;
+const MAX_LEADERBOARD_SIZE: usize = 100;
+
+#[derive(Deserialize)]
+pub struct LeaderboardQuery {
+    pub limit: Option<usize>,
+}
+
+/// Get this week's ranking for a leaderboard
+/// GET /leaderboards/{name}
+pub async fn get_leaderboard(
+    locale: Locale,
+    leaderboard_service: web::Data<LeaderboardService>,
+    path: web::Path<String>,
+    query: web::Query<LeaderboardQuery>,
+) -> Result<HttpResponse, CustomError> {
+    let name = path.into_inner();
+    let board = Leaderboard::from_name(&name)
+        .ok_or_else(|| CustomError::NotFoundError(format!("Unknown leaderboard: {}", name)))?;
+
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_LEADERBOARD_SIZE)
+        .min(MAX_LEADERBOARD_SIZE);
+    let entries = leaderboard_service.top(board, limit).await?;
+
+    Ok(ApiResponse::ok(locale.t("leaderboard-fetched"))
+        .data(json!({ "leaderboard": board.name(), "entries": entries }))
+        .into())
+}
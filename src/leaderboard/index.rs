@@ -0,0 +1,13 @@
+use super::controller::get_leaderboard;
+use crate::middleware::limits::RequestTimeout;
+use crate::middleware::rate_limit::IpRateLimit;
+use actix_web::web;
+
+pub fn leaderboard_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/leaderboards")
+            .wrap(RequestTimeout::standard())
+            .wrap(IpRateLimit::public_endpoints("leaderboards"))
+            .route("/{name}", web::get().to(get_leaderboard)),
+    );
+}
@@ -0,0 +1,44 @@
+use crate::post::post_model::AuthorSummary;
+use mongodb::bson::oid::ObjectId;
+use serde::Serialize;
+
+/// Leaderboards maintained by the application
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Leaderboard {
+    /// Users ranked by comments written this week
+    MostActiveCommenters,
+    /// Posts ranked by distinct users sharing them this week
+    TrendingPosts,
+}
+
+impl Leaderboard {
+    /// Name used in URLs and Redis keys
+    pub fn name(&self) -> &'static str {
+        match self {
+            Leaderboard::MostActiveCommenters => "most-active-commenters",
+            Leaderboard::TrendingPosts => "trending-posts",
+        }
+    }
+
+    /// Look up a leaderboard by its URL name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "most-active-commenters" => Some(Leaderboard::MostActiveCommenters),
+            "trending-posts" => Some(Leaderboard::TrendingPosts),
+            _ => None,
+        }
+    }
+}
+
+/// One ranked row of a leaderboard
+#[derive(Debug, Serialize)]
+pub struct LeaderboardEntry {
+    pub rank: usize,
+    pub score: f64,
+    /// Post id for post leaderboards, user id for user leaderboards
+    pub member_id: ObjectId,
+    /// Post title, for post leaderboards
+    pub title: Option<String>,
+    /// The ranked user, or the post's author
+    pub user: Option<AuthorSummary>,
+}
@@ -0,0 +1,181 @@
+use crate::database::RedisService;
+use crate::leaderboard::model::{Leaderboard, LeaderboardEntry};
+use crate::post::post_model::{AuthorSummary, Post};
+use crate::utils::error::CustomError;
+use chrono::{Datelike, Utc};
+use futures_util::TryStreamExt;
+use mongodb::bson::{doc, oid::ObjectId};
+use mongodb::{Client, Collection};
+use std::collections::HashMap;
+use std::future::Future;
+
+/// Weekly boards are kept a day past the end of the week
+const WEEKLY_BOARD_TTL_SECONDS: u64 = 8 * 24 * 60 * 60;
+
+/// The score updates handlers record, so handler tests can skip Redis.
+/// `LeaderboardService` implements it by delegating to its inherent methods.
+pub trait LeaderboardServiceTrait: Send + Sync + 'static {
+    fn record_comment(&self, author_id: &ObjectId) -> impl Future<Output = ()> + Send;
+}
+
+pub struct LeaderboardService {
+    users: Collection<AuthorSummary>,
+    posts: Collection<Post>,
+    redis_service: RedisService,
+}
+
+impl LeaderboardService {
+    pub fn new(client: &Client, redis_service: RedisService) -> Self {
+        let db = client.database("rust_blogdb");
+        LeaderboardService {
+            users: db.collection::<AuthorSummary>("users"),
+            posts: db.collection::<Post>("posts"),
+            redis_service,
+        }
+    }
+
+    /// Redis board name for the current ISO week
+    fn weekly_board(board: Leaderboard) -> String {
+        let week = Utc::now().iso_week();
+        format!("{}:{}-W{:02}", board.name(), week.year(), week.week())
+    }
+
+    /// Count a comment towards this week's most active commenters
+    #[tracing::instrument(skip_all)]
+    pub async fn record_comment(&self, author_id: &ObjectId) {
+        self.increment(Leaderboard::MostActiveCommenters, author_id, 1.0)
+            .await;
+    }
+
+    /// Count a user's first share of a post towards this week's trending posts
+    #[tracing::instrument(skip_all)]
+    pub async fn record_share(&self, post_id: &ObjectId) {
+        self.increment(Leaderboard::TrendingPosts, post_id, 1.0)
+            .await;
+    }
+
+    /// Boards are derived data, so a Redis failure only gets logged
+    #[tracing::instrument(skip_all)]
+    async fn increment(&self, board: Leaderboard, member: &ObjectId, by: f64) {
+        if let Err(e) = self
+            .redis_service
+            .leaderboard_increment(
+                &Self::weekly_board(board),
+                &member.to_hex(),
+                by,
+                WEEKLY_BOARD_TTL_SECONDS,
+            )
+            .await
+        {
+            log::warn!("Failed to update {} leaderboard: {}", board.name(), e);
+        }
+    }
+
+    /// Get the top entries of a board with their user profiles
+    #[tracing::instrument(skip_all)]
+    pub async fn top(
+        &self,
+        board: Leaderboard,
+        limit: usize,
+    ) -> Result<Vec<LeaderboardEntry>, CustomError> {
+        let ranked: Vec<(ObjectId, f64)> = self
+            .redis_service
+            .leaderboard_top(&Self::weekly_board(board), limit)
+            .await
+            .map_err(CustomError::InternalServerError)?
+            .into_iter()
+            .filter_map(|(member, score)| ObjectId::parse_str(&member).ok().map(|id| (id, score)))
+            .collect();
+
+        let ids: Vec<ObjectId> = ranked.iter().map(|(id, _)| *id).collect();
+
+        let entries = match board {
+            Leaderboard::TrendingPosts => {
+                let posts = self.posts_by_id(&ids).await?;
+                let author_ids: Vec<ObjectId> = posts.values().map(|p| p.author_id).collect();
+                let authors = self.users_by_id(&author_ids).await?;
+
+                // Deleted posts drop out of the ranking
+                ranked
+                    .into_iter()
+                    .filter_map(|(id, score)| {
+                        let post = posts.get(&id)?;
+                        Some((
+                            id,
+                            score,
+                            Some(post.title.clone()),
+                            authors.get(&post.author_id).cloned(),
+                        ))
+                    })
+                    .collect::<Vec<_>>()
+            }
+            Leaderboard::MostActiveCommenters => {
+                let users = self.users_by_id(&ids).await?;
+                ranked
+                    .into_iter()
+                    .filter_map(|(id, score)| {
+                        Some((id, score, None, Some(users.get(&id)?.clone())))
+                    })
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        Ok(entries
+            .into_iter()
+            .enumerate()
+            .map(|(i, (member_id, score, title, user))| LeaderboardEntry {
+                rank: i + 1,
+                score,
+                member_id,
+                title,
+                user,
+            })
+            .collect())
+    }
+
+    /// Fetch live posts by id
+    #[tracing::instrument(skip_all)]
+    async fn posts_by_id(&self, ids: &[ObjectId]) -> Result<HashMap<ObjectId, Post>, CustomError> {
+        let cursor = self
+            .posts
+            .find(doc! { "_id": { "$in": ids }, "deleted_at": null, "hidden_at": null })
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to fetch posts: {}", e))
+            })?;
+
+        let posts: Vec<Post> = cursor.try_collect().await.map_err(|e| {
+            CustomError::InternalServerError(format!("Failed to collect posts: {}", e))
+        })?;
+
+        Ok(posts.into_iter().map(|p| (p.id, p)).collect())
+    }
+
+    /// Fetch public user profiles by id
+    #[tracing::instrument(skip_all)]
+    async fn users_by_id(
+        &self,
+        ids: &[ObjectId],
+    ) -> Result<HashMap<ObjectId, AuthorSummary>, CustomError> {
+        let cursor = self
+            .users
+            .find(doc! { "_id": { "$in": ids } })
+            .projection(doc! { "username": 1, "profile_picture": 1, "is_verified": 1 })
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to fetch users: {}", e))
+            })?;
+
+        let users: Vec<AuthorSummary> = cursor.try_collect().await.map_err(|e| {
+            CustomError::InternalServerError(format!("Failed to collect users: {}", e))
+        })?;
+
+        Ok(users.into_iter().map(|u| (u.id, u)).collect())
+    }
+}
+
+impl LeaderboardServiceTrait for LeaderboardService {
+    async fn record_comment(&self, author_id: &ObjectId) {
+        Self::record_comment(self, author_id).await
+    }
+}
@@ -0,0 +1,49 @@
+use crate::link_safety::model::{FollowLinkQuery, LinkInterstitial, LinkVerdict};
+use crate::link_safety::service::LinkGuard;
+use crate::utils::error::CustomError;
+use crate::utils::i18n::Locale;
+use crate::utils::response::ApiResponse;
+use actix_web::http::header;
+use actix_web::{HttpResponse, web};
+
+/// Open a link from user content. Safe links redirect straight away;
+/// suspicious ones show a warning first and redirect with `?confirm=true`,
+/// and blocked ones never redirect.
+pub async fn follow_link(
+    locale: Locale,
+    slug: web::Path<String>,
+    query: web::Query<FollowLinkQuery>,
+    link_guard: web::Data<LinkGuard>,
+) -> Result<HttpResponse, CustomError> {
+    let link = link_guard
+        .resolve(&slug)
+        .await?
+        .ok_or_else(|| CustomError::NotFoundError("Link not found".to_string()))?;
+
+    let proceed = match link.verdict {
+        LinkVerdict::Safe => true,
+        LinkVerdict::Suspicious => query.confirm,
+        LinkVerdict::Malicious => false,
+    };
+    if proceed {
+        return Ok(HttpResponse::Found()
+            .insert_header((header::LOCATION, link.target()))
+            .finish());
+    }
+
+    let message = match link.verdict {
+        LinkVerdict::Malicious => locale.t("link-blocked"),
+        _ => locale.t("link-warning"),
+    };
+    let continue_url = (link.verdict == LinkVerdict::Suspicious)
+        .then(|| format!("{}?confirm=true", link_guard.redirect_url(&link.slug)));
+
+    Ok(ApiResponse::ok(message)
+        .data(LinkInterstitial {
+            url: link.target(),
+            verdict: link.verdict,
+            threats: link.threats,
+            continue_url,
+        })
+        .into())
+}
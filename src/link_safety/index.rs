@@ -0,0 +1,12 @@
+use super::controller::follow_link;
+use crate::middleware::limits::RequestTimeout;
+use actix_web::web;
+
+/// Interstitial for links in user content; open to signed-out readers
+pub fn link_safety_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/l")
+            .wrap(RequestTimeout::standard())
+            .route("/{slug}", web::get().to(follow_link)),
+    );
+}
@@ -0,0 +1,182 @@
+use crate::utils::datetime::bson_datetime;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+
+/// `http(s)://` or `www.` links, stopping at whitespace, quotes and tags
+static LINK: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)\b(?:https?://|www\.)[^\s<>"']+"#).expect("valid link regex")
+});
+
+/// Byte ranges of the links in user content, leaving out trailing
+/// punctuation such as the full stop ending a sentence
+pub fn find_links(content: &str) -> Vec<(usize, usize)> {
+    LINK.find_iter(content)
+        .map(|found| {
+            let link = found
+                .as_str()
+                .trim_end_matches(['.', ',', ';', ':', '!', '?', ')', ']']);
+            (found.start(), found.start() + link.len())
+        })
+        .collect()
+}
+
+/// Lowercase host of a link, without port or credentials
+pub fn link_host(url: &str) -> String {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+    let host = authority.rsplit('@').next().unwrap_or("");
+    let host = host.split(':').next().unwrap_or("");
+    host.trim_end_matches('.').to_lowercase()
+}
+
+/// How safe a link looks
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkVerdict {
+    Safe,
+    /// Opened only after the reader confirms past a warning
+    Suspicious,
+    /// Rejected when posted
+    Malicious,
+}
+
+impl LinkVerdict {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LinkVerdict::Safe => "safe",
+            LinkVerdict::Suspicious => "suspicious",
+            LinkVerdict::Malicious => "malicious",
+        }
+    }
+}
+
+/// Where link verdicts come from and where checked links point to
+#[derive(Debug, Clone)]
+pub struct LinkSafetyPolicy {
+    /// Domains rejected outright, subdomains included (`LINK_BLOCKLIST`,
+    /// comma-separated)
+    pub blocklist: Vec<String>,
+    /// Safe Browsing v4 compatible lookup API (`LINK_SAFETY_API_URL`,
+    /// defaulting to Google's when a key is set)
+    pub api_url: Option<String>,
+    /// `LINK_SAFETY_API_KEY`; without it only the blocklist is used
+    pub api_key: Option<String>,
+    /// Prefix of rewritten links (`LINK_REDIRECT_BASE_URL`, default `/api/v1/l`)
+    pub redirect_base_url: String,
+}
+
+impl LinkSafetyPolicy {
+    /// Read the policy from the environment; part of `AppConfig::from_env`
+    pub fn from_env() -> Result<Self, String> {
+        let var = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        let is_http = |url: &str| url.starts_with("https://") || url.starts_with("http://");
+
+        let blocklist: Vec<String> = var("LINK_BLOCKLIST")
+            .map(|list| {
+                list.split(',')
+                    .map(|domain| domain.trim().trim_end_matches('.').to_lowercase())
+                    .filter(|domain| !domain.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        if let Some(entry) = blocklist
+            .iter()
+            .find(|domain| domain.contains(|c: char| c.is_whitespace() || "/:@".contains(c)))
+        {
+            return Err(format!(
+                "LINK_BLOCKLIST must list bare domains (got {})",
+                entry
+            ));
+        }
+
+        let api_key = var("LINK_SAFETY_API_KEY");
+        let api_url = match (var("LINK_SAFETY_API_URL"), &api_key) {
+            (Some(url), Some(_)) if is_http(&url) => Some(url),
+            (Some(_), Some(_)) => {
+                return Err("LINK_SAFETY_API_URL must be an http(s) URL".to_string());
+            }
+            (Some(_), None) => {
+                return Err("LINK_SAFETY_API_URL needs LINK_SAFETY_API_KEY".to_string());
+            }
+            (None, Some(_)) => {
+                Some("https://safebrowsing.googleapis.com/v4/threatMatches:find".to_string())
+            }
+            (None, None) => None,
+        };
+
+        let redirect_base_url = var("LINK_REDIRECT_BASE_URL")
+            .map(|url| url.trim_end_matches('/').to_string())
+            .unwrap_or_else(|| "/api/v1/l".to_string());
+        if !redirect_base_url.starts_with('/') && !is_http(&redirect_base_url) {
+            return Err("LINK_REDIRECT_BASE_URL must be a path or an http(s) URL".to_string());
+        }
+
+        Ok(LinkSafetyPolicy {
+            blocklist,
+            api_url,
+            api_key,
+            redirect_base_url,
+        })
+    }
+
+    /// Whether the host or one of its parent domains is blocklisted
+    pub fn is_blocked(&self, host: &str) -> bool {
+        self.blocklist.iter().any(|domain| {
+            host == domain
+                || host
+                    .strip_suffix(domain.as_str())
+                    .is_some_and(|rest| rest.ends_with('.'))
+        })
+    }
+}
+
+/// A checked link that user content points to through `/l/{slug}`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CheckedLink {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub slug: String,
+    pub url: String,
+    pub verdict: LinkVerdict,
+    /// Threat types reported by the lookup API
+    #[serde(default)]
+    pub threats: Vec<String>,
+    #[serde(with = "bson_datetime")]
+    pub checked_at: DateTime<Utc>,
+}
+
+impl CheckedLink {
+    /// Link target with a scheme, as `www.` links are stored as written
+    pub fn target(&self) -> String {
+        if self.url.contains("://") {
+            self.url.clone()
+        } else {
+            format!("https://{}", self.url)
+        }
+    }
+}
+
+/// What the interstitial shows before leaving for a link
+#[derive(Debug, Serialize)]
+pub struct LinkInterstitial {
+    pub url: String,
+    pub verdict: LinkVerdict,
+    pub threats: Vec<String>,
+    /// Follow this to open the link anyway; missing for blocked links
+    pub continue_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FollowLinkQuery {
+    /// Set once the reader has seen the warning
+    #[serde(default)]
+    pub confirm: bool,
+}
@@ -0,0 +1,281 @@
+use crate::link_safety::model::{
+    CheckedLink, LinkSafetyPolicy, LinkVerdict, find_links, link_host,
+};
+use crate::utils::datetime::bson_now;
+use crate::utils::error::CustomError;
+use mongodb::bson::doc;
+use mongodb::options::{IndexOptions, ReturnDocument};
+use mongodb::{Client, Collection, IndexModel};
+use rand::Rng;
+use rand::distr::Alphanumeric;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::IpAddr;
+use std::time::Duration;
+
+/// Length of the base62 slug in rewritten links
+const LINK_SLUG_LENGTH: usize = 10;
+/// How long to wait for the lookup API before letting links through
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(3);
+/// Threat types that get a link rejected rather than flagged
+const MALICIOUS_THREATS: &[&str] = &["MALWARE", "SOCIAL_ENGINEERING"];
+
+#[derive(Debug, Default, Deserialize)]
+struct LookupResponse {
+    #[serde(default)]
+    matches: Vec<ThreatMatch>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ThreatMatch {
+    threat_type: String,
+    threat: ThreatEntry,
+}
+
+#[derive(Debug, Deserialize)]
+struct ThreatEntry {
+    url: String,
+}
+
+/// Link screening as handlers call it, so handler tests need no Safe
+/// Browsing lookups. `LinkGuard` implements it by delegating to its inherent
+/// method.
+pub trait LinkGuardTrait: Send + Sync + 'static {
+    fn screen(&self, content: &str) -> impl Future<Output = Result<String, CustomError>> + Send;
+}
+
+/// Checks the links in posts, comments and chat messages, rejecting
+/// malicious ones and pointing the rest at the `/l/{slug}` interstitial
+pub struct LinkGuard {
+    links: Collection<CheckedLink>,
+    http: reqwest::Client,
+    policy: LinkSafetyPolicy,
+}
+
+impl LinkGuard {
+    pub fn new(client: &Client, policy: LinkSafetyPolicy) -> Self {
+        let db = client.database("rust_blogdb");
+        LinkGuard {
+            links: db.collection::<CheckedLink>("checked_links"),
+            http: reqwest::Client::builder()
+                .timeout(LOOKUP_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            policy,
+        }
+    }
+
+    /// One record per slug and per URL
+    #[tracing::instrument(skip_all)]
+    pub async fn ensure_indexes(&self) -> Result<(), CustomError> {
+        self.links
+            .create_indexes(vec![
+                IndexModel::builder()
+                    .keys(doc! { "slug": 1 })
+                    .options(IndexOptions::builder().unique(true).build())
+                    .build(),
+                IndexModel::builder()
+                    .keys(doc! { "url": 1 })
+                    .options(IndexOptions::builder().unique(true).build())
+                    .build(),
+            ])
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!(
+                    "Failed to create link safety indexes: {}",
+                    e
+                ))
+            })?;
+
+        Ok(())
+    }
+
+    /// Reject content linking to malicious sites, and rewrite every other
+    /// link to go through the interstitial. Links already rewritten are
+    /// left alone, so edited content can be screened again.
+    #[tracing::instrument(skip_all)]
+    pub async fn screen(&self, content: &str) -> Result<String, CustomError> {
+        let links: Vec<(usize, usize)> = find_links(content)
+            .into_iter()
+            .filter(|(start, end)| {
+                !content[*start..*end].starts_with(&self.policy.redirect_base_url)
+            })
+            .collect();
+        if links.is_empty() {
+            return Ok(content.to_string());
+        }
+
+        let urls: Vec<&str> = links
+            .iter()
+            .map(|(start, end)| &content[*start..*end])
+            .collect();
+        let verdicts = self.classify(&urls).await;
+        if let Some(url) = urls
+            .iter()
+            .find(|url| verdicts[**url].0 == LinkVerdict::Malicious)
+        {
+            return Err(CustomError::BadRequestError(format!(
+                "Links to {} aren't allowed because the site is unsafe",
+                link_host(url)
+            )));
+        }
+
+        let mut screened = String::with_capacity(content.len());
+        let mut last = 0;
+        for (start, end) in links {
+            let url = &content[start..end];
+            let (verdict, threats) = verdicts[url].clone();
+            let link = self.record(url, verdict, threats).await?;
+            screened.push_str(&content[last..start]);
+            screened.push_str(&self.redirect_url(&link.slug));
+            last = end;
+        }
+        screened.push_str(&content[last..]);
+
+        Ok(screened)
+    }
+
+    /// Interstitial URL that replaces a link in user content
+    pub fn redirect_url(&self, slug: &str) -> String {
+        format!("{}/{}", self.policy.redirect_base_url, slug)
+    }
+
+    /// The link behind a slug, with the blocklist applied again as it may
+    /// have grown since the link was posted
+    #[tracing::instrument(skip_all)]
+    pub async fn resolve(&self, slug: &str) -> Result<Option<CheckedLink>, CustomError> {
+        let link = self
+            .links
+            .find_one(doc! { "slug": slug })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        Ok(link.map(|mut link| {
+            if self.policy.is_blocked(&link_host(&link.url)) {
+                link.verdict = LinkVerdict::Malicious;
+            }
+            link
+        }))
+    }
+
+    /// Verdict and threat types per URL. Lookup API failures are logged and
+    /// only the blocklist applies, so an outage doesn't stop people posting.
+    async fn classify(&self, urls: &[&str]) -> HashMap<String, (LinkVerdict, Vec<String>)> {
+        let mut verdicts: HashMap<String, (LinkVerdict, Vec<String>)> = urls
+            .iter()
+            .map(|url| {
+                let host = link_host(url);
+                let verdict = if self.policy.is_blocked(&host) {
+                    LinkVerdict::Malicious
+                } else if host.parse::<IpAddr>().is_ok() {
+                    // Raw addresses hide where a link really goes
+                    LinkVerdict::Suspicious
+                } else {
+                    LinkVerdict::Safe
+                };
+                (url.to_string(), (verdict, Vec::new()))
+            })
+            .collect();
+
+        match self.lookup(urls).await {
+            Ok(response) => {
+                for threat in response.matches {
+                    let Some((verdict, threats)) = verdicts.get_mut(&threat.threat.url) else {
+                        continue;
+                    };
+                    let found = if MALICIOUS_THREATS.contains(&threat.threat_type.as_str()) {
+                        LinkVerdict::Malicious
+                    } else {
+                        LinkVerdict::Suspicious
+                    };
+                    *verdict = (*verdict).max(found);
+                    threats.push(threat.threat_type);
+                }
+            }
+            Err(e) => log::warn!("Link safety lookup unavailable: {}", e),
+        }
+
+        verdicts
+    }
+
+    /// Ask the Safe Browsing style API about the URLs
+    async fn lookup(&self, urls: &[&str]) -> Result<LookupResponse, String> {
+        let (Some(api_url), Some(api_key)) = (&self.policy.api_url, &self.policy.api_key) else {
+            return Ok(LookupResponse::default());
+        };
+
+        let entries: Vec<_> = urls.iter().map(|url| json!({ "url": url })).collect();
+        let body = json!({
+            "client": {
+                "clientId": "socialization-app",
+                "clientVersion": env!("CARGO_PKG_VERSION"),
+            },
+            "threatInfo": {
+                "threatTypes": [
+                    "MALWARE",
+                    "SOCIAL_ENGINEERING",
+                    "UNWANTED_SOFTWARE",
+                    "POTENTIALLY_HARMFUL_APPLICATION",
+                ],
+                "platformTypes": ["ANY_PLATFORM"],
+                "threatEntryTypes": ["URL"],
+                "threatEntries": entries,
+            },
+        });
+
+        let response = self
+            .http
+            .post(api_url)
+            .query(&[("key", api_key)])
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("lookup API returned {}", response.status()));
+        }
+
+        response.json().await.map_err(|e| e.to_string())
+    }
+
+    /// Store the latest verdict for a URL, giving it a slug the first time
+    async fn record(
+        &self,
+        url: &str,
+        verdict: LinkVerdict,
+        threats: Vec<String>,
+    ) -> Result<CheckedLink, CustomError> {
+        let slug: String = rand::rng()
+            .sample_iter(&Alphanumeric)
+            .take(LINK_SLUG_LENGTH)
+            .map(char::from)
+            .collect();
+
+        self.links
+            .find_one_and_update(
+                doc! { "url": url },
+                doc! {
+                    "$set": {
+                        "verdict": verdict.name(),
+                        "threats": threats,
+                        "checked_at": bson_now(),
+                    },
+                    "$setOnInsert": { "slug": slug },
+                },
+            )
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .ok_or_else(|| CustomError::InternalServerError("Failed to record link".to_string()))
+    }
+}
+
+impl LinkGuardTrait for LinkGuard {
+    async fn screen(&self, content: &str) -> Result<String, CustomError> {
+        Self::screen(self, content).await
+    }
+}
@@ -1,99 +1,235 @@
-use actix::Actor;
-use actix_web::http::StatusCode;
-use actix_web::middleware::{ErrorHandlers, Logger};
-use actix_web::{App, HttpResponse, HttpServer, Responder, get, web};
+use actix_web::HttpServer;
 use dotenv::dotenv;
-use env_logger::Env;
 use log::info;
+use std::time::Duration;
 
+mod access_token;
+mod activity;
+mod admin;
+mod analytics;
+mod api_key;
+mod app;
+mod app_state;
+mod badge;
 mod chat;
 mod comment;
+mod counter;
 mod database;
+mod export;
+mod feature_flag;
+mod fingerprint;
+mod friend;
+#[cfg(feature = "graphql")]
+mod graphql;
+mod group;
+mod insights;
+mod leaderboard;
+mod link_safety;
 mod middleware;
+mod moderation;
+mod nearby;
+mod notification;
+mod oauth;
 mod post;
 mod router;
+mod share;
+mod spam_guard;
+mod sticker;
+mod subscription;
+// Helpers are shared by tests that don't each use all of them
+#[cfg(all(test, feature = "test-utils"))]
+#[allow(dead_code)]
+mod test_utils;
+mod topic;
 mod uploader;
 mod user;
 mod utils;
+mod verification;
 
-use chat::server::ChatServer;
 use database::{RedisService, connect_to_redis};
-use middleware::not_found::not_found;
-use router::index::routes;
-use serde_json::json;
-
-use crate::comment::service::CommentService;
-use crate::post::post_service::PostService;
-use crate::user::service::UserService;
-
-#[get("/")]
-async fn default() -> impl Responder {
-    HttpResponse::Ok().json(json!({
-        "success": true,
-        "message": "Welcome to my Rust web-Server",
-        "httpStatusCode": StatusCode::OK.as_u16(),
-        "service": std::env::var("SERVICE_NAME").unwrap_or_else(|_| "Unknown".to_string()),
-    }))
-}
+
+use crate::app::build_app;
+use crate::app_state::build_app_state;
+use crate::utils::config::AppConfig;
+use crate::utils::scheduler::{Schedule, Scheduler};
+use crate::utils::telemetry::{init_telemetry, shutdown_telemetry};
+use crate::utils::tls::load_rustls_config;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     // Load environment variables from .env file
     dotenv().ok();
 
-    // Initialize logger with environment variable support
-    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+    // Initialize logging and tracing with environment variable support
+    let tracer_provider = init_telemetry().expect("Failed to initialize telemetry");
 
-    // Get the port from environment variable, default to 8000
-    let port: u16 = std::env::var("PORT")
-        .unwrap_or_else(|_| "8000".to_string())
-        .parse()
-        .expect("PORT must be a valid number");
+    // Load and validate configuration once, reporting every problem together
+    let config = match AppConfig::load() {
+        Ok(config) => config,
+        Err(problems) => {
+            eprintln!("Invalid configuration:");
+            for problem in &problems {
+                eprintln!("  - {}", problem);
+            }
+            eprintln!("Set these in the environment or in a .env file and restart.");
+            std::process::exit(1);
+        }
+    };
+    let host = config.server.host.clone();
+    let port = config.server.port;
+
+    // Fail before connecting anywhere if the certificate can't be used
+    let tls_config = config
+        .server
+        .tls
+        .as_ref()
+        .map(load_rustls_config)
+        .transpose()
+        .expect("Failed to load TLS certificate");
 
     // Log the server start
-    info!("Starting server on http://localhost:{}", port);
+    let scheme = if tls_config.is_some() {
+        "https"
+    } else {
+        "http"
+    };
+    info!("Starting server on {}://{}:{}", scheme, host, port);
 
     // Connect to MongoDB
-    let mongo_client = database::connect_to_mongo()
+    let mongo_client = database::connect_to_mongo(&config.mongo)
         .await
         .expect("Failed to connect to MongoDB");
 
+    // Populate the database with development data and exit: `cargo run -- --seed`
+    if std::env::args().any(|arg| arg == "--seed") {
+        database::seed_database(&mongo_client)
+            .await
+            .expect("Failed to seed database");
+        return Ok(());
+    }
+
+    // Convert legacy string dates into native BSON dates
+    match database::migrate_string_dates(&mongo_client).await {
+        Ok(0) => {}
+        Ok(count) => info!("Migrated {} legacy date field(s)", count),
+        Err(e) => log::error!("Failed to migrate legacy dates: {}", e),
+    }
+    match database::backfill_phone_hashes(&mongo_client).await {
+        Ok(0) => {}
+        Ok(count) => info!("Hashed {} phone number(s) for contact matching", count),
+        Err(e) => log::error!("Failed to backfill phone hashes: {}", e),
+    }
+
     // Connect to Redis
-    let redis_client = connect_to_redis()
+    let redis_client = connect_to_redis(&config.redis)
         .await
         .expect("Failed to connect to Redis");
-    let redis_service = web::Data::new(RedisService::new(&redis_client));
-
-    // Start WebSocket chat server
-    let chat_server = ChatServer::new().start();
-    info!("WebSocket chat server started");
+    let state = build_app_state(&config, &mongo_client, RedisService::new(&redis_client)).await;
 
-    // Create services
-    let user_service = web::Data::new(UserService::new(&mongo_client));
-    let post_service = web::Data::new(PostService::new(&mongo_client));
-    let comment_service = web::Data::new(CommentService::new(&mongo_client));
+    // Periodic background work, run by one instance at a time
+    let outbox = state.email_outbox.clone();
+    let badges = state.badge_service.clone();
+    let notifications = state.notification_service.clone();
+    let exports = state.export_service.clone();
+    let flushed_counters = state.counter_service.clone();
+    let reconciled_counters = state.counter_service.clone();
+    Scheduler::new(state.redis_service.get_ref().clone())
+        .job(
+            "email-outbox",
+            Schedule::every(Duration::from_secs(60)),
+            move || {
+                let outbox = outbox.clone();
+                async move {
+                    let delivered = outbox.process_pending().await?;
+                    if delivered > 0 {
+                        info!("Outbox delivered {} pending email(s)", delivered);
+                    }
+                    Ok(())
+                }
+            },
+        )
+        .job(
+            "notification-dnd-release",
+            Schedule::every(Duration::from_secs(60)),
+            move || {
+                let notifications = notifications.clone();
+                async move {
+                    let released = notifications.release_queued().await?;
+                    if released > 0 {
+                        info!("Released {} notification(s) held for quiet hours", released);
+                    }
+                    Ok(())
+                }
+            },
+        )
+        .job(
+            "report-exports",
+            Schedule::every(Duration::from_secs(15 * 60)),
+            move || {
+                let exports = exports.clone();
+                async move {
+                    let ran = exports.run_due().await?;
+                    if ran > 0 {
+                        info!("Generated {} scheduled export(s)", ran);
+                    }
+                    Ok(())
+                }
+            },
+        )
+        .job(
+            "counter-flush",
+            Schedule::every(Duration::from_secs(60)),
+            move || {
+                let counters = flushed_counters.clone();
+                async move {
+                    counters.flush().await?;
+                    Ok(())
+                }
+            },
+        )
+        .job(
+            "counter-reconcile",
+            Schedule::every(Duration::from_secs(60 * 60)),
+            move || {
+                let counters = reconciled_counters.clone();
+                async move {
+                    let corrected = counters.reconcile().await?;
+                    if corrected > 0 {
+                        info!("Corrected {} drifted counter(s)", corrected);
+                    }
+                    Ok(())
+                }
+            },
+        )
+        .job("badge-anniversaries", Schedule::daily_at(3, 0), move || {
+            let badges = badges.clone();
+            async move {
+                let awarded = badges.award_anniversaries().await?;
+                if awarded > 0 {
+                    info!("Awarded {} one-year member badge(s)", awarded);
+                }
+                Ok(())
+            }
+        })
+        .start();
 
     // Start the HTTP server
-    HttpServer::new(move || {
-        App::new()
-            .wrap(Logger::default())
-            .wrap(Logger::new("%a %{User-Agent}i"))
-            .app_data(web::Data::new(mongo_client.clone()))
-            .app_data(redis_service.clone())
-            .app_data(web::Data::new(chat_server.clone()))
-            .app_data(user_service.clone())
-            .app_data(post_service.clone())
-            .app_data(comment_service.clone())
-            .configure(routes)
-            .wrap(ErrorHandlers::new().handler(StatusCode::NOT_FOUND, not_found))
-            .service(default)
-    })
-    .bind(("localhost", port))?
-    .run()
-    .await?;
+    let mut server = HttpServer::new(move || build_app(&state))
+        .client_request_timeout(config.limits.client_request_timeout);
+
+    if let Some(workers) = config.server.workers {
+        server = server.workers(workers);
+    }
+
+    let server = match tls_config {
+        Some(tls_config) => server.bind_rustls_0_23((host, port), tls_config)?,
+        None => server.bind((host, port))?,
+    };
+    server.run().await?;
 
     // Log after server has started (this line will only be reached when the server shuts down)
     info!("Server has stopped");
+    shutdown_telemetry(tracer_provider);
 
     Ok(())
 }
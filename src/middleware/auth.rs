@@ -1,30 +1,62 @@
-use std::env;
-
+use crate::access_token::model::{ACCESS_TOKEN_PREFIX, required_scope};
+use crate::access_token::service::AccessTokenService;
+use crate::api_key::service::hash_key;
 use crate::database::RedisService;
-use crate::utils::error::CustomError;
-use actix_web::{Error, HttpMessage, dev::ServiceRequest, web};
+use crate::user::model::Role;
+use crate::user::service::UserService;
+use crate::utils::config::AppConfig;
+use crate::utils::error::{CustomError, ErrorCode};
+use crate::utils::i18n::Locale;
+use actix_web::dev::Payload;
+use actix_web::{Error, FromRequest, HttpMessage, HttpRequest, dev::ServiceRequest, web};
 use actix_web_httpauth::extractors::bearer::BearerAuth;
+use futures_util::future::{Ready, ready};
 use jsonwebtoken::{DecodingKey, Validation, decode};
+use mongodb::bson::oid::ObjectId;
+use rand::Rng;
+use rand::distr::Alphanumeric;
 use serde::{Deserialize, Serialize};
 
+/// Lifetime of an access token and its Redis session
+pub const ACCESS_TOKEN_TTL_SECONDS: u64 = 15 * 60;
+
+/// Lifetime of a refresh token; each use replaces it with a new one
+pub const REFRESH_TOKEN_TTL_SECONDS: u64 = 30 * 24 * 60 * 60;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
     pub id: String,
+    /// Tokens issued before roles existed carry none and act as regular users
+    #[serde(default)]
+    pub role: Role,
+    /// Profile language, so responses are localized without a lookup
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<Locale>,
     pub exp: usize,
 }
 
-/// Verify JWT token and validate session in Redis
+/// Verify JWT token and validate session in Redis. Personal access tokens
+/// are accepted too, for the routes their scopes cover.
 pub async fn verify_token(
     req: ServiceRequest,
     credentials: BearerAuth,
 ) -> Result<ServiceRequest, (Error, ServiceRequest)> {
     let token = credentials.token();
-    let secret = std::env::var("JWT_SECRET").unwrap_or_else(|_| "secret".to_string());
+    if token.starts_with(ACCESS_TOKEN_PREFIX) {
+        return verify_access_token(req, token).await;
+    }
+
+    let Some(config) = req.app_data::<web::Data<AppConfig>>() else {
+        return Err((
+            CustomError::InternalServerError("Configuration is not registered".to_string()).into(),
+            req,
+        ));
+    };
 
     // First decode the JWT
     let token_data = match decode::<Claims>(
         token,
-        &DecodingKey::from_secret(secret.as_bytes()),
+        &DecodingKey::from_secret(config.jwt_secret.as_bytes()),
         &Validation::default(),
     ) {
         Ok(data) => data,
@@ -69,22 +101,92 @@ pub async fn verify_token(
     }
 }
 
-/// Create a JWT token and store session in Redis
+/// Authenticate a personal access token. The caller acts as a regular user
+/// whatever their role, and only on routes matching the token's scopes.
+async fn verify_access_token(
+    req: ServiceRequest,
+    token: &str,
+) -> Result<ServiceRequest, (Error, ServiceRequest)> {
+    let Some(token_service) = req.app_data::<web::Data<AccessTokenService>>() else {
+        return Err((
+            actix_web::error::ErrorUnauthorized("Access tokens are not accepted"),
+            req,
+        ));
+    };
+
+    let access_token = match token_service.verify(token).await {
+        Ok(access_token) => access_token,
+        Err(e) => return Err((e.into(), req)),
+    };
+
+    // Tokens outlive sessions, so the account is checked on every use
+    let Some(user_service) = req.app_data::<web::Data<UserService>>() else {
+        return Err((
+            actix_web::error::ErrorUnauthorized("Access tokens are not accepted"),
+            req,
+        ));
+    };
+    let user = match user_service.get_user(&access_token.user_id).await {
+        Ok(Some(user)) => user,
+        Ok(None) => {
+            return Err((
+                CustomError::UnauthorizedError("Invalid access token".to_string()).into(),
+                req,
+            ));
+        }
+        Err(e) => return Err((e.into(), req)),
+    };
+    if let Some(until) = user.suspended_until
+        && until > chrono::Utc::now()
+    {
+        return Err((
+            CustomError::coded(
+                ErrorCode::AuthAccountSuspended,
+                format!("Account suspended until {}", until.to_rfc3339()),
+            )
+            .into(),
+            req,
+        ));
+    }
+
+    let allowed = required_scope(req.method(), req.path())
+        .is_some_and(|scope| access_token.has_scope(&scope));
+    if !allowed {
+        return Err((
+            actix_web::error::ErrorForbidden("Access token scopes do not cover this route"),
+            req,
+        ));
+    }
+
+    let claims = Claims {
+        id: access_token.user_id.to_hex(),
+        role: Role::User,
+        locale: None,
+        exp: access_token
+            .expires_at
+            .map_or(usize::MAX, |at| at.timestamp() as usize),
+    };
+    req.extensions_mut().insert(claims);
+    Ok(req)
+}
+
+/// Create a JWT token signed with `secret` and store session in Redis
 pub async fn create_token_with_session(
     user_id: &str,
+    role: Role,
+    locale: Locale,
+    secret: &str,
     redis_service: &RedisService,
 ) -> Result<String, Error> {
-    let secret = env::var("JWT_SECRET")
-        .map_err(|_| CustomError::UnauthorizedError("JWT_SECRET must be set".to_string()))?;
-
-    // Token expires in 24 hours
     let expiration = chrono::Utc::now()
-        .checked_add_signed(chrono::Duration::hours(24))
+        .checked_add_signed(chrono::Duration::seconds(ACCESS_TOKEN_TTL_SECONDS as i64))
         .expect("valid timestamp")
         .timestamp() as usize;
 
     let claims = Claims {
         id: user_id.to_owned(),
+        role,
+        locale: Some(locale),
         exp: expiration,
     };
 
@@ -95,43 +197,81 @@ pub async fn create_token_with_session(
     )
     .map_err(|_| CustomError::BadRequestError("Token generation failed".to_string()))?;
 
-    // Store session in Redis (24 hours = 86400 seconds)
     redis_service
-        .store_session(user_id, &token, 86400)
+        .store_session(user_id, &token, ACCESS_TOKEN_TTL_SECONDS)
         .await
         .map_err(|e| CustomError::InternalServerError(format!("Failed to store session: {}", e)))?;
 
     Ok(token)
 }
 
-/// Create a JWT token without Redis session (for backward compatibility)
-pub async fn create_token(user_id: &str) -> Result<String, Error> {
-    let secret = env::var("JWT_SECRET")
-        .map_err(|_| CustomError::UnauthorizedError("JWT_SECRET must be set".to_string()))?;
-    let expiration = chrono::Utc::now()
-        .checked_add_signed(chrono::Duration::hours(24))
-        .expect("valid timestamp")
-        .timestamp() as usize;
+/// A short-lived access token and the refresh token that replaces it
+#[derive(Debug, Serialize)]
+pub struct TokenPair {
+    pub token: String,
+    pub refresh_token: String,
+    /// Seconds until `token` expires
+    pub expires_in: u64,
+}
 
-    let claims = Claims {
-        id: user_id.to_owned(),
-        exp: expiration,
-    };
+/// Issue an access token with its session plus a refresh token; only the
+/// refresh token's hash is stored
+pub async fn create_token_pair(
+    user_id: &str,
+    role: Role,
+    locale: Locale,
+    secret: &str,
+    redis_service: &RedisService,
+) -> Result<TokenPair, Error> {
+    let token = create_token_with_session(user_id, role, locale, secret, redis_service).await?;
 
-    let token = jsonwebtoken::encode(
-        &jsonwebtoken::Header::default(),
-        &claims,
-        &jsonwebtoken::EncodingKey::from_secret(secret.as_bytes()),
-    )
-    .map_err(|_| CustomError::BadRequestError("Token generation failed".to_string()))?;
+    let refresh_token: String = rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(48)
+        .map(char::from)
+        .collect();
+    redis_service
+        .store_refresh_token(
+            user_id,
+            &hash_key(&refresh_token),
+            REFRESH_TOKEN_TTL_SECONDS,
+        )
+        .await
+        .map_err(CustomError::InternalServerError)?;
 
-    Ok(token)
+    Ok(TokenPair {
+        token,
+        refresh_token,
+        expires_in: ACCESS_TOKEN_TTL_SECONDS,
+    })
+}
+
+/// Consume a refresh token and return the user it was issued to. Used and
+/// revoked tokens are rejected, so each one works exactly once.
+pub async fn consume_refresh_token(
+    refresh_token: &str,
+    redis_service: &RedisService,
+) -> Result<ObjectId, CustomError> {
+    let user_id = redis_service
+        .take_refresh_token(&hash_key(refresh_token))
+        .await
+        .map_err(CustomError::InternalServerError)?
+        .ok_or_else(|| {
+            CustomError::coded(
+                ErrorCode::AuthInvalidRefreshToken,
+                "Invalid or expired refresh token",
+            )
+        })?;
+
+    ObjectId::parse_str(&user_id).map_err(|_| {
+        CustomError::coded(ErrorCode::AuthInvalidRefreshToken, "Invalid refresh token")
+    })
 }
 
-/// Invalidate a user's session (logout)
+/// Invalidate a user's session and refresh tokens (logout)
 pub async fn invalidate_session(user_id: &str, redis_service: &RedisService) -> Result<(), Error> {
     redis_service
-        .invalidate_session(user_id)
+        .invalidate_all_sessions(user_id)
         .await
         .map_err(|e| {
             CustomError::InternalServerError(format!("Failed to invalidate session: {}", e))
@@ -146,3 +286,61 @@ pub fn get_user_id_from_request(req: &actix_web::HttpRequest) -> Option<String>
         .get::<Claims>()
         .map(|claims| claims.id.clone())
 }
+
+/// The authenticated caller, extracted from the claims set by `verify_token`.
+///
+/// Responds 401 when the route isn't behind the auth middleware or no token
+/// was given, and 400 when the token carries a malformed user id.
+#[derive(Debug, Clone, Copy)]
+pub struct AuthUser {
+    pub id: ObjectId,
+    pub role: Role,
+}
+
+impl AuthUser {
+    pub fn is_admin(&self) -> bool {
+        self.role == Role::Admin
+    }
+
+    /// Reject the request unless the caller is an administrator
+    pub fn require_admin(&self) -> Result<(), CustomError> {
+        if !self.is_admin() {
+            return Err(CustomError::ForbiddenError(
+                "Administrator access required".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Reject the request unless the caller is a moderator or administrator
+    pub fn require_moderator(&self) -> Result<(), CustomError> {
+        if !matches!(self.role, Role::Moderator | Role::Admin) {
+            return Err(CustomError::ForbiddenError(
+                "Moderator access required".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl FromRequest for AuthUser {
+    type Error = CustomError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let result = req
+            .extensions()
+            .get::<Claims>()
+            .ok_or_else(|| CustomError::UnauthorizedError("Not authenticated".to_string()))
+            .and_then(|claims| {
+                let id = ObjectId::parse_str(&claims.id)
+                    .map_err(|_| CustomError::BadRequestError("Invalid user ID".to_string()))?;
+                Ok(AuthUser {
+                    id,
+                    role: claims.role,
+                })
+            });
+
+        ready(result)
+    }
+}
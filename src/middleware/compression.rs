@@ -0,0 +1,99 @@
+use crate::database::parse_optional;
+use actix_web::Error;
+use actix_web::body::{BodySize, MessageBody};
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform, forward_ready};
+use actix_web::http::header::{self, ContentEncoding, HeaderValue};
+use futures_util::future::{LocalBoxFuture, Ready, ready};
+use std::rc::Rc;
+
+/// Decides which responses the outer `Compress` middleware may encode.
+///
+/// Only JSON bodies of at least `min_bytes` are worth compressing; everything
+/// else is marked `Content-Encoding: identity`, which `Compress` leaves alone.
+/// Must be wrapped inside (before) `Compress`.
+#[derive(Debug, Clone, Copy)]
+pub struct CompressionPolicy {
+    pub enabled: bool,
+    pub min_bytes: u64,
+}
+
+impl CompressionPolicy {
+    /// Load from `COMPRESSION_ENABLED` (default true) and
+    /// `COMPRESSION_MIN_BYTES` (default 1024)
+    pub fn from_env() -> Result<Self, String> {
+        let enabled = std::env::var("COMPRESSION_ENABLED")
+            .map(|value| !(value.eq_ignore_ascii_case("false") || value == "0"))
+            .unwrap_or(true);
+        let min_bytes = parse_optional("COMPRESSION_MIN_BYTES")?.unwrap_or(1024);
+
+        Ok(CompressionPolicy { enabled, min_bytes })
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for CompressionPolicy
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = CompressionPolicyMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(CompressionPolicyMiddleware {
+            service: Rc::new(service),
+            policy: *self,
+        }))
+    }
+}
+
+pub struct CompressionPolicyMiddleware<S> {
+    service: Rc<S>,
+    policy: CompressionPolicy,
+}
+
+impl<S, B> Service<ServiceRequest> for CompressionPolicyMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let policy = self.policy;
+
+        Box::pin(async move {
+            let mut res = service.call(req).await?;
+
+            let is_json = res
+                .headers()
+                .get(header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|value| value.starts_with("application/json"));
+            let large_enough = match res.response().body().size() {
+                BodySize::Sized(size) => size >= policy.min_bytes,
+                // Streamed bodies (exports) are usually large
+                BodySize::Stream => true,
+                BodySize::None => false,
+            };
+
+            if !(policy.enabled && is_json && large_enough)
+                && !res.headers().contains_key(header::CONTENT_ENCODING)
+            {
+                res.headers_mut().insert(
+                    header::CONTENT_ENCODING,
+                    HeaderValue::from_static(ContentEncoding::Identity.as_str()),
+                );
+            }
+
+            Ok(res)
+        })
+    }
+}
@@ -0,0 +1,90 @@
+use actix_cors::Cors;
+use actix_web::http::header;
+use std::env;
+
+/// Cross-origin settings loaded from the environment
+#[derive(Debug, Clone)]
+pub struct CorsConfig {
+    /// Origins allowed to call the API, e.g. `https://app.example.com`
+    pub allowed_origins: Vec<String>,
+    /// Whether `CORS_ALLOWED_ORIGINS=*` was given
+    pub allow_any_origin: bool,
+    /// Whether browsers may send cookies/Authorization cross-origin
+    pub allow_credentials: bool,
+    /// How long browsers may cache preflight responses, in seconds
+    pub max_age: usize,
+}
+
+impl CorsConfig {
+    /// Load CORS configuration from `CORS_ALLOWED_ORIGINS` (comma separated,
+    /// or `*`), `CORS_ALLOW_CREDENTIALS` and `CORS_MAX_AGE`
+    pub fn from_env() -> Result<Self, String> {
+        let origins: Vec<String> = env::var("CORS_ALLOWED_ORIGINS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|origin| origin.trim().trim_end_matches('/').to_string())
+            .filter(|origin| !origin.is_empty())
+            .collect();
+
+        let allow_any_origin = origins.iter().any(|origin| origin == "*");
+        let allow_credentials = env::var("CORS_ALLOW_CREDENTIALS")
+            .map(|value| value.eq_ignore_ascii_case("true") || value == "1")
+            .unwrap_or(false);
+
+        // Reflecting any origin while allowing credentials would let every
+        // site act on behalf of logged-in users
+        if allow_any_origin && allow_credentials {
+            return Err(
+                "CORS_ALLOWED_ORIGINS=* cannot be combined with CORS_ALLOW_CREDENTIALS".to_string(),
+            );
+        }
+
+        let max_age = match env::var("CORS_MAX_AGE") {
+            Ok(value) => value
+                .parse()
+                .map_err(|_| "CORS_MAX_AGE must be a valid number".to_string())?,
+            Err(_) => 3600,
+        };
+
+        Ok(Self {
+            allowed_origins: origins.into_iter().filter(|origin| origin != "*").collect(),
+            allow_any_origin,
+            allow_credentials,
+            max_age,
+        })
+    }
+
+    /// CORS is off (same-origin only) until origins are configured
+    pub fn is_enabled(&self) -> bool {
+        self.allow_any_origin || !self.allowed_origins.is_empty()
+    }
+}
+
+/// Build the CORS middleware.
+///
+/// Browsers don't preflight WebSocket upgrades, but they do send `Origin`,
+/// so the same origin list also guards `/ws/*` against cross-site hijacking.
+pub fn cors(config: &CorsConfig) -> Cors {
+    let mut cors = Cors::default()
+        .allowed_methods(vec!["GET", "POST", "PUT", "PATCH", "DELETE", "OPTIONS"])
+        .allowed_headers(vec![
+            header::AUTHORIZATION,
+            header::CONTENT_TYPE,
+            header::ACCEPT,
+        ])
+        .max_age(config.max_age);
+
+    if config.allow_any_origin {
+        cors = cors.allow_any_origin();
+    } else {
+        for origin in &config.allowed_origins {
+            cors = cors.allowed_origin(origin);
+        }
+    }
+
+    if config.allow_credentials {
+        cors = cors.supports_credentials();
+    }
+
+    cors
+}
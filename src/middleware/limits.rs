@@ -0,0 +1,129 @@
+use crate::database::parse_optional;
+use crate::utils::error::CustomError;
+use actix_web::body::EitherBody;
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform, forward_ready};
+use actix_web::{Error, ResponseError};
+use futures_util::future::{LocalBoxFuture, Ready, ready};
+use std::rc::Rc;
+use std::time::Duration;
+
+/// Body size and timeout limits loaded from the environment
+#[derive(Debug, Clone, Copy)]
+pub struct HttpLimits {
+    /// Largest accepted JSON body (`JSON_LIMIT_BYTES`, default 1MB)
+    pub json_limit: usize,
+    /// Largest accepted multipart upload (`UPLOAD_LIMIT_BYTES`, default 50MB)
+    pub upload_limit: usize,
+    /// How long a client may take to send request headers
+    /// (`CLIENT_REQUEST_TIMEOUT_MS`, default 5s)
+    pub client_request_timeout: Duration,
+}
+
+impl HttpLimits {
+    pub fn from_env() -> Result<Self, String> {
+        Ok(Self {
+            json_limit: parse_optional("JSON_LIMIT_BYTES")?.unwrap_or(1024 * 1024),
+            upload_limit: parse_optional("UPLOAD_LIMIT_BYTES")?.unwrap_or(50 * 1024 * 1024),
+            client_request_timeout: Duration::from_millis(
+                parse_optional("CLIENT_REQUEST_TIMEOUT_MS")?.unwrap_or(5_000),
+            ),
+        })
+    }
+}
+
+/// Abort handlers that run longer than a deadline with a 504.
+///
+/// Wrap each scope with the deadline that suits it; scopes are not nested
+/// under a global timeout so slow uploads can get a longer one.
+#[derive(Debug, Clone, Copy)]
+pub struct RequestTimeout(Duration);
+
+impl RequestTimeout {
+    pub fn new(timeout: Duration) -> Self {
+        RequestTimeout(timeout)
+    }
+
+    /// Regular API routes (`REQUEST_TIMEOUT_SECS`, default 30)
+    pub fn standard() -> Self {
+        Self::from_env("REQUEST_TIMEOUT_SECS", 30)
+    }
+
+    /// File uploads (`UPLOAD_TIMEOUT_SECS`, default 120)
+    pub fn uploads() -> Self {
+        Self::from_env("UPLOAD_TIMEOUT_SECS", 120)
+    }
+
+    fn from_env(name: &str, default_seconds: u64) -> Self {
+        let seconds = std::env::var(name)
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(default_seconds);
+        Self::new(Duration::from_secs(seconds))
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RequestTimeout
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = RequestTimeoutMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestTimeoutMiddleware {
+            service: Rc::new(service),
+            timeout: self.0,
+        }))
+    }
+}
+
+pub struct RequestTimeoutMiddleware<S> {
+    service: Rc<S>,
+    timeout: Duration,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestTimeoutMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let timeout = self.timeout;
+
+        Box::pin(async move {
+            let http_req = req.request().clone();
+
+            match tokio::time::timeout(timeout, service.call(req)).await {
+                Ok(res) => res.map(|res| res.map_into_left_body()),
+                Err(_) => {
+                    log::warn!(
+                        "{} {} timed out after {:?}",
+                        http_req.method(),
+                        http_req.path(),
+                        timeout
+                    );
+                    let response = CustomError::TimeoutError(format!(
+                        "Request did not complete within {} seconds",
+                        timeout.as_secs()
+                    ))
+                    .error_response();
+                    Ok(ServiceResponse::new(
+                        http_req,
+                        response.map_into_right_body(),
+                    ))
+                }
+            }
+        })
+    }
+}
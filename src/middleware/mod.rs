@@ -1,3 +1,8 @@
 pub mod auth;
+pub mod compression;
+pub mod cors;
 pub mod error_handler;
+pub mod limits;
 pub mod not_found;
+pub mod rate_limit;
+pub mod security_headers;
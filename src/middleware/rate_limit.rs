@@ -0,0 +1,372 @@
+use crate::database::{RateLimitDecision, RedisService, RedisServiceTrait, parse_optional};
+use crate::utils::config::AppConfig;
+use crate::utils::error::CustomError;
+use actix_web::body::EitherBody;
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform, forward_ready};
+use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue, RETRY_AFTER};
+use actix_web::{Error, HttpRequest, ResponseError, web};
+use futures_util::future::{LocalBoxFuture, Ready, ready};
+use std::net::IpAddr;
+use std::rc::Rc;
+
+/// Login/registration/OTP attempts per client IP
+pub const AUTH_RATE_LIMIT: u64 = 10;
+pub const AUTH_RATE_WINDOW_SECONDS: u64 = 60;
+
+/// Comments a user can post
+pub const COMMENT_RATE_LIMIT: u64 = 5;
+pub const COMMENT_RATE_WINDOW_SECONDS: u64 = 60;
+
+/// Chat messages a user can send
+pub const CHAT_RATE_LIMIT: u64 = 20;
+pub const CHAT_RATE_WINDOW_SECONDS: u64 = 10;
+
+/// Contact lookups a user can make; kept low since short phone numbers make
+/// their hashes easy to enumerate
+pub const CONTACT_SYNC_RATE_LIMIT: u64 = 5;
+pub const CONTACT_SYNC_RATE_WINDOW_SECONDS: u64 = 3600;
+
+/// Get the client IP used as a rate limit key, trusting the proxies listed
+/// in the registered `AppConfig`. See `client_ip_behind`.
+pub fn client_ip(req: &HttpRequest) -> String {
+    let trusted = req
+        .app_data::<web::Data<AppConfig>>()
+        .map(|config| config.server.trusted_proxies.as_slice())
+        .unwrap_or_default();
+    client_ip_behind(req, trusted)
+}
+
+/// Get the client IP of a request that reached us through `trusted` proxies.
+///
+/// This is the peer address unless the peer is a trusted proxy. Then
+/// `X-Forwarded-For` is walked from the right, past any other trusted
+/// proxies, and the first untrusted hop is used; entries further left are
+/// client-supplied and ignored. A malformed hop stops the walk at the last
+/// address that parsed.
+fn client_ip_behind(req: &HttpRequest, trusted: &[IpAddr]) -> String {
+    let Some(peer) = req.peer_addr().map(|addr| addr.ip()) else {
+        return "unknown".to_string();
+    };
+    if !trusted.contains(&peer) {
+        return peer.to_string();
+    }
+
+    let forwarded_for = req
+        .headers()
+        .get_all("x-forwarded-for")
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .collect::<Vec<_>>();
+    let mut client = peer;
+    for hop in forwarded_for.into_iter().rev() {
+        let Ok(hop) = hop.parse::<IpAddr>() else {
+            break;
+        };
+        client = hop;
+        if !trusted.contains(&hop) {
+            break;
+        }
+    }
+
+    client.to_string()
+}
+
+/// Enforce a sliding-window rate limit, failing open if Redis is unavailable
+pub async fn check_rate_limit<R: RedisServiceTrait>(
+    redis_service: &R,
+    key: &str,
+    max_requests: u64,
+    window_seconds: u64,
+) -> Result<(), CustomError> {
+    match redis_service
+        .sliding_window_check(key, max_requests, window_seconds)
+        .await
+    {
+        Ok(decision) if decision.allowed => Ok(()),
+        Ok(decision) => Err(CustomError::TooManyRequestsError(format!(
+            "Rate limit exceeded. Try again in {} seconds.",
+            decision.retry_after_seconds
+        ))),
+        Err(e) => {
+            log::warn!("Rate limiter unavailable: {}", e);
+            Ok(())
+        }
+    }
+}
+
+/// Enforce a room's slow mode for one user, failing open if Redis is unavailable
+pub async fn check_slow_mode(
+    redis_service: &RedisService,
+    room_id: &str,
+    user_id: &str,
+    seconds: u32,
+) -> Result<(), CustomError> {
+    match redis_service
+        .cooldown_start(
+            &format!("chat:slow:{}:{}", room_id, user_id),
+            seconds.into(),
+        )
+        .await
+    {
+        Ok(0) => Ok(()),
+        Ok(retry_after_seconds) => Err(CustomError::SlowModeError {
+            retry_after_seconds,
+        }),
+        Err(e) => {
+            log::warn!("Slow mode check unavailable: {}", e);
+            Ok(())
+        }
+    }
+}
+
+/// A request budget for one IP within a sliding window
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitRule {
+    pub max_requests: u64,
+    pub window_seconds: u64,
+}
+
+impl RateLimitRule {
+    /// Per-minute budget from an env var, falling back to `default`
+    fn per_minute_from_env(name: &str, default: u64) -> Result<Self, String> {
+        Ok(RateLimitRule {
+            max_requests: parse_optional(name)?.unwrap_or(default),
+            window_seconds: 60,
+        })
+    }
+}
+
+/// Per-IP budgets for `IpRateLimit`
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitConfig {
+    /// App-wide limit (`RATE_LIMIT_GLOBAL_PER_MINUTE`, default 300)
+    pub global: RateLimitRule,
+    /// Stricter limit for endpoints reachable without a token
+    /// (`RATE_LIMIT_PUBLIC_PER_MINUTE`, default 60)
+    pub public: RateLimitRule,
+}
+
+impl RateLimitConfig {
+    pub fn from_env() -> Result<Self, String> {
+        Ok(Self {
+            global: RateLimitRule::per_minute_from_env("RATE_LIMIT_GLOBAL_PER_MINUTE", 300)?,
+            public: RateLimitRule::per_minute_from_env("RATE_LIMIT_PUBLIC_PER_MINUTE", 60)?,
+        })
+    }
+}
+
+/// Which of the configured budgets an `IpRateLimit` enforces
+#[derive(Debug, Clone, Copy)]
+enum RateLimitTier {
+    Global,
+    Public,
+}
+
+impl RateLimitTier {
+    fn rule(self, config: &RateLimitConfig) -> RateLimitRule {
+        match self {
+            RateLimitTier::Global => config.global,
+            RateLimitTier::Public => config.public,
+        }
+    }
+}
+
+/// Per-IP rate limiting middleware. Clients are identified as by `client_ip`.
+///
+/// Wrap the whole app with `IpRateLimit::global()` and individual scopes with
+/// stricter limits; each instance counts requests in its own bucket, so a
+/// request through a limited scope is checked against both. Budgets come
+/// from the `AppConfig` registered as app data.
+#[derive(Clone)]
+pub struct IpRateLimit {
+    bucket: &'static str,
+    tier: RateLimitTier,
+}
+
+impl IpRateLimit {
+    /// App-wide limit
+    pub fn global() -> Self {
+        IpRateLimit {
+            bucket: "global",
+            tier: RateLimitTier::Global,
+        }
+    }
+
+    /// Stricter limit for endpoints reachable without a token
+    pub fn public_endpoints(bucket: &'static str) -> Self {
+        IpRateLimit {
+            bucket,
+            tier: RateLimitTier::Public,
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for IpRateLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = IpRateLimitMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(IpRateLimitMiddleware {
+            service: Rc::new(service),
+            bucket: self.bucket,
+            tier: self.tier,
+        }))
+    }
+}
+
+pub struct IpRateLimitMiddleware<S> {
+    service: Rc<S>,
+    bucket: &'static str,
+    tier: RateLimitTier,
+}
+
+impl<S, B> Service<ServiceRequest> for IpRateLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let bucket = self.bucket;
+        let tier = self.tier;
+
+        Box::pin(async move {
+            let redis_service = req.app_data::<web::Data<RedisService>>().cloned();
+            let rule = req
+                .app_data::<web::Data<AppConfig>>()
+                .map(|config| tier.rule(&config.rate_limits));
+            let ip = client_ip(req.request());
+
+            // Fail open when Redis or the configuration is missing, or Redis
+            // is unreachable
+            let decision = match (redis_service, rule) {
+                (Some(redis_service), Some(rule)) => match redis_service
+                    .sliding_window_check(
+                        &format!("ip:{}:{}", bucket, ip),
+                        rule.max_requests,
+                        rule.window_seconds,
+                    )
+                    .await
+                {
+                    Ok(decision) => Some(decision),
+                    Err(e) => {
+                        log::warn!("Rate limiter unavailable: {}", e);
+                        None
+                    }
+                },
+                _ => None,
+            };
+
+            let Some(decision) = decision else {
+                return service.call(req).await.map(|res| res.map_into_left_body());
+            };
+
+            if !decision.allowed {
+                let mut response = CustomError::TooManyRequestsError(format!(
+                    "Rate limit exceeded. Try again in {} seconds.",
+                    decision.retry_after_seconds
+                ))
+                .error_response();
+                insert_rate_limit_headers(response.headers_mut(), &decision);
+                response
+                    .headers_mut()
+                    .insert(RETRY_AFTER, HeaderValue::from(decision.retry_after_seconds));
+                return Ok(req.into_response(response).map_into_right_body());
+            }
+
+            let mut res = service.call(req).await?;
+            insert_rate_limit_headers(res.headers_mut(), &decision);
+            Ok(res.map_into_left_body())
+        })
+    }
+}
+
+/// Add `X-RateLimit-*` headers. Responses pass through the innermost limiter
+/// first, so when several apply the most specific scope's values are kept.
+fn insert_rate_limit_headers(headers: &mut HeaderMap, decision: &RateLimitDecision) {
+    let values = [
+        ("x-ratelimit-limit", decision.limit),
+        ("x-ratelimit-remaining", decision.remaining),
+        ("x-ratelimit-reset", decision.reset_after_seconds),
+    ];
+
+    for (name, value) in values {
+        let name = HeaderName::from_static(name);
+        if !headers.contains_key(&name) {
+            headers.insert(name, HeaderValue::from(value));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    fn request(peer: &str, forwarded_for: Option<&str>) -> HttpRequest {
+        let req = TestRequest::default().peer_addr(format!("{}:443", peer).parse().unwrap());
+        match forwarded_for {
+            Some(value) => req.insert_header(("x-forwarded-for", value)),
+            None => req,
+        }
+        .to_http_request()
+    }
+
+    #[test]
+    fn untrusted_peers_cannot_spoof_their_address() {
+        let trusted = [ip("10.0.0.1")];
+        let req = request("203.0.113.7", Some("198.51.100.1"));
+
+        assert_eq!(client_ip_behind(&req, &trusted), "203.0.113.7");
+    }
+
+    #[test]
+    fn forwarded_for_is_ignored_without_trusted_proxies() {
+        let req = request("10.0.0.1", Some("198.51.100.1"));
+
+        assert_eq!(client_ip_behind(&req, &[]), "10.0.0.1");
+    }
+
+    #[test]
+    fn chained_trusted_proxies_are_skipped() {
+        let trusted = [ip("10.0.0.1"), ip("10.0.0.2")];
+        // The client prepended a fake hop; only the rightmost untrusted one counts
+        let req = request("10.0.0.1", Some("1.2.3.4, 198.51.100.9, 10.0.0.2"));
+
+        assert_eq!(client_ip_behind(&req, &trusted), "198.51.100.9");
+    }
+
+    #[test]
+    fn malformed_hops_stop_the_walk() {
+        let trusted = [ip("10.0.0.1"), ip("10.0.0.2")];
+        let req = request("10.0.0.1", Some("198.51.100.9, not-an-ip, 10.0.0.2"));
+
+        assert_eq!(client_ip_behind(&req, &trusted), "10.0.0.2");
+    }
+
+    #[test]
+    fn trusted_peer_without_forwarded_for_is_the_client() {
+        let trusted = [ip("10.0.0.1")];
+        let req = request("10.0.0.1", None);
+
+        assert_eq!(client_ip_behind(&req, &trusted), "10.0.0.1");
+    }
+}
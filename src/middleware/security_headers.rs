@@ -0,0 +1,51 @@
+use crate::database::parse_optional;
+use actix_web::http::header;
+use actix_web::middleware::DefaultHeaders;
+use std::env;
+
+/// A JSON API never serves documents, so nothing may be loaded or framed
+const DEFAULT_CONTENT_SECURITY_POLICY: &str = "default-src 'none'; frame-ancestors 'none'";
+
+/// One year, the minimum accepted by HSTS preload lists
+const DEFAULT_HSTS_MAX_AGE_SECONDS: u64 = 31_536_000;
+
+/// Overrides for the headers `security_headers` adds
+#[derive(Debug, Clone)]
+pub struct SecurityHeadersConfig {
+    /// `CONTENT_SECURITY_POLICY`, defaulting to a policy that allows nothing
+    pub content_security_policy: String,
+    /// HSTS lifetime in seconds (`HSTS_MAX_AGE`, default one year); 0
+    /// disables HSTS, e.g. for local HTTP development
+    pub hsts_max_age: u64,
+}
+
+impl SecurityHeadersConfig {
+    pub fn from_env() -> Result<Self, String> {
+        Ok(Self {
+            content_security_policy: env::var("CONTENT_SECURITY_POLICY")
+                .unwrap_or_else(|_| DEFAULT_CONTENT_SECURITY_POLICY.to_string()),
+            hsts_max_age: parse_optional("HSTS_MAX_AGE")?.unwrap_or(DEFAULT_HSTS_MAX_AGE_SECONDS),
+        })
+    }
+}
+
+/// Security headers added to every response that doesn't set its own
+pub fn security_headers(config: &SecurityHeadersConfig) -> DefaultHeaders {
+    let mut headers = DefaultHeaders::new()
+        .add((header::X_CONTENT_TYPE_OPTIONS, "nosniff"))
+        .add((header::X_FRAME_OPTIONS, "DENY"))
+        .add((header::REFERRER_POLICY, "no-referrer"))
+        .add((
+            header::CONTENT_SECURITY_POLICY,
+            config.content_security_policy.clone(),
+        ));
+
+    if config.hsts_max_age > 0 {
+        headers = headers.add((
+            header::STRICT_TRANSPORT_SECURITY,
+            format!("max-age={}; includeSubDomains", config.hsts_max_age),
+        ));
+    }
+
+    headers
+}
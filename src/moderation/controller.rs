@@ -0,0 +1,284 @@
+use crate::database::RedisService;
+use crate::middleware::auth::{AuthUser, invalidate_session};
+use crate::moderation::model::{
+    CreateReportRequest, ModerationAction, ModerationActionRequest, ModerationOutcome, ReportStatus,
+};
+use crate::moderation::service::ModerationService;
+use crate::notification::model::NotificationKind;
+use crate::notification::service::NotificationService;
+use crate::post::post_controller::invalidate_post_detail;
+use crate::utils::error::CustomError;
+use crate::utils::i18n::Locale;
+use crate::utils::response::ApiResponse;
+use crate::utils::validation::ValidatedJson;
+use actix_web::{HttpResponse, web};
+use mongodb::bson::oid::ObjectId;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct ReportQuery {
+    pub status: Option<String>,
+    pub limit: Option<i64>,
+}
+
+fn parse_id(id: String, what: &str) -> Result<ObjectId, CustomError> {
+    ObjectId::parse_str(id)
+        .map_err(|_| CustomError::BadRequestError(format!("Invalid {} ID", what)))
+}
+
+/// Report a post, comment or chat message
+/// POST /reports
+pub async fn create_report(
+    locale: Locale,
+    auth_user: AuthUser,
+    moderation_service: web::Data<ModerationService>,
+    body: ValidatedJson<CreateReportRequest>,
+) -> Result<HttpResponse, CustomError> {
+    let report = moderation_service
+        .create_report(auth_user.id, body.into_inner())
+        .await?;
+
+    Ok(ApiResponse::created(locale.t("report-created"))
+        .data(report)
+        .into())
+}
+
+/// Moderator queue
+/// GET /moderation/reports?status=open&limit=50
+pub async fn list_reports(
+    auth_user: AuthUser,
+    moderation_service: web::Data<ModerationService>,
+    query: web::Query<ReportQuery>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_moderator()?;
+
+    let status = match query.status.as_deref() {
+        Some(name) => ReportStatus::from_name(name).ok_or_else(|| {
+            CustomError::BadRequestError(
+                "status must be one of open, actioned or dismissed".to_string(),
+            )
+        })?,
+        None => ReportStatus::Open,
+    };
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+
+    let reports = moderation_service.list_reports(status, limit).await?;
+
+    Ok(ApiResponse::ok("Reports retrieved successfully")
+        .list(reports)
+        .into())
+}
+
+/// GET /moderation/reports/{id}
+pub async fn get_report(
+    auth_user: AuthUser,
+    moderation_service: web::Data<ModerationService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_moderator()?;
+
+    let id = parse_id(path.into_inner(), "report")?;
+    let report = moderation_service
+        .get_report(&id)
+        .await?
+        .ok_or_else(|| CustomError::NotFoundError("Report not found".to_string()))?;
+
+    Ok(ApiResponse::ok("Report retrieved successfully")
+        .data(report)
+        .into())
+}
+
+/// Resolve a report with a moderation action
+/// POST /moderation/reports/{id}/actions
+pub async fn take_action(
+    auth_user: AuthUser,
+    moderation_service: web::Data<ModerationService>,
+    notification_service: web::Data<NotificationService>,
+    redis_service: web::Data<RedisService>,
+    path: web::Path<String>,
+    body: ValidatedJson<ModerationActionRequest>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_moderator()?;
+
+    let id = parse_id(path.into_inner(), "report")?;
+    let (outcome, reporters) = moderation_service
+        .resolve(&id, auth_user.id, body.into_inner())
+        .await?;
+    let report = &outcome.report;
+
+    if let (Some(ModerationAction::Hide | ModerationAction::Delete), Some(post_id)) =
+        (report.action, report.post_id)
+    {
+        invalidate_post_detail(redis_service.get_ref(), &post_id.to_hex()).await;
+    }
+
+    // End the offender's session so a suspension applies immediately
+    if outcome.suspended_until.is_some()
+        && let Err(e) =
+            invalidate_session(&report.offender_id.to_hex(), redis_service.get_ref()).await
+    {
+        log::warn!("Failed to end session for suspended user: {}", e);
+    }
+
+    notify_outcome(&notification_service, &outcome, &reporters).await;
+
+    Ok(ApiResponse::ok("Report resolved").data(outcome).into())
+}
+
+/// Strike count and current suspension for a user
+/// GET /moderation/users/{id}/strikes
+pub async fn get_user_strikes(
+    auth_user: AuthUser,
+    moderation_service: web::Data<ModerationService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_moderator()?;
+
+    let user_id = parse_id(path.into_inner(), "user")?;
+    let strikes = moderation_service.strikes_for(&user_id).await?;
+
+    Ok(ApiResponse::ok("Strikes retrieved successfully")
+        .data(strikes)
+        .into())
+}
+
+/// Hide a user's content from everyone but themselves. The user is not told.
+/// PUT /moderation/users/{id}/shadow-ban
+pub async fn shadow_ban_user(
+    auth_user: AuthUser,
+    moderation_service: web::Data<ModerationService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_moderator()?;
+
+    let user_id = parse_id(path.into_inner(), "user")?;
+    moderation_service.set_shadow_ban(&user_id, true).await?;
+
+    Ok(ApiResponse::ok("User shadow banned").into())
+}
+
+/// DELETE /moderation/users/{id}/shadow-ban
+pub async fn lift_shadow_ban(
+    auth_user: AuthUser,
+    moderation_service: web::Data<ModerationService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_moderator()?;
+
+    let user_id = parse_id(path.into_inner(), "user")?;
+    moderation_service.set_shadow_ban(&user_id, false).await?;
+
+    Ok(ApiResponse::ok("Shadow ban lifted").into())
+}
+
+/// Mark a post as sensitive so clients blur it; the author can't undo this
+/// PUT /moderation/posts/{id}/sensitive
+pub async fn mark_post_sensitive(
+    auth_user: AuthUser,
+    moderation_service: web::Data<ModerationService>,
+    redis_service: web::Data<RedisService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_moderator()?;
+
+    let post_id = parse_id(path.into_inner(), "post")?;
+    moderation_service
+        .set_post_sensitive(&post_id, true)
+        .await?;
+    invalidate_post_detail(redis_service.get_ref(), &post_id.to_hex()).await;
+
+    Ok(ApiResponse::ok("Post marked as sensitive").into())
+}
+
+/// DELETE /moderation/posts/{id}/sensitive
+pub async fn unmark_post_sensitive(
+    auth_user: AuthUser,
+    moderation_service: web::Data<ModerationService>,
+    redis_service: web::Data<RedisService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_moderator()?;
+
+    let post_id = parse_id(path.into_inner(), "post")?;
+    moderation_service
+        .set_post_sensitive(&post_id, false)
+        .await?;
+    invalidate_post_detail(redis_service.get_ref(), &post_id.to_hex()).await;
+
+    Ok(ApiResponse::ok("Sensitive mark removed").into())
+}
+
+/// Tell reporters the report was reviewed and the offender what happened.
+/// Notifications are best effort; the moderation action already stands.
+async fn notify_outcome(
+    notification_service: &NotificationService,
+    outcome: &ModerationOutcome,
+    reporters: &[ObjectId],
+) {
+    let report = &outcome.report;
+    let Some(action) = report.action else {
+        return;
+    };
+    let target = report.target.name();
+
+    let reporter_message = match action {
+        ModerationAction::Dismiss => format!(
+            "Thanks for your report. The {} doesn't break our rules.",
+            target
+        ),
+        _ => format!(
+            "Thanks for your report. A moderator has taken action on the {}.",
+            target
+        ),
+    };
+    for reporter_id in reporters {
+        if let Err(e) = notification_service
+            .notify(
+                *reporter_id,
+                None,
+                NotificationKind::Moderation,
+                reporter_message.clone(),
+                report.id,
+            )
+            .await
+        {
+            log::warn!("Failed to notify reporter of moderation outcome: {}", e);
+        }
+    }
+
+    if !action.is_strike() {
+        return;
+    }
+
+    let mut offender_message = match action {
+        ModerationAction::Warn => format!("You received a warning for your {}.", target),
+        ModerationAction::Suspend => format!("Your account was suspended over your {}.", target),
+        _ => format!(
+            "Your {} was {} by a moderator.",
+            target,
+            action.past_tense()
+        ),
+    };
+    if let Some(strikes) = outcome.strikes {
+        offender_message.push_str(&format!(" You now have {} strike(s).", strikes));
+    }
+    if let Some(until) = outcome.suspended_until {
+        offender_message.push_str(&format!(
+            " Your account is suspended until {}.",
+            until.to_rfc3339()
+        ));
+    }
+
+    if let Err(e) = notification_service
+        .notify(
+            report.offender_id,
+            None,
+            NotificationKind::Moderation,
+            offender_message,
+            report.id,
+        )
+        .await
+    {
+        log::warn!("Failed to notify offender of moderation outcome: {}", e);
+    }
+}
@@ -0,0 +1,33 @@
+use super::controller::{
+    create_report, get_report, get_user_strikes, lift_shadow_ban, list_reports,
+    mark_post_sensitive, shadow_ban_user, take_action, unmark_post_sensitive,
+};
+use crate::middleware::auth::verify_token;
+use crate::middleware::limits::RequestTimeout;
+use actix_web::web;
+use actix_web_httpauth::middleware::HttpAuthentication;
+
+pub fn moderation_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/reports")
+            .wrap(RequestTimeout::standard())
+            .wrap(HttpAuthentication::bearer(verify_token))
+            .route("", web::post().to(create_report)),
+    );
+    cfg.service(
+        web::scope("/moderation")
+            .wrap(RequestTimeout::standard())
+            .wrap(HttpAuthentication::bearer(verify_token))
+            .route("/reports", web::get().to(list_reports))
+            .route("/reports/{id}", web::get().to(get_report))
+            .route("/reports/{id}/actions", web::post().to(take_action))
+            .route("/users/{id}/strikes", web::get().to(get_user_strikes))
+            .route("/users/{id}/shadow-ban", web::put().to(shadow_ban_user))
+            .route("/users/{id}/shadow-ban", web::delete().to(lift_shadow_ban))
+            .route("/posts/{id}/sensitive", web::put().to(mark_post_sensitive))
+            .route(
+                "/posts/{id}/sensitive",
+                web::delete().to(unmark_post_sensitive),
+            ),
+    );
+}
@@ -0,0 +1,175 @@
+use crate::utils::datetime::{bson_datetime, option_bson_datetime};
+use crate::utils::validation::{not_blank, object_id};
+use chrono::{DateTime, Duration, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+/// Suspension applied by an explicit `suspend` action when none is given
+pub const DEFAULT_SUSPENSION_HOURS: i64 = 72;
+
+/// Automatic suspension earned on reaching `strikes`: a day at the third
+/// strike, a week at the fifth and a month for every strike from the seventh
+pub fn penalty_for(strikes: u32) -> Option<Duration> {
+    match strikes {
+        3 => Some(Duration::days(1)),
+        5 => Some(Duration::days(7)),
+        s if s >= 7 => Some(Duration::days(30)),
+        _ => None,
+    }
+}
+
+/// What a report points at
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportTarget {
+    Post,
+    Comment,
+    /// Chat messages aren't stored, so the report targets the sender and
+    /// keeps an excerpt of what was said
+    ChatMessage,
+}
+
+impl ReportTarget {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReportTarget::Post => "post",
+            ReportTarget::Comment => "comment",
+            ReportTarget::ChatMessage => "chat message",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportStatus {
+    Open,
+    Actioned,
+    Dismissed,
+}
+
+impl ReportStatus {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "open" => Some(ReportStatus::Open),
+            "actioned" => Some(ReportStatus::Actioned),
+            "dismissed" => Some(ReportStatus::Dismissed),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReportStatus::Open => "open",
+            ReportStatus::Actioned => "actioned",
+            ReportStatus::Dismissed => "dismissed",
+        }
+    }
+}
+
+/// Decision a moderator takes on a report
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ModerationAction {
+    /// Close the report without penalizing anyone
+    Dismiss,
+    Warn,
+    /// Remove the content from every read; it stays in the database
+    Hide,
+    Delete,
+    Suspend,
+    /// Hide all of the offender's content from everyone but themselves,
+    /// without telling them
+    ShadowBan,
+}
+
+impl ModerationAction {
+    /// Whether the offender receives a strike
+    pub fn is_strike(&self) -> bool {
+        !matches!(
+            self,
+            ModerationAction::Dismiss | ModerationAction::ShadowBan
+        )
+    }
+
+    pub fn past_tense(&self) -> &'static str {
+        match self {
+            ModerationAction::Dismiss => "dismissed",
+            ModerationAction::Warn => "warned",
+            ModerationAction::Hide => "hidden",
+            ModerationAction::Delete => "deleted",
+            ModerationAction::Suspend => "suspended",
+            ModerationAction::ShadowBan => "shadow banned",
+        }
+    }
+}
+
+/// A user's report of a post, comment or chat message
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Report {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub reporter_id: ObjectId,
+    pub target: ReportTarget,
+    pub target_id: ObjectId,
+    /// Author of the reported content
+    pub offender_id: ObjectId,
+    /// Post the content belongs to, for cache invalidation
+    pub post_id: Option<ObjectId>,
+    pub reason: String,
+    pub excerpt: Option<String>,
+    pub status: ReportStatus,
+    pub action: Option<ModerationAction>,
+    pub moderator_id: Option<ObjectId>,
+    pub moderator_note: Option<String>,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(default, with = "option_bson_datetime")]
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+/// Running strike count for a user
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UserStrikes {
+    #[serde(rename = "_id")]
+    pub user_id: ObjectId,
+    pub strikes: u32,
+    #[serde(default, with = "option_bson_datetime")]
+    pub last_strike_at: Option<DateTime<Utc>>,
+    #[serde(default, with = "option_bson_datetime")]
+    pub suspended_until: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct CreateReportRequest {
+    pub target: ReportTarget,
+    /// Post or comment id; the sender's user id for chat messages
+    #[validate(custom(function = "object_id"))]
+    pub target_id: String,
+    #[validate(
+        length(max = 500, message = "must be at most 500 characters"),
+        custom(function = "not_blank")
+    )]
+    pub reason: String,
+    /// Text of the reported chat message
+    #[validate(length(max = 2000, message = "must be at most 2000 characters"))]
+    pub excerpt: Option<String>,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct ModerationActionRequest {
+    pub action: ModerationAction,
+    #[validate(length(max = 500, message = "must be at most 500 characters"))]
+    pub note: Option<String>,
+    /// Length of a `suspend` action; defaults to `DEFAULT_SUSPENSION_HOURS`
+    #[validate(range(min = 1, max = 8760, message = "must be between 1 and 8760"))]
+    pub suspend_hours: Option<i64>,
+}
+
+/// Result of resolving a report, including any escalation that followed
+#[derive(Debug, Serialize)]
+pub struct ModerationOutcome {
+    pub report: Report,
+    pub strikes: Option<u32>,
+    pub suspended_until: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,497 @@
+use crate::comment::model::Comment;
+use crate::database::{MongoRepository, Repository};
+use crate::moderation::model::{
+    CreateReportRequest, DEFAULT_SUSPENSION_HOURS, ModerationAction, ModerationActionRequest,
+    ModerationOutcome, Report, ReportStatus, ReportTarget, UserStrikes, penalty_for,
+};
+use crate::post::post_model::Post;
+use crate::user::model::User;
+use crate::utils::datetime::bson_now;
+use crate::utils::error::{CustomError, ErrorCode};
+use crate::utils::sanitize::{Markup, sanitize};
+use chrono::{DateTime, Duration, Utc};
+use futures_util::TryStreamExt;
+use mongodb::bson::{self, doc, oid::ObjectId};
+use mongodb::options::{IndexOptions, ReturnDocument};
+use mongodb::{Client, Collection, IndexModel};
+use std::future::Future;
+
+/// The shadow ban lookups content handlers make, so they can be tested
+/// against a mock. `ModerationService` implements it by delegating to its
+/// inherent methods.
+pub trait ModerationServiceTrait: Send + Sync + 'static {
+    fn hidden_authors(
+        &self,
+        viewer: Option<&ObjectId>,
+    ) -> impl Future<Output = Result<Vec<ObjectId>, CustomError>> + Send;
+
+    fn is_shadow_banned(
+        &self,
+        user_id: &ObjectId,
+    ) -> impl Future<Output = Result<bool, CustomError>> + Send;
+}
+
+pub struct ModerationService {
+    reports: Collection<Report>,
+    strikes: Collection<UserStrikes>,
+    users: Collection<User>,
+    posts: MongoRepository<Post>,
+    comments: MongoRepository<Comment>,
+}
+
+impl ModerationService {
+    pub fn new(client: &Client) -> Self {
+        let db = client.database("rust_blogdb");
+        ModerationService {
+            reports: db.collection::<Report>("reports"),
+            strikes: db.collection::<UserStrikes>("moderation_strikes"),
+            users: db.collection::<User>("users"),
+            posts: MongoRepository::new(client, "posts"),
+            comments: MongoRepository::new(client, "comments"),
+        }
+    }
+
+    /// Index the moderator queue and the per-target lookups
+    #[tracing::instrument(skip_all)]
+    pub async fn ensure_indexes(&self) -> Result<(), CustomError> {
+        let indexes = vec![
+            IndexModel::builder()
+                .keys(doc! { "status": 1, "created_at": 1 })
+                .build(),
+            IndexModel::builder()
+                .keys(doc! { "target_id": 1, "status": 1 })
+                .build(),
+        ];
+
+        self.reports.create_indexes(indexes).await.map_err(|e| {
+            CustomError::InternalServerError(format!("Failed to create report indexes: {}", e))
+        })?;
+        self.users
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "shadow_banned": 1 })
+                    .options(
+                        IndexOptions::builder()
+                            .partial_filter_expression(doc! { "shadow_banned": true })
+                            .build(),
+                    )
+                    .build(),
+            )
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!(
+                    "Failed to create shadow ban index: {}",
+                    e
+                ))
+            })?;
+
+        Ok(())
+    }
+
+    /// File a report against a post, comment or chat message
+    #[tracing::instrument(skip_all)]
+    pub async fn create_report(
+        &self,
+        reporter_id: ObjectId,
+        request: CreateReportRequest,
+    ) -> Result<Report, CustomError> {
+        let target_id = ObjectId::parse_str(&request.target_id)
+            .map_err(|_| CustomError::BadRequestError("Invalid target ID".to_string()))?;
+
+        let (offender_id, post_id) = match request.target {
+            ReportTarget::Post => {
+                let post =
+                    self.posts.find_by_id(&target_id).await?.ok_or_else(|| {
+                        CustomError::coded(ErrorCode::PostNotFound, "Post not found")
+                    })?;
+                (post.author_id, Some(post.id))
+            }
+            ReportTarget::Comment => {
+                let comment = self.comments.find_by_id(&target_id).await?.ok_or_else(|| {
+                    CustomError::coded(ErrorCode::CommentNotFound, "Comment not found")
+                })?;
+                (comment.author_id, Some(comment.post_id))
+            }
+            ReportTarget::ChatMessage => {
+                if request.excerpt.is_none() {
+                    return Err(CustomError::BadRequestError(
+                        "excerpt is required when reporting a chat message".to_string(),
+                    ));
+                }
+                (target_id, None)
+            }
+        };
+
+        if offender_id == reporter_id {
+            return Err(CustomError::BadRequestError(
+                "You cannot report your own content".to_string(),
+            ));
+        }
+
+        let duplicate = self
+            .reports
+            .count_documents(doc! {
+                "reporter_id": reporter_id,
+                "target_id": target_id,
+                "status": ReportStatus::Open.name(),
+            })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+        if duplicate > 0 {
+            return Err(CustomError::ConflictError(
+                "You have already reported this".to_string(),
+            ));
+        }
+
+        let mut report = Report {
+            id: None,
+            reporter_id,
+            target: request.target,
+            target_id,
+            offender_id,
+            post_id,
+            reason: sanitize(&request.reason, Markup::None),
+            excerpt: request
+                .excerpt
+                .map(|excerpt| sanitize(&excerpt, Markup::None)),
+            status: ReportStatus::Open,
+            action: None,
+            moderator_id: None,
+            moderator_note: None,
+            created_at: Utc::now(),
+            resolved_at: None,
+        };
+
+        let result = self.reports.insert_one(&report).await.map_err(|e| {
+            CustomError::InternalServerError(format!("Failed to create report: {}", e))
+        })?;
+        report.id = result.inserted_id.as_object_id();
+
+        Ok(report)
+    }
+
+    /// Moderator queue, oldest first so nothing waits forever
+    #[tracing::instrument(skip_all)]
+    pub async fn list_reports(
+        &self,
+        status: ReportStatus,
+        limit: i64,
+    ) -> Result<Vec<Report>, CustomError> {
+        let cursor = self
+            .reports
+            .find(doc! { "status": status.name() })
+            .sort(doc! { "created_at": 1 })
+            .limit(limit)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        cursor
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn get_report(&self, id: &ObjectId) -> Result<Option<Report>, CustomError> {
+        self.reports
+            .find_one(doc! { "_id": id })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))
+    }
+
+    /// Resolve a report, closing every other open report on the same target.
+    ///
+    /// Any action but `dismiss` gives the offender a strike; reaching a strike
+    /// threshold suspends them automatically on top of an explicit suspension.
+    #[tracing::instrument(skip_all)]
+    pub async fn resolve(
+        &self,
+        report_id: &ObjectId,
+        moderator_id: ObjectId,
+        request: ModerationActionRequest,
+    ) -> Result<(ModerationOutcome, Vec<ObjectId>), CustomError> {
+        let report = self
+            .get_report(report_id)
+            .await?
+            .ok_or_else(|| CustomError::NotFoundError("Report not found".to_string()))?;
+        if report.status != ReportStatus::Open {
+            return Err(CustomError::ConflictError(
+                "Report has already been resolved".to_string(),
+            ));
+        }
+
+        self.apply_to_content(&report, request.action).await?;
+
+        // Everyone who reported the same content hears about the outcome
+        let reporters: Vec<ObjectId> = self
+            .reports
+            .distinct(
+                "reporter_id",
+                doc! { "target_id": report.target_id, "status": ReportStatus::Open.name() },
+            )
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .into_iter()
+            .filter_map(|id| id.as_object_id())
+            .collect();
+
+        let status = match request.action {
+            ModerationAction::Dismiss => ReportStatus::Dismissed,
+            _ => ReportStatus::Actioned,
+        };
+        let action = bson::to_bson(&request.action)
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+        self.reports
+            .update_many(
+                doc! { "target_id": report.target_id, "status": ReportStatus::Open.name() },
+                doc! {
+                    "$set": {
+                        "status": status.name(),
+                        "action": action,
+                        "moderator_id": moderator_id,
+                        "moderator_note": request.note.clone(),
+                        "resolved_at": bson_now(),
+                    }
+                },
+            )
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to resolve reports: {}", e))
+            })?;
+
+        let (strikes, suspended_until) = if request.action.is_strike() {
+            let strikes = self.add_strike(&report.offender_id).await?;
+
+            let explicit = (request.action == ModerationAction::Suspend).then(|| {
+                Duration::hours(request.suspend_hours.unwrap_or(DEFAULT_SUSPENSION_HOURS))
+            });
+            let suspension = explicit.into_iter().chain(penalty_for(strikes)).max();
+            let suspended_until = match suspension {
+                Some(length) => Some(self.suspend(&report.offender_id, length).await?),
+                None => None,
+            };
+            (Some(strikes), suspended_until)
+        } else {
+            (None, None)
+        };
+
+        let report = self
+            .get_report(report_id)
+            .await?
+            .ok_or_else(|| CustomError::NotFoundError("Report not found".to_string()))?;
+
+        Ok((
+            ModerationOutcome {
+                report,
+                strikes,
+                suspended_until,
+            },
+            reporters,
+        ))
+    }
+
+    /// Strike history for a user; users without strikes get an empty record
+    #[tracing::instrument(skip_all)]
+    pub async fn strikes_for(&self, user_id: &ObjectId) -> Result<UserStrikes, CustomError> {
+        let strikes = self
+            .strikes
+            .find_one(doc! { "_id": user_id })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        Ok(strikes.unwrap_or(UserStrikes {
+            user_id: *user_id,
+            strikes: 0,
+            last_strike_at: None,
+            suspended_until: None,
+        }))
+    }
+
+    /// Shadow-banned users whose content `viewer` must not see. Their own
+    /// content stays visible to them; anonymous readers see none of it.
+    #[tracing::instrument(skip_all)]
+    pub async fn hidden_authors(
+        &self,
+        viewer: Option<&ObjectId>,
+    ) -> Result<Vec<ObjectId>, CustomError> {
+        let mut filter = doc! { "shadow_banned": true };
+        if let Some(viewer) = viewer {
+            filter.insert("_id", doc! { "$ne": viewer });
+        }
+
+        Ok(self
+            .users
+            .distinct("_id", filter)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .into_iter()
+            .filter_map(|id| id.as_object_id())
+            .collect())
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn is_shadow_banned(&self, user_id: &ObjectId) -> Result<bool, CustomError> {
+        let count = self
+            .users
+            .count_documents(doc! { "_id": user_id, "shadow_banned": true })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        Ok(count > 0)
+    }
+
+    /// Apply or lift a shadow ban
+    #[tracing::instrument(skip_all)]
+    pub async fn set_shadow_ban(
+        &self,
+        user_id: &ObjectId,
+        banned: bool,
+    ) -> Result<(), CustomError> {
+        let result = self
+            .users
+            .update_one(
+                doc! { "_id": user_id },
+                doc! { "$set": { "shadow_banned": banned, "updated_at": bson_now() } },
+            )
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to update shadow ban: {}", e))
+            })?;
+        if result.matched_count == 0 {
+            return Err(CustomError::coded(
+                ErrorCode::UserNotFound,
+                "User not found",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Force a post to be shown as sensitive, or lift that so the author
+    /// decides again
+    #[tracing::instrument(skip_all)]
+    pub async fn set_post_sensitive(
+        &self,
+        post_id: &ObjectId,
+        locked: bool,
+    ) -> Result<(), CustomError> {
+        let found = self
+            .posts
+            .update(
+                post_id,
+                doc! {
+                    "is_sensitive": locked,
+                    "sensitive_locked": locked,
+                    "updated_at": bson_now(),
+                },
+            )
+            .await?;
+        if !found {
+            return Err(CustomError::coded(
+                ErrorCode::PostNotFound,
+                "Post not found",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Hide or delete the reported content, or shadow-ban its author
+    async fn apply_to_content(
+        &self,
+        report: &Report,
+        action: ModerationAction,
+    ) -> Result<(), CustomError> {
+        if action == ModerationAction::ShadowBan {
+            return self.set_shadow_ban(&report.offender_id, true).await;
+        }
+
+        let changes = match action {
+            ModerationAction::Hide => doc! { "hidden_at": bson_now() },
+            ModerationAction::Delete => doc! { "deleted_at": bson_now() },
+            _ => return Ok(()),
+        };
+
+        // Missing content was already removed, which is what the moderator wants
+        match report.target {
+            ReportTarget::Post => {
+                self.posts.update(&report.target_id, changes).await?;
+            }
+            ReportTarget::Comment => {
+                self.comments.update(&report.target_id, changes).await?;
+            }
+            ReportTarget::ChatMessage => {
+                return Err(CustomError::BadRequestError(
+                    "Chat messages can't be hidden or deleted; warn or suspend the sender"
+                        .to_string(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Add a strike and return the new total
+    async fn add_strike(&self, user_id: &ObjectId) -> Result<u32, CustomError> {
+        let strikes = self
+            .strikes
+            .find_one_and_update(
+                doc! { "_id": user_id },
+                doc! {
+                    "$inc": { "strikes": 1 },
+                    "$set": { "last_strike_at": bson_now() },
+                },
+            )
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .await
+            .map_err(|e| CustomError::InternalServerError(format!("Failed to add strike: {}", e)))?
+            .ok_or_else(|| CustomError::InternalServerError("Failed to add strike".to_string()))?;
+
+        Ok(strikes.strikes)
+    }
+
+    /// Suspend the user for `length` unless an existing suspension runs longer
+    async fn suspend(
+        &self,
+        user_id: &ObjectId,
+        length: Duration,
+    ) -> Result<DateTime<Utc>, CustomError> {
+        let until = bson::DateTime::from_chrono(Utc::now() + length);
+
+        self.users
+            .update_one(
+                doc! { "_id": user_id },
+                doc! { "$max": { "suspended_until": until } },
+            )
+            .await
+            .map_err(|e| CustomError::InternalServerError(format!("Failed to suspend: {}", e)))?;
+
+        let strikes = self
+            .strikes
+            .find_one_and_update(
+                doc! { "_id": user_id },
+                doc! { "$max": { "suspended_until": until } },
+            )
+            .return_document(ReturnDocument::After)
+            .await
+            .map_err(|e| CustomError::InternalServerError(format!("Failed to suspend: {}", e)))?;
+
+        Ok(strikes
+            .and_then(|s| s.suspended_until)
+            .unwrap_or_else(|| until.to_chrono()))
+    }
+}
+
+impl ModerationServiceTrait for ModerationService {
+    async fn hidden_authors(
+        &self,
+        viewer: Option<&ObjectId>,
+    ) -> Result<Vec<ObjectId>, CustomError> {
+        Self::hidden_authors(self, viewer).await
+    }
+
+    async fn is_shadow_banned(&self, user_id: &ObjectId) -> Result<bool, CustomError> {
+        Self::is_shadow_banned(self, user_id).await
+    }
+}
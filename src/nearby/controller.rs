@@ -0,0 +1,85 @@
+use crate::feature_flag::service::FeatureFlagService;
+use crate::middleware::auth::AuthUser;
+use crate::nearby::model::{
+    DEFAULT_NEARBY_LIMIT, DEFAULT_NEARBY_RADIUS_KM, MAX_NEARBY_RADIUS_KM, NEARBY_FEATURE_FLAG,
+    NearbyQuery, UpdateNearbyLocationRequest,
+};
+use crate::nearby::service::NearbyService;
+use crate::utils::error::CustomError;
+use crate::utils::i18n::Locale;
+use crate::utils::response::ApiResponse;
+use crate::utils::validation::ValidatedJson;
+use actix_web::{HttpResponse, web};
+
+/// Nearby discovery is dark-launched; users outside the flag's cohort see
+/// the routes as missing. Opting out always works.
+async fn require_nearby_flag(
+    feature_flags: &FeatureFlagService,
+    auth_user: &AuthUser,
+) -> Result<(), CustomError> {
+    if feature_flags
+        .is_enabled(NEARBY_FEATURE_FLAG, &auth_user.id)
+        .await
+    {
+        Ok(())
+    } else {
+        Err(CustomError::NotFoundError(
+            "Nearby discovery is not available".to_string(),
+        ))
+    }
+}
+
+/// Opt into nearby discovery, or refresh the caller's approximate location
+/// PUT /users/me/location
+pub async fn update_nearby_location(
+    locale: Locale,
+    auth_user: AuthUser,
+    nearby_service: web::Data<NearbyService>,
+    feature_flags: web::Data<FeatureFlagService>,
+    body: ValidatedJson<UpdateNearbyLocationRequest>,
+) -> Result<HttpResponse, CustomError> {
+    require_nearby_flag(&feature_flags, &auth_user).await?;
+    let location = nearby_service
+        .update(&auth_user.id, body.lat, body.lng)
+        .await?;
+
+    Ok(ApiResponse::ok(locale.t("nearby-location-updated"))
+        .data(location)
+        .into())
+}
+
+/// Opt out of nearby discovery
+/// DELETE /users/me/location
+pub async fn clear_nearby_location(
+    locale: Locale,
+    auth_user: AuthUser,
+    nearby_service: web::Data<NearbyService>,
+) -> Result<HttpResponse, CustomError> {
+    nearby_service.clear(&auth_user.id).await?;
+
+    Ok(ApiResponse::ok(locale.t("nearby-location-cleared")).into())
+}
+
+/// Opted-in users around the caller
+/// GET /users/nearby?radius_km=5&limit=20
+pub async fn get_nearby_users(
+    locale: Locale,
+    auth_user: AuthUser,
+    nearby_service: web::Data<NearbyService>,
+    feature_flags: web::Data<FeatureFlagService>,
+    query: web::Query<NearbyQuery>,
+) -> Result<HttpResponse, CustomError> {
+    require_nearby_flag(&feature_flags, &auth_user).await?;
+    let radius_km = query
+        .radius_km
+        .unwrap_or(DEFAULT_NEARBY_RADIUS_KM)
+        .clamp(1, MAX_NEARBY_RADIUS_KM);
+    let limit = query.limit.unwrap_or(DEFAULT_NEARBY_LIMIT).clamp(1, 50);
+    let users = nearby_service
+        .nearby(&auth_user.id, radius_km, limit)
+        .await?;
+
+    Ok(ApiResponse::ok(locale.t("nearby-users-fetched"))
+        .list(users)
+        .into())
+}
@@ -0,0 +1,72 @@
+use crate::post::post_model::AuthorSummary;
+use crate::utils::datetime::bson_datetime;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+/// Geohash length locations are snapped to; a 6-character cell is roughly
+/// 1.2 × 0.6 km, so nobody can be placed more precisely than that
+pub const NEARBY_GEOHASH_PRECISION: usize = 6;
+/// Locations not refreshed within this long drop out of discovery
+pub const NEARBY_LOCATION_TTL_HOURS: u64 = 24;
+/// Search radius when the client doesn't ask for one
+pub const DEFAULT_NEARBY_RADIUS_KM: u32 = 5;
+pub const MAX_NEARBY_RADIUS_KM: u32 = 50;
+/// Users returned when the client doesn't ask for a number
+pub const DEFAULT_NEARBY_LIMIT: usize = 20;
+/// Feature flag nearby discovery is rolled out behind
+pub const NEARBY_FEATURE_FLAG: &str = "nearby-discovery";
+
+/// GeoJSON point, as a 2dsphere index expects it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GeoPoint {
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// Longitude, then latitude
+    pub coordinates: [f64; 2],
+}
+
+impl GeoPoint {
+    pub fn new(lat: f64, lng: f64) -> Self {
+        GeoPoint {
+            kind: "Point".to_string(),
+            coordinates: [lng, lat],
+        }
+    }
+}
+
+/// Approximate location of a user who opted into nearby discovery. Only the
+/// centre of the user's geohash cell is stored, never the reported position.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NearbyLocation {
+    #[serde(rename = "_id")]
+    pub user_id: ObjectId,
+    pub geohash: String,
+    pub location: GeoPoint,
+    #[serde(with = "bson_datetime")]
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Request body for `PUT /users/me/location`
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateNearbyLocationRequest {
+    #[validate(range(min = -90.0, max = 90.0))]
+    pub lat: f64,
+    #[validate(range(min = -180.0, max = 180.0))]
+    pub lng: f64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NearbyQuery {
+    pub radius_km: Option<u32>,
+    pub limit: Option<usize>,
+}
+
+/// Someone close by
+#[derive(Debug, Serialize)]
+pub struct NearbyUser {
+    pub user: AuthorSummary,
+    /// Whole kilometres between the two cells, at least 1
+    pub distance_km: u32,
+}
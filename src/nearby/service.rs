@@ -0,0 +1,192 @@
+use crate::friend::model::Block;
+use crate::nearby::model::{
+    GeoPoint, NEARBY_GEOHASH_PRECISION, NEARBY_LOCATION_TTL_HOURS, NearbyLocation, NearbyUser,
+};
+use crate::post::post_model::AuthorSummary;
+use crate::utils::error::CustomError;
+use chrono::Utc;
+use futures_util::TryStreamExt;
+use geohash::Coord;
+use mongodb::bson::{self, Bson, doc, oid::ObjectId};
+use mongodb::options::IndexOptions;
+use mongodb::{Client, Collection, IndexModel};
+use serde::Deserialize;
+use std::time::Duration as StdDuration;
+
+/// A `$geoNear` result joined with its user
+#[derive(Deserialize)]
+struct NearbyRow {
+    distance: f64,
+    user: AuthorSummary,
+}
+
+/// Opt-in discovery of users close to each other
+pub struct NearbyService {
+    locations: Collection<NearbyLocation>,
+    blocks: Collection<Block>,
+}
+
+impl NearbyService {
+    pub fn new(client: &Client) -> Self {
+        let db = client.database("rust_blogdb");
+        NearbyService {
+            locations: db.collection::<NearbyLocation>("nearby_locations"),
+            blocks: db.collection::<Block>("user_blocks"),
+        }
+    }
+
+    /// Radius searches need a 2dsphere index; stale locations expire
+    #[tracing::instrument(skip_all)]
+    pub async fn ensure_indexes(&self) -> Result<(), CustomError> {
+        let indexes = vec![
+            IndexModel::builder()
+                .keys(doc! { "location": "2dsphere" })
+                .build(),
+            IndexModel::builder()
+                .keys(doc! { "updated_at": 1 })
+                .options(
+                    IndexOptions::builder()
+                        .expire_after(StdDuration::from_secs(NEARBY_LOCATION_TTL_HOURS * 3600))
+                        .build(),
+                )
+                .build(),
+        ];
+        self.locations.create_indexes(indexes).await.map_err(|e| {
+            CustomError::InternalServerError(format!("Failed to create nearby indexes: {}", e))
+        })?;
+
+        Ok(())
+    }
+
+    /// Opt in, or refresh the user's location. The position is snapped to
+    /// the centre of its geohash cell rather than jittered, since random
+    /// offsets would average out over repeated updates.
+    #[tracing::instrument(skip_all)]
+    pub async fn update(
+        &self,
+        user_id: &ObjectId,
+        lat: f64,
+        lng: f64,
+    ) -> Result<NearbyLocation, CustomError> {
+        let geohash = geohash::encode(Coord { x: lng, y: lat }, NEARBY_GEOHASH_PRECISION)
+            .map_err(|e| CustomError::BadRequestError(format!("Invalid location: {}", e)))?;
+        let (centre, ..) = geohash::decode(&geohash)
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        let location = NearbyLocation {
+            user_id: *user_id,
+            geohash,
+            location: GeoPoint::new(centre.y, centre.x),
+            updated_at: Utc::now(),
+        };
+        self.locations
+            .replace_one(doc! { "_id": user_id }, &location)
+            .upsert(true)
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to save location: {}", e))
+            })?;
+
+        Ok(location)
+    }
+
+    /// Opt out; returns whether the user had shared a location
+    #[tracing::instrument(skip_all)]
+    pub async fn clear(&self, user_id: &ObjectId) -> Result<bool, CustomError> {
+        let result = self
+            .locations
+            .delete_one(doc! { "_id": user_id })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        Ok(result.deleted_count > 0)
+    }
+
+    /// Other opted-in users within `radius_km`, closest first. Only users
+    /// sharing their own location may look, and blocks in either direction
+    /// hide both users from each other.
+    #[tracing::instrument(skip_all)]
+    pub async fn nearby(
+        &self,
+        user_id: &ObjectId,
+        radius_km: u32,
+        limit: usize,
+    ) -> Result<Vec<NearbyUser>, CustomError> {
+        let own = self
+            .locations
+            .find_one(doc! { "_id": user_id })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .ok_or_else(|| {
+                CustomError::BadRequestError("Share your location to see who is nearby".to_string())
+            })?;
+
+        let mut excluded: Vec<ObjectId> = self
+            .blocks
+            .find(doc! { "$or": [{ "blocker_id": user_id }, { "blocked_id": user_id }] })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect::<Vec<Block>>()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .into_iter()
+            .map(|block| {
+                if block.blocker_id == *user_id {
+                    block.blocked_id
+                } else {
+                    block.blocker_id
+                }
+            })
+            .collect();
+        excluded.push(*user_id);
+
+        let near = bson::to_bson(&own.location)
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+        let pipeline = vec![
+            doc! { "$geoNear": {
+                "near": near,
+                "distanceField": "distance",
+                "maxDistance": f64::from(radius_km) * 1000.0,
+                "spherical": true,
+                "query": { "_id": { "$nin": excluded } },
+            } },
+            doc! { "$lookup": {
+                "from": "users",
+                "localField": "_id",
+                "foreignField": "_id",
+                "as": "user",
+            } },
+            doc! { "$unwind": "$user" },
+            doc! { "$match": {
+                "user.is_email_verified": true,
+                "user.shadow_banned": { "$ne": true },
+                "user.deleted_at": Bson::Null,
+            } },
+            doc! { "$limit": limit as i64 },
+            doc! { "$project": {
+                "distance": 1,
+                "user._id": 1,
+                "user.username": 1,
+                "user.profile_picture": 1,
+                "user.is_verified": 1,
+            } },
+        ];
+        let rows: Vec<NearbyRow> = self
+            .locations
+            .aggregate(pipeline)
+            .with_type::<NearbyRow>()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| NearbyUser {
+                user: row.user,
+                distance_km: (row.distance / 1000.0).ceil().max(1.0) as u32,
+            })
+            .collect())
+    }
+}
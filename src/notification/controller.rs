@@ -0,0 +1,84 @@
+use crate::middleware::auth::AuthUser;
+use crate::notification::model::UpdateNotificationSettingsRequest;
+use crate::notification::service::NotificationService;
+use crate::utils::error::CustomError;
+use crate::utils::i18n::Locale;
+use crate::utils::response::ApiResponse;
+use crate::utils::validation::ValidatedJson;
+use actix_web::{HttpResponse, web};
+use serde_json::json;
+
+/// Get the current user's notifications
+/// GET /notifications
+pub async fn get_notifications(
+    locale: Locale,
+    auth_user: AuthUser,
+    notification_service: web::Data<NotificationService>,
+) -> Result<HttpResponse, CustomError> {
+    let notifications = notification_service
+        .get_notifications(&auth_user.id)
+        .await?;
+
+    Ok(ApiResponse::ok(locale.t("notifications-fetched"))
+        .list(notifications)
+        .into())
+}
+
+/// Get the number of unread notifications for the bell badge
+/// GET /notifications/unread-count
+pub async fn get_unread_count(
+    locale: Locale,
+    auth_user: AuthUser,
+    notification_service: web::Data<NotificationService>,
+) -> Result<HttpResponse, CustomError> {
+    let count = notification_service.unread_count(&auth_user.id).await?;
+
+    Ok(ApiResponse::ok(locale.t("unread-count-fetched"))
+        .data(json!({ "count": count }))
+        .into())
+}
+
+/// Mark all notifications as read
+/// POST /notifications/read-all
+pub async fn mark_all_read(
+    locale: Locale,
+    auth_user: AuthUser,
+    notification_service: web::Data<NotificationService>,
+) -> Result<HttpResponse, CustomError> {
+    let updated = notification_service.mark_all_read(&auth_user.id).await?;
+
+    Ok(ApiResponse::ok(locale.t("notifications-marked-read"))
+        .data(json!({ "updated": updated }))
+        .into())
+}
+
+/// Get the current user's notification settings
+/// GET /notifications/settings
+pub async fn get_settings(
+    locale: Locale,
+    auth_user: AuthUser,
+    notification_service: web::Data<NotificationService>,
+) -> Result<HttpResponse, CustomError> {
+    let settings = notification_service.get_settings(&auth_user.id).await?;
+
+    Ok(ApiResponse::ok(locale.t("notification-settings-fetched"))
+        .data(settings)
+        .into())
+}
+
+/// Update the current user's notification settings
+/// PATCH /notifications/settings
+pub async fn update_settings(
+    locale: Locale,
+    auth_user: AuthUser,
+    notification_service: web::Data<NotificationService>,
+    body: ValidatedJson<UpdateNotificationSettingsRequest>,
+) -> Result<HttpResponse, CustomError> {
+    let settings = notification_service
+        .update_settings(&auth_user.id, body.into_inner())
+        .await?;
+
+    Ok(ApiResponse::ok(locale.t("notification-settings-updated"))
+        .data(settings)
+        .into())
+}
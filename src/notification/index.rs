@@ -0,0 +1,20 @@
+use super::controller::{
+    get_notifications, get_settings, get_unread_count, mark_all_read, update_settings,
+};
+use crate::middleware::auth::verify_token;
+use crate::middleware::limits::RequestTimeout;
+use actix_web::web;
+use actix_web_httpauth::middleware::HttpAuthentication;
+
+pub fn notification_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/notifications")
+            .wrap(RequestTimeout::standard())
+            .wrap(HttpAuthentication::bearer(verify_token))
+            .route("", web::get().to(get_notifications))
+            .route("/unread-count", web::get().to(get_unread_count))
+            .route("/read-all", web::post().to(mark_all_read))
+            .route("/settings", web::get().to(get_settings))
+            .route("/settings", web::patch().to(update_settings)),
+    );
+}
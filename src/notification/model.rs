@@ -0,0 +1,141 @@
+use crate::utils::datetime::{bson_datetime, option_bson_datetime};
+use crate::utils::validation::time_zone;
+use chrono::{DateTime, Days, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+/// Notification stored in database
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Notification {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: ObjectId,
+    pub actor_id: Option<ObjectId>,
+    pub kind: NotificationKind,
+    pub message: String,
+    pub reference_id: Option<ObjectId>,
+    pub is_read: bool,
+    /// Held back by the recipient's do-not-disturb hours until this time
+    #[serde(
+        default,
+        with = "option_bson_datetime",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub queued_until: Option<DateTime<Utc>>,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// Type of notification
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    Comment,
+    System,
+    /// Outcome of a report, for the reporter or the offender
+    Moderation,
+    /// Direct message received while away from the room
+    ChatMessage,
+}
+
+impl NotificationKind {
+    /// Urgent notifications are delivered even during do-not-disturb hours
+    pub fn is_urgent(&self) -> bool {
+        match self {
+            NotificationKind::Comment | NotificationKind::ChatMessage => false,
+            NotificationKind::System | NotificationKind::Moderation => true,
+        }
+    }
+}
+
+fn enabled() -> bool {
+    true
+}
+
+/// Per-user notification preferences; users without a document get the defaults
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NotificationSettings {
+    #[serde(rename = "_id")]
+    pub user_id: ObjectId,
+    /// Welcome and onboarding tips emails
+    #[serde(default = "enabled")]
+    pub onboarding_emails: bool,
+    /// Quiet hours during which non-urgent notifications are held back
+    #[serde(default)]
+    pub dnd: Option<DndWindow>,
+    /// Whether chat notifications show the message, or just "New message"
+    #[serde(default = "enabled")]
+    pub message_previews: bool,
+}
+
+impl NotificationSettings {
+    pub fn defaults(user_id: ObjectId) -> Self {
+        NotificationSettings {
+            user_id,
+            onboarding_emails: true,
+            dnd: None,
+            message_previews: true,
+        }
+    }
+
+    /// When held back notifications may go out, if `now` falls in the
+    /// user's do-not-disturb hours
+    pub fn dnd_until(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.dnd
+            .as_ref()
+            .filter(|dnd| dnd.enabled)
+            .and_then(|dnd| dnd.ends_after(now))
+    }
+}
+
+/// Daily do-not-disturb window in the user's local time. A window whose end
+/// is before its start runs past midnight, e.g. 22:00 to 07:00.
+#[derive(Debug, Serialize, Deserialize, Clone, Validate)]
+pub struct DndWindow {
+    pub enabled: bool,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    /// IANA time zone name, e.g. `Europe/Paris`
+    #[validate(custom(function = "time_zone"))]
+    pub time_zone: String,
+}
+
+impl DndWindow {
+    /// End of the window `now` falls in, or `None` outside the window
+    pub fn ends_after(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let tz: Tz = self.time_zone.parse().ok()?;
+        let local = now.with_timezone(&tz);
+        let time = local.time();
+        let inside = if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        };
+        if !inside {
+            return None;
+        }
+
+        let mut date = local.date_naive();
+        if time >= self.end {
+            date = date.checked_add_days(Days::new(1))?;
+        }
+        let end = date.and_time(self.end);
+        // An end time skipped by a DST change is read as UTC rather than lost
+        let end = tz
+            .from_local_datetime(&end)
+            .earliest()
+            .unwrap_or_else(|| tz.from_utc_datetime(&end));
+        Some(end.with_timezone(&Utc))
+    }
+}
+
+/// Partial update of notification settings; omitted fields are unchanged
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateNotificationSettingsRequest {
+    pub onboarding_emails: Option<bool>,
+    #[validate(nested)]
+    pub dnd: Option<DndWindow>,
+    pub message_previews: Option<bool>,
+}
@@ -0,0 +1,339 @@
+use crate::counter::model::Counter;
+use crate::counter::service::CounterService;
+use crate::database::RedisService;
+use crate::notification::model::{
+    Notification, NotificationKind, NotificationSettings, UpdateNotificationSettingsRequest,
+};
+use crate::utils::datetime::bson_now;
+use crate::utils::error::CustomError;
+use chrono::Utc;
+use futures_util::TryStreamExt;
+use mongodb::bson::{self, Document, doc, oid::ObjectId};
+use mongodb::options::{IndexOptions, ReturnDocument};
+use mongodb::{Client, Collection, IndexModel};
+use std::future::Future;
+
+/// Characters of a chat message shown in its notification
+const MESSAGE_PREVIEW_CHARS: usize = 100;
+
+/// How handlers raise notifications, so handler tests can record them
+/// instead. `NotificationService` implements it by delegating to its inherent
+/// methods.
+pub trait NotificationServiceTrait: Send + Sync + 'static {
+    fn notify(
+        &self,
+        user_id: ObjectId,
+        actor_id: Option<ObjectId>,
+        kind: NotificationKind,
+        message: String,
+        reference_id: Option<ObjectId>,
+    ) -> impl Future<Output = Result<ObjectId, CustomError>> + Send;
+}
+
+pub struct NotificationService {
+    collection: Collection<Notification>,
+    settings: Collection<NotificationSettings>,
+    counters: CounterService,
+}
+
+impl NotificationService {
+    pub fn new(client: &Client, redis_service: RedisService) -> Self {
+        let db = client.database("rust_blogdb");
+        let collection = db.collection::<Notification>("notifications");
+        let settings = db.collection::<NotificationSettings>("notification_settings");
+        NotificationService {
+            collection,
+            settings,
+            counters: CounterService::new(client, redis_service),
+        }
+    }
+
+    /// Index the do-not-disturb release sweep
+    #[tracing::instrument(skip_all)]
+    pub async fn ensure_indexes(&self) -> Result<(), CustomError> {
+        self.collection
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "queued_until": 1 })
+                    .options(IndexOptions::builder().sparse(true).build())
+                    .build(),
+            )
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!(
+                    "Failed to create notification indexes: {}",
+                    e
+                ))
+            })?;
+
+        Ok(())
+    }
+
+    /// Notifications the user can already see, leaving out those held back
+    /// by do-not-disturb hours
+    fn delivered(user_id: &ObjectId) -> Document {
+        // Also matches notifications without the field
+        doc! { "user_id": user_id, "queued_until": null }
+    }
+
+    /// Create a notification and bump the recipient's unread counter.
+    /// Non-urgent notifications created during the recipient's
+    /// do-not-disturb hours are queued until the window ends.
+    #[tracing::instrument(skip_all)]
+    pub async fn notify(
+        &self,
+        user_id: ObjectId,
+        actor_id: Option<ObjectId>,
+        kind: NotificationKind,
+        message: String,
+        reference_id: Option<ObjectId>,
+    ) -> Result<ObjectId, CustomError> {
+        let queued_until = if kind.is_urgent() {
+            None
+        } else {
+            self.get_settings(&user_id).await?.dnd_until(Utc::now())
+        };
+        self.insert(user_id, actor_id, kind, message, reference_id, queued_until)
+            .await
+    }
+
+    /// Notify a user of a chat message. The text shows the sender and the
+    /// start of the message, or only "New message" if the recipient turned
+    /// previews off.
+    #[tracing::instrument(skip_all)]
+    pub async fn notify_chat_message(
+        &self,
+        user_id: ObjectId,
+        sender_id: Option<ObjectId>,
+        sender_username: Option<&str>,
+        content: &str,
+        message_id: Option<ObjectId>,
+    ) -> Result<ObjectId, CustomError> {
+        let settings = self.get_settings(&user_id).await?;
+        let message = if settings.message_previews {
+            let mut preview: String = content.chars().take(MESSAGE_PREVIEW_CHARS).collect();
+            if preview.len() < content.len() {
+                preview.push('…');
+            }
+            format!("{}: {}", sender_username.unwrap_or("Someone"), preview)
+        } else {
+            "New message".to_string()
+        };
+        let queued_until = settings.dnd_until(Utc::now());
+
+        self.insert(
+            user_id,
+            sender_id,
+            NotificationKind::ChatMessage,
+            message,
+            message_id,
+            queued_until,
+        )
+        .await
+    }
+
+    async fn insert(
+        &self,
+        user_id: ObjectId,
+        actor_id: Option<ObjectId>,
+        kind: NotificationKind,
+        message: String,
+        reference_id: Option<ObjectId>,
+        queued_until: Option<chrono::DateTime<Utc>>,
+    ) -> Result<ObjectId, CustomError> {
+        let notification = Notification {
+            id: None,
+            user_id,
+            actor_id,
+            kind,
+            message,
+            reference_id,
+            is_read: false,
+            queued_until,
+            created_at: Utc::now(),
+        };
+
+        let result = self
+            .collection
+            .insert_one(notification)
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to create notification: {}", e))
+            })?;
+
+        // The counter is derived from the notifications, so a failure is not fatal
+        if queued_until.is_none()
+            && let Err(e) = self
+                .counters
+                .incr(Counter::UnreadNotifications, &user_id.to_hex())
+                .await
+        {
+            log::warn!("Failed to increment unread counter: {}", e);
+        }
+
+        result.inserted_id.as_object_id().ok_or_else(|| {
+            CustomError::InternalServerError("Failed to get inserted notification ID".to_string())
+        })
+    }
+
+    /// Get the most recent notifications for a user
+    #[tracing::instrument(skip_all)]
+    pub async fn get_notifications(
+        &self,
+        user_id: &ObjectId,
+    ) -> Result<Vec<Notification>, CustomError> {
+        let cursor = self
+            .collection
+            .find(Self::delivered(user_id))
+            .sort(doc! { "created_at": -1 })
+            .limit(50)
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to fetch notifications: {}", e))
+            })?;
+
+        let notifications: Vec<Notification> = cursor.try_collect().await.map_err(|e| {
+            CustomError::InternalServerError(format!("Failed to collect notifications: {}", e))
+        })?;
+
+        Ok(notifications)
+    }
+
+    /// Mark all notifications of a user as read and reset the unread counter
+    #[tracing::instrument(skip_all)]
+    pub async fn mark_all_read(&self, user_id: &ObjectId) -> Result<u64, CustomError> {
+        let mut filter = Self::delivered(user_id);
+        filter.insert("is_read", false);
+        let result = self
+            .collection
+            .update_many(filter, doc! { "$set": { "is_read": true } })
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to update notifications: {}", e))
+            })?;
+
+        self.counters
+            .set(Counter::UnreadNotifications, &user_id.to_hex(), 0)
+            .await?;
+
+        Ok(result.modified_count)
+    }
+
+    /// Get the unread count, served from the unread counter
+    #[tracing::instrument(skip_all)]
+    pub async fn unread_count(&self, user_id: &ObjectId) -> Result<u64, CustomError> {
+        let count = self
+            .counters
+            .get(Counter::UnreadNotifications, &user_id.to_hex())
+            .await?;
+
+        Ok(count.max(0) as u64)
+    }
+
+    /// Get a user's notification settings, falling back to the defaults
+    #[tracing::instrument(skip_all)]
+    pub async fn get_settings(
+        &self,
+        user_id: &ObjectId,
+    ) -> Result<NotificationSettings, CustomError> {
+        let settings = self
+            .settings
+            .find_one(doc! { "_id": user_id })
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to fetch settings: {}", e))
+            })?;
+
+        Ok(settings.unwrap_or_else(|| NotificationSettings::defaults(*user_id)))
+    }
+
+    /// Apply a partial settings update, creating the document on first change
+    #[tracing::instrument(skip_all)]
+    pub async fn update_settings(
+        &self,
+        user_id: &ObjectId,
+        update: UpdateNotificationSettingsRequest,
+    ) -> Result<NotificationSettings, CustomError> {
+        let mut changes = doc! {};
+        if let Some(onboarding_emails) = update.onboarding_emails {
+            changes.insert("onboarding_emails", onboarding_emails);
+        }
+        if let Some(dnd) = update.dnd {
+            let dnd =
+                bson::to_bson(&dnd).map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+            changes.insert("dnd", dnd);
+        }
+        if let Some(message_previews) = update.message_previews {
+            changes.insert("message_previews", message_previews);
+        }
+        if changes.is_empty() {
+            return self.get_settings(user_id).await;
+        }
+
+        let settings = self
+            .settings
+            .find_one_and_update(doc! { "_id": user_id }, doc! { "$set": changes })
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to update settings: {}", e))
+            })?;
+
+        Ok(settings.unwrap_or_else(|| NotificationSettings::defaults(*user_id)))
+    }
+
+    /// Deliver notifications whose do-not-disturb window has ended,
+    /// returning how many were released
+    #[tracing::instrument(skip_all)]
+    pub async fn release_queued(&self) -> Result<usize, CustomError> {
+        let due: Vec<Notification> = self
+            .collection
+            .find(doc! { "queued_until": { "$lte": bson_now() } })
+            .limit(500)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        let mut released = 0;
+        for notification in due {
+            let result = self
+                .collection
+                .update_one(
+                    doc! { "_id": notification.id, "queued_until": { "$ne": null } },
+                    doc! { "$set": { "queued_until": null } },
+                )
+                .await
+                .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+            if result.modified_count == 0 {
+                continue;
+            }
+            released += 1;
+
+            if let Err(e) = self
+                .counters
+                .incr(Counter::UnreadNotifications, &notification.user_id.to_hex())
+                .await
+            {
+                log::warn!("Failed to increment unread counter: {}", e);
+            }
+        }
+
+        Ok(released)
+    }
+}
+
+impl NotificationServiceTrait for NotificationService {
+    async fn notify(
+        &self,
+        user_id: ObjectId,
+        actor_id: Option<ObjectId>,
+        kind: NotificationKind,
+        message: String,
+        reference_id: Option<ObjectId>,
+    ) -> Result<ObjectId, CustomError> {
+        Self::notify(self, user_id, actor_id, kind, message, reference_id).await
+    }
+}
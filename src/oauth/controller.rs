@@ -0,0 +1,70 @@
+use crate::database::RedisService;
+use crate::fingerprint::controller::client_fingerprint;
+use crate::fingerprint::model::FingerprintEvent;
+use crate::fingerprint::service::FingerprintService;
+use crate::middleware::rate_limit::{
+    AUTH_RATE_LIMIT, AUTH_RATE_WINDOW_SECONDS, check_rate_limit, client_ip,
+};
+use crate::oauth::model::OAuthCallbackQuery;
+use crate::oauth::service::GithubOAuth;
+use crate::user::service::UserService;
+use crate::utils::error::CustomError;
+use crate::utils::i18n::Locale;
+use crate::utils::response::ApiResponse;
+use actix_web::http::header;
+use actix_web::{HttpRequest, HttpResponse, web};
+
+/// Send the browser to GitHub to approve the app
+/// GET /auth/oauth/github
+pub async fn github_login(github: web::Data<GithubOAuth>) -> Result<HttpResponse, CustomError> {
+    let url = github.authorize_url().await?;
+
+    Ok(HttpResponse::Found()
+        .insert_header((header::LOCATION, url))
+        .finish())
+}
+
+/// Where GitHub returns the user: log them in, linking or creating an
+/// account on first use
+/// GET /auth/oauth/github/callback
+pub async fn github_callback(
+    locale: Locale,
+    req: HttpRequest,
+    query: web::Query<OAuthCallbackQuery>,
+    github: web::Data<GithubOAuth>,
+    user_service: web::Data<UserService>,
+    redis_service: web::Data<RedisService>,
+    fingerprint_service: web::Data<FingerprintService>,
+) -> Result<HttpResponse, CustomError> {
+    check_rate_limit(
+        redis_service.get_ref(),
+        &format!("auth:oauth:{}", client_ip(&req)),
+        AUTH_RATE_LIMIT,
+        AUTH_RATE_WINDOW_SECONDS,
+    )
+    .await?;
+
+    if let Some(error) = &query.error {
+        return Err(CustomError::UnauthorizedError(format!(
+            "GitHub login was not approved: {}",
+            error
+        )));
+    }
+    let (Some(code), Some(state)) = (&query.code, &query.state) else {
+        return Err(CustomError::BadRequestError(
+            "code and state are required".to_string(),
+        ));
+    };
+
+    let profile = github.complete(code, state).await?;
+    let (user_id, tokens) = user_service
+        .login_with_provider(profile, redis_service.get_ref())
+        .await?;
+    fingerprint_service
+        .record(&user_id, FingerprintEvent::Login, &client_fingerprint(&req))
+        .await;
+
+    Ok(ApiResponse::ok(locale.t("login-successful"))
+        .data(tokens)
+        .into())
+}
@@ -0,0 +1,14 @@
+use super::controller::{github_callback, github_login};
+use crate::middleware::limits::RequestTimeout;
+use crate::middleware::rate_limit::IpRateLimit;
+use actix_web::web;
+
+pub fn oauth_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/auth/oauth")
+            .wrap(RequestTimeout::standard())
+            .wrap(IpRateLimit::public_endpoints("auth"))
+            .route("/github", web::get().to(github_login))
+            .route("/github/callback", web::get().to(github_callback)),
+    );
+}
@@ -0,0 +1,63 @@
+use crate::user::model::AuthProvider;
+use serde::Deserialize;
+
+/// GitHub OAuth app credentials; GitHub login is off without them
+#[derive(Debug, Clone)]
+pub struct GithubOAuthConfig {
+    /// `GITHUB_CLIENT_ID`
+    pub client_id: String,
+    /// `GITHUB_CLIENT_SECRET`
+    pub client_secret: String,
+    /// Callback registered with the app (`GITHUB_REDIRECT_URL`, defaulting
+    /// to this API's `/api/v1/auth/oauth/github/callback`)
+    pub redirect_url: String,
+}
+
+impl GithubOAuthConfig {
+    /// Load from the environment; setting only one of the client id and
+    /// secret is an error rather than silently leaving GitHub login off
+    pub fn from_env(public_base_url: &str) -> Result<Option<Self>, String> {
+        let var = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+
+        let (client_id, client_secret) =
+            match (var("GITHUB_CLIENT_ID"), var("GITHUB_CLIENT_SECRET")) {
+                (Some(client_id), Some(client_secret)) => (client_id, client_secret),
+                (None, None) => return Ok(None),
+                (Some(_), None) => return Err("GITHUB_CLIENT_SECRET is required".to_string()),
+                (None, Some(_)) => return Err("GITHUB_CLIENT_ID is required".to_string()),
+            };
+
+        Ok(Some(GithubOAuthConfig {
+            client_id,
+            client_secret,
+            redirect_url: var("GITHUB_REDIRECT_URL").unwrap_or_else(|| {
+                format!("{}/api/v1/auth/oauth/github/callback", public_base_url)
+            }),
+        }))
+    }
+}
+
+/// The parts of a provider's profile an account is built from
+#[derive(Debug, Clone)]
+pub struct OAuthProfile {
+    pub provider: AuthProvider,
+    pub provider_user_id: String,
+    pub username: String,
+    /// Primary address, only when the provider has verified it
+    pub email: Option<String>,
+    pub avatar_url: Option<String>,
+}
+
+/// Query GitHub sends back to the callback
+#[derive(Debug, Deserialize)]
+pub struct OAuthCallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    /// Set instead of `code` when the user declines
+    pub error: Option<String>,
+}
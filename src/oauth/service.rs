@@ -0,0 +1,172 @@
+use crate::database::RedisService;
+use crate::oauth::model::{GithubOAuthConfig, OAuthProfile};
+use crate::user::model::AuthProvider;
+use crate::utils::error::CustomError;
+use rand::Rng;
+use rand::distr::Alphanumeric;
+use reqwest::Url;
+use serde::Deserialize;
+use std::time::Duration;
+
+const AUTHORIZE_URL: &str = "https://github.com/login/oauth/authorize";
+const TOKEN_URL: &str = "https://github.com/login/oauth/access_token";
+const API_URL: &str = "https://api.github.com";
+/// Profile and verified email addresses
+const SCOPES: &str = "read:user user:email";
+/// How long the user has to approve the app on GitHub
+const STATE_TTL_SECONDS: u64 = 10 * 60;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: Option<String>,
+    error_description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubUser {
+    id: u64,
+    login: String,
+    avatar_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubEmail {
+    email: String,
+    primary: bool,
+    verified: bool,
+}
+
+/// Runs the GitHub authorization code flow and fetches the user's profile
+pub struct GithubOAuth {
+    config: Option<GithubOAuthConfig>,
+    redis: RedisService,
+    http: reqwest::Client,
+}
+
+impl GithubOAuth {
+    pub fn new(config: Option<GithubOAuthConfig>, redis: RedisService) -> Self {
+        GithubOAuth {
+            config,
+            redis,
+            http: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .user_agent("SocializationApp")
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+
+    fn config(&self) -> Result<&GithubOAuthConfig, CustomError> {
+        self.config
+            .as_ref()
+            .ok_or_else(|| CustomError::NotFoundError("GitHub login is not enabled".to_string()))
+    }
+
+    /// GitHub's consent page, carrying a single-use `state` against CSRF
+    #[tracing::instrument(skip_all)]
+    pub async fn authorize_url(&self) -> Result<String, CustomError> {
+        let config = self.config()?;
+        let state: String = rand::rng()
+            .sample_iter(&Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect();
+        self.redis
+            .store_oauth_state(&state, STATE_TTL_SECONDS)
+            .await
+            .map_err(CustomError::InternalServerError)?;
+
+        let url = Url::parse_with_params(
+            AUTHORIZE_URL,
+            &[
+                ("client_id", config.client_id.as_str()),
+                ("redirect_uri", config.redirect_url.as_str()),
+                ("scope", SCOPES),
+                ("state", state.as_str()),
+            ],
+        )
+        .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+        Ok(url.into())
+    }
+
+    /// Check the callback's `state`, trade the code for a token and read
+    /// the profile behind it
+    #[tracing::instrument(skip_all)]
+    pub async fn complete(&self, code: &str, state: &str) -> Result<OAuthProfile, CustomError> {
+        let config = self.config()?;
+        let issued_here = self
+            .redis
+            .take_oauth_state(state)
+            .await
+            .map_err(CustomError::InternalServerError)?;
+        if !issued_here {
+            return Err(CustomError::BadRequestError(
+                "Invalid or expired OAuth state; start the login again".to_string(),
+            ));
+        }
+
+        let token: TokenResponse = self
+            .http
+            .post(TOKEN_URL)
+            .header("Accept", "application/json")
+            .form(&[
+                ("client_id", config.client_id.as_str()),
+                ("client_secret", config.client_secret.as_str()),
+                ("code", code),
+                ("redirect_uri", config.redirect_url.as_str()),
+            ])
+            .send()
+            .await
+            .and_then(|res| res.error_for_status())
+            .map_err(github_error)?
+            .json()
+            .await
+            .map_err(github_error)?;
+        let access_token = token.access_token.ok_or_else(|| {
+            CustomError::BadRequestError(
+                token
+                    .error_description
+                    .unwrap_or_else(|| "GitHub rejected the authorization code".to_string()),
+            )
+        })?;
+
+        let user: GithubUser = self.get(&access_token, "/user").await?;
+        let emails: Vec<GithubEmail> = self.get(&access_token, "/user/emails").await?;
+        let email = emails
+            .into_iter()
+            .find(|email| email.primary && email.verified)
+            .map(|email| email.email.to_lowercase());
+
+        Ok(OAuthProfile {
+            provider: AuthProvider::Github,
+            provider_user_id: user.id.to_string(),
+            username: user.login,
+            email,
+            avatar_url: user.avatar_url,
+        })
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(
+        &self,
+        access_token: &str,
+        path: &str,
+    ) -> Result<T, CustomError> {
+        self.http
+            .get(format!("{}{}", API_URL, path))
+            .bearer_auth(access_token)
+            .header("Accept", "application/vnd.github+json")
+            .send()
+            .await
+            .and_then(|res| res.error_for_status())
+            .map_err(github_error)?
+            .json()
+            .await
+            .map_err(github_error)
+    }
+}
+
+fn github_error(e: reqwest::Error) -> CustomError {
+    log::warn!("GitHub OAuth request failed: {}", e);
+    CustomError::InternalServerError("Could not reach GitHub".to_string())
+}
@@ -1,4 +1,5 @@
 pub mod post_controller;
+pub mod post_dto;
 pub mod post_index;
 pub mod post_model;
 pub mod post_service;
@@ -1,88 +1,546 @@
-use crate::middleware::auth::Claims;
-use crate::post::post_model::CreatePostRequest;
-use crate::post::post_service::PostService;
-use crate::{post::post_model::Post, utils::error::CustomError};
-use actix_web::{HttpMessage, HttpRequest, HttpResponse, web};
+use crate::badge::service::BadgeServiceTrait;
+use crate::database::{RedisService, RedisServiceTrait};
+use crate::friend::service::FriendService;
+use crate::group::service::GroupService;
+use crate::link_safety::service::LinkGuardTrait;
+use crate::middleware::auth::AuthUser;
+use crate::moderation::service::ModerationService;
+use crate::post::post_dto::{PostDetailDto, PostDto};
+use crate::post::post_model::{CreatePostRequest, PostImage, PostImageRequest, UpdatePostRequest};
+use crate::post::post_service::{PostService, PostServiceTrait};
+use crate::spam_guard::model::SpamAction;
+use crate::spam_guard::service::SpamGuardTrait;
+use crate::topic::service::TopicServiceTrait;
+use crate::uploader::service::MediaServiceTrait;
+use crate::user::controller::profile_cache_key;
+use crate::utils::i18n::Locale;
+use crate::utils::response::ApiResponse;
+use crate::utils::validation::ValidatedJson;
+use crate::{
+    post::post_model::Post,
+    utils::error::{CustomError, ErrorCode},
+};
+use actix_web::{HttpResponse, web};
 use mongodb::bson::oid::ObjectId;
 
-pub async fn create_post(
-    post_service: web::Data<PostService>,
-    post: web::Json<CreatePostRequest>,
-    req: HttpRequest, // ✅ Add HttpRequest parameter
-) -> Result<HttpResponse, CustomError> {
-    // ✅ Extract claims from request extensions
-    let claims = req
-        .extensions()
-        .get::<Claims>()
-        .ok_or_else(|| CustomError::UnauthorizedError("No claims found".into()))?
-        .clone();
+/// How long the aggregated post view stays cached
+const POST_DETAIL_CACHE_SECONDS: u64 = 30;
+
+/// Cache key for the aggregated post view
+pub fn post_detail_cache_key(post_id: &str) -> String {
+    format!("post:{}:full", post_id)
+}
 
+pub async fn create_post<
+    P: PostServiceTrait,
+    B: BadgeServiceTrait,
+    T: TopicServiceTrait,
+    S: SpamGuardTrait,
+    L: LinkGuardTrait,
+    M: MediaServiceTrait,
+>(
+    locale: Locale,
+    post_service: web::Data<P>,
+    badge_service: web::Data<B>,
+    topic_service: web::Data<T>,
+    spam_guard: web::Data<S>,
+    link_guard: web::Data<L>,
+    media_service: web::Data<M>,
+    post: ValidatedJson<CreatePostRequest>,
+    auth_user: AuthUser,
+) -> Result<HttpResponse, CustomError> {
     // ✅ Author comes from token
-    let author_id = match ObjectId::parse_str(&claims.id) {
-        Ok(id) => id,
-        Err(_) => {
-            return Err(CustomError::BadRequestError(
-                "Invalid user id in token".into(),
-            ));
-        }
-    };
+    let author_id = auth_user.id;
+    spam_guard
+        .check(
+            &author_id,
+            SpamAction::Post,
+            &format!("{}\n{}", post.title, post.content),
+        )
+        .await?;
+    let content = link_guard.screen(&post.content).await?;
+    let images = gallery_images(media_service.get_ref(), &author_id, &post.images).await?;
 
     // ✅ Create new post object
     let new_post = Post {
         id: ObjectId::new(),
         title: post.title.clone(),
-        content: post.content.clone(),
+        content,
         author_id,
+        group_id: None,
+        topics: post.topics.clone(),
+        images,
+        version: 0,
+        share_slug: None,
+        share_count: 0,
+        is_sensitive: post.is_sensitive,
+        sensitive_locked: false,
+        language: None,
+        pinned_at: None,
         created_at: chrono::Utc::now(),
         updated_at: chrono::Utc::now(),
     };
 
     // ✅ Insert post using the service
     let inserted_post = post_service.create_post(new_post).await?;
+    badge_service.on_post_created(&author_id).await;
+    topic_service.on_post_created(&inserted_post.topics).await;
 
-    Ok(HttpResponse::Ok().json(serde_json::json!({
-        "success": true,
-        "message": "Post created successfully",
-        "httpStatusCode": 200,
-        "service": std::env::var("SERVICE_NAME").unwrap_or_else(|_| "Unknown".to_string()),
-        "post": inserted_post
-    })))
+    Ok(ApiResponse::created(locale.t("post-created"))
+        .data(PostDto::from(inserted_post))
+        .into())
 }
 
-pub async fn get_post(
+pub async fn get_post<P: PostServiceTrait>(
+    locale: Locale,
+    auth_user: AuthUser,
     post_id: web::Path<String>,
-    post_service: web::Data<PostService>,
+    post_service: web::Data<P>,
+    group_service: web::Data<GroupService>,
+    friend_service: web::Data<FriendService>,
+    moderation_service: web::Data<ModerationService>,
 ) -> Result<HttpResponse, CustomError> {
     let post_id = post_id.into_inner();
     let post = post_service.get_post(&post_id).await?;
+    let hidden = moderation_service
+        .hidden_authors(Some(&auth_user.id))
+        .await?;
 
     match post {
-        Some(p) => Ok(HttpResponse::Ok().json(serde_json::json!({
-            "success": true,
-            "message": "Post fetched successfully",
-            "httpStatusCode": 200,
-            "service": std::env::var("SERVICE_NAME").unwrap_or_else(|_| "Unknown".to_string()),
-            "post": p
-        }))),
-        None => Err(CustomError::NotFoundError("Post not found".into())),
+        Some(p) if !hidden.contains(&p.author_id) => {
+            ensure_group_access(&group_service, p.group_id, &auth_user).await?;
+            ensure_author_access(&friend_service, p.group_id, &p.author_id, &auth_user).await?;
+            Ok(ApiResponse::ok(locale.t("post-fetched"))
+                .data(PostDto::from(p))
+                .into())
+        }
+        _ => Err(CustomError::coded(
+            ErrorCode::PostNotFound,
+            "Post not found",
+        )),
     }
 }
 
-pub async fn delete_post(
+pub async fn get_post_full<P: PostServiceTrait>(
+    locale: Locale,
+    auth_user: AuthUser,
     post_id: web::Path<String>,
-    post_service: web::Data<PostService>,
+    post_service: web::Data<P>,
+    group_service: web::Data<GroupService>,
+    friend_service: web::Data<FriendService>,
+    moderation_service: web::Data<ModerationService>,
+    redis_service: web::Data<RedisService>,
+) -> Result<HttpResponse, CustomError> {
+    let post_id = post_id.into_inner();
+    let mut post = redis_service
+        .cache_get_or_set_json(
+            &post_detail_cache_key(&post_id),
+            POST_DETAIL_CACHE_SECONDS,
+            || post_service.get_post_detail(&post_id),
+        )
+        .await?
+        .ok_or_else(|| CustomError::coded(ErrorCode::PostNotFound, "Post not found"))?;
+    ensure_group_access(&group_service, post.group_id, &auth_user).await?;
+    ensure_author_access(&friend_service, post.group_id, &post.author_id, &auth_user).await?;
+
+    // The cached view is shared by every reader, so shadow bans apply here
+    let hidden = moderation_service
+        .hidden_authors(Some(&auth_user.id))
+        .await?;
+    if hidden.contains(&post.author_id) {
+        return Err(CustomError::coded(
+            ErrorCode::PostNotFound,
+            "Post not found",
+        ));
+    }
+    let before = post.comments.len();
+    post.comments
+        .retain(|comment| !hidden.contains(&comment.author_id));
+    post.comment_count -= (before - post.comments.len()) as i64;
+
+    Ok(ApiResponse::ok(locale.t("post-fetched"))
+        .data(PostDetailDto::from(post))
+        .into())
+}
+
+pub async fn delete_post<P: PostServiceTrait>(
+    locale: Locale,
+    post_id: web::Path<String>,
+    post_service: web::Data<P>,
+    redis_service: web::Data<RedisService>,
 ) -> Result<HttpResponse, CustomError> {
     let post_id = post_id.into_inner();
     let deleted = post_service.delete_post(&post_id).await?;
+    invalidate_post_detail(redis_service.get_ref(), &post_id).await;
 
     if deleted {
-        Ok(HttpResponse::Ok().json(serde_json::json!({
-            "success": true,
-            "message": "Post deleted successfully",
-            "httpStatusCode": 200,
-            "service": std::env::var("SERVICE_NAME").unwrap_or_else(|_| "Unknown".to_string()),
-        })))
+        Ok(ApiResponse::ok(locale.t("post-deleted")).into())
     } else {
-        Err(CustomError::NotFoundError("Post not found".into()))
+        Err(CustomError::coded(
+            ErrorCode::PostNotFound,
+            "Post not found",
+        ))
+    }
+}
+
+pub async fn update_post<
+    P: PostServiceTrait,
+    R: RedisServiceTrait,
+    M: MediaServiceTrait,
+    L: LinkGuardTrait,
+>(
+    locale: Locale,
+    post_id: web::Path<String>,
+    post_service: web::Data<P>,
+    redis_service: web::Data<R>,
+    media_service: web::Data<M>,
+    link_guard: web::Data<L>,
+    body: ValidatedJson<UpdatePostRequest>,
+    auth_user: AuthUser,
+) -> Result<HttpResponse, CustomError> {
+    let post_id = post_id.into_inner();
+    let existing = post_service
+        .get_post(&post_id)
+        .await?
+        .ok_or_else(|| CustomError::coded(ErrorCode::PostNotFound, "Post not found"))?;
+
+    // Only the author can edit a post
+    if existing.author_id != auth_user.id {
+        return Err(CustomError::UnauthorizedError(
+            "You can only edit your own posts".into(),
+        ));
+    }
+
+    if existing.sensitive_locked && body.is_sensitive == Some(false) {
+        return Err(CustomError::ForbiddenError(
+            "A moderator marked this post as sensitive".into(),
+        ));
+    }
+
+    let body = body.into_inner();
+    let content = match &body.content {
+        Some(content) => Some(link_guard.screen(content).await?),
+        None => None,
+    };
+    let images = match &body.images {
+        Some(images) => Some(gallery_images(media_service.get_ref(), &auth_user.id, images).await?),
+        None => None,
+    };
+    let updated = post_service
+        .update_post(
+            &post_id,
+            body.title,
+            content,
+            body.is_sensitive,
+            images,
+            body.version,
+        )
+        .await?
+        .ok_or_else(|| CustomError::coded(ErrorCode::PostNotFound, "Post not found"))?;
+    invalidate_post_detail(redis_service.get_ref(), &post_id).await;
+
+    Ok(ApiResponse::ok(locale.t("post-updated"))
+        .data(PostDto::from(updated))
+        .into())
+}
+
+/// Pin a post to the top of the author's profile
+/// PUT /posts/{id}/pin
+pub async fn pin_post<P: PostServiceTrait>(
+    locale: Locale,
+    post_id: web::Path<String>,
+    post_service: web::Data<P>,
+    redis_service: web::Data<RedisService>,
+    auth_user: AuthUser,
+) -> Result<HttpResponse, CustomError> {
+    let post = set_pinned(&post_id, true, &post_service, &redis_service, &auth_user).await?;
+
+    Ok(ApiResponse::ok(locale.t("post-pinned"))
+        .data(PostDto::from(post))
+        .into())
+}
+
+/// Take a post off the top of the author's profile
+/// DELETE /posts/{id}/pin
+pub async fn unpin_post<P: PostServiceTrait>(
+    locale: Locale,
+    post_id: web::Path<String>,
+    post_service: web::Data<P>,
+    redis_service: web::Data<RedisService>,
+    auth_user: AuthUser,
+) -> Result<HttpResponse, CustomError> {
+    let post = set_pinned(&post_id, false, &post_service, &redis_service, &auth_user).await?;
+
+    Ok(ApiResponse::ok(locale.t("post-unpinned"))
+        .data(PostDto::from(post))
+        .into())
+}
+
+async fn set_pinned<P: PostServiceTrait>(
+    post_id: &str,
+    pinned: bool,
+    post_service: &P,
+    redis_service: &RedisService,
+    auth_user: &AuthUser,
+) -> Result<Post, CustomError> {
+    let existing = post_service
+        .get_post(post_id)
+        .await?
+        .ok_or_else(|| CustomError::coded(ErrorCode::PostNotFound, "Post not found"))?;
+
+    // Only the author decides what sits on their profile
+    if existing.author_id != auth_user.id {
+        return Err(CustomError::UnauthorizedError(
+            "You can only pin your own posts".into(),
+        ));
+    }
+
+    let post = post_service
+        .set_pinned(post_id, pinned)
+        .await?
+        .ok_or_else(|| CustomError::coded(ErrorCode::PostNotFound, "Post not found"))?;
+    // Pinned posts are part of the cached public profile
+    if let Err(e) = redis_service
+        .cache_delete(&profile_cache_key(&auth_user.id))
+        .await
+    {
+        log::warn!("Failed to invalidate profile cache: {}", e);
+    }
+
+    Ok(post)
+}
+
+/// Resolve a gallery to the author's uploads, keeping the requested order
+pub async fn gallery_images<M: MediaServiceTrait>(
+    media_service: &M,
+    author_id: &ObjectId,
+    images: &[PostImageRequest],
+) -> Result<Vec<PostImage>, CustomError> {
+    if images.is_empty() {
+        return Ok(Vec::new());
+    }
+    let public_ids: Vec<String> = images.iter().map(|image| image.public_id.clone()).collect();
+    if (1..public_ids.len()).any(|i| public_ids[..i].contains(&public_ids[i])) {
+        return Err(CustomError::BadRequestError(
+            "Each image can only appear once".into(),
+        ));
+    }
+
+    let uploads = media_service.owned_by(author_id, &public_ids).await?;
+    Ok(uploads
+        .into_iter()
+        .zip(images)
+        .map(|(upload, image)| PostImage {
+            public_id: upload.public_id,
+            url: upload.secure_url,
+            alt_text: image.alt_text.clone().or(upload.alt_text),
+            caption: image.caption.clone().or(upload.caption),
+            width: upload.width,
+            height: upload.height,
+        })
+        .collect())
+}
+
+/// A post the caller may read, applying group, privacy and shadow ban rules
+pub async fn readable_post(
+    auth_user: &AuthUser,
+    post_service: &PostService,
+    group_service: &GroupService,
+    friend_service: &FriendService,
+    moderation_service: &ModerationService,
+    post_id: &str,
+) -> Result<Post, CustomError> {
+    let hidden = moderation_service
+        .hidden_authors(Some(&auth_user.id))
+        .await?;
+    let post = post_service
+        .get_post(post_id)
+        .await?
+        .filter(|post| !hidden.contains(&post.author_id))
+        .ok_or_else(|| CustomError::coded(ErrorCode::PostNotFound, "Post not found"))?;
+    ensure_group_access(group_service, post.group_id, auth_user).await?;
+    ensure_author_access(friend_service, post.group_id, &post.author_id, auth_user).await?;
+
+    Ok(post)
+}
+
+/// Posts shared in a group are only readable by those who can read the group
+pub async fn ensure_group_access(
+    group_service: &GroupService,
+    group_id: Option<ObjectId>,
+    auth_user: &AuthUser,
+) -> Result<(), CustomError> {
+    let Some(group_id) = group_id else {
+        return Ok(());
+    };
+    let group = group_service
+        .get_group(&group_id)
+        .await?
+        .ok_or_else(|| CustomError::coded(ErrorCode::PostNotFound, "Post not found"))?;
+
+    if !group_service.can_view(&group, &auth_user.id).await? {
+        return Err(CustomError::coded(
+            ErrorCode::PostNotFound,
+            "Post not found",
+        ));
+    }
+    Ok(())
+}
+
+/// Outside groups, authors who share with friends only hide their posts
+/// from everyone else
+pub async fn ensure_author_access(
+    friend_service: &FriendService,
+    group_id: Option<ObjectId>,
+    author_id: &ObjectId,
+    auth_user: &AuthUser,
+) -> Result<(), CustomError> {
+    if group_id.is_some() {
+        return Ok(());
+    }
+    if !friend_service
+        .can_view_posts(author_id, &auth_user.id)
+        .await?
+    {
+        return Err(CustomError::coded(
+            ErrorCode::PostNotFound,
+            "Post not found",
+        ));
+    }
+    Ok(())
+}
+
+/// Drop the cached aggregated view after the post or its comments change
+pub async fn invalidate_post_detail<R: RedisServiceTrait>(redis_service: &R, post_id: &str) {
+    if let Err(e) = redis_service
+        .cache_delete(&post_detail_cache_key(post_id))
+        .await
+    {
+        log::warn!("Failed to invalidate post cache for {}: {}", post_id, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::user::model::Role;
+    use crate::utils::mocks::{MockPosts, MockRedis, NoOp, PassThrough, post_by};
+    use actix_web::http::StatusCode;
+
+    fn caller() -> AuthUser {
+        AuthUser {
+            id: ObjectId::new(),
+            role: Role::User,
+        }
+    }
+
+    fn title_update(title: &str) -> ValidatedJson<UpdatePostRequest> {
+        ValidatedJson(UpdatePostRequest {
+            title: Some(title.to_string()),
+            content: None,
+            is_sensitive: None,
+            images: None,
+            version: 0,
+        })
+    }
+
+    async fn update(
+        posts: &web::Data<MockPosts>,
+        redis: &web::Data<MockRedis>,
+        post_id: &ObjectId,
+        auth_user: AuthUser,
+    ) -> Result<HttpResponse, CustomError> {
+        update_post(
+            Locale::default(),
+            web::Path::from(post_id.to_hex()),
+            posts.clone(),
+            redis.clone(),
+            web::Data::new(NoOp),
+            web::Data::new(PassThrough),
+            title_update("Edited"),
+            auth_user,
+        )
+        .await
+    }
+
+    #[actix_web::test]
+    async fn create_post_is_authored_by_the_caller() {
+        let posts = web::Data::new(MockPosts::default());
+        let auth_user = caller();
+
+        let res = create_post(
+            Locale::default(),
+            posts.clone(),
+            web::Data::new(NoOp),
+            web::Data::new(NoOp),
+            web::Data::new(PassThrough),
+            web::Data::new(PassThrough),
+            web::Data::new(NoOp),
+            ValidatedJson(CreatePostRequest {
+                title: "Hello".to_string(),
+                content: "First post".to_string(),
+                topics: Vec::new(),
+                images: Vec::new(),
+                is_sensitive: false,
+            }),
+            auth_user,
+        )
+        .await
+        .expect("create_post failed");
+
+        assert_eq!(res.status(), StatusCode::CREATED);
+        let posts = posts.posts.lock().unwrap();
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0].author_id, auth_user.id);
+    }
+
+    #[actix_web::test]
+    async fn update_post_rejects_other_users() {
+        let post = post_by(ObjectId::new());
+        let post_id = post.id;
+        let posts = web::Data::new(MockPosts::with(vec![post]));
+        let redis = web::Data::new(MockRedis::default());
+
+        let result = update(&posts, &redis, &post_id, caller()).await;
+
+        assert!(matches!(result, Err(CustomError::UnauthorizedError(_))));
+        assert_eq!(posts.posts.lock().unwrap()[0].title, "Hello");
+        assert!(redis.deleted.lock().unwrap().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn update_post_by_its_author_drops_the_cached_view() {
+        let auth_user = caller();
+        let post = post_by(auth_user.id);
+        let post_id = post.id;
+        let posts = web::Data::new(MockPosts::with(vec![post]));
+        let redis = web::Data::new(MockRedis::default());
+
+        let res = update(&posts, &redis, &post_id, auth_user)
+            .await
+            .expect("update_post failed");
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(posts.posts.lock().unwrap()[0].title, "Edited");
+        assert_eq!(
+            *redis.deleted.lock().unwrap(),
+            vec![post_detail_cache_key(&post_id.to_hex())]
+        );
+    }
+
+    #[actix_web::test]
+    async fn update_post_of_missing_post_is_not_found() {
+        let posts = web::Data::new(MockPosts::default());
+        let redis = web::Data::new(MockRedis::default());
+
+        let result = update(&posts, &redis, &ObjectId::new(), caller()).await;
+
+        assert!(matches!(
+            result,
+            Err(CustomError::CodedError {
+                code: ErrorCode::PostNotFound,
+                ..
+            })
+        ));
     }
 }
@@ -0,0 +1,116 @@
+use crate::comment::dto::CommentDto;
+use crate::post::post_model::{AuthorSummary, Post, PostDetail, PostImage};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// A post as returned by the API
+#[derive(Debug, Serialize)]
+pub struct PostDto {
+    pub id: String,
+    pub title: String,
+    pub content: String,
+    pub author_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group_id: Option<String>,
+    pub topics: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<PostImage>,
+    pub version: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub share_slug: Option<String>,
+    pub share_count: i64,
+    pub is_sensitive: bool,
+    pub sensitive_locked: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pinned_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<Post> for PostDto {
+    fn from(post: Post) -> Self {
+        PostDto {
+            id: post.id.to_hex(),
+            title: post.title,
+            content: post.content,
+            author_id: post.author_id.to_hex(),
+            group_id: post.group_id.map(|id| id.to_hex()),
+            topics: post.topics,
+            images: post.images,
+            version: post.version,
+            share_slug: post.share_slug,
+            share_count: post.share_count,
+            is_sensitive: post.is_sensitive,
+            sensitive_locked: post.sensitive_locked,
+            language: post.language,
+            pinned_at: post.pinned_at,
+            created_at: post.created_at,
+            updated_at: post.updated_at,
+        }
+    }
+}
+
+/// `AuthorSummary` as returned by the API
+#[derive(Debug, Serialize)]
+pub struct AuthorDto {
+    pub id: String,
+    pub username: String,
+    pub profile_picture: Option<String>,
+    pub is_verified: bool,
+}
+
+impl From<AuthorSummary> for AuthorDto {
+    fn from(author: AuthorSummary) -> Self {
+        AuthorDto {
+            id: author.id.to_hex(),
+            username: author.username,
+            profile_picture: author.profile_picture,
+            is_verified: author.is_verified,
+        }
+    }
+}
+
+/// `PostDetail` as returned by the API
+#[derive(Debug, Serialize)]
+pub struct PostDetailDto {
+    pub id: String,
+    pub title: String,
+    pub content: String,
+    pub author_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group_id: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<PostImage>,
+    pub version: i64,
+    pub is_sensitive: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub author: Option<AuthorDto>,
+    pub comment_count: i64,
+    pub comments: Vec<CommentDto>,
+}
+
+impl From<PostDetail> for PostDetailDto {
+    fn from(post: PostDetail) -> Self {
+        PostDetailDto {
+            id: post.id.to_hex(),
+            title: post.title,
+            content: post.content,
+            author_id: post.author_id.to_hex(),
+            group_id: post.group_id.map(|id| id.to_hex()),
+            images: post.images,
+            version: post.version,
+            is_sensitive: post.is_sensitive,
+            language: post.language,
+            created_at: post.created_at,
+            updated_at: post.updated_at,
+            author: post.author.map(Into::into),
+            comment_count: post.comment_count,
+            comments: post.comments.into_iter().map(Into::into).collect(),
+        }
+    }
+}
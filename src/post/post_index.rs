@@ -1,14 +1,47 @@
-use super::post_controller::{create_post, delete_post, get_post};
+use super::post_controller::{
+    create_post, delete_post, get_post, get_post_full, pin_post, unpin_post, update_post,
+};
+use super::post_service::PostService;
+use crate::badge::service::BadgeService;
+use crate::database::RedisService;
+use crate::link_safety::service::LinkGuard;
 use crate::middleware::auth::verify_token;
+use crate::middleware::limits::RequestTimeout;
+use crate::share::controller::share_post;
+use crate::spam_guard::service::SpamGuard;
+use crate::subscription::controller::{subscribe, unsubscribe};
+use crate::topic::service::TopicService;
+use crate::uploader::service::MediaService;
 use actix_web::web;
 use actix_web_httpauth::middleware::HttpAuthentication;
 
 pub fn post_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/posts")
+            .wrap(RequestTimeout::standard())
             .wrap(HttpAuthentication::bearer(verify_token))
-            .route("", web::post().to(create_post))
-            .route("/{id}", web::get().to(get_post))
-            .route("/{id}", web::delete().to(delete_post)),
+            .route(
+                "",
+                web::post().to(create_post::<
+                    PostService,
+                    BadgeService,
+                    TopicService,
+                    SpamGuard,
+                    LinkGuard,
+                    MediaService,
+                >),
+            )
+            .route("/{id}", web::get().to(get_post::<PostService>))
+            .route("/{id}/full", web::get().to(get_post_full::<PostService>))
+            .route("/{id}/subscribe", web::post().to(subscribe))
+            .route("/{id}/subscribe", web::delete().to(unsubscribe))
+            .route("/{id}/share", web::post().to(share_post))
+            .route("/{id}/pin", web::put().to(pin_post::<PostService>))
+            .route("/{id}/pin", web::delete().to(unpin_post::<PostService>))
+            .route(
+                "/{id}",
+                web::put().to(update_post::<PostService, RedisService, MediaService, LinkGuard>),
+            )
+            .route("/{id}", web::delete().to(delete_post::<PostService>)),
     );
 }
@@ -1,6 +1,10 @@
+use crate::comment::model::Comment;
+use crate::utils::datetime::{bson_datetime, option_bson_datetime};
+use crate::utils::validation::not_blank;
 use chrono::{DateTime, Utc};
 use mongodb::bson::oid::ObjectId;
 use serde::{Deserialize, Serialize};
+use validator::Validate;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Post {
@@ -9,12 +13,149 @@ pub struct Post {
     pub title: String,
     pub content: String,
     pub author_id: ObjectId,
+    /// Group the post was shared in; `None` for posts on the author's profile
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group_id: Option<ObjectId>,
+    /// Topic slugs, picked by the author or taken from hashtags
+    #[serde(default)]
+    pub topics: Vec<String>,
+    /// Gallery images in display order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<PostImage>,
+    /// Incremented on every update for optimistic concurrency control
+    #[serde(default)]
+    pub version: i64,
+    /// Short id for `/p/{slug}` share links, assigned on first share
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub share_slug: Option<String>,
+    /// Times shared, copied from `Counter::PostShares`
+    #[serde(default)]
+    pub share_count: i64,
+    /// Clients blur the post's media unless the reader opted to see it
+    #[serde(default)]
+    pub is_sensitive: bool,
+    /// Set when a moderator flagged the post; the author cannot clear it
+    #[serde(default)]
+    pub sensitive_locked: bool,
+    /// ISO 639-3 code detected from the content; `None` when unsure
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// Set while the author keeps the post at the top of their profile
+    #[serde(default, with = "option_bson_datetime")]
+    pub pinned_at: Option<DateTime<Utc>>,
+    #[serde(with = "bson_datetime")]
     pub created_at: DateTime<Utc>,
+    #[serde(with = "bson_datetime")]
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Validate)]
 pub struct CreatePostRequest {
+    #[validate(
+        length(max = 200, message = "must be at most 200 characters"),
+        custom(function = "not_blank")
+    )]
     pub title: String,
+    #[validate(
+        length(max = 10000, message = "must be at most 10000 characters"),
+        custom(function = "not_blank")
+    )]
     pub content: String,
+    /// Topics besides the hashtags in the content
+    #[serde(default)]
+    #[validate(length(max = 5, message = "must have at most 5 topics"))]
+    pub topics: Vec<String>,
+    /// The author's uploads to show as a gallery, in order
+    #[serde(default)]
+    #[validate(length(max = 10, message = "must have at most 10 images"), nested)]
+    pub images: Vec<PostImageRequest>,
+    #[serde(default)]
+    pub is_sensitive: bool,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct UpdatePostRequest {
+    #[validate(
+        length(max = 200, message = "must be at most 200 characters"),
+        custom(function = "not_blank")
+    )]
+    pub title: Option<String>,
+    #[validate(
+        length(max = 10000, message = "must be at most 10000 characters"),
+        custom(function = "not_blank")
+    )]
+    pub content: Option<String>,
+    pub is_sensitive: Option<bool>,
+    /// Replaces the whole gallery, e.g. to reorder it
+    #[validate(length(max = 10, message = "must have at most 10 images"), nested)]
+    pub images: Option<Vec<PostImageRequest>>,
+    /// Version the client last read; the update is rejected if it is stale
+    pub version: i64,
+}
+
+/// One image of a post's gallery
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
+pub struct PostImage {
+    /// Cloudinary public id of the author's upload
+    pub public_id: String,
+    pub url: String,
+    /// Description read out by screen readers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alt_text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub caption: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct PostImageRequest {
+    /// Public id returned by the upload endpoints
+    #[validate(length(min = 1, max = 255, message = "must be between 1 and 255 characters"))]
+    pub public_id: String,
+    /// Overrides the alt text given at upload time
+    #[validate(length(max = 1000, message = "must be at most 1000 characters"))]
+    pub alt_text: Option<String>,
+    /// Overrides the caption given at upload time
+    #[validate(length(max = 2000, message = "must be at most 2000 characters"))]
+    pub caption: Option<String>,
+}
+
+/// Public profile of a post author
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AuthorSummary {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub username: String,
+    pub profile_picture: Option<String>,
+    #[serde(default)]
+    pub is_verified: bool,
+}
+
+/// Post with its author, comment count and first page of comments
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PostDetail {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub title: String,
+    pub content: String,
+    pub author_id: ObjectId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group_id: Option<ObjectId>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<PostImage>,
+    #[serde(default)]
+    pub version: i64,
+    #[serde(default)]
+    pub is_sensitive: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "bson_datetime")]
+    pub updated_at: DateTime<Utc>,
+    pub author: Option<AuthorSummary>,
+    pub comment_count: i64,
+    pub comments: Vec<Comment>,
 }
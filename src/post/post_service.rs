@@ -1,91 +1,416 @@
-use crate::post::post_model::Post;
+use crate::database::{MongoRepository, Page, Repository};
+use crate::post::post_model::{Post, PostDetail, PostImage};
+use crate::topic::model::post_topics;
+use crate::utils::datetime::bson_now;
 use crate::utils::error::CustomError;
-use chrono::Utc;
+use crate::utils::language::detect_language;
+use crate::utils::sanitize::{Markup, sanitize, sanitize_required};
 use mongodb::{
-    Client, Collection,
-    bson::{doc, oid::ObjectId},
+    Client,
+    bson::{Bson, Document, doc, oid::ObjectId},
 };
+use std::future::Future;
 
-pub struct PostService {
-    collection: Collection<Post>,
+/// Number of comments embedded in the post detail response
+const DETAIL_COMMENTS_PAGE_SIZE: i64 = 20;
+
+/// How many posts an author can keep pinned to their profile
+pub const MAX_PINNED_POSTS: u64 = 3;
+
+/// The post operations handlers use, so they can be unit-tested against a
+/// mock instead of MongoDB. `PostService` implements it by delegating to its
+/// inherent methods.
+pub trait PostServiceTrait: Send + Sync + 'static {
+    fn create_post(&self, post: Post) -> impl Future<Output = Result<Post, CustomError>> + Send;
+
+    fn get_post(&self, id: &str) -> impl Future<Output = Result<Option<Post>, CustomError>> + Send;
+
+    fn get_post_detail(
+        &self,
+        id: &str,
+    ) -> impl Future<Output = Result<Option<PostDetail>, CustomError>> + Send;
+
+    fn update_post(
+        &self,
+        id: &str,
+        title: Option<String>,
+        content: Option<String>,
+        is_sensitive: Option<bool>,
+        images: Option<Vec<PostImage>>,
+        expected_version: i64,
+    ) -> impl Future<Output = Result<Option<Post>, CustomError>> + Send;
+
+    fn delete_post(&self, id: &str) -> impl Future<Output = Result<bool, CustomError>> + Send;
+
+    fn delete_posts_by_author(
+        &self,
+        author_id: &ObjectId,
+    ) -> impl Future<Output = Result<u64, CustomError>> + Send;
+
+    fn set_pinned(
+        &self,
+        id: &str,
+        pinned: bool,
+    ) -> impl Future<Output = Result<Option<Post>, CustomError>> + Send;
+}
+
+pub struct PostService<R: Repository<Post> = MongoRepository<Post>> {
+    repository: R,
 }
 
 impl PostService {
     pub fn new(client: &Client) -> Self {
-        let collection = client.database("rust_blogdb").collection::<Post>("posts");
-        PostService { collection }
+        PostService {
+            repository: MongoRepository::new(client, "posts"),
+        }
+    }
+}
+
+impl<R: Repository<Post>> PostService<R> {
+    /// Create a PostService over any repository (e.g. an in-memory mock)
+    pub fn with_repository(repository: R) -> Self {
+        PostService { repository }
     }
 
-    // ✅ Add &self parameter and use self.collection
-    pub async fn create_post(&self, post: Post) -> Result<Post, CustomError> {
-        self.collection
-            .insert_one(&post)
+    #[tracing::instrument(skip_all)]
+    pub async fn create_post(&self, mut post: Post) -> Result<Post, CustomError> {
+        post.title = sanitize_required(&post.title, Markup::None, "title")?;
+        post.content = sanitize_required(&post.content, Markup::Safe, "content")?;
+        post.topics = post_topics(&post.topics, &post.content);
+        post.language = detect_language(&post.content);
+        post.images = sanitize_images(post.images);
+
+        self.repository
+            .insert(&post)
             .await
             .map_err(|_| CustomError::InternalServerError("Failed to create post".into()))?;
 
         Ok(post)
     }
 
-    // ✅ Add &self parameter
+    #[tracing::instrument(skip_all)]
     pub async fn get_post(&self, id: &str) -> Result<Option<Post>, CustomError> {
         let object_id = ObjectId::parse_str(id)
             .map_err(|_| CustomError::BadRequestError("Invalid post ID".into()))?;
 
-        self.collection
-            .find_one(doc! { "_id": object_id })
+        self.repository
+            .find_by_id(&object_id)
             .await
             .map_err(|_| CustomError::InternalServerError("Failed to fetch post".into()))
     }
 
-    // ✅ Add &self parameter
+    #[tracing::instrument(skip_all)]
     pub async fn delete_post(&self, id: &str) -> Result<bool, CustomError> {
         let object_id = ObjectId::parse_str(id)
             .map_err(|_| CustomError::BadRequestError("Invalid post ID".into()))?;
 
-        let result = self
-            .collection
-            .delete_one(doc! { "_id": object_id })
+        self.repository
+            .soft_delete(&object_id)
             .await
-            .map_err(|_| CustomError::InternalServerError("Failed to delete post".into()))?;
+            .map_err(|_| CustomError::InternalServerError("Failed to delete post".into()))
+    }
 
-        Ok(result.deleted_count > 0)
+    /// Soft-delete every post by `author_id`, returning how many
+    #[tracing::instrument(skip_all)]
+    pub async fn delete_posts_by_author(&self, author_id: &ObjectId) -> Result<u64, CustomError> {
+        self.repository
+            .soft_delete_many(doc! { "author_id": author_id })
+            .await
     }
 
-    // ✅ Add &self parameter
+    /// Update a post, rejecting the change if `expected_version` is stale
+    #[tracing::instrument(skip_all)]
     pub async fn update_post(
         &self,
         id: &str,
         title: Option<String>,
         content: Option<String>,
+        is_sensitive: Option<bool>,
+        images: Option<Vec<PostImage>>,
+        expected_version: i64,
     ) -> Result<Option<Post>, CustomError> {
         let object_id = ObjectId::parse_str(id)
             .map_err(|_| CustomError::BadRequestError("Invalid post ID".into()))?;
 
-        let mut update_doc = doc! {
-            "$set": {
-                "updated_at": mongodb::bson::DateTime::from_millis(Utc::now().timestamp_millis())
-            }
+        let mut changes = doc! {
+            "updated_at": bson_now()
         };
 
         if let Some(t) = title {
-            update_doc
-                .get_document_mut("$set")
-                .unwrap()
-                .insert("title", t);
+            changes.insert("title", sanitize_required(&t, Markup::None, "title")?);
         }
         if let Some(c) = content {
-            update_doc
-                .get_document_mut("$set")
-                .unwrap()
-                .insert("content", c);
+            let content = sanitize_required(&c, Markup::Safe, "content")?;
+            changes.insert("language", detect_language(&content));
+            changes.insert("content", content);
+        }
+        if let Some(is_sensitive) = is_sensitive {
+            changes.insert("is_sensitive", is_sensitive);
+        }
+        if let Some(images) = images {
+            let images = mongodb::bson::to_bson(&sanitize_images(images))
+                .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+            changes.insert("images", images);
         }
 
-        let updated_post = self
-            .collection
-            .find_one_and_update(doc! { "_id": object_id }, update_doc)
+        let found = self
+            .repository
+            .update_versioned(&object_id, expected_version, changes)
+            .await
+            .map_err(|e| match e {
+                CustomError::ConflictError(_) => e,
+                _ => CustomError::InternalServerError("Failed to update post".into()),
+            })?;
+
+        if !found {
+            return Ok(None);
+        }
+
+        self.get_post(id).await
+    }
+
+    /// Pin a profile post to the top of its author's profile, or unpin it.
+    /// Group posts can't be pinned, and an author keeps at most
+    /// `MAX_PINNED_POSTS` pinned at once.
+    #[tracing::instrument(skip_all)]
+    pub async fn set_pinned(&self, id: &str, pinned: bool) -> Result<Option<Post>, CustomError> {
+        let Some(post) = self.get_post(id).await? else {
+            return Ok(None);
+        };
+        if pinned == post.pinned_at.is_some() {
+            return Ok(Some(post));
+        }
+
+        let changes = if pinned {
+            if post.group_id.is_some() {
+                return Err(CustomError::BadRequestError(
+                    "Only posts on your profile can be pinned".into(),
+                ));
+            }
+            let pinned_count = self
+                .repository
+                .count(doc! {
+                    "author_id": post.author_id,
+                    "group_id": null,
+                    "pinned_at": { "$ne": null },
+                })
+                .await?;
+            if pinned_count >= MAX_PINNED_POSTS {
+                return Err(CustomError::ConflictError(format!(
+                    "You can pin at most {} posts; unpin one first",
+                    MAX_PINNED_POSTS
+                )));
+            }
+            doc! { "pinned_at": bson_now() }
+        } else {
+            doc! { "pinned_at": null }
+        };
+
+        let found = self
+            .repository
+            .update(&post.id, changes)
             .await
             .map_err(|_| CustomError::InternalServerError("Failed to update post".into()))?;
+        if !found {
+            return Ok(None);
+        }
+
+        self.get_post(id).await
+    }
 
-        Ok(updated_post)
+    /// Newest posts shared in a group, leaving out `hidden_authors` and, for
+    /// readers who hide them, sensitive posts. A non-empty `languages` keeps
+    /// posts in those languages and those whose language is unknown.
+    #[tracing::instrument(skip_all)]
+    pub async fn get_group_feed(
+        &self,
+        group_id: &ObjectId,
+        hidden_authors: &[ObjectId],
+        hide_sensitive: bool,
+        languages: &[String],
+        page: u64,
+        per_page: u64,
+    ) -> Result<Page<Post>, CustomError> {
+        let mut filter = doc! { "group_id": group_id, "author_id": { "$nin": hidden_authors } };
+        if hide_sensitive {
+            filter.insert("is_sensitive", doc! { "$ne": true });
+        }
+        if let Some(languages) = language_filter(languages) {
+            filter.insert("language", languages);
+        }
+
+        self.repository
+            .find_paginated(filter, doc! { "created_at": -1 }, page, per_page)
+            .await
+    }
+
+    /// Newest posts on an author's profile, leaving out group posts
+    #[tracing::instrument(skip_all)]
+    pub async fn get_posts_by_author(
+        &self,
+        author_id: &ObjectId,
+        page: u64,
+        per_page: u64,
+    ) -> Result<Page<Post>, CustomError> {
+        self.repository
+            .find_paginated(
+                doc! { "author_id": author_id, "group_id": null },
+                doc! { "created_at": -1 },
+                page,
+                per_page,
+            )
+            .await
+    }
+
+    /// Fetch a post with its author profile, comment count and first page of
+    /// comments in a single aggregation round trip
+    #[tracing::instrument(skip_all)]
+    pub async fn get_post_detail(&self, id: &str) -> Result<Option<PostDetail>, CustomError> {
+        let object_id = ObjectId::parse_str(id)
+            .map_err(|_| CustomError::BadRequestError("Invalid post ID".into()))?;
+
+        let pipeline = vec![
+            doc! { "$match": { "_id": object_id, "deleted_at": null, "hidden_at": null } },
+            doc! {
+                "$lookup": {
+                    "from": "users",
+                    "localField": "author_id",
+                    "foreignField": "_id",
+                    "as": "author"
+                }
+            },
+            doc! { "$unwind": { "path": "$author", "preserveNullAndEmptyArrays": true } },
+            doc! {
+                "$lookup": {
+                    "from": "comments",
+                    "let": { "post_id": "$_id" },
+                    "pipeline": [
+                        { "$match": { "$expr": { "$eq": ["$post_id", "$$post_id"] }, "deleted_at": null, "hidden_at": null } },
+                        { "$count": "count" }
+                    ],
+                    "as": "comment_count"
+                }
+            },
+            doc! {
+                "$lookup": {
+                    "from": "comments",
+                    "let": { "post_id": "$_id" },
+                    "pipeline": [
+                        { "$match": { "$expr": { "$eq": ["$post_id", "$$post_id"] }, "deleted_at": null, "hidden_at": null } },
+                        { "$sort": { "created_at": 1 } },
+                        { "$limit": DETAIL_COMMENTS_PAGE_SIZE }
+                    ],
+                    "as": "comments"
+                }
+            },
+            doc! {
+                "$addFields": {
+                    "comment_count": {
+                        "$ifNull": [{ "$arrayElemAt": ["$comment_count.count", 0] }, 0]
+                    },
+                    "author": {
+                        "$cond": [
+                            { "$ifNull": ["$author", false] },
+                            {
+                                "_id": "$author._id",
+                                "username": "$author.username",
+                                "profile_picture": "$author.profile_picture",
+                                "is_verified": { "$ifNull": ["$author.is_verified", false] }
+                            },
+                            null
+                        ]
+                    }
+                }
+            },
+        ];
+
+        let result = self
+            .repository
+            .aggregate(pipeline)
+            .await
+            .map_err(|_| CustomError::InternalServerError("Failed to fetch post".into()))?;
+
+        match result.into_iter().next() {
+            Some(document) => mongodb::bson::from_document(document)
+                .map(Some)
+                .map_err(|e| CustomError::InternalServerError(format!("Invalid post data: {}", e))),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<R: Repository<Post> + 'static> PostServiceTrait for PostService<R> {
+    async fn create_post(&self, post: Post) -> Result<Post, CustomError> {
+        Self::create_post(self, post).await
+    }
+
+    async fn get_post(&self, id: &str) -> Result<Option<Post>, CustomError> {
+        Self::get_post(self, id).await
+    }
+
+    async fn get_post_detail(&self, id: &str) -> Result<Option<PostDetail>, CustomError> {
+        Self::get_post_detail(self, id).await
+    }
+
+    async fn update_post(
+        &self,
+        id: &str,
+        title: Option<String>,
+        content: Option<String>,
+        is_sensitive: Option<bool>,
+        images: Option<Vec<PostImage>>,
+        expected_version: i64,
+    ) -> Result<Option<Post>, CustomError> {
+        Self::update_post(
+            self,
+            id,
+            title,
+            content,
+            is_sensitive,
+            images,
+            expected_version,
+        )
+        .await
+    }
+
+    async fn delete_post(&self, id: &str) -> Result<bool, CustomError> {
+        Self::delete_post(self, id).await
     }
+
+    async fn delete_posts_by_author(&self, author_id: &ObjectId) -> Result<u64, CustomError> {
+        Self::delete_posts_by_author(self, author_id).await
+    }
+
+    async fn set_pinned(&self, id: &str, pinned: bool) -> Result<Option<Post>, CustomError> {
+        Self::set_pinned(self, id, pinned).await
+    }
+}
+
+/// Query matching posts in `languages` or in an unknown language; `None`
+/// when every language is wanted
+pub fn language_filter(languages: &[String]) -> Option<Document> {
+    if languages.is_empty() {
+        return None;
+    }
+    let mut allowed: Vec<Bson> = languages.iter().cloned().map(Bson::String).collect();
+    allowed.push(Bson::Null);
+    Some(doc! { "$in": allowed })
+}
+
+/// Strip markup from alt text and captions, dropping ones left blank
+fn sanitize_images(images: Vec<PostImage>) -> Vec<PostImage> {
+    images
+        .into_iter()
+        .map(|mut image| {
+            image.alt_text = sanitize_description(image.alt_text);
+            image.caption = sanitize_description(image.caption);
+            image
+        })
+        .collect()
+}
+
+fn sanitize_description(value: Option<String>) -> Option<String> {
+    value
+        .map(|value| sanitize(&value, Markup::None))
+        .filter(|value| !value.trim().is_empty())
 }
@@ -1,14 +1,81 @@
+use crate::admin::index::admin_routes;
+use crate::analytics::index::analytics_routes;
+use crate::api_key::index::{api_key_routes, internal_routes};
+use crate::badge::index::badge_routes;
 use crate::chat::index::chat_routes;
 use crate::comment::index::comment_routes;
+use crate::export::index::export_routes;
+use crate::feature_flag::index::feature_flag_routes;
+use crate::fingerprint::index::fingerprint_routes;
+use crate::friend::index::friend_routes;
+#[cfg(feature = "graphql")]
+use crate::graphql::index::graphql_routes;
+use crate::group::index::group_routes;
+use crate::leaderboard::index::leaderboard_routes;
+use crate::link_safety::index::link_safety_routes;
+use crate::moderation::index::moderation_routes;
+use crate::notification::index::notification_routes;
+use crate::oauth::index::oauth_routes;
 use crate::post::post_index::post_routes;
+use crate::router::openapi::get_openapi_document;
+use crate::share::index::share_routes;
+use crate::spam_guard::index::spam_guard_routes;
+use crate::sticker::index::sticker_routes;
+use crate::topic::index::topic_routes;
 use crate::uploader::index::upload_routes;
 use crate::user::index::user_routes;
-use actix_web::web;
+use crate::utils::error::{ErrorCode, ErrorCodeEntry};
+use crate::utils::response::ApiResponse;
+use crate::verification::index::verification_routes;
+use actix_web::{HttpResponse, web};
 
+/// Mount every API version side by side.
+///
+/// To introduce a breaking change, add a `v2_routes` that reuses the
+/// unchanged modules' route functions, swaps in the new ones, and mount it
+/// here under `/api/v2`; `/api/v1` keeps serving existing clients.
 pub fn routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/api/v1").configure(v1_routes));
+}
+
+/// Routes served under `/api/v1`
+pub fn v1_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("/error-codes", web::get().to(list_error_codes));
+    cfg.route("/openapi.json", web::get().to(get_openapi_document));
     cfg.configure(user_routes);
+    cfg.configure(oauth_routes);
     cfg.configure(post_routes);
+    cfg.configure(share_routes);
+    cfg.configure(link_safety_routes);
+    cfg.configure(topic_routes);
     cfg.configure(upload_routes);
     cfg.configure(comment_routes);
+    cfg.configure(group_routes);
+    cfg.configure(friend_routes);
+    cfg.configure(badge_routes);
     cfg.configure(chat_routes);
+    cfg.configure(sticker_routes);
+    cfg.configure(notification_routes);
+    cfg.configure(leaderboard_routes);
+    cfg.configure(moderation_routes);
+    cfg.configure(verification_routes);
+    cfg.configure(admin_routes);
+    cfg.configure(export_routes);
+    cfg.configure(spam_guard_routes);
+    cfg.configure(fingerprint_routes);
+    cfg.configure(analytics_routes);
+    cfg.configure(feature_flag_routes);
+    cfg.configure(api_key_routes);
+    cfg.configure(internal_routes);
+    #[cfg(feature = "graphql")]
+    cfg.configure(graphql_routes);
+}
+
+/// The catalog of error codes clients can branch on
+/// GET /error-codes
+async fn list_error_codes() -> HttpResponse {
+    let codes: Vec<ErrorCodeEntry> = ErrorCode::ALL.iter().map(|&code| code.into()).collect();
+    ApiResponse::ok("Error codes fetched successfully")
+        .list(codes)
+        .into()
 }
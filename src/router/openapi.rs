@@ -0,0 +1,119 @@
+//! OpenAPI description of the error envelope, so clients can generate a
+//! typed `ErrorCode` instead of copying strings from `GET /error-codes`
+
+use crate::utils::error::ErrorCode;
+use actix_web::HttpResponse;
+use serde_json::{Value, json};
+
+/// The OpenAPI 3 document for the error envelope and the code catalog
+pub fn openapi_document() -> Value {
+    let codes: Vec<&str> = ErrorCode::ALL.iter().map(|code| code.as_str()).collect();
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "SocializationApp API",
+            "version": "1",
+        },
+        "servers": [{ "url": "/api/v1" }],
+        "paths": {
+            "/error-codes": {
+                "get": {
+                    "summary": "The catalog of error codes clients can branch on",
+                    "responses": {
+                        "200": {
+                            "description": "Every code, its HTTP status and what it means",
+                            "content": {
+                                "application/json": {
+                                    "schema": { "$ref": "#/components/schemas/ErrorCodeList" }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        },
+        "components": {
+            "schemas": {
+                "ErrorCode": {
+                    "type": "string",
+                    "description": "Machine-readable failure code; never renamed once released",
+                    "enum": codes,
+                },
+                "ErrorCodeEntry": {
+                    "type": "object",
+                    "required": ["code", "httpStatusCode", "description"],
+                    "properties": {
+                        "code": { "$ref": "#/components/schemas/ErrorCode" },
+                        "httpStatusCode": { "type": "integer" },
+                        "description": { "type": "string" },
+                    }
+                },
+                "ErrorCodeList": {
+                    "type": "object",
+                    "required": ["success", "message", "httpStatusCode", "service", "data", "count"],
+                    "properties": {
+                        "success": { "type": "boolean" },
+                        "message": { "type": "string" },
+                        "httpStatusCode": { "type": "integer" },
+                        "service": { "type": "string" },
+                        "data": {
+                            "type": "array",
+                            "items": { "$ref": "#/components/schemas/ErrorCodeEntry" },
+                        },
+                        "count": { "type": "integer" },
+                    }
+                },
+                "ErrorResponse": {
+                    "type": "object",
+                    "description": "The envelope every failed request returns",
+                    "required": ["success", "message", "httpStatusCode", "error", "code", "service"],
+                    "properties": {
+                        "success": { "type": "boolean", "enum": [false] },
+                        "message": { "type": "string" },
+                        "httpStatusCode": { "type": "integer" },
+                        "error": { "type": "string" },
+                        "code": {
+                            "description": "An ErrorCode when one applies, otherwise the same as `error`",
+                            "anyOf": [
+                                { "$ref": "#/components/schemas/ErrorCode" },
+                                { "type": "string" },
+                            ]
+                        },
+                        "details": {
+                            "type": "object",
+                            "description": "Structured data about the failure, described by its code",
+                        },
+                        "service": { "type": "string" },
+                        "errors": {
+                            "type": "object",
+                            "description": "Invalid request body fields, keyed by field name",
+                            "additionalProperties": {
+                                "type": "array",
+                                "items": { "type": "string" },
+                            }
+                        },
+                        "retryAfterSeconds": { "type": "integer" },
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// GET /openapi.json
+pub async fn get_openapi_document() -> HttpResponse {
+    HttpResponse::Ok().json(openapi_document())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_code_enum_lists_every_code() {
+        let document = openapi_document();
+        let listed = &document["components"]["schemas"]["ErrorCode"]["enum"];
+        let expected: Vec<&str> = ErrorCode::ALL.iter().map(|code| code.as_str()).collect();
+        assert_eq!(listed, &json!(expected));
+    }
+}
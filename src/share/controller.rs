@@ -0,0 +1,84 @@
+use crate::friend::model::Audience;
+use crate::friend::service::FriendService;
+use crate::group::service::GroupService;
+use crate::leaderboard::service::LeaderboardService;
+use crate::middleware::auth::AuthUser;
+use crate::moderation::service::ModerationService;
+use crate::post::post_controller::readable_post;
+use crate::post::post_service::PostService;
+use crate::share::model::SharePostRequest;
+use crate::share::service::ShareService;
+use crate::utils::config::AppConfig;
+use crate::utils::error::{CustomError, ErrorCode};
+use crate::utils::i18n::Locale;
+use crate::utils::response::ApiResponse;
+use crate::utils::validation::ValidatedJson;
+use actix_web::http::header;
+use actix_web::{HttpResponse, web};
+
+/// Record a share of a post and return its share link
+pub async fn share_post(
+    locale: Locale,
+    auth_user: AuthUser,
+    post_id: web::Path<String>,
+    body: ValidatedJson<SharePostRequest>,
+    post_service: web::Data<PostService>,
+    group_service: web::Data<GroupService>,
+    friend_service: web::Data<FriendService>,
+    moderation_service: web::Data<ModerationService>,
+    share_service: web::Data<ShareService>,
+    leaderboard_service: web::Data<LeaderboardService>,
+) -> Result<HttpResponse, CustomError> {
+    let post = readable_post(
+        &auth_user,
+        &post_service,
+        &group_service,
+        &friend_service,
+        &moderation_service,
+        &post_id,
+    )
+    .await?;
+
+    let (link, first_share) = share_service
+        .share(&post, &auth_user.id, body.channel)
+        .await?;
+    if first_share {
+        leaderboard_service.record_share(&post.id).await;
+    }
+
+    Ok(ApiResponse::ok(locale.t("post-shared")).data(link).into())
+}
+
+/// Open a share link. Redirects to the web app when `SHARE_LINK_BASE_URL`
+/// is set; otherwise returns the post if anyone may read it.
+pub async fn resolve_share_link(
+    locale: Locale,
+    config: web::Data<AppConfig>,
+    slug: web::Path<String>,
+    share_service: web::Data<ShareService>,
+    friend_service: web::Data<FriendService>,
+    moderation_service: web::Data<ModerationService>,
+) -> Result<HttpResponse, CustomError> {
+    let not_found = || CustomError::coded(ErrorCode::PostNotFound, "Post not found");
+    let post = share_service.resolve(&slug).await?.ok_or_else(not_found)?;
+
+    if let Some(base_url) = &config.share_link_base_url {
+        return Ok(HttpResponse::Found()
+            .insert_header((
+                header::LOCATION,
+                format!("{}/posts/{}", base_url, post.id.to_hex()),
+            ))
+            .finish());
+    }
+
+    // Visitors may be signed out, so only posts open to everyone are shown
+    let hidden = moderation_service.hidden_authors(None).await?;
+    if post.group_id.is_some()
+        || hidden.contains(&post.author_id)
+        || friend_service.privacy(&post.author_id).await?.posts != Audience::Everyone
+    {
+        return Err(not_found());
+    }
+
+    Ok(ApiResponse::ok(locale.t("post-fetched")).data(post).into())
+}
@@ -0,0 +1,12 @@
+use super::controller::resolve_share_link;
+use crate::middleware::limits::RequestTimeout;
+use actix_web::web;
+
+/// Public share links; `POST /posts/{id}/share` lives with the post routes
+pub fn share_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/p")
+            .wrap(RequestTimeout::standard())
+            .route("/{slug}", web::get().to(resolve_share_link)),
+    );
+}
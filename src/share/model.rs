@@ -0,0 +1,42 @@
+use crate::utils::datetime::bson_datetime;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+/// How a post left the app
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ShareChannel {
+    /// The share link was copied to the clipboard
+    CopyLink,
+    /// Shared through another app, e.g. the system share sheet
+    External,
+}
+
+/// One share of a post by a user
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PostShare {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub post_id: ObjectId,
+    pub user_id: ObjectId,
+    pub channel: ShareChannel,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct SharePostRequest {
+    pub channel: ShareChannel,
+}
+
+/// Where a shared post can be opened
+#[derive(Debug, Serialize)]
+pub struct ShareLink {
+    pub post_id: ObjectId,
+    pub slug: String,
+    /// Path of the public resolver, relative to the API host
+    pub path: String,
+    pub share_count: i64,
+}
@@ -0,0 +1,182 @@
+use crate::counter::model::Counter;
+use crate::counter::service::CounterService;
+use crate::database::RedisService;
+use crate::post::post_model::Post;
+use crate::share::model::{PostShare, ShareChannel, ShareLink};
+use crate::utils::error::{CustomError, ErrorCode};
+use chrono::Utc;
+use mongodb::bson::{Bson, doc, oid::ObjectId};
+use mongodb::options::IndexOptions;
+use mongodb::{Client, Collection, IndexModel};
+use rand::Rng;
+use rand::distr::Alphanumeric;
+
+/// Length of the base62 slug in share links
+const SHARE_SLUG_LENGTH: usize = 8;
+/// Slug collisions are vanishingly rare; give up after this many
+const SHARE_SLUG_ATTEMPTS: usize = 5;
+
+/// Records post shares and resolves `/p/{slug}` share links
+pub struct ShareService {
+    posts: Collection<Post>,
+    shares: Collection<PostShare>,
+    counters: CounterService,
+}
+
+impl ShareService {
+    pub fn new(client: &Client, redis_service: RedisService) -> Self {
+        let db = client.database("rust_blogdb");
+        ShareService {
+            posts: db.collection::<Post>("posts"),
+            shares: db.collection::<PostShare>("post_shares"),
+            counters: CounterService::new(client, redis_service),
+        }
+    }
+
+    /// Slugs are unique among posts that have one; shares are looked up by
+    /// post and user
+    #[tracing::instrument(skip_all)]
+    pub async fn ensure_indexes(&self) -> Result<(), CustomError> {
+        self.posts
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "share_slug": 1 })
+                    .options(
+                        IndexOptions::builder()
+                            .unique(true)
+                            .partial_filter_expression(doc! { "share_slug": { "$type": "string" } })
+                            .build(),
+                    )
+                    .build(),
+            )
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!(
+                    "Failed to create share slug index: {}",
+                    e
+                ))
+            })?;
+
+        self.shares
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "post_id": 1, "user_id": 1 })
+                    .build(),
+            )
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to create share indexes: {}", e))
+            })?;
+
+        Ok(())
+    }
+
+    /// Record a share and return the post's share link. The second value is
+    /// whether this is the user's first share of the post, which is what
+    /// counts towards trending.
+    #[tracing::instrument(skip_all)]
+    pub async fn share(
+        &self,
+        post: &Post,
+        user_id: &ObjectId,
+        channel: ShareChannel,
+    ) -> Result<(ShareLink, bool), CustomError> {
+        let first_share = self
+            .shares
+            .find_one(doc! { "post_id": post.id, "user_id": user_id })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .is_none();
+
+        self.shares
+            .insert_one(PostShare {
+                id: None,
+                post_id: post.id,
+                user_id: *user_id,
+                channel,
+                created_at: Utc::now(),
+            })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        let slug = match &post.share_slug {
+            Some(slug) => slug.clone(),
+            None => self.assign_slug(&post.id).await?,
+        };
+
+        let subject = post.id.to_hex();
+        self.counters.incr(Counter::PostShares, &subject).await?;
+        let share_count = self.counters.get(Counter::PostShares, &subject).await?;
+
+        Ok((
+            ShareLink {
+                post_id: post.id,
+                path: format!("/api/v1/p/{}", slug),
+                slug,
+                share_count,
+            },
+            first_share,
+        ))
+    }
+
+    /// Give a post its share slug, keeping one another request may have set
+    /// meanwhile
+    #[tracing::instrument(skip_all)]
+    async fn assign_slug(&self, post_id: &ObjectId) -> Result<String, CustomError> {
+        for _ in 0..SHARE_SLUG_ATTEMPTS {
+            let slug = random_slug();
+            let taken = self
+                .posts
+                .count_documents(doc! { "share_slug": slug.clone() })
+                .await
+                .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+                > 0;
+            if taken {
+                continue;
+            }
+
+            self.posts
+                .update_one(
+                    doc! { "_id": post_id, "share_slug": Bson::Null },
+                    doc! { "$set": { "share_slug": slug } },
+                )
+                .await
+                .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+            let post = self
+                .posts
+                .find_one(doc! { "_id": post_id })
+                .await
+                .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+                .ok_or_else(|| CustomError::coded(ErrorCode::PostNotFound, "Post not found"))?;
+            if let Some(slug) = post.share_slug {
+                return Ok(slug);
+            }
+        }
+
+        Err(CustomError::InternalServerError(
+            "Failed to assign a share link".to_string(),
+        ))
+    }
+
+    /// The live post behind a share slug
+    #[tracing::instrument(skip_all)]
+    pub async fn resolve(&self, slug: &str) -> Result<Option<Post>, CustomError> {
+        self.posts
+            .find_one(doc! {
+                "share_slug": slug,
+                "deleted_at": Bson::Null,
+                "hidden_at": Bson::Null,
+            })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))
+    }
+}
+
+fn random_slug() -> String {
+    rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(SHARE_SLUG_LENGTH)
+        .map(char::from)
+        .collect()
+}
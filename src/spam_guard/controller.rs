@@ -0,0 +1,79 @@
+use crate::middleware::auth::AuthUser;
+use crate::spam_guard::model::AddSpamOverrideRequest;
+use crate::spam_guard::service::SpamGuard;
+use crate::utils::error::CustomError;
+use crate::utils::response::ApiResponse;
+use crate::utils::sanitize::{Markup, sanitize};
+use crate::utils::validation::ValidatedJson;
+use actix_web::{HttpResponse, web};
+use mongodb::bson::oid::ObjectId;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct OverridesQuery {
+    pub limit: Option<i64>,
+}
+
+fn parse_user_id(id: &str) -> Result<ObjectId, CustomError> {
+    ObjectId::parse_str(id).map_err(|_| CustomError::BadRequestError("Invalid user ID".to_string()))
+}
+
+/// New accounts exempted from the spam limits
+/// GET /admin/spam-guard/overrides?limit=50
+pub async fn list_spam_overrides(
+    auth_user: AuthUser,
+    spam_guard: web::Data<SpamGuard>,
+    query: web::Query<OverridesQuery>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_admin()?;
+
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+    let overrides = spam_guard.list_overrides(limit).await?;
+
+    Ok(
+        ApiResponse::ok("Spam guard overrides retrieved successfully")
+            .list(overrides)
+            .into(),
+    )
+}
+
+/// POST /admin/spam-guard/overrides
+pub async fn add_spam_override(
+    auth_user: AuthUser,
+    spam_guard: web::Data<SpamGuard>,
+    body: ValidatedJson<AddSpamOverrideRequest>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_admin()?;
+
+    let body = body.into_inner();
+    let user_id = parse_user_id(&body.user_id)?;
+    let note = body
+        .note
+        .map(|note| sanitize(&note, Markup::None))
+        .filter(|note| !note.trim().is_empty());
+    let entry = spam_guard.add_override(user_id, auth_user.id, note).await?;
+
+    Ok(
+        ApiResponse::created("Spam guard override added successfully")
+            .data(entry)
+            .into(),
+    )
+}
+
+/// DELETE /admin/spam-guard/overrides/{user_id}
+pub async fn remove_spam_override(
+    auth_user: AuthUser,
+    spam_guard: web::Data<SpamGuard>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_admin()?;
+
+    let user_id = parse_user_id(&path)?;
+    if !spam_guard.remove_override(&user_id).await? {
+        return Err(CustomError::NotFoundError(
+            "Spam guard override not found".to_string(),
+        ));
+    }
+
+    Ok(ApiResponse::ok("Spam guard override removed successfully").into())
+}
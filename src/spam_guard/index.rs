@@ -0,0 +1,19 @@
+use super::controller::{add_spam_override, list_spam_overrides, remove_spam_override};
+use crate::middleware::auth::verify_token;
+use crate::middleware::limits::RequestTimeout;
+use actix_web::web;
+use actix_web_httpauth::middleware::HttpAuthentication;
+
+pub fn spam_guard_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/admin/spam-guard")
+            .wrap(RequestTimeout::standard())
+            .wrap(HttpAuthentication::bearer(verify_token))
+            .route("/overrides", web::get().to(list_spam_overrides))
+            .route("/overrides", web::post().to(add_spam_override))
+            .route(
+                "/overrides/{user_id}",
+                web::delete().to(remove_spam_override),
+            ),
+    );
+}
@@ -0,0 +1,110 @@
+use crate::database::parse_optional;
+use crate::utils::datetime::bson_datetime;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+use validator::Validate;
+
+/// `http(s)://` or `www.` links in user content
+static LINK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\b(?:https?://|www\.)\S+").expect("valid link regex"));
+
+/// `@username` mentions; the preceding character rules out email addresses
+static MENTION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:^|[^\w@])@([A-Za-z0-9_]{2,32})").expect("valid mention regex")
+});
+
+pub fn count_links(content: &str) -> usize {
+    LINK.find_iter(content).count()
+}
+
+pub fn count_mentions(content: &str) -> usize {
+    MENTION.captures_iter(content).count()
+}
+
+/// What a new account is trying to publish
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpamAction {
+    Post,
+    Comment,
+    ChatMessage,
+}
+
+impl SpamAction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SpamAction::Post => "post",
+            SpamAction::Comment => "comment",
+            SpamAction::ChatMessage => "chat",
+        }
+    }
+}
+
+/// Stricter limits for accounts younger than `new_account_days`. They apply
+/// on top of the regular rate limits.
+#[derive(Debug, Clone, Copy)]
+pub struct SpamPolicy {
+    /// `SPAM_NEW_ACCOUNT_DAYS`, default 7; 0 turns the guard off
+    pub new_account_days: i64,
+    /// Links allowed in one post, comment or message (`SPAM_MAX_LINKS`, default 1)
+    pub max_links: usize,
+    /// Mentions allowed in one post, comment or message (`SPAM_MAX_MENTIONS`, default 3)
+    pub max_mentions: usize,
+    /// Posts per hour (`SPAM_POST_RATE_LIMIT`, default 3)
+    pub post_rate_limit: u64,
+    /// Comments per minute (`SPAM_COMMENT_RATE_LIMIT`, default 2)
+    pub comment_rate_limit: u64,
+    /// Chat messages per 10 seconds (`SPAM_CHAT_RATE_LIMIT`, default 5)
+    pub chat_rate_limit: u64,
+}
+
+impl SpamPolicy {
+    /// Read the thresholds from the environment; part of
+    /// `AppConfig::from_env`
+    pub fn from_env() -> Result<Self, String> {
+        let new_account_days = parse_optional::<i64>("SPAM_NEW_ACCOUNT_DAYS")?.unwrap_or(7);
+        if new_account_days < 0 {
+            return Err("SPAM_NEW_ACCOUNT_DAYS must not be negative".to_string());
+        }
+
+        Ok(SpamPolicy {
+            new_account_days,
+            max_links: parse_optional("SPAM_MAX_LINKS")?.unwrap_or(1),
+            max_mentions: parse_optional("SPAM_MAX_MENTIONS")?.unwrap_or(3),
+            post_rate_limit: parse_optional("SPAM_POST_RATE_LIMIT")?.unwrap_or(3),
+            comment_rate_limit: parse_optional("SPAM_COMMENT_RATE_LIMIT")?.unwrap_or(2),
+            chat_rate_limit: parse_optional("SPAM_CHAT_RATE_LIMIT")?.unwrap_or(5),
+        })
+    }
+
+    /// Budget and window in seconds for an action
+    pub fn rate_limit(&self, action: SpamAction) -> (u64, u64) {
+        match action {
+            SpamAction::Post => (self.post_rate_limit, 3600),
+            SpamAction::Comment => (self.comment_rate_limit, 60),
+            SpamAction::ChatMessage => (self.chat_rate_limit, 10),
+        }
+    }
+}
+
+/// A new account an admin has exempted from the stricter limits
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SpamOverride {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: ObjectId,
+    pub added_by: ObjectId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct AddSpamOverrideRequest {
+    pub user_id: String,
+    #[validate(length(max = 500))]
+    pub note: Option<String>,
+}
@@ -0,0 +1,219 @@
+use crate::database::RedisService;
+use crate::middleware::rate_limit::check_rate_limit;
+use crate::spam_guard::model::{SpamAction, SpamOverride, SpamPolicy, count_links, count_mentions};
+use crate::utils::datetime::bson_datetime;
+use crate::utils::error::{CustomError, ErrorCode};
+use chrono::{DateTime, Duration, Utc};
+use futures_util::TryStreamExt;
+use mongodb::bson::{Document, doc, oid::ObjectId};
+use mongodb::options::IndexOptions;
+use mongodb::{Client, Collection, IndexModel};
+use serde::Deserialize;
+use std::future::Future;
+
+/// Only the signup time of a user document
+#[derive(Debug, Deserialize)]
+struct AccountAge {
+    #[serde(with = "bson_datetime")]
+    created_at: DateTime<Utc>,
+}
+
+/// The spam check handlers run on new content, so handler tests can stub
+/// it. `SpamGuard` implements it by delegating to its inherent method.
+pub trait SpamGuardTrait: Send + Sync + 'static {
+    fn check(
+        &self,
+        user_id: &ObjectId,
+        action: SpamAction,
+        content: &str,
+    ) -> impl Future<Output = Result<(), CustomError>> + Send;
+}
+
+/// Applies the stricter new-account policy to posts, comments and chat
+/// messages, unless an admin has exempted the account
+pub struct SpamGuard {
+    overrides: Collection<SpamOverride>,
+    users: Collection<Document>,
+    redis_service: RedisService,
+    policy: SpamPolicy,
+}
+
+impl SpamGuard {
+    pub fn new(client: &Client, redis_service: RedisService, policy: SpamPolicy) -> Self {
+        let db = client.database("rust_blogdb");
+        SpamGuard {
+            overrides: db.collection::<SpamOverride>("spam_guard_overrides"),
+            users: db.collection::<Document>("users"),
+            redis_service,
+            policy,
+        }
+    }
+
+    /// One override per user
+    #[tracing::instrument(skip_all)]
+    pub async fn ensure_indexes(&self) -> Result<(), CustomError> {
+        self.overrides
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "user_id": 1 })
+                    .options(IndexOptions::builder().unique(true).build())
+                    .build(),
+            )
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!(
+                    "Failed to create spam guard indexes: {}",
+                    e
+                ))
+            })?;
+
+        Ok(())
+    }
+
+    /// Reject content from a new account that has too many links or
+    /// mentions, or that comes too quickly
+    #[tracing::instrument(skip_all, fields(action = action.name()))]
+    pub async fn check(
+        &self,
+        user_id: &ObjectId,
+        action: SpamAction,
+        content: &str,
+    ) -> Result<(), CustomError> {
+        if !self.is_restricted(user_id).await? {
+            return Ok(());
+        }
+
+        if count_links(content) > self.policy.max_links {
+            return Err(CustomError::BadRequestError(format!(
+                "New accounts can include at most {} link(s)",
+                self.policy.max_links
+            )));
+        }
+        if count_mentions(content) > self.policy.max_mentions {
+            return Err(CustomError::BadRequestError(format!(
+                "New accounts can mention at most {} user(s)",
+                self.policy.max_mentions
+            )));
+        }
+
+        let (max_requests, window_seconds) = self.policy.rate_limit(action);
+        check_rate_limit(
+            &self.redis_service,
+            &format!("spam:{}:{}", action.name(), user_id),
+            max_requests,
+            window_seconds,
+        )
+        .await
+    }
+
+    /// Whether the account is still new and not exempted
+    async fn is_restricted(&self, user_id: &ObjectId) -> Result<bool, CustomError> {
+        if self.policy.new_account_days <= 0 {
+            return Ok(false);
+        }
+
+        let account = self
+            .users
+            .clone_with_type::<AccountAge>()
+            .find_one(doc! { "_id": user_id })
+            .projection(doc! { "created_at": 1 })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+        let cutoff = Utc::now() - Duration::days(self.policy.new_account_days);
+        if !account.is_some_and(|account| account.created_at > cutoff) {
+            return Ok(false);
+        }
+
+        let exempted = self
+            .overrides
+            .find_one(doc! { "user_id": user_id })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .is_some();
+        Ok(!exempted)
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn list_overrides(&self, limit: i64) -> Result<Vec<SpamOverride>, CustomError> {
+        self.overrides
+            .find(doc! {})
+            .sort(doc! { "created_at": -1 })
+            .limit(limit)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))
+    }
+
+    /// Exempt an existing account from the new-account limits
+    #[tracing::instrument(skip_all)]
+    pub async fn add_override(
+        &self,
+        user_id: ObjectId,
+        added_by: ObjectId,
+        note: Option<String>,
+    ) -> Result<SpamOverride, CustomError> {
+        let exists = self
+            .users
+            .count_documents(doc! { "_id": user_id })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+        if exists == 0 {
+            return Err(CustomError::coded(
+                ErrorCode::UserNotFound,
+                "User not found",
+            ));
+        }
+
+        let already = self
+            .overrides
+            .find_one(doc! { "user_id": user_id })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+        if already.is_some() {
+            return Err(CustomError::ConflictError(
+                "User is already exempted".to_string(),
+            ));
+        }
+
+        let mut entry = SpamOverride {
+            id: None,
+            user_id,
+            added_by,
+            note,
+            created_at: Utc::now(),
+        };
+        let result = self
+            .overrides
+            .insert_one(&entry)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+        entry.id = result.inserted_id.as_object_id();
+
+        Ok(entry)
+    }
+
+    /// Returns whether an override existed
+    #[tracing::instrument(skip_all)]
+    pub async fn remove_override(&self, user_id: &ObjectId) -> Result<bool, CustomError> {
+        let result = self
+            .overrides
+            .delete_one(doc! { "user_id": user_id })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        Ok(result.deleted_count > 0)
+    }
+}
+
+impl SpamGuardTrait for SpamGuard {
+    async fn check(
+        &self,
+        user_id: &ObjectId,
+        action: SpamAction,
+        content: &str,
+    ) -> Result<(), CustomError> {
+        Self::check(self, user_id, action, content).await
+    }
+}
@@ -0,0 +1,191 @@
+use crate::middleware::auth::AuthUser;
+use crate::middleware::limits::HttpLimits;
+use crate::sticker::model::{CreateStickerPackRequest, Sticker};
+use crate::sticker::service::StickerService;
+use crate::uploader::controller::extract_upload_form;
+use crate::utils::config::AppConfig;
+use crate::utils::error::CustomError;
+use crate::utils::i18n::Locale;
+use crate::utils::response::ApiResponse;
+use crate::utils::sanitize::{Markup, sanitize};
+use crate::utils::uploads::{FileValidator, UploadService};
+use crate::utils::validation::ValidatedJson;
+use actix_multipart::Multipart;
+use actix_web::{HttpResponse, web};
+use mongodb::bson::oid::ObjectId;
+
+/// Largest sticker image
+const MAX_STICKER_BYTES: usize = 1024 * 1024;
+
+fn parse_id(id: &str, what: &str) -> Result<ObjectId, CustomError> {
+    ObjectId::parse_str(id)
+        .map_err(|_| CustomError::BadRequestError(format!("Invalid {} ID", what)))
+}
+
+fn pack_not_found() -> CustomError {
+    CustomError::NotFoundError("Sticker pack not found".to_string())
+}
+
+/// Packs users can pick stickers from
+/// GET /stickers/packs
+pub async fn list_sticker_packs(
+    locale: Locale,
+    sticker_service: web::Data<StickerService>,
+) -> Result<HttpResponse, CustomError> {
+    let packs = sticker_service.list(false).await?;
+
+    Ok(ApiResponse::ok(locale.t("sticker-packs-fetched"))
+        .list(packs)
+        .into())
+}
+
+/// Every pack, including empty ones
+/// GET /admin/stickers/packs
+pub async fn list_all_sticker_packs(
+    auth_user: AuthUser,
+    sticker_service: web::Data<StickerService>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_admin()?;
+
+    let packs = sticker_service.list(true).await?;
+
+    Ok(ApiResponse::ok("Sticker packs retrieved successfully")
+        .list(packs)
+        .into())
+}
+
+/// POST /admin/stickers/packs
+pub async fn create_sticker_pack(
+    auth_user: AuthUser,
+    sticker_service: web::Data<StickerService>,
+    body: ValidatedJson<CreateStickerPackRequest>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_admin()?;
+
+    let pack = sticker_service
+        .create(body.into_inner(), auth_user.id)
+        .await?;
+
+    Ok(ApiResponse::created("Sticker pack created successfully")
+        .data(pack)
+        .into())
+}
+
+/// Upload images into a pack. Each `file` field becomes a sticker, described
+/// by the `alt_text` field in the same position.
+/// POST /admin/stickers/packs/{id}/stickers
+pub async fn upload_stickers(
+    auth_user: AuthUser,
+    sticker_service: web::Data<StickerService>,
+    payload: Multipart,
+    limits: web::Data<HttpLimits>,
+    config: web::Data<AppConfig>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_admin()?;
+    let pack_id = parse_id(&path, "sticker pack")?;
+    if sticker_service.get(&pack_id).await?.is_none() {
+        return Err(pack_not_found());
+    }
+
+    let mut form = extract_upload_form(payload, limits.upload_limit).await?;
+    let files = std::mem::take(&mut form.files);
+    if files.is_empty() {
+        return Err(CustomError::BadRequestError(
+            "Attach at least one image".to_string(),
+        ));
+    }
+
+    // Check every file up front, so a bad one doesn't leave half a batch
+    let validator = FileValidator::images()
+        .with_extensions(vec!["png", "gif", "webp"])
+        .with_max_size(MAX_STICKER_BYTES);
+    for file in &files {
+        validator
+            .validate(file)
+            .map_err(|e| CustomError::BadRequestError(format!("{}: {}", file.file_name, e)))?;
+    }
+
+    let total_files = files.len();
+    let upload_service = UploadService::with_config(config.cloudinary.clone());
+    let results = upload_service
+        .upload_multiple_files(files, Some("stickers"), &validator)
+        .await
+        .map_err(CustomError::BadRequestError)?;
+
+    let mut stickers = Vec::with_capacity(results.len());
+    for (index, result) in results.into_iter().enumerate() {
+        let Some(response) = result.response else {
+            log::warn!(
+                "Failed to upload sticker {}: {}",
+                result.file_name,
+                result.error.unwrap_or_default()
+            );
+            continue;
+        };
+        stickers.push(Sticker {
+            id: ObjectId::new(),
+            pack_id,
+            public_id: response.public_id,
+            url: response.secure_url,
+            width: response.width,
+            height: response.height,
+            alt_text: form
+                .alt_texts
+                .get(index)
+                .map(|alt_text| sanitize(alt_text, Markup::None))
+                .filter(|alt_text| !alt_text.trim().is_empty()),
+        });
+    }
+
+    if stickers.is_empty() {
+        return Err(CustomError::InternalServerError(
+            "Failed to upload stickers".to_string(),
+        ));
+    }
+    let message = if stickers.len() == total_files {
+        "Stickers added successfully".to_string()
+    } else {
+        format!("{} of {} stickers added", stickers.len(), total_files)
+    };
+    let pack = sticker_service.add_stickers(&pack_id, stickers).await?;
+
+    Ok(ApiResponse::created(message).data(pack).into())
+}
+
+/// DELETE /admin/stickers/packs/{id}/stickers/{sticker_id}
+pub async fn delete_sticker(
+    auth_user: AuthUser,
+    sticker_service: web::Data<StickerService>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_admin()?;
+    let (pack_id, sticker_id) = path.into_inner();
+    let pack_id = parse_id(&pack_id, "sticker pack")?;
+    let sticker_id = parse_id(&sticker_id, "sticker")?;
+
+    let pack = sticker_service
+        .remove_sticker(&pack_id, &sticker_id)
+        .await?
+        .ok_or_else(|| CustomError::NotFoundError("Sticker not found".to_string()))?;
+
+    Ok(ApiResponse::ok("Sticker removed successfully")
+        .data(pack)
+        .into())
+}
+
+/// DELETE /admin/stickers/packs/{id}
+pub async fn delete_sticker_pack(
+    auth_user: AuthUser,
+    sticker_service: web::Data<StickerService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_admin()?;
+    let pack_id = parse_id(&path, "sticker pack")?;
+
+    if !sticker_service.delete(&pack_id).await? {
+        return Err(pack_not_found());
+    }
+
+    Ok(ApiResponse::ok("Sticker pack deleted successfully").into())
+}
@@ -0,0 +1,30 @@
+use super::controller::{
+    create_sticker_pack, delete_sticker, delete_sticker_pack, list_all_sticker_packs,
+    list_sticker_packs, upload_stickers,
+};
+use crate::middleware::auth::verify_token;
+use crate::middleware::limits::RequestTimeout;
+use actix_web::web;
+use actix_web_httpauth::middleware::HttpAuthentication;
+
+pub fn sticker_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/stickers")
+            .wrap(RequestTimeout::standard())
+            .wrap(HttpAuthentication::bearer(verify_token))
+            .route("/packs", web::get().to(list_sticker_packs)),
+    );
+    cfg.service(
+        web::scope("/admin/stickers/packs")
+            .wrap(RequestTimeout::uploads())
+            .wrap(HttpAuthentication::bearer(verify_token))
+            .route("", web::get().to(list_all_sticker_packs))
+            .route("", web::post().to(create_sticker_pack))
+            .route("/{id}", web::delete().to(delete_sticker_pack))
+            .route("/{id}/stickers", web::post().to(upload_stickers))
+            .route(
+                "/{id}/stickers/{sticker_id}",
+                web::delete().to(delete_sticker),
+            ),
+    );
+}
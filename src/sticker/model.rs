@@ -0,0 +1,55 @@
+use crate::utils::datetime::bson_datetime;
+use crate::utils::validation::not_blank;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+/// Most stickers a pack can hold
+pub const MAX_STICKERS_PER_PACK: usize = 120;
+
+/// A set of stickers managed by admins and offered to every user
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StickerPack {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub stickers: Vec<Sticker>,
+    pub created_by: ObjectId,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "bson_datetime")]
+    pub updated_at: DateTime<Utc>,
+}
+
+/// An image in a sticker pack. Messages keep their own copy, so a sticker
+/// still shows in history after it is removed from its pack.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Sticker {
+    pub id: ObjectId,
+    pub pack_id: ObjectId,
+    /// Cloudinary public id of the image
+    pub public_id: String,
+    pub url: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Description read out by screen readers, and shown by clients that
+    /// can't render stickers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alt_text: Option<String>,
+}
+
+/// Request body for `POST /admin/stickers/packs`
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateStickerPackRequest {
+    #[validate(
+        length(max = 60, message = "must be at most 60 characters"),
+        custom(function = "not_blank")
+    )]
+    pub name: String,
+    #[validate(length(max = 300, message = "must be at most 300 characters"))]
+    pub description: Option<String>,
+}
@@ -0,0 +1,204 @@
+use crate::sticker::model::{
+    CreateStickerPackRequest, MAX_STICKERS_PER_PACK, Sticker, StickerPack,
+};
+use crate::utils::error::CustomError;
+use crate::utils::sanitize::{Markup, sanitize, sanitize_required};
+use chrono::Utc;
+use futures_util::TryStreamExt;
+use mongodb::bson::{self, doc, oid::ObjectId};
+use mongodb::options::ReturnDocument;
+use mongodb::{Client, Collection, IndexModel};
+
+/// Admin-managed sticker packs
+pub struct StickerService {
+    packs: Collection<StickerPack>,
+}
+
+impl StickerService {
+    pub fn new(client: &Client) -> Self {
+        let db = client.database("rust_blogdb");
+        StickerService {
+            packs: db.collection::<StickerPack>("sticker_packs"),
+        }
+    }
+
+    /// Stickers are looked up by id when sent
+    #[tracing::instrument(skip_all)]
+    pub async fn ensure_indexes(&self) -> Result<(), CustomError> {
+        self.packs
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "stickers.id": 1 })
+                    .build(),
+            )
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to create sticker indexes: {}", e))
+            })?;
+
+        Ok(())
+    }
+
+    /// Packs, oldest first. Empty packs are left out unless asked for, so
+    /// users only see packs they can use.
+    #[tracing::instrument(skip_all)]
+    pub async fn list(&self, include_empty: bool) -> Result<Vec<StickerPack>, CustomError> {
+        let filter = if include_empty {
+            doc! {}
+        } else {
+            doc! { "stickers.0": { "$exists": true } }
+        };
+        let cursor = self
+            .packs
+            .find(filter)
+            .sort(doc! { "created_at": 1 })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        cursor
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn get(&self, pack_id: &ObjectId) -> Result<Option<StickerPack>, CustomError> {
+        self.packs
+            .find_one(doc! { "_id": pack_id })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn create(
+        &self,
+        request: CreateStickerPackRequest,
+        created_by: ObjectId,
+    ) -> Result<StickerPack, CustomError> {
+        let name = sanitize_required(&request.name, Markup::None, "name")?;
+        let exists = self
+            .packs
+            .find_one(doc! { "name": &name })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .is_some();
+        if exists {
+            return Err(CustomError::ConflictError(format!(
+                "Sticker pack {} already exists",
+                name
+            )));
+        }
+
+        let now = Utc::now();
+        let mut pack = StickerPack {
+            id: None,
+            name,
+            description: request
+                .description
+                .map(|description| sanitize(&description, Markup::None))
+                .filter(|description| !description.trim().is_empty()),
+            stickers: Vec::new(),
+            created_by,
+            created_at: now,
+            updated_at: now,
+        };
+        let result = self.packs.insert_one(&pack).await.map_err(|e| {
+            CustomError::InternalServerError(format!("Failed to create sticker pack: {}", e))
+        })?;
+        pack.id = result.inserted_id.as_object_id();
+
+        Ok(pack)
+    }
+
+    /// Add uploaded stickers to a pack, keeping it within
+    /// `MAX_STICKERS_PER_PACK`
+    #[tracing::instrument(skip_all)]
+    pub async fn add_stickers(
+        &self,
+        pack_id: &ObjectId,
+        stickers: Vec<Sticker>,
+    ) -> Result<StickerPack, CustomError> {
+        let room = MAX_STICKERS_PER_PACK.saturating_sub(stickers.len());
+        let stickers = bson::to_bson(&stickers)
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        // The size check rides on the update, so concurrent uploads can't
+        // overfill a pack
+        let past_limit = format!("stickers.{}", room);
+        let filter = doc! { "_id": pack_id, past_limit: { "$exists": false } };
+        let updated = self
+            .packs
+            .find_one_and_update(
+                filter,
+                doc! {
+                    "$push": { "stickers": { "$each": stickers } },
+                    "$set": { "updated_at": bson::DateTime::now() },
+                },
+            )
+            .return_document(ReturnDocument::After)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        match updated {
+            Some(pack) => Ok(pack),
+            None if self.get(pack_id).await?.is_some() => Err(CustomError::BadRequestError(
+                format!("A pack holds at most {} stickers", MAX_STICKERS_PER_PACK),
+            )),
+            None => Err(CustomError::NotFoundError(
+                "Sticker pack not found".to_string(),
+            )),
+        }
+    }
+
+    /// Take a sticker out of its pack. Messages that used it keep showing it.
+    #[tracing::instrument(skip_all)]
+    pub async fn remove_sticker(
+        &self,
+        pack_id: &ObjectId,
+        sticker_id: &ObjectId,
+    ) -> Result<Option<StickerPack>, CustomError> {
+        self.packs
+            .find_one_and_update(
+                doc! { "_id": pack_id, "stickers.id": sticker_id },
+                doc! {
+                    "$pull": { "stickers": { "id": sticker_id } },
+                    "$set": { "updated_at": bson::DateTime::now() },
+                },
+            )
+            .return_document(ReturnDocument::After)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))
+    }
+
+    /// Delete a pack. Its images stay on Cloudinary, since messages that
+    /// used its stickers still point at them.
+    #[tracing::instrument(skip_all)]
+    pub async fn delete(&self, pack_id: &ObjectId) -> Result<bool, CustomError> {
+        let result = self
+            .packs
+            .delete_one(doc! { "_id": pack_id })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        Ok(result.deleted_count > 0)
+    }
+
+    /// A sticker currently offered in one of the packs
+    #[tracing::instrument(skip_all)]
+    pub async fn find_sticker(
+        &self,
+        sticker_id: &ObjectId,
+    ) -> Result<Option<Sticker>, CustomError> {
+        let pack = self
+            .packs
+            .find_one(doc! { "stickers.id": sticker_id })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        Ok(pack.and_then(|pack| {
+            pack.stickers
+                .into_iter()
+                .find(|sticker| sticker.id == *sticker_id)
+        }))
+    }
+}
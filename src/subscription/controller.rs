@@ -0,0 +1,78 @@
+use crate::friend::service::FriendService;
+use crate::group::service::GroupService;
+use crate::middleware::auth::AuthUser;
+use crate::moderation::service::ModerationService;
+use crate::post::post_controller::readable_post;
+use crate::post::post_service::PostService;
+use crate::subscription::model::SubscriptionStatus;
+use crate::subscription::service::SubscriptionService;
+use crate::utils::error::CustomError;
+use crate::utils::i18n::Locale;
+use crate::utils::response::ApiResponse;
+use actix_web::{HttpResponse, web};
+
+/// Follow a post's comments
+/// POST /posts/{id}/subscribe
+pub async fn subscribe(
+    locale: Locale,
+    auth_user: AuthUser,
+    post_service: web::Data<PostService>,
+    group_service: web::Data<GroupService>,
+    friend_service: web::Data<FriendService>,
+    moderation_service: web::Data<ModerationService>,
+    subscription_service: web::Data<SubscriptionService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    let post = readable_post(
+        &auth_user,
+        &post_service,
+        &group_service,
+        &friend_service,
+        &moderation_service,
+        &path,
+    )
+    .await?;
+    subscription_service
+        .set(&post.id, &auth_user.id, true)
+        .await?;
+
+    Ok(ApiResponse::ok(locale.t("post-subscribed"))
+        .data(SubscriptionStatus {
+            post_id: post.id,
+            subscribed: true,
+        })
+        .into())
+}
+
+/// Stop following a post's comments, including your own post's
+/// DELETE /posts/{id}/subscribe
+pub async fn unsubscribe(
+    locale: Locale,
+    auth_user: AuthUser,
+    post_service: web::Data<PostService>,
+    group_service: web::Data<GroupService>,
+    friend_service: web::Data<FriendService>,
+    moderation_service: web::Data<ModerationService>,
+    subscription_service: web::Data<SubscriptionService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    let post = readable_post(
+        &auth_user,
+        &post_service,
+        &group_service,
+        &friend_service,
+        &moderation_service,
+        &path,
+    )
+    .await?;
+    subscription_service
+        .set(&post.id, &auth_user.id, false)
+        .await?;
+
+    Ok(ApiResponse::ok(locale.t("post-unsubscribed"))
+        .data(SubscriptionStatus {
+            post_id: post.id,
+            subscribed: false,
+        })
+        .into())
+}
@@ -0,0 +1,23 @@
+use crate::utils::datetime::bson_datetime;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+/// A user's choice to follow or mute a post's comment thread. Post authors
+/// follow their own posts unless they have a `subscribed: false` record.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PostSubscription {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub post_id: ObjectId,
+    pub user_id: ObjectId,
+    pub subscribed: bool,
+    #[serde(with = "bson_datetime")]
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SubscriptionStatus {
+    pub post_id: ObjectId,
+    pub subscribed: bool,
+}
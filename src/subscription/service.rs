@@ -0,0 +1,118 @@
+use crate::subscription::model::PostSubscription;
+use crate::utils::datetime::bson_now;
+use crate::utils::error::CustomError;
+use futures_util::TryStreamExt;
+use mongodb::bson::{doc, oid::ObjectId};
+use mongodb::options::IndexOptions;
+use mongodb::{Client, Collection, IndexModel};
+use std::future::Future;
+
+/// The subscriber lookup comment handlers make, so they can be tested
+/// against a mock. `SubscriptionService` implements it by delegating to its
+/// inherent method.
+pub trait SubscriptionServiceTrait: Send + Sync + 'static {
+    fn subscribers(
+        &self,
+        post_id: &ObjectId,
+        author_id: &ObjectId,
+    ) -> impl Future<Output = Result<Vec<ObjectId>, CustomError>> + Send;
+}
+
+/// Who follows which post's comment thread
+pub struct SubscriptionService {
+    subscriptions: Collection<PostSubscription>,
+}
+
+impl SubscriptionService {
+    pub fn new(client: &Client) -> Self {
+        let db = client.database("rust_blogdb");
+        SubscriptionService {
+            subscriptions: db.collection::<PostSubscription>("post_subscriptions"),
+        }
+    }
+
+    /// One record per post and user, which also serves lookups by post
+    #[tracing::instrument(skip_all)]
+    pub async fn ensure_indexes(&self) -> Result<(), CustomError> {
+        let indexes = vec![
+            IndexModel::builder()
+                .keys(doc! { "post_id": 1, "user_id": 1 })
+                .options(IndexOptions::builder().unique(true).build())
+                .build(),
+        ];
+
+        self.subscriptions
+            .create_indexes(indexes)
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!(
+                    "Failed to create subscription indexes: {}",
+                    e
+                ))
+            })?;
+
+        Ok(())
+    }
+
+    /// Follow or mute a post's comments
+    #[tracing::instrument(skip_all)]
+    pub async fn set(
+        &self,
+        post_id: &ObjectId,
+        user_id: &ObjectId,
+        subscribed: bool,
+    ) -> Result<(), CustomError> {
+        self.subscriptions
+            .update_one(
+                doc! { "post_id": post_id, "user_id": user_id },
+                doc! { "$set": { "subscribed": subscribed, "updated_at": bson_now() } },
+            )
+            .upsert(true)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Users to notify about a new comment: explicit subscribers, plus the
+    /// author unless they muted the thread
+    #[tracing::instrument(skip_all)]
+    pub async fn subscribers(
+        &self,
+        post_id: &ObjectId,
+        author_id: &ObjectId,
+    ) -> Result<Vec<ObjectId>, CustomError> {
+        let records: Vec<PostSubscription> = self
+            .subscriptions
+            .find(doc! { "post_id": post_id })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        let author_muted = records
+            .iter()
+            .any(|record| record.user_id == *author_id && !record.subscribed);
+        let mut subscribers: Vec<ObjectId> = records
+            .into_iter()
+            .filter(|record| record.subscribed && record.user_id != *author_id)
+            .map(|record| record.user_id)
+            .collect();
+        if !author_muted {
+            subscribers.insert(0, *author_id);
+        }
+
+        Ok(subscribers)
+    }
+}
+
+impl SubscriptionServiceTrait for SubscriptionService {
+    async fn subscribers(
+        &self,
+        post_id: &ObjectId,
+        author_id: &ObjectId,
+    ) -> Result<Vec<ObjectId>, CustomError> {
+        Self::subscribers(self, post_id, author_id).await
+    }
+}
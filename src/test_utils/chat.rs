@@ -0,0 +1,87 @@
+use super::{TestApp, TestUser};
+use crate::app::build_app;
+use actix_codec::Framed;
+use actix_test::TestServer;
+use awc::BoxedSocket;
+use awc::ws::{Codec, Frame, Message};
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{Value, json};
+use std::time::Duration;
+
+/// How long `ChatClient::expect` waits for a matching event
+const EVENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A WebSocket chat session opened through `/ws/chat/token`
+pub struct ChatClient {
+    socket: Framed<BoxedSocket, Codec>,
+    // The session is served by this server, so it lives as long as the client
+    _server: TestServer,
+}
+
+impl TestApp {
+    /// Connect `user` to the chat over a real WebSocket. Each client gets its
+    /// own server, but all of them share this app's `ChatServer`, so clients
+    /// see each other's messages.
+    pub async fn ws_chat(&self, user: &TestUser) -> ChatClient {
+        let state = self.state.clone();
+        let server = actix_test::start(move || build_app(&state));
+        let socket = server
+            .ws_at(&format!("/api/v1/ws/chat/token?token={}", user.token))
+            .await
+            .expect("Failed to open chat WebSocket");
+
+        let mut client = ChatClient {
+            socket,
+            _server: server,
+        };
+        client.expect("connected").await;
+        client
+    }
+}
+
+impl ChatClient {
+    /// Send a client message, e.g. `json!({ "type": "join", "room_id": id })`
+    pub async fn send(&mut self, message: &Value) {
+        self.socket
+            .send(Message::Text(message.to_string().into()))
+            .await
+            .expect("Failed to send chat message");
+    }
+
+    /// Join `room_id`, waiting for the server to confirm
+    pub async fn join(&mut self, room_id: &str) {
+        self.send(&json!({ "type": "join", "room_id": room_id }))
+            .await;
+        let joined = self.expect("joined").await;
+        assert_eq!(
+            joined["room_id"], room_id,
+            "joined another room: {}",
+            joined
+        );
+    }
+
+    /// The next server event of `event_type`, skipping others. Panics if
+    /// none arrives within `EVENT_TIMEOUT`.
+    pub async fn expect(&mut self, event_type: &str) -> Value {
+        let wait = async {
+            loop {
+                let frame = self
+                    .socket
+                    .next()
+                    .await
+                    .expect("Chat WebSocket closed")
+                    .expect("Failed to read chat frame");
+                let Frame::Text(text) = frame else {
+                    continue;
+                };
+                let event: Value = serde_json::from_slice(&text).expect("Chat event is not JSON");
+                if event["type"] == event_type {
+                    return event;
+                }
+            }
+        };
+        tokio::time::timeout(EVENT_TIMEOUT, wait)
+            .await
+            .unwrap_or_else(|_| panic!("No {} event within {:?}", event_type, EVENT_TIMEOUT))
+    }
+}
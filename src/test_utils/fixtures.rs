@@ -0,0 +1,189 @@
+use super::TestApp;
+use crate::api_key::service::hash_key;
+use crate::chat::model::{ChatRoom, RoomType};
+use crate::middleware::auth::create_token_with_session;
+use crate::user::model::{Otp, Role, User};
+use crate::utils::i18n::Locale;
+use crate::utils::otp::generate_otp_code;
+use actix_web::test::TestRequest;
+use chrono::Utc;
+use mongodb::bson::doc;
+use mongodb::bson::oid::ObjectId;
+use serde_json::json;
+use std::sync::atomic::{AtomicU32, Ordering};
+use uuid::Uuid;
+
+/// Password of every user created by `TestApp::create_user`
+pub const TEST_PASSWORD: &str = "Password123!";
+
+/// Keeps phone numbers unique across fixtures
+static NEXT_PHONE: AtomicU32 = AtomicU32::new(0);
+
+/// A verified account with a live session
+pub struct TestUser {
+    pub id: ObjectId,
+    pub username: String,
+    pub email: String,
+    pub token: String,
+}
+
+impl TestUser {
+    /// Send `req` as this user
+    pub fn authorize(&self, req: TestRequest) -> TestRequest {
+        req.insert_header(("Authorization", format!("Bearer {}", self.token)))
+    }
+}
+
+impl TestApp {
+    /// Register `username` through `UserService`, mark it verified and open
+    /// a session for it. The password is `TEST_PASSWORD`.
+    pub async fn create_user(&self, username: &str) -> TestUser {
+        self.create_user_with_role(username, Role::User).await
+    }
+
+    /// Like `create_user`, for moderators and admins
+    pub async fn create_user_with_role(&self, username: &str, role: Role) -> TestUser {
+        let email = format!("{}@example.com", username);
+        let phone_number = format!("+1555{:07}", NEXT_PHONE.fetch_add(1, Ordering::Relaxed));
+        let id = self
+            .state
+            .user_service
+            .create_user(
+                username.to_string(),
+                email.clone(),
+                TEST_PASSWORD.to_string(),
+                phone_number,
+                Locale::default(),
+            )
+            .await
+            .expect("Failed to create test user");
+
+        self.mongo_client
+            .database("rust_blogdb")
+            .collection::<User>("users")
+            .update_one(
+                doc! { "_id": id },
+                doc! { "$set": {
+                    "is_email_verified": true,
+                    "is_phone_verified": true,
+                    "role": mongodb::bson::to_bson(&role).expect("Role serializes")
+                } },
+            )
+            .await
+            .expect("Failed to verify test user");
+
+        let token = create_token_with_session(
+            &id.to_hex(),
+            role,
+            Locale::default(),
+            &self.state.config.jwt_secret,
+            &self.state.redis_service,
+        )
+        .await
+        .expect("Failed to create test session");
+
+        TestUser {
+            id,
+            username: username.to_string(),
+            email,
+            token,
+        }
+    }
+
+    /// The `phone_hash` stored for `user`, as a contact list would send it
+    pub async fn phone_hash_of(&self, user: &TestUser) -> String {
+        self.mongo_client
+            .database("rust_blogdb")
+            .collection::<User>("users")
+            .find_one(doc! { "_id": user.id })
+            .await
+            .expect("Failed to load test user")
+            .and_then(|user| user.phone_hash)
+            .expect("Test user has no phone hash")
+    }
+
+    /// Create a post as `author` through the API, returning its id
+    pub async fn create_post(&self, author: &TestUser, title: &str, content: &str) -> String {
+        let (status, body) = self
+            .call(
+                author
+                    .authorize(self.post("/posts", &json!({ "title": title, "content": content }))),
+            )
+            .await;
+        assert!(
+            status.is_success(),
+            "create_post failed with {}: {}",
+            status,
+            body
+        );
+        body["data"]["id"]
+            .as_str()
+            .expect("Created post has no id")
+            .to_string()
+    }
+
+    /// Create a public chat room owned by `owner`, returning its room id
+    pub async fn create_room(&self, owner: &TestUser, name: &str) -> String {
+        let owner_id = owner.id.to_hex();
+        let room = ChatRoom {
+            id: None,
+            room_id: Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            room_type: RoomType::Public,
+            participants: vec![owner_id.clone()],
+            created_by: owner_id,
+            description: None,
+            avatar_url: None,
+            last_message_at: None,
+            read_only: None,
+            slow_mode_seconds: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        self.mongo_client
+            .database("rust_blogdb")
+            .collection::<ChatRoom>("chat_rooms")
+            .insert_one(&room)
+            .await
+            .expect("Failed to create chat room");
+        room.room_id
+    }
+
+    /// Replace the most recent email verification code sent to `email`
+    /// with one the test knows, since only codes' hashes are stored
+    pub async fn replace_latest_otp(&self, email: &str) -> String {
+        let code = generate_otp_code(6);
+        self.mongo_client
+            .database("rust_blogdb")
+            .collection::<Otp>("otps")
+            .find_one_and_update(
+                doc! { "email": email },
+                doc! { "$set": { "code_hash": hash_key(&code) } },
+            )
+            .sort(doc! { "created_at": -1 })
+            .await
+            .expect("Failed to update OTPs")
+            .expect("No OTP was sent");
+        code
+    }
+}
+
+/// A `multipart/form-data` body holding one file, for upload routes. Returns
+/// the `Content-Type` header value and the body.
+pub fn multipart_file(
+    field: &str,
+    filename: &str,
+    content_type: &str,
+    bytes: &[u8],
+) -> (String, Vec<u8>) {
+    let boundary = format!("----test-boundary-{}", ObjectId::new().to_hex());
+    let mut body = format!(
+        "--{}\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n",
+        boundary, field, filename, content_type
+    )
+    .into_bytes();
+    body.extend_from_slice(bytes);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+
+    (format!("multipart/form-data; boundary={}", boundary), body)
+}
@@ -0,0 +1,503 @@
+//! End-to-end test harness, built with `--features test-utils`.
+//!
+//! `TestApp::spawn` starts throwaway MongoDB and Redis containers (Docker must
+//! be running), builds the full `AppState` against them and serves requests
+//! through the same `App` as `main`, middleware included:
+//!
+//! ```ignore
+//! let app = TestApp::spawn().await;
+//! let user = app.create_user("ada").await;
+//! let (status, body) = app.call(user.authorize(app.get("/users/me"))).await;
+//! ```
+//!
+//! Every `TestApp` has its own containers, so tests can run in parallel.
+
+mod chat;
+mod fixtures;
+
+pub use chat::ChatClient;
+pub use fixtures::{TEST_PASSWORD, TestUser, multipart_file};
+
+use crate::app::build_app;
+use crate::app_state::{AppState, build_app_state};
+use crate::database::{
+    MongoConfig, RedisConfig, RedisService, RedisTopology, connect_to_mongo, connect_to_redis,
+};
+use crate::utils::config::AppConfig;
+use actix_web::dev::Service;
+use actix_web::http::StatusCode;
+use actix_web::test;
+use mongodb::Client;
+use serde_json::Value;
+use std::sync::OnceLock;
+use testcontainers_modules::mongo::Mongo;
+use testcontainers_modules::redis::{REDIS_PORT, Redis};
+use testcontainers_modules::testcontainers::ContainerAsync;
+use testcontainers_modules::testcontainers::runners::AsyncRunner;
+
+/// Settings `AppConfig::load` requires, filled in when the environment
+/// doesn't provide them. Email and SMS are logged rather than sent.
+const TEST_ENV: &[(&str, &str)] = &[
+    (
+        "JWT_SECRET",
+        "test-only-secret-9f3c2a7b51e84d06a1c4e7f2b8d93a65",
+    ),
+    ("FINGERPRINT_SALT", "test-only-fingerprint-salt"),
+    ("EXPORT_SIGNING_SECRET", "test-only-export-signing-secret"),
+    ("MONGODB_URI", "mongodb://127.0.0.1:27017"),
+    ("EMAIL_PROVIDER", "smtp"),
+    ("SMTP_FROM_EMAIL", "tests@example.com"),
+    ("SMTP_USERNAME", "tests"),
+    ("SMTP_PASSWORD", "tests"),
+    ("EMAIL_DRY_RUN", "true"),
+    ("SMS_DRY_RUN", "true"),
+    ("CLOUDINARY_CLOUD_NAME", "test"),
+    ("CLOUDINARY_API_KEY", "test"),
+    ("CLOUDINARY_API_SECRET", "test"),
+    // Test requests share one client IP, so keep the app-wide budgets out
+    // of the way
+    ("RATE_LIMIT_GLOBAL_PER_MINUTE", "100000"),
+    ("RATE_LIMIT_PUBLIC_PER_MINUTE", "100000"),
+];
+
+static CONFIG: OnceLock<AppConfig> = OnceLock::new();
+
+/// Load the configuration once, with `TEST_ENV` defaults
+fn load_test_config() -> &'static AppConfig {
+    CONFIG.get_or_init(|| {
+        dotenv::dotenv().ok();
+        for (name, value) in TEST_ENV {
+            if std::env::var(name).is_err() {
+                // SAFETY: the harness reads the environment only inside this
+                // initializer, and nothing else in tests touches it
+                unsafe { std::env::set_var(name, value) };
+            }
+        }
+        AppConfig::load().unwrap_or_else(|problems| {
+            panic!("Invalid test configuration: {}", problems.join("; "))
+        })
+    })
+}
+
+/// The application running against its own MongoDB and Redis containers
+pub struct TestApp {
+    pub state: AppState,
+    pub mongo_client: Client,
+    // Dropping a container stops it, so they live as long as the app
+    _mongo: ContainerAsync<Mongo>,
+    _redis: ContainerAsync<Redis>,
+}
+
+impl TestApp {
+    /// Start MongoDB and Redis, then build every service against them.
+    /// Panics if Docker is unavailable.
+    pub async fn spawn() -> Self {
+        let config = load_test_config();
+
+        let mongo = Mongo::default()
+            .start()
+            .await
+            .expect("Failed to start MongoDB container");
+        let redis = Redis::default()
+            .start()
+            .await
+            .expect("Failed to start Redis container");
+
+        let mongo_port = mongo
+            .get_host_port_ipv4(27017)
+            .await
+            .expect("MongoDB port not exposed");
+        let redis_port = redis
+            .get_host_port_ipv4(REDIS_PORT)
+            .await
+            .expect("Redis port not exposed");
+
+        let mongo_client = connect_to_mongo(&MongoConfig {
+            uri: format!("mongodb://127.0.0.1:{}", mongo_port),
+            ..config.mongo.clone()
+        })
+        .await
+        .expect("Failed to connect to MongoDB container");
+        let redis_client = connect_to_redis(&RedisConfig {
+            topology: RedisTopology::Standalone {
+                url: format!("redis://127.0.0.1:{}", redis_port),
+            },
+            ..config.redis.clone()
+        })
+        .await
+        .expect("Failed to connect to Redis container");
+
+        let state = build_app_state(config, &mongo_client, RedisService::new(&redis_client)).await;
+
+        Self {
+            state,
+            mongo_client,
+            _mongo: mongo,
+            _redis: redis,
+        }
+    }
+
+    /// A GET request to `path` under `/api/v1`
+    pub fn get(&self, path: &str) -> test::TestRequest {
+        test::TestRequest::get().uri(&api_path(path))
+    }
+
+    /// A POST request to `path` under `/api/v1` with a JSON body
+    pub fn post(&self, path: &str, body: &Value) -> test::TestRequest {
+        test::TestRequest::post()
+            .uri(&api_path(path))
+            .set_json(body)
+    }
+
+    /// A PUT request to `path` under `/api/v1` with a JSON body
+    pub fn put(&self, path: &str, body: &Value) -> test::TestRequest {
+        test::TestRequest::put().uri(&api_path(path)).set_json(body)
+    }
+
+    /// A DELETE request to `path` under `/api/v1`
+    pub fn delete(&self, path: &str) -> test::TestRequest {
+        test::TestRequest::delete().uri(&api_path(path))
+    }
+
+    /// Send `req` through the app `main` serves, middleware included,
+    /// returning the status and JSON body (`Value::Null` for empty or
+    /// non-JSON bodies)
+    pub async fn call(&self, req: test::TestRequest) -> (StatusCode, Value) {
+        let app = test::init_service(build_app(&self.state)).await;
+
+        let res = match app.call(req.to_request()).await {
+            Ok(res) => res.map_into_boxed_body(),
+            Err(e) => {
+                let res = e.error_response();
+                return (res.status(), Value::Null);
+            }
+        };
+        let status = res.status();
+        let body = test::read_body(res).await;
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+}
+
+fn api_path(path: &str) -> String {
+    format!("/api/v1{}", path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::friend::model::DIRECT_ROOM_PREFIX;
+    use crate::user::model::User;
+    use mongodb::bson::doc;
+    use serde_json::json;
+
+    #[actix_web::test]
+    async fn registered_user_verifies_and_logs_in() {
+        let app = TestApp::spawn().await;
+
+        let (status, _) = app
+            .call(app.post(
+                "/auth/user/register",
+                &json!({
+                    "username": "grace",
+                    "email": "grace@example.com",
+                    "password": TEST_PASSWORD,
+                    "phone_number": "+15550001234"
+                }),
+            ))
+            .await;
+        assert!(status.is_success(), "register failed with {}", status);
+
+        let code = app.replace_latest_otp("grace@example.com").await;
+        let (status, _) = app
+            .call(app.post(
+                "/auth/user/verify-email",
+                &json!({ "email": "grace@example.com", "otp_code": code }),
+            ))
+            .await;
+        assert!(status.is_success(), "verify failed with {}", status);
+
+        let (status, body) = app
+            .call(app.post(
+                "/auth/user/login",
+                &json!({ "username": "grace", "password": TEST_PASSWORD }),
+            ))
+            .await;
+        assert!(status.is_success(), "login failed with {}", status);
+        assert!(body["data"]["token"].is_string(), "no token in {}", body);
+    }
+
+    #[actix_web::test]
+    async fn verification_codes_burn_after_too_many_guesses() {
+        let app = TestApp::spawn().await;
+        let (status, _) = app
+            .call(app.post(
+                "/auth/user/register",
+                &json!({
+                    "username": "alan",
+                    "email": "alan@example.com",
+                    "password": TEST_PASSWORD,
+                    "phone_number": "+15550004321"
+                }),
+            ))
+            .await;
+        assert!(status.is_success(), "register failed with {}", status);
+        let code = app.replace_latest_otp("alan@example.com").await;
+
+        for _ in 0..crate::utils::otp::OTP_MAX_ATTEMPTS {
+            let (status, _) = app
+                .call(app.post(
+                    "/auth/user/verify-email",
+                    &json!({ "email": "alan@example.com", "otp_code": "000000x" }),
+                ))
+                .await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+        }
+
+        let (status, body) = app
+            .call(app.post(
+                "/auth/user/verify-email",
+                &json!({ "email": "alan@example.com", "otp_code": code }),
+            ))
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "AUTH_OTP_EXPIRED", "unexpected body {}", body);
+    }
+
+    #[actix_web::test]
+    async fn comments_attach_to_posts() {
+        let app = TestApp::spawn().await;
+        let author = app.create_user("linus").await;
+        let reader = app.create_user("ken").await;
+
+        let post_id = app.create_post(&author, "Hello", "First post").await;
+        let (status, _) = app
+            .call(reader.authorize(app.post(
+                "/comments",
+                &json!({ "post_id": post_id, "content": "Welcome!" }),
+            )))
+            .await;
+        assert!(status.is_success(), "comment failed with {}", status);
+
+        let (status, body) = app
+            .call(reader.authorize(app.get(&format!("/comments/count/{}", post_id))))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["count"], 1, "unexpected count in {}", body);
+    }
+
+    #[actix_web::test]
+    async fn deleted_accounts_lose_sessions_and_posts() {
+        let app = TestApp::spawn().await;
+        let user = app.create_user("barbara").await;
+        let post_id = app.create_post(&user, "Hello", "Soon gone").await;
+
+        let req = test::TestRequest::delete()
+            .uri(&api_path("/auth/user/me"))
+            .set_json(json!({ "password": TEST_PASSWORD }));
+        let (status, _) = app.call(user.authorize(req)).await;
+        assert!(status.is_success(), "delete failed with {}", status);
+
+        let (status, _) = app.call(user.authorize(app.get("/users/me"))).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let reader = app.create_user("ken").await;
+        let (status, _) = app
+            .call(reader.authorize(app.get(&format!("/posts/{}", post_id))))
+            .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, _) = app
+            .call(app.post(
+                "/auth/user/login",
+                &json!({ "username": "barbara", "password": TEST_PASSWORD }),
+            ))
+            .await;
+        assert!(!status.is_success(), "deleted account logged in");
+    }
+
+    #[actix_web::test]
+    async fn profiles_show_pinned_posts_and_hide_from_blocked_users() {
+        let app = TestApp::spawn().await;
+        let author = app.create_user("grace").await;
+        let reader = app.create_user("edsger").await;
+        let post_id = app.create_post(&author, "Hello", "Pinned post").await;
+
+        let (status, _) = app
+            .call(author.authorize(app.put(&format!("/posts/{}/pin", post_id), &json!({}))))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, body) = app
+            .call(reader.authorize(app.get("/users/by-username/grace")))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body["data"]["pinned_posts"][0]["id"], post_id,
+            "unexpected body {}",
+            body
+        );
+
+        let (status, _) = app
+            .call(author.authorize(app.post(&format!("/friends/blocks/{}", reader.id), &json!({}))))
+            .await;
+        assert!(status.is_success(), "block failed with {}", status);
+
+        let (status, body) = app
+            .call(reader.authorize(app.get("/users/by-username/grace")))
+            .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["code"], "USER_NOT_FOUND", "unexpected body {}", body);
+    }
+
+    #[actix_web::test]
+    async fn contacts_only_match_verified_phone_numbers() {
+        let app = TestApp::spawn().await;
+        let searcher = app.create_user("barbara").await;
+        let owner = app.create_user("frances").await;
+        let claimant = app.create_user("mallory").await;
+        app.mongo_client
+            .database("rust_blogdb")
+            .collection::<User>("users")
+            .update_one(
+                doc! { "_id": claimant.id },
+                doc! { "$set": { "is_phone_verified": false } },
+            )
+            .await
+            .expect("Failed to unverify phone");
+
+        let phone_hashes = vec![
+            app.phone_hash_of(&owner).await,
+            app.phone_hash_of(&claimant).await,
+        ];
+        let (status, body) = app
+            .call(searcher.authorize(app.post(
+                "/users/find-by-contacts",
+                &json!({ "phone_hashes": phone_hashes }),
+            )))
+            .await;
+
+        assert_eq!(status, StatusCode::OK, "unexpected body {}", body);
+        let usernames: Vec<&str> = body["data"]
+            .as_array()
+            .expect("Matches are not a list")
+            .iter()
+            .filter_map(|contact| contact["user"]["username"].as_str())
+            .collect();
+        assert_eq!(usernames, vec!["frances"]);
+    }
+
+    #[actix_web::test]
+    async fn chat_messages_reach_room_members() {
+        let app = TestApp::spawn().await;
+        let alice = app.create_user("alice").await;
+        let bob = app.create_user("bob").await;
+        let room_id = app.create_room(&alice, "General").await;
+
+        let mut alice_chat = app.ws_chat(&alice).await;
+        let mut bob_chat = app.ws_chat(&bob).await;
+        alice_chat.join(&room_id).await;
+        bob_chat.join(&room_id).await;
+
+        alice_chat
+            .send(&json!({ "type": "message", "room_id": room_id, "content": "Hi Bob" }))
+            .await;
+        let message = bob_chat.expect("message").await;
+        assert_eq!(message["room_id"], room_id.as_str());
+        assert_eq!(message["sender_id"], alice.id.to_hex());
+        assert_eq!(message["content"], "Hi Bob");
+    }
+
+    #[actix_web::test]
+    async fn messages_dropped_by_a_block_stay_hidden_after_unblocking() {
+        let app = TestApp::spawn().await;
+        let alice = app.create_user("alice").await;
+        let bob = app.create_user("bob").await;
+        let (first, second) = if alice.id < bob.id {
+            (alice.id, bob.id)
+        } else {
+            (bob.id, alice.id)
+        };
+        let room_id = format!(
+            "{}{}:{}",
+            DIRECT_ROOM_PREFIX,
+            first.to_hex(),
+            second.to_hex()
+        );
+
+        let mut alice_chat = app.ws_chat(&alice).await;
+        alice_chat.join(&room_id).await;
+
+        let (status, _) = app
+            .call(bob.authorize(app.post(&format!("/friends/blocks/{}", alice.id), &json!({}))))
+            .await;
+        assert!(status.is_success(), "block failed with {}", status);
+
+        alice_chat
+            .send(&json!({ "type": "message", "room_id": room_id, "content": "Still there?" }))
+            .await;
+        // Only the sender gets a dropped message, once it is stored
+        alice_chat.expect("message").await;
+
+        let (status, _) = app
+            .call(bob.authorize(app.delete(&format!("/friends/blocks/{}", alice.id))))
+            .await;
+        assert!(status.is_success(), "unblock failed with {}", status);
+
+        let history = format!("/chat/rooms/{}/messages", room_id);
+        let (status, body) = app.call(bob.authorize(app.get(&history))).await;
+        assert_eq!(status, StatusCode::OK, "unexpected body {}", body);
+        assert_eq!(body["count"], 0, "dropped message shown: {}", body);
+
+        let (status, body) = app.call(alice.authorize(app.get(&history))).await;
+        assert_eq!(status, StatusCode::OK, "unexpected body {}", body);
+        assert_eq!(
+            body["data"][0]["content"], "Still there?",
+            "unexpected body {}",
+            body
+        );
+    }
+
+    #[actix_web::test]
+    async fn uploads_reject_files_that_are_not_images() {
+        let app = TestApp::spawn().await;
+        let user = app.create_user("ada").await;
+
+        let (content_type, body) =
+            multipart_file("file", "notes.txt", "text/plain", b"not an image");
+        let req = test::TestRequest::post()
+            .uri(&api_path("/upload/single"))
+            .insert_header(("Content-Type", content_type))
+            .set_payload(body);
+        let (status, body) = app.call(user.authorize(req)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(
+            body["message"]
+                .as_str()
+                .is_some_and(|message| message.contains("Invalid file type")),
+            "unexpected body {}",
+            body
+        );
+    }
+
+    #[actix_web::test]
+    async fn responses_carry_security_headers() {
+        let app = TestApp::spawn().await;
+        let req = app.get("/error-codes").to_request();
+        let res = test::call_service(&test::init_service(build_app(&app.state)).await, req).await;
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(
+            res.headers().contains_key("x-content-type-options"),
+            "missing security headers: {:?}",
+            res.headers()
+        );
+    }
+
+    #[actix_web::test]
+    async fn protected_routes_reject_anonymous_requests() {
+        let app = TestApp::spawn().await;
+
+        let (status, _) = app.call(app.get("/users/me")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+}
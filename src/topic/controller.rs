@@ -0,0 +1,155 @@
+use crate::friend::service::FriendService;
+use crate::middleware::auth::AuthUser;
+use crate::moderation::service::ModerationService;
+use crate::post::post_model::Post;
+use crate::topic::model::{UpdateInterestsRequest, normalize_topic};
+use crate::topic::service::TopicService;
+use crate::user::service::UserService;
+use crate::utils::error::CustomError;
+use crate::utils::i18n::Locale;
+use crate::utils::response::ApiResponse;
+use crate::utils::validation::ValidatedJson;
+use actix_web::{HttpResponse, web};
+use mongodb::bson::oid::ObjectId;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+#[derive(Debug, Deserialize)]
+pub struct TopicQuery {
+    pub limit: Option<i64>,
+}
+
+impl TopicQuery {
+    fn limit(&self) -> i64 {
+        self.limit.unwrap_or(20).clamp(1, 100)
+    }
+}
+
+/// Drop posts whose authors share with friends only, unless the viewer is one
+async fn visible_posts(
+    friend_service: &FriendService,
+    posts: Vec<Post>,
+    viewer_id: &ObjectId,
+) -> Result<Vec<Post>, CustomError> {
+    let mut allowed: HashMap<ObjectId, bool> = HashMap::new();
+    let mut visible = Vec::with_capacity(posts.len());
+    for post in posts {
+        let can_view = match allowed.get(&post.author_id) {
+            Some(can_view) => *can_view,
+            None => {
+                let can_view = friend_service
+                    .can_view_posts(&post.author_id, viewer_id)
+                    .await?;
+                allowed.insert(post.author_id, can_view);
+                can_view
+            }
+        };
+        if can_view {
+            visible.push(post);
+        }
+    }
+    Ok(visible)
+}
+
+/// Most used topics
+/// GET /topics?limit=20
+pub async fn list_topics(
+    locale: Locale,
+    topic_service: web::Data<TopicService>,
+    query: web::Query<TopicQuery>,
+) -> Result<HttpResponse, CustomError> {
+    let topics = topic_service.popular(query.limit()).await?;
+
+    Ok(ApiResponse::ok(locale.t("topics-fetched"))
+        .list(topics)
+        .into())
+}
+
+/// Newest posts tagged with a topic
+/// GET /topics/{slug}/posts?limit=20
+pub async fn get_topic_posts(
+    locale: Locale,
+    auth_user: AuthUser,
+    topic_service: web::Data<TopicService>,
+    friend_service: web::Data<FriendService>,
+    moderation_service: web::Data<ModerationService>,
+    user_service: web::Data<UserService>,
+    path: web::Path<String>,
+    query: web::Query<TopicQuery>,
+) -> Result<HttpResponse, CustomError> {
+    let slug = normalize_topic(&path)
+        .ok_or_else(|| CustomError::BadRequestError("Invalid topic".to_string()))?;
+    let hidden = moderation_service
+        .hidden_authors(Some(&auth_user.id))
+        .await?;
+    let languages = user_service.content_languages(&auth_user.id).await?;
+    let posts = topic_service
+        .posts(&slug, &hidden, &languages, query.limit())
+        .await?;
+    let mut posts = visible_posts(&friend_service, posts, &auth_user.id).await?;
+    if user_service.hides_sensitive(&auth_user.id).await? {
+        posts.retain(|post| !post.is_sensitive);
+    }
+
+    Ok(ApiResponse::ok(locale.t("topic-posts-fetched"))
+        .list(posts)
+        .into())
+}
+
+/// Recent posts ranked by the caller's interests
+/// GET /topics/discover?limit=20
+pub async fn discover(
+    locale: Locale,
+    auth_user: AuthUser,
+    topic_service: web::Data<TopicService>,
+    friend_service: web::Data<FriendService>,
+    moderation_service: web::Data<ModerationService>,
+    user_service: web::Data<UserService>,
+    query: web::Query<TopicQuery>,
+) -> Result<HttpResponse, CustomError> {
+    let hidden = moderation_service
+        .hidden_authors(Some(&auth_user.id))
+        .await?;
+    let languages = user_service.content_languages(&auth_user.id).await?;
+    let posts = topic_service
+        .discover(&auth_user.id, &hidden, &languages, query.limit())
+        .await?;
+    let mut posts = visible_posts(&friend_service, posts, &auth_user.id).await?;
+    if user_service.hides_sensitive(&auth_user.id).await? {
+        posts.retain(|post| !post.is_sensitive);
+    }
+
+    Ok(ApiResponse::ok(locale.t("topic-posts-fetched"))
+        .list(posts)
+        .into())
+}
+
+/// GET /users/me/interests
+pub async fn get_interests(
+    locale: Locale,
+    auth_user: AuthUser,
+    topic_service: web::Data<TopicService>,
+) -> Result<HttpResponse, CustomError> {
+    let interests = topic_service.interests(&auth_user.id).await?;
+
+    Ok(ApiResponse::ok(locale.t("interests-fetched"))
+        .list(interests)
+        .into())
+}
+
+/// Pick the topics the caller is interested in, replacing earlier picks
+/// PUT /users/me/interests
+pub async fn update_interests(
+    locale: Locale,
+    auth_user: AuthUser,
+    topic_service: web::Data<TopicService>,
+    body: ValidatedJson<UpdateInterestsRequest>,
+) -> Result<HttpResponse, CustomError> {
+    let interests = topic_service
+        .set_interests(&auth_user.id, &body.topics)
+        .await?;
+
+    Ok(ApiResponse::ok(locale.t("interests-updated"))
+        .list(interests)
+        .into())
+}
@@ -0,0 +1,16 @@
+use super::controller::{discover, get_topic_posts, list_topics};
+use crate::middleware::auth::verify_token;
+use crate::middleware::limits::RequestTimeout;
+use actix_web::web;
+use actix_web_httpauth::middleware::HttpAuthentication;
+
+pub fn topic_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/topics")
+            .wrap(RequestTimeout::standard())
+            .wrap(HttpAuthentication::bearer(verify_token))
+            .route("", web::get().to(list_topics))
+            .route("/discover", web::get().to(discover))
+            .route("/{slug}/posts", web::get().to(get_topic_posts)),
+    );
+}
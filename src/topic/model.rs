@@ -0,0 +1,78 @@
+use crate::utils::datetime::bson_datetime;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+use validator::Validate;
+
+/// Topics a post can be tagged with
+pub const MAX_POST_TOPICS: usize = 5;
+/// Topics a user can follow as interests
+pub const MAX_INTERESTS: usize = 20;
+
+/// `#tag` in post content; the preceding character rules out HTML entities
+/// such as `&#39;` left by sanitizing
+static HASHTAG: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:^|[^&\w])#([A-Za-z][A-Za-z0-9_-]{1,31})").expect("valid hashtag regex")
+});
+
+/// Canonical slug of a topic name: lowercase, with `_` and spaces as `-`.
+/// `None` if nothing usable is left.
+pub fn normalize_topic(name: &str) -> Option<String> {
+    let slug: String = name
+        .trim()
+        .trim_start_matches('#')
+        .chars()
+        .filter_map(|c| match c {
+            'a'..='z' | '0'..='9' | '-' => Some(c),
+            'A'..='Z' => Some(c.to_ascii_lowercase()),
+            '_' | ' ' => Some('-'),
+            _ => None,
+        })
+        .collect();
+    let slug = slug.trim_matches('-').to_string();
+    (2..=32).contains(&slug.len()).then_some(slug)
+}
+
+/// Topics of a post: the ones picked explicitly, then hashtags in the
+/// content, deduplicated and capped
+pub fn post_topics(explicit: &[String], content: &str) -> Vec<String> {
+    let hashtags = HASHTAG
+        .captures_iter(content)
+        .filter_map(|captures| captures.get(1))
+        .map(|tag| tag.as_str());
+
+    let mut topics: Vec<String> = Vec::new();
+    for slug in explicit
+        .iter()
+        .map(String::as_str)
+        .chain(hashtags)
+        .filter_map(normalize_topic)
+    {
+        if topics.len() == MAX_POST_TOPICS {
+            break;
+        }
+        if !topics.contains(&slug) {
+            topics.push(slug);
+        }
+    }
+    topics
+}
+
+/// A topic, created the first time a post is tagged with it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Topic {
+    #[serde(rename = "_id")]
+    pub slug: String,
+    /// Posts tagged with the topic, copied from `Counter::TopicPosts`
+    pub post_count: i64,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// Request body for `PUT /users/me/interests`
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateInterestsRequest {
+    #[validate(length(max = 20, message = "must have at most 20 topics"))]
+    pub topics: Vec<String>,
+}
@@ -0,0 +1,254 @@
+use crate::counter::model::Counter;
+use crate::counter::service::CounterService;
+use crate::database::RedisService;
+use crate::post::post_model::Post;
+use crate::post::post_service::language_filter;
+use crate::topic::model::{MAX_INTERESTS, Topic, normalize_topic};
+use crate::user::model::User;
+use crate::utils::datetime::bson_now;
+use crate::utils::error::CustomError;
+use chrono::{Duration, Utc};
+use futures_util::TryStreamExt;
+use mongodb::bson::{self, Bson, doc, oid::ObjectId};
+use mongodb::{Client, Collection, IndexModel};
+use serde::Deserialize;
+use std::future::Future;
+
+/// How far back discovery looks for posts
+const DISCOVER_WINDOW_DAYS: i64 = 14;
+
+/// Only the interests of a user document
+#[derive(Debug, Deserialize)]
+struct UserInterests {
+    #[serde(default)]
+    interests: Vec<String>,
+}
+
+/// The topic counters handlers bump, so handler tests can ignore them.
+/// `TopicService` implements it by delegating to its inherent methods.
+pub trait TopicServiceTrait: Send + Sync + 'static {
+    fn on_post_created(&self, topics: &[String]) -> impl Future<Output = ()> + Send;
+}
+
+pub struct TopicService {
+    topics: Collection<Topic>,
+    posts: Collection<Post>,
+    users: Collection<User>,
+    counters: CounterService,
+}
+
+impl TopicService {
+    pub fn new(client: &Client, redis_service: RedisService) -> Self {
+        let db = client.database("rust_blogdb");
+        TopicService {
+            topics: db.collection::<Topic>("topics"),
+            posts: db.collection::<Post>("posts"),
+            users: db.collection::<User>("users"),
+            counters: CounterService::new(client, redis_service),
+        }
+    }
+
+    /// Popular topics, and posts by topic for discovery
+    #[tracing::instrument(skip_all)]
+    pub async fn ensure_indexes(&self) -> Result<(), CustomError> {
+        self.topics
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "post_count": -1 })
+                    .build(),
+            )
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to create topic indexes: {}", e))
+            })?;
+        self.posts
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "topics": 1, "created_at": -1 })
+                    .build(),
+            )
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to create topic indexes: {}", e))
+            })?;
+
+        Ok(())
+    }
+
+    /// Count a new post towards its topics, creating unknown ones. Topic
+    /// counters are derived data, so failures are only logged.
+    #[tracing::instrument(skip_all)]
+    pub async fn on_post_created(&self, topics: &[String]) {
+        for slug in topics {
+            if let Err(e) = self
+                .topics
+                .update_one(
+                    doc! { "_id": slug },
+                    doc! { "$setOnInsert": { "post_count": 0, "created_at": bson_now() } },
+                )
+                .upsert(true)
+                .await
+            {
+                log::warn!("Failed to record topic {}: {}", slug, e);
+                continue;
+            }
+            if let Err(e) = self.counters.incr(Counter::TopicPosts, slug).await {
+                log::warn!("Failed to count topic {}: {}", slug, e);
+            }
+        }
+    }
+
+    /// Most used topics, for onboarding and browsing
+    #[tracing::instrument(skip_all)]
+    pub async fn popular(&self, limit: i64) -> Result<Vec<Topic>, CustomError> {
+        self.topics
+            .find(doc! {})
+            .sort(doc! { "post_count": -1, "_id": 1 })
+            .limit(limit)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn interests(&self, user_id: &ObjectId) -> Result<Vec<String>, CustomError> {
+        let user = self
+            .users
+            .clone_with_type::<UserInterests>()
+            .find_one(doc! { "_id": user_id })
+            .projection(doc! { "interests": 1 })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        Ok(user.map(|user| user.interests).unwrap_or_default())
+    }
+
+    /// Replace a user's interests; every topic must already exist
+    #[tracing::instrument(skip_all)]
+    pub async fn set_interests(
+        &self,
+        user_id: &ObjectId,
+        topics: &[String],
+    ) -> Result<Vec<String>, CustomError> {
+        let mut slugs: Vec<String> = Vec::new();
+        for topic in topics {
+            let slug = normalize_topic(topic)
+                .ok_or_else(|| CustomError::BadRequestError(format!("Invalid topic {}", topic)))?;
+            if !slugs.contains(&slug) {
+                slugs.push(slug);
+            }
+        }
+        slugs.truncate(MAX_INTERESTS);
+
+        let known = self
+            .topics
+            .count_documents(doc! { "_id": { "$in": slugs.clone() } })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+        if known as usize != slugs.len() {
+            return Err(CustomError::BadRequestError(
+                "Unknown topics in interests".to_string(),
+            ));
+        }
+
+        self.users
+            .update_one(
+                doc! { "_id": user_id },
+                doc! { "$set": { "interests": slugs.clone(), "updated_at": bson_now() } },
+            )
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        Ok(slugs)
+    }
+
+    /// Newest profile posts tagged with a topic, leaving out `hidden_authors`
+    /// and, when `languages` is given, posts known to be in other languages
+    #[tracing::instrument(skip_all)]
+    pub async fn posts(
+        &self,
+        slug: &str,
+        hidden_authors: &[ObjectId],
+        languages: &[String],
+        limit: i64,
+    ) -> Result<Vec<Post>, CustomError> {
+        let mut filter = doc! {
+            "topics": slug,
+            "author_id": { "$nin": hidden_authors },
+            "group_id": Bson::Null,
+            "deleted_at": Bson::Null,
+            "hidden_at": Bson::Null,
+        };
+        if let Some(languages) = language_filter(languages) {
+            filter.insert("language", languages);
+        }
+
+        self.posts
+            .find(filter)
+            .sort(doc! { "created_at": -1 })
+            .limit(limit)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))
+    }
+
+    /// Recent profile posts by others ranked by how many of the user's
+    /// interests they share, newest first among equals. Users without
+    /// interests get the newest posts. `languages` filters as in `posts`.
+    #[tracing::instrument(skip_all)]
+    pub async fn discover(
+        &self,
+        user_id: &ObjectId,
+        hidden_authors: &[ObjectId],
+        languages: &[String],
+        limit: i64,
+    ) -> Result<Vec<Post>, CustomError> {
+        let interests = self.interests(user_id).await?;
+        let since = bson::DateTime::from_chrono(Utc::now() - Duration::days(DISCOVER_WINDOW_DAYS));
+
+        let mut excluded = hidden_authors.to_vec();
+        excluded.push(*user_id);
+        let mut filter = doc! {
+            "author_id": { "$nin": excluded },
+            "group_id": Bson::Null,
+            "deleted_at": Bson::Null,
+            "hidden_at": Bson::Null,
+            "created_at": { "$gte": since },
+        };
+        if !interests.is_empty() {
+            filter.insert("topics", doc! { "$in": interests.clone() });
+        }
+        if let Some(languages) = language_filter(languages) {
+            filter.insert("language", languages);
+        }
+
+        let pipeline = vec![
+            doc! { "$match": filter },
+            doc! { "$addFields": { "relevance": {
+                "$size": { "$setIntersection": [{ "$ifNull": ["$topics", []] }, interests] }
+            } } },
+            doc! { "$sort": { "relevance": -1, "created_at": -1 } },
+            doc! { "$limit": limit },
+            doc! { "$project": { "relevance": 0 } },
+        ];
+
+        self.posts
+            .aggregate(pipeline)
+            .with_type::<Post>()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))
+    }
+}
+
+impl TopicServiceTrait for TopicService {
+    async fn on_post_created(&self, topics: &[String]) {
+        Self::on_post_created(self, topics).await
+    }
+}
@@ -1,18 +1,26 @@
-use actix_multipart::Multipart;
-use actix_web::{HttpResponse, Responder};
+use actix_multipart::{Field, Multipart};
+use actix_web::{HttpResponse, web};
+use chrono::Utc;
 use futures_util::StreamExt;
+use mongodb::bson::oid::ObjectId;
 use serde::Serialize;
 use serde_json::json;
 
-use crate::utils::uploads::{FileUpload, FileValidator, UploadService};
+use crate::middleware::auth::AuthUser;
+use crate::middleware::limits::HttpLimits;
+use crate::uploader::model::MediaUpload;
+use crate::uploader::service::MediaService;
+use crate::utils::config::AppConfig;
+use crate::utils::error::{CustomError, ErrorCode};
+use crate::utils::i18n::Locale;
+use crate::utils::response::ApiResponse;
+use crate::utils::sanitize::{Markup, sanitize};
+use crate::utils::uploads::{CloudinaryUploadResponse, FileUpload, FileValidator, UploadService};
 
-/// Response for single file upload
-#[derive(Debug, Serialize)]
-pub struct SingleUploadResponse {
-    pub success: bool,
-    pub message: String,
-    pub data: Option<UploadData>,
-}
+/// Longest accepted `alt_text` form field
+const MAX_ALT_TEXT_BYTES: usize = 1000;
+/// Longest accepted `caption` form field
+const MAX_CAPTION_BYTES: usize = 2000;
 
 /// Upload data returned after successful upload
 #[derive(Debug, Serialize)]
@@ -24,17 +32,54 @@ pub struct UploadData {
     pub width: Option<u32>,
     pub height: Option<u32>,
     pub bytes: u64,
+    /// Echoes the form's `is_sensitive` field so clients blur the media
+    pub is_sensitive: bool,
+    /// Description read out by screen readers
+    pub alt_text: Option<String>,
+    pub caption: Option<String>,
+}
+
+impl UploadData {
+    /// Describe the `index`th file of the form
+    fn new(response: CloudinaryUploadResponse, form: &UploadForm, index: usize) -> Self {
+        UploadData {
+            public_id: response.public_id,
+            url: response.url,
+            secure_url: response.secure_url,
+            format: response.format,
+            width: response.width,
+            height: response.height,
+            bytes: response.bytes,
+            is_sensitive: form.is_sensitive,
+            alt_text: description(form.alt_texts.get(index)),
+            caption: description(form.captions.get(index)),
+        }
+    }
+
+    /// Record of the upload, so the owner can attach it to posts
+    fn record(&self, owner_id: ObjectId) -> MediaUpload {
+        MediaUpload {
+            id: None,
+            owner_id,
+            public_id: self.public_id.clone(),
+            secure_url: self.secure_url.clone(),
+            width: self.width,
+            height: self.height,
+            is_sensitive: self.is_sensitive,
+            alt_text: self.alt_text.clone(),
+            caption: self.caption.clone(),
+            created_at: Utc::now(),
+        }
+    }
 }
 
-/// Response for multiple file upload
+/// Summary returned after a multiple file upload
 #[derive(Debug, Serialize)]
-pub struct MultipleUploadResponse {
-    pub success: bool,
-    pub message: String,
+pub struct MultipleUploadSummary {
     pub total_files: usize,
     pub successful_uploads: usize,
     pub failed_uploads: usize,
-    pub data: Vec<MultipleUploadData>,
+    pub files: Vec<MultipleUploadData>,
 }
 
 #[derive(Debug, Serialize)]
@@ -45,12 +90,52 @@ pub struct MultipleUploadData {
     pub error: Option<String>,
 }
 
+/// Files and flags read from an upload form
+pub struct UploadForm {
+    pub files: Vec<FileUpload>,
+    /// Set by an `is_sensitive=true` form field
+    pub is_sensitive: bool,
+    /// `alt_text` fields; the nth one describes the nth file
+    pub alt_texts: Vec<String>,
+    /// `caption` fields; the nth one belongs to the nth file
+    pub captions: Vec<String>,
+}
+
 /// Helper function to extract files from multipart form
-async fn extract_files_from_multipart(mut payload: Multipart) -> Result<Vec<FileUpload>, String> {
+/// Fails once the combined size of all files exceeds `max_bytes`
+pub async fn extract_files_from_multipart(
+    payload: Multipart,
+    max_bytes: usize,
+) -> Result<Vec<FileUpload>, CustomError> {
+    extract_upload_form(payload, max_bytes)
+        .await
+        .map(|form| form.files)
+}
+
+/// The error for an upload over `max_bytes`
+fn upload_too_large(max_bytes: usize) -> CustomError {
+    CustomError::coded(
+        ErrorCode::UploadTooLarge,
+        format!("Upload exceeds the maximum size of {} bytes", max_bytes),
+    )
+    .with_details(json!({ "max_bytes": max_bytes }))
+}
+
+/// Like `extract_files_from_multipart`, also reading the form's flags
+pub async fn extract_upload_form(
+    mut payload: Multipart,
+    max_bytes: usize,
+) -> Result<UploadForm, CustomError> {
     let mut files = Vec::new();
+    let mut is_sensitive = false;
+    let mut alt_texts = Vec::new();
+    let mut captions = Vec::new();
+    let mut total_bytes = 0;
 
     while let Some(item) = payload.next().await {
-        let mut field = item.map_err(|e| format!("Error reading multipart field: {}", e))?;
+        let mut field = item.map_err(|e| {
+            CustomError::BadRequestError(format!("Error reading multipart field: {}", e))
+        })?;
 
         // Get content disposition - skip if not present
         let content_disposition = match field.content_disposition() {
@@ -71,187 +156,181 @@ async fn extract_files_from_multipart(mut payload: Multipart) -> Result<Vec<File
 
             let mut data = Vec::new();
             while let Some(chunk) = field.next().await {
-                let chunk = chunk.map_err(|e| format!("Error reading file chunk: {}", e))?;
+                let chunk = chunk.map_err(|e| {
+                    CustomError::BadRequestError(format!("Error reading file chunk: {}", e))
+                })?;
+                total_bytes += chunk.len();
+                if total_bytes > max_bytes {
+                    return Err(upload_too_large(max_bytes));
+                }
                 data.extend_from_slice(&chunk);
             }
 
             if !data.is_empty() {
                 files.push(FileUpload::new(file_name, data, content_type));
             }
+        } else if field_name == "is_sensitive" {
+            let value = read_text_field(&mut field, "is_sensitive", 16)
+                .await
+                .map_err(CustomError::BadRequestError)?;
+            is_sensitive = matches!(value.trim(), "true" | "1");
+        } else if field_name == "alt_text" {
+            alt_texts.push(
+                read_text_field(&mut field, "alt_text", MAX_ALT_TEXT_BYTES)
+                    .await
+                    .map_err(CustomError::BadRequestError)?,
+            );
+        } else if field_name == "caption" {
+            captions.push(
+                read_text_field(&mut field, "caption", MAX_CAPTION_BYTES)
+                    .await
+                    .map_err(CustomError::BadRequestError)?,
+            );
+        }
+    }
+
+    Ok(UploadForm {
+        files,
+        is_sensitive,
+        alt_texts,
+        captions,
+    })
+}
+
+/// Read a text form field of at most `max_bytes`
+async fn read_text_field(
+    field: &mut Field,
+    name: &str,
+    max_bytes: usize,
+) -> Result<String, String> {
+    let mut value = Vec::new();
+    while let Some(chunk) = field.next().await {
+        let chunk = chunk.map_err(|e| format!("Error reading form field: {}", e))?;
+        value.extend_from_slice(&chunk);
+        if value.len() > max_bytes {
+            return Err(format!("{} must be at most {} bytes", name, max_bytes));
         }
     }
+    String::from_utf8(value).map_err(|_| format!("{} must be valid UTF-8", name))
+}
 
-    Ok(files)
+/// Strip markup from a description, treating a blank one as absent
+fn description(value: Option<&String>) -> Option<String> {
+    value
+        .map(|value| sanitize(value, Markup::None))
+        .filter(|value| !value.trim().is_empty())
 }
 
 /// Upload a single file
 /// POST /upload/single
-pub async fn upload_single(payload: Multipart) -> impl Responder {
+pub async fn upload_single(
+    locale: Locale,
+    auth_user: AuthUser,
+    media_service: web::Data<MediaService>,
+    payload: Multipart,
+    limits: web::Data<HttpLimits>,
+    config: web::Data<AppConfig>,
+) -> Result<HttpResponse, CustomError> {
     // Extract files from multipart
-    let files = match extract_files_from_multipart(payload).await {
-        Ok(f) => f,
-        Err(e) => {
-            return HttpResponse::BadRequest().json(json!({
-                "success": false,
-                "message": e,
-                "data": null
-            }));
-        }
-    };
+    let mut form = extract_upload_form(payload, limits.upload_limit).await?;
+    let files = std::mem::take(&mut form.files);
 
     // Check if file was provided
     if files.is_empty() {
-        return HttpResponse::BadRequest().json(json!({
-            "success": false,
-            "message": "No file provided",
-            "data": null
-        }));
+        return Err(CustomError::BadRequestError(locale.t("upload-no-file")));
     }
 
     // Get the first file
     let file = files.into_iter().next().unwrap();
 
     // Create upload service
-    let upload_service = match UploadService::new() {
-        Ok(s) => s,
-        Err(e) => {
-            return HttpResponse::InternalServerError().json(json!({
-                "success": false,
-                "message": format!("Upload service error: {}", e),
-                "data": null
-            }));
-        }
-    };
+    let upload_service = UploadService::with_config(config.cloudinary.clone());
 
     // Create validator for images
     let validator = FileValidator::images();
+    if file.size() > validator.max_file_size {
+        return Err(upload_too_large(validator.max_file_size));
+    }
 
     // Upload the file
-    match upload_service
+    let response = upload_service
         .upload_single_file(file, Some("uploads"), &validator)
         .await
-    {
-        Ok(response) => HttpResponse::Ok().json(SingleUploadResponse {
-            success: true,
-            message: "File uploaded successfully".to_string(),
-            data: Some(UploadData {
-                public_id: response.public_id,
-                url: response.url,
-                secure_url: response.secure_url,
-                format: response.format,
-                width: response.width,
-                height: response.height,
-                bytes: response.bytes,
-            }),
-        }),
-        Err(e) => HttpResponse::BadRequest().json(json!({
-            "success": false,
-            "message": e,
-            "data": null
-        })),
-    }
+        .map_err(CustomError::BadRequestError)?;
+
+    let data = UploadData::new(response, &form, 0);
+    media_service.record(data.record(auth_user.id)).await;
+
+    Ok(ApiResponse::created("File uploaded successfully")
+        .data(data)
+        .into())
 }
 
 /// Upload multiple files
 /// POST /upload/multiple
-pub async fn upload_multiple(payload: Multipart) -> impl Responder {
+pub async fn upload_multiple(
+    locale: Locale,
+    auth_user: AuthUser,
+    media_service: web::Data<MediaService>,
+    payload: Multipart,
+    limits: web::Data<HttpLimits>,
+    config: web::Data<AppConfig>,
+) -> Result<HttpResponse, CustomError> {
     // Extract files from multipart
-    let files = match extract_files_from_multipart(payload).await {
-        Ok(f) => f,
-        Err(e) => {
-            return HttpResponse::BadRequest().json(json!({
-                "success": false,
-                "message": e,
-                "total_files": 0,
-                "successful_uploads": 0,
-                "failed_uploads": 0,
-                "data": []
-            }));
-        }
-    };
+    let mut form = extract_upload_form(payload, limits.upload_limit).await?;
+    let files = std::mem::take(&mut form.files);
 
     // Check if files were provided
     if files.is_empty() {
-        return HttpResponse::BadRequest().json(json!({
-            "success": false,
-            "message": "No files provided",
-            "total_files": 0,
-            "successful_uploads": 0,
-            "failed_uploads": 0,
-            "data": []
-        }));
+        return Err(CustomError::BadRequestError(locale.t("upload-no-files")));
     }
 
     let total_files = files.len();
 
     // Create upload service
-    let upload_service = match UploadService::new() {
-        Ok(s) => s,
-        Err(e) => {
-            return HttpResponse::InternalServerError().json(json!({
-                "success": false,
-                "message": format!("Upload service error: {}", e),
-                "total_files": total_files,
-                "successful_uploads": 0,
-                "failed_uploads": total_files,
-                "data": []
-            }));
-        }
-    };
+    let upload_service = UploadService::with_config(config.cloudinary.clone());
 
     // Create validator for images
     let validator = FileValidator::images();
 
     // Upload all files
-    match upload_service
+    let results = upload_service
         .upload_multiple_files(files, Some("uploads"), &validator)
         .await
-    {
-        Ok(results) => {
-            let successful_uploads = results.iter().filter(|r| r.success).count();
-            let failed_uploads = results.iter().filter(|r| !r.success).count();
-
-            let data: Vec<MultipleUploadData> = results
-                .into_iter()
-                .map(|r| MultipleUploadData {
-                    file_name: r.file_name,
-                    success: r.success,
-                    data: r.response.map(|resp| UploadData {
-                        public_id: resp.public_id,
-                        url: resp.url,
-                        secure_url: resp.secure_url,
-                        format: resp.format,
-                        width: resp.width,
-                        height: resp.height,
-                        bytes: resp.bytes,
-                    }),
-                    error: r.error,
-                })
-                .collect();
-
-            HttpResponse::Ok().json(MultipleUploadResponse {
-                success: failed_uploads == 0,
-                message: if failed_uploads == 0 {
-                    "All files uploaded successfully".to_string()
-                } else {
-                    format!(
-                        "{} of {} files uploaded successfully",
-                        successful_uploads, total_files
-                    )
-                },
-                total_files,
-                successful_uploads,
-                failed_uploads,
-                data,
-            })
+        .map_err(CustomError::BadRequestError)?;
+
+    let successful_uploads = results.iter().filter(|r| r.success).count();
+    let failed_uploads = results.iter().filter(|r| !r.success).count();
+
+    let mut data: Vec<MultipleUploadData> = Vec::with_capacity(results.len());
+    for (index, r) in results.into_iter().enumerate() {
+        let upload = r.response.map(|resp| UploadData::new(resp, &form, index));
+        if let Some(upload) = &upload {
+            media_service.record(upload.record(auth_user.id)).await;
         }
-        Err(e) => HttpResponse::BadRequest().json(json!({
-            "success": false,
-            "message": e,
-            "total_files": total_files,
-            "successful_uploads": 0,
-            "failed_uploads": total_files,
-            "data": []
-        })),
+        data.push(MultipleUploadData {
+            file_name: r.file_name,
+            success: r.success,
+            data: upload,
+            error: r.error,
+        });
     }
+
+    let message = if failed_uploads == 0 {
+        "All files uploaded successfully".to_string()
+    } else {
+        format!(
+            "{} of {} files uploaded successfully",
+            successful_uploads, total_files
+        )
+    };
+
+    Ok(ApiResponse::ok(message)
+        .data(MultipleUploadSummary {
+            total_files,
+            successful_uploads,
+            failed_uploads,
+            files: data,
+        })
+        .into())
 }
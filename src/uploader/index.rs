@@ -1,9 +1,14 @@
 use super::controller::{upload_multiple, upload_single};
+use crate::middleware::auth::verify_token;
+use crate::middleware::limits::RequestTimeout;
 use actix_web::web;
+use actix_web_httpauth::middleware::HttpAuthentication;
 
 pub fn upload_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/upload")
+            .wrap(RequestTimeout::uploads())
+            .wrap(HttpAuthentication::bearer(verify_token))
             .route("/single", web::post().to(upload_single))
             .route("/multiple", web::post().to(upload_multiple)),
     );
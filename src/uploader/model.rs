@@ -0,0 +1,27 @@
+use crate::utils::datetime::bson_datetime;
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+/// A file a user uploaded, kept so posts can only reference the author's
+/// own uploads
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MediaUpload {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub owner_id: ObjectId,
+    /// Cloudinary public id, which clients use to reference the upload
+    pub public_id: String,
+    pub secure_url: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    #[serde(default)]
+    pub is_sensitive: bool,
+    /// Defaults for posts that attach the upload without their own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alt_text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub caption: Option<String>,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
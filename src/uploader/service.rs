@@ -0,0 +1,102 @@
+use crate::uploader::model::MediaUpload;
+use crate::utils::error::CustomError;
+use futures_util::TryStreamExt;
+use mongodb::bson::{doc, oid::ObjectId};
+use mongodb::options::IndexOptions;
+use mongodb::{Client, Collection, IndexModel};
+use std::future::Future;
+
+/// The upload lookups post handlers make, so they can be tested against a
+/// mock. `MediaService` implements it by delegating to its inherent methods.
+pub trait MediaServiceTrait: Send + Sync + 'static {
+    fn owned_by(
+        &self,
+        owner_id: &ObjectId,
+        public_ids: &[String],
+    ) -> impl Future<Output = Result<Vec<MediaUpload>, CustomError>> + Send;
+}
+
+/// Who uploaded which file
+pub struct MediaService {
+    uploads: Collection<MediaUpload>,
+}
+
+impl MediaService {
+    pub fn new(client: &Client) -> Self {
+        let db = client.database("rust_blogdb");
+        MediaService {
+            uploads: db.collection::<MediaUpload>("uploads"),
+        }
+    }
+
+    /// Uploads are looked up by public id, always together with their owner
+    #[tracing::instrument(skip_all)]
+    pub async fn ensure_indexes(&self) -> Result<(), CustomError> {
+        self.uploads
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "public_id": 1 })
+                    .options(IndexOptions::builder().unique(true).build())
+                    .build(),
+            )
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to create upload indexes: {}", e))
+            })?;
+
+        Ok(())
+    }
+
+    /// Remember an upload's owner. The file is already stored, so a failure
+    /// only means it can't be attached to posts, and just gets logged.
+    #[tracing::instrument(skip_all)]
+    pub async fn record(&self, upload: MediaUpload) {
+        if let Err(e) = self.uploads.insert_one(upload).await {
+            log::warn!("Failed to record upload: {}", e);
+        }
+    }
+
+    /// The given uploads in the order asked for, failing unless every one
+    /// exists and belongs to `owner_id`
+    #[tracing::instrument(skip_all)]
+    pub async fn owned_by(
+        &self,
+        owner_id: &ObjectId,
+        public_ids: &[String],
+    ) -> Result<Vec<MediaUpload>, CustomError> {
+        let uploads: Vec<MediaUpload> = self
+            .uploads
+            .find(doc! { "owner_id": owner_id, "public_id": { "$in": public_ids } })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        public_ids
+            .iter()
+            .map(|public_id| {
+                uploads
+                    .iter()
+                    .find(|upload| upload.public_id == *public_id)
+                    .cloned()
+                    .ok_or_else(|| {
+                        CustomError::BadRequestError(format!(
+                            "Image {} is not one of your uploads",
+                            public_id
+                        ))
+                    })
+            })
+            .collect()
+    }
+}
+
+impl MediaServiceTrait for MediaService {
+    async fn owned_by(
+        &self,
+        owner_id: &ObjectId,
+        public_ids: &[String],
+    ) -> Result<Vec<MediaUpload>, CustomError> {
+        Self::owned_by(self, owner_id, public_ids).await
+    }
+}
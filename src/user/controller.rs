@@ -1,83 +1,250 @@
-use crate::database::RedisService;
-use crate::middleware::auth::{get_user_id_from_request, invalidate_session};
-use crate::user::model::{CreateUserRequest, ResendOtpRequest, VerifyEmailRequest};
-use crate::user::service::UserService;
-use crate::utils::error::CustomError;
+use crate::access_token::service::AccessTokenService;
+use crate::badge::service::BadgeService;
+use crate::comment::service::CommentServiceTrait;
+use crate::counter::service::CounterService;
+use crate::database::{RedisService, RedisServiceTrait};
+use crate::fingerprint::controller::client_fingerprint;
+use crate::fingerprint::model::FingerprintEvent;
+use crate::fingerprint::service::{FingerprintService, FingerprintServiceTrait};
+use crate::friend::controller::{DEFAULT_SUGGESTIONS, update_friend_counts};
+use crate::friend::service::FriendService;
+use crate::insights::service::InsightsService;
+use crate::middleware::auth::{
+    AuthUser, consume_refresh_token, get_user_id_from_request, invalidate_session,
+};
+use crate::middleware::rate_limit::{
+    AUTH_RATE_LIMIT, AUTH_RATE_WINDOW_SECONDS, check_rate_limit, client_ip,
+};
+use crate::post::post_service::PostServiceTrait;
+use crate::user::dto::{ProfileDto, UserDto};
+use crate::user::model::{
+    ChangePasswordRequest, CreateUserRequest, DeleteAccountRequest, ForgotPasswordRequest,
+    ProfileView, RefreshTokenRequest, ResendOtpRequest, ResetPasswordRequest, UpdateMeRequest,
+    VerifyEmailRequest, VerifyPhoneRequest,
+};
+use crate::user::service::UserServiceTrait;
+use crate::utils::config::AppConfig;
+use crate::utils::error::{CustomError, ErrorCode};
+use crate::utils::i18n::Locale;
 use crate::utils::model::LoginRequests;
+use crate::utils::qr::{DEFAULT_QR_SIZE, QrFormat, render_qr};
+use crate::utils::response::ApiResponse;
+use crate::utils::uploads::UploadService;
+use crate::utils::validation::ValidatedJson;
+use actix_web::http::header;
 use actix_web::{HttpRequest, HttpResponse, web};
+use mongodb::bson::oid::ObjectId;
+use serde::Deserialize;
+use serde_json::json;
 
-pub async fn register_user(
-    user_service: web::Data<UserService>,
-    user_info: web::Json<CreateUserRequest>,
+/// How long the viewer-independent part of a profile stays cached
+const PROFILE_CACHE_SECONDS: u64 = 60;
+
+#[derive(Debug, Deserialize)]
+pub struct QrQuery {
+    #[serde(default)]
+    pub format: QrFormat,
+    pub size: Option<u32>,
+}
+
+pub fn profile_cache_key(user_id: &ObjectId) -> String {
+    format!("profile:{}", user_id.to_hex())
+}
+
+pub async fn register_user<U: UserServiceTrait>(
+    locale: Locale,
+    req: HttpRequest,
+    user_service: web::Data<U>,
+    redis_service: web::Data<RedisService>,
+    fingerprint_service: web::Data<FingerprintService>,
+    user_info: ValidatedJson<CreateUserRequest>,
 ) -> Result<HttpResponse, CustomError> {
+    check_rate_limit(
+        redis_service.get_ref(),
+        &format!("auth:register:{}", client_ip(&req)),
+        AUTH_RATE_LIMIT,
+        AUTH_RATE_WINDOW_SECONDS,
+    )
+    .await?;
+
     let user_id = user_service
         .create_user(
             user_info.username.clone(),
             user_info.email.clone(),
             user_info.password.clone(),
             user_info.phone_number.clone(),
+            user_info.locale.unwrap_or(locale),
         )
         .await
         .map_err(|arg0| arg0)?;
+    fingerprint_service
+        .record(
+            &user_id,
+            FingerprintEvent::Registration,
+            &client_fingerprint(&req),
+        )
+        .await;
 
-    Ok(HttpResponse::Ok().json(serde_json::json!({
-        "success": true,
-        "message": "User created successfully. Please check your email for verification code.",
-        "httpStatusCode": 200,
-        "service": std::env::var("SERVICE_NAME").unwrap_or_else(|_| "Unknown".to_string()),
-        "user_id": user_id.to_hex()
-    })))
+    Ok(ApiResponse::created(locale.t("user-registered"))
+        .data(json!({ "user_id": user_id.to_hex() }))
+        .into())
 }
 
-pub async fn verify_email(
-    user_service: web::Data<UserService>,
-    body: web::Json<VerifyEmailRequest>,
+pub async fn verify_email<U: UserServiceTrait>(
+    locale: Locale,
+    req: HttpRequest,
+    user_service: web::Data<U>,
+    badge_service: web::Data<BadgeService>,
+    friend_service: web::Data<FriendService>,
+    redis_service: web::Data<RedisService>,
+    body: ValidatedJson<VerifyEmailRequest>,
 ) -> Result<HttpResponse, CustomError> {
-    user_service
+    check_rate_limit(
+        redis_service.get_ref(),
+        &format!("auth:verify:{}", client_ip(&req)),
+        AUTH_RATE_LIMIT,
+        AUTH_RATE_WINDOW_SECONDS,
+    )
+    .await?;
+
+    let user_id = user_service
         .verify_email(&body.email, &body.otp_code)
         .await?;
+    badge_service.on_email_verified(&user_id).await;
 
-    Ok(HttpResponse::Ok().json(serde_json::json!({
-        "success": true,
-        "message": "Email verified successfully. You can now login.",
-        "httpStatusCode": 200,
-        "service": std::env::var("SERVICE_NAME").unwrap_or_else(|_| "Unknown".to_string())
-    })))
+    // Give the new account people to connect with; verification has already
+    // succeeded, so a failure here only leaves the list empty
+    let suggestions = friend_service
+        .suggestions(&user_id, DEFAULT_SUGGESTIONS)
+        .await
+        .unwrap_or_else(|e| {
+            log::warn!("Failed to build suggestions for {}: {}", user_id, e);
+            Vec::new()
+        });
+
+    Ok(ApiResponse::ok(locale.t("email-verified"))
+        .data(json!({ "suggestions": suggestions }))
+        .into())
 }
 
-pub async fn resend_otp(
-    user_service: web::Data<UserService>,
-    body: web::Json<ResendOtpRequest>,
+pub async fn resend_otp<U: UserServiceTrait>(
+    locale: Locale,
+    req: HttpRequest,
+    user_service: web::Data<U>,
+    redis_service: web::Data<RedisService>,
+    body: ValidatedJson<ResendOtpRequest>,
 ) -> Result<HttpResponse, CustomError> {
+    check_rate_limit(
+        redis_service.get_ref(),
+        &format!("auth:resend:{}", client_ip(&req)),
+        AUTH_RATE_LIMIT,
+        AUTH_RATE_WINDOW_SECONDS,
+    )
+    .await?;
+
     user_service.resend_otp(&body.email).await?;
 
-    Ok(HttpResponse::Ok().json(serde_json::json!({
-        "success": true,
-        "message": "Verification code sent to your email.",
-        "httpStatusCode": 200,
-        "service": std::env::var("SERVICE_NAME").unwrap_or_else(|_| "Unknown".to_string())
-    })))
+    Ok(ApiResponse::ok(locale.t("otp-resent")).into())
 }
 
-pub async fn login_user(
-    user_service: web::Data<UserService>,
+pub async fn send_phone_otp<U: UserServiceTrait>(
+    locale: Locale,
+    req: HttpRequest,
+    user_service: web::Data<U>,
+    redis_service: web::Data<RedisService>,
+    body: ValidatedJson<ResendOtpRequest>,
+) -> Result<HttpResponse, CustomError> {
+    check_rate_limit(
+        redis_service.get_ref(),
+        &format!("auth:phone-otp:{}", client_ip(&req)),
+        AUTH_RATE_LIMIT,
+        AUTH_RATE_WINDOW_SECONDS,
+    )
+    .await?;
+
+    user_service.send_phone_otp(&body.email).await?;
+
+    Ok(ApiResponse::ok(locale.t("phone-otp-sent")).into())
+}
+
+pub async fn verify_phone<U: UserServiceTrait>(
+    locale: Locale,
+    req: HttpRequest,
+    user_service: web::Data<U>,
     redis_service: web::Data<RedisService>,
-    login_info: web::Json<LoginRequests>,
+    body: ValidatedJson<VerifyPhoneRequest>,
 ) -> Result<HttpResponse, CustomError> {
-    let token = user_service
-        .login_fn(login_info.into_inner(), Some(redis_service.get_ref()))
+    check_rate_limit(
+        redis_service.get_ref(),
+        &format!("auth:verify-phone:{}", client_ip(&req)),
+        AUTH_RATE_LIMIT,
+        AUTH_RATE_WINDOW_SECONDS,
+    )
+    .await?;
+
+    user_service
+        .verify_phone(&body.email, &body.otp_code)
         .await?;
 
-    Ok(HttpResponse::Ok().json(serde_json::json!({
-        "success": true,
-        "message": "Login successful",
-        "httpStatusCode": 200,
-        "service": std::env::var("SERVICE_NAME").unwrap_or_else(|_| "Unknown".to_string()),
-        "token": token
-    })))
+    Ok(ApiResponse::ok(locale.t("phone-verified")).into())
+}
+
+pub async fn login_user<U: UserServiceTrait, R: RedisServiceTrait, F: FingerprintServiceTrait>(
+    locale: Locale,
+    req: HttpRequest,
+    user_service: web::Data<U>,
+    redis_service: web::Data<R>,
+    fingerprint_service: web::Data<F>,
+    login_info: ValidatedJson<LoginRequests>,
+) -> Result<HttpResponse, CustomError> {
+    check_rate_limit(
+        redis_service.get_ref(),
+        &format!("auth:login:{}", client_ip(&req)),
+        AUTH_RATE_LIMIT,
+        AUTH_RATE_WINDOW_SECONDS,
+    )
+    .await?;
+
+    let (user_id, tokens) = user_service.login_fn(login_info.into_inner()).await?;
+    fingerprint_service
+        .record(&user_id, FingerprintEvent::Login, &client_fingerprint(&req))
+        .await;
+
+    Ok(ApiResponse::ok(locale.t("login-successful"))
+        .data(tokens)
+        .into())
+}
+
+/// Trade a refresh token for a new access and refresh token; the old
+/// refresh token stops working
+/// POST /auth/user/refresh
+pub async fn refresh_token<U: UserServiceTrait>(
+    locale: Locale,
+    req: HttpRequest,
+    user_service: web::Data<U>,
+    redis_service: web::Data<RedisService>,
+    body: ValidatedJson<RefreshTokenRequest>,
+) -> Result<HttpResponse, CustomError> {
+    check_rate_limit(
+        redis_service.get_ref(),
+        &format!("auth:refresh:{}", client_ip(&req)),
+        AUTH_RATE_LIMIT,
+        AUTH_RATE_WINDOW_SECONDS,
+    )
+    .await?;
+
+    let user_id = consume_refresh_token(&body.refresh_token, redis_service.get_ref()).await?;
+    let tokens = user_service
+        .refresh_session(&user_id, redis_service.get_ref())
+        .await?;
+
+    Ok(ApiResponse::ok(locale.t("token-refreshed"))
+        .data(tokens)
+        .into())
 }
 
 pub async fn logout_user(
+    locale: Locale,
     req: HttpRequest,
     redis_service: web::Data<RedisService>,
 ) -> Result<HttpResponse, CustomError> {
@@ -90,10 +257,579 @@ pub async fn logout_user(
         .await
         .map_err(|_| CustomError::InternalServerError("Failed to logout".to_string()))?;
 
-    Ok(HttpResponse::Ok().json(serde_json::json!({
-        "success": true,
-        "message": "Logged out successfully",
-        "httpStatusCode": 200,
-        "service": std::env::var("SERVICE_NAME").unwrap_or_else(|_| "Unknown".to_string())
-    })))
+    Ok(ApiResponse::ok(locale.t("logout-successful")).into())
+}
+
+/// Email a password reset token. Responds the same whether or not the
+/// email has an account.
+/// POST /auth/user/forgot-password
+pub async fn forgot_password<U: UserServiceTrait>(
+    locale: Locale,
+    req: HttpRequest,
+    user_service: web::Data<U>,
+    redis_service: web::Data<RedisService>,
+    body: ValidatedJson<ForgotPasswordRequest>,
+) -> Result<HttpResponse, CustomError> {
+    check_rate_limit(
+        redis_service.get_ref(),
+        &format!("auth:forgot:{}", client_ip(&req)),
+        AUTH_RATE_LIMIT,
+        AUTH_RATE_WINDOW_SECONDS,
+    )
+    .await?;
+
+    user_service.request_password_reset(&body.email).await?;
+
+    Ok(ApiResponse::ok(locale.t("password-reset-requested")).into())
+}
+
+/// Set a new password with an emailed token and sign out everywhere
+/// POST /auth/user/reset-password
+pub async fn reset_password<U: UserServiceTrait>(
+    locale: Locale,
+    req: HttpRequest,
+    user_service: web::Data<U>,
+    redis_service: web::Data<RedisService>,
+    token_service: web::Data<AccessTokenService>,
+    body: ValidatedJson<ResetPasswordRequest>,
+) -> Result<HttpResponse, CustomError> {
+    check_rate_limit(
+        redis_service.get_ref(),
+        &format!("auth:reset:{}", client_ip(&req)),
+        AUTH_RATE_LIMIT,
+        AUTH_RATE_WINDOW_SECONDS,
+    )
+    .await?;
+
+    let user_id = user_service
+        .reset_password(&body.token, &body.new_password)
+        .await?;
+    redis_service
+        .invalidate_all_sessions(&user_id.to_hex())
+        .await
+        .map_err(CustomError::InternalServerError)?;
+    token_service.revoke_all_for_user(&user_id).await?;
+
+    Ok(ApiResponse::ok(locale.t("password-reset-successful")).into())
+}
+
+/// Change the signed-in user's password and sign out everywhere
+/// PUT /auth/user/change-password
+pub async fn change_password<U: UserServiceTrait>(
+    locale: Locale,
+    auth_user: AuthUser,
+    user_service: web::Data<U>,
+    redis_service: web::Data<RedisService>,
+    token_service: web::Data<AccessTokenService>,
+    body: ValidatedJson<ChangePasswordRequest>,
+) -> Result<HttpResponse, CustomError> {
+    user_service
+        .change_password(&auth_user.id, &body.current_password, &body.new_password)
+        .await?;
+    redis_service
+        .invalidate_all_sessions(&auth_user.id.to_hex())
+        .await
+        .map_err(CustomError::InternalServerError)?;
+    token_service.revoke_all_for_user(&auth_user.id).await?;
+
+    Ok(ApiResponse::ok(locale.t("password-changed")).into())
+}
+
+/// Delete the signed-in user's account and what it published. Content
+/// cleanup after the account is gone is best effort and only logged.
+/// DELETE /auth/user/me
+pub async fn delete_me<U: UserServiceTrait, P: PostServiceTrait, C: CommentServiceTrait>(
+    locale: Locale,
+    auth_user: AuthUser,
+    user_service: web::Data<U>,
+    post_service: web::Data<P>,
+    comment_service: web::Data<C>,
+    friend_service: web::Data<FriendService>,
+    counter_service: web::Data<CounterService>,
+    redis_service: web::Data<RedisService>,
+    config: web::Data<AppConfig>,
+    token_service: web::Data<AccessTokenService>,
+    body: ValidatedJson<DeleteAccountRequest>,
+) -> Result<HttpResponse, CustomError> {
+    let user = user_service
+        .delete_account(&auth_user.id, &body.password)
+        .await?;
+    redis_service
+        .invalidate_all_sessions(&auth_user.id.to_hex())
+        .await
+        .map_err(CustomError::InternalServerError)?;
+    token_service.revoke_all_for_user(&auth_user.id).await?;
+
+    if let Err(e) = post_service.delete_posts_by_author(&auth_user.id).await {
+        log::warn!("Failed to delete posts of {}: {}", auth_user.id, e);
+    }
+    if let Err(e) = comment_service
+        .delete_comments_by_author(&auth_user.id)
+        .await
+    {
+        log::warn!("Failed to delete comments of {}: {}", auth_user.id, e);
+    }
+    match friend_service.remove_user(&auth_user.id).await {
+        Ok(friend_ids) => {
+            for friend_id in &friend_ids {
+                update_friend_counts(&counter_service, [friend_id, &auth_user.id], Some(-1)).await;
+            }
+        }
+        Err(e) => log::warn!("Failed to remove friendships of {}: {}", auth_user.id, e),
+    }
+
+    // Pictures hosted elsewhere (e.g. GitHub avatars) aren't ours to delete
+    if let Some((public_id, resource_type)) = user
+        .profile_picture
+        .as_deref()
+        .and_then(|url| config.cloudinary.asset_from_url(url))
+        && let Err(e) = UploadService::with_config(config.cloudinary.clone())
+            .delete_resource(&public_id, &resource_type)
+            .await
+    {
+        log::warn!("Failed to delete profile picture {}: {}", public_id, e);
+    }
+
+    Ok(ApiResponse::ok(locale.t("account-deleted")).into())
+}
+
+/// The signed-in user's account
+/// GET /users/me
+pub async fn get_me<U: UserServiceTrait>(
+    locale: Locale,
+    auth_user: AuthUser,
+    user_service: web::Data<U>,
+) -> Result<HttpResponse, CustomError> {
+    let user = user_service
+        .get_user(&auth_user.id)
+        .await?
+        .ok_or_else(user_not_found)?;
+
+    Ok(ApiResponse::ok(locale.t("profile-fetched"))
+        .data(UserDto::from(user))
+        .into())
+}
+
+/// Update the signed-in user's account
+/// PATCH /users/me
+pub async fn update_me<U: UserServiceTrait>(
+    locale: Locale,
+    auth_user: AuthUser,
+    user_service: web::Data<U>,
+    redis_service: web::Data<RedisService>,
+    body: ValidatedJson<UpdateMeRequest>,
+) -> Result<HttpResponse, CustomError> {
+    let body = body.into_inner();
+    let mut updated = serde_json::Map::new();
+    if let Some(username) = body.username {
+        let username = user_service
+            .change_username(&auth_user.id, &username)
+            .await?;
+        updated.insert("username".to_string(), json!(username));
+    }
+    if let Some(preference) = body.sensitive_content {
+        user_service
+            .set_sensitive_content(&auth_user.id, preference)
+            .await?;
+        updated.insert("sensitive_content".to_string(), json!(preference));
+    }
+    if let Some(mut languages) = body.content_languages {
+        languages.sort();
+        languages.dedup();
+        user_service
+            .set_content_languages(&auth_user.id, &languages)
+            .await?;
+        updated.insert("content_languages".to_string(), json!(languages));
+    }
+    if !updated.is_empty()
+        && let Err(e) = redis_service
+            .cache_delete(&profile_cache_key(&auth_user.id))
+            .await
+    {
+        log::warn!("Failed to invalidate profile cache: {}", e);
+    }
+
+    Ok(ApiResponse::ok(locale.t("profile-updated"))
+        .data(updated)
+        .into())
+}
+
+/// A user's public profile. Old usernames still resolve for a while after a
+/// rename.
+/// GET /users/by-username/{username}
+pub async fn get_profile_by_username<U: UserServiceTrait>(
+    locale: Locale,
+    auth_user: AuthUser,
+    user_service: web::Data<U>,
+    friend_service: web::Data<FriendService>,
+    redis_service: web::Data<RedisService>,
+    insights_service: web::Data<InsightsService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    let username = path.into_inner();
+
+    let (user_id, redirected) = user_service
+        .resolve_username(&username)
+        .await?
+        .ok_or_else(user_not_found)?;
+    let view = view_profile(
+        &auth_user,
+        user_service.get_ref(),
+        &friend_service,
+        &redis_service,
+        &insights_service,
+        user_id,
+        redirected.then_some(username),
+    )
+    .await?;
+
+    Ok(ApiResponse::ok(locale.t("profile-fetched"))
+        .data(ProfileDto::from(view))
+        .into())
+}
+
+/// QR code linking to the caller's profile
+/// GET /users/me/qr?format=png|svg&size=512
+pub async fn get_my_qr<U: UserServiceTrait>(
+    req: HttpRequest,
+    auth_user: AuthUser,
+    config: web::Data<AppConfig>,
+    user_service: web::Data<U>,
+    query: web::Query<QrQuery>,
+) -> Result<HttpResponse, CustomError> {
+    let token = user_service.profile_token(&auth_user.id).await?;
+    let image = render_qr(
+        &profile_link(&req, &config, &token),
+        query.format,
+        query.size.unwrap_or(DEFAULT_QR_SIZE),
+    )?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(query.format.content_type())
+        .insert_header((header::CACHE_CONTROL, "private, max-age=3600"))
+        .body(image))
+}
+
+/// Profile behind a scanned QR code
+/// GET /users/by-qr/{token}
+pub async fn get_profile_by_qr<U: UserServiceTrait>(
+    locale: Locale,
+    auth_user: AuthUser,
+    user_service: web::Data<U>,
+    friend_service: web::Data<FriendService>,
+    redis_service: web::Data<RedisService>,
+    insights_service: web::Data<InsightsService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    let user_id = user_service
+        .resolve_profile_token(&path)
+        .await?
+        .ok_or_else(user_not_found)?;
+    let view = view_profile(
+        &auth_user,
+        user_service.get_ref(),
+        &friend_service,
+        &redis_service,
+        &insights_service,
+        user_id,
+        None,
+    )
+    .await?;
+
+    Ok(ApiResponse::ok(locale.t("profile-fetched"))
+        .data(ProfileDto::from(view))
+        .into())
+}
+
+fn user_not_found() -> CustomError {
+    CustomError::coded(ErrorCode::UserNotFound, "User not found")
+}
+
+/// Where a profile QR code points: the web app when `SHARE_LINK_BASE_URL`
+/// is set, otherwise the resolver on this API host
+fn profile_link(req: &HttpRequest, config: &AppConfig, token: &str) -> String {
+    match &config.share_link_base_url {
+        Some(base_url) => format!("{}/u/{}", base_url, token),
+        None => {
+            let info = req.connection_info();
+            format!(
+                "{}://{}/api/v1/users/by-qr/{}",
+                info.scheme(),
+                info.host(),
+                token
+            )
+        }
+    }
+}
+
+/// A profile as seen by the signed-in user
+async fn view_profile<U: UserServiceTrait>(
+    auth_user: &AuthUser,
+    user_service: &U,
+    friend_service: &FriendService,
+    redis_service: &RedisService,
+    insights_service: &InsightsService,
+    user_id: ObjectId,
+    previous_username: Option<String>,
+) -> Result<ProfileView, CustomError> {
+    // Blocks hide the profile both ways, before it is read or the view logged
+    if friend_service
+        .is_blocked_between(&user_id, &auth_user.id)
+        .await?
+    {
+        return Err(user_not_found());
+    }
+
+    let mut profile = redis_service
+        .cache_get_or_set_json(&profile_cache_key(&user_id), PROFILE_CACHE_SECONDS, || {
+            user_service.public_profile(&user_id)
+        })
+        .await?
+        .ok_or_else(user_not_found)?;
+
+    let is_friend =
+        user_id != auth_user.id && friend_service.are_friends(&user_id, &auth_user.id).await?;
+    let posts_visible = friend_service
+        .can_view_posts(&user_id, &auth_user.id)
+        .await?;
+    if !posts_visible {
+        profile.pinned_posts.clear();
+    }
+    insights_service.record_view(&user_id, &auth_user.id).await;
+
+    Ok(ProfileView {
+        profile,
+        is_friend,
+        posts_visible,
+        previous_username,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::auth::TokenPair;
+    use crate::user::model::{PublicProfile, SensitiveContent, User};
+    use crate::utils::mocks::{MockRedis, NoOp, not_mocked};
+    use actix_web::body::to_bytes;
+    use actix_web::http::StatusCode;
+    use actix_web::test::TestRequest;
+    use serde_json::Value;
+    use std::sync::Mutex;
+
+    const PASSWORD: &str = "correct horse";
+
+    /// Logs in anyone whose password is `PASSWORD`, recording each attempt
+    #[derive(Default)]
+    struct MockUsers {
+        logins: Mutex<Vec<String>>,
+    }
+
+    impl UserServiceTrait for MockUsers {
+        async fn create_user(
+            &self,
+            _username: String,
+            _email: String,
+            _password: String,
+            _phone_number: String,
+            _locale: Locale,
+        ) -> Result<ObjectId, CustomError> {
+            not_mocked()
+        }
+
+        async fn verify_email(
+            &self,
+            _email: &str,
+            _otp_code: &str,
+        ) -> Result<ObjectId, CustomError> {
+            not_mocked()
+        }
+
+        async fn resend_otp(&self, _email: &str) -> Result<(), CustomError> {
+            not_mocked()
+        }
+
+        async fn send_phone_otp(&self, _email: &str) -> Result<(), CustomError> {
+            not_mocked()
+        }
+
+        async fn verify_phone(
+            &self,
+            _email: &str,
+            _otp_code: &str,
+        ) -> Result<ObjectId, CustomError> {
+            not_mocked()
+        }
+
+        async fn login_fn(
+            &self,
+            login_data: LoginRequests,
+        ) -> Result<(ObjectId, TokenPair), CustomError> {
+            self.logins.lock().unwrap().push(login_data.username);
+            if login_data.password != PASSWORD {
+                return Err(CustomError::coded(
+                    ErrorCode::AuthInvalidCredentials,
+                    "Invalid credentials",
+                ));
+            }
+            let tokens = TokenPair {
+                token: "access".to_string(),
+                refresh_token: "refresh".to_string(),
+                expires_in: 900,
+            };
+            Ok((ObjectId::new(), tokens))
+        }
+
+        async fn refresh_session(
+            &self,
+            _user_id: &ObjectId,
+            _redis_service: &RedisService,
+        ) -> Result<TokenPair, CustomError> {
+            not_mocked()
+        }
+
+        async fn request_password_reset(&self, _email: &str) -> Result<(), CustomError> {
+            not_mocked()
+        }
+
+        async fn reset_password(
+            &self,
+            _reset_token: &str,
+            _new_password: &str,
+        ) -> Result<ObjectId, CustomError> {
+            not_mocked()
+        }
+
+        async fn change_password(
+            &self,
+            _user_id: &ObjectId,
+            _current_password: &str,
+            _new_password: &str,
+        ) -> Result<(), CustomError> {
+            not_mocked()
+        }
+
+        async fn delete_account(
+            &self,
+            _user_id: &ObjectId,
+            _password: &str,
+        ) -> Result<User, CustomError> {
+            not_mocked()
+        }
+
+        async fn get_user(&self, _user_id: &ObjectId) -> Result<Option<User>, CustomError> {
+            not_mocked()
+        }
+
+        async fn change_username(
+            &self,
+            _user_id: &ObjectId,
+            _new_username: &str,
+        ) -> Result<String, CustomError> {
+            not_mocked()
+        }
+
+        async fn set_content_languages(
+            &self,
+            _user_id: &ObjectId,
+            _languages: &[String],
+        ) -> Result<(), CustomError> {
+            not_mocked()
+        }
+
+        async fn set_sensitive_content(
+            &self,
+            _user_id: &ObjectId,
+            _preference: SensitiveContent,
+        ) -> Result<(), CustomError> {
+            not_mocked()
+        }
+
+        async fn public_profile(
+            &self,
+            _user_id: &ObjectId,
+        ) -> Result<Option<PublicProfile>, CustomError> {
+            not_mocked()
+        }
+
+        async fn resolve_username(
+            &self,
+            _username: &str,
+        ) -> Result<Option<(ObjectId, bool)>, CustomError> {
+            not_mocked()
+        }
+
+        async fn resolve_profile_token(
+            &self,
+            _token: &str,
+        ) -> Result<Option<ObjectId>, CustomError> {
+            not_mocked()
+        }
+
+        async fn profile_token(&self, _user_id: &ObjectId) -> Result<String, CustomError> {
+            not_mocked()
+        }
+    }
+
+    async fn login(
+        users: &web::Data<MockUsers>,
+        redis: MockRedis,
+        password: &str,
+    ) -> Result<HttpResponse, CustomError> {
+        login_user(
+            Locale::default(),
+            TestRequest::default().to_http_request(),
+            users.clone(),
+            web::Data::new(redis),
+            web::Data::new(NoOp),
+            ValidatedJson(LoginRequests {
+                username: "alice".to_string(),
+                password: password.to_string(),
+            }),
+        )
+        .await
+    }
+
+    #[actix_web::test]
+    async fn login_user_returns_the_issued_tokens() {
+        let users = web::Data::new(MockUsers::default());
+
+        let res = login(&users, MockRedis::default(), PASSWORD)
+            .await
+            .expect("login_user failed");
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = to_bytes(res.into_body())
+            .await
+            .expect("Failed to read body");
+        let body: Value = serde_json::from_slice(&body).expect("Body is not JSON");
+        assert_eq!(body["data"]["token"], "access");
+        assert_eq!(body["data"]["refresh_token"], "refresh");
+        assert_eq!(*users.logins.lock().unwrap(), vec!["alice".to_string()]);
+    }
+
+    #[actix_web::test]
+    async fn login_user_passes_on_rejected_credentials() {
+        let users = web::Data::new(MockUsers::default());
+
+        let result = login(&users, MockRedis::default(), "wrong").await;
+
+        assert!(matches!(
+            result,
+            Err(CustomError::CodedError {
+                code: ErrorCode::AuthInvalidCredentials,
+                ..
+            })
+        ));
+    }
+
+    #[actix_web::test]
+    async fn login_user_is_rate_limited_before_checking_credentials() {
+        let users = web::Data::new(MockUsers::default());
+
+        let result = login(&users, MockRedis::limited(), PASSWORD).await;
+
+        assert!(matches!(result, Err(CustomError::TooManyRequestsError(_))));
+        assert!(users.logins.lock().unwrap().is_empty());
+    }
 }
@@ -0,0 +1,102 @@
+use crate::badge::model::EarnedBadge;
+use crate::post::post_dto::PostDto;
+use crate::user::model::{AuthProvider, ProfileView, Role, SensitiveContent, User};
+use crate::utils::i18n::Locale;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// The signed-in user's own account. Credentials and moderation state are
+/// left out.
+#[derive(Debug, Serialize)]
+pub struct UserDto {
+    pub id: String,
+    pub username: String,
+    pub email: String,
+    pub phone_number: String,
+    pub profile_picture: Option<String>,
+    pub is_email_verified: bool,
+    pub is_phone_verified: bool,
+    pub is_verified: bool,
+    /// False for OAuth accounts that haven't set a password yet
+    pub has_password: bool,
+    /// Providers the user can log in with besides a password
+    pub providers: Vec<AuthProvider>,
+    pub role: Role,
+    pub locale: Locale,
+    pub badges: Vec<EarnedBadge>,
+    pub interests: Vec<String>,
+    pub sensitive_content: SensitiveContent,
+    pub content_languages: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<User> for UserDto {
+    fn from(user: User) -> Self {
+        UserDto {
+            id: user.id.map(|id| id.to_hex()).unwrap_or_default(),
+            username: user.username,
+            email: user.email,
+            phone_number: user.phone_number,
+            profile_picture: user.profile_picture,
+            is_email_verified: user.is_email_verified,
+            is_phone_verified: user.is_phone_verified,
+            is_verified: user.is_verified,
+            has_password: user.password.is_some(),
+            providers: user
+                .providers
+                .iter()
+                .map(|linked| linked.provider)
+                .collect(),
+            role: user.role,
+            locale: user.locale,
+            badges: user.badges,
+            interests: user.interests,
+            sensitive_content: user.sensitive_content,
+            content_languages: user.content_languages,
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+        }
+    }
+}
+
+/// `ProfileView` as returned by the API
+#[derive(Debug, Serialize)]
+pub struct ProfileDto {
+    pub id: String,
+    pub username: String,
+    pub profile_picture: Option<String>,
+    pub is_verified: bool,
+    pub badges: Vec<EarnedBadge>,
+    pub post_count: u64,
+    pub friend_count: u64,
+    pub joined_at: DateTime<Utc>,
+    pub pinned_posts: Vec<PostDto>,
+    pub is_friend: bool,
+    pub posts_visible: bool,
+    pub previous_username: Option<String>,
+}
+
+impl From<ProfileView> for ProfileDto {
+    fn from(view: ProfileView) -> Self {
+        let profile = view.profile;
+        ProfileDto {
+            id: profile.id.to_hex(),
+            username: profile.username,
+            profile_picture: profile.profile_picture,
+            is_verified: profile.is_verified,
+            badges: profile.badges,
+            post_count: profile.post_count,
+            friend_count: profile.friend_count,
+            joined_at: profile.joined_at,
+            pinned_posts: profile
+                .pinned_posts
+                .into_iter()
+                .map(PostDto::from)
+                .collect(),
+            is_friend: view.is_friend,
+            posts_visible: view.posts_visible,
+            previous_username: view.previous_username,
+        }
+    }
+}
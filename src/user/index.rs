@@ -1,13 +1,99 @@
-use super::controller::{login_user, logout_user, register_user, resend_otp, verify_email};
+use super::controller::{
+    change_password, delete_me, forgot_password, get_me, get_my_qr, get_profile_by_qr,
+    get_profile_by_username, login_user, logout_user, refresh_token, register_user, resend_otp,
+    reset_password, send_phone_otp, update_me, verify_email, verify_phone,
+};
+use super::service::UserService;
+use crate::access_token::controller::{
+    create_access_token, list_access_tokens, revoke_access_token,
+};
+use crate::activity::controller::get_activity;
+use crate::badge::controller::get_user_badges;
+use crate::comment::service::CommentService;
+use crate::database::RedisService;
+use crate::fingerprint::service::FingerprintService;
+use crate::friend::controller::{find_by_contacts, get_suggestions};
+use crate::insights::controller::get_insights;
+use crate::middleware::auth::verify_token;
+use crate::middleware::limits::RequestTimeout;
+use crate::middleware::rate_limit::IpRateLimit;
+use crate::nearby::controller::{clear_nearby_location, get_nearby_users, update_nearby_location};
+use crate::post::post_service::PostService;
+use crate::topic::controller::{get_interests, update_interests};
 use actix_web::web;
+use actix_web_httpauth::middleware::HttpAuthentication;
 
 pub fn user_routes(cfg: &mut web::ServiceConfig) {
+    // Registered before `/auth/user`, whose prefix would otherwise match
+    cfg.service(
+        web::scope("/auth/user/tokens")
+            .wrap(RequestTimeout::standard())
+            .wrap(HttpAuthentication::bearer(verify_token))
+            .route("", web::post().to(create_access_token))
+            .route("", web::get().to(list_access_tokens))
+            .route("/{id}", web::delete().to(revoke_access_token)),
+    );
     cfg.service(
         web::scope("/auth/user")
-            .route("/register", web::post().to(register_user))
-            .route("/verify-email", web::post().to(verify_email))
-            .route("/resend-otp", web::post().to(resend_otp))
-            .route("/login", web::post().to(login_user))
-            .route("/logout", web::post().to(logout_user)),
+            .wrap(RequestTimeout::standard())
+            .wrap(IpRateLimit::public_endpoints("auth"))
+            .route("/register", web::post().to(register_user::<UserService>))
+            .route("/verify-email", web::post().to(verify_email::<UserService>))
+            .route("/resend-otp", web::post().to(resend_otp::<UserService>))
+            .route(
+                "/send-phone-otp",
+                web::post().to(send_phone_otp::<UserService>),
+            )
+            .route("/verify-phone", web::post().to(verify_phone::<UserService>))
+            .route(
+                "/login",
+                web::post().to(login_user::<UserService, RedisService, FingerprintService>),
+            )
+            .route("/refresh", web::post().to(refresh_token::<UserService>))
+            .route("/logout", web::post().to(logout_user))
+            .route(
+                "/forgot-password",
+                web::post().to(forgot_password::<UserService>),
+            )
+            .route(
+                "/reset-password",
+                web::post().to(reset_password::<UserService>),
+            )
+            .service(
+                web::resource("/change-password")
+                    .wrap(HttpAuthentication::bearer(verify_token))
+                    .route(web::put().to(change_password::<UserService>)),
+            )
+            .service(
+                web::resource("/me")
+                    .wrap(HttpAuthentication::bearer(verify_token))
+                    .route(web::delete().to(delete_me::<UserService, PostService, CommentService>)),
+            ),
+    );
+    cfg.service(
+        web::scope("/users")
+            .wrap(RequestTimeout::standard())
+            .wrap(HttpAuthentication::bearer(verify_token))
+            .route("/me", web::get().to(get_me::<UserService>))
+            .route("/me", web::patch().to(update_me::<UserService>))
+            .route("/me/activity", web::get().to(get_activity))
+            .route("/me/interests", web::get().to(get_interests))
+            .route("/me/interests", web::put().to(update_interests))
+            .route("/me/qr", web::get().to(get_my_qr::<UserService>))
+            .route("/me/insights", web::get().to(get_insights))
+            .route("/me/location", web::put().to(update_nearby_location))
+            .route("/me/location", web::delete().to(clear_nearby_location))
+            .route("/suggestions", web::get().to(get_suggestions))
+            .route("/find-by-contacts", web::post().to(find_by_contacts))
+            .route("/nearby", web::get().to(get_nearby_users))
+            .route(
+                "/by-username/{username}",
+                web::get().to(get_profile_by_username::<UserService>),
+            )
+            .route(
+                "/by-qr/{token}",
+                web::get().to(get_profile_by_qr::<UserService>),
+            )
+            .route("/{user_id}/badges", web::get().to(get_user_badges)),
     );
 }
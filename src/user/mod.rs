@@ -1,4 +1,5 @@
 pub mod controller;
+pub mod dto;
 pub mod index;
 pub mod model;
 pub mod service;
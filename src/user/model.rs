@@ -1,6 +1,15 @@
+use crate::api_key::service::hex;
+use crate::badge::model::EarnedBadge;
+use crate::post::post_model::Post;
+use crate::utils::datetime::{bson_datetime, option_bson_datetime};
+use crate::utils::i18n::Locale;
+use crate::utils::language::language_codes;
+use crate::utils::validation::not_blank;
 use chrono::{DateTime, Utc};
 use mongodb::bson::oid::ObjectId;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use validator::Validate;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct User {
@@ -8,44 +17,324 @@ pub struct User {
     pub id: Option<ObjectId>,
     pub username: String,
     pub email: String,
-    pub password: String,
+    /// bcrypt hash; `None` for accounts created through an OAuth provider
+    /// until a password is set with a reset token
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Empty for accounts created through an OAuth provider
+    #[serde(default)]
     pub phone_number: String,
+    /// External accounts the user can log in with
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub providers: Vec<LinkedProvider>,
+    /// `phone_hash` of the number, for contact matching
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phone_hash: Option<String>,
+    /// Random token behind the profile QR code, assigned on first use
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile_token: Option<String>,
     pub profile_picture: Option<String>,
     pub is_email_verified: bool,
+    /// Set once the user enters a code texted to `phone_number`
+    #[serde(default)]
+    pub is_phone_verified: bool,
+    /// Verified badge, granted by an admin after reviewing a document
+    #[serde(default)]
+    pub is_verified: bool,
+    #[serde(default)]
+    pub role: Role,
+    /// Language for emails and API messages
+    #[serde(default)]
+    pub locale: Locale,
+    /// Set by moderation; the account cannot log in until this passes
+    #[serde(default, with = "option_bson_datetime")]
+    pub suspended_until: Option<DateTime<Utc>>,
+    /// Set by moderation; the user's content is hidden from everyone else
+    #[serde(default)]
+    pub shadow_banned: bool,
+    /// Achievements, awarded by the badge service
+    #[serde(default)]
+    pub badges: Vec<EarnedBadge>,
+    /// Topic slugs picked during onboarding
+    #[serde(default)]
+    pub interests: Vec<String>,
+    /// How posts and uploads flagged as sensitive are shown to the user
+    #[serde(default)]
+    pub sensitive_content: SensitiveContent,
+    /// ISO 639-3 codes the user reads; feeds show every language when empty
+    #[serde(default)]
+    pub content_languages: Vec<String>,
+    #[serde(with = "bson_datetime")]
     pub created_at: DateTime<Utc>,
+    #[serde(with = "bson_datetime")]
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Deserialize)]
+/// External identity providers users can log in with
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthProvider {
+    Github,
+}
+
+impl AuthProvider {
+    /// Name stored on the user document
+    pub fn name(&self) -> &'static str {
+        match self {
+            AuthProvider::Github => "github",
+        }
+    }
+}
+
+/// An external account linked to a user
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LinkedProvider {
+    pub provider: AuthProvider,
+    /// The provider's stable id for the account
+    pub provider_user_id: String,
+    /// Provider-side username at the last login
+    pub username: String,
+    #[serde(with = "bson_datetime")]
+    pub linked_at: DateTime<Utc>,
+}
+
+/// Authorization level of an account
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    #[default]
+    User,
+    Moderator,
+    Admin,
+}
+
+/// A user's preference for content flagged as sensitive
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SensitiveContent {
+    /// Shown behind a blur the reader can tap through
+    #[default]
+    Blur,
+    /// Always shown unblurred
+    Show,
+    /// Left out of feeds altogether
+    Hide,
+}
+
+impl SensitiveContent {
+    /// Name stored on the user document
+    pub fn name(&self) -> &'static str {
+        match self {
+            SensitiveContent::Blur => "blur",
+            SensitiveContent::Show => "show",
+            SensitiveContent::Hide => "hide",
+        }
+    }
+}
+
+#[derive(Deserialize, Validate)]
 pub struct CreateUserRequest {
+    #[validate(
+        length(min = 3, max = 30, message = "must be between 3 and 30 characters"),
+        custom(function = "not_blank")
+    )]
     pub username: String,
+    #[validate(email(message = "must be a valid email address"))]
     pub email: String,
+    #[validate(length(min = 8, max = 20, message = "must be between 8 and 20 characters"))]
     pub password: String,
+    #[validate(length(min = 7, max = 20, message = "must be between 7 and 20 characters"))]
     pub phone_number: String,
+    /// Defaults to the request's `Accept-Language`
+    #[serde(default)]
+    pub locale: Option<Locale>,
 }
 
-/// OTP model for email verification
+/// A code emailed to verify an address; only its hash is stored
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Otp {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<ObjectId>,
     pub user_id: ObjectId,
     pub email: String,
-    pub code: String,
+    /// Empty for codes stored in plain text before hashing, which never match
+    #[serde(default)]
+    pub code_hash: String,
+    /// Guesses made so far, capped at `OTP_MAX_ATTEMPTS`
+    #[serde(default)]
+    pub attempts: u32,
+    /// Stored as a BSON date so the TTL index can expire it
+    #[serde(with = "bson_datetime")]
     pub expires_at: DateTime<Utc>,
     pub is_used: bool,
+    #[serde(with = "bson_datetime")]
     pub created_at: DateTime<Utc>,
 }
 
 /// Request body for email verification
-#[derive(Deserialize)]
+#[derive(Deserialize, Validate)]
 pub struct VerifyEmailRequest {
+    #[validate(email(message = "must be a valid email address"))]
+    pub email: String,
+    #[validate(custom(function = "not_blank"))]
+    pub otp_code: String,
+}
+
+/// A code texted to confirm a phone number; only its hash is stored
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PhoneOtp {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: ObjectId,
+    /// The number the code was sent to, in case the user changes it meanwhile
+    pub phone_number: String,
+    pub code_hash: String,
+    /// Guesses made so far, capped at `OTP_MAX_ATTEMPTS`
+    #[serde(default)]
+    pub attempts: u32,
+    #[serde(with = "bson_datetime")]
+    pub expires_at: DateTime<Utc>,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// Request body for `POST /auth/user/verify-phone`
+#[derive(Deserialize, Validate)]
+pub struct VerifyPhoneRequest {
+    #[validate(email(message = "must be a valid email address"))]
     pub email: String,
+    #[validate(custom(function = "not_blank"))]
     pub otp_code: String,
 }
 
 /// Request body for resending OTP
-#[derive(Deserialize)]
+#[derive(Deserialize, Validate)]
 pub struct ResendOtpRequest {
+    #[validate(email(message = "must be a valid email address"))]
+    pub email: String,
+}
+
+/// A pending password reset; only the token's SHA-256 hash is stored
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PasswordReset {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: ObjectId,
+    pub token_hash: String,
+    /// Stored as a BSON date so the TTL index can expire it
+    #[serde(with = "bson_datetime")]
+    pub expires_at: DateTime<Utc>,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// Request body for `POST /auth/user/forgot-password`
+#[derive(Deserialize, Validate)]
+pub struct ForgotPasswordRequest {
+    #[validate(email(message = "must be a valid email address"))]
     pub email: String,
 }
+
+/// Request body for `POST /auth/user/reset-password`
+#[derive(Deserialize, Validate)]
+pub struct ResetPasswordRequest {
+    #[validate(custom(function = "not_blank"))]
+    pub token: String,
+    #[validate(length(min = 1, message = "must not be empty"))]
+    pub new_password: String,
+}
+
+/// Request body for `POST /auth/user/refresh`
+#[derive(Deserialize, Validate)]
+pub struct RefreshTokenRequest {
+    #[validate(custom(function = "not_blank"))]
+    pub refresh_token: String,
+}
+
+/// Request body for `PUT /auth/user/change-password`
+#[derive(Deserialize, Validate)]
+pub struct ChangePasswordRequest {
+    #[validate(length(min = 1, message = "must not be empty"))]
+    pub current_password: String,
+    #[validate(length(min = 1, message = "must not be empty"))]
+    pub new_password: String,
+}
+
+/// Request body for `DELETE /auth/user/me`
+#[derive(Deserialize, Validate)]
+pub struct DeleteAccountRequest {
+    #[validate(length(min = 1, message = "must not be empty"))]
+    pub password: String,
+}
+
+/// Request body for `PATCH /users/me`; absent fields are left unchanged
+#[derive(Deserialize, Validate)]
+pub struct UpdateMeRequest {
+    #[validate(
+        length(min = 3, max = 30, message = "must be between 3 and 30 characters"),
+        custom(function = "not_blank")
+    )]
+    pub username: Option<String>,
+    pub sensitive_content: Option<SensitiveContent>,
+    /// Replaces the preferred content languages; empty clears them
+    #[validate(
+        length(max = 10, message = "must have at most 10 languages"),
+        custom(function = "language_codes")
+    )]
+    pub content_languages: Option<Vec<String>>,
+}
+
+/// A past username, kept for the change cooldown and for redirecting
+/// lookups of the old name
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UsernameChange {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: ObjectId,
+    pub old_username: String,
+    pub new_username: String,
+    #[serde(with = "bson_datetime")]
+    pub changed_at: DateTime<Utc>,
+}
+
+/// Public part of a profile, shared by every viewer
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PublicProfile {
+    pub id: ObjectId,
+    pub username: String,
+    pub profile_picture: Option<String>,
+    pub is_verified: bool,
+    pub badges: Vec<EarnedBadge>,
+    /// Profile posts; group posts aren't counted
+    pub post_count: u64,
+    pub friend_count: u64,
+    pub joined_at: DateTime<Utc>,
+    /// Profile posts the user pinned, most recently pinned first
+    #[serde(default)]
+    pub pinned_posts: Vec<Post>,
+}
+
+/// A profile as seen by one viewer
+#[derive(Debug, Serialize)]
+pub struct ProfileView {
+    #[serde(flatten)]
+    pub profile: PublicProfile,
+    pub is_friend: bool,
+    /// False when the user shares posts with friends only; pinned posts
+    /// are left out then
+    pub posts_visible: bool,
+    /// Set when the lookup used a name the user has since given up
+    pub previous_username: Option<String>,
+}
+
+/// SHA-256 hex digest of a phone number reduced to an optional leading `+`
+/// and its digits. Clients hash their address book the same way, so numbers
+/// can be matched without being uploaded.
+pub fn phone_hash(phone_number: &str) -> String {
+    let trimmed = phone_number.trim();
+    let mut normalized: String = trimmed.chars().filter(char::is_ascii_digit).collect();
+    if trimmed.starts_with('+') {
+        normalized.insert(0, '+');
+    }
+    hex(&Sha256::digest(normalized.as_bytes()))
+}
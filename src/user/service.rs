@@ -1,85 +1,543 @@
-use crate::database::RedisService;
-use crate::middleware::auth::{create_token, create_token_with_session};
-use crate::user::model::{Otp, User};
-use crate::utils::email::EmailService;
-use crate::utils::error::CustomError;
-use crate::utils::helpers::{OTP_EXPIRATION_MINUTES, generate_otp_code};
+use crate::access_token::service::AccessTokenService;
+use crate::api_key::service::hash_key;
+use crate::counter::model::Counter;
+use crate::counter::service::CounterService;
+use crate::database::{MongoRepository, RedisService, Repository};
+use crate::middleware::auth::{TokenPair, create_token_pair};
+use crate::oauth::model::OAuthProfile;
+use crate::post::post_model::Post;
+use crate::post::post_service::MAX_PINNED_POSTS;
+use crate::user::model::{
+    LinkedProvider, Otp, PasswordReset, PhoneOtp, PublicProfile, Role, SensitiveContent, User,
+    UsernameChange, phone_hash,
+};
+use crate::utils::config::AppConfig;
+use crate::utils::datetime::bson_now;
+use crate::utils::error::{CustomError, ErrorCode};
+use crate::utils::helpers::{
+    OTP_RETENTION_GRACE_HOURS, PASSWORD_RESET_EXPIRATION_MINUTES, generate_reset_token,
+};
+use crate::utils::i18n::Locale;
 use crate::utils::model::LoginRequests;
+use crate::utils::otp::{OTP_MAX_ATTEMPTS, OtpConfig, generate_otp_code};
+use crate::utils::outbox::{EmailOutbox, ONBOARDING_TIPS_DELAY_HOURS, OutboxEmail, OutboxPayload};
+use crate::utils::sms::SmsService;
 use crate::utils::{hashing, password_validation};
 use chrono::{Duration, Utc};
-use mongodb::bson::{doc, oid::ObjectId};
-use mongodb::{Client, Collection};
+use futures_util::TryStreamExt;
+use mongodb::bson::{self, Bson, doc, oid::ObjectId};
+use mongodb::options::IndexOptions;
+use mongodb::{Client, ClientSession, Collection, IndexModel};
+use rand::Rng;
+use rand::distr::Alphanumeric;
+use serde_json::json;
+use std::future::Future;
+use std::time::Duration as StdDuration;
+use tokio::sync::OnceCell;
 
-pub struct UserService {
-    collection: Collection<User>,
+/// How long a user must wait between username changes
+pub const USERNAME_CHANGE_COOLDOWN_DAYS: i64 = 30;
+/// How long an old username keeps pointing at its previous owner
+pub const USERNAME_REDIRECT_GRACE_DAYS: i64 = 30;
+/// Length of the base62 token encoded in profile QR codes
+const PROFILE_TOKEN_LENGTH: usize = 12;
+/// Token collisions are vanishingly rare; give up after this many
+const PROFILE_TOKEN_ATTEMPTS: usize = 5;
+
+/// Confirm `password` is the account's current one. Accounts created through
+/// OAuth set their first password with a reset token.
+fn check_current_password(user: &User, password: &str) -> Result<(), CustomError> {
+    let Some(password_hash) = user.password.as_deref() else {
+        return Err(CustomError::BadRequestError(
+            "This account has no password yet; set one with forgot-password".to_string(),
+        ));
+    };
+    if !hashing::verify_password(password, password_hash)
+        .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+    {
+        return Err(CustomError::coded(
+            ErrorCode::AuthWrongPassword,
+            "Current password is incorrect",
+        ));
+    }
+    Ok(())
+}
+
+/// Count a guess against the code `otp_id` in `codes`, failing once it has
+/// had `OTP_MAX_ATTEMPTS`. Guesses are counted before the code is compared,
+/// so parallel requests can't exceed the budget.
+async fn claim_otp_attempt<T: Send + Sync>(
+    codes: &Collection<T>,
+    otp_id: &ObjectId,
+) -> Result<(), CustomError> {
+    let claimed = codes
+        .update_one(
+            doc! { "_id": otp_id, "attempts": { "$not": { "$gte": OTP_MAX_ATTEMPTS } } },
+            doc! { "$inc": { "attempts": 1 } },
+        )
+        .await
+        .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+    if claimed.modified_count == 0 {
+        return Err(CustomError::coded(
+            ErrorCode::AuthOtpExpired,
+            "Too many wrong attempts; request a new code",
+        ));
+    }
+    Ok(())
+}
+
+/// The account operations handlers use, so they can be unit-tested against a
+/// mock instead of MongoDB and Redis. `UserService` implements it by
+/// delegating to its inherent methods.
+pub trait UserServiceTrait: Send + Sync + 'static {
+    fn create_user(
+        &self,
+        username: String,
+        email: String,
+        password: String,
+        phone_number: String,
+        locale: Locale,
+    ) -> impl Future<Output = Result<ObjectId, CustomError>> + Send;
+
+    fn verify_email(
+        &self,
+        email: &str,
+        otp_code: &str,
+    ) -> impl Future<Output = Result<ObjectId, CustomError>> + Send;
+
+    fn resend_otp(&self, email: &str) -> impl Future<Output = Result<(), CustomError>> + Send;
+
+    fn send_phone_otp(&self, email: &str) -> impl Future<Output = Result<(), CustomError>> + Send;
+
+    fn verify_phone(
+        &self,
+        email: &str,
+        otp_code: &str,
+    ) -> impl Future<Output = Result<ObjectId, CustomError>> + Send;
+
+    fn login_fn(
+        &self,
+        login_data: LoginRequests,
+    ) -> impl Future<Output = Result<(ObjectId, TokenPair), CustomError>> + Send;
+
+    fn refresh_session(
+        &self,
+        user_id: &ObjectId,
+        redis_service: &RedisService,
+    ) -> impl Future<Output = Result<TokenPair, CustomError>> + Send;
+
+    fn request_password_reset(
+        &self,
+        email: &str,
+    ) -> impl Future<Output = Result<(), CustomError>> + Send;
+
+    fn reset_password(
+        &self,
+        reset_token: &str,
+        new_password: &str,
+    ) -> impl Future<Output = Result<ObjectId, CustomError>> + Send;
+
+    fn change_password(
+        &self,
+        user_id: &ObjectId,
+        current_password: &str,
+        new_password: &str,
+    ) -> impl Future<Output = Result<(), CustomError>> + Send;
+
+    fn delete_account(
+        &self,
+        user_id: &ObjectId,
+        password: &str,
+    ) -> impl Future<Output = Result<User, CustomError>> + Send;
+
+    fn get_user(
+        &self,
+        user_id: &ObjectId,
+    ) -> impl Future<Output = Result<Option<User>, CustomError>> + Send;
+
+    fn change_username(
+        &self,
+        user_id: &ObjectId,
+        new_username: &str,
+    ) -> impl Future<Output = Result<String, CustomError>> + Send;
+
+    fn set_content_languages(
+        &self,
+        user_id: &ObjectId,
+        languages: &[String],
+    ) -> impl Future<Output = Result<(), CustomError>> + Send;
+
+    fn set_sensitive_content(
+        &self,
+        user_id: &ObjectId,
+        preference: SensitiveContent,
+    ) -> impl Future<Output = Result<(), CustomError>> + Send;
+
+    fn public_profile(
+        &self,
+        user_id: &ObjectId,
+    ) -> impl Future<Output = Result<Option<PublicProfile>, CustomError>> + Send;
+
+    fn resolve_username(
+        &self,
+        username: &str,
+    ) -> impl Future<Output = Result<Option<(ObjectId, bool)>, CustomError>> + Send;
+
+    fn resolve_profile_token(
+        &self,
+        token: &str,
+    ) -> impl Future<Output = Result<Option<ObjectId>, CustomError>> + Send;
+
+    fn profile_token(
+        &self,
+        user_id: &ObjectId,
+    ) -> impl Future<Output = Result<String, CustomError>> + Send;
+}
+
+pub struct UserService<R: Repository<User> = MongoRepository<User>> {
+    client: Client,
+    users: R,
     otp_collection: Collection<Otp>,
+    username_history: Collection<UsernameChange>,
+    password_resets: Collection<PasswordReset>,
+    phone_otps: Collection<PhoneOtp>,
+    posts: Collection<Post>,
+    counters: CounterService,
+    /// Holds the sessions issued at login
+    redis_service: RedisService,
+    outbox: EmailOutbox,
+    access_tokens: AccessTokenService,
+    /// `None` unless an SMS provider is configured
+    sms: Option<SmsService>,
+    otp: OtpConfig,
+    /// Signs the JWTs issued at login and refresh
+    jwt_secret: String,
+    supports_transactions: OnceCell<bool>,
 }
 
 impl UserService {
-    pub fn new(client: &Client) -> Self {
+    pub fn new(client: &Client, redis_service: RedisService, config: &AppConfig) -> Self {
+        Self::with_repository(
+            client,
+            redis_service,
+            config,
+            MongoRepository::new(client, "users"),
+        )
+    }
+
+    /// Create the indexes backing OTP lookups and expiry, username history,
+    /// password resets, phone codes and profile QR tokens
+    #[tracing::instrument(skip_all)]
+    pub async fn ensure_indexes(&self) -> Result<(), CustomError> {
+        // Expired OTPs are removed by MongoDB after a grace period
+        let ttl_index = IndexModel::builder()
+            .keys(doc! { "expires_at": 1 })
+            .options(
+                IndexOptions::builder()
+                    .expire_after(StdDuration::from_secs(OTP_RETENTION_GRACE_HOURS * 3600))
+                    .build(),
+            )
+            .build();
+
+        // Serves "latest OTP for this email" lookups
+        let lookup_index = IndexModel::builder()
+            .keys(doc! { "email": 1, "created_at": -1 })
+            .build();
+
+        self.otp_collection
+            .create_indexes(vec![ttl_index, lookup_index])
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to create OTP indexes: {}", e))
+            })?;
+
+        // Serve the cooldown check and lookups by a previous name
+        let history_indexes = vec![
+            IndexModel::builder()
+                .keys(doc! { "user_id": 1, "changed_at": -1 })
+                .build(),
+            IndexModel::builder()
+                .keys(doc! { "old_username": 1, "changed_at": -1 })
+                .build(),
+        ];
+        self.username_history
+            .create_indexes(history_indexes)
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!(
+                    "Failed to create username history indexes: {}",
+                    e
+                ))
+            })?;
+
+        // Profile QR tokens are unique among users that have one
+        self.users
+            .collection()
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "profile_token": 1 })
+                    .options(
+                        IndexOptions::builder()
+                            .unique(true)
+                            .partial_filter_expression(
+                                doc! { "profile_token": { "$type": "string" } },
+                            )
+                            .build(),
+                    )
+                    .build(),
+            )
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!(
+                    "Failed to create profile token index: {}",
+                    e
+                ))
+            })?;
+
+        // Each external account belongs to at most one user
+        self.users
+            .collection()
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "providers.provider": 1, "providers.provider_user_id": 1 })
+                    .options(
+                        IndexOptions::builder()
+                            .unique(true)
+                            .partial_filter_expression(
+                                doc! { "providers.provider_user_id": { "$exists": true } },
+                            )
+                            .build(),
+                    )
+                    .build(),
+            )
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!(
+                    "Failed to create linked provider index: {}",
+                    e
+                ))
+            })?;
+
+        // Reset tokens are looked up by hash and removed once expired
+        let reset_indexes = vec![
+            IndexModel::builder()
+                .keys(doc! { "token_hash": 1 })
+                .options(IndexOptions::builder().unique(true).build())
+                .build(),
+            IndexModel::builder()
+                .keys(doc! { "expires_at": 1 })
+                .options(
+                    IndexOptions::builder()
+                        .expire_after(StdDuration::ZERO)
+                        .build(),
+                )
+                .build(),
+        ];
+        self.password_resets
+            .create_indexes(reset_indexes)
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!(
+                    "Failed to create password reset indexes: {}",
+                    e
+                ))
+            })?;
+
+        // Phone codes are looked up per user and removed once expired
+        let phone_otp_indexes = vec![
+            IndexModel::builder()
+                .keys(doc! { "user_id": 1, "created_at": -1 })
+                .build(),
+            IndexModel::builder()
+                .keys(doc! { "expires_at": 1 })
+                .options(
+                    IndexOptions::builder()
+                        .expire_after(StdDuration::ZERO)
+                        .build(),
+                )
+                .build(),
+        ];
+        self.phone_otps
+            .create_indexes(phone_otp_indexes)
+            .await
+            .map_err(|e| {
+                CustomError::InternalServerError(format!(
+                    "Failed to create phone OTP indexes: {}",
+                    e
+                ))
+            })?;
+
+        Ok(())
+    }
+}
+
+impl<R: Repository<User>> UserService<R> {
+    /// Create a UserService over any users repository (e.g. an in-memory
+    /// mock); the other collections still come from `client`
+    pub fn with_repository(
+        client: &Client,
+        redis_service: RedisService,
+        config: &AppConfig,
+        users: R,
+    ) -> Self {
         let db = client.database("rust_blogdb");
-        let collection = db.collection::<User>("users");
         let otp_collection = db.collection::<Otp>("otps");
 
         UserService {
-            collection,
+            client: client.clone(),
+            users,
             otp_collection,
+            username_history: db.collection::<UsernameChange>("username_history"),
+            password_resets: db.collection::<PasswordReset>("password_resets"),
+            phone_otps: db.collection::<PhoneOtp>("phone_otps"),
+            posts: db.collection::<Post>("posts"),
+            counters: CounterService::new(client, redis_service.clone()),
+            redis_service,
+            outbox: EmailOutbox::new(client, config),
+            access_tokens: AccessTokenService::new(client),
+            sms: config.sms.clone().map(SmsService::with_config),
+            otp: config.otp.clone(),
+            jwt_secret: config.jwt_secret.clone(),
+            supports_transactions: OnceCell::new(),
         }
     }
 
-    /// Create and store OTP for a user
-    async fn create_otp(&self, user_id: ObjectId, email: &str) -> Result<String, CustomError> {
-        let code = generate_otp_code();
-
-        // Mark any existing unused OTPs as used
-        let _ = self
-            .otp_collection
-            .update_many(
-                doc! { "email": email, "is_used": false },
-                doc! { "$set": { "is_used": true } },
-            )
+    /// Transactions require a replica set or sharded cluster, so probe the
+    /// deployment and remember the answer. A failed probe isn't remembered,
+    /// so the next call tries again.
+    #[tracing::instrument(skip_all)]
+    async fn supports_transactions(&self) -> bool {
+        let probed = self
+            .supports_transactions
+            .get_or_try_init(|| async {
+                let reply = self
+                    .client
+                    .database("admin")
+                    .run_command(doc! { "hello": 1 })
+                    .await?;
+                Ok::<_, mongodb::error::Error>(
+                    reply.get_str("setName").is_ok()
+                        || reply
+                            .get_str("msg")
+                            .map(|m| m == "isdbgrid")
+                            .unwrap_or(false),
+                )
+            })
             .await;
 
-        // Create new OTP
+        match probed {
+            Ok(supported) => *supported,
+            Err(e) => {
+                log::warn!("Failed to probe MongoDB for transaction support: {}", e);
+                false
+            }
+        }
+    }
+
+    /// Create and store OTP for a user
+    #[tracing::instrument(skip_all)]
+    async fn create_otp(
+        &self,
+        user_id: ObjectId,
+        email: &str,
+        session: Option<&mut ClientSession>,
+    ) -> Result<String, CustomError> {
+        let otp_config = &self.otp;
+        let code = generate_otp_code(otp_config.length);
+
+        // Create new OTP; it supersedes older ones since only the latest is accepted
         let otp = Otp {
             id: None,
             user_id,
             email: email.to_string(),
-            code: code.clone(),
-            expires_at: Utc::now() + Duration::minutes(OTP_EXPIRATION_MINUTES),
+            code_hash: hash_key(&code),
+            attempts: 0,
+            expires_at: Utc::now() + Duration::minutes(otp_config.expiry_minutes),
             is_used: false,
             created_at: Utc::now(),
         };
 
-        self.otp_collection
-            .insert_one(otp)
-            .await
-            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+        match session {
+            Some(s) => self.otp_collection.insert_one(otp).session(s).await,
+            None => self.otp_collection.insert_one(otp).await,
+        }
+        .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
 
         Ok(code)
     }
 
-    /// Send OTP email to user
-    async fn send_otp_email(&self, email: &str, otp_code: &str) -> Result<(), CustomError> {
-        let email_service = EmailService::new()
-            .map_err(|e| CustomError::InternalServerError(format!("Email service error: {}", e)))?;
+    /// Queue the OTP email and try to deliver it right away; failed
+    /// deliveries stay in the outbox and are retried by the worker
+    #[tracing::instrument(skip_all)]
+    async fn send_otp_email(
+        &self,
+        email: &str,
+        otp_code: &str,
+        locale: Locale,
+    ) -> Result<(), CustomError> {
+        let queued = self
+            .outbox
+            .enqueue(
+                email,
+                OutboxPayload::Verification {
+                    otp_code: otp_code.to_string(),
+                    locale,
+                },
+                None,
+            )
+            .await?;
 
-        email_service
-            .send_verification_email(email, otp_code)
-            .await
-            .map_err(|e| {
-                CustomError::InternalServerError(format!("Failed to send email: {}", e))
-            })?;
+        if let Err(e) = self.outbox.dispatch(&queued).await {
+            log::warn!("Verification email to {} queued for retry: {}", email, e);
+        }
 
         Ok(())
     }
 
+    /// Insert the user and, when codes go out by email, their OTP and the
+    /// verification email into the outbox
+    #[tracing::instrument(skip_all)]
+    async fn insert_registration(
+        &self,
+        new_user: &User,
+        mut session: Option<&mut ClientSession>,
+    ) -> Result<(ObjectId, Option<OutboxEmail>), CustomError> {
+        let user_id = match session.as_deref_mut() {
+            Some(s) => self.users.insert_in_session(new_user, s).await?,
+            None => self.users.insert(new_user).await?,
+        };
+
+        if !self.otp.channel.sends_email() {
+            return Ok((user_id, None));
+        }
+
+        let otp_code = self
+            .create_otp(user_id, &new_user.email, session.as_deref_mut())
+            .await?;
+
+        let queued = self
+            .outbox
+            .enqueue(
+                &new_user.email,
+                OutboxPayload::Verification {
+                    otp_code,
+                    locale: new_user.locale,
+                },
+                session,
+            )
+            .await?;
+
+        Ok((user_id, Some(queued)))
+    }
+
+    #[tracing::instrument(skip_all)]
     pub async fn create_user(
         &self,
         username: String,
         email: String,
         password: String,
         phone_number: String,
+        locale: Locale,
     ) -> Result<ObjectId, CustomError> {
         // Check if email already exists
         if self.email_exists(&email).await.map_err(|_| {
@@ -120,89 +578,187 @@ impl UserService {
             id: None,
             username,
             email: email.clone(),
+            phone_hash: Some(phone_hash(&phone_number)),
             phone_number,
-            password: hashed_password,
+            profile_token: None,
+            password: Some(hashed_password),
+            providers: Vec::new(),
             profile_picture: None,
             is_email_verified: false,
+            is_phone_verified: false,
+            is_verified: false,
+            role: Role::User,
+            locale,
+            suspended_until: None,
+            shadow_banned: false,
+            badges: Vec::new(),
+            interests: Vec::new(),
+            sensitive_content: SensitiveContent::default(),
+            content_languages: Vec::new(),
             created_at: Utc::now(),
             updated_at: Utc::now(),
         };
 
-        // Insert the user
-        let result = self
-            .collection
-            .insert_one(new_user)
-            .await
-            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+        // Insert user, OTP and outbox email atomically when the deployment allows it
+        let (user_id, queued) = if self.supports_transactions().await {
+            let mut session = self
+                .client
+                .start_session()
+                .await
+                .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+            session
+                .start_transaction()
+                .await
+                .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
 
-        // Get the inserted ID
-        let user_id = result.inserted_id.as_object_id().ok_or_else(|| {
-            CustomError::InternalServerError("Failed to get inserted ID".to_string())
-        })?;
+            match self
+                .insert_registration(&new_user, Some(&mut session))
+                .await
+            {
+                Ok(inserted) => {
+                    session
+                        .commit_transaction()
+                        .await
+                        .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+                    inserted
+                }
+                Err(e) => {
+                    let _ = session.abort_transaction().await;
+                    return Err(e);
+                }
+            }
+        } else {
+            self.insert_registration(&new_user, None).await?
+        };
+
+        // Deliver the verification email now; the outbox worker retries failures
+        if let Some(queued) = queued
+            && let Err(e) = self.outbox.dispatch(&queued).await
+        {
+            log::warn!("Verification email to {} queued for retry: {}", email, e);
+        }
 
-        // Generate and send OTP
-        let otp_code = self.create_otp(user_id, &email).await?;
-        self.send_otp_email(&email, &otp_code).await?;
+        // The account exists either way; a failed text can be resent
+        if self.otp.channel.sends_sms()
+            && let Err(e) = self.send_phone_otp(&email).await
+        {
+            log::warn!("Verification SMS for {} not sent: {}", email, e);
+        }
 
         Ok(user_id)
     }
 
     /// Verify user's email with OTP
-    pub async fn verify_email(&self, email: &str, otp_code: &str) -> Result<(), CustomError> {
-        // Find the OTP
+    #[tracing::instrument(skip_all)]
+    pub async fn verify_email(&self, email: &str, otp_code: &str) -> Result<ObjectId, CustomError> {
+        // Only the most recently issued OTP for the email is valid
         let otp = self
             .otp_collection
-            .find_one(doc! {
-                "email": email,
-                "code": otp_code,
-                "is_used": false
-            })
+            .find_one(doc! { "email": email })
+            .sort(doc! { "created_at": -1 })
             .await
             .map_err(|e| CustomError::InternalServerError(e.to_string()))?
-            .ok_or_else(|| CustomError::BadRequestError("Invalid OTP code".to_string()))?;
+            .filter(|otp| !otp.is_used)
+            .ok_or_else(|| CustomError::coded(ErrorCode::AuthInvalidOtp, "Invalid OTP code"))?;
+        let otp_id = otp
+            .id
+            .ok_or_else(|| CustomError::InternalServerError("OTP ID missing".to_string()))?;
 
         // Check if OTP is expired
         if otp.expires_at < Utc::now() {
-            return Err(CustomError::BadRequestError("OTP has expired".to_string()));
+            return Err(CustomError::coded(
+                ErrorCode::AuthOtpExpired,
+                "OTP has expired",
+            ));
+        }
+        claim_otp_attempt(&self.otp_collection, &otp_id).await?;
+        if otp.code_hash != hash_key(otp_code) {
+            return Err(CustomError::coded(
+                ErrorCode::AuthInvalidOtp,
+                "Invalid OTP code",
+            ));
         }
 
         // Mark OTP as used
         self.otp_collection
-            .update_one(doc! { "_id": otp.id }, doc! { "$set": { "is_used": true } })
+            .update_one(doc! { "_id": otp_id }, doc! { "$set": { "is_used": true } })
             .await
             .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
 
         // Update user's email verification status
-        self.collection
+        self.users
             .update_one(
                 doc! { "email": email },
                 doc! {
                     "$set": {
                         "is_email_verified": true,
-                        "updated_at": Utc::now().to_rfc3339()
+                        "updated_at": bson_now()
                     }
                 },
             )
-            .await
-            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+            .await?;
+
+        if let Some(user) = self.users.find_by_id(&otp.user_id).await? {
+            self.queue_onboarding_emails(otp.user_id, &user).await?;
+        }
+
+        Ok(otp.user_id)
+    }
+
+    /// Send the welcome email now and schedule the onboarding tips; both
+    /// are skipped by the outbox if the user opts out
+    #[tracing::instrument(skip_all)]
+    async fn queue_onboarding_emails(
+        &self,
+        user_id: ObjectId,
+        user: &User,
+    ) -> Result<(), CustomError> {
+        let welcome = self
+            .outbox
+            .enqueue(
+                &user.email,
+                OutboxPayload::Welcome {
+                    user_id,
+                    username: user.username.clone(),
+                    locale: user.locale,
+                },
+                None,
+            )
+            .await?;
+
+        if let Err(e) = self.outbox.dispatch(&welcome).await {
+            log::warn!("Welcome email to {} queued for retry: {}", user.email, e);
+        }
+
+        self.outbox
+            .enqueue_at(
+                &user.email,
+                OutboxPayload::OnboardingTips {
+                    user_id,
+                    username: user.username.clone(),
+                    locale: user.locale,
+                },
+                Utc::now() + Duration::hours(ONBOARDING_TIPS_DELAY_HOURS),
+                None,
+            )
+            .await?;
 
         Ok(())
     }
 
-    /// Resend OTP to user's email
+    /// Resend a verification code on every channel codes go out on
+    #[tracing::instrument(skip_all)]
     pub async fn resend_otp(&self, email: &str) -> Result<(), CustomError> {
         // Find the user
         let user = self
-            .collection
+            .users
             .find_one(doc! { "email": email })
-            .await
-            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
-            .ok_or_else(|| CustomError::NotFoundError("User not found".to_string()))?;
+            .await?
+            .ok_or_else(|| CustomError::coded(ErrorCode::UserNotFound, "User not found"))?;
 
-        // Check if already verified
-        if user.is_email_verified {
+        if self.is_activated(&user) {
             return Err(CustomError::BadRequestError(
-                "Email is already verified".to_string(),
+                "Account is already verified".to_string(),
             ));
         }
 
@@ -211,74 +767,875 @@ impl UserService {
             .ok_or_else(|| CustomError::InternalServerError("User ID missing".to_string()))?;
 
         // Generate and send new OTP
-        let otp_code = self.create_otp(user_id, email).await?;
-        self.send_otp_email(email, &otp_code).await?;
+        if self.otp.channel.sends_email() {
+            let otp_code = self.create_otp(user_id, email, None).await?;
+            self.send_otp_email(email, &otp_code, user.locale).await?;
+        }
+        if self.otp.channel.sends_sms() {
+            self.send_phone_otp(email).await?;
+        }
 
         Ok(())
     }
 
-    async fn email_exists(&self, email: &str) -> Result<bool, mongodb::error::Error> {
+    /// Text a verification code to the phone number of the account with
+    /// `email`. A new code replaces any earlier one.
+    #[tracing::instrument(skip_all)]
+    pub async fn send_phone_otp(&self, email: &str) -> Result<(), CustomError> {
+        let sms = self.sms.as_ref().ok_or_else(|| {
+            CustomError::BadRequestError("Phone verification is not enabled".to_string())
+        })?;
+        let user = self
+            .users
+            .find_one(doc! { "email": email })
+            .await?
+            .ok_or_else(|| CustomError::coded(ErrorCode::UserNotFound, "User not found"))?;
+        if user.phone_number.trim().is_empty() {
+            return Err(CustomError::BadRequestError(
+                "This account has no phone number".to_string(),
+            ));
+        }
+        if user.is_phone_verified {
+            return Err(CustomError::BadRequestError(
+                "Phone number is already verified".to_string(),
+            ));
+        }
+        let user_id = user
+            .id
+            .ok_or_else(|| CustomError::InternalServerError("User ID missing".to_string()))?;
+
+        let otp_config = &self.otp;
+        let code = generate_otp_code(otp_config.length);
+        self.phone_otps
+            .delete_many(doc! { "user_id": user_id })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+        self.phone_otps
+            .insert_one(PhoneOtp {
+                id: None,
+                user_id,
+                phone_number: user.phone_number.clone(),
+                code_hash: hash_key(&code),
+                attempts: 0,
+                expires_at: Utc::now() + Duration::minutes(otp_config.expiry_minutes),
+                created_at: Utc::now(),
+            })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        let message = user.locale.t_args(
+            "sms-otp-code",
+            &[
+                ("code", code.into()),
+                ("minutes", otp_config.expiry_minutes.into()),
+            ],
+        );
+        sms.send(&user.phone_number, &message).await.map_err(|e| {
+            log::warn!("Verification SMS to user {} failed: {}", user_id, e);
+            CustomError::InternalServerError("Failed to send verification SMS".to_string())
+        })
+    }
+
+    /// Confirm the phone number of the account with `email` using the code
+    /// texted to it. Codes work once, and only for the number they went to.
+    #[tracing::instrument(skip_all)]
+    pub async fn verify_phone(&self, email: &str, otp_code: &str) -> Result<ObjectId, CustomError> {
+        let user = self
+            .users
+            .find_one(doc! { "email": email })
+            .await?
+            .ok_or_else(|| CustomError::coded(ErrorCode::AuthInvalidOtp, "Invalid OTP code"))?;
+        let user_id = user
+            .id
+            .ok_or_else(|| CustomError::InternalServerError("User ID missing".to_string()))?;
+
+        let otp = self
+            .phone_otps
+            .find_one(doc! { "user_id": user_id })
+            .sort(doc! { "created_at": -1 })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .filter(|otp| otp.phone_number == user.phone_number)
+            .ok_or_else(|| CustomError::coded(ErrorCode::AuthInvalidOtp, "Invalid OTP code"))?;
+        let otp_id = otp
+            .id
+            .ok_or_else(|| CustomError::InternalServerError("OTP ID missing".to_string()))?;
+        if otp.expires_at < Utc::now() {
+            return Err(CustomError::coded(
+                ErrorCode::AuthOtpExpired,
+                "OTP has expired",
+            ));
+        }
+        claim_otp_attempt(&self.phone_otps, &otp_id).await?;
+        if otp.code_hash != hash_key(otp_code) {
+            return Err(CustomError::coded(
+                ErrorCode::AuthInvalidOtp,
+                "Invalid OTP code",
+            ));
+        }
+
+        self.phone_otps
+            .delete_many(doc! { "user_id": user_id })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+        self.users
+            .update(
+                &user_id,
+                doc! { "is_phone_verified": true, "updated_at": bson_now() },
+            )
+            .await?;
+
+        Ok(user_id)
+    }
+
+    /// Email a password reset token. Unknown emails are ignored without an
+    /// error, so the endpoint doesn't reveal who has an account. A new
+    /// request replaces any earlier token.
+    #[tracing::instrument(skip_all)]
+    pub async fn request_password_reset(&self, email: &str) -> Result<(), CustomError> {
+        let Some(user) = self.users.find_one(doc! { "email": email }).await? else {
+            return Ok(());
+        };
+        let user_id = user
+            .id
+            .ok_or_else(|| CustomError::InternalServerError("User ID missing".to_string()))?;
+
+        self.password_resets
+            .delete_many(doc! { "user_id": user_id })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        let reset_token = generate_reset_token();
+        let now = Utc::now();
+        let reset = PasswordReset {
+            id: None,
+            user_id,
+            token_hash: hash_key(&reset_token),
+            expires_at: now + Duration::minutes(PASSWORD_RESET_EXPIRATION_MINUTES),
+            created_at: now,
+        };
+        self.password_resets.insert_one(&reset).await.map_err(|e| {
+            CustomError::InternalServerError(format!("Failed to create password reset: {}", e))
+        })?;
+
+        // Left to the outbox worker: sending inline would make known emails
+        // measurably slower to answer than unknown ones
+        self.outbox
+            .enqueue(
+                email,
+                OutboxPayload::PasswordReset {
+                    reset_token,
+                    locale: user.locale,
+                },
+                None,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Set a new password with a reset token, which works only once.
+    /// Returns the user whose password changed.
+    #[tracing::instrument(skip_all)]
+    pub async fn reset_password(
+        &self,
+        reset_token: &str,
+        new_password: &str,
+    ) -> Result<ObjectId, CustomError> {
+        password_validation::validate_password(new_password)?;
+
+        let reset = self
+            .password_resets
+            .find_one_and_delete(doc! {
+                "token_hash": hash_key(reset_token),
+                "expires_at": { "$gt": bson_now() },
+            })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .ok_or_else(|| {
+                CustomError::coded(
+                    ErrorCode::AuthInvalidResetToken,
+                    "Invalid or expired reset token",
+                )
+            })?;
+
+        let hashed_password = hashing::hash_password(new_password)
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+        self.users
+            .update(
+                &reset.user_id,
+                doc! { "password": hashed_password, "updated_at": bson_now() },
+            )
+            .await?;
+
+        Ok(reset.user_id)
+    }
+
+    /// Replace a signed-in user's password after checking the current one
+    #[tracing::instrument(skip_all)]
+    pub async fn change_password(
+        &self,
+        user_id: &ObjectId,
+        current_password: &str,
+        new_password: &str,
+    ) -> Result<(), CustomError> {
+        let user = self
+            .users
+            .find_by_id(user_id)
+            .await?
+            .ok_or_else(|| CustomError::coded(ErrorCode::UserNotFound, "User not found"))?;
+
+        check_current_password(&user, current_password)?;
+        if current_password == new_password {
+            return Err(CustomError::BadRequestError(
+                "New password must differ from the current one".to_string(),
+            ));
+        }
+        password_validation::validate_password(new_password)?;
+
+        let hashed_password = hashing::hash_password(new_password)
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+        self.users
+            .update(
+                user_id,
+                doc! { "password": hashed_password, "updated_at": bson_now() },
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Soft-delete an account after confirming its password. Personal details
+    /// are scrubbed so the email, username and phone number can be reused;
+    /// the account as it was is returned for cleaning up what it owned.
+    #[tracing::instrument(skip_all)]
+    pub async fn delete_account(
+        &self,
+        user_id: &ObjectId,
+        password: &str,
+    ) -> Result<User, CustomError> {
+        let user = self
+            .users
+            .find_by_id(user_id)
+            .await?
+            .ok_or_else(|| CustomError::coded(ErrorCode::UserNotFound, "User not found"))?;
+        check_current_password(&user, password)?;
+
+        let placeholder = format!("deleted-{}", user_id.to_hex());
+        let deleted = self
+            .users
+            .update(
+                user_id,
+                doc! {
+                    "username": &placeholder,
+                    "email": format!("{}@deleted.invalid", placeholder),
+                    "password": Bson::Null,
+                    "phone_number": "",
+                    "phone_hash": Bson::Null,
+                    "is_phone_verified": false,
+                    "providers": [],
+                    "profile_token": Bson::Null,
+                    "profile_picture": Bson::Null,
+                    "interests": [],
+                    "deleted_at": bson_now(),
+                    "updated_at": bson_now(),
+                },
+            )
+            .await?;
+        if !deleted {
+            return Err(CustomError::coded(
+                ErrorCode::UserNotFound,
+                "User not found",
+            ));
+        }
+
+        // Old names would otherwise keep redirecting to the deleted account
+        self.username_history
+            .delete_many(doc! { "user_id": user_id })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        Ok(user)
+    }
+
+    /// Rename a user, recording the old name. Names can change once per
+    /// cooldown, and a name given up recently stays reserved for its previous
+    /// owner so lookups of it still redirect there.
+    #[tracing::instrument(skip_all)]
+    pub async fn change_username(
+        &self,
+        user_id: &ObjectId,
+        new_username: &str,
+    ) -> Result<String, CustomError> {
+        let user = self
+            .users
+            .find_by_id(user_id)
+            .await?
+            .ok_or_else(|| CustomError::coded(ErrorCode::UserNotFound, "User not found"))?;
+        let new_username = new_username.trim();
+        if user.username == new_username {
+            return Ok(user.username);
+        }
+
+        let now = Utc::now();
+        let last_change = self
+            .username_history
+            .find_one(doc! { "user_id": user_id })
+            .sort(doc! { "changed_at": -1 })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+        if let Some(last_change) = last_change {
+            let next_allowed =
+                last_change.changed_at + Duration::days(USERNAME_CHANGE_COOLDOWN_DAYS);
+            if next_allowed > now {
+                return Err(CustomError::TooManyRequestsError(format!(
+                    "Username can be changed again after {}",
+                    next_allowed.format("%Y-%m-%d")
+                )));
+            }
+        }
+
+        let reserved_since =
+            bson::DateTime::from_chrono(now - Duration::days(USERNAME_REDIRECT_GRACE_DAYS));
+        let reserved = self
+            .username_history
+            .count_documents(doc! {
+                "old_username": new_username,
+                "user_id": { "$ne": user_id },
+                "changed_at": { "$gte": reserved_since },
+            })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            > 0;
+        if reserved || self.username_exists(new_username).await? {
+            return Err(CustomError::ConflictError(
+                "Username already exists".to_string(),
+            ));
+        }
+
+        self.users
+            .update(
+                user_id,
+                doc! { "username": new_username, "updated_at": bson_now() },
+            )
+            .await?;
+        self.username_history
+            .insert_one(UsernameChange {
+                id: None,
+                user_id: *user_id,
+                old_username: user.username,
+                new_username: new_username.to_string(),
+                changed_at: now,
+            })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        Ok(new_username.to_string())
+    }
+
+    /// Store how the user wants sensitive content shown
+    #[tracing::instrument(skip_all)]
+    pub async fn set_sensitive_content(
+        &self,
+        user_id: &ObjectId,
+        preference: SensitiveContent,
+    ) -> Result<(), CustomError> {
+        let found = self
+            .users
+            .update(
+                user_id,
+                doc! { "sensitive_content": preference.name(), "updated_at": bson_now() },
+            )
+            .await?;
+        if !found {
+            return Err(CustomError::coded(
+                ErrorCode::UserNotFound,
+                "User not found",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether posts flagged as sensitive are left out of the user's feeds
+    #[tracing::instrument(skip_all)]
+    pub async fn hides_sensitive(&self, user_id: &ObjectId) -> Result<bool, CustomError> {
+        Ok(self
+            .users
+            .find_by_id(user_id)
+            .await?
+            .is_some_and(|user| user.sensitive_content == SensitiveContent::Hide))
+    }
+
+    /// Store the languages the user wants to see in feeds
+    #[tracing::instrument(skip_all)]
+    pub async fn set_content_languages(
+        &self,
+        user_id: &ObjectId,
+        languages: &[String],
+    ) -> Result<(), CustomError> {
+        let found = self
+            .users
+            .update(
+                user_id,
+                doc! { "content_languages": languages, "updated_at": bson_now() },
+            )
+            .await?;
+        if !found {
+            return Err(CustomError::coded(
+                ErrorCode::UserNotFound,
+                "User not found",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Languages the user's feeds are limited to; empty for all of them
+    #[tracing::instrument(skip_all)]
+    pub async fn content_languages(&self, user_id: &ObjectId) -> Result<Vec<String>, CustomError> {
+        Ok(self
+            .users
+            .find_by_id(user_id)
+            .await?
+            .map(|user| user.content_languages)
+            .unwrap_or_default())
+    }
+
+    /// Find who a username belongs to. A name given up within the grace period
+    /// still resolves to its previous owner; the flag says whether it did.
+    #[tracing::instrument(skip_all)]
+    pub async fn resolve_username(
+        &self,
+        username: &str,
+    ) -> Result<Option<(ObjectId, bool)>, CustomError> {
+        if let Some(user) = self.users.find_one(doc! { "username": username }).await? {
+            return Ok(user.id.map(|id| (id, false)));
+        }
+
+        let since =
+            bson::DateTime::from_chrono(Utc::now() - Duration::days(USERNAME_REDIRECT_GRACE_DAYS));
+        let change = self
+            .username_history
+            .find_one(doc! { "old_username": username, "changed_at": { "$gte": since } })
+            .sort(doc! { "changed_at": -1 })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        Ok(change.map(|change| (change.user_id, true)))
+    }
+
+    /// A user's own account
+    #[tracing::instrument(skip_all)]
+    pub async fn get_user(&self, user_id: &ObjectId) -> Result<Option<User>, CustomError> {
+        self.users.find_by_id(user_id).await
+    }
+
+    /// Profile fields and counters anyone signed in may see
+    #[tracing::instrument(skip_all)]
+    pub async fn public_profile(
+        &self,
+        user_id: &ObjectId,
+    ) -> Result<Option<PublicProfile>, CustomError> {
+        let Some(user) = self.users.find_by_id(user_id).await? else {
+            return Ok(None);
+        };
+
+        let post_count = self
+            .posts
+            .count_documents(doc! {
+                "author_id": user_id,
+                "group_id": Bson::Null,
+                "deleted_at": Bson::Null,
+                "hidden_at": Bson::Null,
+            })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+        let friend_count = self
+            .counters
+            .get(Counter::Friends, &user_id.to_hex())
+            .await?
+            .max(0) as u64;
+        let pinned_posts = self
+            .posts
+            .find(doc! {
+                "author_id": user_id,
+                "group_id": Bson::Null,
+                "pinned_at": { "$ne": Bson::Null },
+                "deleted_at": Bson::Null,
+                "hidden_at": Bson::Null,
+            })
+            .sort(doc! { "pinned_at": -1 })
+            .limit(MAX_PINNED_POSTS as i64)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        Ok(Some(PublicProfile {
+            id: *user_id,
+            username: user.username,
+            profile_picture: user.profile_picture,
+            is_verified: user.is_verified,
+            badges: user.badges,
+            post_count,
+            friend_count,
+            joined_at: user.created_at,
+            pinned_posts,
+        }))
+    }
+
+    /// The token behind the user's profile QR code, assigned on first use
+    #[tracing::instrument(skip_all)]
+    pub async fn profile_token(&self, user_id: &ObjectId) -> Result<String, CustomError> {
+        let not_found = || CustomError::coded(ErrorCode::UserNotFound, "User not found");
+        let user = self
+            .users
+            .find_by_id(user_id)
+            .await?
+            .ok_or_else(not_found)?;
+        if let Some(token) = user.profile_token {
+            return Ok(token);
+        }
+
+        for _ in 0..PROFILE_TOKEN_ATTEMPTS {
+            let token = random_profile_token();
+            // Deleted accounts keep their token, so look past the soft delete
+            let taken = self
+                .users
+                .count_including_deleted(doc! { "profile_token": &token })
+                .await?
+                > 0;
+            if taken {
+                continue;
+            }
+
+            // Keep a token another request may have set meanwhile
+            self.users
+                .update_one(
+                    doc! { "_id": user_id, "profile_token": Bson::Null },
+                    doc! { "$set": { "profile_token": token } },
+                )
+                .await?;
+
+            let user = self
+                .users
+                .find_by_id(user_id)
+                .await?
+                .ok_or_else(not_found)?;
+            if let Some(token) = user.profile_token {
+                return Ok(token);
+            }
+        }
+
+        Err(CustomError::InternalServerError(
+            "Failed to assign a profile QR code".to_string(),
+        ))
+    }
+
+    /// The user a profile QR token belongs to
+    #[tracing::instrument(skip_all)]
+    pub async fn resolve_profile_token(
+        &self,
+        token: &str,
+    ) -> Result<Option<ObjectId>, CustomError> {
+        Ok(self
+            .users
+            .find_one(doc! { "profile_token": token })
+            .await?
+            .and_then(|user| user.id))
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn email_exists(&self, email: &str) -> Result<bool, CustomError> {
         let count = self
-            .collection
-            .count_documents(doc! { "email": email })
+            .users
+            .count_including_deleted(doc! { "email": email })
             .await?;
         Ok(count > 0)
     }
 
-    async fn username_exists(&self, username: &str) -> Result<bool, mongodb::error::Error> {
+    #[tracing::instrument(skip_all)]
+    async fn username_exists(&self, username: &str) -> Result<bool, CustomError> {
         let count = self
-            .collection
-            .count_documents(doc! { "username": username })
+            .users
+            .count_including_deleted(doc! { "username": username })
             .await?;
         Ok(count > 0)
     }
 
-    async fn phone_number(&self, phone_number: &str) -> Result<bool, mongodb::error::Error> {
+    #[tracing::instrument(skip_all)]
+    async fn phone_number(&self, phone_number: &str) -> Result<bool, CustomError> {
         let count = self
-            .collection
-            .count_documents(doc! { "phone_number": phone_number})
+            .users
+            .count_including_deleted(doc! { "phone_number": phone_number })
             .await?;
         Ok(count > 0)
     }
 
+    #[tracing::instrument(skip_all)]
     pub async fn authenticate_user(
         &self,
         username: &str,
         password: &str,
     ) -> Result<User, CustomError> {
         let user = self
-            .collection
+            .users
             .find_one(doc! { "username": username })
             .await
             .map_err(|_| CustomError::InternalServerError("Database error".to_string()))?
-            .ok_or_else(|| CustomError::UnauthorizedError("Invalid credentials".to_string()))?;
+            .ok_or_else(|| {
+                CustomError::coded(ErrorCode::AuthInvalidCredentials, "Invalid credentials")
+            })?;
 
-        if !hashing::verify_password(password, &user.password)
+        let Some(password_hash) = user.password.as_deref() else {
+            return Err(CustomError::coded(
+                ErrorCode::AuthInvalidCredentials,
+                "Invalid credentials",
+            ));
+        };
+        if !hashing::verify_password(password, password_hash)
             .map_err(|_| CustomError::InternalServerError("Invalid credentials".to_string()))?
         {
-            return Err(CustomError::UnauthorizedError(
-                "Invalid credentials".to_string(),
+            return Err(CustomError::coded(
+                ErrorCode::AuthInvalidCredentials,
+                "Invalid credentials",
             ));
         }
 
         Ok(user)
     }
 
+    /// Accounts are activated through whichever channel codes go out on
+    fn is_activated(&self, user: &User) -> bool {
+        let channel = self.otp.channel;
+        (channel.sends_email() && user.is_email_verified)
+            || (channel.sends_sms() && user.is_phone_verified)
+    }
+
+    #[tracing::instrument(skip_all)]
     pub async fn login_fn(
         &self,
         login_data: LoginRequests,
-        redis_service: Option<&RedisService>,
-    ) -> Result<String, CustomError> {
+    ) -> Result<(ObjectId, TokenPair), CustomError> {
         // Authenticate user
         let user = self
             .authenticate_user(&login_data.username, &login_data.password)
             .await?;
 
-        // Check if email is verified
-        if !user.is_email_verified {
-            return Err(CustomError::UnauthorizedError(
-                "Please verify your email before logging in".to_string(),
-            ));
+        if !self.is_activated(&user) {
+            return Err(if self.otp.channel.sends_email() {
+                CustomError::coded(
+                    ErrorCode::AuthEmailNotVerified,
+                    "Please verify your email before logging in",
+                )
+            } else {
+                CustomError::coded(
+                    ErrorCode::AuthPhoneNotVerified,
+                    "Please verify your phone number before logging in",
+                )
+            });
+        }
+
+        self.issue_tokens(&user, &self.redis_service).await
+    }
+
+    /// Log in with an external account. The account's user is found by
+    /// the link made at first login, else by verified email (linking the
+    /// account to it), else created without a password. Linking to an
+    /// account whose email was never verified removes its password and
+    /// signs it out everywhere, since its owner may not be the address's.
+    #[tracing::instrument(skip_all)]
+    pub async fn login_with_provider(
+        &self,
+        profile: OAuthProfile,
+        redis_service: &RedisService,
+    ) -> Result<(ObjectId, TokenPair), CustomError> {
+        let provider = profile.provider.name();
+        let linked = self
+            .users
+            .find_one(doc! {
+                "providers": { "$elemMatch": {
+                    "provider": provider,
+                    "provider_user_id": &profile.provider_user_id,
+                } }
+            })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        let user = match linked {
+            Some(user) => {
+                self.users
+                    .update_one(
+                        doc! {
+                            "_id": user.id,
+                            "providers.provider": provider,
+                            "providers.provider_user_id": &profile.provider_user_id,
+                        },
+                        doc! { "$set": { "providers.$.username": &profile.username } },
+                    )
+                    .await?;
+                user
+            }
+            None => {
+                let email = profile.email.clone().ok_or_else(|| {
+                    CustomError::BadRequestError(format!(
+                        "Your {} account has no verified email address",
+                        provider
+                    ))
+                })?;
+                let link = LinkedProvider {
+                    provider: profile.provider,
+                    provider_user_id: profile.provider_user_id.clone(),
+                    username: profile.username.clone(),
+                    linked_at: Utc::now(),
+                };
+
+                match self
+                    .users
+                    .find_one(doc! { "email": &email })
+                    .await
+                    .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+                {
+                    Some(mut user) => {
+                        let user_id = user.id.ok_or_else(|| {
+                            CustomError::InternalServerError("User ID missing".to_string())
+                        })?;
+                        let link = doc! {
+                            "provider": link.provider.name(),
+                            "provider_user_id": &link.provider_user_id,
+                            "username": &link.username,
+                            "linked_at": bson::DateTime::from_chrono(link.linked_at),
+                        };
+                        if user.is_email_verified {
+                            self.users
+                                .update_one(
+                                    doc! { "_id": user_id },
+                                    doc! {
+                                        "$push": { "providers": link },
+                                        "$set": { "updated_at": bson_now() },
+                                    },
+                                )
+                                .await?;
+                        } else {
+                            // Anyone could have registered the address, so
+                            // whoever set the password is locked out before
+                            // the provider's vouching makes it verified
+                            self.users
+                                .update_one(
+                                    doc! { "_id": user_id },
+                                    doc! {
+                                        "$push": { "providers": link },
+                                        "$set": { "is_email_verified": true, "updated_at": bson_now() },
+                                        "$unset": { "password": "" },
+                                    },
+                                )
+                                .await?;
+                            redis_service
+                                .invalidate_all_sessions(&user_id.to_hex())
+                                .await
+                                .map_err(CustomError::InternalServerError)?;
+                            self.access_tokens.revoke_all_for_user(&user_id).await?;
+                            user.password = None;
+                            user.is_email_verified = true;
+                        }
+                        user
+                    }
+                    None => self.create_provider_user(&profile, email, link).await?,
+                }
+            }
+        };
+
+        self.issue_tokens(&user, redis_service).await
+    }
+
+    async fn create_provider_user(
+        &self,
+        profile: &OAuthProfile,
+        email: String,
+        link: LinkedProvider,
+    ) -> Result<User, CustomError> {
+        let username = self.available_username(&profile.username).await?;
+        let mut user = User {
+            id: None,
+            username,
+            email,
+            password: None,
+            phone_number: String::new(),
+            providers: vec![link],
+            phone_hash: None,
+            profile_token: None,
+            profile_picture: profile.avatar_url.clone(),
+            is_email_verified: true,
+            is_phone_verified: false,
+            is_verified: false,
+            role: Role::User,
+            locale: Locale::default(),
+            suspended_until: None,
+            shadow_banned: false,
+            badges: Vec::new(),
+            interests: Vec::new(),
+            sensitive_content: SensitiveContent::default(),
+            content_languages: Vec::new(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        user.id = Some(self.users.insert(&user).await?);
+        Ok(user)
+    }
+
+    /// `wanted` if nobody has it, else with a random numeric suffix. Kept
+    /// within the 3 to 30 characters registration allows.
+    async fn available_username(&self, wanted: &str) -> Result<String, CustomError> {
+        let mut base: String = wanted.chars().take(30).collect();
+        while base.chars().count() < 3 {
+            base.push('_');
+        }
+
+        let mut candidate = base.clone();
+        for _ in 0..5 {
+            if !self.username_exists(&candidate).await? {
+                return Ok(candidate);
+            }
+            let suffix = format!("-{}", rand::rng().random_range(1000..10000));
+            let stem: String = base.chars().take(30 - suffix.len()).collect();
+            candidate = format!("{}{}", stem, suffix);
+        }
+
+        Err(CustomError::ConflictError(
+            "Could not find a free username; please register instead".to_string(),
+        ))
+    }
+
+    /// Trade a refresh token for a new token pair. The user is reloaded so
+    /// role changes and suspensions apply from the next refresh.
+    #[tracing::instrument(skip_all)]
+    pub async fn refresh_session(
+        &self,
+        user_id: &ObjectId,
+        redis_service: &RedisService,
+    ) -> Result<TokenPair, CustomError> {
+        let user = self.users.find_by_id(user_id).await?.ok_or_else(|| {
+            CustomError::coded(ErrorCode::AuthInvalidRefreshToken, "Invalid refresh token")
+        })?;
+
+        let (_, tokens) = self.issue_tokens(&user, redis_service).await?;
+        Ok(tokens)
+    }
+
+    async fn issue_tokens(
+        &self,
+        user: &User,
+        redis_service: &RedisService,
+    ) -> Result<(ObjectId, TokenPair), CustomError> {
+        if let Some(until) = user.suspended_until
+            && until > Utc::now()
+        {
+            return Err(CustomError::coded(
+                ErrorCode::AuthAccountSuspended,
+                format!("Account suspended until {}", until.to_rfc3339()),
+            )
+            .with_details(json!({ "suspended_until": until.to_rfc3339() })));
         }
 
         // Generate JWT token
@@ -287,17 +1644,147 @@ impl UserService {
             .as_ref()
             .ok_or_else(|| CustomError::InternalServerError("User ID missing".to_string()))?;
 
-        // Create token with Redis session if available
-        let token = if let Some(redis) = redis_service {
-            create_token_with_session(&user_id.to_hex(), redis)
-                .await
-                .map_err(|_| CustomError::BadRequestError("Token generation failed".to_string()))?
-        } else {
-            create_token(&user_id.to_hex())
-                .await
-                .map_err(|_| CustomError::BadRequestError("Token generation failed".to_string()))?
-        };
+        let tokens = create_token_pair(
+            &user_id.to_hex(),
+            user.role,
+            user.locale,
+            &self.jwt_secret,
+            redis_service,
+        )
+        .await
+        .map_err(|_| CustomError::BadRequestError("Token generation failed".to_string()))?;
+
+        Ok((*user_id, tokens))
+    }
+}
+
+impl<R: Repository<User> + 'static> UserServiceTrait for UserService<R> {
+    async fn create_user(
+        &self,
+        username: String,
+        email: String,
+        password: String,
+        phone_number: String,
+        locale: Locale,
+    ) -> Result<ObjectId, CustomError> {
+        Self::create_user(self, username, email, password, phone_number, locale).await
+    }
+
+    async fn verify_email(&self, email: &str, otp_code: &str) -> Result<ObjectId, CustomError> {
+        Self::verify_email(self, email, otp_code).await
+    }
+
+    async fn resend_otp(&self, email: &str) -> Result<(), CustomError> {
+        Self::resend_otp(self, email).await
+    }
+
+    async fn send_phone_otp(&self, email: &str) -> Result<(), CustomError> {
+        Self::send_phone_otp(self, email).await
+    }
+
+    async fn verify_phone(&self, email: &str, otp_code: &str) -> Result<ObjectId, CustomError> {
+        Self::verify_phone(self, email, otp_code).await
+    }
 
-        Ok(token)
+    async fn login_fn(
+        &self,
+        login_data: LoginRequests,
+    ) -> Result<(ObjectId, TokenPair), CustomError> {
+        Self::login_fn(self, login_data).await
+    }
+
+    async fn refresh_session(
+        &self,
+        user_id: &ObjectId,
+        redis_service: &RedisService,
+    ) -> Result<TokenPair, CustomError> {
+        Self::refresh_session(self, user_id, redis_service).await
+    }
+
+    async fn request_password_reset(&self, email: &str) -> Result<(), CustomError> {
+        Self::request_password_reset(self, email).await
     }
+
+    async fn reset_password(
+        &self,
+        reset_token: &str,
+        new_password: &str,
+    ) -> Result<ObjectId, CustomError> {
+        Self::reset_password(self, reset_token, new_password).await
+    }
+
+    async fn change_password(
+        &self,
+        user_id: &ObjectId,
+        current_password: &str,
+        new_password: &str,
+    ) -> Result<(), CustomError> {
+        Self::change_password(self, user_id, current_password, new_password).await
+    }
+
+    async fn delete_account(
+        &self,
+        user_id: &ObjectId,
+        password: &str,
+    ) -> Result<User, CustomError> {
+        Self::delete_account(self, user_id, password).await
+    }
+
+    async fn get_user(&self, user_id: &ObjectId) -> Result<Option<User>, CustomError> {
+        Self::get_user(self, user_id).await
+    }
+
+    async fn change_username(
+        &self,
+        user_id: &ObjectId,
+        new_username: &str,
+    ) -> Result<String, CustomError> {
+        Self::change_username(self, user_id, new_username).await
+    }
+
+    async fn set_content_languages(
+        &self,
+        user_id: &ObjectId,
+        languages: &[String],
+    ) -> Result<(), CustomError> {
+        Self::set_content_languages(self, user_id, languages).await
+    }
+
+    async fn set_sensitive_content(
+        &self,
+        user_id: &ObjectId,
+        preference: SensitiveContent,
+    ) -> Result<(), CustomError> {
+        Self::set_sensitive_content(self, user_id, preference).await
+    }
+
+    async fn public_profile(
+        &self,
+        user_id: &ObjectId,
+    ) -> Result<Option<PublicProfile>, CustomError> {
+        Self::public_profile(self, user_id).await
+    }
+
+    async fn resolve_username(
+        &self,
+        username: &str,
+    ) -> Result<Option<(ObjectId, bool)>, CustomError> {
+        Self::resolve_username(self, username).await
+    }
+
+    async fn resolve_profile_token(&self, token: &str) -> Result<Option<ObjectId>, CustomError> {
+        Self::resolve_profile_token(self, token).await
+    }
+
+    async fn profile_token(&self, user_id: &ObjectId) -> Result<String, CustomError> {
+        Self::profile_token(self, user_id).await
+    }
+}
+
+fn random_profile_token() -> String {
+    rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(PROFILE_TOKEN_LENGTH)
+        .map(char::from)
+        .collect()
 }
@@ -0,0 +1,387 @@
+use crate::database::{MongoConfig, RedisConfig, parse_optional};
+use crate::link_safety::model::LinkSafetyPolicy;
+use crate::middleware::compression::CompressionPolicy;
+use crate::middleware::cors::CorsConfig;
+use crate::middleware::limits::HttpLimits;
+use crate::middleware::rate_limit::RateLimitConfig;
+use crate::middleware::security_headers::SecurityHeadersConfig;
+use crate::oauth::model::GithubOAuthConfig;
+use crate::spam_guard::model::SpamPolicy;
+use crate::utils::email::EmailConfig;
+use crate::utils::otp::OtpConfig;
+use crate::utils::sms::SmsConfig;
+use crate::utils::uploads::CloudinaryConfig;
+use std::env;
+use std::net::IpAddr;
+use std::sync::OnceLock;
+
+static SERVICE_NAME: OnceLock<String> = OnceLock::new();
+
+/// Variables the server cannot run without
+const REQUIRED_VARS: &[&str] = &[
+    "JWT_SECRET",
+    "FINGERPRINT_SALT",
+    "EXPORT_SIGNING_SECRET",
+    "MONGODB_URI",
+    "SMTP_FROM_EMAIL",
+    "CLOUDINARY_CLOUD_NAME",
+    "CLOUDINARY_API_KEY",
+    "CLOUDINARY_API_SECRET",
+];
+
+/// Credentials the selected `EMAIL_PROVIDER` needs on top of `REQUIRED_VARS`
+fn email_provider_vars() -> &'static [&'static str] {
+    match env::var("EMAIL_PROVIDER")
+        .unwrap_or_else(|_| "smtp".to_string())
+        .to_lowercase()
+        .as_str()
+    {
+        "smtp" => &["SMTP_USERNAME", "SMTP_PASSWORD"],
+        "sendgrid" => &["SENDGRID_API_KEY"],
+        "ses" => &["AWS_ACCESS_KEY_ID", "AWS_SECRET_ACCESS_KEY"],
+        _ => &[],
+    }
+}
+
+/// Shortest JWT secret accepted; HS256 keys should be at least 256 bits
+const JWT_SECRET_MIN_LENGTH: usize = 32;
+
+/// Estimated entropy a JWT secret needs, in bits. The character-frequency
+/// estimate undercounts short random values: a 32-character hex key (128
+/// real bits) scores about 115 and rarely under 90, so the threshold leaves
+/// room below that while still rejecting single characters and short
+/// repeated patterns.
+const JWT_SECRET_MIN_ENTROPY_BITS: f64 = 80.0;
+
+/// Why a JWT secret is too weak to sign tokens with, if it is
+fn jwt_secret_problem(secret: &str) -> Option<String> {
+    if secret.len() < JWT_SECRET_MIN_LENGTH {
+        return Some(format!(
+            "JWT_SECRET must be at least {} characters long",
+            JWT_SECRET_MIN_LENGTH
+        ));
+    }
+
+    // Shannon entropy of the character distribution, scaled by length,
+    // rejects repeated or low-variety values such as "aaaa..." or "1212..."
+    let mut counts = std::collections::HashMap::new();
+    for c in secret.chars() {
+        *counts.entry(c).or_insert(0usize) += 1;
+    }
+    let length = secret.chars().count() as f64;
+    let bits_per_char: f64 = counts
+        .values()
+        .map(|&count| {
+            let p = count as f64 / length;
+            -p * p.log2()
+        })
+        .sum();
+    if bits_per_char * length < JWT_SECRET_MIN_ENTROPY_BITS {
+        return Some(
+            "JWT_SECRET is too predictable; generate one with `openssl rand -base64 48`"
+                .to_string(),
+        );
+    }
+
+    None
+}
+
+/// Certificate and private key for serving HTTPS directly
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    pub cert_path: String,
+    pub key_path: String,
+}
+
+/// Address, worker count and optional TLS for the HTTP server
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub host: String,
+    pub port: u16,
+    /// Defaults to the number of physical cores when unset
+    pub workers: Option<usize>,
+    pub tls: Option<TlsConfig>,
+    /// Reverse proxies whose `X-Forwarded-For` is believed
+    /// (`TRUSTED_PROXIES`, comma-separated IPs); empty means clients are
+    /// identified by their peer address only
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+/// Application configuration, loaded and validated once at startup and
+/// handed to services at construction and to handlers as `web::Data`
+#[derive(Clone)]
+pub struct AppConfig {
+    pub service_name: String,
+    pub server: ServerConfig,
+    pub jwt_secret: String,
+    /// Salt for hashed IP and device fingerprints (`FINGERPRINT_SALT`);
+    /// kept apart from the JWT secret so rotating one leaves the other intact
+    pub fingerprint_salt: String,
+    /// Key export download links are signed with (`EXPORT_SIGNING_SECRET`);
+    /// rotating it invalidates outstanding links but not sessions
+    pub export_signing_secret: String,
+    /// Web app origin that `/p/{slug}` share links redirect to and profile
+    /// QR codes point at as `/u/{token}` (`SHARE_LINK_BASE_URL`); without it
+    /// share links return the post as JSON and QR codes link to the API
+    pub share_link_base_url: Option<String>,
+    /// Origin of this API for links built outside a request, such as export
+    /// download links in emails (`PUBLIC_BASE_URL`); defaults to the listen
+    /// address
+    pub public_base_url: String,
+    pub mongo: MongoConfig,
+    pub redis: RedisConfig,
+    pub email: EmailConfig,
+    pub otp: OtpConfig,
+    /// Text message delivery, off unless `SMS_PROVIDER` is set
+    pub sms: Option<SmsConfig>,
+    pub cloudinary: CloudinaryConfig,
+    pub cors: CorsConfig,
+    pub limits: HttpLimits,
+    pub compression: CompressionPolicy,
+    pub security_headers: SecurityHeadersConfig,
+    pub rate_limits: RateLimitConfig,
+    /// Stricter limits for new accounts (`SPAM_*`)
+    pub spam: SpamPolicy,
+    /// Link blocklist and lookup API (`LINK_*`)
+    pub link_safety: LinkSafetyPolicy,
+    /// GitHub login, off unless `GITHUB_CLIENT_ID` is set
+    pub github_oauth: Option<GithubOAuthConfig>,
+}
+
+impl AppConfig {
+    /// Read every setting from the environment, collecting all problems
+    /// instead of stopping at the first one
+    pub fn from_env() -> Result<Self, Vec<String>> {
+        let mut problems: Vec<String> = REQUIRED_VARS
+            .iter()
+            .chain(email_provider_vars())
+            .filter(|name| !env::var(name).is_ok_and(|v| !v.trim().is_empty()))
+            .map(|name| format!("{} is required", name))
+            .collect();
+
+        if let Ok(secret) = env::var("JWT_SECRET")
+            && !secret.trim().is_empty()
+            && let Some(problem) = jwt_secret_problem(&secret)
+        {
+            problems.push(problem);
+        }
+
+        let port = collect(
+            env::var("PORT")
+                .unwrap_or_else(|_| "8000".to_string())
+                .parse::<u16>()
+                .map_err(|_| "PORT must be a valid number".to_string()),
+            &mut problems,
+        );
+        let workers = collect(
+            parse_optional::<usize>("HTTP_WORKERS").and_then(|workers| match workers {
+                Some(0) => Err("HTTP_WORKERS must be greater than 0".to_string()),
+                workers => Ok(workers),
+            }),
+            &mut problems,
+        );
+        let tls = collect(
+            match (env::var("TLS_CERT_PATH"), env::var("TLS_KEY_PATH")) {
+                (Ok(cert_path), Ok(key_path)) => Ok(Some(TlsConfig {
+                    cert_path,
+                    key_path,
+                })),
+                (Err(_), Err(_)) => Ok(None),
+                _ => Err("TLS_CERT_PATH and TLS_KEY_PATH must be set together".to_string()),
+            },
+            &mut problems,
+        );
+        let trusted_proxies = collect(
+            env::var("TRUSTED_PROXIES")
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|ip| !ip.is_empty())
+                .map(|ip| {
+                    ip.parse::<IpAddr>().map_err(|_| {
+                        format!("TRUSTED_PROXIES contains an invalid IP address: {}", ip)
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>(),
+            &mut problems,
+        );
+        let mongo = collect(MongoConfig::from_env(), &mut problems);
+        let redis = collect(RedisConfig::from_env(), &mut problems);
+        let email = collect(EmailConfig::from_env(), &mut problems);
+        let otp = collect(OtpConfig::from_env(), &mut problems);
+        let sms = collect(SmsConfig::from_env(), &mut problems);
+        if let (Some(otp), Some(None)) = (&otp, &sms)
+            && otp.channel.sends_sms()
+        {
+            problems.push("OTP_CHANNEL sms and both need SMS_PROVIDER".to_string());
+        }
+        let cloudinary = collect(CloudinaryConfig::from_env(), &mut problems);
+        let cors = collect(CorsConfig::from_env(), &mut problems);
+        let limits = collect(HttpLimits::from_env(), &mut problems);
+        let compression = collect(CompressionPolicy::from_env(), &mut problems);
+        let security_headers = collect(SecurityHeadersConfig::from_env(), &mut problems);
+        let rate_limits = collect(RateLimitConfig::from_env(), &mut problems);
+        let spam = collect(SpamPolicy::from_env(), &mut problems);
+        let link_safety = collect(LinkSafetyPolicy::from_env(), &mut problems);
+
+        // Falls back to the listen address; port and TLS problems are
+        // already listed when they are missing
+        let host = env::var("HOST").unwrap_or_else(|_| "localhost".to_string());
+        let public_base_url = env::var("PUBLIC_BASE_URL")
+            .ok()
+            .map(|url| url.trim().trim_end_matches('/').to_string())
+            .filter(|url| !url.is_empty())
+            .unwrap_or_else(|| {
+                let scheme = if matches!(tls, Some(Some(_))) {
+                    "https"
+                } else {
+                    "http"
+                };
+                format!("{}://{}:{}", scheme, host, port.unwrap_or_default())
+            });
+        let github_oauth = collect(GithubOAuthConfig::from_env(&public_base_url), &mut problems);
+
+        let (
+            true,
+            Some(port),
+            Some(workers),
+            Some(tls),
+            Some(trusted_proxies),
+            Some(mongo),
+            Some(redis),
+            Some(email),
+            Some(otp),
+            Some(sms),
+            Some(cloudinary),
+            Some(cors),
+            Some(limits),
+            Some(compression),
+            Some(security_headers),
+            Some(rate_limits),
+            Some(spam),
+            Some(link_safety),
+            Some(github_oauth),
+        ) = (
+            problems.is_empty(),
+            port,
+            workers,
+            tls,
+            trusted_proxies,
+            mongo,
+            redis,
+            email,
+            otp,
+            sms,
+            cloudinary,
+            cors,
+            limits,
+            compression,
+            security_headers,
+            rate_limits,
+            spam,
+            link_safety,
+            github_oauth,
+        )
+        else {
+            return Err(problems);
+        };
+
+        let jwt_secret = env::var("JWT_SECRET").unwrap_or_default();
+        Ok(Self {
+            service_name: env::var("SERVICE_NAME").unwrap_or_else(|_| "Unknown".to_string()),
+            server: ServerConfig {
+                host,
+                port,
+                workers,
+                tls,
+                trusted_proxies,
+            },
+            fingerprint_salt: env::var("FINGERPRINT_SALT").unwrap_or_default(),
+            export_signing_secret: env::var("EXPORT_SIGNING_SECRET").unwrap_or_default(),
+            jwt_secret,
+            share_link_base_url: env::var("SHARE_LINK_BASE_URL")
+                .ok()
+                .map(|url| url.trim().trim_end_matches('/').to_string())
+                .filter(|url| !url.is_empty()),
+            public_base_url,
+            mongo,
+            redis,
+            email,
+            otp,
+            sms,
+            cloudinary,
+            cors,
+            limits,
+            compression,
+            security_headers,
+            rate_limits,
+            spam,
+            link_safety,
+            github_oauth,
+        })
+    }
+
+    /// Read the configuration and record the service name for response
+    /// envelopes, which are built without access to app data
+    pub fn load() -> Result<AppConfig, Vec<String>> {
+        let config = Self::from_env()?;
+        SERVICE_NAME.get_or_init(|| config.service_name.clone());
+        Ok(config)
+    }
+}
+
+/// Service name for response envelopes, usable before the config is loaded
+pub fn service_name() -> &'static str {
+    SERVICE_NAME.get().map(String::as_str).unwrap_or("Unknown")
+}
+
+/// Record a sub-config error unless an identical problem is already listed
+fn collect<T>(result: Result<T, String>, problems: &mut Vec<String>) -> Option<T> {
+    match result {
+        Ok(value) => Some(value),
+        Err(e) => {
+            if !problems.contains(&e) {
+                problems.push(e);
+            }
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_secrets_are_rejected() {
+        let problem = jwt_secret_problem("0123456789abcdef").expect("too short");
+        assert!(problem.contains("at least 32 characters"), "{}", problem);
+    }
+
+    #[test]
+    fn repeated_characters_are_rejected() {
+        let problem = jwt_secret_problem(&"a".repeat(64)).expect("too predictable");
+        assert!(problem.contains("too predictable"), "{}", problem);
+    }
+
+    #[test]
+    fn short_repeating_patterns_are_rejected() {
+        assert!(jwt_secret_problem(&"12".repeat(32)).is_some());
+        assert!(jwt_secret_problem(&"abcd".repeat(8)).is_some());
+    }
+
+    #[test]
+    fn openssl_hex_16_passes() {
+        // `openssl rand -hex 16`
+        assert_eq!(jwt_secret_problem("6133c917763d792a58dd53206ef1dbc8"), None);
+    }
+
+    #[test]
+    fn openssl_base64_48_passes() {
+        // `openssl rand -base64 48`
+        assert_eq!(
+            jwt_secret_problem("GKIB54P7W19wIh/K8hUdk+zx3KIsPg9GvM5aPh1kiH716pq2QyuLHjpNjRL6dMVo"),
+            None
+        );
+    }
+}
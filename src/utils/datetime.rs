@@ -0,0 +1,71 @@
+use chrono::{DateTime, Utc};
+use mongodb::bson::{self, Bson};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Current time as a BSON date, for use in `doc!` updates
+pub fn bson_now() -> bson::DateTime {
+    bson::DateTime::now()
+}
+
+/// Serde helper for `DateTime<Utc>` fields on Mongo models.
+///
+/// Always writes a native BSON date, whatever the serializer: `bson::to_bson`
+/// and `doc!` report themselves as human-readable, so switching on that would
+/// store strings. Reads either form so documents written before the migration
+/// still load. Types that are only ever sent as JSON should not use it.
+pub mod bson_datetime {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        value: &DateTime<Utc>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        bson::DateTime::from_chrono(*value).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<DateTime<Utc>, D::Error> {
+        match Bson::deserialize(deserializer)? {
+            Bson::DateTime(value) => Ok(value.to_chrono()),
+            Bson::String(value) => DateTime::parse_from_rfc3339(&value)
+                .map(|dt| dt.with_timezone(&Utc))
+                .map_err(serde::de::Error::custom),
+            other => Err(serde::de::Error::custom(format!(
+                "expected a date, found {:?}",
+                other.element_type()
+            ))),
+        }
+    }
+}
+
+/// Same as `bson_datetime` for optional fields
+pub mod option_bson_datetime {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<DateTime<Utc>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => super::bson_datetime::serialize(value, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<DateTime<Utc>>, D::Error> {
+        match Bson::deserialize(deserializer)? {
+            Bson::Null | Bson::Undefined => Ok(None),
+            Bson::DateTime(value) => Ok(Some(value.to_chrono())),
+            Bson::String(value) => DateTime::parse_from_rfc3339(&value)
+                .map(|dt| Some(dt.with_timezone(&Utc)))
+                .map_err(serde::de::Error::custom),
+            other => Err(serde::de::Error::custom(format!(
+                "expected a date, found {:?}",
+                other.element_type()
+            ))),
+        }
+    }
+}
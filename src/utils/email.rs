@@ -1,68 +1,110 @@
-use lettre::message::header::ContentType;
-use lettre::transport::smtp::authentication::Credentials;
-use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use crate::utils::email_provider::{
+    DryRunProvider, EmailError, EmailMessage, EmailProvider, SendGridProvider, SesProvider,
+    SmtpProvider,
+};
+use crate::utils::email_templates::EmailTemplate;
+use crate::utils::helpers::PASSWORD_RESET_EXPIRATION_MINUTES;
+use crate::utils::i18n::Locale;
 use std::env;
 
 /// SMTP Configuration for Zoho
+#[derive(Clone)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    pub password: String,
+}
+
+/// Which delivery backend to use, with its credentials
+#[derive(Clone)]
+pub enum EmailProviderConfig {
+    Smtp(SmtpConfig),
+    SendGrid {
+        api_key: String,
+    },
+    Ses {
+        region: String,
+        access_key_id: String,
+        secret_access_key: String,
+    },
+}
+
+/// Email configuration: sender identity and delivery backend
+#[derive(Clone)]
 pub struct EmailConfig {
-    pub smtp_host: String,
-    pub smtp_port: u16,
-    pub smtp_username: String,
-    pub smtp_password: String,
     pub from_email: String,
     pub from_name: String,
+    pub provider: EmailProviderConfig,
+    /// Log instead of delivering (SendGrid uses its sandbox mode instead)
+    pub dry_run: bool,
+}
+
+fn required(name: &str) -> Result<String, String> {
+    env::var(name).map_err(|_| format!("{} is required", name))
 }
 
 impl EmailConfig {
     /// Load email configuration from environment variables
     pub fn from_env() -> Result<Self, String> {
+        let provider = match env::var("EMAIL_PROVIDER")
+            .unwrap_or_else(|_| "smtp".to_string())
+            .to_lowercase()
+            .as_str()
+        {
+            "smtp" => EmailProviderConfig::Smtp(SmtpConfig {
+                host: env::var("SMTP_HOST").unwrap_or_else(|_| "smtp.zoho.com".to_string()),
+                port: env::var("SMTP_PORT")
+                    .unwrap_or_else(|_| "465".to_string())
+                    .parse()
+                    .map_err(|_| "SMTP_PORT must be a valid number")?,
+                username: required("SMTP_USERNAME")?,
+                password: required("SMTP_PASSWORD")?,
+            }),
+            "sendgrid" => EmailProviderConfig::SendGrid {
+                api_key: required("SENDGRID_API_KEY")?,
+            },
+            "ses" => EmailProviderConfig::Ses {
+                region: env::var("SES_REGION").or_else(|_| required("AWS_REGION"))?,
+                access_key_id: required("AWS_ACCESS_KEY_ID")?,
+                secret_access_key: required("AWS_SECRET_ACCESS_KEY")?,
+            },
+            other => {
+                return Err(format!(
+                    "EMAIL_PROVIDER must be smtp, sendgrid or ses (got {})",
+                    other
+                ));
+            }
+        };
+
         Ok(Self {
-            smtp_host: env::var("SMTP_HOST").unwrap_or_else(|_| "smtp.zoho.com".to_string()),
-            smtp_port: env::var("SMTP_PORT")
-                .unwrap_or_else(|_| "465".to_string())
-                .parse()
-                .map_err(|_| "SMTP_PORT must be a valid number")?,
-            smtp_username: env::var("SMTP_USERNAME").map_err(|_| "SMTP_USERNAME is required")?,
-            smtp_password: env::var("SMTP_PASSWORD").map_err(|_| "SMTP_PASSWORD is required")?,
-            from_email: env::var("SMTP_FROM_EMAIL").map_err(|_| "SMTP_FROM_EMAIL is required")?,
+            from_email: required("SMTP_FROM_EMAIL")?,
             from_name: env::var("SMTP_FROM_NAME")
                 .unwrap_or_else(|_| "SocializationApp".to_string()),
+            provider,
+            dry_run: env::var("EMAIL_DRY_RUN")
+                .map(|v| v.eq_ignore_ascii_case("true") || v == "1")
+                .unwrap_or(false),
         })
     }
 }
 
-/// Email service for sending emails via Zoho SMTP
+/// Email service that renders messages and hands them to the configured provider
 pub struct EmailService {
-    config: EmailConfig,
+    provider: Box<dyn EmailProvider>,
 }
 
 impl EmailService {
-    /// Create a new EmailService instance
-    pub fn new() -> Result<Self, String> {
-        let config = EmailConfig::from_env()?;
-        Ok(Self { config })
-    }
-
     /// Create a new EmailService with custom config
     pub fn with_config(config: EmailConfig) -> Self {
-        Self { config }
-    }
-
-    /// Build the SMTP transport
-    fn build_transport(&self) -> Result<AsyncSmtpTransport<Tokio1Executor>, String> {
-        let creds = Credentials::new(
-            self.config.smtp_username.clone(),
-            self.config.smtp_password.clone(),
-        );
-
-        // Zoho uses port 465 with implicit TLS (SMTPS)
-        let transport = AsyncSmtpTransport::<Tokio1Executor>::relay(&self.config.smtp_host)
-            .map_err(|e| format!("Failed to create SMTP transport: {}", e))?
-            .credentials(creds)
-            .port(self.config.smtp_port)
-            .build();
-
-        Ok(transport)
+        let provider: Box<dyn EmailProvider> = match &config.provider {
+            EmailProviderConfig::SendGrid { .. } => Box::new(SendGridProvider::new(&config)),
+            _ if config.dry_run => Box::new(DryRunProvider),
+            EmailProviderConfig::Smtp(_) => Box::new(SmtpProvider::new(&config)),
+            EmailProviderConfig::Ses { .. } => Box::new(SesProvider::new(&config)),
+        };
+
+        Self { provider }
     }
 
     /// Send a plain text email
@@ -71,82 +113,51 @@ impl EmailService {
         to_email: &str,
         subject: &str,
         body: &str,
-    ) -> Result<(), String> {
-        let from_address = format!("{} <{}>", self.config.from_name, self.config.from_email);
-
-        let email = Message::builder()
-            .from(
-                from_address
-                    .parse()
-                    .map_err(|e| format!("Invalid from address: {}", e))?,
-            )
-            .to(to_email
-                .parse()
-                .map_err(|e| format!("Invalid to address: {}", e))?)
-            .subject(subject)
-            .header(ContentType::TEXT_PLAIN)
-            .body(body.to_string())
-            .map_err(|e| format!("Failed to build email: {}", e))?;
-
-        let transport = self.build_transport()?;
-
-        transport
-            .send(email)
-            .await
-            .map_err(|e| format!("Failed to send email: {}", e))?;
-
-        Ok(())
+    ) -> Result<(), EmailError> {
+        let message = EmailMessage {
+            to: to_email.to_string(),
+            subject: subject.to_string(),
+            text: body.to_string(),
+            html: None,
+        };
+
+        self.provider.send(&message).await
     }
 
-    /// Send an HTML email
-    pub async fn send_html_email(
+    /// Render a template and send it with HTML and plain-text alternatives
+    pub async fn send_template(
         &self,
         to_email: &str,
-        subject: &str,
-        html_body: &str,
-    ) -> Result<(), String> {
-        let from_address = format!("{} <{}>", self.config.from_name, self.config.from_email);
-
-        let email = Message::builder()
-            .from(
-                from_address
-                    .parse()
-                    .map_err(|e| format!("Invalid from address: {}", e))?,
-            )
-            .to(to_email
-                .parse()
-                .map_err(|e| format!("Invalid to address: {}", e))?)
-            .subject(subject)
-            .header(ContentType::TEXT_HTML)
-            .body(html_body.to_string())
-            .map_err(|e| format!("Failed to build email: {}", e))?;
-
-        let transport = self.build_transport()?;
-
-        transport
-            .send(email)
-            .await
-            .map_err(|e| format!("Failed to send email: {}", e))?;
-
-        Ok(())
+        template: &EmailTemplate,
+        locale: Locale,
+    ) -> Result<(), EmailError> {
+        let rendered = template.render(locale).map_err(EmailError::Rejected)?;
+        let message = EmailMessage {
+            to: to_email.to_string(),
+            subject: rendered.subject,
+            text: rendered.text,
+            html: Some(rendered.html),
+        };
+
+        log::debug!("Sending email via {}", self.provider.name());
+        self.provider.send(&message).await
     }
 
-    /// Send a verification email with OTP
+    /// Send a verification email with an OTP that expires in
+    /// `expires_in_minutes`
     pub async fn send_verification_email(
         &self,
         to_email: &str,
         otp_code: &str,
-    ) -> Result<(), String> {
-        let subject = "Verify Your Email - SocializationApp";
-        let body = format!(
-            "Welcome to SocializationApp!\n\n\
-            Your verification code is: {}\n\n\
-            This code will expire in 10 minutes.\n\n\
-            If you didn't request this, please ignore this email.",
-            otp_code
-        );
-
-        self.send_email(to_email, subject, &body).await
+        expires_in_minutes: i64,
+        locale: Locale,
+    ) -> Result<(), EmailError> {
+        let template = EmailTemplate::Verification {
+            otp_code: otp_code.to_string(),
+            expires_in_minutes: expires_in_minutes as u32,
+        };
+
+        self.send_template(to_email, &template, locale).await
     }
 
     /// Send a password reset email
@@ -154,16 +165,13 @@ impl EmailService {
         &self,
         to_email: &str,
         reset_token: &str,
-    ) -> Result<(), String> {
-        let subject = "Password Reset - SocializationApp";
-        let body = format!(
-            "You requested a password reset.\n\n\
-            Your reset token is: {}\n\n\
-            This token will expire in 15 minutes.\n\n\
-            If you didn't request this, please ignore this email.",
-            reset_token
-        );
-
-        self.send_email(to_email, subject, &body).await
+        locale: Locale,
+    ) -> Result<(), EmailError> {
+        let template = EmailTemplate::PasswordReset {
+            reset_token: reset_token.to_string(),
+            expires_in_minutes: PASSWORD_RESET_EXPIRATION_MINUTES as u32,
+        };
+
+        self.send_template(to_email, &template, locale).await
     }
 }
@@ -0,0 +1,330 @@
+use crate::utils::email::{EmailConfig, EmailProviderConfig};
+use aws_sdk_sesv2::config::{BehaviorVersion, Credentials as AwsCredentials, Region};
+use aws_sdk_sesv2::error::{DisplayErrorContext, SdkError};
+use aws_sdk_sesv2::operation::send_email::SendEmailError;
+use aws_sdk_sesv2::types::{Body, Content, Destination, EmailContent};
+use futures_util::future::BoxFuture;
+use lettre::message::MultiPart;
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde_json::json;
+use thiserror::Error;
+
+/// A rendered email ready for delivery
+#[derive(Debug, Clone)]
+pub struct EmailMessage {
+    pub to: String,
+    pub subject: String,
+    pub text: String,
+    pub html: Option<String>,
+}
+
+/// Delivery failures, classified so callers know whether a retry can help
+#[derive(Debug, Error)]
+pub enum EmailError {
+    /// The provider will never accept this message (bad address, content)
+    #[error("Email rejected: {0}")]
+    Rejected(String),
+
+    /// Credentials or sender setup are wrong; retrying works once fixed
+    #[error("Email provider misconfigured: {0}")]
+    Misconfigured(String),
+
+    /// Network errors, throttling and provider outages
+    #[error("Email provider unavailable: {0}")]
+    Transient(String),
+}
+
+impl EmailError {
+    /// Whether the same message could succeed later
+    pub fn is_retryable(&self) -> bool {
+        !matches!(self, EmailError::Rejected(_))
+    }
+}
+
+/// A backend that delivers email
+pub trait EmailProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn send<'a>(&'a self, message: &'a EmailMessage) -> BoxFuture<'a, Result<(), EmailError>>;
+}
+
+fn sender(config: &EmailConfig) -> String {
+    format!("{} <{}>", config.from_name, config.from_email)
+}
+
+/// Delivery over SMTP (Zoho by default)
+pub struct SmtpProvider {
+    from: String,
+    host: String,
+    port: u16,
+    credentials: Credentials,
+}
+
+impl SmtpProvider {
+    pub fn new(config: &EmailConfig) -> Self {
+        let EmailProviderConfig::Smtp(smtp) = &config.provider else {
+            unreachable!("SmtpProvider requires an SMTP configuration");
+        };
+
+        Self {
+            from: sender(config),
+            host: smtp.host.clone(),
+            port: smtp.port,
+            credentials: Credentials::new(smtp.username.clone(), smtp.password.clone()),
+        }
+    }
+
+    /// Build the SMTP transport
+    fn build_transport(&self) -> Result<AsyncSmtpTransport<Tokio1Executor>, EmailError> {
+        // Zoho uses port 465 with implicit TLS (SMTPS)
+        let transport = AsyncSmtpTransport::<Tokio1Executor>::relay(&self.host)
+            .map_err(|e| {
+                EmailError::Misconfigured(format!("Failed to create SMTP transport: {}", e))
+            })?
+            .credentials(self.credentials.clone())
+            .port(self.port)
+            .build();
+
+        Ok(transport)
+    }
+}
+
+/// 53x replies are authentication problems; other permanent replies reject the message
+fn map_smtp_error(e: lettre::transport::smtp::Error) -> EmailError {
+    let code = e.status().map(|code| code.to_string()).unwrap_or_default();
+    if code.starts_with("53") {
+        EmailError::Misconfigured(e.to_string())
+    } else if e.is_permanent() {
+        EmailError::Rejected(e.to_string())
+    } else {
+        EmailError::Transient(e.to_string())
+    }
+}
+
+impl EmailProvider for SmtpProvider {
+    fn name(&self) -> &'static str {
+        "smtp"
+    }
+
+    fn send<'a>(&'a self, message: &'a EmailMessage) -> BoxFuture<'a, Result<(), EmailError>> {
+        Box::pin(async move {
+            let builder = Message::builder()
+                .from(self.from.parse().map_err(|e| {
+                    EmailError::Misconfigured(format!("Invalid from address: {}", e))
+                })?)
+                .to(message
+                    .to
+                    .parse()
+                    .map_err(|e| EmailError::Rejected(format!("Invalid to address: {}", e)))?)
+                .subject(&message.subject);
+
+            let email = match &message.html {
+                Some(html) => builder.multipart(MultiPart::alternative_plain_html(
+                    message.text.clone(),
+                    html.clone(),
+                )),
+                None => builder
+                    .header(ContentType::TEXT_PLAIN)
+                    .body(message.text.clone()),
+            }
+            .map_err(|e| EmailError::Rejected(format!("Failed to build email: {}", e)))?;
+
+            self.build_transport()?
+                .send(email)
+                .await
+                .map_err(map_smtp_error)?;
+
+            Ok(())
+        })
+    }
+}
+
+/// Delivery through the SendGrid v3 mail API
+pub struct SendGridProvider {
+    api_key: String,
+    from_email: String,
+    from_name: String,
+    sandbox: bool,
+    client: reqwest::Client,
+}
+
+impl SendGridProvider {
+    pub fn new(config: &EmailConfig) -> Self {
+        let EmailProviderConfig::SendGrid { api_key } = &config.provider else {
+            unreachable!("SendGridProvider requires a SendGrid configuration");
+        };
+
+        Self {
+            api_key: api_key.clone(),
+            from_email: config.from_email.clone(),
+            from_name: config.from_name.clone(),
+            sandbox: config.dry_run,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+impl EmailProvider for SendGridProvider {
+    fn name(&self) -> &'static str {
+        "sendgrid"
+    }
+
+    fn send<'a>(&'a self, message: &'a EmailMessage) -> BoxFuture<'a, Result<(), EmailError>> {
+        Box::pin(async move {
+            let mut content = vec![json!({ "type": "text/plain", "value": message.text })];
+            if let Some(html) = &message.html {
+                content.push(json!({ "type": "text/html", "value": html }));
+            }
+
+            // Sandbox mode validates the request without delivering it
+            let body = json!({
+                "personalizations": [{ "to": [{ "email": message.to }] }],
+                "from": { "email": self.from_email, "name": self.from_name },
+                "subject": message.subject,
+                "content": content,
+                "mail_settings": { "sandbox_mode": { "enable": self.sandbox } }
+            });
+
+            let response = self
+                .client
+                .post("https://api.sendgrid.com/v3/mail/send")
+                .bearer_auth(&self.api_key)
+                .json(&body)
+                .send()
+                .await
+                .map_err(|e| EmailError::Transient(format!("SendGrid request failed: {}", e)))?;
+
+            let status = response.status();
+            if status.is_success() {
+                return Ok(());
+            }
+
+            let detail = response.text().await.unwrap_or_default();
+            let detail = format!("SendGrid returned {}: {}", status, detail);
+            Err(match status.as_u16() {
+                401 | 403 => EmailError::Misconfigured(detail),
+                400 | 413 => EmailError::Rejected(detail),
+                _ => EmailError::Transient(detail),
+            })
+        })
+    }
+}
+
+/// Delivery through the Amazon SES v2 API
+pub struct SesProvider {
+    from: String,
+    client: aws_sdk_sesv2::Client,
+}
+
+impl SesProvider {
+    pub fn new(config: &EmailConfig) -> Self {
+        let EmailProviderConfig::Ses {
+            region,
+            access_key_id,
+            secret_access_key,
+        } = &config.provider
+        else {
+            unreachable!("SesProvider requires an SES configuration");
+        };
+
+        let credentials = AwsCredentials::new(
+            access_key_id.clone(),
+            secret_access_key.clone(),
+            None,
+            None,
+            "environment",
+        );
+        let ses_config = aws_sdk_sesv2::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new(region.clone()))
+            .credentials_provider(credentials)
+            .build();
+
+        Self {
+            from: sender(config),
+            client: aws_sdk_sesv2::Client::from_conf(ses_config),
+        }
+    }
+}
+
+fn ses_content(data: &str) -> Result<Content, EmailError> {
+    Content::builder()
+        .data(data)
+        .charset("UTF-8")
+        .build()
+        .map_err(|e| EmailError::Rejected(format!("Failed to build email: {}", e)))
+}
+
+fn map_ses_error(e: SdkError<SendEmailError>) -> EmailError {
+    let detail = DisplayErrorContext(&e).to_string();
+    match e.as_service_error() {
+        Some(SendEmailError::MessageRejected(_) | SendEmailError::BadRequestException(_)) => {
+            EmailError::Rejected(detail)
+        }
+        Some(
+            SendEmailError::MailFromDomainNotVerifiedException(_)
+            | SendEmailError::AccountSuspendedException(_)
+            | SendEmailError::SendingPausedException(_)
+            | SendEmailError::NotFoundException(_),
+        ) => EmailError::Misconfigured(detail),
+        _ => EmailError::Transient(detail),
+    }
+}
+
+impl EmailProvider for SesProvider {
+    fn name(&self) -> &'static str {
+        "ses"
+    }
+
+    fn send<'a>(&'a self, message: &'a EmailMessage) -> BoxFuture<'a, Result<(), EmailError>> {
+        Box::pin(async move {
+            let mut body = Body::builder().text(ses_content(&message.text)?);
+            if let Some(html) = &message.html {
+                body = body.html(ses_content(html)?);
+            }
+
+            let content = EmailContent::builder()
+                .simple(
+                    aws_sdk_sesv2::types::Message::builder()
+                        .subject(ses_content(&message.subject)?)
+                        .body(body.build())
+                        .build(),
+                )
+                .build();
+
+            self.client
+                .send_email()
+                .from_email_address(&self.from)
+                .destination(Destination::builder().to_addresses(&message.to).build())
+                .content(content)
+                .send()
+                .await
+                .map_err(map_ses_error)?;
+
+            Ok(())
+        })
+    }
+}
+
+/// Development mode: log the email instead of delivering it
+pub struct DryRunProvider;
+
+impl EmailProvider for DryRunProvider {
+    fn name(&self) -> &'static str {
+        "dry-run"
+    }
+
+    fn send<'a>(&'a self, message: &'a EmailMessage) -> BoxFuture<'a, Result<(), EmailError>> {
+        Box::pin(async move {
+            log::info!(
+                "[dry-run] Email to {} not sent: {:?}\n{}",
+                message.to,
+                message.subject,
+                message.text
+            );
+            Ok(())
+        })
+    }
+}
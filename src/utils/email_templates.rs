@@ -0,0 +1,186 @@
+use crate::utils::i18n::Locale;
+use fluent_templates::fluent_bundle::FluentValue;
+use serde::Serialize;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use tera::{Context, Tera, Value};
+
+/// Brand name shown in subjects and the email header
+const APP_NAME: &str = "SocializationApp";
+
+/// Templates are embedded at compile time so the binary has no runtime
+/// dependency on the source tree
+static TEMPLATES: LazyLock<Tera> = LazyLock::new(|| {
+    let mut tera = Tera::default();
+    tera.add_raw_templates(vec![
+        ("base.html", include_str!("../../templates/email/base.html")),
+        (
+            "verification.html",
+            include_str!("../../templates/email/verification.html"),
+        ),
+        (
+            "verification.txt",
+            include_str!("../../templates/email/verification.txt"),
+        ),
+        (
+            "password_reset.html",
+            include_str!("../../templates/email/password_reset.html"),
+        ),
+        (
+            "password_reset.txt",
+            include_str!("../../templates/email/password_reset.txt"),
+        ),
+        (
+            "welcome.html",
+            include_str!("../../templates/email/welcome.html"),
+        ),
+        (
+            "welcome.txt",
+            include_str!("../../templates/email/welcome.txt"),
+        ),
+        (
+            "onboarding_tips.html",
+            include_str!("../../templates/email/onboarding_tips.html"),
+        ),
+        (
+            "onboarding_tips.txt",
+            include_str!("../../templates/email/onboarding_tips.txt"),
+        ),
+        (
+            "digest.html",
+            include_str!("../../templates/email/digest.html"),
+        ),
+        (
+            "digest.txt",
+            include_str!("../../templates/email/digest.txt"),
+        ),
+        (
+            "report_export.html",
+            include_str!("../../templates/email/report_export.html"),
+        ),
+        (
+            "report_export.txt",
+            include_str!("../../templates/email/report_export.txt"),
+        ),
+    ])
+    .expect("Embedded email templates must parse");
+    tera.register_function("t", translate);
+    tera
+});
+
+/// Template function: `{{ t(key="email-greeting", lang=lang, username=username) }}`.
+/// Every argument other than `key` and `lang` is passed to the catalog.
+fn translate(args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let key = args
+        .get("key")
+        .and_then(Value::as_str)
+        .ok_or_else(|| tera::Error::msg("t() requires a string `key`"))?;
+    let locale = args
+        .get("lang")
+        .and_then(Value::as_str)
+        .and_then(Locale::from_tag)
+        .unwrap_or_default();
+
+    let fluent_args = args
+        .iter()
+        .filter(|(name, _)| name.as_str() != "key" && name.as_str() != "lang")
+        .map(|(name, value)| {
+            let value = match value {
+                Value::Number(n) => FluentValue::from(n.as_f64().unwrap_or_default()),
+                Value::String(s) => FluentValue::from(s.clone()),
+                other => FluentValue::from(other.to_string()),
+            };
+            (Cow::Owned(name.clone()), value)
+        })
+        .collect();
+
+    Ok(Value::String(locale.lookup(key, &fluent_args)))
+}
+
+/// A single entry in a digest email
+#[derive(Debug, Clone, Serialize)]
+pub struct DigestItem {
+    pub title: String,
+    pub summary: String,
+    pub url: Option<String>,
+}
+
+/// Transactional emails and the variables each template needs
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum EmailTemplate {
+    Verification {
+        otp_code: String,
+        expires_in_minutes: u32,
+    },
+    PasswordReset {
+        reset_token: String,
+        expires_in_minutes: u32,
+    },
+    Welcome {
+        username: String,
+    },
+    OnboardingTips {
+        username: String,
+    },
+    Digest {
+        username: String,
+        items: Vec<DigestItem>,
+    },
+    ReportExport {
+        report: String,
+        row_count: u64,
+        download_url: String,
+        expires_in_hours: u32,
+    },
+}
+
+/// A rendered email with HTML and plain-text alternatives
+#[derive(Debug, Clone)]
+pub struct RenderedEmail {
+    pub subject: String,
+    pub html: String,
+    pub text: String,
+}
+
+impl EmailTemplate {
+    /// Template file name without extension
+    fn name(&self) -> &'static str {
+        match self {
+            EmailTemplate::Verification { .. } => "verification",
+            EmailTemplate::PasswordReset { .. } => "password_reset",
+            EmailTemplate::Welcome { .. } => "welcome",
+            EmailTemplate::OnboardingTips { .. } => "onboarding_tips",
+            EmailTemplate::Digest { .. } => "digest",
+            EmailTemplate::ReportExport { .. } => "report_export",
+        }
+    }
+
+    fn subject(&self, locale: Locale) -> String {
+        let key = format!("email-{}-subject", self.name().replace('_', "-"));
+        locale.t_args(&key, &[("app_name", APP_NAME.into())])
+    }
+
+    /// Render the subject, HTML body and plain-text body in `locale`
+    pub fn render(&self, locale: Locale) -> Result<RenderedEmail, String> {
+        let mut context = Context::from_serialize(self)
+            .map_err(|e| format!("Failed to build email context: {}", e))?;
+        context.insert("app_name", APP_NAME);
+        context.insert("lang", locale.code());
+
+        let render = |extension: &str| {
+            let template = format!("{}.{}", self.name(), extension);
+            TEMPLATES
+                .render(&template, &context)
+                .map_err(|e| format!("Failed to render {}: {}", template, e))
+        };
+
+        Ok(RenderedEmail {
+            subject: self.subject(locale),
+            html: render("html")?,
+            text: render("txt")?,
+        })
+    }
+}
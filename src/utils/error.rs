@@ -1,7 +1,148 @@
+use crate::utils::response::ApiError;
+use actix_web::http::header::{HeaderValue, RETRY_AFTER};
 use actix_web::{HttpResponse, ResponseError, http::StatusCode};
-use serde_json::json;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
 use thiserror::Error;
 
+/// Machine-readable codes for failures clients commonly branch on. They're
+/// sent as `code` in the error envelope; other errors repeat their `error`
+/// category there. Codes are never renamed once released.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    AuthInvalidCredentials,
+    AuthEmailNotVerified,
+    AuthPhoneNotVerified,
+    AuthAccountSuspended,
+    AuthInvalidOtp,
+    AuthOtpExpired,
+    AuthInvalidResetToken,
+    AuthInvalidRefreshToken,
+    AuthWrongPassword,
+    AuthWeakPassword,
+    UserNotFound,
+    PostNotFound,
+    CommentNotFound,
+    UploadTooLarge,
+}
+
+impl ErrorCode {
+    pub const ALL: &[ErrorCode] = &[
+        ErrorCode::AuthInvalidCredentials,
+        ErrorCode::AuthEmailNotVerified,
+        ErrorCode::AuthPhoneNotVerified,
+        ErrorCode::AuthAccountSuspended,
+        ErrorCode::AuthInvalidOtp,
+        ErrorCode::AuthOtpExpired,
+        ErrorCode::AuthInvalidResetToken,
+        ErrorCode::AuthInvalidRefreshToken,
+        ErrorCode::AuthWrongPassword,
+        ErrorCode::AuthWeakPassword,
+        ErrorCode::UserNotFound,
+        ErrorCode::PostNotFound,
+        ErrorCode::CommentNotFound,
+        ErrorCode::UploadTooLarge,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::AuthInvalidCredentials => "AUTH_INVALID_CREDENTIALS",
+            ErrorCode::AuthEmailNotVerified => "AUTH_EMAIL_NOT_VERIFIED",
+            ErrorCode::AuthPhoneNotVerified => "AUTH_PHONE_NOT_VERIFIED",
+            ErrorCode::AuthAccountSuspended => "AUTH_ACCOUNT_SUSPENDED",
+            ErrorCode::AuthInvalidOtp => "AUTH_INVALID_OTP",
+            ErrorCode::AuthOtpExpired => "AUTH_OTP_EXPIRED",
+            ErrorCode::AuthInvalidResetToken => "AUTH_INVALID_RESET_TOKEN",
+            ErrorCode::AuthInvalidRefreshToken => "AUTH_INVALID_REFRESH_TOKEN",
+            ErrorCode::AuthWrongPassword => "AUTH_WRONG_PASSWORD",
+            ErrorCode::AuthWeakPassword => "AUTH_WEAK_PASSWORD",
+            ErrorCode::UserNotFound => "USER_NOT_FOUND",
+            ErrorCode::PostNotFound => "POST_NOT_FOUND",
+            ErrorCode::CommentNotFound => "COMMENT_NOT_FOUND",
+            ErrorCode::UploadTooLarge => "UPLOAD_TOO_LARGE",
+        }
+    }
+
+    pub fn status(self) -> StatusCode {
+        match self {
+            ErrorCode::AuthInvalidCredentials
+            | ErrorCode::AuthEmailNotVerified
+            | ErrorCode::AuthPhoneNotVerified
+            | ErrorCode::AuthInvalidRefreshToken
+            | ErrorCode::AuthWrongPassword => StatusCode::UNAUTHORIZED,
+            ErrorCode::AuthAccountSuspended => StatusCode::FORBIDDEN,
+            ErrorCode::AuthInvalidOtp
+            | ErrorCode::AuthOtpExpired
+            | ErrorCode::AuthInvalidResetToken
+            | ErrorCode::AuthWeakPassword => StatusCode::BAD_REQUEST,
+            ErrorCode::UserNotFound | ErrorCode::PostNotFound | ErrorCode::CommentNotFound => {
+                StatusCode::NOT_FOUND
+            }
+            ErrorCode::UploadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+        }
+    }
+
+    /// The broader `error` category, matching the uncoded errors' names
+    pub fn category(self) -> &'static str {
+        match self.status() {
+            StatusCode::UNAUTHORIZED => "UNAUTHORIZED_ERROR",
+            StatusCode::FORBIDDEN => "FORBIDDEN_ERROR",
+            StatusCode::NOT_FOUND => "NOT_FOUND_ERROR",
+            StatusCode::PAYLOAD_TOO_LARGE => "PAYLOAD_TOO_LARGE",
+            _ => "BAD_REQUEST_ERROR",
+        }
+    }
+
+    /// When the code is returned, and what `details` carries if anything
+    pub fn description(self) -> &'static str {
+        match self {
+            ErrorCode::AuthInvalidCredentials => "Username or password is wrong",
+            ErrorCode::AuthEmailNotVerified => "Login before the email address was verified",
+            ErrorCode::AuthPhoneNotVerified => {
+                "Login before the phone number was verified, when codes go out by SMS only"
+            }
+            ErrorCode::AuthAccountSuspended => {
+                "The account is suspended; details.suspended_until is when it ends"
+            }
+            ErrorCode::AuthInvalidOtp => "The OTP code doesn't match the one sent",
+            ErrorCode::AuthOtpExpired => "The OTP code has expired; request a new one",
+            ErrorCode::AuthInvalidResetToken => "Password reset token is wrong, used or expired",
+            ErrorCode::AuthInvalidRefreshToken => {
+                "Refresh token is wrong, already used, revoked or expired; log in again"
+            }
+            ErrorCode::AuthWrongPassword => "The current password given is wrong",
+            ErrorCode::AuthWeakPassword => "The new password doesn't meet the password rules",
+            ErrorCode::UserNotFound => "No such user",
+            ErrorCode::PostNotFound => "No such post, or it isn't visible to the caller",
+            ErrorCode::CommentNotFound => "No such comment",
+            ErrorCode::UploadTooLarge => {
+                "The upload is over the size limit; details.max_bytes is the limit"
+            }
+        }
+    }
+}
+
+/// One `ErrorCode` as listed by `GET /error-codes`
+#[derive(Debug, Serialize)]
+pub struct ErrorCodeEntry {
+    pub code: ErrorCode,
+    #[serde(rename = "httpStatusCode")]
+    pub http_status_code: u16,
+    pub description: &'static str,
+}
+
+impl From<ErrorCode> for ErrorCodeEntry {
+    fn from(code: ErrorCode) -> Self {
+        Self {
+            code,
+            http_status_code: code.status().as_u16(),
+            description: code.description(),
+        }
+    }
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Error)]
 pub enum CustomError {
@@ -11,6 +152,9 @@ pub enum CustomError {
     #[error("Bad Request: {0}")]
     BadRequestError(String),
 
+    #[error("Forbidden: {0}")]
+    ForbiddenError(String),
+
     #[error("Conflict: {0}")]
     ConflictError(String),
 
@@ -25,6 +169,47 @@ pub enum CustomError {
 
     #[error("Validation Error: {0}")]
     ValidationError(String),
+
+    #[error("Too Many Requests: {0}")]
+    TooManyRequestsError(String),
+
+    #[error("Timeout: {0}")]
+    TimeoutError(String),
+
+    /// A room's slow mode is holding back the user's next message
+    #[error("Slow mode is on. You can post again in {retry_after_seconds} seconds.")]
+    SlowModeError { retry_after_seconds: u64 },
+
+    /// Invalid request body fields, keyed by field name
+    #[error("Validation Error: request contains invalid fields")]
+    FieldValidationError(BTreeMap<String, Vec<String>>),
+
+    /// A failure with a catalogued code, and optionally structured details
+    #[error("{message}")]
+    CodedError {
+        code: ErrorCode,
+        message: String,
+        details: Option<Value>,
+    },
+}
+
+impl CustomError {
+    /// A catalogued failure; its status comes from the code
+    pub fn coded(code: ErrorCode, message: impl Into<String>) -> Self {
+        CustomError::CodedError {
+            code,
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    /// Attach structured details to a coded error; other errors are unchanged
+    pub fn with_details(mut self, value: Value) -> Self {
+        if let CustomError::CodedError { details, .. } = &mut self {
+            *details = Some(value);
+        }
+        self
+    }
 }
 
 impl ResponseError for CustomError {
@@ -32,31 +217,58 @@ impl ResponseError for CustomError {
         match *self {
             CustomError::UnauthorizedError(..) => StatusCode::UNAUTHORIZED,
             CustomError::BadRequestError(..) => StatusCode::BAD_REQUEST,
+            CustomError::ForbiddenError(..) => StatusCode::FORBIDDEN,
             CustomError::ConflictError(..) => StatusCode::CONFLICT,
             CustomError::InternalServerError(..) => StatusCode::INTERNAL_SERVER_ERROR,
             CustomError::UnauthenticatedError(..) => StatusCode::UNAUTHORIZED,
             CustomError::NotFoundError(..) => StatusCode::NOT_FOUND,
             CustomError::ValidationError(..) => StatusCode::BAD_REQUEST,
+            CustomError::TooManyRequestsError(..) => StatusCode::TOO_MANY_REQUESTS,
+            CustomError::TimeoutError(..) => StatusCode::GATEWAY_TIMEOUT,
+            CustomError::SlowModeError { .. } => StatusCode::TOO_MANY_REQUESTS,
+            CustomError::FieldValidationError(..) => StatusCode::UNPROCESSABLE_ENTITY,
+            CustomError::CodedError { code, .. } => code.status(),
         }
     }
 
     fn error_response(&self) -> HttpResponse {
-        let error_message = json!({
-            "success": false,
-            "message": self.to_string(),
-            "httpStatusCode": self.status_code().as_u16(),
-            "error": match *self {
-                CustomError::UnauthorizedError(..) => "UNAUTHORIZED_ERROR",
-                CustomError::BadRequestError(..) => "BAD_REQUEST_ERROR",
-                CustomError::ConflictError(..) => "CONFLICT_ERROR",
-                CustomError::InternalServerError(..) => "INTERNAL_SERVER_ERROR",
-                CustomError::UnauthenticatedError(..) => "UNAUTHENTICATED_ERROR",
-                CustomError::NotFoundError(..) => "NOT_FOUND_ERROR",
-                CustomError::ValidationError(..) => "VALIDATION_ERROR",
-            },
-            "service": std::env::var("SERVICE_NAME").unwrap_or_else(|_| "Unknown".to_string()),
-        });
-
-        HttpResponse::build(self.status_code()).json(error_message)
+        let error = match *self {
+            CustomError::UnauthorizedError(..) => "UNAUTHORIZED_ERROR",
+            CustomError::BadRequestError(..) => "BAD_REQUEST_ERROR",
+            CustomError::ForbiddenError(..) => "FORBIDDEN_ERROR",
+            CustomError::ConflictError(..) => "CONFLICT_ERROR",
+            CustomError::InternalServerError(..) => "INTERNAL_SERVER_ERROR",
+            CustomError::UnauthenticatedError(..) => "UNAUTHENTICATED_ERROR",
+            CustomError::NotFoundError(..) => "NOT_FOUND_ERROR",
+            CustomError::ValidationError(..) => "VALIDATION_ERROR",
+            CustomError::TooManyRequestsError(..) => "TOO_MANY_REQUESTS_ERROR",
+            CustomError::TimeoutError(..) => "TIMEOUT_ERROR",
+            CustomError::SlowModeError { .. } => "SLOW_MODE_ERROR",
+            CustomError::FieldValidationError(..) => "VALIDATION_ERROR",
+            CustomError::CodedError { code, .. } => code.category(),
+        };
+
+        let mut body = ApiError::new(self.status_code(), error, self.to_string());
+        if let CustomError::CodedError { code, details, .. } = self {
+            body = body.code(code.as_str());
+            if let Some(details) = details {
+                body = body.details(details.clone());
+            }
+        }
+        if let CustomError::FieldValidationError(fields) = self {
+            body = body.field_errors(fields.clone());
+        }
+        if let CustomError::SlowModeError {
+            retry_after_seconds,
+        } = self
+        {
+            let mut response: HttpResponse = body.retry_after(*retry_after_seconds).into();
+            response
+                .headers_mut()
+                .insert(RETRY_AFTER, HeaderValue::from(*retry_after_seconds));
+            return response;
+        }
+
+        body.into()
     }
 }
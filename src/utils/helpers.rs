@@ -1,11 +1,17 @@
 use rand::Rng;
+use rand::distr::Alphanumeric;
 
-/// Generate a 6-digit OTP code
-pub fn generate_otp_code() -> String {
-    let mut rng = rand::rng();
-    let code: u32 = rng.random_range(100000..999999);
-    code.to_string()
+/// Generate a password reset token; only its hash is stored
+pub fn generate_reset_token() -> String {
+    rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect()
 }
 
-/// OTP expiration time in minutes
-pub const OTP_EXPIRATION_MINUTES: i64 = 10;
+/// How long expired OTPs are kept before the TTL index removes them
+pub const OTP_RETENTION_GRACE_HOURS: u64 = 24;
+
+/// Password reset token expiration time in minutes
+pub const PASSWORD_RESET_EXPIRATION_MINUTES: i64 = 15;
@@ -0,0 +1,129 @@
+use crate::middleware::auth::Claims;
+use actix_web::dev::Payload;
+use actix_web::http::header::ACCEPT_LANGUAGE;
+use actix_web::{FromRequest, HttpMessage, HttpRequest};
+use fluent_templates::fluent_bundle::FluentValue;
+use fluent_templates::{LanguageIdentifier, Loader, static_loader};
+use futures_util::future::{Ready, ready};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::LazyLock;
+
+// Catalogs live in `locales/<lang>/*.ftl` and are compiled into the binary
+static_loader! {
+    static CATALOGS = {
+        locales: "./locales",
+        fallback_language: "en",
+        customise: |bundle| bundle.set_use_isolating(false),
+    };
+}
+
+static EN: LazyLock<LanguageIdentifier> =
+    LazyLock::new(|| "en".parse().expect("valid language tag"));
+static FR: LazyLock<LanguageIdentifier> =
+    LazyLock::new(|| "fr".parse().expect("valid language tag"));
+
+/// Language used for response messages, validation errors and emails
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    En,
+    Fr,
+}
+
+impl Locale {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::Fr => "fr",
+        }
+    }
+
+    /// Match a BCP 47 tag on its primary language, so `fr-CA` is French
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let primary = tag.split(['-', '_']).next()?.trim();
+        match primary.to_ascii_lowercase().as_str() {
+            "en" => Some(Locale::En),
+            "fr" => Some(Locale::Fr),
+            _ => None,
+        }
+    }
+
+    /// Best supported language from an `Accept-Language` header, by q-value
+    pub fn from_accept_language(header: &str) -> Option<Self> {
+        let mut ranges: Vec<(f32, &str)> = header
+            .split(',')
+            .filter_map(|range| {
+                let mut parts = range.split(';');
+                let tag = parts.next()?.trim();
+                let quality = parts
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .and_then(|q| q.parse().ok())
+                    .unwrap_or(1.0);
+                (quality > 0.0).then_some((quality, tag))
+            })
+            .collect();
+        // Stable sort keeps header order for equal weights
+        ranges.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        ranges.into_iter().find_map(|(_, tag)| Self::from_tag(tag))
+    }
+
+    /// The caller's profile locale, then `Accept-Language`, then English
+    pub fn resolve(req: &HttpRequest) -> Self {
+        if let Some(locale) = req
+            .extensions()
+            .get::<Claims>()
+            .and_then(|claims| claims.locale)
+        {
+            return locale;
+        }
+
+        req.headers()
+            .get(ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(Self::from_accept_language)
+            .unwrap_or_default()
+    }
+
+    fn language(&self) -> &'static LanguageIdentifier {
+        match self {
+            Locale::En => &EN,
+            Locale::Fr => &FR,
+        }
+    }
+
+    /// Translate a message without arguments
+    pub fn t(&self, key: &str) -> String {
+        CATALOGS.lookup(self.language(), key)
+    }
+
+    /// Translate a message with named arguments
+    pub fn t_args(&self, key: &str, args: &[(&'static str, FluentValue<'static>)]) -> String {
+        let args = args
+            .iter()
+            .map(|(name, value)| (Cow::Borrowed(*name), value.clone()))
+            .collect();
+        self.lookup(key, &args)
+    }
+
+    pub fn lookup(
+        &self,
+        key: &str,
+        args: &HashMap<Cow<'static, str>, FluentValue<'static>>,
+    ) -> String {
+        CATALOGS.lookup_with_args(self.language(), key, args)
+    }
+}
+
+impl FromRequest for Locale {
+    type Error = Infallible;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Ok(Locale::resolve(req)))
+    }
+}
@@ -0,0 +1,30 @@
+use crate::utils::sanitize::{Markup, sanitize};
+use validator::ValidationError;
+
+/// Fewest characters of text worth guessing a language from
+const MIN_DETECTION_CHARS: usize = 20;
+
+/// ISO 639-3 code of the language `text` is written in, e.g. `eng`. Short or
+/// ambiguous text gets `None` rather than a guess.
+pub fn detect_language(text: &str) -> Option<String> {
+    let text = sanitize(text, Markup::None);
+    if text.chars().filter(|c| c.is_alphabetic()).count() < MIN_DETECTION_CHARS {
+        return None;
+    }
+
+    whatlang::detect(&text)
+        .filter(|info| info.is_reliable())
+        .map(|info| info.lang().code().to_string())
+}
+
+/// Reject language lists with codes that `detect_language` never returns
+pub fn language_codes(codes: &[String]) -> Result<(), ValidationError> {
+    if codes
+        .iter()
+        .all(|code| whatlang::Lang::from_code(code).is_some())
+    {
+        return Ok(());
+    }
+    Err(ValidationError::new("language_codes")
+        .with_message("must be ISO 639-3 language codes such as eng".into()))
+}
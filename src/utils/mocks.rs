@@ -0,0 +1,283 @@
+//! In-memory stand-ins for the services handlers depend on, so handler unit
+//! tests run without MongoDB, Redis or outside APIs
+
+use crate::badge::service::BadgeServiceTrait;
+use crate::database::{RateLimitDecision, RedisServiceTrait};
+use crate::fingerprint::model::FingerprintEvent;
+use crate::fingerprint::service::{ClientFingerprint, FingerprintServiceTrait};
+use crate::leaderboard::service::LeaderboardServiceTrait;
+use crate::link_safety::service::LinkGuardTrait;
+use crate::moderation::service::ModerationServiceTrait;
+use crate::notification::model::NotificationKind;
+use crate::notification::service::NotificationServiceTrait;
+use crate::post::post_model::{Post, PostDetail, PostImage};
+use crate::post::post_service::PostServiceTrait;
+use crate::spam_guard::model::SpamAction;
+use crate::spam_guard::service::SpamGuardTrait;
+use crate::subscription::service::SubscriptionServiceTrait;
+use crate::topic::service::TopicServiceTrait;
+use crate::uploader::model::MediaUpload;
+use crate::uploader::service::MediaServiceTrait;
+use crate::utils::error::CustomError;
+use chrono::Utc;
+use mongodb::bson::oid::ObjectId;
+use std::sync::Mutex;
+
+/// Window the mock rate limiter reports when it rejects a request
+const MOCK_RETRY_AFTER_SECONDS: u64 = 30;
+
+/// What mocks return from the methods a test doesn't exercise
+pub fn not_mocked<T>() -> Result<T, CustomError> {
+    Err(CustomError::InternalServerError("not mocked".to_string()))
+}
+
+/// A post by `author_id`, as stored before any edits
+pub fn post_by(author_id: ObjectId) -> Post {
+    Post {
+        id: ObjectId::new(),
+        title: "Hello".to_string(),
+        content: "First post".to_string(),
+        author_id,
+        group_id: None,
+        topics: Vec::new(),
+        images: Vec::new(),
+        version: 0,
+        share_slug: None,
+        share_count: 0,
+        is_sensitive: false,
+        sensitive_locked: false,
+        language: None,
+        pinned_at: None,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    }
+}
+
+/// Keeps posts in a list instead of MongoDB
+#[derive(Default)]
+pub struct MockPosts {
+    pub posts: Mutex<Vec<Post>>,
+}
+
+impl MockPosts {
+    pub fn with(posts: Vec<Post>) -> Self {
+        MockPosts {
+            posts: Mutex::new(posts),
+        }
+    }
+}
+
+impl PostServiceTrait for MockPosts {
+    async fn create_post(&self, post: Post) -> Result<Post, CustomError> {
+        self.posts.lock().unwrap().push(post.clone());
+        Ok(post)
+    }
+
+    async fn get_post(&self, id: &str) -> Result<Option<Post>, CustomError> {
+        let posts = self.posts.lock().unwrap();
+        Ok(posts.iter().find(|post| post.id.to_hex() == id).cloned())
+    }
+
+    async fn get_post_detail(&self, _id: &str) -> Result<Option<PostDetail>, CustomError> {
+        not_mocked()
+    }
+
+    async fn update_post(
+        &self,
+        id: &str,
+        title: Option<String>,
+        content: Option<String>,
+        is_sensitive: Option<bool>,
+        images: Option<Vec<PostImage>>,
+        _expected_version: i64,
+    ) -> Result<Option<Post>, CustomError> {
+        let mut posts = self.posts.lock().unwrap();
+        let Some(post) = posts.iter_mut().find(|post| post.id.to_hex() == id) else {
+            return Ok(None);
+        };
+        if let Some(title) = title {
+            post.title = title;
+        }
+        if let Some(content) = content {
+            post.content = content;
+        }
+        if let Some(images) = images {
+            post.images = images;
+        }
+        post.is_sensitive = is_sensitive.unwrap_or(post.is_sensitive);
+        post.version += 1;
+        Ok(Some(post.clone()))
+    }
+
+    async fn delete_post(&self, _id: &str) -> Result<bool, CustomError> {
+        not_mocked()
+    }
+
+    async fn delete_posts_by_author(&self, _author_id: &ObjectId) -> Result<u64, CustomError> {
+        not_mocked()
+    }
+
+    async fn set_pinned(&self, _id: &str, _pinned: bool) -> Result<Option<Post>, CustomError> {
+        not_mocked()
+    }
+}
+
+/// Admits every request unless `limited`, and records cache deletions
+#[derive(Default)]
+pub struct MockRedis {
+    pub limited: bool,
+    pub deleted: Mutex<Vec<String>>,
+}
+
+impl MockRedis {
+    /// A rate limiter that rejects every request
+    pub fn limited() -> Self {
+        MockRedis {
+            limited: true,
+            ..Default::default()
+        }
+    }
+}
+
+impl RedisServiceTrait for MockRedis {
+    async fn sliding_window_check(
+        &self,
+        _key: &str,
+        max_requests: u64,
+        _window_seconds: u64,
+    ) -> Result<RateLimitDecision, String> {
+        let retry_after_seconds = if self.limited {
+            MOCK_RETRY_AFTER_SECONDS
+        } else {
+            0
+        };
+        Ok(RateLimitDecision {
+            allowed: !self.limited,
+            limit: max_requests,
+            remaining: if self.limited { 0 } else { max_requests },
+            reset_after_seconds: retry_after_seconds,
+            retry_after_seconds,
+        })
+    }
+
+    async fn cache_delete(&self, key: &str) -> Result<(), String> {
+        self.deleted.lock().unwrap().push(key.to_string());
+        Ok(())
+    }
+}
+
+/// Lets all content through: no spam restrictions, links left as written
+pub struct PassThrough;
+
+impl SpamGuardTrait for PassThrough {
+    async fn check(
+        &self,
+        _user_id: &ObjectId,
+        _action: SpamAction,
+        _content: &str,
+    ) -> Result<(), CustomError> {
+        Ok(())
+    }
+}
+
+impl LinkGuardTrait for PassThrough {
+    async fn screen(&self, content: &str) -> Result<String, CustomError> {
+        Ok(content.to_string())
+    }
+}
+
+/// Drops the side effects tests don't look at: badges, topic counters,
+/// leaderboards and fingerprints. Owns no uploads.
+pub struct NoOp;
+
+impl BadgeServiceTrait for NoOp {
+    async fn on_post_created(&self, _author_id: &ObjectId) {}
+}
+
+impl TopicServiceTrait for NoOp {
+    async fn on_post_created(&self, _topics: &[String]) {}
+}
+
+impl LeaderboardServiceTrait for NoOp {
+    async fn record_comment(&self, _author_id: &ObjectId) {}
+}
+
+impl FingerprintServiceTrait for NoOp {
+    async fn record(
+        &self,
+        _user_id: &ObjectId,
+        _event: FingerprintEvent,
+        _client: &ClientFingerprint,
+    ) {
+    }
+}
+
+impl MediaServiceTrait for NoOp {
+    async fn owned_by(
+        &self,
+        _owner_id: &ObjectId,
+        _public_ids: &[String],
+    ) -> Result<Vec<MediaUpload>, CustomError> {
+        Ok(Vec::new())
+    }
+}
+
+/// Shadow bans the listed users
+#[derive(Default)]
+pub struct MockModeration {
+    pub shadow_banned: Vec<ObjectId>,
+}
+
+impl ModerationServiceTrait for MockModeration {
+    async fn hidden_authors(
+        &self,
+        viewer: Option<&ObjectId>,
+    ) -> Result<Vec<ObjectId>, CustomError> {
+        Ok(self
+            .shadow_banned
+            .iter()
+            .filter(|id| Some(*id) != viewer)
+            .copied()
+            .collect())
+    }
+
+    async fn is_shadow_banned(&self, user_id: &ObjectId) -> Result<bool, CustomError> {
+        Ok(self.shadow_banned.contains(user_id))
+    }
+}
+
+/// Every post has the same subscribers
+#[derive(Default)]
+pub struct MockSubscriptions {
+    pub subscribers: Vec<ObjectId>,
+}
+
+impl SubscriptionServiceTrait for MockSubscriptions {
+    async fn subscribers(
+        &self,
+        _post_id: &ObjectId,
+        _author_id: &ObjectId,
+    ) -> Result<Vec<ObjectId>, CustomError> {
+        Ok(self.subscribers.clone())
+    }
+}
+
+/// Records who was notified instead of storing notifications
+#[derive(Default)]
+pub struct MockNotifications {
+    pub sent: Mutex<Vec<ObjectId>>,
+}
+
+impl NotificationServiceTrait for MockNotifications {
+    async fn notify(
+        &self,
+        user_id: ObjectId,
+        _actor_id: Option<ObjectId>,
+        _kind: NotificationKind,
+        _message: String,
+        _reference_id: Option<ObjectId>,
+    ) -> Result<ObjectId, CustomError> {
+        self.sent.lock().unwrap().push(user_id);
+        Ok(ObjectId::new())
+    }
+}
@@ -1,7 +1,25 @@
+pub mod config;
+pub mod datetime;
 pub mod email;
+pub mod email_provider;
+pub mod email_templates;
 pub mod error;
 pub mod hashing;
 pub mod helpers;
+pub mod i18n;
+pub mod language;
+#[cfg(test)]
+pub mod mocks;
 pub mod model;
+pub mod otp;
+pub mod outbox;
 pub mod password_validation;
+pub mod qr;
+pub mod response;
+pub mod sanitize;
+pub mod scheduler;
+pub mod sms;
+pub mod telemetry;
+pub mod tls;
 pub mod uploads;
+pub mod validation;
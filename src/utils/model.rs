@@ -1,7 +1,11 @@
+use crate::utils::validation::not_blank;
 use serde::Deserialize;
+use validator::Validate;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Validate)]
 pub struct LoginRequests {
+    #[validate(custom(function = "not_blank"))]
     pub username: String,
+    #[validate(length(min = 1, message = "must not be empty"))]
     pub password: String,
 }
@@ -0,0 +1,84 @@
+use crate::database::parse_optional;
+use rand::rngs::OsRng;
+use rand::{Rng, TryRngCore};
+use std::env;
+
+/// Where verification codes are sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OtpChannel {
+    Email,
+    Sms,
+    Both,
+}
+
+impl OtpChannel {
+    pub fn sends_email(&self) -> bool {
+        matches!(self, OtpChannel::Email | OtpChannel::Both)
+    }
+
+    pub fn sends_sms(&self) -> bool {
+        matches!(self, OtpChannel::Sms | OtpChannel::Both)
+    }
+}
+
+/// Guesses allowed against one code before it is burned and a new one must
+/// be requested; with a 6-digit code that is a 1 in 200,000 chance
+pub const OTP_MAX_ATTEMPTS: u32 = 5;
+
+/// Shape and delivery of one-time verification codes
+#[derive(Debug, Clone)]
+pub struct OtpConfig {
+    /// Digits per code (`OTP_LENGTH`, 6 to 10, default 6)
+    pub length: usize,
+    /// How long a code is accepted (`OTP_EXPIRY_MINUTES`, default 10)
+    pub expiry_minutes: i64,
+    /// `OTP_CHANNEL`: `email` (default), `sms` or `both`; SMS needs
+    /// `SMS_PROVIDER`
+    pub channel: OtpChannel,
+}
+
+impl OtpConfig {
+    /// Load OTP settings from environment variables
+    pub fn from_env() -> Result<Self, String> {
+        let length = parse_optional::<usize>("OTP_LENGTH")?.unwrap_or(6);
+        if !(6..=10).contains(&length) {
+            return Err("OTP_LENGTH must be between 6 and 10".to_string());
+        }
+
+        let expiry_minutes = parse_optional::<i64>("OTP_EXPIRY_MINUTES")?.unwrap_or(10);
+        if expiry_minutes <= 0 {
+            return Err("OTP_EXPIRY_MINUTES must be greater than 0".to_string());
+        }
+
+        let channel = match env::var("OTP_CHANNEL")
+            .unwrap_or_else(|_| "email".to_string())
+            .to_lowercase()
+            .as_str()
+        {
+            "email" => OtpChannel::Email,
+            "sms" => OtpChannel::Sms,
+            "both" => OtpChannel::Both,
+            other => {
+                return Err(format!(
+                    "OTP_CHANNEL must be email, sms or both (got {})",
+                    other
+                ));
+            }
+        };
+
+        Ok(Self {
+            length,
+            expiry_minutes,
+            channel,
+        })
+    }
+}
+
+/// Generate a numeric code of `length` digits from the operating system's
+/// CSPRNG. Every digit is uniform, so leading zeros occur.
+pub fn generate_otp_code(length: usize) -> String {
+    let mut rng = OsRng.unwrap_err();
+    (0..length)
+        .map(|_| char::from(b'0' + rng.random_range(0..10u8)))
+        .collect()
+}
@@ -0,0 +1,635 @@
+use crate::notification::model::NotificationSettings;
+use crate::utils::config::AppConfig;
+use crate::utils::datetime::bson_datetime;
+use crate::utils::datetime::bson_now;
+use crate::utils::datetime::option_bson_datetime;
+use crate::utils::email::{EmailConfig, EmailService};
+use crate::utils::email_provider::EmailError;
+use crate::utils::email_templates::EmailTemplate;
+use crate::utils::error::CustomError;
+use crate::utils::i18n::Locale;
+use chrono::{DateTime, Utc};
+use futures_util::TryStreamExt;
+use mongodb::bson::{Bson, Document, doc, oid::ObjectId};
+use mongodb::options::ReturnDocument;
+use mongodb::{Client, ClientSession, Collection, IndexModel};
+use serde::{Deserialize, Serialize};
+
+/// Delay between the welcome email and the onboarding tips email
+pub const ONBOARDING_TIPS_DELAY_HOURS: i64 = 24;
+
+/// Maximum number of delivery attempts before an email is marked as failed
+pub const MAX_DELIVERY_ATTEMPTS: u32 = 5;
+
+/// Delay before the first retry; doubles with every failed attempt
+const RETRY_BASE_DELAY_SECONDS: i64 = 30;
+
+/// Upper bound for the retry delay
+const RETRY_MAX_DELAY_SECONDS: i64 = 60 * 60;
+
+/// How long a claimed email is reserved for the sender that claimed it. A
+/// sender that dies mid-send loses the claim after this and it is retried.
+const SEND_LEASE_SECONDS: i64 = 5 * 60;
+
+/// Emails claimed per worker run
+const PENDING_BATCH_SIZE: usize = 50;
+
+/// How long to wait before retrying after `attempts` failed deliveries
+fn retry_delay(attempts: u32) -> chrono::Duration {
+    let factor = 1_i64 << attempts.saturating_sub(1).min(16);
+    chrono::Duration::seconds((RETRY_BASE_DELAY_SECONDS * factor).min(RETRY_MAX_DELAY_SECONDS))
+}
+
+/// Payload fields holding one-time secrets. They are removed once an email
+/// stops being pending, so a sent or failed row can't be used to sign in.
+const SECRET_FIELDS: [&str; 2] = ["payload.otp_code", "payload.reset_token"];
+
+/// `$unset` document removing every one-time secret from a payload
+fn redact_secrets() -> Document {
+    SECRET_FIELDS
+        .iter()
+        .map(|field| (field.to_string(), Bson::from("")))
+        .collect()
+}
+
+/// Email waiting to be delivered
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OutboxEmail {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub to_email: String,
+    pub payload: OutboxPayload,
+    pub status: OutboxStatus,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    /// Earliest time the worker may retry; missing on emails queued before backoff
+    #[serde(default, with = "option_bson_datetime")]
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    /// End of the claim while the email is being sent
+    #[serde(default, with = "option_bson_datetime")]
+    pub lease_until: Option<DateTime<Utc>>,
+    #[serde(default, with = "option_bson_datetime")]
+    pub sent_at: Option<DateTime<Utc>>,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "bson_datetime")]
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Delivery status for support tooling, without the email contents
+#[derive(Debug, Serialize)]
+pub struct OutboxEmailStatus {
+    pub id: Option<String>,
+    pub to_email: String,
+    pub kind: &'static str,
+    pub status: OutboxStatus,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    pub sent_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<OutboxEmail> for OutboxEmailStatus {
+    fn from(email: OutboxEmail) -> Self {
+        OutboxEmailStatus {
+            id: email.id.map(|id| id.to_hex()),
+            to_email: email.to_email,
+            kind: email.payload.kind(),
+            status: email.status,
+            attempts: email.attempts,
+            last_error: email.last_error,
+            next_attempt_at: email.next_attempt_at,
+            sent_at: email.sent_at,
+            created_at: email.created_at,
+            updated_at: email.updated_at,
+        }
+    }
+}
+
+/// Content of an outbox email
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum OutboxPayload {
+    Verification {
+        /// Empty once the email is no longer pending
+        #[serde(default)]
+        otp_code: String,
+        #[serde(default)]
+        locale: Locale,
+    },
+    PasswordReset {
+        /// Empty once the email is no longer pending
+        #[serde(default)]
+        reset_token: String,
+        #[serde(default)]
+        locale: Locale,
+    },
+    Welcome {
+        user_id: ObjectId,
+        username: String,
+        #[serde(default)]
+        locale: Locale,
+    },
+    OnboardingTips {
+        user_id: ObjectId,
+        username: String,
+        #[serde(default)]
+        locale: Locale,
+    },
+    /// A scheduled admin export is ready to download
+    ReportExport {
+        report: String,
+        row_count: u64,
+        download_url: String,
+        expires_in_hours: u32,
+        #[serde(default)]
+        locale: Locale,
+    },
+}
+
+impl OutboxPayload {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            OutboxPayload::Verification { .. } => "verification",
+            OutboxPayload::PasswordReset { .. } => "password_reset",
+            OutboxPayload::Welcome { .. } => "welcome",
+            OutboxPayload::OnboardingTips { .. } => "onboarding_tips",
+            OutboxPayload::ReportExport { .. } => "report_export",
+        }
+    }
+
+    /// Whether the one-time secret was removed from the payload. Such emails
+    /// can't be sent again; the user has to ask for a new code or link.
+    fn secret_removed(&self) -> bool {
+        match self {
+            OutboxPayload::Verification { otp_code, .. } => otp_code.is_empty(),
+            OutboxPayload::PasswordReset { reset_token, .. } => reset_token.is_empty(),
+            _ => false,
+        }
+    }
+
+    /// Account owner for emails that users can opt out of. These are also
+    /// the non-urgent ones, held back during do-not-disturb hours.
+    fn opt_out_user(&self) -> Option<&ObjectId> {
+        match self {
+            OutboxPayload::Verification { .. }
+            | OutboxPayload::PasswordReset { .. }
+            | OutboxPayload::ReportExport { .. } => None,
+            OutboxPayload::Welcome { user_id, .. }
+            | OutboxPayload::OnboardingTips { user_id, .. } => Some(user_id),
+        }
+    }
+}
+
+/// Delivery status of an outbox email
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutboxStatus {
+    Pending,
+    /// Claimed by a sender, until `lease_until`
+    Sending,
+    Sent,
+    Failed,
+    /// Not sent because the user opted out
+    Skipped,
+}
+
+impl OutboxStatus {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "pending" => Some(OutboxStatus::Pending),
+            "sending" => Some(OutboxStatus::Sending),
+            "sent" => Some(OutboxStatus::Sent),
+            "failed" => Some(OutboxStatus::Failed),
+            "skipped" => Some(OutboxStatus::Skipped),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            OutboxStatus::Pending => "pending",
+            OutboxStatus::Sending => "sending",
+            OutboxStatus::Sent => "sent",
+            OutboxStatus::Failed => "failed",
+            OutboxStatus::Skipped => "skipped",
+        }
+    }
+}
+
+/// Outbox for emails that must survive SMTP failures
+pub struct EmailOutbox {
+    collection: Collection<OutboxEmail>,
+    settings: Collection<NotificationSettings>,
+    email: EmailConfig,
+    /// Stated in verification emails
+    otp_expiry_minutes: i64,
+}
+
+impl EmailOutbox {
+    pub fn new(client: &Client, config: &AppConfig) -> Self {
+        let db = client.database("rust_blogdb");
+        let collection = db.collection::<OutboxEmail>("email_outbox");
+        let settings = db.collection::<NotificationSettings>("notification_settings");
+        EmailOutbox {
+            collection,
+            settings,
+            email: config.email.clone(),
+            otp_expiry_minutes: config.otp.expiry_minutes,
+        }
+    }
+
+    /// Index the worker's due-email query and the support lookup by recipient
+    pub async fn ensure_indexes(&self) -> Result<(), CustomError> {
+        let indexes = vec![
+            IndexModel::builder()
+                .keys(doc! { "status": 1, "next_attempt_at": 1 })
+                .build(),
+            IndexModel::builder()
+                .keys(doc! { "to_email": 1, "created_at": -1 })
+                .build(),
+        ];
+
+        self.collection.create_indexes(indexes).await.map_err(|e| {
+            CustomError::InternalServerError(format!("Failed to create outbox indexes: {}", e))
+        })?;
+
+        Ok(())
+    }
+
+    /// Queue an email, optionally as part of a transaction
+    #[tracing::instrument(skip_all)]
+    pub async fn enqueue(
+        &self,
+        to_email: &str,
+        payload: OutboxPayload,
+        session: Option<&mut ClientSession>,
+    ) -> Result<OutboxEmail, CustomError> {
+        self.enqueue_at(to_email, payload, Utc::now(), session)
+            .await
+    }
+
+    /// Queue an email that the worker must not send before `send_at`
+    #[tracing::instrument(skip_all)]
+    pub async fn enqueue_at(
+        &self,
+        to_email: &str,
+        payload: OutboxPayload,
+        send_at: DateTime<Utc>,
+        session: Option<&mut ClientSession>,
+    ) -> Result<OutboxEmail, CustomError> {
+        let mut email = OutboxEmail {
+            id: None,
+            to_email: to_email.to_string(),
+            payload,
+            status: OutboxStatus::Pending,
+            attempts: 0,
+            last_error: None,
+            next_attempt_at: Some(send_at),
+            lease_until: None,
+            sent_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        let result = match session {
+            Some(s) => self.collection.insert_one(&email).session(s).await,
+            None => self.collection.insert_one(&email).await,
+        }
+        .map_err(|e| CustomError::InternalServerError(format!("Failed to queue email: {}", e)))?;
+
+        email.id = result.inserted_id.as_object_id();
+        Ok(email)
+    }
+
+    /// Filter for emails a sender may claim: pending ones that are due, and
+    /// ones whose sender's lease ran out
+    fn claimable() -> Document {
+        let now = bson_now();
+        doc! {
+            "$or": [
+                {
+                    "status": "pending",
+                    "$or": [
+                        { "next_attempt_at": { "$lte": now } },
+                        { "next_attempt_at": null }
+                    ]
+                },
+                { "status": "sending", "lease_until": { "$lte": now } }
+            ]
+        }
+    }
+
+    /// Atomically mark a claimable email as being sent, so no other sender
+    /// picks it up. `None` when nothing matched.
+    async fn claim(&self, filter: Document) -> Result<Option<OutboxEmail>, CustomError> {
+        let lease_until = Utc::now() + chrono::Duration::seconds(SEND_LEASE_SECONDS);
+        self.collection
+            .find_one_and_update(
+                doc! { "$and": [filter, Self::claimable()] },
+                doc! {
+                    "$set": {
+                        "status": "sending",
+                        "lease_until": mongodb::bson::DateTime::from_chrono(lease_until),
+                        "updated_at": bson_now()
+                    }
+                },
+            )
+            .sort(doc! { "created_at": 1 })
+            .return_document(ReturnDocument::After)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))
+    }
+
+    /// Try to deliver a single email and record the outcome. Does nothing if
+    /// another sender already claimed it or it isn't due yet.
+    #[tracing::instrument(skip_all)]
+    pub async fn dispatch(&self, email: &OutboxEmail) -> Result<(), CustomError> {
+        let id = email.id.ok_or_else(|| {
+            CustomError::InternalServerError("Outbox email ID missing".to_string())
+        })?;
+
+        match self.claim(doc! { "_id": id }).await? {
+            Some(email) => self.deliver(&email).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Send a claimed email and release the claim with the outcome
+    async fn deliver(&self, email: &OutboxEmail) -> Result<(), CustomError> {
+        let id = email.id.ok_or_else(|| {
+            CustomError::InternalServerError("Outbox email ID missing".to_string())
+        })?;
+
+        let settings = match email.payload.opt_out_user() {
+            Some(user_id) => Some(self.user_settings(user_id).await?),
+            None => None,
+        };
+
+        // Opt-outs are checked at send time so they also cover queued emails
+        if settings
+            .as_ref()
+            .is_some_and(|settings| !settings.onboarding_emails)
+        {
+            self.collection
+                .update_one(
+                    doc! { "_id": id },
+                    doc! {
+                        "$set": {
+                            "status": "skipped",
+                            "next_attempt_at": null,
+                            "lease_until": null,
+                            "updated_at": bson_now()
+                        },
+                        "$unset": redact_secrets(),
+                    },
+                )
+                .await
+                .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+            return Ok(());
+        }
+
+        // Wait for the user's do-not-disturb hours to end, without using up an attempt
+        if let Some(until) = settings.and_then(|settings| settings.dnd_until(Utc::now())) {
+            self.collection
+                .update_one(
+                    doc! { "_id": id },
+                    doc! {
+                        "$set": {
+                            "status": "pending",
+                            "next_attempt_at": mongodb::bson::DateTime::from_chrono(until),
+                            "lease_until": null,
+                            "updated_at": bson_now()
+                        }
+                    },
+                )
+                .await
+                .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+            return Ok(());
+        }
+
+        match self.send(email).await {
+            Ok(()) => {
+                self.collection
+                    .update_one(
+                        doc! { "_id": id },
+                        doc! {
+                            "$set": {
+                                "status": "sent",
+                                "last_error": null,
+                                "next_attempt_at": null,
+                                "lease_until": null,
+                                "sent_at": bson_now(),
+                                "updated_at": bson_now()
+                            },
+                            "$inc": { "attempts": 1 },
+                            "$unset": redact_secrets(),
+                        },
+                    )
+                    .await
+                    .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+                Ok(())
+            }
+            Err(error) => {
+                let attempts = email.attempts + 1;
+                // Rejected messages fail the same way every time, so stop now
+                let (status, next_attempt_at) =
+                    if attempts >= MAX_DELIVERY_ATTEMPTS || !error.is_retryable() {
+                        ("failed", None)
+                    } else {
+                        let next = Utc::now() + retry_delay(attempts);
+                        ("pending", Some(mongodb::bson::DateTime::from_chrono(next)))
+                    };
+
+                let mut update = doc! {
+                    "$set": {
+                        "status": status,
+                        "last_error": error.to_string(),
+                        "next_attempt_at": next_attempt_at,
+                        "lease_until": null,
+                        "updated_at": bson_now()
+                    },
+                    "$inc": { "attempts": 1 }
+                };
+                if status == "failed" {
+                    update.insert("$unset", redact_secrets());
+                }
+                self.collection
+                    .update_one(doc! { "_id": id }, update)
+                    .await
+                    .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+                Err(CustomError::InternalServerError(format!(
+                    "Failed to send email: {}",
+                    error
+                )))
+            }
+        }
+    }
+
+    /// Retry every pending email whose backoff has elapsed, returning how
+    /// many were delivered
+    #[tracing::instrument(skip_all)]
+    pub async fn process_pending(&self) -> Result<usize, CustomError> {
+        let mut delivered = 0;
+        for _ in 0..PENDING_BATCH_SIZE {
+            let Some(email) = self.claim(doc! {}).await? else {
+                break;
+            };
+            match self.deliver(&email).await {
+                Ok(()) => delivered += 1,
+                Err(e) => log::warn!("Outbox delivery to {} failed: {}", email.to_email, e),
+            }
+        }
+
+        Ok(delivered)
+    }
+
+    /// Most recent emails, optionally filtered by status and recipient
+    #[tracing::instrument(skip_all)]
+    pub async fn list(
+        &self,
+        status: Option<OutboxStatus>,
+        to_email: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<OutboxEmail>, CustomError> {
+        let mut filter = doc! {};
+        if let Some(status) = status {
+            filter.insert("status", status.name());
+        }
+        if let Some(to_email) = to_email {
+            filter.insert("to_email", to_email);
+        }
+
+        let cursor = self
+            .collection
+            .find(filter)
+            .sort(doc! { "created_at": -1 })
+            .limit(limit)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        cursor
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn get(&self, id: &ObjectId) -> Result<Option<OutboxEmail>, CustomError> {
+        self.collection
+            .find_one(doc! { "_id": id })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))
+    }
+
+    /// Put a failed email back in the queue with a fresh attempt budget.
+    /// Verification and reset emails lost their secret when they failed, so
+    /// they aren't requeued.
+    #[tracing::instrument(skip_all)]
+    pub async fn requeue(&self, id: &ObjectId) -> Result<bool, CustomError> {
+        let result = self
+            .collection
+            .update_one(
+                doc! {
+                    "_id": id,
+                    "status": "failed",
+                    "payload.kind": { "$nin": ["verification", "password_reset"] },
+                },
+                doc! {
+                    "$set": {
+                        "status": "pending",
+                        "attempts": 0,
+                        "next_attempt_at": bson_now(),
+                        "updated_at": bson_now()
+                    }
+                },
+            )
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        Ok(result.matched_count > 0)
+    }
+
+    async fn user_settings(&self, user_id: &ObjectId) -> Result<NotificationSettings, CustomError> {
+        let settings = self
+            .settings
+            .find_one(doc! { "_id": user_id })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        Ok(settings.unwrap_or_else(|| NotificationSettings::defaults(*user_id)))
+    }
+
+    /// Render and send the email through the configured provider
+    #[tracing::instrument(skip_all)]
+    async fn send(&self, email: &OutboxEmail) -> Result<(), EmailError> {
+        if email.payload.secret_removed() {
+            return Err(EmailError::Rejected(
+                "One-time secret was removed from this email".to_string(),
+            ));
+        }
+
+        let email_service = EmailService::with_config(self.email.clone());
+
+        match &email.payload {
+            OutboxPayload::Verification { otp_code, locale } => {
+                email_service
+                    .send_verification_email(
+                        &email.to_email,
+                        otp_code,
+                        self.otp_expiry_minutes,
+                        *locale,
+                    )
+                    .await
+            }
+            OutboxPayload::PasswordReset {
+                reset_token,
+                locale,
+            } => {
+                email_service
+                    .send_password_reset_email(&email.to_email, reset_token, *locale)
+                    .await
+            }
+            OutboxPayload::Welcome {
+                username, locale, ..
+            } => {
+                let template = EmailTemplate::Welcome {
+                    username: username.clone(),
+                };
+                email_service
+                    .send_template(&email.to_email, &template, *locale)
+                    .await
+            }
+            OutboxPayload::OnboardingTips {
+                username, locale, ..
+            } => {
+                let template = EmailTemplate::OnboardingTips {
+                    username: username.clone(),
+                };
+                email_service
+                    .send_template(&email.to_email, &template, *locale)
+                    .await
+            }
+            OutboxPayload::ReportExport {
+                report,
+                row_count,
+                download_url,
+                expires_in_hours,
+                locale,
+            } => {
+                let template = EmailTemplate::ReportExport {
+                    report: report.clone(),
+                    row_count: *row_count,
+                    download_url: download_url.clone(),
+                    expires_in_hours: *expires_in_hours,
+                };
+                email_service
+                    .send_template(&email.to_email, &template, *locale)
+                    .await
+            }
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::utils::error::CustomError;
+use crate::utils::error::{CustomError, ErrorCode};
 
 // pub fn validate_password(password: &str) -> Result<(), CustomError> {
 //     // Regex pattern to check for length and character requirements
@@ -26,8 +26,9 @@ use crate::utils::error::CustomError;
 pub fn validate_password(password: &str) -> Result<(), CustomError> {
     // Check password length
     if password.len() < 8 || password.len() > 20 {
-        return Err(CustomError::BadRequestError(
-            "Password must be between 8 and 20 characters long.".into(),
+        return Err(CustomError::coded(
+            ErrorCode::AuthWeakPassword,
+            "Password must be between 8 and 20 characters long.",
         ));
     }
 
@@ -37,7 +38,10 @@ pub fn validate_password(password: &str) -> Result<(), CustomError> {
     let has_digit = password.chars().any(|c| c.is_digit(10));
 
     if !has_lowercase || !has_uppercase || !has_digit {
-        return Err(CustomError::BadRequestError("Password must include at least one uppercase letter, one lowercase letter, and one number.".into()));
+        return Err(CustomError::coded(
+            ErrorCode::AuthWeakPassword,
+            "Password must include at least one uppercase letter, one lowercase letter, and one number.",
+        ));
     }
 
     Ok(())
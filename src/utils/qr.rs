@@ -0,0 +1,54 @@
+use crate::utils::error::CustomError;
+use image::{ImageFormat, Luma};
+use qrcode::QrCode;
+use qrcode::render::svg;
+use serde::Deserialize;
+use std::io::Cursor;
+
+/// Side of a rendered QR code when the client doesn't ask for one, in pixels
+pub const DEFAULT_QR_SIZE: u32 = 512;
+const MIN_QR_SIZE: u32 = 128;
+const MAX_QR_SIZE: u32 = 1024;
+
+/// Image formats a QR code can be rendered as
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum QrFormat {
+    #[default]
+    Png,
+    Svg,
+}
+
+impl QrFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            QrFormat::Png => "image/png",
+            QrFormat::Svg => "image/svg+xml",
+        }
+    }
+}
+
+/// Render `data` as a QR code about `size` pixels wide, clamped to a sane
+/// range
+pub fn render_qr(data: &str, format: QrFormat, size: u32) -> Result<Vec<u8>, CustomError> {
+    let code = QrCode::new(data.as_bytes()).map_err(|e| {
+        CustomError::InternalServerError(format!("Failed to encode QR code: {}", e))
+    })?;
+    let size = size.clamp(MIN_QR_SIZE, MAX_QR_SIZE);
+
+    match format {
+        QrFormat::Png => {
+            let image = code.render::<Luma<u8>>().min_dimensions(size, size).build();
+            let mut png = Cursor::new(Vec::new());
+            image.write_to(&mut png, ImageFormat::Png).map_err(|e| {
+                CustomError::InternalServerError(format!("Failed to render QR code: {}", e))
+            })?;
+            Ok(png.into_inner())
+        }
+        QrFormat::Svg => Ok(code
+            .render::<svg::Color>()
+            .min_dimensions(size, size)
+            .build()
+            .into_bytes()),
+    }
+}
@@ -0,0 +1,182 @@
+use crate::utils::config::service_name;
+use actix_web::body::BoxBody;
+use actix_web::http::StatusCode;
+use actix_web::{HttpRequest, HttpResponse, Responder};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Success envelope shared by every handler.
+///
+/// ```json
+/// { "success": true, "message": "...", "httpStatusCode": 200,
+///   "service": "...", "data": ..., "count": 3 }
+/// ```
+///
+/// `data` and `count` are omitted when not set.
+#[derive(Debug, Serialize)]
+pub struct ApiResponse<T: Serialize = ()> {
+    success: bool,
+    message: String,
+    #[serde(rename = "httpStatusCode")]
+    http_status_code: u16,
+    service: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    count: Option<usize>,
+}
+
+impl ApiResponse {
+    pub fn with_status(status: StatusCode, message: impl Into<String>) -> Self {
+        ApiResponse {
+            success: true,
+            message: message.into(),
+            http_status_code: status.as_u16(),
+            service: service_name(),
+            data: None,
+            count: None,
+        }
+    }
+
+    /// 200 OK
+    pub fn ok(message: impl Into<String>) -> Self {
+        Self::with_status(StatusCode::OK, message)
+    }
+
+    /// 201 Created
+    pub fn created(message: impl Into<String>) -> Self {
+        Self::with_status(StatusCode::CREATED, message)
+    }
+}
+
+impl<T: Serialize> ApiResponse<T> {
+    /// Attach the payload
+    pub fn data<U: Serialize>(self, data: U) -> ApiResponse<U> {
+        ApiResponse {
+            success: self.success,
+            message: self.message,
+            http_status_code: self.http_status_code,
+            service: self.service,
+            data: Some(data),
+            count: self.count,
+        }
+    }
+
+    /// Number of items, for list payloads
+    pub fn count(mut self, count: usize) -> Self {
+        self.count = Some(count);
+        self
+    }
+
+    /// Attach a list payload and its length
+    pub fn list<U: Serialize>(self, items: Vec<U>) -> ApiResponse<Vec<U>> {
+        let count = items.len();
+        self.data(items).count(count)
+    }
+
+    fn status(&self) -> StatusCode {
+        StatusCode::from_u16(self.http_status_code).unwrap_or(StatusCode::OK)
+    }
+}
+
+impl<T: Serialize> From<ApiResponse<T>> for HttpResponse {
+    fn from(response: ApiResponse<T>) -> Self {
+        HttpResponse::build(response.status()).json(response)
+    }
+}
+
+impl<T: Serialize> Responder for ApiResponse<T> {
+    type Body = BoxBody;
+
+    fn respond_to(self, _: &HttpRequest) -> HttpResponse {
+        self.into()
+    }
+}
+
+/// Error envelope, produced by `CustomError` and the error-handling middleware
+#[derive(Debug, Serialize)]
+pub struct ApiError {
+    success: bool,
+    message: String,
+    #[serde(rename = "httpStatusCode")]
+    http_status_code: u16,
+    error: String,
+    /// `ErrorCode` when one applies, otherwise the same as `error`
+    code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    details: Option<serde_json::Value>,
+    service: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    errors: Option<BTreeMap<String, Vec<String>>>,
+    #[serde(rename = "retryAfterSeconds", skip_serializing_if = "Option::is_none")]
+    retry_after_seconds: Option<u64>,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, error: impl Into<String>, message: impl Into<String>) -> Self {
+        let error = error.into();
+        ApiError {
+            success: false,
+            message: message.into(),
+            http_status_code: status.as_u16(),
+            code: error.clone(),
+            details: None,
+            error,
+            service: service_name(),
+            errors: None,
+            retry_after_seconds: None,
+        }
+    }
+
+    /// A more specific code than the `error` category
+    pub fn code(mut self, code: impl Into<String>) -> Self {
+        self.code = code.into();
+        self
+    }
+
+    /// Structured data about the failure, described by its code
+    pub fn details(mut self, details: serde_json::Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    /// Per-field validation messages
+    pub fn field_errors(mut self, errors: BTreeMap<String, Vec<String>>) -> Self {
+        self.errors = Some(errors);
+        self
+    }
+
+    /// When the client may try again
+    pub fn retry_after(mut self, seconds: u64) -> Self {
+        self.retry_after_seconds = Some(seconds);
+        self
+    }
+}
+
+/// The `error` category for a status, as `CustomError` reports it
+pub fn status_error(status_code: StatusCode) -> String {
+    match status_code {
+        StatusCode::BAD_REQUEST => "BAD_REQUEST_ERROR".to_string(),
+        StatusCode::UNAUTHORIZED => "UNAUTHORIZED_ERROR".to_string(),
+        StatusCode::FORBIDDEN => "FORBIDDEN_ERROR".to_string(),
+        StatusCode::NOT_FOUND => "NOT_FOUND_ERROR".to_string(),
+        StatusCode::CONFLICT => "CONFLICT_ERROR".to_string(),
+        StatusCode::UNPROCESSABLE_ENTITY => "VALIDATION_ERROR".to_string(),
+        StatusCode::TOO_MANY_REQUESTS => "TOO_MANY_REQUESTS_ERROR".to_string(),
+        StatusCode::GATEWAY_TIMEOUT => "TIMEOUT_ERROR".to_string(),
+        StatusCode::INTERNAL_SERVER_ERROR => "INTERNAL_SERVER_ERROR".to_string(),
+        other => other
+            .canonical_reason()
+            .unwrap_or("Unknown")
+            .to_uppercase()
+            .replace([' ', '-'], "_"),
+    }
+}
+
+impl From<ApiError> for HttpResponse {
+    fn from(error: ApiError) -> Self {
+        let status = StatusCode::from_u16(error.http_status_code)
+            .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        HttpResponse::build(status).json(error)
+    }
+}
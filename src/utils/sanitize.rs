@@ -0,0 +1,74 @@
+use crate::utils::error::CustomError;
+use ammonia::Builder;
+use std::collections::HashSet;
+use std::sync::LazyLock;
+
+/// Tags kept by `Markup::Safe`; everything else is stripped with its attributes
+const SAFE_TAGS: &[&str] = &[
+    "a",
+    "b",
+    "blockquote",
+    "br",
+    "code",
+    "em",
+    "i",
+    "li",
+    "ol",
+    "p",
+    "pre",
+    "strong",
+    "ul",
+];
+
+/// Elements dropped together with everything inside them
+const STRIPPED_WITH_CONTENT: &[&str] = &["script", "style", "iframe", "noscript", "template"];
+
+static PLAIN: LazyLock<Builder<'static>> = LazyLock::new(|| {
+    let mut builder = Builder::empty();
+    builder.clean_content_tags(STRIPPED_WITH_CONTENT.iter().copied().collect());
+    builder
+});
+
+static SAFE: LazyLock<Builder<'static>> = LazyLock::new(|| {
+    let mut builder = Builder::empty();
+    builder
+        .add_tags(SAFE_TAGS)
+        .clean_content_tags(STRIPPED_WITH_CONTENT.iter().copied().collect())
+        .add_tag_attributes("a", &["href"])
+        .url_schemes(HashSet::from(["http", "https", "mailto"]))
+        .link_rel(Some("noopener noreferrer nofollow ugc"));
+    builder
+});
+
+/// How much markup survives sanitization
+#[derive(Debug, Clone, Copy)]
+pub enum Markup {
+    /// Strip every tag, keeping only the text
+    None,
+    /// Keep basic formatting and links; drop scripts, iframes, styles and event handlers
+    Safe,
+}
+
+/// Clean user-generated content before it is stored or broadcast.
+///
+/// `<script>`, `<style>` and similar elements are removed along with their
+/// contents; other disallowed tags are unwrapped so their text is kept.
+/// Text is returned HTML-escaped, so it is safe to render as markup.
+pub fn sanitize(input: &str, markup: Markup) -> String {
+    match markup {
+        Markup::None => PLAIN.clean(input).to_string(),
+        Markup::Safe => SAFE.clean(input).to_string(),
+    }
+}
+
+/// Sanitize `input` and reject it if nothing but markup was submitted
+pub fn sanitize_required(input: &str, markup: Markup, field: &str) -> Result<String, CustomError> {
+    let cleaned = sanitize(input, markup);
+    if cleaned.trim().is_empty() {
+        return Err(CustomError::BadRequestError(format!(
+            "{} must contain text",
+            field
+        )));
+    }
+    Ok(cleaned)
+}
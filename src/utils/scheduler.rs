@@ -0,0 +1,124 @@
+use crate::database::RedisService;
+use crate::utils::error::CustomError;
+use chrono::Utc;
+use futures_util::future::LocalBoxFuture;
+use std::future::Future;
+use std::rc::Rc;
+use std::time::Duration;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// When a job runs. Runs are aligned to wall-clock slots (every 5 minutes
+/// means :00, :05, ...), so every instance agrees on which run is which.
+#[derive(Debug, Clone, Copy)]
+pub struct Schedule {
+    period: u64,
+    offset: u64,
+}
+
+impl Schedule {
+    /// Every `period`, on multiples of it since the Unix epoch
+    pub fn every(period: Duration) -> Self {
+        Schedule {
+            period: period.as_secs().max(1),
+            offset: 0,
+        }
+    }
+
+    /// Once a day at `hour:minute` UTC
+    pub fn daily_at(hour: u32, minute: u32) -> Self {
+        Schedule {
+            period: SECONDS_PER_DAY,
+            offset: (hour as u64 % 24) * 3600 + (minute as u64 % 60) * 60,
+        }
+    }
+
+    /// First slot strictly after `now`, as Unix seconds
+    fn next_after(&self, now: u64) -> u64 {
+        let since_offset = now.saturating_sub(self.offset);
+        (since_offset / self.period + 1) * self.period + self.offset
+    }
+}
+
+type JobFn = Rc<dyn Fn() -> LocalBoxFuture<'static, Result<(), CustomError>>>;
+
+struct Job {
+    name: &'static str,
+    schedule: Schedule,
+    run: JobFn,
+}
+
+/// Runs periodic background work.
+///
+/// Each run takes a Redis lock named after the job and its slot, so with
+/// several instances only one of them runs it. If Redis is unreachable the
+/// job runs anyway, which matches a single-instance deployment.
+pub struct Scheduler {
+    redis_service: RedisService,
+    jobs: Vec<Job>,
+}
+
+impl Scheduler {
+    pub fn new(redis_service: RedisService) -> Self {
+        Scheduler {
+            redis_service,
+            jobs: Vec::new(),
+        }
+    }
+
+    /// Register a job; `name` must be unique as it keys the lock
+    pub fn job<F, Fut>(mut self, name: &'static str, schedule: Schedule, run: F) -> Self
+    where
+        F: Fn() -> Fut + 'static,
+        Fut: Future<Output = Result<(), CustomError>> + 'static,
+    {
+        self.jobs.push(Job {
+            name,
+            schedule,
+            run: Rc::new(move || Box::pin(run())),
+        });
+        self
+    }
+
+    /// Spawn one loop per job on the current runtime
+    pub fn start(self) {
+        for job in self.jobs {
+            let redis_service = self.redis_service.clone();
+            log::info!("Scheduled job {} registered", job.name);
+            actix_web::rt::spawn(async move {
+                loop {
+                    let now = Utc::now().timestamp().max(0) as u64;
+                    let slot = job.schedule.next_after(now);
+                    actix_web::rt::time::sleep(Duration::from_secs(slot - now)).await;
+
+                    if !claim(&redis_service, &job, slot).await {
+                        continue;
+                    }
+                    let started = std::time::Instant::now();
+                    match (job.run)().await {
+                        Ok(()) => {
+                            log::debug!("Job {} finished in {:?}", job.name, started.elapsed())
+                        }
+                        Err(e) => log::error!("Job {} failed: {}", job.name, e),
+                    }
+                }
+            });
+        }
+    }
+}
+
+/// Take the lock for this run. It isn't released; it expires with the
+/// period, so instances that wake up late can't run the same slot again.
+async fn claim(redis_service: &RedisService, job: &Job, slot: u64) -> bool {
+    let key = format!("job:{}:{}", job.name, slot);
+    match redis_service
+        .acquire_lock(&key, job.schedule.period * 1000)
+        .await
+    {
+        Ok(token) => token.is_some(),
+        Err(e) => {
+            log::warn!("Scheduler lock unavailable for {}: {}", job.name, e);
+            true
+        }
+    }
+}
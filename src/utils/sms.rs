@@ -0,0 +1,229 @@
+use futures_util::future::BoxFuture;
+use serde_json::json;
+use std::env;
+use thiserror::Error;
+
+/// Which SMS backend delivers messages, and its credentials
+#[derive(Clone)]
+pub enum SmsProviderConfig {
+    Twilio {
+        account_sid: String,
+        auth_token: String,
+        /// Sending number in E.164 form
+        from: String,
+    },
+    Termii {
+        api_key: String,
+        sender_id: String,
+    },
+}
+
+/// SMS configuration; SMS is off unless `SMS_PROVIDER` is set
+#[derive(Clone)]
+pub struct SmsConfig {
+    pub provider: SmsProviderConfig,
+    /// Log instead of delivering
+    pub dry_run: bool,
+}
+
+fn required(name: &str) -> Result<String, String> {
+    env::var(name).map_err(|_| format!("{} is required", name))
+}
+
+impl SmsConfig {
+    /// Load SMS configuration from environment variables
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let Ok(provider) = env::var("SMS_PROVIDER") else {
+            return Ok(None);
+        };
+
+        let provider = match provider.to_lowercase().as_str() {
+            "twilio" => SmsProviderConfig::Twilio {
+                account_sid: required("TWILIO_ACCOUNT_SID")?,
+                auth_token: required("TWILIO_AUTH_TOKEN")?,
+                from: required("TWILIO_FROM_NUMBER")?,
+            },
+            "termii" => SmsProviderConfig::Termii {
+                api_key: required("TERMII_API_KEY")?,
+                sender_id: required("TERMII_SENDER_ID")?,
+            },
+            other => {
+                return Err(format!(
+                    "SMS_PROVIDER must be twilio or termii (got {})",
+                    other
+                ));
+            }
+        };
+
+        Ok(Some(Self {
+            provider,
+            dry_run: env::var("SMS_DRY_RUN")
+                .map(|v| v.eq_ignore_ascii_case("true") || v == "1")
+                .unwrap_or(false),
+        }))
+    }
+}
+
+/// Delivery failures, classified like `EmailError`
+#[derive(Debug, Error)]
+pub enum SmsError {
+    /// The provider will never accept this message (bad number, content)
+    #[error("SMS rejected: {0}")]
+    Rejected(String),
+
+    /// Credentials or sender setup are wrong
+    #[error("SMS provider misconfigured: {0}")]
+    Misconfigured(String),
+
+    /// Network errors, throttling and provider outages
+    #[error("SMS provider unavailable: {0}")]
+    Transient(String),
+}
+
+/// A backend that delivers text messages
+pub trait SmsProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn send<'a>(&'a self, to: &'a str, body: &'a str) -> BoxFuture<'a, Result<(), SmsError>>;
+}
+
+/// Classify a provider's HTTP error response
+fn status_error(provider: &str, status: reqwest::StatusCode, detail: String) -> SmsError {
+    let detail = format!("{} returned {}: {}", provider, status, detail);
+    match status.as_u16() {
+        401 | 403 => SmsError::Misconfigured(detail),
+        400 | 404 | 422 => SmsError::Rejected(detail),
+        _ => SmsError::Transient(detail),
+    }
+}
+
+/// Delivery through Twilio's Messages API
+pub struct TwilioProvider {
+    account_sid: String,
+    auth_token: String,
+    from: String,
+    client: reqwest::Client,
+}
+
+impl SmsProvider for TwilioProvider {
+    fn name(&self) -> &'static str {
+        "twilio"
+    }
+
+    fn send<'a>(&'a self, to: &'a str, body: &'a str) -> BoxFuture<'a, Result<(), SmsError>> {
+        Box::pin(async move {
+            let url = format!(
+                "https://api.twilio.com/2010-04-01/Accounts/{}/Messages.json",
+                self.account_sid
+            );
+            let response = self
+                .client
+                .post(url)
+                .basic_auth(&self.account_sid, Some(&self.auth_token))
+                .form(&[("To", to), ("From", self.from.as_str()), ("Body", body)])
+                .send()
+                .await
+                .map_err(|e| SmsError::Transient(format!("Twilio request failed: {}", e)))?;
+
+            let status = response.status();
+            if status.is_success() {
+                return Ok(());
+            }
+            let detail = response.text().await.unwrap_or_default();
+            Err(status_error("Twilio", status, detail))
+        })
+    }
+}
+
+/// Delivery through Termii's messaging API
+pub struct TermiiProvider {
+    api_key: String,
+    sender_id: String,
+    client: reqwest::Client,
+}
+
+impl SmsProvider for TermiiProvider {
+    fn name(&self) -> &'static str {
+        "termii"
+    }
+
+    fn send<'a>(&'a self, to: &'a str, body: &'a str) -> BoxFuture<'a, Result<(), SmsError>> {
+        Box::pin(async move {
+            // Termii expects the number without the leading plus
+            let payload = json!({
+                "api_key": self.api_key,
+                "to": to.trim_start_matches('+'),
+                "from": self.sender_id,
+                "sms": body,
+                "type": "plain",
+                "channel": "generic",
+            });
+            let response = self
+                .client
+                .post("https://api.ng.termii.com/api/sms/send")
+                .json(&payload)
+                .send()
+                .await
+                .map_err(|e| SmsError::Transient(format!("Termii request failed: {}", e)))?;
+
+            let status = response.status();
+            if status.is_success() {
+                return Ok(());
+            }
+            let detail = response.text().await.unwrap_or_default();
+            Err(status_error("Termii", status, detail))
+        })
+    }
+}
+
+/// Logs messages instead of sending them, for development
+pub struct DryRunSmsProvider;
+
+impl SmsProvider for DryRunSmsProvider {
+    fn name(&self) -> &'static str {
+        "dry-run"
+    }
+
+    fn send<'a>(&'a self, to: &'a str, body: &'a str) -> BoxFuture<'a, Result<(), SmsError>> {
+        Box::pin(async move {
+            log::info!("[dry-run] SMS to {} not sent: {}", to, body);
+            Ok(())
+        })
+    }
+}
+
+/// Sends text messages through the configured provider
+pub struct SmsService {
+    provider: Box<dyn SmsProvider>,
+}
+
+impl SmsService {
+    pub fn with_config(config: SmsConfig) -> Self {
+        let client = reqwest::Client::new();
+        let provider: Box<dyn SmsProvider> = match config.provider {
+            _ if config.dry_run => Box::new(DryRunSmsProvider),
+            SmsProviderConfig::Twilio {
+                account_sid,
+                auth_token,
+                from,
+            } => Box::new(TwilioProvider {
+                account_sid,
+                auth_token,
+                from,
+                client,
+            }),
+            SmsProviderConfig::Termii { api_key, sender_id } => Box::new(TermiiProvider {
+                api_key,
+                sender_id,
+                client,
+            }),
+        };
+
+        Self { provider }
+    }
+
+    pub async fn send(&self, to: &str, body: &str) -> Result<(), SmsError> {
+        log::debug!("Sending SMS via {}", self.provider.name());
+        self.provider.send(to, body).await
+    }
+}
@@ -0,0 +1,63 @@
+use opentelemetry::KeyValue;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::trace::TracerProvider;
+use opentelemetry_sdk::{Resource, runtime};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, fmt};
+
+/// Set up logging and tracing.
+///
+/// Log output honours `RUST_LOG` (default `info`); existing `log` macros are
+/// forwarded into `tracing`. When `OTEL_EXPORTER_OTLP_ENDPOINT` is set, spans
+/// are also exported over OTLP/gRPC under `SERVICE_NAME`. Returns the tracer
+/// provider so pending spans can be flushed on shutdown.
+pub fn init_telemetry() -> Result<Option<TracerProvider>, String> {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let registry = tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer());
+
+    let Ok(endpoint) = std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT") else {
+        registry
+            .try_init()
+            .map_err(|e| format!("Failed to initialize logging: {}", e))?;
+        return Ok(None);
+    };
+
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()
+        .map_err(|e| format!("Failed to create OTLP exporter: {}", e))?;
+
+    let service_name =
+        std::env::var("SERVICE_NAME").unwrap_or_else(|_| "socialization-app".to_string());
+    let provider = TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_resource(Resource::new(vec![KeyValue::new(
+            "service.name",
+            service_name,
+        )]))
+        .build();
+
+    let tracer = provider.tracer("socialization-app");
+    opentelemetry::global::set_tracer_provider(provider.clone());
+
+    registry
+        .with(tracing_opentelemetry::layer().with_tracer(tracer))
+        .try_init()
+        .map_err(|e| format!("Failed to initialize tracing: {}", e))?;
+
+    Ok(Some(provider))
+}
+
+/// Flush spans that are still buffered
+pub fn shutdown_telemetry(provider: Option<TracerProvider>) {
+    if let Some(provider) = provider
+        && let Err(e) = provider.shutdown()
+    {
+        eprintln!("Failed to flush traces: {}", e);
+    }
+}
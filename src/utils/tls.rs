@@ -0,0 +1,30 @@
+use crate::utils::config::TlsConfig;
+use rustls::ServerConfig;
+use std::fs::File;
+use std::io::BufReader;
+
+/// Build a rustls server config from the PEM certificate chain and key
+pub fn load_rustls_config(tls: &TlsConfig) -> Result<ServerConfig, String> {
+    let mut cert_reader = BufReader::new(
+        File::open(&tls.cert_path)
+            .map_err(|e| format!("Failed to open {}: {}", tls.cert_path, e))?,
+    );
+    let certs = rustls_pemfile::certs(&mut cert_reader)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read certificates from {}: {}", tls.cert_path, e))?;
+    if certs.is_empty() {
+        return Err(format!("No certificates found in {}", tls.cert_path));
+    }
+
+    let mut key_reader = BufReader::new(
+        File::open(&tls.key_path).map_err(|e| format!("Failed to open {}: {}", tls.key_path, e))?,
+    );
+    let key = rustls_pemfile::private_key(&mut key_reader)
+        .map_err(|e| format!("Failed to read private key from {}: {}", tls.key_path, e))?
+        .ok_or_else(|| format!("No private key found in {}", tls.key_path))?;
+
+    ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| format!("Invalid certificate/key pair: {}", e))
+}
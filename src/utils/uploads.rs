@@ -4,6 +4,7 @@ use sha1::{Digest, Sha1};
 use std::env;
 
 /// Cloudinary configuration loaded from environment variables
+#[derive(Clone)]
 pub struct CloudinaryConfig {
     pub cloud_name: String,
     pub api_key: String,
@@ -33,6 +34,33 @@ impl CloudinaryConfig {
         )
     }
 
+    /// Public ID and resource type of an asset delivered from this cloud,
+    /// e.g. `https://res.cloudinary.com/<cloud>/image/upload/v17/avatars/a.png`
+    /// gives `("avatars/a", "image")`. `None` for URLs hosted elsewhere.
+    pub fn asset_from_url(&self, url: &str) -> Option<(String, String)> {
+        let prefix = format!("https://res.cloudinary.com/{}/", self.cloud_name);
+        let mut segments = url.strip_prefix(&prefix)?.split('/');
+        let resource_type = segments.next()?.to_string();
+        if segments.next()? != "upload" {
+            return None;
+        }
+
+        // Skip the optional version segment
+        let mut path: Vec<&str> = segments.collect();
+        if path.first().is_some_and(|s| {
+            s.len() > 1 && s.starts_with('v') && s[1..].bytes().all(|b| b.is_ascii_digit())
+        }) {
+            path.remove(0);
+        }
+        let path = path.join("/");
+        let public_id = match path.rsplit_once('.') {
+            Some((public_id, _)) if resource_type != "raw" => public_id.to_string(),
+            _ => path,
+        };
+
+        (!public_id.is_empty()).then_some((public_id, resource_type))
+    }
+
     /// Generate a signature for authenticated uploads
     pub fn generate_signature(&self, params: &str, timestamp: i64) -> String {
         let to_sign = format!("{}&timestamp={}{}", params, timestamp, self.api_secret);
@@ -76,13 +104,6 @@ pub struct UploadService {
 }
 
 impl UploadService {
-    /// Create a new UploadService instance
-    pub fn new() -> Result<Self, String> {
-        let config = CloudinaryConfig::from_env()?;
-        let client = reqwest::Client::new();
-        Ok(Self { config, client })
-    }
-
     /// Create a new UploadService with custom config
     pub fn with_config(config: CloudinaryConfig) -> Self {
         let client = reqwest::Client::new();
@@ -122,6 +143,7 @@ impl UploadService {
     }
 
     /// Generic file upload to Cloudinary
+    #[tracing::instrument(skip_all)]
     async fn upload_file(
         &self,
         file_data: Vec<u8>,
@@ -193,6 +215,7 @@ impl UploadService {
     }
 
     /// Upload image from base64 string
+    #[tracing::instrument(skip_all)]
     pub async fn upload_image_base64(
         &self,
         base64_data: &str,
@@ -257,6 +280,7 @@ impl UploadService {
     }
 
     /// Delete a resource from Cloudinary
+    #[tracing::instrument(skip_all)]
     pub async fn delete_resource(
         &self,
         public_id: &str,
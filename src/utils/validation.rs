@@ -0,0 +1,123 @@
+use crate::utils::error::CustomError;
+use crate::utils::i18n::Locale;
+use actix_web::dev::Payload;
+use actix_web::{FromRequest, HttpRequest, web};
+use fluent_templates::fluent_bundle::FluentValue;
+use futures_util::future::LocalBoxFuture;
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+use std::ops::Deref;
+use validator::{Validate, ValidationError, ValidationErrors};
+
+/// JSON body extractor that runs `Validate` on the payload.
+///
+/// Malformed JSON is a bad request; a well-formed body that fails validation
+/// returns every invalid field with its messages.
+pub struct ValidatedJson<T>(pub T);
+
+impl<T> ValidatedJson<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for ValidatedJson<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: DeserializeOwned + Validate + 'static> FromRequest for ValidatedJson<T> {
+    type Error = CustomError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let json = web::Json::<T>::from_request(req, payload);
+        let locale = Locale::resolve(req);
+
+        Box::pin(async move {
+            let body = json
+                .await
+                .map_err(|e| CustomError::BadRequestError(e.to_string()))?
+                .into_inner();
+
+            body.validate().map_err(|errors| {
+                CustomError::FieldValidationError(field_errors(&errors, locale))
+            })?;
+
+            Ok(ValidatedJson(body))
+        })
+    }
+}
+
+/// Flatten validator errors into `field -> [messages]`
+fn field_errors(errors: &ValidationErrors, locale: Locale) -> BTreeMap<String, Vec<String>> {
+    errors
+        .field_errors()
+        .into_iter()
+        .map(|(field, errors)| {
+            let messages = errors
+                .iter()
+                .map(|e| {
+                    translate(e, locale).unwrap_or_else(|| match &e.message {
+                        Some(message) => message.to_string(),
+                        None => format!("{} is invalid ({})", field, e.code),
+                    })
+                })
+                .collect();
+            (field.to_string(), messages)
+        })
+        .collect()
+}
+
+/// Catalog message for the validator's error code, using its min/max params
+fn translate(error: &ValidationError, locale: Locale) -> Option<String> {
+    let param = |name: &str| {
+        error
+            .params
+            .get(name)
+            .and_then(|value| value.as_f64())
+            .map(FluentValue::from)
+    };
+
+    let message = match (error.code.as_ref(), param("min"), param("max")) {
+        ("length", Some(min), Some(max)) => {
+            locale.t_args("validation-length", &[("min", min), ("max", max)])
+        }
+        ("length", Some(min), None) => locale.t_args("validation-length-min", &[("min", min)]),
+        ("length", None, Some(max)) => locale.t_args("validation-length-max", &[("max", max)]),
+        ("range", Some(min), Some(max)) => {
+            locale.t_args("validation-range", &[("min", min), ("max", max)])
+        }
+        ("email", ..) => locale.t("validation-email"),
+        ("url", ..) => locale.t("validation-url"),
+        ("blank", ..) => locale.t("validation-blank"),
+        ("object_id", ..) => locale.t("validation-object-id"),
+        _ => return None,
+    };
+    Some(message)
+}
+
+/// Reject strings that are empty or only whitespace
+pub fn not_blank(value: &str) -> Result<(), ValidationError> {
+    if value.trim().is_empty() {
+        return Err(ValidationError::new("blank").with_message("must not be blank".into()));
+    }
+    Ok(())
+}
+
+/// Reject strings that aren't a MongoDB ObjectId
+pub fn object_id(value: &str) -> Result<(), ValidationError> {
+    mongodb::bson::oid::ObjectId::parse_str(value)
+        .map(|_| ())
+        .map_err(|_| ValidationError::new("object_id").with_message("must be a valid id".into()))
+}
+
+/// Reject strings that aren't an IANA time zone name such as `Europe/Paris`
+pub fn time_zone(value: &str) -> Result<(), ValidationError> {
+    value.parse::<chrono_tz::Tz>().map(|_| ()).map_err(|_| {
+        ValidationError::new("time_zone").with_message("must be a valid time zone".into())
+    })
+}
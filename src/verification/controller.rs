@@ -0,0 +1,192 @@
+use crate::database::RedisService;
+use crate::middleware::auth::AuthUser;
+use crate::middleware::limits::HttpLimits;
+use crate::notification::model::NotificationKind;
+use crate::notification::service::NotificationService;
+use crate::uploader::controller::extract_files_from_multipart;
+use crate::user::controller::profile_cache_key;
+use crate::utils::config::AppConfig;
+use crate::utils::error::{CustomError, ErrorCode};
+use crate::utils::i18n::Locale;
+use crate::utils::response::ApiResponse;
+use crate::utils::uploads::{FileValidator, UploadService};
+use crate::utils::validation::ValidatedJson;
+use crate::verification::model::{ReviewVerificationRequest, VerificationStatus};
+use crate::verification::service::VerificationService;
+use actix_multipart::Multipart;
+use actix_web::{HttpResponse, web};
+use mongodb::bson::oid::ObjectId;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct VerificationQuery {
+    pub status: Option<String>,
+    pub limit: Option<i64>,
+}
+
+fn parse_id(id: String, what: &str) -> Result<ObjectId, CustomError> {
+    ObjectId::parse_str(id)
+        .map_err(|_| CustomError::BadRequestError(format!("Invalid {} ID", what)))
+}
+
+async fn invalidate_profile(redis_service: &RedisService, user_id: &ObjectId) {
+    if let Err(e) = redis_service
+        .cache_delete(&profile_cache_key(user_id))
+        .await
+    {
+        log::warn!("Failed to invalidate profile cache: {}", e);
+    }
+}
+
+/// Ask for the verified badge with an identity document (PDF or image)
+/// POST /verification/requests
+pub async fn submit_verification(
+    locale: Locale,
+    auth_user: AuthUser,
+    verification_service: web::Data<VerificationService>,
+    limits: web::Data<HttpLimits>,
+    config: web::Data<AppConfig>,
+    payload: Multipart,
+) -> Result<HttpResponse, CustomError> {
+    // Check before uploading so a refused request leaves no stray document
+    verification_service
+        .ensure_can_submit(&auth_user.id)
+        .await?;
+
+    let file = extract_files_from_multipart(payload, limits.upload_limit)
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| CustomError::BadRequestError(locale.t("upload-no-file")))?;
+
+    let validator = FileValidator::documents()
+        .with_extensions(vec!["pdf", "jpg", "jpeg", "png"])
+        .with_max_size_mb(10);
+    let document = UploadService::with_config(config.cloudinary.clone())
+        .upload_single_file(file, Some("verification"), &validator)
+        .await
+        .map_err(CustomError::BadRequestError)?;
+
+    let request = verification_service.submit(auth_user.id, document).await?;
+
+    Ok(ApiResponse::created(locale.t("verification-requested"))
+        .data(request)
+        .into())
+}
+
+/// The caller's latest verification request
+/// GET /verification/requests/me
+pub async fn my_verification(
+    locale: Locale,
+    auth_user: AuthUser,
+    verification_service: web::Data<VerificationService>,
+) -> Result<HttpResponse, CustomError> {
+    let request = verification_service
+        .current(&auth_user.id)
+        .await?
+        .ok_or_else(|| CustomError::NotFoundError("No verification request found".to_string()))?;
+
+    Ok(ApiResponse::ok(locale.t("verification-fetched"))
+        .data(request)
+        .into())
+}
+
+/// Review queue
+/// GET /admin/verification?status=pending&limit=50
+pub async fn list_verifications(
+    auth_user: AuthUser,
+    verification_service: web::Data<VerificationService>,
+    query: web::Query<VerificationQuery>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_admin()?;
+
+    let status = match query.status.as_deref() {
+        Some(name) => VerificationStatus::from_name(name).ok_or_else(|| {
+            CustomError::BadRequestError(
+                "status must be one of pending, approved or rejected".to_string(),
+            )
+        })?,
+        None => VerificationStatus::Pending,
+    };
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+
+    let requests = verification_service.list(status, limit).await?;
+
+    Ok(
+        ApiResponse::ok("Verification requests retrieved successfully")
+            .list(requests)
+            .into(),
+    )
+}
+
+/// Approve or reject a pending request. The document is deleted either way.
+/// POST /admin/verification/{id}/review
+pub async fn review_verification(
+    auth_user: AuthUser,
+    verification_service: web::Data<VerificationService>,
+    notification_service: web::Data<NotificationService>,
+    redis_service: web::Data<RedisService>,
+    config: web::Data<AppConfig>,
+    path: web::Path<String>,
+    body: ValidatedJson<ReviewVerificationRequest>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_admin()?;
+
+    let id = parse_id(path.into_inner(), "verification request")?;
+    let body = body.into_inner();
+    let request = verification_service
+        .review(&id, auth_user.id, body.approve, body.reason.clone())
+        .await?;
+
+    if let Some(public_id) = &request.document_public_id
+        && let Err(e) = UploadService::with_config(config.cloudinary.clone())
+            .delete_resource(public_id, &request.document_resource_type)
+            .await
+    {
+        log::warn!(
+            "Failed to delete verification document {}: {}",
+            public_id,
+            e
+        );
+    }
+    invalidate_profile(&redis_service, &request.user_id).await;
+
+    let message = match (body.approve, &body.reason) {
+        (true, _) => "Your account is now verified".to_string(),
+        (false, Some(reason)) => format!("Your verification request was declined: {}", reason),
+        (false, None) => "Your verification request was declined".to_string(),
+    };
+    notification_service
+        .notify(
+            request.user_id,
+            Some(auth_user.id),
+            NotificationKind::System,
+            message,
+            request.id,
+        )
+        .await?;
+
+    Ok(ApiResponse::ok("Verification request reviewed").into())
+}
+
+/// Take the verified badge away
+/// DELETE /admin/verification/users/{user_id}
+pub async fn revoke_verification(
+    auth_user: AuthUser,
+    verification_service: web::Data<VerificationService>,
+    redis_service: web::Data<RedisService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    auth_user.require_admin()?;
+
+    let user_id = parse_id(path.into_inner(), "user")?;
+    if !verification_service.set_verified(&user_id, false).await? {
+        return Err(CustomError::coded(
+            ErrorCode::UserNotFound,
+            "User not found",
+        ));
+    }
+    invalidate_profile(&redis_service, &user_id).await;
+
+    Ok(ApiResponse::ok("Verification revoked").into())
+}
@@ -0,0 +1,26 @@
+use super::controller::{
+    list_verifications, my_verification, review_verification, revoke_verification,
+    submit_verification,
+};
+use crate::middleware::auth::verify_token;
+use crate::middleware::limits::RequestTimeout;
+use actix_web::web;
+use actix_web_httpauth::middleware::HttpAuthentication;
+
+pub fn verification_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/verification")
+            .wrap(RequestTimeout::uploads())
+            .wrap(HttpAuthentication::bearer(verify_token))
+            .route("/requests", web::post().to(submit_verification))
+            .route("/requests/me", web::get().to(my_verification)),
+    );
+    cfg.service(
+        web::scope("/admin/verification")
+            .wrap(RequestTimeout::standard())
+            .wrap(HttpAuthentication::bearer(verify_token))
+            .route("", web::get().to(list_verifications))
+            .route("/{id}/review", web::post().to(review_verification))
+            .route("/users/{user_id}", web::delete().to(revoke_verification)),
+    );
+}
@@ -0,0 +1,64 @@
+use crate::utils::datetime::{bson_datetime, option_bson_datetime};
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VerificationStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+impl VerificationStatus {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "pending" => Some(VerificationStatus::Pending),
+            "approved" => Some(VerificationStatus::Approved),
+            "rejected" => Some(VerificationStatus::Rejected),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            VerificationStatus::Pending => "pending",
+            VerificationStatus::Approved => "approved",
+            VerificationStatus::Rejected => "rejected",
+        }
+    }
+}
+
+/// A request to get the verified badge, with the identity document backing it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VerificationRequest {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: ObjectId,
+    pub status: VerificationStatus,
+    /// Cloudinary id of the document; removed once the request is reviewed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub document_public_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub document_url: Option<String>,
+    pub document_resource_type: String,
+    /// Shown to the user when the request is rejected
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reviewed_by: Option<ObjectId>,
+    #[serde(default, with = "option_bson_datetime")]
+    pub reviewed_at: Option<DateTime<Utc>>,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// Admin decision on a verification request
+#[derive(Debug, Deserialize, Validate)]
+pub struct ReviewVerificationRequest {
+    pub approve: bool,
+    #[validate(length(max = 500, message = "must be at most 500 characters"))]
+    pub reason: Option<String>,
+}
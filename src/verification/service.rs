@@ -0,0 +1,204 @@
+use crate::user::model::User;
+use crate::utils::datetime::bson_now;
+use crate::utils::error::{CustomError, ErrorCode};
+use crate::utils::sanitize::{Markup, sanitize};
+use crate::utils::uploads::CloudinaryUploadResponse;
+use crate::verification::model::{VerificationRequest, VerificationStatus};
+use chrono::Utc;
+use futures_util::TryStreamExt;
+use mongodb::bson::{doc, oid::ObjectId};
+use mongodb::options::{IndexOptions, ReturnDocument};
+use mongodb::{Client, Collection, IndexModel};
+
+pub struct VerificationService {
+    requests: Collection<VerificationRequest>,
+    users: Collection<User>,
+}
+
+impl VerificationService {
+    pub fn new(client: &Client) -> Self {
+        let db = client.database("rust_blogdb");
+        VerificationService {
+            requests: db.collection::<VerificationRequest>("verification_requests"),
+            users: db.collection::<User>("users"),
+        }
+    }
+
+    /// At most one pending request per user, and the review queue
+    #[tracing::instrument(skip_all)]
+    pub async fn ensure_indexes(&self) -> Result<(), CustomError> {
+        let indexes = vec![
+            IndexModel::builder()
+                .keys(doc! { "user_id": 1 })
+                .options(
+                    IndexOptions::builder()
+                        .unique(true)
+                        .partial_filter_expression(doc! { "status": "pending" })
+                        .build(),
+                )
+                .build(),
+            IndexModel::builder()
+                .keys(doc! { "status": 1, "created_at": 1 })
+                .build(),
+        ];
+
+        self.requests.create_indexes(indexes).await.map_err(|e| {
+            CustomError::InternalServerError(format!(
+                "Failed to create verification indexes: {}",
+                e
+            ))
+        })?;
+
+        Ok(())
+    }
+
+    /// Fail unless the user may open a new request
+    #[tracing::instrument(skip_all)]
+    pub async fn ensure_can_submit(&self, user_id: &ObjectId) -> Result<(), CustomError> {
+        let user = self
+            .users
+            .find_one(doc! { "_id": user_id })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .ok_or_else(|| CustomError::coded(ErrorCode::UserNotFound, "User not found"))?;
+        if user.is_verified {
+            return Err(CustomError::ConflictError(
+                "Your account is already verified".to_string(),
+            ));
+        }
+        if self
+            .current(user_id)
+            .await?
+            .is_some_and(|request| request.status == VerificationStatus::Pending)
+        {
+            return Err(CustomError::ConflictError(
+                "A verification request is already pending".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Queue a request for the uploaded document
+    #[tracing::instrument(skip_all)]
+    pub async fn submit(
+        &self,
+        user_id: ObjectId,
+        document: CloudinaryUploadResponse,
+    ) -> Result<VerificationRequest, CustomError> {
+        let mut request = VerificationRequest {
+            id: None,
+            user_id,
+            status: VerificationStatus::Pending,
+            document_public_id: Some(document.public_id),
+            document_url: Some(document.secure_url),
+            document_resource_type: document.resource_type,
+            reason: None,
+            reviewed_by: None,
+            reviewed_at: None,
+            created_at: Utc::now(),
+        };
+
+        let result = self.requests.insert_one(&request).await.map_err(|e| {
+            CustomError::InternalServerError(format!("Failed to save verification request: {}", e))
+        })?;
+        request.id = result.inserted_id.as_object_id();
+
+        Ok(request)
+    }
+
+    /// The user's latest request
+    #[tracing::instrument(skip_all)]
+    pub async fn current(
+        &self,
+        user_id: &ObjectId,
+    ) -> Result<Option<VerificationRequest>, CustomError> {
+        self.requests
+            .find_one(doc! { "user_id": user_id })
+            .sort(doc! { "created_at": -1 })
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))
+    }
+
+    /// Review queue, oldest first
+    #[tracing::instrument(skip_all)]
+    pub async fn list(
+        &self,
+        status: VerificationStatus,
+        limit: i64,
+    ) -> Result<Vec<VerificationRequest>, CustomError> {
+        self.requests
+            .find(doc! { "status": status.name() })
+            .sort(doc! { "created_at": 1 })
+            .limit(limit)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .try_collect()
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))
+    }
+
+    /// Decide a pending request and set the user's verified flag to match.
+    /// Returns the request as it was before the document was dropped, so
+    /// the caller can delete the upload.
+    #[tracing::instrument(skip_all)]
+    pub async fn review(
+        &self,
+        id: &ObjectId,
+        reviewer_id: ObjectId,
+        approve: bool,
+        reason: Option<String>,
+    ) -> Result<VerificationRequest, CustomError> {
+        let status = if approve {
+            VerificationStatus::Approved
+        } else {
+            VerificationStatus::Rejected
+        };
+        let reason = reason.map(|reason| sanitize(&reason, Markup::None));
+
+        let request = self
+            .requests
+            .find_one_and_update(
+                doc! { "_id": id, "status": VerificationStatus::Pending.name() },
+                doc! {
+                    "$set": {
+                        "status": status.name(),
+                        "reason": reason,
+                        "reviewed_by": reviewer_id,
+                        "reviewed_at": bson_now(),
+                    },
+                    "$unset": { "document_public_id": "", "document_url": "" },
+                },
+            )
+            .return_document(ReturnDocument::Before)
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?
+            .ok_or_else(|| {
+                CustomError::NotFoundError("Pending verification request not found".to_string())
+            })?;
+
+        if approve {
+            self.set_verified(&request.user_id, true).await?;
+        }
+
+        Ok(request)
+    }
+
+    /// Grant or revoke the verified flag
+    #[tracing::instrument(skip_all)]
+    pub async fn set_verified(
+        &self,
+        user_id: &ObjectId,
+        verified: bool,
+    ) -> Result<bool, CustomError> {
+        let result = self
+            .users
+            .update_one(
+                doc! { "_id": user_id },
+                doc! { "$set": { "is_verified": verified, "updated_at": bson_now() } },
+            )
+            .await
+            .map_err(|e| CustomError::InternalServerError(e.to_string()))?;
+
+        Ok(result.matched_count > 0)
+    }
+}